@@ -79,6 +79,33 @@ mod wire {
         });
     }
 
+    #[bench]
+    #[cfg(any(feature = "proto-ipv6", feature = "proto-ipv4"))]
+    fn bench_checksum_udp(b: &mut test::Bencher) {
+        let mut bytes = vec![0xa5; 1472];
+        let mut packet = UdpPacket::new_unchecked(&mut bytes);
+        packet.set_len(1472);
+
+        b.bytes = 1472;
+        b.iter(|| {
+            packet.fill_checksum(&SRC_ADDR, &DST_ADDR);
+            test::black_box(packet.checksum())
+        });
+    }
+
+    #[bench]
+    #[cfg(any(feature = "proto-ipv6", feature = "proto-ipv4"))]
+    fn bench_checksum_tcp(b: &mut test::Bencher) {
+        let mut bytes = vec![0xa5; 1460];
+        let mut packet = TcpPacket::new_unchecked(&mut bytes);
+
+        b.bytes = 1460;
+        b.iter(|| {
+            packet.fill_checksum(&SRC_ADDR, &DST_ADDR);
+            test::black_box(packet.checksum())
+        });
+    }
+
     #[bench]
     #[cfg(feature = "proto-ipv4")]
     fn bench_emit_ipv4(b: &mut test::Bencher) {
@@ -0,0 +1,178 @@
+#![feature(test)]
+
+#[cfg(all(
+    feature = "std",
+    feature = "medium-ethernet",
+    feature = "proto-ipv4",
+    feature = "socket-tcp"
+))]
+mod tcp {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-sixlowpan-fragmentation"
+    ))]
+    use smoltcp::iface::FragmentsCache;
+    use smoltcp::iface::{Interface, InterfaceBuilder, NeighborCache, SocketHandle, SocketSet};
+    use smoltcp::phy::{Loopback, Medium};
+    use smoltcp::socket::tcp;
+    use smoltcp::time::{Duration, Instant};
+    use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr};
+
+    extern crate test;
+
+    /// An allocator counting the allocations made through it.
+    struct CountingAllocator;
+
+    static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    const SERVER_PORT: u16 = 1234;
+    const CLIENT_PORT: u16 = 65000;
+    const BUFFER_LEN: usize = 65535;
+
+    /// A client and a server socket connected over a loopback interface.
+    struct Link {
+        iface: Interface<'static>,
+        device: Loopback,
+        sockets: SocketSet<'static>,
+        server: SocketHandle,
+        client: SocketHandle,
+        timestamp: Instant,
+        polls: usize,
+    }
+
+    impl Link {
+        fn new() -> Link {
+            let mut device = Loopback::new(Medium::Ethernet);
+            let builder = InterfaceBuilder::new()
+                .hardware_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]).into())
+                .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+                .ip_addrs([IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8)]);
+            #[cfg(feature = "proto-ipv4-fragmentation")]
+            let builder =
+                builder.ipv4_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()));
+            #[cfg(feature = "proto-sixlowpan-fragmentation")]
+            let builder = builder
+                .sixlowpan_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()))
+                .sixlowpan_out_packet_cache(vec![]);
+            let iface = builder.try_finalize(&mut device).unwrap();
+
+            let socket = || {
+                tcp::Socket::new(
+                    tcp::SocketBuffer::new(vec![0; BUFFER_LEN]),
+                    tcp::SocketBuffer::new(vec![0; BUFFER_LEN]),
+                )
+            };
+            let mut sockets = SocketSet::new(vec![]);
+            let server = sockets.add(socket());
+            let client = sockets.add(socket());
+
+            let mut link = Link {
+                iface,
+                device,
+                sockets,
+                server,
+                client,
+                timestamp: Instant::from_millis(0),
+                polls: 0,
+            };
+            link.sockets
+                .get_mut::<tcp::Socket>(server)
+                .listen(SERVER_PORT)
+                .unwrap();
+            let cx = link.iface.context();
+            link.sockets
+                .get_mut::<tcp::Socket>(client)
+                .connect(cx, (IpAddress::v4(127, 0, 0, 1), SERVER_PORT), CLIENT_PORT)
+                .unwrap();
+            while !link.sockets.get::<tcp::Socket>(client).may_send() {
+                link.poll();
+            }
+            link
+        }
+
+        fn poll(&mut self) {
+            self.iface
+                .poll(self.timestamp, &mut self.device, &mut self.sockets)
+                .unwrap();
+            self.timestamp += Duration::from_millis(1);
+            self.polls += 1;
+        }
+
+        /// Send `len` bytes from `sender` to `receiver`.
+        fn transfer(&mut self, sender: SocketHandle, receiver: SocketHandle, len: usize) {
+            let (mut sent, mut received) = (0, 0);
+            while received < len {
+                let socket = self.sockets.get_mut::<tcp::Socket>(sender);
+                if sent < len {
+                    sent += socket
+                        .send(|buffer| {
+                            let size = buffer.len().min(len - sent);
+                            (size, size)
+                        })
+                        .unwrap();
+                }
+                self.poll();
+                let socket = self.sockets.get_mut::<tcp::Socket>(receiver);
+                received += socket.recv(|buffer| (buffer.len(), buffer.len())).unwrap();
+            }
+        }
+    }
+
+    fn bench_transfer(b: &mut test::Bencher, len: usize, transmit: bool) {
+        let mut link = Link::new();
+        let (sender, receiver) = if transmit {
+            (link.client, link.server)
+        } else {
+            (link.server, link.client)
+        };
+
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let polls = link.polls;
+        b.bytes = len as u64;
+        b.iter(|| link.transfer(sender, receiver, len));
+
+        // The loopback device allocates a buffer for each frame, and a couple of frames
+        // are exchanged per poll; the stack itself must not allocate in the data path.
+        let polls = link.polls - polls;
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+        assert!(
+            allocations <= polls * 2,
+            "{} allocations in {} polls",
+            allocations,
+            polls
+        );
+    }
+
+    #[bench]
+    fn bench_tcp_transmit_64k(b: &mut test::Bencher) {
+        bench_transfer(b, 65536, true)
+    }
+
+    #[bench]
+    fn bench_tcp_receive_64k(b: &mut test::Bencher) {
+        bench_transfer(b, 65536, false)
+    }
+
+    #[bench]
+    fn bench_tcp_transmit_1k(b: &mut test::Bencher) {
+        bench_transfer(b, 1024, true)
+    }
+}
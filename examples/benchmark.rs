@@ -3,16 +3,19 @@
 mod utils;
 
 use log::debug;
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::cmp;
 use std::collections::BTreeMap;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::os::unix::io::AsRawFd;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::thread;
 
+#[cfg(feature = "proto-ipv4-fragmentation")]
+use smoltcp::iface::FragmentsCache;
 use smoltcp::iface::{InterfaceBuilder, NeighborCache, SocketSet};
-use smoltcp::phy::{wait as phy_wait, Device, Medium};
+use smoltcp::phy::{wait as phy_wait, Device, Medium, Tracer};
 use smoltcp::socket::tcp;
 use smoltcp::time::{Duration, Instant};
 use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr};
@@ -62,6 +65,28 @@ fn client(kind: Client) {
 
 static CLIENT_DONE: AtomicBool = AtomicBool::new(false);
 
+/// An allocator counting the allocations made through it, to find allocations
+/// in the data path.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+static FRAMES: AtomicUsize = AtomicUsize::new(0);
+
 fn main() {
     #[cfg(feature = "log")]
     utils::setup_logging("info");
@@ -74,8 +99,10 @@ fn main() {
     let mut matches = utils::parse_options(&opts, free);
     let device = utils::parse_tuntap_options(&mut matches);
     let fd = device.as_raw_fd();
-    let mut device =
-        utils::parse_middleware_options(&mut matches, device, /*loopback=*/ false);
+    let device = utils::parse_middleware_options(&mut matches, device, /*loopback=*/ false);
+    let mut device = Tracer::new(device, |_timestamp, _packet| {
+        FRAMES.fetch_add(1, Ordering::Relaxed);
+    });
     let mode = match matches.free[0].as_ref() {
         "reader" => Client::Reader,
         "writer" => Client::Writer,
@@ -96,12 +123,17 @@ fn main() {
     let ip_addrs = [IpCidr::new(IpAddress::v4(192, 168, 69, 1), 24)];
     let medium = device.capabilities().medium;
     let mut builder = InterfaceBuilder::new().ip_addrs(ip_addrs);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    {
+        let ipv4_frag_cache = FragmentsCache::new(vec![], BTreeMap::new());
+        builder = builder.ipv4_fragments_cache(ipv4_frag_cache);
+    }
     if medium == Medium::Ethernet {
         builder = builder
             .hardware_addr(ethernet_addr.into())
             .neighbor_cache(neighbor_cache);
     }
-    let mut iface = builder.finalize(&mut device);
+    let mut iface = builder.try_finalize(&mut device).unwrap();
 
     let mut sockets = SocketSet::new(vec![]);
     let tcp1_handle = sockets.add(tcp1_socket);
@@ -110,8 +142,11 @@ fn main() {
 
     thread::spawn(move || client(mode));
     let mut processed = 0;
+    let mut polls = 0;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
     while !CLIENT_DONE.load(Ordering::SeqCst) {
         let timestamp = Instant::now();
+        polls += 1;
         match iface.poll(timestamp, &mut device, &mut sockets) {
             Ok(_) => {}
             Err(e) => {
@@ -165,4 +200,12 @@ fn main() {
             }
         }
     }
+
+    let frames = FRAMES.load(Ordering::Relaxed);
+    println!(
+        "polls: {}, frames per poll: {:.2}, allocations: {}",
+        polls,
+        frames as f64 / polls as f64,
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations
+    );
 }
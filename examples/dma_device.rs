@@ -0,0 +1,378 @@
+//! A template `Device` for Ethernet MACs with DMA descriptor rings.
+//!
+//! Most MACs found in microcontrollers, such as the one in STM32 parts, move frames between
+//! memory and the wire with a DMA engine driven by rings of descriptors. Each descriptor
+//! points to a buffer and has an OWN bit, telling whether the descriptor currently belongs
+//! to the DMA engine or to the driver. This example shows how smoltcp's token-based API maps
+//! onto such rings without copying frames:
+//!
+//!   * a receive token lends a filled receive buffer to the stack, and returns its
+//!     descriptor to the DMA engine once the frame is processed;
+//!   * a transmit token lends a free transmit buffer to the stack, and hands its descriptor
+//!     to the DMA engine once the frame is written.
+//!
+//! The `dma` module only uses `core`, and is meant to be copied into a port and adapted
+//! to the descriptor format and registers of the MAC. Since no hardware is available here,
+//! the `mac` module simulates a MAC in loopback mode.
+
+#[cfg(any(
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use smoltcp::iface::FragmentsCache;
+#[cfg(any(
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use std::collections::BTreeMap;
+
+use smoltcp::iface::{InterfaceBuilder, NeighborCache, SocketSet};
+use smoltcp::socket::udp;
+use smoltcp::time::{Duration, Instant};
+use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint};
+
+mod dma {
+    use core::ptr;
+    use core::sync::atomic::{fence, Ordering};
+
+    use smoltcp::phy::{self, Device, DeviceCapabilities, Medium};
+    use smoltcp::time::Instant;
+    use smoltcp::{Error, Result};
+
+    pub const RX_RING_LEN: usize = 4;
+    pub const TX_RING_LEN: usize = 4;
+    pub const BUFFER_LEN: usize = 1536;
+
+    /// Set when the descriptor belongs to the DMA engine.
+    pub const OWN: u32 = 1 << 31;
+    /// Set by the DMA engine when a received frame had an error.
+    pub const ERROR: u32 = 1 << 15;
+    /// The length of a frame, in the status word of a descriptor.
+    pub const LEN_MASK: u32 = 0x3fff;
+
+    /// A DMA descriptor, as read and written by the DMA engine.
+    ///
+    /// Real descriptors have more fields, such as the address of the buffer and of the next
+    /// descriptor; they are written once, when the rings are set up.
+    #[repr(C, align(16))]
+    #[derive(Default)]
+    pub struct Descriptor {
+        status: u32,
+    }
+
+    impl Descriptor {
+        /// Read the status word. The DMA engine may write it at any time, so the compiler
+        /// must not cache it.
+        pub fn status(&self) -> u32 {
+            unsafe { ptr::read_volatile(&self.status) }
+        }
+
+        pub fn set_status(&mut self, status: u32) {
+            unsafe { ptr::write_volatile(&mut self.status, status) }
+        }
+
+        pub fn is_owned_by_dma(&self) -> bool {
+            self.status() & OWN != 0
+        }
+
+        /// Give the descriptor, and its buffer, to the DMA engine.
+        fn give_to_dma(&mut self, status: u32) {
+            // The buffer must be written before the DMA engine can see the OWN bit.
+            fence(Ordering::Release);
+            self.set_status(status | OWN);
+        }
+    }
+
+    /// The descriptor rings and their buffers. On hardware, these are placed in memory
+    /// reachable by the DMA engine, usually with a linker section.
+    pub struct Rings {
+        pub rx_descriptors: [Descriptor; RX_RING_LEN],
+        pub rx_buffers: [[u8; BUFFER_LEN]; RX_RING_LEN],
+        pub tx_descriptors: [Descriptor; TX_RING_LEN],
+        pub tx_buffers: [[u8; BUFFER_LEN]; TX_RING_LEN],
+    }
+
+    impl Rings {
+        pub fn new() -> Rings {
+            Rings {
+                rx_descriptors: Default::default(),
+                rx_buffers: [[0; BUFFER_LEN]; RX_RING_LEN],
+                tx_descriptors: Default::default(),
+                tx_buffers: [[0; BUFFER_LEN]; TX_RING_LEN],
+            }
+        }
+    }
+
+    /// The hook points of a port: cache maintenance, and the registers of the MAC.
+    ///
+    /// Cache maintenance is only needed on cores with a data cache, such as the Cortex-M7,
+    /// when the rings are in cacheable memory.
+    pub trait Hooks {
+        /// Write the cache lines covering `buffer` back to memory, before the DMA engine
+        /// reads it.
+        fn clean_dcache(&self, _buffer: &[u8]) {}
+
+        /// Discard the cache lines covering `buffer`, after the DMA engine wrote it.
+        fn invalidate_dcache(&self, _buffer: &[u8]) {}
+
+        /// Tell the DMA engine that receive descriptors were returned to it, e.g. by writing
+        /// the receive poll demand register.
+        fn rx_poll_demand(&self) {}
+
+        /// Tell the DMA engine that transmit descriptors were given to it, e.g. by writing
+        /// the transmit poll demand register.
+        fn tx_poll_demand(&self) {}
+    }
+
+    pub struct DmaDevice<'a, H: Hooks> {
+        rings: &'a mut Rings,
+        hooks: H,
+        rx_index: usize,
+        tx_index: usize,
+    }
+
+    impl<'a, H: Hooks> DmaDevice<'a, H> {
+        /// Set up the rings, and give all receive descriptors to the DMA engine.
+        pub fn new(rings: &'a mut Rings, hooks: H) -> Self {
+            for descriptor in rings.rx_descriptors.iter_mut() {
+                descriptor.give_to_dma(0);
+            }
+            for descriptor in rings.tx_descriptors.iter_mut() {
+                descriptor.set_status(0);
+            }
+            hooks.rx_poll_demand();
+            DmaDevice {
+                rings,
+                hooks,
+                rx_index: 0,
+                tx_index: 0,
+            }
+        }
+
+        pub fn hooks(&self) -> &H {
+            &self.hooks
+        }
+
+        /// Return the rings, e.g. to let a simulated MAC process them.
+        pub fn rings_mut(&mut self) -> &mut Rings {
+            self.rings
+        }
+    }
+
+    impl<'a, 'b, H: Hooks + 'a> Device<'a> for DmaDevice<'b, H> {
+        type RxToken = RxToken<'a, H>;
+        type TxToken = TxToken<'a, H>;
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            let mut caps = DeviceCapabilities::default();
+            caps.medium = Medium::Ethernet;
+            caps.max_transmission_unit = 1514;
+            caps.max_burst_size = Some(TX_RING_LEN);
+            caps
+        }
+
+        fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+            let rings = &mut *self.rings;
+            let rx_descriptor = &mut rings.rx_descriptors[self.rx_index];
+            let tx_descriptor = &mut rings.tx_descriptors[self.tx_index];
+            // A transmit descriptor must be free too, to answer the received frame.
+            if rx_descriptor.is_owned_by_dma() || tx_descriptor.is_owned_by_dma() {
+                return None;
+            }
+            // The buffer must not be read before the OWN bit is seen cleared.
+            fence(Ordering::Acquire);
+            let rx = RxToken {
+                descriptor: rx_descriptor,
+                buffer: &mut rings.rx_buffers[self.rx_index],
+                index: &mut self.rx_index,
+                hooks: &self.hooks,
+            };
+            let tx = TxToken {
+                descriptor: tx_descriptor,
+                buffer: &mut rings.tx_buffers[self.tx_index],
+                index: &mut self.tx_index,
+                hooks: &self.hooks,
+            };
+            Some((rx, tx))
+        }
+
+        fn transmit(&'a mut self) -> Option<Self::TxToken> {
+            let descriptor = &mut self.rings.tx_descriptors[self.tx_index];
+            if descriptor.is_owned_by_dma() {
+                return None;
+            }
+            Some(TxToken {
+                descriptor,
+                buffer: &mut self.rings.tx_buffers[self.tx_index],
+                index: &mut self.tx_index,
+                hooks: &self.hooks,
+            })
+        }
+    }
+
+    pub struct RxToken<'a, H: Hooks> {
+        descriptor: &'a mut Descriptor,
+        buffer: &'a mut [u8; BUFFER_LEN],
+        index: &'a mut usize,
+        hooks: &'a H,
+    }
+
+    impl<'a, H: Hooks> phy::RxToken for RxToken<'a, H> {
+        fn consume<R, F>(self, _timestamp: Instant, f: F) -> Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> Result<R>,
+        {
+            let status = self.descriptor.status();
+            let len = ((status & LEN_MASK) as usize).min(BUFFER_LEN);
+            let result = if status & ERROR != 0 {
+                Err(Error::Checksum)
+            } else {
+                self.hooks.invalidate_dcache(&self.buffer[..len]);
+                f(&mut self.buffer[..len])
+            };
+
+            // Whatever the outcome, the descriptor goes back to the DMA engine.
+            self.descriptor.give_to_dma(0);
+            self.hooks.rx_poll_demand();
+            *self.index = (*self.index + 1) % RX_RING_LEN;
+            result
+        }
+    }
+
+    pub struct TxToken<'a, H: Hooks> {
+        descriptor: &'a mut Descriptor,
+        buffer: &'a mut [u8; BUFFER_LEN],
+        index: &'a mut usize,
+        hooks: &'a H,
+    }
+
+    impl<'a, H: Hooks> phy::TxToken for TxToken<'a, H> {
+        fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> Result<R>,
+        {
+            if len > BUFFER_LEN {
+                return Err(Error::Exhausted);
+            }
+            // If the frame is not written, the descriptor stays with the driver.
+            let result = f(&mut self.buffer[..len])?;
+
+            self.hooks.clean_dcache(&self.buffer[..len]);
+            self.descriptor.give_to_dma(len as u32);
+            self.hooks.tx_poll_demand();
+            *self.index = (*self.index + 1) % TX_RING_LEN;
+            Ok(result)
+        }
+    }
+}
+
+mod mac {
+    use super::dma::{Descriptor, Hooks, Rings, LEN_MASK, OWN};
+    use std::cell::Cell;
+
+    /// Counts the calls to the hooks, in place of real cache maintenance and registers.
+    #[derive(Default)]
+    pub struct CountingHooks {
+        pub cleaned: Cell<usize>,
+        pub invalidated: Cell<usize>,
+    }
+
+    impl Hooks for CountingHooks {
+        fn clean_dcache(&self, _buffer: &[u8]) {
+            self.cleaned.set(self.cleaned.get() + 1)
+        }
+
+        fn invalidate_dcache(&self, _buffer: &[u8]) {
+            self.invalidated.set(self.invalidated.get() + 1)
+        }
+    }
+
+    /// A simulated MAC in loopback mode, with its DMA engine.
+    #[derive(Default)]
+    pub struct LoopbackMac {
+        tx_index: usize,
+        rx_index: usize,
+    }
+
+    impl LoopbackMac {
+        /// Move the frames of the transmit descriptors owned by the DMA engine to the
+        /// receive descriptors owned by it, in ring order.
+        pub fn run(&mut self, rings: &mut Rings) {
+            while rings.tx_descriptors[self.tx_index].is_owned_by_dma() {
+                let len = (rings.tx_descriptors[self.tx_index].status() & LEN_MASK) as usize;
+                let rx_descriptor: &mut Descriptor = &mut rings.rx_descriptors[self.rx_index];
+                // If the receive ring is full, a real MAC would count a missed frame.
+                if rx_descriptor.is_owned_by_dma() {
+                    rings.rx_buffers[self.rx_index][..len]
+                        .copy_from_slice(&rings.tx_buffers[self.tx_index][..len]);
+                    rx_descriptor.set_status(len as u32);
+                    self.rx_index = (self.rx_index + 1) % rings.rx_descriptors.len();
+                }
+                let tx_descriptor = &mut rings.tx_descriptors[self.tx_index];
+                tx_descriptor.set_status(tx_descriptor.status() & !OWN);
+                self.tx_index = (self.tx_index + 1) % rings.tx_descriptors.len();
+            }
+        }
+    }
+}
+
+fn main() {
+    let mut rings = dma::Rings::new();
+    let mut device = dma::DmaDevice::new(&mut rings, mac::CountingHooks::default());
+    let mut mac = mac::LoopbackMac::default();
+
+    let mut neighbor_cache_entries = [None; 8];
+    let neighbor_cache = NeighborCache::new(&mut neighbor_cache_entries[..]);
+    let ip_addrs = [IpCidr::new(IpAddress::v4(192, 168, 69, 1), 24)];
+    let builder = InterfaceBuilder::new()
+        .hardware_addr(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]).into())
+        .neighbor_cache(neighbor_cache)
+        .ip_addrs(ip_addrs);
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    let builder = builder.ipv4_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()));
+    #[cfg(feature = "proto-sixlowpan-fragmentation")]
+    let builder = builder
+        .sixlowpan_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()))
+        .sixlowpan_out_packet_cache(vec![]);
+    let mut iface = builder.try_finalize(&mut device).unwrap();
+
+    let endpoint = IpEndpoint::new(IpAddress::v4(192, 168, 69, 1), 1234);
+    let mut socket = udp::Socket::new(
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+    );
+    socket.bind(endpoint).unwrap();
+    let mut sockets: [_; 1] = Default::default();
+    let mut sockets = SocketSet::new(&mut sockets[..]);
+    let handle = sockets.add(socket);
+
+    let mut timestamp = Instant::ZERO;
+    let mut sent = 0;
+    let mut received = 0;
+    while received < 3 && timestamp < Instant::from_secs(10) {
+        let socket = sockets.get_mut::<udp::Socket>(handle);
+        // The first datagram is lost while the neighbor cache is filled.
+        if timestamp.total_millis() % 100 == 0 && socket.can_send() {
+            socket.send_slice(b"hello", endpoint).unwrap();
+            sent += 1;
+        }
+        while let Ok((data, from)) = socket.recv() {
+            println!("received {:?} from {}", core::str::from_utf8(data), from);
+            received += 1;
+        }
+
+        if let Err(err) = iface.poll(timestamp, &mut device, &mut sockets) {
+            println!("poll error: {}", err);
+        }
+        // On hardware, this happens concurrently; here it happens between polls.
+        mac.run(device.rings_mut());
+        timestamp += Duration::from_millis(1);
+    }
+
+    println!("received {} of {} datagrams", received, sent);
+    println!(
+        "cleaned {} and invalidated {} buffers",
+        device.hooks().cleaned.get(),
+        device.hooks().invalidated.get()
+    );
+}
@@ -62,7 +62,7 @@ fn main() {
             .hardware_addr(HardwareAddress::Ethernet(ethernet_addr))
             .neighbor_cache(neighbor_cache);
     }
-    let mut iface = builder.finalize(&mut device);
+    let mut iface = builder.try_finalize(&mut device).unwrap();
 
     let mut sockets = SocketSet::new(vec![]);
     let dns_handle = sockets.add(dns_socket);
@@ -55,7 +55,7 @@ fn main() {
             .hardware_addr(ethernet_addr.into())
             .neighbor_cache(neighbor_cache);
     }
-    let mut iface = builder.finalize(&mut device);
+    let mut iface = builder.try_finalize(&mut device).unwrap();
 
     let mut sockets = SocketSet::new(vec![]);
     let tcp_handle = sockets.add(tcp_socket);
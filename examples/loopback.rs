@@ -90,7 +90,8 @@ fn main() {
         .hardware_addr(EthernetAddress::default().into())
         .neighbor_cache(neighbor_cache)
         .ip_addrs(ip_addrs)
-        .finalize(&mut device);
+        .try_finalize(&mut device)
+        .unwrap();
 
     let server_socket = {
         // It is not strictly necessary to use a `static mut` and unsafe code here, but
@@ -40,7 +40,8 @@ fn main() {
         .neighbor_cache(neighbor_cache)
         .ip_addrs([ip_addr])
         .ipv4_multicast_groups(&mut ipv4_multicast_storage[..])
-        .finalize(&mut device);
+        .try_finalize(&mut device)
+        .unwrap();
 
     let now = Instant::now();
     // Join a multicast group to receive mDNS traffic
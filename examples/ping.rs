@@ -15,7 +15,7 @@ use smoltcp::phy::Device;
 use smoltcp::socket::icmp;
 use smoltcp::wire::{
     EthernetAddress, Icmpv4Packet, Icmpv4Repr, Icmpv6Packet, Icmpv6Repr, IpAddress, IpCidr,
-    Ipv4Address, Ipv6Address,
+    Ipv4Address, Ipv6Address, ParseMode,
 };
 use smoltcp::{
     phy::Medium,
@@ -135,7 +135,7 @@ fn main() {
             .hardware_addr(ethernet_addr.into())
             .neighbor_cache(neighbor_cache);
     }
-    let mut iface = builder.finalize(&mut device);
+    let mut iface = builder.try_finalize(&mut device).unwrap();
 
     let mut sockets = SocketSet::new(vec![]);
     let icmp_handle = sockets.add(icmp_socket);
@@ -209,7 +209,12 @@ fn main() {
             match remote_addr {
                 IpAddress::Ipv4(_) => {
                     let icmp_packet = Icmpv4Packet::new_checked(&payload).unwrap();
-                    let icmp_repr = Icmpv4Repr::parse(&icmp_packet, &device_caps.checksum).unwrap();
+                    let icmp_repr = Icmpv4Repr::parse(
+                        &icmp_packet,
+                        &device_caps.checksum,
+                        ParseMode::Permissive,
+                    )
+                    .unwrap();
                     get_icmp_pong!(
                         Icmpv4Repr,
                         icmp_repr,
@@ -227,6 +232,7 @@ fn main() {
                         &src_ipv6,
                         &icmp_packet,
                         &device_caps.checksum,
+                        ParseMode::Permissive,
                     )
                     .unwrap();
                     get_icmp_pong!(
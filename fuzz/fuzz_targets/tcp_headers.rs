@@ -136,7 +136,8 @@ fuzz_target!(|data: &[u8]| {
         .ethernet_addr(EthernetAddress::default())
         .neighbor_cache(neighbor_cache)
         .ip_addrs(ip_addrs)
-        .finalize(&mut device);
+        .try_finalize(&mut device)
+        .unwrap();
 
     let server_socket = {
         // It is not strictly necessary to use a `static mut` and unsafe code here, but
@@ -0,0 +1,889 @@
+/*! DHCPv4 server.
+
+A [Server] hands out IPv4 addresses from a pool to the hosts attached to the link (RFC 2131),
+so that a device acting as a gateway or as a USB network adapter can configure them.
+
+The server operates on a [udp::Socket] bound to [DHCP_SERVER_PORT]. Leases are kept in storage
+provided by the application; when it is full, new clients are not served until a lease expires.
+Addresses can be reserved for a given hardware address with [Server::reserve].
+Relay agents are not supported, and replies are broadcast unless the client has an address.
+
+[Server::poll] must be called after every call to `Interface::poll()`.
+
+[DHCP_SERVER_PORT]: crate::wire::DHCP_SERVER_PORT
+*/
+
+use managed::ManagedSlice;
+
+use crate::socket::udp;
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, IpEndpoint, Ipv4Address, Ipv4Cidr,
+    DHCP_CLIENT_PORT, DHCP_MAX_DNS_SERVER_COUNT,
+};
+use crate::{Error, Result};
+
+/// Time an offered address is set aside for a client that does not request it.
+const OFFER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Configuration of a DHCPv4 server.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Address and subnet of the interface. The address identifies the server, and the
+    /// subnet mask is sent to clients.
+    pub server: Ipv4Cidr,
+    /// First address of the pool.
+    pub pool_start: Ipv4Address,
+    /// Number of addresses in the pool.
+    pub pool_size: u32,
+    /// Router address sent to clients, also known as default gateway.
+    pub router: Option<Ipv4Address>,
+    /// DNS servers sent to clients.
+    pub dns_servers: [Option<Ipv4Address>; DHCP_MAX_DNS_SERVER_COUNT],
+    /// Duration of the leases.
+    pub lease_duration: Duration,
+}
+
+impl Config {
+    /// Default duration of the leases.
+    pub const DEFAULT_LEASE_DURATION: Duration = Duration::from_secs(3600);
+
+    /// Create a configuration handing out `pool_size` addresses starting at `pool_start`,
+    /// without router or DNS servers.
+    ///
+    /// Returns `Err(Error::Illegal)` if the pool is empty, if any of its addresses is not
+    /// a host address of the subnet of the server, or if it contains the address of the server.
+    pub fn new(server: Ipv4Cidr, pool_start: Ipv4Address, pool_size: u32) -> Result<Config> {
+        let pool_end = pool_size
+            .checked_sub(1)
+            .and_then(|last| to_u32(pool_start).checked_add(last))
+            .map(from_u32)
+            .ok_or(Error::Illegal)?;
+        let is_host = |address: Ipv4Address| {
+            server.contains_addr(&address)
+                && (server.prefix_len() >= 31
+                    || (address != server.network().address()
+                        && Some(address) != server.broadcast()))
+        };
+        let config = Config {
+            server,
+            pool_start,
+            pool_size,
+            router: None,
+            dns_servers: [None; DHCP_MAX_DNS_SERVER_COUNT],
+            lease_duration: Self::DEFAULT_LEASE_DURATION,
+        };
+        if !is_host(pool_start) || !is_host(pool_end) || config.in_pool(server.address()) {
+            return Err(Error::Illegal);
+        }
+        Ok(config)
+    }
+
+    fn in_pool(&self, addr: Ipv4Address) -> bool {
+        let offset = to_u32(addr).wrapping_sub(to_u32(self.pool_start));
+        offset < self.pool_size
+    }
+}
+
+fn to_u32(addr: Ipv4Address) -> u32 {
+    u32::from_be_bytes(addr.0)
+}
+
+fn from_u32(addr: u32) -> Ipv4Address {
+    Ipv4Address(addr.to_be_bytes())
+}
+
+/// State of a lease.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LeaseState {
+    /// The address is reserved for the client, but not used.
+    Free,
+    /// The address was offered to the client, which has not requested it yet.
+    Offered,
+    /// The address is used by the client.
+    Bound,
+    /// The client found the address to be used by another host, and it is set aside
+    /// for the lease duration.
+    Declined,
+}
+
+/// An address leased to a client.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Lease {
+    hardware_addr: EthernetAddress,
+    address: Ipv4Address,
+    state: LeaseState,
+    expires_at: Instant,
+    reserved: bool,
+}
+
+impl Lease {
+    /// Return the hardware address of the client.
+    pub fn hardware_addr(&self) -> EthernetAddress {
+        self.hardware_addr
+    }
+
+    /// Return the leased address.
+    pub fn address(&self) -> Ipv4Address {
+        self.address
+    }
+
+    /// Return the state of the lease.
+    pub fn state(&self) -> LeaseState {
+        self.state
+    }
+
+    /// Return the time at which the lease expires, or `None` if it is a free reservation.
+    pub fn expires_at(&self) -> Option<Instant> {
+        match self.state {
+            LeaseState::Free => None,
+            _ => Some(self.expires_at),
+        }
+    }
+
+    /// Query whether the address is reserved for the client.
+    pub fn is_reserved(&self) -> bool {
+        self.reserved
+    }
+
+    fn is_of(&self, hardware_addr: EthernetAddress) -> bool {
+        self.state != LeaseState::Declined && self.hardware_addr == hardware_addr
+    }
+}
+
+/// A DHCPv4 server.
+#[derive(Debug)]
+pub struct Server<'a> {
+    config: Config,
+    leases: ManagedSlice<'a, Option<Lease>>,
+    /// Offset in the pool of the next address to offer to a client without a preference.
+    next_offset: u32,
+}
+
+impl<'a> Server<'a> {
+    /// Create a server, keeping the leases in `storage`.
+    pub fn new<S>(config: Config, storage: S) -> Server<'a>
+    where
+        S: Into<ManagedSlice<'a, Option<Lease>>>,
+    {
+        let mut leases = storage.into();
+        for lease in leases.iter_mut() {
+            *lease = None;
+        }
+        Server {
+            config,
+            leases,
+            next_offset: 0,
+        }
+    }
+
+    /// Return the configuration of the server.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Return the leases, including free reservations.
+    pub fn leases(&self) -> impl Iterator<Item = &Lease> {
+        self.leases.iter().flatten()
+    }
+
+    /// Reserve `address` for the client with the hardware address `hardware_addr`.
+    ///
+    /// The address does not have to be in the pool, but must be in the subnet of the server.
+    /// Any lease of the client, or of another client for the address, is replaced.
+    ///
+    /// Returns `Err(Error::Illegal)` if the address is not a host address of the subnet,
+    /// or is the address of the server.
+    /// Returns `Err(Error::Exhausted)` if there is no space left in the storage.
+    pub fn reserve(&mut self, hardware_addr: EthernetAddress, address: Ipv4Address) -> Result<()> {
+        let server = self.config.server;
+        if !server.contains_addr(&address)
+            || address == server.address()
+            || address == server.network().address()
+            || Some(address) == server.broadcast()
+        {
+            return Err(Error::Illegal);
+        }
+
+        for slot in self.leases.iter_mut() {
+            if matches!(slot, Some(lease) if lease.hardware_addr == hardware_addr || lease.address == address)
+            {
+                *slot = None;
+            }
+        }
+        let slot = self
+            .leases
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(Error::Exhausted)?;
+        *slot = Some(Lease {
+            hardware_addr,
+            address,
+            state: LeaseState::Free,
+            expires_at: Instant::ZERO,
+            reserved: true,
+        });
+        Ok(())
+    }
+
+    /// Answer the messages received on `socket`.
+    pub fn poll(&mut self, socket: &mut udp::Socket, now: Instant) {
+        self.expire(now);
+
+        while let Ok((payload, _)) = socket.recv() {
+            let (repr, dst_addr) = match self.process(payload, now) {
+                Some(reply) => reply,
+                None => continue,
+            };
+
+            net_debug!("dhcp: send {:?} to {}", repr.message_type, dst_addr);
+            let endpoint = IpEndpoint::new(dst_addr.into(), DHCP_CLIENT_PORT);
+            match socket.send(repr.buffer_len(), endpoint) {
+                // Cannot fail, the buffer has the length of the representation.
+                Ok(buffer) => repr.emit(&mut DhcpPacket::new_unchecked(buffer)).unwrap(),
+                Err(_) => net_debug!("dhcp: cannot send reply to {}", dst_addr),
+            }
+        }
+    }
+
+    /// Forget the leases that expired at `now`.
+    fn expire(&mut self, now: Instant) {
+        for slot in self.leases.iter_mut() {
+            match slot {
+                Some(lease) if lease.state != LeaseState::Free && lease.expires_at <= now => {
+                    net_debug!("dhcp: lease of {} expired", lease.address);
+                    if lease.reserved {
+                        lease.state = LeaseState::Free;
+                    } else {
+                        *slot = None;
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Process a message from a client, and return the reply with its destination, if any.
+    fn process(
+        &mut self,
+        payload: &[u8],
+        now: Instant,
+    ) -> Option<(DhcpRepr<'static>, Ipv4Address)> {
+        let request = match DhcpPacket::new_checked(payload).and_then(|p| DhcpRepr::parse(&p)) {
+            Ok(request) => request,
+            Err(_) => {
+                net_debug!("dhcp: invalid message");
+                return None;
+            }
+        };
+        if !request.relay_agent_ip.is_unspecified() {
+            net_debug!("dhcp: ignoring relayed {:?}", request.message_type);
+            return None;
+        }
+        let client = request.client_hardware_address;
+        net_debug!("dhcp: recv {:?} from {}", request.message_type, client);
+
+        let server_addr = self.config.server.address();
+        let for_us =
+            request.server_identifier.is_none() || request.server_identifier == Some(server_addr);
+
+        match request.message_type {
+            DhcpMessageType::Discover => {
+                let index = self.allocate(client, request.requested_ip)?;
+                let lease = self.leases[index].as_mut().unwrap();
+                if lease.state != LeaseState::Bound {
+                    lease.state = LeaseState::Offered;
+                    lease.expires_at = now + OFFER_TIMEOUT;
+                }
+                let address = lease.address;
+                Some(self.reply(&request, DhcpMessageType::Offer, address))
+            }
+            DhcpMessageType::Request if !for_us => {
+                // The client accepted the offer of another server.
+                if let Some(index) = self.find(client) {
+                    if self.leases[index].unwrap().state == LeaseState::Offered {
+                        self.release(index);
+                    }
+                }
+                None
+            }
+            DhcpMessageType::Request => {
+                let address = match (request.requested_ip, request.client_ip) {
+                    (Some(address), _) => address,
+                    (None, address) if !address.is_unspecified() => address,
+                    _ => return None,
+                };
+                let index = match self.find(client) {
+                    Some(index) if self.leases[index].unwrap().address == address => Some(index),
+                    Some(_) => None,
+                    None if self.is_available(address) => self.allocate(client, Some(address)),
+                    None => None,
+                };
+                match index {
+                    Some(index) => {
+                        let lease = self.leases[index].as_mut().unwrap();
+                        lease.state = LeaseState::Bound;
+                        lease.expires_at = now + self.config.lease_duration;
+                        Some(self.reply(&request, DhcpMessageType::Ack, address))
+                    }
+                    None => Some(self.reply(&request, DhcpMessageType::Nak, address)),
+                }
+            }
+            DhcpMessageType::Decline if for_us => {
+                let index = self.find(client)?;
+                let lease = self.leases[index].as_mut().unwrap();
+                if request.requested_ip != Some(lease.address) {
+                    return None;
+                }
+                net_debug!("dhcp: {} declined {}", client, lease.address);
+                if lease.reserved {
+                    lease.state = LeaseState::Free;
+                } else {
+                    lease.state = LeaseState::Declined;
+                    lease.expires_at = now + self.config.lease_duration;
+                }
+                None
+            }
+            DhcpMessageType::Release if for_us => {
+                let index = self.find(client)?;
+                if self.leases[index].unwrap().address == request.client_ip {
+                    self.release(index);
+                }
+                None
+            }
+            DhcpMessageType::Inform if !request.client_ip.is_unspecified() => {
+                Some(self.reply(&request, DhcpMessageType::Ack, Ipv4Address::UNSPECIFIED))
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the index of the lease of `client`.
+    fn find(&self, client: EthernetAddress) -> Option<usize> {
+        self.leases
+            .iter()
+            .position(|slot| matches!(slot, Some(lease) if lease.is_of(client)))
+    }
+
+    /// Query whether `address` can be leased to a client without a reservation.
+    fn is_available(&self, address: Ipv4Address) -> bool {
+        self.config.in_pool(address)
+            && address != self.config.server.address()
+            && Some(address) != self.config.server.broadcast()
+            && !self.leases().any(|lease| lease.address == address)
+    }
+
+    /// Return the index of the lease of `client`, creating it if necessary, preferably
+    /// for the address `requested`.
+    fn allocate(
+        &mut self,
+        client: EthernetAddress,
+        requested: Option<Ipv4Address>,
+    ) -> Option<usize> {
+        if let Some(index) = self.find(client) {
+            return Some(index);
+        }
+
+        let index = match self.leases.iter().position(|slot| slot.is_none()) {
+            Some(index) => index,
+            None => {
+                net_debug!("dhcp: no space to lease an address to {}", client);
+                return None;
+            }
+        };
+        let address = match requested {
+            Some(address) if self.is_available(address) => address,
+            _ => match self.next_available() {
+                Some(address) => address,
+                None => {
+                    net_debug!("dhcp: no address left for {}", client);
+                    return None;
+                }
+            },
+        };
+        self.leases[index] = Some(Lease {
+            hardware_addr: client,
+            address,
+            state: LeaseState::Offered,
+            expires_at: Instant::ZERO,
+            reserved: false,
+        });
+        Some(index)
+    }
+
+    /// Return the first available address of the pool at or after the cursor, wrapping
+    /// around, and move the cursor past it.
+    ///
+    /// At most one address per lease, and the address of the server, can be unavailable,
+    /// so this checks at most as many addresses as there are leases plus two, rather than
+    /// the whole pool, when there is storage left for a lease.
+    fn next_available(&mut self) -> Option<Ipv4Address> {
+        let pool_start = to_u32(self.config.pool_start);
+        for _ in 0..self.config.pool_size {
+            let offset = self.next_offset % self.config.pool_size;
+            self.next_offset = offset + 1;
+            let address = match pool_start.checked_add(offset) {
+                Some(address) => from_u32(address),
+                // The pool of a configuration that was not validated by `Config::new`
+                // can extend past the end of the address space.
+                None => continue,
+            };
+            if self.is_available(address) {
+                return Some(address);
+            }
+        }
+        None
+    }
+
+    fn release(&mut self, index: usize) {
+        let slot = &mut self.leases[index];
+        match slot {
+            Some(lease) if lease.reserved => lease.state = LeaseState::Free,
+            _ => *slot = None,
+        }
+    }
+
+    /// Build a reply of type `message_type` to `request`, for the address `address`.
+    fn reply(
+        &self,
+        request: &DhcpRepr,
+        message_type: DhcpMessageType,
+        address: Ipv4Address,
+    ) -> (DhcpRepr<'static>, Ipv4Address) {
+        let mut repr = DhcpRepr {
+            message_type,
+            transaction_id: request.transaction_id,
+            client_hardware_address: request.client_hardware_address,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: None,
+            subnet_mask: None,
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: request.broadcast,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(self.config.server.address()),
+            parameter_request_list: None,
+            dns_servers: None,
+            host_name: None,
+            client_fqdn: None,
+            server_name: None,
+            boot_file: None,
+            additional_options: &[],
+            max_size: None,
+            lease_duration: None,
+        };
+        if message_type == DhcpMessageType::Nak {
+            return (repr, Ipv4Address::BROADCAST);
+        }
+
+        repr.your_ip = address;
+        repr.router = self.config.router;
+        repr.subnet_mask = Some(self.config.server.netmask());
+        if self.config.dns_servers.iter().any(Option::is_some) {
+            repr.dns_servers = Some(self.config.dns_servers);
+        }
+        if !address.is_unspecified() {
+            repr.lease_duration = Some(self.config.lease_duration.secs() as u32);
+        }
+        if message_type == DhcpMessageType::Ack {
+            repr.client_ip = request.client_ip;
+        }
+
+        let dst_addr = if request.client_ip.is_unspecified() {
+            Ipv4Address::BROADCAST
+        } else {
+            request.client_ip
+        };
+        (repr, dst_addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+    use std::vec::Vec;
+
+    const SERVER: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
+    const CLIENT_A: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0xa]);
+    const CLIENT_B: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0xb]);
+    const CLIENT_C: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 0xc]);
+    const POOL_1: Ipv4Address = Ipv4Address([192, 168, 1, 100]);
+    const POOL_2: Ipv4Address = Ipv4Address([192, 168, 1, 101]);
+
+    fn server(leases: usize) -> Server<'static> {
+        let mut config = Config::new(Ipv4Cidr::new(SERVER, 24), POOL_1, 2).unwrap();
+        config.router = Some(SERVER);
+        Server::new(config, vec![None; leases])
+    }
+
+    fn request(
+        message_type: DhcpMessageType,
+        client: EthernetAddress,
+        requested_ip: Option<Ipv4Address>,
+    ) -> DhcpRepr<'static> {
+        DhcpRepr {
+            message_type,
+            transaction_id: 0x12345678,
+            client_hardware_address: client,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: None,
+            subnet_mask: None,
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: false,
+            requested_ip,
+            client_identifier: Some(client),
+            server_identifier: None,
+            parameter_request_list: None,
+            dns_servers: None,
+            host_name: None,
+            client_fqdn: None,
+            server_name: None,
+            boot_file: None,
+            additional_options: &[],
+            max_size: None,
+            lease_duration: None,
+        }
+    }
+
+    fn emit(request: &DhcpRepr) -> Vec<u8> {
+        let mut bytes = vec![0; request.buffer_len()];
+        request
+            .emit(&mut DhcpPacket::new_unchecked(&mut bytes[..]))
+            .unwrap();
+        bytes
+    }
+
+    /// Process `request`, and return the type of the reply, the address it is for, and
+    /// its destination.
+    fn send(
+        server: &mut Server,
+        request: DhcpRepr,
+        now: Instant,
+    ) -> Option<(DhcpMessageType, Ipv4Address, Ipv4Address)> {
+        let bytes = emit(&request);
+        server.expire(now);
+        let (reply, dst_addr) = server.process(&bytes, now)?;
+        assert_eq!(reply.transaction_id, request.transaction_id);
+        assert_eq!(reply.server_identifier, Some(SERVER));
+        Some((reply.message_type, reply.your_ip, dst_addr))
+    }
+
+    /// Send a DHCPDISCOVER from `client`, and return the offered address.
+    fn offer(server: &mut Server, client: EthernetAddress) -> Option<Ipv4Address> {
+        let bytes = emit(&request(DhcpMessageType::Discover, client, None));
+        let (reply, _) = server.process(&bytes, Instant::ZERO)?;
+        assert_eq!(reply.message_type, DhcpMessageType::Offer);
+        Some(reply.your_ip)
+    }
+
+    fn lease(server: &Server, client: EthernetAddress) -> Option<Lease> {
+        server
+            .leases()
+            .find(|lease| lease.hardware_addr() == client)
+            .cloned()
+    }
+
+    #[test]
+    fn test_offer_ack() {
+        let mut server = server(4);
+        let now = Instant::ZERO;
+        let discover = request(DhcpMessageType::Discover, CLIENT_A, None);
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, POOL_1, Ipv4Address::BROADCAST))
+        );
+        assert_eq!(
+            lease(&server, CLIENT_A).unwrap().state(),
+            LeaseState::Offered
+        );
+
+        let mut req = request(DhcpMessageType::Request, CLIENT_A, Some(POOL_1));
+        req.server_identifier = Some(SERVER);
+        assert_eq!(
+            send(&mut server, req, now),
+            Some((DhcpMessageType::Ack, POOL_1, Ipv4Address::BROADCAST))
+        );
+        let lease_a = lease(&server, CLIENT_A).unwrap();
+        assert_eq!(lease_a.state(), LeaseState::Bound);
+        assert_eq!(
+            lease_a.expires_at(),
+            Some(now + Config::DEFAULT_LEASE_DURATION)
+        );
+
+        // Renewing is unicast to the client.
+        let mut renew = request(DhcpMessageType::Request, CLIENT_A, None);
+        renew.client_ip = POOL_1;
+        let now = Instant::from_secs(1800);
+        assert_eq!(
+            send(&mut server, renew, now),
+            Some((DhcpMessageType::Ack, POOL_1, POOL_1))
+        );
+        assert_eq!(
+            lease(&server, CLIENT_A).unwrap().expires_at(),
+            Some(now + Config::DEFAULT_LEASE_DURATION)
+        );
+
+        // Another client gets another address, even if it requests the same one.
+        let discover = request(DhcpMessageType::Discover, CLIENT_B, Some(POOL_1));
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, POOL_2, Ipv4Address::BROADCAST))
+        );
+        let req = request(DhcpMessageType::Request, CLIENT_B, Some(POOL_1));
+        assert_eq!(
+            send(&mut server, req, now),
+            Some((
+                DhcpMessageType::Nak,
+                Ipv4Address::UNSPECIFIED,
+                Ipv4Address::BROADCAST
+            ))
+        );
+    }
+
+    #[test]
+    fn test_pool_exhausted() {
+        let mut server = server(4);
+        let now = Instant::ZERO;
+        for client in [CLIENT_A, CLIENT_B].iter() {
+            let discover = request(DhcpMessageType::Discover, *client, None);
+            assert!(send(&mut server, discover, now).is_some());
+        }
+        let discover = request(DhcpMessageType::Discover, CLIENT_C, None);
+        assert_eq!(send(&mut server, discover, now), None);
+
+        // Offers that are not requested expire.
+        let now = now + OFFER_TIMEOUT;
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, POOL_1, Ipv4Address::BROADCAST))
+        );
+    }
+
+    #[test]
+    fn test_storage_exhausted() {
+        let mut server = server(1);
+        let discover = request(DhcpMessageType::Discover, CLIENT_A, None);
+        assert!(send(&mut server, discover, Instant::ZERO).is_some());
+        let discover = request(DhcpMessageType::Discover, CLIENT_B, None);
+        assert_eq!(send(&mut server, discover, Instant::ZERO), None);
+    }
+
+    #[test]
+    fn test_other_server() {
+        let mut server = server(4);
+        let now = Instant::ZERO;
+        let discover = request(DhcpMessageType::Discover, CLIENT_A, None);
+        assert!(send(&mut server, discover, now).is_some());
+
+        let mut req = request(DhcpMessageType::Request, CLIENT_A, Some(POOL_1));
+        req.server_identifier = Some(Ipv4Address([192, 168, 1, 2]));
+        assert_eq!(send(&mut server, req, now), None);
+        assert_eq!(lease(&server, CLIENT_A), None);
+    }
+
+    #[test]
+    fn test_release_decline() {
+        let mut server = server(4);
+        let now = Instant::ZERO;
+        let req = request(DhcpMessageType::Request, CLIENT_A, Some(POOL_2));
+        assert_eq!(
+            send(&mut server, req, now),
+            Some((DhcpMessageType::Ack, POOL_2, Ipv4Address::BROADCAST))
+        );
+
+        let mut release = request(DhcpMessageType::Release, CLIENT_A, None);
+        release.client_ip = POOL_2;
+        assert_eq!(send(&mut server, release, now), None);
+        assert_eq!(lease(&server, CLIENT_A), None);
+
+        // A declined address is not offered again until the lease duration elapsed.
+        let discover = request(DhcpMessageType::Discover, CLIENT_A, None);
+        assert!(send(&mut server, discover, now).is_some());
+        let decline = request(DhcpMessageType::Decline, CLIENT_A, Some(POOL_1));
+        assert_eq!(send(&mut server, decline, now), None);
+        let discover = request(DhcpMessageType::Discover, CLIENT_B, Some(POOL_1));
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, POOL_2, Ipv4Address::BROADCAST))
+        );
+        let discover = request(DhcpMessageType::Discover, CLIENT_C, Some(POOL_1));
+        let now = now + Config::DEFAULT_LEASE_DURATION;
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, POOL_1, Ipv4Address::BROADCAST))
+        );
+    }
+
+    #[test]
+    fn test_reservation() {
+        let mut server = server(4);
+        let reserved = Ipv4Address([192, 168, 1, 50]);
+        assert_eq!(server.reserve(CLIENT_C, SERVER), Err(Error::Illegal));
+        assert_eq!(
+            server.reserve(CLIENT_C, Ipv4Address([10, 0, 0, 1])),
+            Err(Error::Illegal)
+        );
+        assert_eq!(server.reserve(CLIENT_C, reserved), Ok(()));
+        assert_eq!(lease(&server, CLIENT_C).unwrap().expires_at(), None);
+
+        let now = Instant::ZERO;
+        let discover = request(DhcpMessageType::Discover, CLIENT_C, Some(POOL_1));
+        assert_eq!(
+            send(&mut server, discover, now),
+            Some((DhcpMessageType::Offer, reserved, Ipv4Address::BROADCAST))
+        );
+        let req = request(DhcpMessageType::Request, CLIENT_C, Some(reserved));
+        assert!(send(&mut server, req, now).is_some());
+
+        // The reservation is kept after the lease expired.
+        let now = now + Config::DEFAULT_LEASE_DURATION;
+        server.expire(now);
+        let lease_c = lease(&server, CLIENT_C).unwrap();
+        assert!(lease_c.is_reserved());
+        assert_eq!(lease_c.state(), LeaseState::Free);
+    }
+
+    #[test]
+    fn test_config() {
+        let subnet = Ipv4Cidr::new(SERVER, 24);
+        // Empty pool.
+        assert_eq!(Config::new(subnet, POOL_1, 0), Err(Error::Illegal));
+        // Pools containing the address of the server.
+        assert_eq!(Config::new(subnet, SERVER, 10), Err(Error::Illegal));
+        assert!(Config::new(subnet, Ipv4Address([192, 168, 1, 2]), 253).is_ok());
+        assert_eq!(
+            Config::new(
+                Ipv4Cidr::new(POOL_1, 24),
+                Ipv4Address([192, 168, 1, 2]),
+                253
+            ),
+            Err(Error::Illegal)
+        );
+        // Pools outside the subnet, or containing its network or broadcast address.
+        assert_eq!(
+            Config::new(subnet, Ipv4Address([192, 168, 1, 0]), 1),
+            Err(Error::Illegal)
+        );
+        assert_eq!(
+            Config::new(subnet, Ipv4Address([192, 168, 2, 100]), 10),
+            Err(Error::Illegal)
+        );
+        assert_eq!(
+            Config::new(subnet, Ipv4Address([192, 168, 1, 250]), 10),
+            Err(Error::Illegal)
+        );
+        assert_eq!(
+            Config::new(subnet, Ipv4Address([192, 168, 1, 2]), 254),
+            Err(Error::Illegal)
+        );
+    }
+
+    #[test]
+    fn test_pool_at_top_of_address_space() {
+        // Pools reaching past 255.255.255.255 are rejected.
+        let subnet = Ipv4Cidr::new(Ipv4Address([255, 255, 255, 1]), 24);
+        assert_eq!(
+            Config::new(subnet, Ipv4Address([255, 255, 255, 250]), 10),
+            Err(Error::Illegal)
+        );
+
+        // 255.255.255.255 is a host address of a /31 subnet.
+        let subnet = Ipv4Cidr::new(Ipv4Address([255, 255, 255, 254]), 31);
+        let config = Config::new(subnet, Ipv4Address::BROADCAST, 1).unwrap();
+        let mut server = Server::new(config, vec![None; 4]);
+        assert_eq!(offer(&mut server, CLIENT_A), Some(Ipv4Address::BROADCAST));
+        assert_eq!(offer(&mut server, CLIENT_B), None);
+
+        // A configuration built by hand can have a pool that contains the address of the
+        // server and extends past the end of the address space; only the addresses that
+        // exist and are not the server's are offered.
+        let config = Config {
+            pool_start: Ipv4Address([255, 255, 255, 254]),
+            pool_size: 4,
+            ..config
+        };
+        let mut server = Server::new(config, vec![None; 4]);
+        assert_eq!(offer(&mut server, CLIENT_A), Some(Ipv4Address::BROADCAST));
+        assert_eq!(offer(&mut server, CLIENT_B), None);
+    }
+
+    #[test]
+    fn test_inform() {
+        let mut server = server(4);
+        let mut inform = request(DhcpMessageType::Inform, CLIENT_A, None);
+        inform.client_ip = Ipv4Address([192, 168, 1, 10]);
+        assert_eq!(
+            send(&mut server, inform, Instant::ZERO),
+            Some((
+                DhcpMessageType::Ack,
+                Ipv4Address::UNSPECIFIED,
+                Ipv4Address([192, 168, 1, 10])
+            ))
+        );
+        assert_eq!(server.leases().count(), 0);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-dhcpv4", feature = "medium-ethernet"))]
+    fn test_dhcp_client() {
+        use crate::iface::{InterfaceBuilder, NeighborCache, SocketSet};
+        use crate::phy::{Loopback, Medium};
+        use crate::socket::dhcpv4;
+        use crate::socket::udp::{PacketBuffer, PacketMetadata};
+        use crate::wire::{IpCidr, DHCP_SERVER_PORT};
+        use std::collections::BTreeMap;
+
+        // The client and the server share the interface, and talk over the loopback device.
+        let mut device = Loopback::new(Medium::Ethernet);
+        let iface_builder = InterfaceBuilder::new()
+            .hardware_addr(CLIENT_A.into())
+            .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+            .ip_addrs(vec![IpCidr::new(SERVER.into(), 24)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder = iface_builder
+            .ipv4_fragments_cache(crate::iface::FragmentsCache::new(vec![], BTreeMap::new()));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(crate::iface::FragmentsCache::new(vec![], BTreeMap::new()))
+            .sixlowpan_out_packet_cache(vec![]);
+        let mut iface = iface_builder.try_finalize(&mut device).unwrap();
+
+        let rx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 2048]);
+        let tx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 2048]);
+        let mut udp_socket = udp::Socket::new(rx, tx);
+        udp_socket.bind(DHCP_SERVER_PORT).unwrap();
+
+        let mut sockets = SocketSet::new(vec![]);
+        let server_handle = sockets.add(udp_socket);
+        let client_handle = sockets.add(dhcpv4::Socket::new());
+        let mut server = server(4);
+
+        let mut event = None;
+        for _ in 0..10 {
+            iface
+                .poll(Instant::ZERO, &mut device, &mut sockets)
+                .unwrap();
+            server.poll(sockets.get_mut::<udp::Socket>(server_handle), Instant::ZERO);
+            if let Some(new_event) = sockets.get_mut::<dhcpv4::Socket>(client_handle).poll() {
+                event = Some(new_event);
+            }
+        }
+        assert_eq!(
+            event,
+            Some(dhcpv4::Event::Configured(dhcpv4::Config {
+                address: Ipv4Cidr::new(POOL_1, 24),
+                router: Some(SERVER),
+                dns_servers: [None; DHCP_MAX_DNS_SERVER_COUNT],
+            }))
+        );
+        assert_eq!(lease(&server, CLIENT_A).unwrap().state(), LeaseState::Bound);
+    }
+}
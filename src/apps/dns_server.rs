@@ -0,0 +1,782 @@
+/*! DNS server and forwarder.
+
+A [Server] answers DNS queries (RFC 1035) for the names in a static table of [Record]s, and
+forwards the other queries to an upstream server, caching the addresses it returns. Together
+with the DHCP server, it lets the hosts attached to a gateway reach it and the Internet by name.
+
+The server operates on a [udp::Socket] bound to port 53. Only A and AAAA queries are answered
+from the table and the cache; other queries for names in the table are answered with no
+records, and other queries for other names are forwarded as is. Without an upstream server,
+queries for names not in the table are refused.
+
+In captive portal mode, enabled with [Server::set_captive_portal], queries for names that
+are neither in the table nor allowed are answered with the address of the device, so that
+browsers are directed to its configuration page.
+
+[Server::poll] must be called after every call to `Interface::poll()`.
+*/
+
+use managed::ManagedSlice;
+
+use crate::rand::Rand;
+use crate::socket::udp;
+use crate::time::{Duration, Instant};
+use crate::wire::dns::{
+    Flags, Opcode, Packet, Question, Rcode, Record as DnsRecord, RecordData, Type,
+};
+use crate::wire::{IpAddress, IpEndpoint};
+
+/// The UDP port DNS servers listen on.
+pub const UDP_PORT: u16 = 53;
+
+/// The default TTL of answers from the record table.
+const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+/// The maximum size of a DNS message over UDP, per RFC 1035 § 4.2.1.
+const MAX_MESSAGE_LEN: usize = 512;
+
+/// The maximum length of a name in wire format.
+const MAX_NAME_LEN: usize = 255;
+
+/// Time after which a query forwarded upstream without a response is forgotten.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Offset of the question name in a message, used in answers to point to it.
+const QUESTION_NAME_PTR: [u8; 2] = [0xc0, 0x0c];
+
+/// A static record.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Record<'a> {
+    /// Name of the record, such as `"router.lan"`. Names are compared case-insensitively.
+    pub name: &'a str,
+    /// Address the name resolves to, returned in A or AAAA records.
+    pub address: IpAddress,
+}
+
+/// Configuration of the captive portal mode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CaptivePortal<'a> {
+    /// Address queries are answered with, usually the address of the device.
+    pub address: IpAddress,
+    /// Names resolved as usual, such as the names of servers the device must reach. Names
+    /// also allow the names below them, e.g. `"example.com"` allows `"www.example.com"`.
+    pub allowed: &'a [&'a str],
+}
+
+/// An address cached from a response of the upstream server.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheEntry {
+    name: [u8; MAX_NAME_LEN],
+    name_len: usize,
+    type_: Type,
+    address: IpAddress,
+    expires_at: Instant,
+}
+
+impl CacheEntry {
+    fn name(&self) -> &[u8] {
+        &self.name[..self.name_len]
+    }
+}
+
+/// A query forwarded to the upstream server.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ForwardedQuery {
+    transaction_id: u16,
+    client: IpEndpoint,
+    client_transaction_id: u16,
+    expires_at: Instant,
+}
+
+/// A DNS server.
+#[derive(Debug)]
+pub struct Server<'a> {
+    records: &'a [Record<'a>],
+    ttl: Duration,
+    upstream: Option<IpEndpoint>,
+    cache: ManagedSlice<'a, Option<CacheEntry>>,
+    forwarded: ManagedSlice<'a, Option<ForwardedQuery>>,
+    captive_portal: Option<CaptivePortal<'a>>,
+    rand: Rand,
+}
+
+impl<'a> Server<'a> {
+    /// Create a server answering queries for `records`, caching addresses in `cache`, and
+    /// keeping track of the queries forwarded upstream in `forwarded`.
+    pub fn new<C, F>(records: &'a [Record<'a>], cache: C, forwarded: F) -> Server<'a>
+    where
+        C: Into<ManagedSlice<'a, Option<CacheEntry>>>,
+        F: Into<ManagedSlice<'a, Option<ForwardedQuery>>>,
+    {
+        let mut cache = cache.into();
+        for entry in cache.iter_mut() {
+            *entry = None;
+        }
+        let mut forwarded = forwarded.into();
+        for query in forwarded.iter_mut() {
+            *query = None;
+        }
+        Server {
+            records,
+            ttl: DEFAULT_TTL,
+            upstream: None,
+            cache,
+            forwarded,
+            captive_portal: None,
+            rand: Rand::new(0),
+        }
+    }
+
+    /// Return the static records.
+    pub fn records(&self) -> &'a [Record<'a>] {
+        self.records
+    }
+
+    /// Set the static records.
+    pub fn set_records(&mut self, records: &'a [Record<'a>]) {
+        self.records = records
+    }
+
+    /// Return the TTL of answers from the record table.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Set the TTL of answers from the record table.
+    ///
+    /// The default is 60 seconds.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl
+    }
+
+    /// Return the server queries are forwarded to.
+    pub fn upstream(&self) -> Option<IpEndpoint> {
+        self.upstream
+    }
+
+    /// Set the server queries are forwarded to, and flush the cache.
+    ///
+    /// `random_seed` is used to pick the transaction IDs of forwarded queries, and should be
+    /// different every time the device boots, so that responses cannot easily be forged.
+    pub fn set_upstream(&mut self, upstream: Option<IpEndpoint>, random_seed: u64) {
+        self.upstream = upstream;
+        self.rand = Rand::new(random_seed);
+        for entry in self.cache.iter_mut() {
+            *entry = None;
+        }
+        for query in self.forwarded.iter_mut() {
+            *query = None;
+        }
+    }
+
+    /// Return the captive portal configuration.
+    pub fn captive_portal(&self) -> Option<CaptivePortal<'a>> {
+        self.captive_portal
+    }
+
+    /// Enable or disable the captive portal mode.
+    ///
+    /// When enabled, queries for names that are neither in the record table nor allowed are
+    /// answered with the address of the portal, with a TTL of zero so that clients do not
+    /// keep using it once the mode is disabled.
+    pub fn set_captive_portal(&mut self, captive_portal: Option<CaptivePortal<'a>>) {
+        self.captive_portal = captive_portal
+    }
+
+    /// Answer the queries received on `socket`, and relay the responses of the upstream
+    /// server.
+    pub fn poll(&mut self, socket: &mut udp::Socket, now: Instant) {
+        for slot in self.forwarded.iter_mut() {
+            if matches!(slot, Some(query) if query.expires_at <= now) {
+                *slot = None;
+            }
+        }
+        for slot in self.cache.iter_mut() {
+            if matches!(slot, Some(entry) if entry.expires_at <= now) {
+                *slot = None;
+            }
+        }
+
+        while let Ok((payload, meta)) = socket.recv() {
+            let endpoint = meta.endpoint;
+            let mut buffer = [0; MAX_MESSAGE_LEN];
+            let reply = if Some(endpoint) == self.upstream {
+                self.process_response(payload, now, &mut buffer)
+            } else {
+                self.process_query(payload, endpoint, now, &mut buffer)
+            };
+            let (len, endpoint) = match reply {
+                Some(reply) => reply,
+                None => continue,
+            };
+            if socket.send_slice(&buffer[..len], endpoint).is_err() {
+                net_debug!("dns: cannot send message to {}", endpoint);
+            }
+        }
+    }
+
+    /// Process a query from `client`, and emit the response or the query to forward
+    /// into `buffer`. Returns its length and destination, if any.
+    fn process_query(
+        &mut self,
+        payload: &[u8],
+        client: IpEndpoint,
+        now: Instant,
+        buffer: &mut [u8],
+    ) -> Option<(usize, IpEndpoint)> {
+        let packet = Packet::new_checked(payload).ok()?;
+        if packet.flags().contains(Flags::RESPONSE)
+            || packet.opcode() != Opcode::Query
+            || packet.question_count() != 1
+        {
+            return None;
+        }
+        let (_, question) = Question::parse(packet.payload()).ok()?;
+        net_trace!("dns: query from {} type {:?}", client, question.type_);
+
+        if self
+            .records
+            .iter()
+            .any(|record| name_eq(question.name, record.name))
+        {
+            let addresses = self
+                .records
+                .iter()
+                .filter(|record| name_eq(question.name, record.name))
+                .map(|record| record.address);
+            let ttl = self.ttl.secs() as u32;
+            let len = emit_response(&packet, &question, true, ttl, addresses, buffer);
+            return Some((len, client));
+        }
+
+        if let Some(portal) = self.captive_portal {
+            if !portal
+                .allowed
+                .iter()
+                .any(|domain| name_in_domain(question.name, domain))
+            {
+                net_trace!("dns: redirecting query from {} to the portal", client);
+                let addresses = core::iter::once(portal.address);
+                let len = emit_response(&packet, &question, true, 0, addresses, buffer);
+                return Some((len, client));
+            }
+        }
+
+        let cached = self.cache.iter().flatten().find(|entry| {
+            entry.type_ == question.type_ && entry.name().eq_ignore_ascii_case(question.name)
+        });
+        if let Some(entry) = cached {
+            let ttl = (entry.expires_at - now).secs() as u32;
+            let addresses = core::iter::once(entry.address);
+            let len = emit_response(&packet, &question, false, ttl, addresses, buffer);
+            return Some((len, client));
+        }
+
+        let upstream = match self.upstream {
+            Some(upstream) => upstream,
+            None => return Some((emit_error(&packet, Rcode::Refused, buffer)?, client)),
+        };
+        let slot = match self.forwarded.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => slot,
+            None => {
+                net_debug!("dns: no space to forward query from {}", client);
+                return Some((emit_error(&packet, Rcode::ServFail, buffer)?, client));
+            }
+        };
+        let transaction_id = self.rand.rand_u16();
+        *slot = Some(ForwardedQuery {
+            transaction_id,
+            client,
+            client_transaction_id: packet.transaction_id(),
+            expires_at: now + FORWARD_TIMEOUT,
+        });
+
+        let buffer = buffer.get_mut(..payload.len())?;
+        buffer.copy_from_slice(payload);
+        Packet::new_unchecked(&mut buffer[..]).set_transaction_id(transaction_id);
+        Some((payload.len(), upstream))
+    }
+
+    /// Process a response from the upstream server, and emit the response to the client
+    /// into `buffer`. Returns its length and destination, if any.
+    fn process_response(
+        &mut self,
+        payload: &[u8],
+        now: Instant,
+        buffer: &mut [u8],
+    ) -> Option<(usize, IpEndpoint)> {
+        let packet = Packet::new_checked(payload).ok()?;
+        if !packet.flags().contains(Flags::RESPONSE) {
+            return None;
+        }
+        let slot = self.forwarded.iter_mut().find(
+            |slot| matches!(slot, Some(query) if query.transaction_id == packet.transaction_id()),
+        )?;
+        let query = slot.take().unwrap();
+
+        if packet.rcode() == Rcode::NoError && packet.question_count() == 1 {
+            if let Some((question, address, ttl)) = parse_address(&packet) {
+                self.cache_address(question, address, now + Duration::from_secs(ttl as u64));
+            }
+        }
+
+        let buffer = buffer.get_mut(..payload.len())?;
+        buffer.copy_from_slice(payload);
+        Packet::new_unchecked(&mut buffer[..]).set_transaction_id(query.client_transaction_id);
+        Some((payload.len(), query.client))
+    }
+
+    fn cache_address(&mut self, question: Question, address: IpAddress, expires_at: Instant) {
+        if question.name.len() > MAX_NAME_LEN {
+            return;
+        }
+        // Replace the entry that expires first if the cache is full.
+        let slot = self
+            .cache
+            .iter_mut()
+            .min_by_key(|slot| slot.as_ref().map(|entry| entry.expires_at));
+        if let Some(slot) = slot {
+            let mut entry = CacheEntry {
+                name: [0; MAX_NAME_LEN],
+                name_len: question.name.len(),
+                type_: question.type_,
+                address,
+                expires_at,
+            };
+            entry.name[..entry.name_len].copy_from_slice(question.name);
+            *slot = Some(entry);
+        }
+    }
+}
+
+/// Query whether the wire format name `wire` is equal to `name`, ignoring case.
+fn name_eq(mut wire: &[u8], name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let mut labels = name.split('.');
+    loop {
+        let len = match wire.first() {
+            Some(&len) if len & 0xc0 == 0 => len as usize,
+            _ => return false,
+        };
+        if len == 0 {
+            return labels.next().is_none();
+        }
+        let label = match wire.get(1..1 + len) {
+            Some(label) => label,
+            None => return false,
+        };
+        match labels.next() {
+            Some(expected) if label.eq_ignore_ascii_case(expected.as_bytes()) => (),
+            _ => return false,
+        }
+        wire = &wire[1 + len..];
+    }
+}
+
+/// Query whether the wire format name `wire` is equal to `domain` or below it, ignoring case.
+fn name_in_domain(mut wire: &[u8], domain: &str) -> bool {
+    loop {
+        if name_eq(wire, domain) {
+            return true;
+        }
+        match wire.first() {
+            Some(&len) if len != 0 && len & 0xc0 == 0 => {
+                wire = match wire.get(1 + len as usize..) {
+                    Some(rest) => rest,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Return the question of `packet`, and the first address of the requested type in
+/// its answers, with its TTL.
+fn parse_address<'a>(packet: &'a Packet<&'a [u8]>) -> Option<(Question<'a>, IpAddress, u32)> {
+    let (mut payload, question) = Question::parse(packet.payload()).ok()?;
+    for _ in 0..packet.answer_record_count() {
+        let (rest, record) = DnsRecord::parse(payload).ok()?;
+        payload = rest;
+        let address = match (question.type_, record.data) {
+            #[cfg(feature = "proto-ipv4")]
+            (Type::A, RecordData::A(address)) => IpAddress::Ipv4(address),
+            #[cfg(feature = "proto-ipv6")]
+            (Type::Aaaa, RecordData::Aaaa(address)) => IpAddress::Ipv6(address),
+            _ => continue,
+        };
+        return Some((question, address, record.ttl));
+    }
+    None
+}
+
+/// Emit a response to the query in `packet` with the addresses of the requested type in
+/// `addresses` into `buffer`, and return its length.
+fn emit_response(
+    packet: &Packet<&[u8]>,
+    question: &Question,
+    authoritative: bool,
+    ttl: u32,
+    addresses: impl Iterator<Item = IpAddress>,
+    buffer: &mut [u8],
+) -> usize {
+    let mut len = 12 + question.buffer_len();
+    let mut count = 0;
+    for address in addresses {
+        let (type_, data) = match address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(ref address) => (Type::A, address.as_bytes()),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(ref address) => (Type::Aaaa, address.as_bytes()),
+        };
+        let record_len = QUESTION_NAME_PTR.len() + 10 + data.len();
+        if type_ != question.type_ || len + record_len > buffer.len() {
+            continue;
+        }
+        let record = &mut buffer[len..len + record_len];
+        record[..2].copy_from_slice(&QUESTION_NAME_PTR);
+        record[2..4].copy_from_slice(&u16::from(type_).to_be_bytes());
+        record[4..6].copy_from_slice(&1u16.to_be_bytes());
+        record[6..10].copy_from_slice(&ttl.to_be_bytes());
+        record[10..12].copy_from_slice(&(data.len() as u16).to_be_bytes());
+        record[12..].copy_from_slice(data);
+        len += record_len;
+        count += 1;
+    }
+
+    let mut flags = Flags::RESPONSE | Flags::RECURSION_AVAILABLE;
+    flags |= packet.flags() & Flags::RECURSION_DESIRED;
+    if authoritative {
+        flags |= Flags::AUTHORITATIVE;
+    }
+    let mut response = Packet::new_unchecked(&mut buffer[..len]);
+    emit_header(packet, &mut response, flags, Rcode::NoError);
+    response.set_question_count(1);
+    response.set_answer_record_count(count);
+    question.emit(response.payload_mut());
+    len
+}
+
+/// Emit a response to the query in `packet` with the error `rcode` and no question into
+/// `buffer`, and return its length.
+fn emit_error(packet: &Packet<&[u8]>, rcode: Rcode, buffer: &mut [u8]) -> Option<usize> {
+    let mut response = Packet::new_unchecked(buffer.get_mut(..12)?);
+    let flags = Flags::RESPONSE | (packet.flags() & Flags::RECURSION_DESIRED);
+    emit_header(packet, &mut response, flags, rcode);
+    response.set_question_count(0);
+    response.set_answer_record_count(0);
+    Some(12)
+}
+
+fn emit_header(
+    packet: &Packet<&[u8]>,
+    response: &mut Packet<&mut [u8]>,
+    flags: Flags,
+    rcode: Rcode,
+) {
+    response.set_transaction_id(packet.transaction_id());
+    response.set_flags(flags);
+    response.set_opcode(Opcode::Query);
+    response.set_rcode(rcode);
+    response.set_authority_record_count(0);
+    response.set_additional_record_count(0);
+}
+
+#[cfg(all(test, feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::wire::dns::Repr;
+    use crate::wire::Ipv4Address;
+    use std::vec;
+    use std::vec::Vec;
+
+    const NAME: &[u8] = b"\x06router\x03lan\x00";
+    const OTHER_NAME: &[u8] = b"\x07example\x03com\x00";
+    const ROUTER: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
+    const CLIENT: Ipv4Address = Ipv4Address([192, 168, 1, 100]);
+    const UPSTREAM: Ipv4Address = Ipv4Address([192, 0, 2, 53]);
+
+    static RECORDS: [Record; 2] = [
+        Record {
+            name: "Router.lan.",
+            address: IpAddress::Ipv4(ROUTER),
+        },
+        Record {
+            name: "router.lan",
+            address: IpAddress::Ipv4(Ipv4Address([10, 0, 0, 1])),
+        },
+    ];
+
+    fn server() -> Server<'static> {
+        Server::new(&RECORDS, vec![None; 2], vec![None; 2])
+    }
+
+    fn client() -> IpEndpoint {
+        IpEndpoint::new(CLIENT.into(), 49152)
+    }
+
+    fn query(name: &[u8], type_: Type) -> Vec<u8> {
+        let repr = Repr {
+            transaction_id: 0x1234,
+            opcode: Opcode::Query,
+            flags: Flags::RECURSION_DESIRED,
+            question: Question { name, type_ },
+        };
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        bytes
+    }
+
+    /// Return the rcode, flags and addresses of `response`.
+    fn parse(response: &[u8]) -> (Rcode, Flags, Vec<(IpAddress, u32)>) {
+        let packet = Packet::new_checked(response).unwrap();
+        assert_eq!(packet.transaction_id(), 0x1234);
+        let mut addresses = vec![];
+        if packet.question_count() == 1 {
+            let (mut payload, _) = Question::parse(packet.payload()).unwrap();
+            for _ in 0..packet.answer_record_count() {
+                let (rest, record) = DnsRecord::parse(payload).unwrap();
+                payload = rest;
+                if let RecordData::A(address) = record.data {
+                    addresses.push((IpAddress::Ipv4(address), record.ttl));
+                }
+            }
+        }
+        (packet.rcode(), packet.flags(), addresses)
+    }
+
+    #[test]
+    fn test_record() {
+        let mut server = server();
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+        let (len, endpoint) = server
+            .process_query(&query(NAME, Type::A), client(), Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_eq!(endpoint, client());
+        let (rcode, flags, addresses) = parse(&buffer[..len]);
+        assert_eq!(rcode, Rcode::NoError);
+        assert!(flags.contains(Flags::RESPONSE | Flags::AUTHORITATIVE));
+        assert_eq!(
+            addresses,
+            vec![
+                (IpAddress::Ipv4(ROUTER), 60),
+                (IpAddress::Ipv4(Ipv4Address([10, 0, 0, 1])), 60)
+            ]
+        );
+
+        // Names in the table are answered without records for other types.
+        let (len, _) = server
+            .process_query(
+                &query(NAME, Type::Aaaa),
+                client(),
+                Instant::ZERO,
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(parse(&buffer[..len]).0, Rcode::NoError);
+        assert_eq!(parse(&buffer[..len]).2, vec![]);
+
+        // Without upstream server, other names are refused.
+        let (len, _) = server
+            .process_query(
+                &query(OTHER_NAME, Type::A),
+                client(),
+                Instant::ZERO,
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(parse(&buffer[..len]).0, Rcode::Refused);
+    }
+
+    #[test]
+    fn test_name_eq() {
+        assert!(name_eq(NAME, "router.lan"));
+        assert!(name_eq(NAME, "ROUTER.LAN."));
+        assert!(!name_eq(NAME, "router"));
+        assert!(!name_eq(NAME, "router.lan.example"));
+        assert!(!name_eq(b"\x06router\xc0\x0c", "router.lan"));
+    }
+
+    #[test]
+    fn test_name_in_domain() {
+        assert!(name_in_domain(OTHER_NAME, "example.com"));
+        assert!(name_in_domain(OTHER_NAME, "com"));
+        assert!(name_in_domain(
+            b"\x03www\x07Example\x03com\x00",
+            "example.com."
+        ));
+        assert!(!name_in_domain(OTHER_NAME, "ample.com"));
+        assert!(!name_in_domain(OTHER_NAME, "www.example.com"));
+    }
+
+    #[test]
+    fn test_captive_portal() {
+        let mut server = server();
+        server.set_upstream(Some(IpEndpoint::new(UPSTREAM.into(), UDP_PORT)), 0);
+        server.set_captive_portal(Some(CaptivePortal {
+            address: IpAddress::Ipv4(ROUTER),
+            allowed: &["update.example"],
+        }));
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+
+        // Other names resolve to the portal.
+        let (len, endpoint) = server
+            .process_query(
+                &query(OTHER_NAME, Type::A),
+                client(),
+                Instant::ZERO,
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(endpoint, client());
+        let (rcode, _, addresses) = parse(&buffer[..len]);
+        assert_eq!(rcode, Rcode::NoError);
+        assert_eq!(addresses, vec![(IpAddress::Ipv4(ROUTER), 0)]);
+
+        // Names in the table are answered as usual.
+        let (len, _) = server
+            .process_query(&query(NAME, Type::A), client(), Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_eq!(parse(&buffer[..len]).2.len(), 2);
+
+        // Allowed names are forwarded.
+        let (_, endpoint) = server
+            .process_query(
+                &query(b"\x02v2\x06update\x07example\x00", Type::A),
+                client(),
+                Instant::ZERO,
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(endpoint, server.upstream().unwrap());
+    }
+
+    #[test]
+    fn test_forward() {
+        let mut server = server();
+        let upstream = IpEndpoint::new(UPSTREAM.into(), UDP_PORT);
+        server.set_upstream(Some(upstream), 0x5eed);
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+
+        // The query is forwarded with another transaction ID.
+        let query = query(OTHER_NAME, Type::A);
+        let (len, endpoint) = server
+            .process_query(&query, client(), Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_eq!(endpoint, upstream);
+        assert_eq!(&buffer[2..len], &query[2..]);
+        let transaction_id = Packet::new_checked(&buffer[..len])
+            .unwrap()
+            .transaction_id();
+
+        // The response is relayed with the transaction ID of the client.
+        let mut response = query.clone();
+        response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 120, 0, 4, 192, 0, 2, 1]);
+        {
+            let mut packet = Packet::new_unchecked(&mut response[..]);
+            packet.set_transaction_id(transaction_id);
+            packet.set_flags(Flags::RESPONSE | Flags::RECURSION_AVAILABLE);
+            packet.set_answer_record_count(1);
+        }
+        let (len, endpoint) = server
+            .process_response(&response, Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_eq!(endpoint, client());
+        let example = IpAddress::Ipv4(Ipv4Address([192, 0, 2, 1]));
+        assert_eq!(parse(&buffer[..len]).2, vec![(example, 120)]);
+
+        // Responses are only relayed once.
+        assert_eq!(
+            server.process_response(&response, Instant::ZERO, &mut buffer),
+            None
+        );
+
+        // The address is then answered from the cache, until it expires.
+        let (len, endpoint) = server
+            .process_query(&query, client(), Instant::from_secs(20), &mut buffer)
+            .unwrap();
+        assert_eq!(endpoint, client());
+        let (rcode, flags, addresses) = parse(&buffer[..len]);
+        assert_eq!(rcode, Rcode::NoError);
+        assert!(!flags.contains(Flags::AUTHORITATIVE));
+        assert_eq!(addresses, vec![(example, 100)]);
+    }
+
+    #[test]
+    fn test_forward_exhausted() {
+        let mut server = Server::new(&RECORDS, vec![], vec![None; 1]);
+        server.set_upstream(Some(IpEndpoint::new(UPSTREAM.into(), UDP_PORT)), 0);
+        let mut buffer = [0; MAX_MESSAGE_LEN];
+        let query = query(OTHER_NAME, Type::A);
+        let (_, endpoint) = server
+            .process_query(&query, client(), Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_ne!(endpoint, client());
+        let (len, endpoint) = server
+            .process_query(&query, client(), Instant::ZERO, &mut buffer)
+            .unwrap();
+        assert_eq!(endpoint, client());
+        assert_eq!(parse(&buffer[..len]).0, Rcode::ServFail);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-dns", feature = "medium-ip"))]
+    fn test_dns_client() {
+        use crate::iface::{InterfaceBuilder, SocketSet};
+        use crate::phy::{Loopback, Medium};
+        use crate::socket::dns;
+        use crate::socket::udp::{PacketBuffer, PacketMetadata};
+        use crate::wire::IpCidr;
+
+        let mut device = Loopback::new(Medium::Ip);
+        let iface_builder = InterfaceBuilder::new().ip_addrs(vec![IpCidr::new(ROUTER.into(), 24)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder = iface_builder.ipv4_fragments_cache(crate::iface::FragmentsCache::new(
+            vec![],
+            std::collections::BTreeMap::new(),
+        ));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(crate::iface::FragmentsCache::new(
+                vec![],
+                std::collections::BTreeMap::new(),
+            ))
+            .sixlowpan_out_packet_cache(vec![]);
+        let mut iface = iface_builder.try_finalize(&mut device).unwrap();
+
+        let rx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let tx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let mut udp_socket = udp::Socket::new(rx, tx);
+        udp_socket.bind(UDP_PORT).unwrap();
+
+        let mut sockets = SocketSet::new(vec![]);
+        let server_handle = sockets.add(udp_socket);
+        let client_handle = sockets.add(dns::Socket::new(&[ROUTER.into()], vec![]));
+        let query = sockets
+            .get_mut::<dns::Socket>(client_handle)
+            .start_query(iface.context(), "router.lan")
+            .unwrap();
+        let mut server = server();
+
+        for _ in 0..3 {
+            iface
+                .poll(Instant::ZERO, &mut device, &mut sockets)
+                .unwrap();
+            server.poll(sockets.get_mut::<udp::Socket>(server_handle), Instant::ZERO);
+        }
+        let addresses = sockets
+            .get_mut::<dns::Socket>(client_handle)
+            .get_query_result(query)
+            .unwrap();
+        assert_eq!(
+            &addresses[..],
+            &[
+                IpAddress::Ipv4(ROUTER),
+                IpAddress::Ipv4(Ipv4Address([10, 0, 0, 1]))
+            ]
+        );
+    }
+}
@@ -0,0 +1,671 @@
+/*! HTTP/1.1 message framing.
+
+This module implements the parts of HTTP/1.1 (RFC 7230) that every application talking HTTP
+over a [tcp::Socket] needs: parsing the head of a message, and finding where its body ends.
+It is sans-IO: [Head::parse] works on a buffer, and [Decoder] and [Encoder] convert between
+the body of a message and its representation on the wire, either with the `Content-Length`
+header or with the `chunked` transfer coding. The [Decoder::recv] and [Encoder::send] methods
+bridge them to a TCP socket.
+
+Everything else, such as the meaning of methods, status codes and headers, and the writing of
+message heads, is left to the application.
+*/
+
+use core::str;
+
+use crate::socket::tcp;
+
+/// The error type for the HTTP framing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The message is not valid HTTP/1.x.
+    Malformed,
+    /// More data than announced in the `Content-Length` header was given.
+    TooLong,
+    /// The message ended before its body was complete.
+    Truncated,
+    /// The TCP socket is not in a state that allows sending or receiving data.
+    InvalidState,
+}
+
+/// The result type for the HTTP framing.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// How the end of the body of a message is determined, per RFC 7230 § 3.3.3.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Framing {
+    /// The body has the given length, from the `Content-Length` header.
+    ContentLength(usize),
+    /// The body uses the `chunked` transfer coding.
+    Chunked,
+    /// The body ends when the connection is closed. Only valid for responses.
+    UntilClose,
+}
+
+/// The start line of a message.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StartLine<'a> {
+    /// The request line of a request.
+    Request { method: &'a str, target: &'a str },
+    /// The status line of a response.
+    Response { status: u16, reason: &'a str },
+}
+
+/// The head of a message: its start line and its header fields.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Head<'a> {
+    start_line: StartLine<'a>,
+    minor_version: u8,
+    headers: &'a [u8],
+    framing: Framing,
+}
+
+impl<'a> Head<'a> {
+    /// Parse the head of a message at the start of `buffer`.
+    ///
+    /// Returns the head and its length, or `Ok(None)` if `buffer` does not contain the
+    /// whole head yet.
+    ///
+    /// The framing of responses to `HEAD` requests and of `2xx` responses to `CONNECT`
+    /// requests does not depend on the response, and must be determined by the application.
+    pub fn parse(buffer: &'a [u8]) -> Result<Option<(Head<'a>, usize)>> {
+        let len = match buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => end + 4,
+            None => return Ok(None),
+        };
+        let head = &buffer[..len - 2];
+        let line_end = head
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or(Error::Malformed)?;
+        let line = str::from_utf8(&head[..line_end]).map_err(|_| Error::Malformed)?;
+        let headers = &head[line_end + 2..];
+
+        let mut parts = line.splitn(3, ' ');
+        let (first, second, third) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(first), Some(second), Some(third)) => (first, second, third),
+            (Some(first), Some(second), None) if first.starts_with("HTTP/") => (first, second, ""),
+            _ => return Err(Error::Malformed),
+        };
+        let (start_line, version) = if first.starts_with("HTTP/") {
+            let status = match second.parse() {
+                Ok(status) if second.len() == 3 && (100..600).contains(&status) => status,
+                _ => return Err(Error::Malformed),
+            };
+            let reason = third;
+            (StartLine::Response { status, reason }, first)
+        } else {
+            if first.is_empty() || second.is_empty() {
+                return Err(Error::Malformed);
+            }
+            let (method, target) = (first, second);
+            (StartLine::Request { method, target }, third)
+        };
+        let minor_version = match version {
+            "HTTP/1.0" => 0,
+            "HTTP/1.1" => 1,
+            _ => return Err(Error::Malformed),
+        };
+
+        let mut head = Head {
+            start_line,
+            minor_version,
+            headers,
+            framing: Framing::UntilClose,
+        };
+        head.framing = head.parse_framing()?;
+        Ok(Some((head, len)))
+    }
+
+    fn parse_framing(&self) -> Result<Framing> {
+        let is_request = matches!(self.start_line, StartLine::Request { .. });
+        let mut content_length = None;
+        let mut chunked = None;
+        for header in self.headers() {
+            let (name, value) = header?;
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                // Only the last transfer coding determines the framing.
+                let last = value.rsplit(|&c| c == b',').next().unwrap_or(value);
+                chunked = Some(trim(last).eq_ignore_ascii_case(b"chunked"));
+            } else if name.eq_ignore_ascii_case("content-length") {
+                let length = str::from_utf8(value)
+                    .ok()
+                    .filter(|value| value.bytes().all(|c| c.is_ascii_digit()))
+                    .and_then(|value| value.parse().ok())
+                    .ok_or(Error::Malformed)?;
+                if matches!(content_length, Some(other) if other != length) {
+                    return Err(Error::Malformed);
+                }
+                content_length = Some(length);
+            }
+        }
+
+        match (chunked, content_length, self.start_line) {
+            (Some(true), _, _) => Ok(Framing::Chunked),
+            (Some(false), _, _) if is_request => Err(Error::Malformed),
+            (Some(false), _, _) => Ok(Framing::UntilClose),
+            (None, Some(length), _) => Ok(Framing::ContentLength(length)),
+            (None, None, StartLine::Request { .. }) => Ok(Framing::ContentLength(0)),
+            (None, None, StartLine::Response { status, .. })
+                if status < 200 || status == 204 || status == 304 =>
+            {
+                Ok(Framing::ContentLength(0))
+            }
+            (None, None, StartLine::Response { .. }) => Ok(Framing::UntilClose),
+        }
+    }
+
+    /// Return the start line.
+    pub fn start_line(&self) -> StartLine<'a> {
+        self.start_line
+    }
+
+    /// Return the minor version of HTTP/1 used by the message.
+    pub fn minor_version(&self) -> u8 {
+        self.minor_version
+    }
+
+    /// Return how the end of the body is determined.
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Return an iterator over the header fields, as names and values without surrounding
+    /// whitespace. Yields `Err(Error::Malformed)` for invalid fields.
+    pub fn headers(&self) -> impl Iterator<Item = Result<(&'a str, &'a [u8])>> {
+        let mut headers = self.headers;
+        core::iter::from_fn(move || {
+            if headers.is_empty() {
+                return None;
+            }
+            let end = headers.windows(2).position(|w| w == b"\r\n")?;
+            let line = &headers[..end];
+            headers = &headers[end + 2..];
+
+            let colon = match line.iter().position(|&c| c == b':') {
+                Some(colon) if colon > 0 => colon,
+                _ => return Some(Err(Error::Malformed)),
+            };
+            let name = match str::from_utf8(&line[..colon]) {
+                Ok(name) if !name.contains(|c: char| c.is_ascii_whitespace()) => name,
+                _ => return Some(Err(Error::Malformed)),
+            };
+            Some(Ok((name, trim(&line[colon + 1..]))))
+        })
+    }
+
+    /// Return the value of the first header field named `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&'a [u8]> {
+        self.headers()
+            .flatten()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+fn trim(mut value: &[u8]) -> &[u8] {
+    while let Some((&c, rest)) = value.split_first() {
+        if c != b' ' && c != b'\t' {
+            break;
+        }
+        value = rest;
+    }
+    while let Some((&c, rest)) = value.split_last() {
+        if c != b' ' && c != b'\t' {
+            break;
+        }
+        value = rest;
+    }
+    value
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum DecoderState {
+    Length(usize),
+    UntilClose,
+    ChunkSize { size: usize, digits: usize },
+    ChunkExtension { size: usize },
+    ChunkSizeLf { size: usize },
+    ChunkData(usize),
+    ChunkDataCr,
+    ChunkDataLf,
+    Trailer { line_len: usize },
+    TrailerLf { line_len: usize },
+    Done,
+}
+
+/// A decoder extracting the body of a message from its representation on the wire.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Decoder {
+    state: DecoderState,
+}
+
+impl Decoder {
+    /// Create a decoder for a body with the given framing.
+    pub fn new(framing: Framing) -> Decoder {
+        let state = match framing {
+            Framing::ContentLength(length) => DecoderState::Length(length),
+            Framing::Chunked => DecoderState::ChunkSize { size: 0, digits: 0 },
+            Framing::UntilClose => DecoderState::UntilClose,
+        };
+        Decoder { state }
+    }
+
+    /// Query whether the whole body was decoded. Any data that follows belongs to the
+    /// next message.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, DecoderState::Length(0) | DecoderState::Done)
+    }
+
+    /// Decode the data in `input` into `output`.
+    ///
+    /// Returns the number of octets consumed from `input` and the number of octets of the
+    /// body written to `output`. Decoding stops at the end of the body, or when `output`
+    /// is full.
+    pub fn decode(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize)> {
+        let (mut consumed, mut produced) = (0, 0);
+        while consumed < input.len() && !self.is_done() {
+            let remaining = match self.state {
+                DecoderState::Length(remaining) | DecoderState::ChunkData(remaining) => remaining,
+                DecoderState::UntilClose => usize::MAX,
+                _ => {
+                    self.state = self.next_state(input[consumed])?;
+                    consumed += 1;
+                    continue;
+                }
+            };
+
+            let len = remaining
+                .min(input.len() - consumed)
+                .min(output.len() - produced);
+            if len == 0 {
+                break;
+            }
+            output[produced..produced + len].copy_from_slice(&input[consumed..consumed + len]);
+            consumed += len;
+            produced += len;
+            self.state = match self.state {
+                DecoderState::Length(remaining) => DecoderState::Length(remaining - len),
+                DecoderState::ChunkData(remaining) if remaining == len => DecoderState::ChunkDataCr,
+                DecoderState::ChunkData(remaining) => DecoderState::ChunkData(remaining - len),
+                state => state,
+            };
+        }
+        Ok((consumed, produced))
+    }
+
+    /// Return the state of the chunked decoder after `byte`.
+    fn next_state(&self, byte: u8) -> Result<DecoderState> {
+        let state = match (self.state, byte) {
+            (DecoderState::ChunkSize { size, digits }, _) if (byte as char).is_ascii_hexdigit() => {
+                let digit = (byte as char).to_digit(16).unwrap() as usize;
+                let size = size
+                    .checked_mul(16)
+                    .and_then(|size| size.checked_add(digit))
+                    .ok_or(Error::Malformed)?;
+                DecoderState::ChunkSize {
+                    size,
+                    digits: digits + 1,
+                }
+            }
+            (DecoderState::ChunkSize { digits: 0, .. }, _) => return Err(Error::Malformed),
+            (DecoderState::ChunkSize { size, .. }, b';')
+            | (DecoderState::ChunkExtension { size }, _) => {
+                if byte == b'\r' {
+                    DecoderState::ChunkSizeLf { size }
+                } else {
+                    DecoderState::ChunkExtension { size }
+                }
+            }
+            (DecoderState::ChunkSize { size, .. }, b'\r') => DecoderState::ChunkSizeLf { size },
+            (DecoderState::ChunkSizeLf { size: 0 }, b'\n') => DecoderState::Trailer { line_len: 0 },
+            (DecoderState::ChunkSizeLf { size }, b'\n') => DecoderState::ChunkData(size),
+            (DecoderState::ChunkDataCr, b'\r') => DecoderState::ChunkDataLf,
+            (DecoderState::ChunkDataLf, b'\n') => DecoderState::ChunkSize { size: 0, digits: 0 },
+            (DecoderState::Trailer { line_len }, b'\r') => DecoderState::TrailerLf { line_len },
+            (DecoderState::Trailer { line_len }, _) => DecoderState::Trailer {
+                line_len: line_len + 1,
+            },
+            (DecoderState::TrailerLf { line_len: 0 }, b'\n') => DecoderState::Done,
+            (DecoderState::TrailerLf { .. }, b'\n') => DecoderState::Trailer { line_len: 0 },
+            _ => return Err(Error::Malformed),
+        };
+        Ok(state)
+    }
+
+    /// Receive data from `socket`, and decode it into `output`.
+    ///
+    /// Returns the number of octets of the body written to `output`. Only the data of this
+    /// message is dequeued from the socket.
+    pub fn recv(&mut self, socket: &mut tcp::Socket, output: &mut [u8]) -> Result<usize> {
+        if self.is_done() {
+            return Ok(0);
+        }
+        let result = socket.recv(|buffer| match self.decode(buffer, output) {
+            Ok((consumed, produced)) => (consumed, Ok(produced)),
+            Err(error) => (0, Err(error)),
+        });
+        match result {
+            Ok(result) => result,
+            Err(tcp::RecvError::Finished) if self.state == DecoderState::UntilClose => {
+                self.state = DecoderState::Done;
+                Ok(0)
+            }
+            Err(tcp::RecvError::Finished) => Err(Error::Truncated),
+            Err(tcp::RecvError::InvalidState) => Err(Error::InvalidState),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum EncoderState {
+    Length(usize),
+    Chunked,
+    UntilClose,
+    Done,
+}
+
+/// The last chunk, followed by an empty trailer section.
+const LAST_CHUNK: &[u8] = b"0\r\n\r\n";
+
+/// An encoder producing the representation on the wire of the body of a message.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Encoder {
+    state: EncoderState,
+}
+
+impl Encoder {
+    /// Create an encoder for a body with the given framing.
+    pub fn new(framing: Framing) -> Encoder {
+        let state = match framing {
+            Framing::ContentLength(length) => EncoderState::Length(length),
+            Framing::Chunked => EncoderState::Chunked,
+            Framing::UntilClose => EncoderState::UntilClose,
+        };
+        Encoder { state }
+    }
+
+    /// Query whether the whole body was encoded.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, EncoderState::Done)
+    }
+
+    /// Encode the body data in `input` into `output`.
+    ///
+    /// Returns the number of octets consumed from `input` and written to `output`.
+    /// With the `chunked` transfer coding, each call produces at most one chunk.
+    ///
+    /// Returns `Err(Error::TooLong)` if `input` exceeds the `Content-Length` of the body.
+    pub fn encode(&mut self, input: &[u8], output: &mut [u8]) -> Result<(usize, usize)> {
+        match self.state {
+            EncoderState::Length(remaining) => {
+                if input.len() > remaining {
+                    return Err(Error::TooLong);
+                }
+                let len = input.len().min(output.len());
+                output[..len].copy_from_slice(&input[..len]);
+                self.state = EncoderState::Length(remaining - len);
+                Ok((len, len))
+            }
+            EncoderState::Chunked => {
+                // Reserve space for the chunk size, assuming the chunk fills the output.
+                let overhead = hex_len(output.len()) + 4;
+                let len = input.len().min(output.len().saturating_sub(overhead));
+                if len == 0 {
+                    return Ok((0, 0));
+                }
+                let mut pos = hex_len(len);
+                let mut size = len;
+                for c in output[..pos].iter_mut().rev() {
+                    *c = b"0123456789abcdef"[size % 16];
+                    size /= 16;
+                }
+                output[pos..pos + 2].copy_from_slice(b"\r\n");
+                pos += 2;
+                output[pos..pos + len].copy_from_slice(&input[..len]);
+                pos += len;
+                output[pos..pos + 2].copy_from_slice(b"\r\n");
+                Ok((len, pos + 2))
+            }
+            EncoderState::UntilClose => {
+                let len = input.len().min(output.len());
+                output[..len].copy_from_slice(&input[..len]);
+                Ok((len, len))
+            }
+            EncoderState::Done => Err(Error::TooLong),
+        }
+    }
+
+    /// Write the end of the body into `output`.
+    ///
+    /// Returns the number of octets written to `output`. If it is too small, nothing is
+    /// written, and the encoder is not done.
+    ///
+    /// Returns `Err(Error::Truncated)` if the body is shorter than its `Content-Length`.
+    /// With a body delimited by the end of the connection, the connection must then be closed.
+    pub fn finish(&mut self, output: &mut [u8]) -> Result<usize> {
+        match self.state {
+            EncoderState::Length(0) | EncoderState::UntilClose => {
+                self.state = EncoderState::Done;
+                Ok(0)
+            }
+            EncoderState::Length(_) => Err(Error::Truncated),
+            EncoderState::Chunked if output.len() < LAST_CHUNK.len() => Ok(0),
+            EncoderState::Chunked => {
+                output[..LAST_CHUNK.len()].copy_from_slice(LAST_CHUNK);
+                self.state = EncoderState::Done;
+                Ok(LAST_CHUNK.len())
+            }
+            EncoderState::Done => Ok(0),
+        }
+    }
+
+    /// Encode the body data in `input`, and enqueue it in `socket`.
+    ///
+    /// Returns the number of octets of `input` that were enqueued.
+    pub fn send(&mut self, socket: &mut tcp::Socket, input: &[u8]) -> Result<usize> {
+        let result = socket.send(|buffer| match self.encode(input, buffer) {
+            Ok((consumed, produced)) => (produced, Ok(consumed)),
+            Err(error) => (0, Err(error)),
+        });
+        result.map_err(|_| Error::InvalidState)?
+    }
+
+    /// Enqueue the end of the body in `socket`.
+    ///
+    /// Returns whether it was enqueued; if not, this method must be called again once
+    /// there is more space in the transmit buffer.
+    pub fn send_finish(&mut self, socket: &mut tcp::Socket) -> Result<bool> {
+        if self.state == EncoderState::Chunked
+            && socket.send_capacity() - socket.send_queue() < LAST_CHUNK.len()
+        {
+            return Ok(false);
+        }
+        // The last chunk may wrap around the end of the transmit buffer.
+        let mut chunk = [0; LAST_CHUNK.len()];
+        let len = self.finish(&mut chunk)?;
+        let sent = socket
+            .send_slice(&chunk[..len])
+            .map_err(|_| Error::InvalidState)?;
+        debug_assert_eq!(sent, len);
+        Ok(true)
+    }
+}
+
+/// Return the number of hexadecimal digits of `value`.
+fn hex_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 16 {
+        value /= 16;
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn test_parse_request() {
+        let buffer = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nAccept:  */* \r\n\r\nbody";
+        let (head, len) = Head::parse(buffer).unwrap().unwrap();
+        assert_eq!(len, buffer.len() - 4);
+        assert_eq!(
+            head.start_line(),
+            StartLine::Request {
+                method: "GET",
+                target: "/index.html"
+            }
+        );
+        assert_eq!(head.minor_version(), 1);
+        assert_eq!(head.header("host"), Some(&b"example.com"[..]));
+        assert_eq!(head.header("Accept"), Some(&b"*/*"[..]));
+        assert_eq!(head.header("Content-Type"), None);
+        assert_eq!(head.framing(), Framing::ContentLength(0));
+
+        assert_eq!(Head::parse(&buffer[..20]), Ok(None));
+    }
+
+    #[test]
+    fn test_parse_framing() {
+        let framing = |head: &[u8]| Head::parse(head).map(|head| head.unwrap().0.framing());
+        assert_eq!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: 42\r\n\r\n"),
+            Ok(Framing::ContentLength(42))
+        );
+        assert_eq!(
+            framing(b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, Chunked\r\nContent-Length: 42\r\n\r\n"),
+            Ok(Framing::Chunked)
+        );
+        assert_eq!(
+            framing(b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip\r\n\r\n"),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: 1\r\nContent-Length: 2\r\n\r\n"),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            framing(b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n"),
+            Err(Error::Malformed)
+        );
+        assert_eq!(framing(b"HTTP/1.0 200 OK\r\n\r\n"), Ok(Framing::UntilClose));
+        assert_eq!(
+            framing(b"HTTP/1.1 204 No Content\r\n\r\n"),
+            Ok(Framing::ContentLength(0))
+        );
+        assert_eq!(framing(b"HTTP/2 200 OK\r\n\r\n"), Err(Error::Malformed));
+        assert_eq!(framing(b"HTTP/1.1 2000 OK\r\n\r\n"), Err(Error::Malformed));
+        assert_eq!(framing(b"GET /\r\n\r\n"), Err(Error::Malformed));
+        assert_eq!(
+            framing(b"GET / HTTP/1.1\r\nBad Header: x\r\n\r\n"),
+            Err(Error::Malformed)
+        );
+    }
+
+    /// Decode `input` feeding it `step` octets at a time.
+    fn decode(framing: Framing, input: &[u8], step: usize) -> Result<(Vec<u8>, usize)> {
+        let mut decoder = Decoder::new(framing);
+        let mut body = Vec::new();
+        let mut consumed = 0;
+        while consumed < input.len() && !decoder.is_done() {
+            let end = (consumed + step).min(input.len());
+            let mut output = [0; 4];
+            let (len, produced) = decoder.decode(&input[consumed..end], &mut output)?;
+            consumed += len;
+            body.extend_from_slice(&output[..produced]);
+        }
+        Ok((body, consumed))
+    }
+
+    #[test]
+    fn test_decode_length() {
+        let input = b"hello worldGET";
+        assert_eq!(
+            decode(Framing::ContentLength(11), input, 5),
+            Ok((b"hello world".to_vec(), 11))
+        );
+        assert_eq!(
+            decode(Framing::UntilClose, input, 5),
+            Ok((input.to_vec(), input.len()))
+        );
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        let input = b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nTrailer: x\r\n\r\nGET";
+        for step in 1..input.len() {
+            assert_eq!(
+                decode(Framing::Chunked, input, step),
+                Ok((b"hello world".to_vec(), input.len() - 3))
+            );
+        }
+
+        assert_eq!(decode(Framing::Chunked, b"x\r\n", 1), Err(Error::Malformed));
+        assert_eq!(
+            decode(Framing::Chunked, b"1\r\nab\r\n", 1),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            decode(Framing::Chunked, b"fffffffffffffffff\r\n", 1),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn test_encode_chunked() {
+        let mut encoder = Encoder::new(Framing::Chunked);
+        let mut output = [0; 32];
+        let input = [b'x'; 40];
+        assert_eq!(encoder.encode(&input, &mut output[..4]), Ok((0, 0)));
+        assert_eq!(encoder.encode(&input, &mut output), Ok((26, 32)));
+        assert_eq!(&output[..4], b"1a\r\n");
+        assert_eq!(&output[30..], b"\r\n");
+        assert_eq!(encoder.finish(&mut output[..4]), Ok(0));
+        assert!(!encoder.is_done());
+        assert_eq!(encoder.finish(&mut output), Ok(5));
+        assert_eq!(&output[..5], b"0\r\n\r\n");
+        assert!(encoder.is_done());
+    }
+
+    #[test]
+    fn test_encode_length() {
+        let mut encoder = Encoder::new(Framing::ContentLength(5));
+        let mut output = [0; 32];
+        assert_eq!(encoder.encode(b"hello!", &mut output), Err(Error::TooLong));
+        assert_eq!(encoder.encode(b"hel", &mut output), Ok((3, 3)));
+        assert_eq!(encoder.finish(&mut output), Err(Error::Truncated));
+        assert_eq!(encoder.encode(b"lo", &mut output), Ok((2, 2)));
+        assert_eq!(encoder.finish(&mut output), Ok(0));
+        assert!(encoder.is_done());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let body: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let mut encoder = Encoder::new(Framing::Chunked);
+        let mut wire = Vec::new();
+        let mut sent = 0;
+        while sent < body.len() {
+            let mut output = [0; 100];
+            let (consumed, produced) = encoder.encode(&body[sent..], &mut output).unwrap();
+            sent += consumed;
+            wire.extend_from_slice(&output[..produced]);
+        }
+        let mut output = [0; 5];
+        let len = encoder.finish(&mut output).unwrap();
+        wire.extend_from_slice(&output[..len]);
+
+        assert_eq!(decode(Framing::Chunked, &wire, 7), Ok((body, wire.len())));
+    }
+}
@@ -0,0 +1,240 @@
+/*! LLMNR responder.
+
+A [Responder] answers LLMNR (RFC 4795) queries for the host name of the device, which
+lets hosts on the same link, notably Windows hosts, reach it by name without any DNS
+infrastructure.
+
+The responder operates on a [udp::Socket] bound to [LLMNR_UDP_PORT]. To receive multicast
+queries over IPv4, the interface must also be a member of [LLMNR_IPV4_MULTICAST_ADDR]; see
+`Interface::join_multicast_group()`. Since IPv6 multicast groups cannot be joined yet,
+multicast queries over IPv6 are only received on media without hardware address filtering.
+Queries are answered with the first address of the
+requested family that is assigned to the interface.
+
+[Responder::poll] must be called after every call to `Interface::poll()`.
+
+[LLMNR_UDP_PORT]: crate::wire::LLMNR_UDP_PORT
+[LLMNR_IPV4_MULTICAST_ADDR]: crate::wire::LLMNR_IPV4_MULTICAST_ADDR
+*/
+
+use crate::socket::udp;
+use crate::time::Duration;
+use crate::wire::{
+    IpAddress, IpCidr, LlmnrAnswer, LlmnrFlags, LlmnrPacket, LlmnrRcode, LlmnrRepr, LlmnrType,
+    LLMNR_HEADER_LEN,
+};
+
+/// The default TTL of answers, as recommended by RFC 4795 § 2.8.
+const DEFAULT_TTL: Duration = Duration::from_secs(30);
+
+/// The maximum size of a response: a question with a name of maximum length,
+/// and an AAAA record.
+const MAX_RESPONSE_LEN: usize = LLMNR_HEADER_LEN + 255 + 4 + 2 + 10 + 16;
+
+/// A responder for LLMNR queries about a single host name.
+#[derive(Debug)]
+pub struct Responder<'a> {
+    hostname: &'a str,
+    ttl: Duration,
+}
+
+impl<'a> Responder<'a> {
+    /// Create a responder for `hostname`.
+    ///
+    /// Host names are compared case-insensitively, and may consist of several labels.
+    pub fn new(hostname: &'a str) -> Responder<'a> {
+        Responder {
+            hostname,
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// Return the host name queries are answered for.
+    pub fn hostname(&self) -> &'a str {
+        self.hostname
+    }
+
+    /// Set the host name queries are answered for.
+    pub fn set_hostname(&mut self, hostname: &'a str) {
+        self.hostname = hostname
+    }
+
+    /// Return the TTL of answers.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Set the TTL of answers.
+    ///
+    /// The default is 30 seconds.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl
+    }
+
+    /// Answer the queries received on `socket`, using the addresses in `addrs`.
+    pub fn poll(&self, socket: &mut udp::Socket, addrs: &[IpCidr]) {
+        while let Ok((payload, endpoint)) = socket.recv() {
+            let mut response = [0; MAX_RESPONSE_LEN];
+            let len = match self.respond(payload, addrs, &mut response) {
+                Some(len) => len,
+                None => continue,
+            };
+            // Responses are always unicast to the sender of the query.
+            if socket.send_slice(&response[..len], endpoint).is_err() {
+                net_debug!("llmnr: cannot send response to {}", endpoint);
+            }
+        }
+    }
+
+    /// Emit the response to `query` into `buffer`, returning its length, or `None` if
+    /// the query must not be answered.
+    fn respond(&self, query: &[u8], addrs: &[IpCidr], buffer: &mut [u8]) -> Option<usize> {
+        let query = LlmnrRepr::parse(&LlmnrPacket::new_checked(query).ok()?).ok()?;
+        if query.flags.contains(LlmnrFlags::RESPONSE)
+            || !query.name.eq_ignore_ascii_case(self.hostname)
+        {
+            return None;
+        }
+        net_trace!("llmnr: query for {} type {}", query.name, query.type_);
+
+        let address =
+            addrs
+                .iter()
+                .map(|cidr| cidr.address())
+                .find(|address| match (query.type_, address) {
+                    #[cfg(feature = "proto-ipv4")]
+                    (LlmnrType::A, IpAddress::Ipv4(_)) => true,
+                    #[cfg(feature = "proto-ipv6")]
+                    (LlmnrType::Aaaa, IpAddress::Ipv6(_)) => true,
+                    (LlmnrType::Any, _) => true,
+                    _ => false,
+                });
+
+        // Queries for a name we are authoritative for are answered even if there is no
+        // address of the requested type, so that the sender does not wait for a timeout.
+        let response = LlmnrRepr {
+            transaction_id: query.transaction_id,
+            flags: LlmnrFlags::RESPONSE,
+            rcode: LlmnrRcode::NoError,
+            name: query.name,
+            type_: query.type_,
+            answer: address.map(|address| LlmnrAnswer {
+                ttl: self.ttl.secs() as u32,
+                address,
+            }),
+        };
+        let len = response.buffer_len();
+        response.emit(&mut LlmnrPacket::new_unchecked(&mut buffer[..len]));
+        Some(len)
+    }
+}
+
+#[cfg(all(test, feature = "proto-ipv4", feature = "medium-ip"))]
+mod test {
+    use super::*;
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-sixlowpan-fragmentation"
+    ))]
+    use crate::iface::FragmentsCache;
+    use crate::iface::{Interface, InterfaceBuilder};
+    use crate::phy::{Loopback, Medium};
+    use crate::socket::udp::{PacketBuffer, PacketMetadata};
+    use crate::time::Instant;
+    use crate::wire::{IpEndpoint, Ipv4Address, LlmnrName, LLMNR_UDP_PORT};
+    use std::vec;
+
+    const LOCALHOST: Ipv4Address = Ipv4Address([127, 0, 0, 1]);
+
+    fn udp_socket(port: u16) -> udp::Socket<'static> {
+        let rx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let tx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let mut socket = udp::Socket::new(rx, tx);
+        socket.bind(port).unwrap();
+        socket
+    }
+
+    fn interface(device: &mut Loopback) -> Interface<'static> {
+        let iface_builder =
+            InterfaceBuilder::new().ip_addrs(vec![IpCidr::new(LOCALHOST.into(), 8)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder = iface_builder.ipv4_fragments_cache(FragmentsCache::new(
+            vec![],
+            std::collections::BTreeMap::new(),
+        ));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(FragmentsCache::new(
+                vec![],
+                std::collections::BTreeMap::new(),
+            ))
+            .sixlowpan_out_packet_cache(vec![]);
+        iface_builder.try_finalize(device).unwrap()
+    }
+
+    /// Send a query for `name` to the responder, and return the parsed answer, if any.
+    fn query(name: &str, type_: LlmnrType) -> Option<Option<LlmnrAnswer>> {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut iface = interface(&mut device);
+        let mut sockets = crate::iface::SocketSet::new(vec![]);
+        let client_handle = sockets.add(udp_socket(49152));
+        let responder_handle = sockets.add(udp_socket(LLMNR_UDP_PORT));
+        let responder = Responder::new("Device");
+
+        let mut name_buf = [0; 64];
+        let repr = LlmnrRepr {
+            transaction_id: 0x1234,
+            flags: LlmnrFlags::empty(),
+            rcode: LlmnrRcode::NoError,
+            name: LlmnrName::encode(name, &mut name_buf).unwrap(),
+            type_,
+            answer: None,
+        };
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut LlmnrPacket::new_unchecked(&mut bytes[..]));
+        sockets
+            .get_mut::<udp::Socket>(client_handle)
+            .send_slice(&bytes, IpEndpoint::new(LOCALHOST.into(), LLMNR_UDP_PORT))
+            .unwrap();
+
+        for _ in 0..3 {
+            iface
+                .poll(Instant::ZERO, &mut device, &mut sockets)
+                .unwrap();
+            responder.poll(
+                sockets.get_mut::<udp::Socket>(responder_handle),
+                iface.ip_addrs(),
+            );
+        }
+
+        let client = sockets.get_mut::<udp::Socket>(client_handle);
+        let (payload, _) = client.recv().ok()?;
+        let response = LlmnrRepr::parse(&LlmnrPacket::new_checked(payload).unwrap()).unwrap();
+        assert_eq!(response.transaction_id, 0x1234);
+        assert_eq!(response.flags, LlmnrFlags::RESPONSE);
+        assert!(response.name.eq_ignore_ascii_case(name));
+        Some(response.answer)
+    }
+
+    #[test]
+    fn test_answer() {
+        assert_eq!(
+            query("device", LlmnrType::A),
+            Some(Some(LlmnrAnswer {
+                ttl: 30,
+                address: LOCALHOST.into()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_other_name() {
+        assert_eq!(query("printer", LlmnrType::A), None);
+        assert_eq!(query("device.lan", LlmnrType::A), None);
+    }
+
+    #[test]
+    fn test_no_address_of_type() {
+        assert_eq!(query("DEVICE", LlmnrType::Aaaa), Some(None));
+    }
+}
@@ -0,0 +1,20 @@
+/*! Application protocols built on top of sockets.
+
+The `apps` module provides small, allocation-free implementations of application
+protocols that are commonly needed on embedded devices. Unlike sockets, they are
+not polled by the interface; instead, they operate on a socket owned by the
+application, and must be polled after every call to `Interface::poll()`.
+*/
+
+#[cfg(all(feature = "proto-dhcpv4", feature = "socket-udp"))]
+pub mod dhcpv4_server;
+#[cfg(all(feature = "proto-dns", feature = "socket-udp"))]
+pub mod dns_server;
+#[cfg(all(feature = "proto-http", feature = "socket-tcp"))]
+pub mod http;
+#[cfg(all(feature = "proto-llmnr", feature = "socket-udp"))]
+pub mod llmnr;
+#[cfg(all(feature = "proto-nbns", feature = "socket-udp"))]
+pub mod nbns;
+#[cfg(all(feature = "proto-tftp", feature = "socket-udp"))]
+pub mod tftp;
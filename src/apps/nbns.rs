@@ -0,0 +1,215 @@
+/*! NetBIOS name service responder.
+
+A [Responder] answers NetBIOS name queries (RFC 1002) for the name of the device, for the
+benefit of older Windows hosts that do not resolve names using LLMNR.
+
+The responder operates on a [udp::Socket] bound to [NBNS_UDP_PORT]. Queries are broadcast,
+and answered with the first IPv4 address assigned to the interface. Names are not
+registered or defended; the responder only acts as a B-node answering queries.
+
+[Responder::poll] must be called after every call to `Interface::poll()`.
+
+[NBNS_UDP_PORT]: crate::wire::NBNS_UDP_PORT
+*/
+
+use crate::socket::udp;
+use crate::time::Duration;
+use crate::wire::{IpAddress, IpCidr, NbnsName, NbnsPacket, NbnsRepr, Result};
+
+/// The default TTL of answers, matching the default of Windows.
+const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+/// The size of a positive name query response.
+const RESPONSE_LEN: usize = 62;
+
+/// A responder for NetBIOS name queries about a single name.
+#[derive(Debug)]
+pub struct Responder {
+    name: NbnsName,
+    ttl: Duration,
+}
+
+impl Responder {
+    /// Create a responder for `name`.
+    ///
+    /// Queries for the workstation and file server services of the name are answered.
+    /// Returns `Err(Error)` if `name` is not a valid NetBIOS name.
+    pub fn new(name: &str) -> Result<Responder> {
+        Ok(Responder {
+            name: NbnsName::new(name, NbnsName::SUFFIX_WORKSTATION)?,
+            ttl: DEFAULT_TTL,
+        })
+    }
+
+    /// Return the name queries are answered for, without padding or suffix.
+    pub fn name(&self) -> &[u8] {
+        self.name.name()
+    }
+
+    /// Return the TTL of answers.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Set the TTL of answers.
+    ///
+    /// The default is 300 seconds.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        self.ttl = ttl
+    }
+
+    /// Answer the queries received on `socket`, using the addresses in `addrs`.
+    pub fn poll(&self, socket: &mut udp::Socket, addrs: &[IpCidr]) {
+        while let Ok((payload, endpoint)) = socket.recv() {
+            let mut response = [0; RESPONSE_LEN];
+            if !self.respond(payload, addrs, &mut response) {
+                continue;
+            }
+            if socket.send_slice(&response, endpoint).is_err() {
+                net_debug!("nbns: cannot send response to {}", endpoint);
+            }
+        }
+    }
+
+    /// Emit the response to `query` into `buffer`, returning whether it must be sent.
+    fn respond(&self, query: &[u8], addrs: &[IpCidr], buffer: &mut [u8]) -> bool {
+        let (transaction_id, name) =
+            match NbnsPacket::new_checked(query).and_then(|packet| NbnsRepr::parse(&packet)) {
+                Ok(NbnsRepr::Query {
+                    transaction_id,
+                    name,
+                    ..
+                }) => (transaction_id, name),
+                _ => return false,
+            };
+        if name.name() != self.name.name()
+            || (name.suffix() != NbnsName::SUFFIX_WORKSTATION
+                && name.suffix() != NbnsName::SUFFIX_SERVER)
+        {
+            return false;
+        }
+        net_trace!("nbns: query for {}", name);
+
+        let address = addrs.iter().find_map(|cidr| match cidr.address() {
+            IpAddress::Ipv4(address) => Some(address),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        });
+        let address = match address {
+            Some(address) => address,
+            None => return false,
+        };
+
+        let response = NbnsRepr::Response {
+            transaction_id,
+            name,
+            ttl: self.ttl.secs() as u32,
+            group: false,
+            address,
+        };
+        response.emit(&mut NbnsPacket::new_unchecked(&mut buffer[..]));
+        true
+    }
+}
+
+#[cfg(all(test, feature = "medium-ip"))]
+mod test {
+    use super::*;
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-sixlowpan-fragmentation"
+    ))]
+    use crate::iface::FragmentsCache;
+    use crate::iface::{Interface, InterfaceBuilder};
+    use crate::phy::{Loopback, Medium};
+    use crate::socket::udp::{PacketBuffer, PacketMetadata};
+    use crate::time::Instant;
+    use crate::wire::{IpEndpoint, Ipv4Address, NBNS_UDP_PORT};
+    use std::vec;
+
+    const LOCALHOST: Ipv4Address = Ipv4Address([127, 0, 0, 1]);
+
+    fn udp_socket(port: u16) -> udp::Socket<'static> {
+        let rx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let tx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 1024]);
+        let mut socket = udp::Socket::new(rx, tx);
+        socket.bind(port).unwrap();
+        socket
+    }
+
+    fn interface(device: &mut Loopback) -> Interface<'static> {
+        let iface_builder =
+            InterfaceBuilder::new().ip_addrs(vec![IpCidr::new(LOCALHOST.into(), 8)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder = iface_builder.ipv4_fragments_cache(FragmentsCache::new(
+            vec![],
+            std::collections::BTreeMap::new(),
+        ));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(FragmentsCache::new(
+                vec![],
+                std::collections::BTreeMap::new(),
+            ))
+            .sixlowpan_out_packet_cache(vec![]);
+        iface_builder.try_finalize(device).unwrap()
+    }
+
+    /// Send a query for `name` to the responder, and return the parsed response, if any.
+    fn query(name: NbnsName) -> Option<NbnsRepr> {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut iface = interface(&mut device);
+        let mut sockets = crate::iface::SocketSet::new(vec![]);
+        let client_handle = sockets.add(udp_socket(49152));
+        let responder_handle = sockets.add(udp_socket(NBNS_UDP_PORT));
+        let responder = Responder::new("device").unwrap();
+
+        let repr = NbnsRepr::Query {
+            transaction_id: 0x1234,
+            broadcast: true,
+            recursion_desired: true,
+            name,
+        };
+        let mut bytes = vec![0; repr.buffer_len()];
+        repr.emit(&mut NbnsPacket::new_unchecked(&mut bytes[..]));
+        sockets
+            .get_mut::<udp::Socket>(client_handle)
+            .send_slice(&bytes, IpEndpoint::new(LOCALHOST.into(), NBNS_UDP_PORT))
+            .unwrap();
+
+        for _ in 0..3 {
+            iface
+                .poll(Instant::ZERO, &mut device, &mut sockets)
+                .unwrap();
+            responder.poll(
+                sockets.get_mut::<udp::Socket>(responder_handle),
+                iface.ip_addrs(),
+            );
+        }
+
+        let client = sockets.get_mut::<udp::Socket>(client_handle);
+        let (payload, _) = client.recv().ok()?;
+        Some(NbnsRepr::parse(&NbnsPacket::new_checked(payload).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_answer() {
+        let name = NbnsName::new("DEVICE", NbnsName::SUFFIX_SERVER).unwrap();
+        assert_eq!(
+            query(name),
+            Some(NbnsRepr::Response {
+                transaction_id: 0x1234,
+                name,
+                ttl: 300,
+                group: false,
+                address: LOCALHOST,
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_name() {
+        assert_eq!(query(NbnsName::new("PRINTER", 0x00).unwrap()), None);
+        assert_eq!(query(NbnsName::new("DEVICE", 0x1c).unwrap()), None);
+    }
+}
@@ -0,0 +1,560 @@
+/*! TFTP client and server transfers.
+
+A [Transfer] implements the TFTP (RFC 1350) block-transfer state machine for a single
+file on top of a [udp::Socket]. File data is read from and written to a [Handle]
+provided by the application.
+
+A client creates a transfer with [Transfer::read] or [Transfer::write] using a socket
+bound to any free local port. A server listens for requests on a socket bound to
+[TFTP_SERVER_PORT], and creates a transfer with [Transfer::accept] for each request,
+using a separate socket bound to a fresh local port, which serves as the transfer ID.
+
+In both cases, [Transfer::poll] must be called after every call to `Interface::poll()`,
+until it returns something other than [Status::InProgress].
+
+The data of `netascii` transfers is passed through unmodified.
+
+[TFTP_SERVER_PORT]: crate::wire::TFTP_SERVER_PORT
+*/
+
+use crate::socket::udp;
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    IpEndpoint, TftpErrorCode as ErrorCode, TftpMode as Mode, TftpPacket, TftpRepr, TFTP_BLOCK_SIZE,
+};
+
+const RETRANSMIT_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_RETRIES: u8 = 5;
+
+/// The maximum size of a TFTP packet: a DATA packet carrying a full block.
+const MAX_PACKET_LEN: usize = 4 + TFTP_BLOCK_SIZE;
+
+/// Storage for the file being transferred.
+pub trait Handle {
+    /// Fill `buf` with the next part of the file, and return the number of octets written.
+    ///
+    /// Returning fewer than `buf.len()` octets ends the transfer.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorCode>;
+
+    /// Append `data` to the file.
+    fn write(&mut self, data: &[u8]) -> Result<(), ErrorCode>;
+}
+
+/// Error that ended a transfer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The peer did not respond after several retransmissions.
+    Timeout,
+    /// The peer sent an ERROR packet.
+    Peer(ErrorCode),
+    /// The [Handle] returned an error, which was sent to the peer.
+    Handle(ErrorCode),
+    /// The peer sent a packet that is not valid at this point in the transfer.
+    Protocol,
+}
+
+/// State of a transfer, as returned by [Transfer::poll].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Status {
+    InProgress,
+    Complete,
+    Failed(Error),
+}
+
+/// A single TFTP file transfer.
+#[derive(Debug)]
+pub struct Transfer {
+    peer: IpEndpoint,
+    /// Whether the port of the peer (its transfer ID) is known. A client only learns it
+    /// from the first response of the server.
+    peer_tid_known: bool,
+    /// Whether the file is being received, rather than sent.
+    receiving: bool,
+    /// Sending: the block in `packet` waiting for an acknowledgement.
+    /// Receiving: the last block received and acknowledged.
+    block_num: u16,
+    /// Sending: whether the block in `packet` is the last one.
+    /// Receiving: whether the last block has been received.
+    last_block: bool,
+    /// Sending: whether the next block must be read from the handle.
+    next_block: bool,
+
+    /// The last packet sent, kept for retransmission.
+    packet: [u8; MAX_PACKET_LEN],
+    packet_len: usize,
+    /// Whether `packet` must be (re)transmitted.
+    transmit: bool,
+    retransmit_at: Instant,
+    retries: u8,
+
+    status: Status,
+}
+
+impl Transfer {
+    fn new(peer: IpEndpoint, peer_tid_known: bool, receiving: bool) -> Transfer {
+        Transfer {
+            peer,
+            peer_tid_known,
+            receiving,
+            block_num: 0,
+            last_block: false,
+            next_block: false,
+            packet: [0; MAX_PACKET_LEN],
+            packet_len: 0,
+            transmit: false,
+            retransmit_at: Instant::ZERO,
+            retries: 0,
+            status: Status::InProgress,
+        }
+    }
+
+    fn new_request(server: IpEndpoint, receiving: bool, request: TftpRepr) -> Transfer {
+        assert!(
+            request.buffer_len() <= MAX_PACKET_LEN,
+            "the TFTP filename is too long"
+        );
+        let mut transfer = Transfer::new(server, false, receiving);
+        transfer.set_packet(&request);
+        transfer
+    }
+
+    /// Start downloading `filename` from the server at `server`, usually on
+    /// [TFTP_SERVER_PORT](crate::wire::TFTP_SERVER_PORT).
+    ///
+    /// # Panics
+    /// This function panics if `filename` is too long to fit into a single packet.
+    pub fn read(server: IpEndpoint, filename: &str, mode: Mode) -> Transfer {
+        Self::new_request(server, true, TftpRepr::ReadRequest { filename, mode })
+    }
+
+    /// Start uploading `filename` to the server at `server`, usually on
+    /// [TFTP_SERVER_PORT](crate::wire::TFTP_SERVER_PORT).
+    ///
+    /// # Panics
+    /// This function panics if `filename` is too long to fit into a single packet.
+    pub fn write(server: IpEndpoint, filename: &str, mode: Mode) -> Transfer {
+        Self::new_request(server, false, TftpRepr::WriteRequest { filename, mode })
+    }
+
+    /// Serve a request received from `client` on the server port.
+    ///
+    /// Returns `None` if `request` is not a read or write request.
+    pub fn accept(request: &TftpRepr, client: IpEndpoint) -> Option<Transfer> {
+        match request {
+            TftpRepr::ReadRequest { .. } => {
+                let mut transfer = Transfer::new(client, true, false);
+                transfer.next_block = true;
+                Some(transfer)
+            }
+            TftpRepr::WriteRequest { .. } => {
+                let mut transfer = Transfer::new(client, true, true);
+                transfer.set_packet(&TftpRepr::Ack { block_num: 0 });
+                Some(transfer)
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the endpoint of the peer.
+    pub fn peer(&self) -> IpEndpoint {
+        self.peer
+    }
+
+    /// Return the state of the transfer.
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Return the time at which the transfer needs to be polled again if nothing
+    /// is received, or `None` if the transfer is over.
+    pub fn poll_at(&self) -> Option<Instant> {
+        match self.status {
+            Status::InProgress if self.transmit || self.next_block => Some(Instant::ZERO),
+            Status::InProgress => Some(self.retransmit_at),
+            _ => None,
+        }
+    }
+
+    /// Process packets received on `socket`, and send the next packet if possible.
+    pub fn poll<H: Handle + ?Sized>(
+        &mut self,
+        socket: &mut udp::Socket,
+        now: Instant,
+        handle: &mut H,
+    ) -> Status {
+        while self.status == Status::InProgress {
+            let (payload, endpoint) = match socket.recv() {
+                Ok((payload, meta)) => (payload, meta.endpoint),
+                Err(_) => break,
+            };
+            if let Some((endpoint, code)) = self.process(endpoint, payload, handle) {
+                Self::send_error(socket, endpoint, code);
+            }
+        }
+
+        if self.status != Status::InProgress {
+            return self.status;
+        }
+
+        if self.next_block {
+            let mut data = [0; TFTP_BLOCK_SIZE];
+            match handle.read(&mut data) {
+                Ok(len) => {
+                    let len = len.min(TFTP_BLOCK_SIZE);
+                    self.block_num = self.block_num.wrapping_add(1);
+                    self.last_block = len < TFTP_BLOCK_SIZE;
+                    self.next_block = false;
+                    self.set_packet(&TftpRepr::Data {
+                        block_num: self.block_num,
+                        data: &data[..len],
+                    });
+                }
+                Err(code) => {
+                    Self::send_error(socket, self.peer, code);
+                    self.status = Status::Failed(Error::Handle(code));
+                    return self.status;
+                }
+            }
+        }
+
+        if !self.transmit && now >= self.retransmit_at {
+            if self.retries >= MAX_RETRIES {
+                net_debug!("tftp: {}: timed out", self.peer);
+                self.status = Status::Failed(Error::Timeout);
+                return self.status;
+            }
+            self.retries += 1;
+            self.transmit = true;
+        }
+
+        if self.transmit
+            && socket
+                .send_slice(&self.packet[..self.packet_len], self.peer)
+                .is_ok()
+        {
+            self.transmit = false;
+            self.retransmit_at = now + RETRANSMIT_TIMEOUT;
+            // The final ACK is not retransmitted; RFC 1350 § 6 leaves it to the sender
+            // of the last block to retransmit it if the ACK is lost.
+            if self.receiving && self.last_block {
+                self.status = Status::Complete;
+            }
+        }
+
+        self.status
+    }
+
+    /// Process a single packet, and return the endpoint and code of an error to send
+    /// in response, if any.
+    fn process<H: Handle + ?Sized>(
+        &mut self,
+        endpoint: IpEndpoint,
+        payload: &[u8],
+        handle: &mut H,
+    ) -> Option<(IpEndpoint, ErrorCode)> {
+        if self.peer_tid_known && endpoint != self.peer {
+            net_debug!("tftp: packet from unknown transfer id {}", endpoint);
+            return Some((endpoint, ErrorCode::UnknownTransferId));
+        }
+        if !self.peer_tid_known && endpoint.addr != self.peer.addr {
+            return None;
+        }
+
+        let repr = match TftpPacket::new_checked(payload).and_then(|p| TftpRepr::parse(&p)) {
+            Ok(repr) => repr,
+            Err(_) => {
+                self.status = Status::Failed(Error::Protocol);
+                return Some((endpoint, ErrorCode::IllegalOperation));
+            }
+        };
+
+        match repr {
+            TftpRepr::Error { code, .. } => {
+                net_debug!("tftp: {}: peer error {}", endpoint, code);
+                self.status = Status::Failed(Error::Peer(code));
+                return None;
+            }
+            TftpRepr::Data { block_num, data } if self.receiving => {
+                if block_num == self.block_num.wrapping_add(1) {
+                    if let Err(code) = handle.write(data) {
+                        self.status = Status::Failed(Error::Handle(code));
+                        return Some((endpoint, code));
+                    }
+                    self.block_num = block_num;
+                    self.last_block = data.len() < TFTP_BLOCK_SIZE;
+                    self.set_packet(&TftpRepr::Ack { block_num });
+                } else if block_num == self.block_num && block_num != 0 {
+                    // Our ACK was lost; send it again.
+                    self.transmit = true;
+                }
+            }
+            TftpRepr::Ack { block_num } if !self.receiving => {
+                // Duplicate ACKs are ignored, to avoid the Sorcerer's Apprentice syndrome.
+                if block_num == self.block_num && !self.next_block {
+                    if self.last_block {
+                        self.status = Status::Complete;
+                    } else {
+                        self.next_block = true;
+                        self.retries = 0;
+                    }
+                }
+            }
+            _ => {
+                self.status = Status::Failed(Error::Protocol);
+                return Some((endpoint, ErrorCode::IllegalOperation));
+            }
+        }
+
+        if !self.peer_tid_known {
+            self.peer = endpoint;
+            self.peer_tid_known = true;
+        }
+        None
+    }
+
+    fn set_packet(&mut self, repr: &TftpRepr) {
+        self.packet_len = repr.buffer_len();
+        repr.emit(&mut TftpPacket::new_unchecked(
+            &mut self.packet[..self.packet_len],
+        ));
+        self.transmit = true;
+        self.retries = 0;
+    }
+
+    fn send_error(socket: &mut udp::Socket, endpoint: IpEndpoint, code: ErrorCode) {
+        let repr = TftpRepr::Error { code, msg: "" };
+        let _ = socket.send_with(repr.buffer_len(), endpoint, |buf| {
+            repr.emit(&mut TftpPacket::new_unchecked(buf));
+            repr.buffer_len()
+        });
+    }
+}
+
+#[cfg(all(test, feature = "medium-ip", feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    #[cfg(any(
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-sixlowpan-fragmentation"
+    ))]
+    use crate::iface::FragmentsCache;
+    use crate::iface::{Interface, InterfaceBuilder};
+    use crate::phy::{Loopback, Medium};
+    use crate::socket::udp::{PacketBuffer, PacketMetadata};
+    use crate::wire::{IpAddress, IpCidr, Ipv4Address, TFTP_SERVER_PORT};
+    use std::vec::Vec;
+
+    const LOCALHOST: IpAddress = IpAddress::Ipv4(Ipv4Address([127, 0, 0, 1]));
+
+    #[derive(Default)]
+    struct File {
+        data: Vec<u8>,
+        offset: usize,
+    }
+
+    impl Handle for File {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorCode> {
+            let len = buf.len().min(self.data.len() - self.offset);
+            buf[..len].copy_from_slice(&self.data[self.offset..][..len]);
+            self.offset += len;
+            Ok(len)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), ErrorCode> {
+            self.data.extend_from_slice(data);
+            Ok(())
+        }
+    }
+
+    struct Refuse;
+
+    impl Handle for Refuse {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize, ErrorCode> {
+            Err(ErrorCode::FileNotFound)
+        }
+
+        fn write(&mut self, _data: &[u8]) -> Result<(), ErrorCode> {
+            Err(ErrorCode::DiskFull)
+        }
+    }
+
+    fn udp_socket(port: u16) -> udp::Socket<'static> {
+        let rx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 4 * 1024]);
+        let tx = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 4 * 1024]);
+        let mut socket = udp::Socket::new(rx, tx);
+        socket.bind(port).unwrap();
+        socket
+    }
+
+    fn interface(device: &mut Loopback) -> Interface<'static> {
+        let iface_builder = InterfaceBuilder::new().ip_addrs(vec![IpCidr::new(LOCALHOST, 8)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder = iface_builder.ipv4_fragments_cache(FragmentsCache::new(
+            vec![],
+            std::collections::BTreeMap::new(),
+        ));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(FragmentsCache::new(
+                vec![],
+                std::collections::BTreeMap::new(),
+            ))
+            .sixlowpan_out_packet_cache(vec![]);
+        iface_builder.try_finalize(device).unwrap()
+    }
+
+    /// Run a client transfer against a server, returning the status of both.
+    fn run<C: Handle, S: Handle>(
+        mut client: Transfer,
+        client_file: &mut C,
+        server_file: &mut S,
+        mut drop_packet: impl FnMut(usize) -> bool,
+    ) -> (Status, Status) {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut iface = interface(&mut device);
+        let mut sockets = crate::iface::SocketSet::new(vec![]);
+        let client_handle = sockets.add(udp_socket(49152));
+        let listen_handle = sockets.add(udp_socket(TFTP_SERVER_PORT));
+        let server_handle = sockets.add(udp_socket(49153));
+
+        let mut server: Option<Transfer> = None;
+        let mut now = Instant::ZERO;
+        let mut packets = 0;
+        for _ in 0..1000 {
+            iface.poll(now, &mut device, &mut sockets).unwrap();
+
+            // Simulate packet loss by discarding received packets.
+            for handle in [client_handle, server_handle].iter() {
+                let socket = sockets.get_mut::<udp::Socket>(*handle);
+                while socket.can_recv() {
+                    packets += 1;
+                    if !drop_packet(packets) {
+                        break;
+                    }
+                    socket.recv().unwrap();
+                }
+            }
+
+            let listen = sockets.get_mut::<udp::Socket>(listen_handle);
+            if let Ok((payload, meta)) = listen.recv() {
+                let endpoint = meta.endpoint;
+                let repr = TftpRepr::parse(&TftpPacket::new_checked(payload).unwrap()).unwrap();
+                if server.is_none() {
+                    server = Transfer::accept(&repr, endpoint);
+                }
+            }
+
+            let client_status = client.poll(
+                sockets.get_mut::<udp::Socket>(client_handle),
+                now,
+                client_file,
+            );
+            let server_status = server.as_mut().map(|server| {
+                server.poll(
+                    sockets.get_mut::<udp::Socket>(server_handle),
+                    now,
+                    server_file,
+                )
+            });
+            if client_status != Status::InProgress && server_status != Some(Status::InProgress) {
+                // Flush any error packets.
+                iface.poll(now, &mut device, &mut sockets).unwrap();
+                return (
+                    client_status,
+                    server.map_or(Status::InProgress, |s| s.status()),
+                );
+            }
+            now += Duration::from_millis(100);
+        }
+        panic!("transfer did not finish");
+    }
+
+    fn test_data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_read() {
+        let server = IpEndpoint::new(LOCALHOST, TFTP_SERVER_PORT);
+        for &len in [0, 100, 512, 1500].iter() {
+            let mut client_file = File::default();
+            let mut server_file = File {
+                data: test_data(len),
+                offset: 0,
+            };
+            let client = Transfer::read(server, "boot.bin", Mode::Octet);
+            let status = run(client, &mut client_file, &mut server_file, |_| false);
+            assert_eq!(status, (Status::Complete, Status::Complete));
+            assert_eq!(client_file.data, server_file.data);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let server = IpEndpoint::new(LOCALHOST, TFTP_SERVER_PORT);
+        let mut client_file = File {
+            data: test_data(2000),
+            offset: 0,
+        };
+        let mut server_file = File::default();
+        let client = Transfer::write(server, "upload.bin", Mode::Octet);
+        let status = run(client, &mut client_file, &mut server_file, |_| false);
+        assert_eq!(status.0, Status::Complete);
+        assert_eq!(client_file.data, server_file.data);
+    }
+
+    #[test]
+    fn test_read_lossy() {
+        let server = IpEndpoint::new(LOCALHOST, TFTP_SERVER_PORT);
+        let mut client_file = File::default();
+        let mut server_file = File {
+            data: test_data(3000),
+            offset: 0,
+        };
+        let client = Transfer::read(server, "boot.bin", Mode::Octet);
+        let status = run(client, &mut client_file, &mut server_file, |n| n % 3 == 0);
+        assert_eq!(status.0, Status::Complete);
+        assert_eq!(client_file.data, server_file.data);
+    }
+
+    #[test]
+    fn test_read_refused() {
+        let server = IpEndpoint::new(LOCALHOST, TFTP_SERVER_PORT);
+        let mut client_file = File::default();
+        let client = Transfer::read(server, "missing.bin", Mode::Octet);
+        let status = run(client, &mut client_file, &mut Refuse, |_| false);
+        assert_eq!(
+            status,
+            (
+                Status::Failed(Error::Peer(ErrorCode::FileNotFound)),
+                Status::Failed(Error::Handle(ErrorCode::FileNotFound))
+            )
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let mut device = Loopback::new(Medium::Ip);
+        let mut iface = interface(&mut device);
+        let mut sockets = crate::iface::SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket(49152));
+
+        // Nobody listens on the server port.
+        let server = IpEndpoint::new(LOCALHOST, 6969);
+        let mut client = Transfer::read(server, "boot.bin", Mode::Octet);
+        let mut file = File::default();
+        let mut now = Instant::ZERO;
+        let status = loop {
+            let _ = iface.poll(now, &mut device, &mut sockets);
+            let status = client.poll(sockets.get_mut::<udp::Socket>(handle), now, &mut file);
+            if status != Status::InProgress {
+                break status;
+            }
+            now += Duration::from_millis(500);
+        };
+        assert_eq!(status, Status::Failed(Error::Timeout));
+        assert_eq!(now, Instant::from_secs(6));
+    }
+}
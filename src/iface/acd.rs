@@ -0,0 +1,569 @@
+//! IPv4 Address Conflict Detection: probing, announcing and defending addresses as
+//! described in RFC 5227 § 2.
+
+use managed::ManagedMap;
+
+use crate::rand::Rand;
+use crate::time::{Duration, Instant};
+use crate::wire::Ipv4Address;
+
+/// Configuration of IPv4 Address Conflict Detection.
+///
+/// The defaults are the protocol constants of RFC 5227 § 1.1.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Maximum random delay before the first probe, `PROBE_WAIT`.
+    pub probe_wait: Duration,
+    /// Number of probes sent for each address, `PROBE_NUM`.
+    pub probe_num: u8,
+    /// Minimum delay between probes, `PROBE_MIN`.
+    pub probe_min: Duration,
+    /// Maximum delay between probes, `PROBE_MAX`.
+    pub probe_max: Duration,
+    /// Delay after the last probe until the address is claimed, `ANNOUNCE_WAIT`.
+    pub announce_wait: Duration,
+    /// Number of announcements sent for each address, `ANNOUNCE_NUM`.
+    pub announce_num: u8,
+    /// Time between announcements, `ANNOUNCE_INTERVAL`.
+    pub announce_interval: Duration,
+    /// Number of conflicts after which probing is rate limited, `MAX_CONFLICTS`.
+    pub max_conflicts: u8,
+    /// Delay between probing attempts once rate limited, `RATE_LIMIT_INTERVAL`.
+    pub rate_limit_interval: Duration,
+    /// Minimum time between defensive announcements; a second conflict within this
+    /// time makes the address unusable, `DEFEND_INTERVAL`.
+    pub defend_interval: Duration,
+}
+
+impl Config {
+    pub const DEFAULT_PROBE_WAIT: Duration = Duration::from_secs(1);
+    pub const DEFAULT_PROBE_NUM: u8 = 3;
+    pub const DEFAULT_PROBE_MIN: Duration = Duration::from_secs(1);
+    pub const DEFAULT_PROBE_MAX: Duration = Duration::from_secs(2);
+    pub const DEFAULT_ANNOUNCE_WAIT: Duration = Duration::from_secs(2);
+    pub const DEFAULT_ANNOUNCE_NUM: u8 = 2;
+    pub const DEFAULT_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(2);
+    pub const DEFAULT_MAX_CONFLICTS: u8 = 10;
+    pub const DEFAULT_RATE_LIMIT_INTERVAL: Duration = Duration::from_secs(60);
+    pub const DEFAULT_DEFEND_INTERVAL: Duration = Duration::from_secs(10);
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            probe_wait: Self::DEFAULT_PROBE_WAIT,
+            probe_num: Self::DEFAULT_PROBE_NUM,
+            probe_min: Self::DEFAULT_PROBE_MIN,
+            probe_max: Self::DEFAULT_PROBE_MAX,
+            announce_wait: Self::DEFAULT_ANNOUNCE_WAIT,
+            announce_num: Self::DEFAULT_ANNOUNCE_NUM,
+            announce_interval: Self::DEFAULT_ANNOUNCE_INTERVAL,
+            max_conflicts: Self::DEFAULT_MAX_CONFLICTS,
+            rate_limit_interval: Self::DEFAULT_RATE_LIMIT_INTERVAL,
+            defend_interval: Self::DEFAULT_DEFEND_INTERVAL,
+        }
+    }
+}
+
+/// State of an IPv4 address with respect to Address Conflict Detection.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressState {
+    /// The address is being probed. It is not used, and packets sent to it are discarded.
+    Probing,
+    /// No other node answered the probes, and the address is being announced.
+    Announcing,
+    /// The address was announced, and is defended against conflicting ARP packets.
+    Bound,
+    /// The address is used by another node on the link, and is not used. The address
+    /// should be removed from the interface.
+    Conflict,
+}
+
+impl AddressState {
+    /// Query whether the address can be used as the source or destination of packets.
+    pub fn is_usable(&self) -> bool {
+        matches!(self, AddressState::Announcing | AddressState::Bound)
+    }
+
+    /// Query whether the address is being probed.
+    pub fn is_probing(&self) -> bool {
+        *self == AddressState::Probing
+    }
+
+    fn has_timer(&self) -> bool {
+        matches!(self, AddressState::Probing | AddressState::Announcing)
+    }
+}
+
+/// Address Conflict Detection progress of an IPv4 address.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Entry {
+    state: AddressState,
+    /// Number of probes or announcements sent in the current state.
+    sent: u8,
+    next_event: Instant,
+    last_defense: Option<Instant>,
+    /// Whether the address is tracked before being added to the interface.
+    pinned: bool,
+}
+
+impl Entry {
+    fn new(config: &Config, rate_limited: bool, timestamp: Instant, rand: &mut Rand) -> Self {
+        let (state, delay) = if config.probe_num == 0 {
+            (AddressState::Announcing, Duration::ZERO)
+        } else if rate_limited {
+            // RFC 5227 § 2.1.1: limit the rate of probing attempts after repeated conflicts.
+            (AddressState::Probing, config.rate_limit_interval)
+        } else {
+            (
+                AddressState::Probing,
+                random_delay(rand, Duration::ZERO, config.probe_wait),
+            )
+        };
+        Entry {
+            state,
+            sent: 0,
+            next_event: timestamp + delay,
+            last_defense: None,
+            pinned: false,
+        }
+    }
+
+    /// Return the state of the address.
+    pub fn state(&self) -> AddressState {
+        self.state
+    }
+}
+
+/// What to do for an address whose Address Conflict Detection timer expired.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    /// Send an ARP probe for the address.
+    Probe(Ipv4Address),
+    /// Send an ARP announcement for the address.
+    Announce(Ipv4Address),
+    /// Nothing to send.
+    None,
+}
+
+/// Address Conflict Detection state of an interface.
+#[derive(Debug)]
+pub(crate) struct Acd<'a> {
+    config: Config,
+    entries: ManagedMap<'a, Ipv4Address, Entry>,
+    /// Number of conflicts since an address was last claimed successfully.
+    conflicts: u8,
+}
+
+impl<'a> Acd<'a> {
+    pub(crate) fn new(config: Config, entries: ManagedMap<'a, Ipv4Address, Entry>) -> Self {
+        Acd {
+            config,
+            entries,
+            conflicts: 0,
+        }
+    }
+
+    /// Return the state of `addr`, or `None` if it is not tracked.
+    pub(crate) fn state(&self, addr: &Ipv4Address) -> Option<AddressState> {
+        self.entries.get(addr).map(Entry::state)
+    }
+
+    fn rate_limited(&self) -> bool {
+        self.conflicts >= self.config.max_conflicts
+    }
+
+    /// Start probing the addresses in `addrs` that are not tracked yet, and forget the
+    /// tracked addresses that are not in `addrs` anymore unless they are pinned.
+    pub(crate) fn sync<I>(&mut self, addrs: I, timestamp: Instant, rand: &mut Rand)
+    where
+        I: Iterator<Item = Ipv4Address> + Clone,
+    {
+        while let Some(removed) = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !entry.pinned)
+            .map(|(addr, _)| *addr)
+            .find(|addr| !addrs.clone().any(|probe| probe == *addr))
+        {
+            self.entries.remove(&removed);
+        }
+
+        for addr in addrs {
+            if !addr.is_unicast() || addr.is_loopback() {
+                continue;
+            }
+            match self.entries.get(&addr) {
+                Some(entry) => {
+                    let entry = Entry {
+                        pinned: false,
+                        ..*entry
+                    };
+                    let _ = self.entries.insert(addr, entry);
+                }
+                None => {
+                    let entry = Entry::new(&self.config, self.rate_limited(), timestamp, rand);
+                    if self.entries.insert(addr, entry).is_err() {
+                        net_debug!("acd: no space to track {}", addr);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Start probing `addr` before it is added to the interface, and keep tracking it
+    /// until it is added or released.
+    ///
+    /// Returns whether the address is tracked.
+    pub(crate) fn probe(&mut self, addr: Ipv4Address, timestamp: Instant, rand: &mut Rand) -> bool {
+        let entry = match self.entries.get(&addr) {
+            Some(entry) => *entry,
+            None => Entry::new(&self.config, self.rate_limited(), timestamp, rand),
+        };
+        let entry = Entry {
+            pinned: true,
+            ..entry
+        };
+        if self.entries.insert(addr, entry).is_err() {
+            net_debug!("acd: no space to track {}", addr);
+            return false;
+        }
+        true
+    }
+
+    /// Stop tracking `addr` if it was pinned by [`probe`](Self::probe).
+    pub(crate) fn release(&mut self, addr: &Ipv4Address) {
+        if matches!(self.entries.get(addr), Some(entry) if entry.pinned) {
+            self.entries.remove(addr);
+        }
+    }
+
+    /// Probe all the tracked addresses again, except those found to be in conflict;
+    /// e.g. after the link came back up.
+    pub(crate) fn restart(&mut self, timestamp: Instant, rand: &mut Rand) {
+        let config = self.config;
+        let rate_limited = self.rate_limited();
+        for (_, entry) in self.entries.iter_mut() {
+            if entry.state != AddressState::Conflict {
+                *entry = Entry {
+                    pinned: entry.pinned,
+                    ..Entry::new(&config, rate_limited, timestamp, rand)
+                };
+            }
+        }
+    }
+
+    /// Return the earliest time at which an action is required.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.state.has_timer())
+            .map(|(_, entry)| entry.next_event)
+            .min()
+    }
+
+    /// Advance the timer of the first address for which an action is due at `timestamp`,
+    /// or return `None` if there is none.
+    pub(crate) fn next_action(&mut self, timestamp: Instant, rand: &mut Rand) -> Option<Action> {
+        let config = self.config;
+        let (addr, entry) = self
+            .entries
+            .iter()
+            .find(|(_, entry)| entry.state.has_timer() && entry.next_event <= timestamp)
+            .map(|(addr, entry)| (*addr, *entry))?;
+
+        let (entry, action) = match entry.state {
+            AddressState::Probing if entry.sent < config.probe_num => {
+                let sent = entry.sent + 1;
+                let delay = if sent < config.probe_num {
+                    random_delay(rand, config.probe_min, config.probe_max)
+                } else {
+                    config.announce_wait
+                };
+                let entry = Entry {
+                    sent,
+                    next_event: timestamp + delay,
+                    ..entry
+                };
+                (entry, Action::Probe(addr))
+            }
+            AddressState::Probing => {
+                net_debug!("acd: claiming {}", addr);
+                self.conflicts = 0;
+                let entry = Entry {
+                    state: AddressState::Announcing,
+                    sent: 0,
+                    ..entry
+                };
+                (entry, Action::None)
+            }
+            _ if entry.sent < config.announce_num => {
+                let entry = Entry {
+                    sent: entry.sent + 1,
+                    next_event: timestamp + config.announce_interval,
+                    ..entry
+                };
+                (entry, Action::Announce(addr))
+            }
+            _ => {
+                let entry = Entry {
+                    state: AddressState::Bound,
+                    ..entry
+                };
+                (entry, Action::None)
+            }
+        };
+        // Cannot fail, the address is already present.
+        let _ = self.entries.insert(addr, entry);
+        Some(action)
+    }
+
+    /// Process an ARP packet sent by another node, with the sender and target protocol
+    /// addresses `source_addr` and `target_addr`.
+    ///
+    /// Returns the address to defend with an announcement, if any.
+    pub(crate) fn process_arp(
+        &mut self,
+        source_addr: Ipv4Address,
+        target_addr: Ipv4Address,
+        timestamp: Instant,
+    ) -> Option<Ipv4Address> {
+        if let Some(entry) = self.entries.get(&source_addr).copied() {
+            match entry.state {
+                AddressState::Probing => self.conflict(source_addr, entry),
+                AddressState::Announcing | AddressState::Bound => match entry.last_defense {
+                    Some(last_defense)
+                        if timestamp < last_defense + self.config.defend_interval =>
+                    {
+                        self.conflict(source_addr, entry)
+                    }
+                    _ => {
+                        net_debug!("acd: defending {}", source_addr);
+                        let entry = Entry {
+                            last_defense: Some(timestamp),
+                            ..entry
+                        };
+                        let _ = self.entries.insert(source_addr, entry);
+                        return Some(source_addr);
+                    }
+                },
+                AddressState::Conflict => (),
+            }
+        } else if source_addr.is_unspecified() {
+            // Another node probing the address at the same time.
+            match self.entries.get(&target_addr).copied() {
+                Some(entry) if entry.state.is_probing() => self.conflict(target_addr, entry),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn conflict(&mut self, addr: Ipv4Address, entry: Entry) {
+        net_debug!("acd: {} is used by another node", addr);
+        self.conflicts = self.conflicts.saturating_add(1);
+        let entry = Entry {
+            state: AddressState::Conflict,
+            ..entry
+        };
+        let _ = self.entries.insert(addr, entry);
+    }
+
+    /// Return the addresses found to be used by another node.
+    pub(crate) fn conflicts(&self) -> impl Iterator<Item = Ipv4Address> + '_ {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.state == AddressState::Conflict)
+            .map(|(addr, _)| *addr)
+    }
+}
+
+/// Return a random duration between `min` and `max`.
+fn random_delay(rand: &mut Rand, min: Duration, max: Duration) -> Duration {
+    let range = (max - min).total_millis();
+    if range == 0 {
+        return min;
+    }
+    min + Duration::from_millis(rand.rand_u32() as u64 % (range + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const ADDR: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
+    const OTHER_ADDR: Ipv4Address = Ipv4Address([192, 168, 1, 2]);
+
+    fn acd(config: Config, rand: &mut Rand) -> Acd<'static> {
+        let mut acd = Acd::new(config, ManagedMap::Owned(BTreeMap::new()));
+        acd.sync(
+            [ADDR, Ipv4Address::new(127, 0, 0, 1)].iter().cloned(),
+            Instant::ZERO,
+            rand,
+        );
+        acd
+    }
+
+    /// A configuration without random delays.
+    fn fixed_config() -> Config {
+        Config {
+            probe_wait: Duration::ZERO,
+            probe_max: Config::DEFAULT_PROBE_MIN,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(fixed_config(), &mut rand);
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Probing));
+        assert_eq!(acd.state(&Ipv4Address::new(127, 0, 0, 1)), None);
+        assert_eq!(acd.poll_at(), Some(Instant::ZERO));
+
+        for secs in 0..3 {
+            let now = Instant::from_secs(secs);
+            assert_eq!(acd.next_action(now, &mut rand), Some(Action::Probe(ADDR)));
+            assert_eq!(acd.next_action(now, &mut rand), None);
+        }
+        assert_eq!(acd.poll_at(), Some(Instant::from_secs(4)));
+        assert_eq!(
+            acd.next_action(Instant::from_secs(4), &mut rand),
+            Some(Action::None)
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Announcing));
+        assert_eq!(
+            acd.next_action(Instant::from_secs(4), &mut rand),
+            Some(Action::Announce(ADDR))
+        );
+        assert_eq!(
+            acd.next_action(Instant::from_secs(6), &mut rand),
+            Some(Action::Announce(ADDR))
+        );
+        assert_eq!(
+            acd.next_action(Instant::from_secs(8), &mut rand),
+            Some(Action::None)
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Bound));
+        assert_eq!(acd.poll_at(), None);
+    }
+
+    #[test]
+    fn test_random_delays() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(Config::default(), &mut rand);
+        let first = acd.poll_at().unwrap();
+        assert!(first <= Instant::ZERO + Config::DEFAULT_PROBE_WAIT);
+        assert_eq!(acd.next_action(first, &mut rand), Some(Action::Probe(ADDR)));
+        let second = acd.poll_at().unwrap();
+        assert!(second >= first + Config::DEFAULT_PROBE_MIN);
+        assert!(second <= first + Config::DEFAULT_PROBE_MAX);
+    }
+
+    #[test]
+    fn test_conflict_while_probing() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(fixed_config(), &mut rand);
+        assert_eq!(
+            acd.next_action(Instant::ZERO, &mut rand),
+            Some(Action::Probe(ADDR))
+        );
+        // A probe for another address is not a conflict.
+        assert_eq!(
+            acd.process_arp(Ipv4Address::UNSPECIFIED, OTHER_ADDR, Instant::ZERO),
+            None
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Probing));
+        // Another node probing the same address is.
+        assert_eq!(
+            acd.process_arp(Ipv4Address::UNSPECIFIED, ADDR, Instant::ZERO),
+            None
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Conflict));
+        assert_eq!(acd.conflicts().collect::<std::vec::Vec<_>>(), [ADDR]);
+        assert_eq!(acd.poll_at(), None);
+
+        // Removing the address forgets it, adding it again restarts probing.
+        acd.sync([].iter().cloned(), Instant::from_secs(5), &mut rand);
+        assert_eq!(acd.state(&ADDR), None);
+        acd.sync([ADDR].iter().cloned(), Instant::from_secs(5), &mut rand);
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Probing));
+    }
+
+    #[test]
+    fn test_defend() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(
+            Config {
+                probe_num: 0,
+                ..fixed_config()
+            },
+            &mut rand,
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Announcing));
+
+        // The first conflict is defended, a second one within the defend interval is not.
+        assert_eq!(
+            acd.process_arp(ADDR, ADDR, Instant::from_secs(1)),
+            Some(ADDR)
+        );
+        assert_eq!(
+            acd.process_arp(ADDR, OTHER_ADDR, Instant::from_secs(12)),
+            Some(ADDR)
+        );
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Announcing));
+        assert_eq!(acd.process_arp(ADDR, ADDR, Instant::from_secs(15)), None);
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Conflict));
+
+        // Restarting does not probe addresses in conflict again.
+        acd.restart(Instant::from_secs(20), &mut rand);
+        assert_eq!(acd.state(&ADDR), Some(AddressState::Conflict));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(
+            Config {
+                max_conflicts: 2,
+                ..fixed_config()
+            },
+            &mut rand,
+        );
+        for _ in 0..2 {
+            acd.process_arp(ADDR, OTHER_ADDR, Instant::ZERO);
+            acd.sync([].iter().cloned(), Instant::ZERO, &mut rand);
+            acd.sync([ADDR].iter().cloned(), Instant::ZERO, &mut rand);
+        }
+        assert_eq!(
+            acd.poll_at(),
+            Some(Instant::ZERO + Config::DEFAULT_RATE_LIMIT_INTERVAL)
+        );
+    }
+
+    #[test]
+    fn test_pinned() {
+        let mut rand = Rand::new(1234);
+        let mut acd = acd(fixed_config(), &mut rand);
+        assert!(acd.probe(OTHER_ADDR, Instant::ZERO, &mut rand));
+        assert_eq!(acd.state(&OTHER_ADDR), Some(AddressState::Probing));
+
+        // Pinned addresses are kept until they are added to the interface.
+        acd.sync([ADDR].iter().cloned(), Instant::ZERO, &mut rand);
+        assert_eq!(acd.state(&OTHER_ADDR), Some(AddressState::Probing));
+        acd.sync([ADDR, OTHER_ADDR].iter().cloned(), Instant::ZERO, &mut rand);
+        acd.release(&OTHER_ADDR);
+        assert_eq!(acd.state(&OTHER_ADDR), Some(AddressState::Probing));
+        acd.sync([ADDR].iter().cloned(), Instant::ZERO, &mut rand);
+        assert_eq!(acd.state(&OTHER_ADDR), None);
+
+        // Releasing a pinned address forgets it.
+        assert!(acd.probe(OTHER_ADDR, Instant::ZERO, &mut rand));
+        acd.release(&OTHER_ADDR);
+        assert_eq!(acd.state(&OTHER_ADDR), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Link-local IPv4 address autoconfiguration: address selection, defense and delivery
+//! as described in RFC 3927 § 2.
+
+use crate::rand::Rand;
+use crate::time::{Duration, Instant};
+use crate::wire::{Ipv4Address, Ipv4Cidr};
+
+/// Length of the prefix of link-local addresses, per RFC 3927 § 2.1.
+const PREFIX_LEN: u8 = 16;
+
+/// First address link-local addresses are selected from, 169.254.1.0, per RFC 3927 § 2.1.
+const FIRST_ADDR: u32 = 0xa9fe_0100;
+
+/// Number of addresses link-local addresses are selected from, up to 169.254.254.255.
+const ADDR_COUNT: u32 = 254 * 256;
+
+/// Configuration of link-local IPv4 address autoconfiguration.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Time the interface stays without a routable IPv4 address, e.g. while a DHCP client
+    /// is unable to obtain a lease, before a link-local address is configured.
+    pub fallback_delay: Duration,
+}
+
+impl Config {
+    /// Default time without a routable address before falling back to a link-local one.
+    pub const DEFAULT_FALLBACK_DELAY: Duration = Duration::from_secs(10);
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            fallback_delay: Self::DEFAULT_FALLBACK_DELAY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// The interface has a routable address, or was not polled yet.
+    Idle,
+    /// The interface has no routable address, a link-local address is configured at the
+    /// given time.
+    Waiting(Instant),
+    /// A link-local address is configured.
+    Configured(Ipv4Address),
+}
+
+/// Change to make to the addresses of the interface.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    /// Add a link-local address.
+    Add(Ipv4Cidr),
+    /// Remove the link-local address.
+    Remove(Ipv4Address),
+}
+
+/// Link-local IPv4 address autoconfiguration state of an interface.
+#[derive(Debug)]
+pub(crate) struct AutoIp {
+    config: Config,
+    state: State,
+    /// Generator of candidate addresses, seeded with the hardware address so that a host
+    /// picks the same sequence of addresses each time, and other hosts a different one.
+    rand: Rand,
+}
+
+impl AutoIp {
+    pub(crate) fn new(config: Config, hardware_addr: &[u8]) -> Self {
+        let seed = hardware_addr
+            .iter()
+            .fold(0u64, |seed, byte| seed << 8 | *byte as u64);
+        AutoIp {
+            config,
+            state: State::Idle,
+            rand: Rand::new(seed),
+        }
+    }
+
+    /// Return the configured link-local address, if any.
+    pub(crate) fn address(&self) -> Option<Ipv4Address> {
+        match self.state {
+            State::Configured(addr) => Some(addr),
+            _ => None,
+        }
+    }
+
+    /// Return the time at which a link-local address is configured, if one is due.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        match self.state {
+            State::Waiting(at) => Some(at),
+            _ => None,
+        }
+    }
+
+    /// Advance the state at `timestamp`, given whether the interface has a routable
+    /// address and whether the link-local address was found to be used by another node.
+    ///
+    /// Returns the change to make to the addresses of the interface, if any.
+    pub(crate) fn update(
+        &mut self,
+        has_routable: bool,
+        conflict: bool,
+        timestamp: Instant,
+    ) -> Option<Action> {
+        match self.state {
+            State::Configured(addr) if has_routable => {
+                // RFC 3927 § 1.9: the link-local address is not needed anymore.
+                net_debug!("autoip: routable address configured, removing {}", addr);
+                self.state = State::Idle;
+                Some(Action::Remove(addr))
+            }
+            _ if has_routable => {
+                self.state = State::Idle;
+                None
+            }
+            State::Idle => {
+                self.state = State::Waiting(timestamp + self.config.fallback_delay);
+                None
+            }
+            State::Waiting(at) if at <= timestamp => {
+                let addr = self.select();
+                net_debug!("autoip: configuring {}", addr);
+                self.state = State::Configured(addr);
+                Some(Action::Add(Ipv4Cidr::new(addr, PREFIX_LEN)))
+            }
+            State::Configured(addr) if conflict => {
+                // RFC 3927 § 2.2.1: select a new address. Address Conflict Detection
+                // limits the rate of attempts after repeated conflicts.
+                self.state = State::Waiting(timestamp);
+                Some(Action::Remove(addr))
+            }
+            State::Waiting(_) | State::Configured(_) => None,
+        }
+    }
+
+    /// Select a pseudo-random link-local address, per RFC 3927 § 2.1.
+    fn select(&mut self) -> Ipv4Address {
+        let offset = self.rand.rand_u32() % ADDR_COUNT;
+        Ipv4Address::from_bytes(&(FIRST_ADDR + offset).to_be_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HARDWARE_ADDR: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+
+    fn autoip() -> AutoIp {
+        AutoIp::new(Config::default(), &HARDWARE_ADDR)
+    }
+
+    #[test]
+    fn test_fallback() {
+        let mut autoip = autoip();
+        assert_eq!(autoip.poll_at(), None);
+        assert_eq!(autoip.update(false, false, Instant::ZERO), None);
+        assert_eq!(autoip.poll_at(), Some(Instant::from_secs(10)));
+        assert_eq!(autoip.update(false, false, Instant::from_secs(5)), None);
+
+        let cidr = match autoip.update(false, false, Instant::from_secs(10)) {
+            Some(Action::Add(cidr)) => cidr,
+            action => panic!("unexpected {:?}", action),
+        };
+        assert_eq!(cidr.prefix_len(), 16);
+        let bytes = cidr.address().0;
+        assert_eq!(bytes[..2], [169, 254]);
+        assert!((1..=254).contains(&bytes[2]));
+        assert_eq!(autoip.address(), Some(cidr.address()));
+        assert_eq!(autoip.poll_at(), None);
+        assert_eq!(autoip.update(false, false, Instant::from_secs(11)), None);
+
+        // The same hardware address selects the same address.
+        let mut other = self::autoip();
+        other.update(false, false, Instant::ZERO);
+        assert_eq!(
+            other.update(false, false, Instant::from_secs(10)),
+            Some(Action::Add(cidr))
+        );
+
+        // A routable address replaces the link-local address.
+        assert_eq!(
+            autoip.update(true, false, Instant::from_secs(12)),
+            Some(Action::Remove(cidr.address()))
+        );
+        assert_eq!(autoip.address(), None);
+        assert_eq!(autoip.update(false, false, Instant::from_secs(20)), None);
+        assert_eq!(autoip.poll_at(), Some(Instant::from_secs(30)));
+    }
+
+    #[test]
+    fn test_routable() {
+        let mut autoip = autoip();
+        assert_eq!(autoip.update(true, false, Instant::from_secs(10)), None);
+        assert_eq!(autoip.poll_at(), None);
+        assert_eq!(autoip.address(), None);
+    }
+
+    #[test]
+    fn test_conflict() {
+        let mut autoip = autoip();
+        autoip.update(false, false, Instant::ZERO);
+        let first = match autoip.update(false, false, Instant::from_secs(10)) {
+            Some(Action::Add(cidr)) => cidr,
+            action => panic!("unexpected {:?}", action),
+        };
+        assert_eq!(
+            autoip.update(false, true, Instant::from_secs(11)),
+            Some(Action::Remove(first.address()))
+        );
+        match autoip.update(false, false, Instant::from_secs(11)) {
+            Some(Action::Add(cidr)) => assert_ne!(cidr, first),
+            action => panic!("unexpected {:?}", action),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! IPv6 Duplicate Address Detection (RFC 4862 § 5.4), with the optimistic mode of
+//! RFC 4429 § 3.
+
+use managed::ManagedMap;
+
+use crate::time::{Duration, Instant};
+use crate::wire::Ipv6Address;
+
+/// Configuration of Duplicate Address Detection.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Number of Neighbor Solicitations sent for each address, `DupAddrDetectTransmits`
+    /// in RFC 4862.
+    pub transmits: u8,
+    /// Time between Neighbor Solicitations, and after the last one until the address
+    /// is considered unique.
+    pub retrans_time: Duration,
+    /// Whether addresses can be used while their uniqueness is being verified,
+    /// as specified in RFC 4429.
+    pub optimistic: bool,
+}
+
+impl Config {
+    /// Default number of Neighbor Solicitations, per RFC 4862 § 5.1.
+    pub const DEFAULT_TRANSMITS: u8 = 1;
+
+    /// Default time between Neighbor Solicitations, per RFC 4861 § 10.
+    pub const DEFAULT_RETRANS_TIME: Duration = Duration::from_secs(1);
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            transmits: Self::DEFAULT_TRANSMITS,
+            retrans_time: Self::DEFAULT_RETRANS_TIME,
+            optimistic: false,
+        }
+    }
+}
+
+/// State of an IPv6 address with respect to Duplicate Address Detection.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressState {
+    /// Uniqueness of the address is being verified. The address is not used, and
+    /// packets sent to it are discarded.
+    Tentative,
+    /// Uniqueness of the address is being verified, but the address is used
+    /// nonetheless.
+    Optimistic,
+    /// The address is unique, and used without restrictions.
+    Preferred,
+    /// The address is used by another node on the link, and is not used. The address
+    /// should be removed from the interface.
+    Duplicate,
+}
+
+impl AddressState {
+    /// Query whether the address can be used as the source or destination of packets.
+    pub fn is_usable(&self) -> bool {
+        matches!(self, AddressState::Optimistic | AddressState::Preferred)
+    }
+
+    /// Query whether the uniqueness of the address is being verified.
+    pub fn is_probing(&self) -> bool {
+        matches!(self, AddressState::Tentative | AddressState::Optimistic)
+    }
+}
+
+/// Duplicate Address Detection progress of an IPv6 address.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Entry {
+    state: AddressState,
+    probes_sent: u8,
+    next_event: Instant,
+}
+
+impl Entry {
+    fn new(config: &Config, timestamp: Instant) -> Self {
+        let state = match (config.transmits, config.optimistic) {
+            (0, _) => AddressState::Preferred,
+            (_, true) => AddressState::Optimistic,
+            (_, false) => AddressState::Tentative,
+        };
+        Entry {
+            state,
+            probes_sent: 0,
+            next_event: timestamp,
+        }
+    }
+
+    /// Return the state of the address.
+    pub fn state(&self) -> AddressState {
+        self.state
+    }
+}
+
+/// What to do for an address whose Duplicate Address Detection timer expired.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Action {
+    /// Send a Neighbor Solicitation for the address.
+    Probe(Ipv6Address),
+    /// Nothing to send.
+    None,
+}
+
+/// Duplicate Address Detection state of an interface.
+#[derive(Debug)]
+pub(crate) struct Dad<'a> {
+    config: Config,
+    entries: ManagedMap<'a, Ipv6Address, Entry>,
+}
+
+impl<'a> Dad<'a> {
+    pub(crate) fn new(config: Config, entries: ManagedMap<'a, Ipv6Address, Entry>) -> Self {
+        Dad { config, entries }
+    }
+
+    /// Return the state of `addr`, or `None` if it is not tracked.
+    pub(crate) fn state(&self, addr: &Ipv6Address) -> Option<AddressState> {
+        self.entries.get(addr).map(Entry::state)
+    }
+
+    /// Start verifying the uniqueness of the addresses in `addrs` that are not tracked yet,
+    /// and forget the tracked addresses that are not in `addrs` anymore.
+    pub(crate) fn sync<I>(&mut self, addrs: I, timestamp: Instant)
+    where
+        I: Iterator<Item = Ipv6Address> + Clone,
+    {
+        while let Some(removed) = self
+            .entries
+            .iter()
+            .map(|(addr, _)| *addr)
+            .find(|addr| !addrs.clone().any(|probe| probe == *addr))
+        {
+            self.entries.remove(&removed);
+        }
+
+        for addr in addrs {
+            if addr.is_unicast() && !addr.is_loopback() && self.entries.get(&addr).is_none() {
+                let entry = Entry::new(&self.config, timestamp);
+                if self.entries.insert(addr, entry).is_err() {
+                    net_debug!("dad: no space to track {}", addr);
+                }
+            }
+        }
+    }
+
+    /// Verify the uniqueness of all the tracked addresses again, except those already
+    /// found to be duplicates; e.g. after the link came back up.
+    pub(crate) fn restart(&mut self, timestamp: Instant) {
+        let config = self.config;
+        for (_, entry) in self.entries.iter_mut() {
+            if entry.state != AddressState::Duplicate {
+                *entry = Entry::new(&config, timestamp);
+            }
+        }
+    }
+
+    /// Return the earliest time at which an action is required.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.state.is_probing())
+            .map(|(_, entry)| entry.next_event)
+            .min()
+    }
+
+    /// Advance the timer of the first address for which an action is due at `timestamp`,
+    /// or return `None` if there is none.
+    pub(crate) fn next_action(&mut self, timestamp: Instant) -> Option<Action> {
+        let config = self.config;
+        let (addr, entry) = self
+            .entries
+            .iter()
+            .find(|(_, entry)| entry.state.is_probing() && entry.next_event <= timestamp)
+            .map(|(addr, entry)| (*addr, *entry))?;
+
+        let entry = if entry.probes_sent < config.transmits {
+            Entry {
+                probes_sent: entry.probes_sent + 1,
+                next_event: timestamp + config.retrans_time,
+                ..entry
+            }
+        } else {
+            net_debug!("dad: {} is unique", addr);
+            Entry {
+                state: AddressState::Preferred,
+                ..entry
+            }
+        };
+        let action = if entry.state.is_probing() {
+            Action::Probe(addr)
+        } else {
+            Action::None
+        };
+        // Cannot fail, the address is already present.
+        let _ = self.entries.insert(addr, entry);
+        Some(action)
+    }
+
+    /// Record that another node uses `addr`, if its uniqueness is being verified.
+    ///
+    /// Returns whether the address was found to be a duplicate.
+    pub(crate) fn conflict(&mut self, addr: &Ipv6Address) -> bool {
+        match self.entries.get(addr) {
+            Some(entry) if entry.state.is_probing() => {
+                net_debug!("dad: {} is a duplicate", addr);
+                let entry = Entry {
+                    state: AddressState::Duplicate,
+                    ..*entry
+                };
+                let _ = self.entries.insert(*addr, entry);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the addresses found to be duplicates.
+    pub(crate) fn duplicates(&self) -> impl Iterator<Item = Ipv6Address> + '_ {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.state == AddressState::Duplicate)
+            .map(|(addr, _)| *addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const ADDR: Ipv6Address = Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    fn dad(config: Config) -> Dad<'static> {
+        let mut dad = Dad::new(config, ManagedMap::Owned(BTreeMap::new()));
+        dad.sync([ADDR, Ipv6Address::LOOPBACK].iter().cloned(), Instant::ZERO);
+        dad
+    }
+
+    #[test]
+    fn test_unique() {
+        let mut dad = dad(Config {
+            transmits: 2,
+            ..Config::default()
+        });
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Tentative));
+        assert_eq!(dad.state(&Ipv6Address::LOOPBACK), None);
+        assert_eq!(dad.poll_at(), Some(Instant::ZERO));
+
+        assert_eq!(dad.next_action(Instant::ZERO), Some(Action::Probe(ADDR)));
+        assert_eq!(dad.next_action(Instant::ZERO), None);
+        assert_eq!(dad.poll_at(), Some(Instant::from_secs(1)));
+        assert_eq!(
+            dad.next_action(Instant::from_secs(1)),
+            Some(Action::Probe(ADDR))
+        );
+        assert_eq!(dad.next_action(Instant::from_secs(2)), Some(Action::None));
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Preferred));
+        assert_eq!(dad.poll_at(), None);
+        assert!(!dad.conflict(&ADDR));
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let mut dad = dad(Config {
+            optimistic: true,
+            ..Config::default()
+        });
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Optimistic));
+        assert_eq!(dad.next_action(Instant::ZERO), Some(Action::Probe(ADDR)));
+        assert!(dad.conflict(&ADDR));
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Duplicate));
+        assert_eq!(dad.duplicates().collect::<std::vec::Vec<_>>(), [ADDR]);
+        assert_eq!(dad.poll_at(), None);
+
+        // Removing the address forgets it, adding it again restarts detection.
+        dad.sync([].iter().cloned(), Instant::from_secs(5));
+        assert_eq!(dad.state(&ADDR), None);
+        dad.sync([ADDR].iter().cloned(), Instant::from_secs(5));
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Optimistic));
+    }
+
+    #[test]
+    fn test_disabled() {
+        let dad = dad(Config {
+            transmits: 0,
+            ..Config::default()
+        });
+        assert_eq!(dad.state(&ADDR), Some(AddressState::Preferred));
+        assert_eq!(dad.poll_at(), None);
+    }
+}
@@ -0,0 +1,328 @@
+use core::iter;
+
+use crate::time::Instant;
+
+/// Where the node of a key is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// In neither the heap nor the list of changed keys.
+    Idle,
+    /// In the heap.
+    Queued,
+    /// In the list of changed keys.
+    Changed,
+}
+
+/// The node of a key in a `DeadlineQueue`.
+///
+/// Nodes are stored by whoever owns the keys, so that the queue itself does not need any
+/// storage; a `SocketSet` stores the node of a socket handle alongside it.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Node {
+    state: State,
+    deadline: Instant,
+    /// The first child of the node in the heap.
+    child: Option<usize>,
+    /// The next sibling of the node in the heap, or the next key in the list of changed keys.
+    next: Option<usize>,
+    /// The previous sibling of the node in the heap, or its parent if it is the first child.
+    prev: Option<usize>,
+}
+
+impl Node {
+    pub(crate) const EMPTY: Self = Node {
+        state: State::Idle,
+        deadline: Instant::ZERO,
+        child: None,
+        next: None,
+        prev: None,
+    };
+}
+
+/// Storage of the nodes of a `DeadlineQueue`, indexed by key.
+pub(crate) trait Nodes {
+    fn node(&self, key: usize) -> &Node;
+    fn node_mut(&mut self, key: usize) -> &mut Node;
+}
+
+/// The deadline computed for a changed key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Deadline {
+    /// The key has to be visited at the given time.
+    At(Instant),
+    /// The key does not have a deadline.
+    Never,
+    /// The deadline cannot be known in advance; the key stays in the list of changed keys.
+    Unknown,
+}
+
+/// A priority queue of the keys with a deadline, along with a list of the keys whose
+/// deadline has to be computed again.
+///
+/// The queue is a pairing heap, so that inserting a key takes constant time, and removing
+/// one takes logarithmic time, amortized. Its nodes are intrusive: they are linked through
+/// their keys, and stored in a `Nodes`.
+#[derive(Debug, Default)]
+pub(crate) struct DeadlineQueue {
+    root: Option<usize>,
+    changed: Option<usize>,
+}
+
+impl DeadlineQueue {
+    /// Return the earliest deadline in the heap.
+    pub(crate) fn earliest<N: Nodes + ?Sized>(&self, nodes: &N) -> Option<Instant> {
+        self.root.map(|root| nodes.node(root).deadline)
+    }
+
+    /// Iterate over the keys whose deadline has to be computed again.
+    pub(crate) fn changed<'n, N: Nodes + ?Sized>(
+        &self,
+        nodes: &'n N,
+    ) -> impl Iterator<Item = usize> + 'n {
+        iter::successors(self.changed, move |&key| nodes.node(key).next)
+    }
+
+    /// Move `key` to the list of changed keys, taking it out of the heap if it is queued.
+    pub(crate) fn mark_changed<N: Nodes + ?Sized>(&mut self, nodes: &mut N, key: usize) {
+        match nodes.node(key).state {
+            State::Changed => return,
+            State::Queued => self.remove(nodes, key),
+            State::Idle => (),
+        }
+        *nodes.node_mut(key) = Node {
+            state: State::Changed,
+            next: self.changed,
+            ..Node::EMPTY
+        };
+        self.changed = Some(key);
+    }
+
+    /// Move the keys whose deadline is at or before `now` to the list of changed keys.
+    pub(crate) fn mark_due<N: Nodes + ?Sized>(&mut self, nodes: &mut N, now: Instant) {
+        while let Some(root) = self.root {
+            if nodes.node(root).deadline > now {
+                break;
+            }
+            self.mark_changed(nodes, root);
+        }
+    }
+
+    /// Compute the deadline of every changed key with `deadline`, and queue the ones that
+    /// have one.
+    pub(crate) fn update<N, F>(&mut self, nodes: &mut N, mut deadline: F)
+    where
+        N: Nodes + ?Sized,
+        F: FnMut(&N, usize) -> Deadline,
+    {
+        let mut unknown = None;
+        let mut next = self.changed.take();
+        while let Some(key) = next {
+            next = nodes.node(key).next;
+            *nodes.node_mut(key) = Node::EMPTY;
+            match deadline(nodes, key) {
+                Deadline::At(at) => self.insert(nodes, key, at),
+                Deadline::Never => (),
+                Deadline::Unknown => {
+                    *nodes.node_mut(key) = Node {
+                        state: State::Changed,
+                        next: unknown,
+                        ..Node::EMPTY
+                    };
+                    unknown = Some(key);
+                }
+            }
+        }
+        self.changed = unknown;
+    }
+
+    /// Take `key` out of the heap, if it is queued.
+    ///
+    /// A changed key stays in the list of changed keys, since that list is singly linked;
+    /// `deadline` is expected to return `Deadline::Never` for keys that are not in use.
+    pub(crate) fn remove<N: Nodes + ?Sized>(&mut self, nodes: &mut N, key: usize) {
+        let node = *nodes.node(key);
+        if node.state != State::Queued {
+            return;
+        }
+
+        let children = Self::merge_pairs(nodes, node.child);
+        if self.root == Some(key) {
+            self.root = children;
+        } else {
+            let prev = node.prev.expect("queued node without a parent");
+            if nodes.node(prev).child == Some(key) {
+                nodes.node_mut(prev).child = node.next;
+            } else {
+                nodes.node_mut(prev).next = node.next;
+            }
+            if let Some(next) = node.next {
+                nodes.node_mut(next).prev = Some(prev);
+            }
+            self.root = Self::meld_opt(nodes, self.root, children);
+        }
+        *nodes.node_mut(key) = Node::EMPTY;
+    }
+
+    fn insert<N: Nodes + ?Sized>(&mut self, nodes: &mut N, key: usize, deadline: Instant) {
+        *nodes.node_mut(key) = Node {
+            state: State::Queued,
+            deadline,
+            ..Node::EMPTY
+        };
+        self.root = Self::meld_opt(nodes, self.root, Some(key));
+    }
+
+    fn meld_opt<N: Nodes + ?Sized>(
+        nodes: &mut N,
+        a: Option<usize>,
+        b: Option<usize>,
+    ) -> Option<usize> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(Self::meld(nodes, a, b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Meld the heaps rooted at `a` and `b`, and return the root of the result.
+    fn meld<N: Nodes + ?Sized>(nodes: &mut N, a: usize, b: usize) -> usize {
+        let (parent, child) = if nodes.node(b).deadline < nodes.node(a).deadline {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let first = nodes.node(parent).child;
+        if let Some(first) = first {
+            nodes.node_mut(first).prev = Some(child);
+        }
+        let node = nodes.node_mut(child);
+        node.prev = Some(parent);
+        node.next = first;
+        let node = nodes.node_mut(parent);
+        node.child = Some(child);
+        node.prev = None;
+        node.next = None;
+        parent
+    }
+
+    /// Meld the heaps in the list of siblings starting at `first`, in two passes, and
+    /// return the root of the result.
+    fn merge_pairs<N: Nodes + ?Sized>(nodes: &mut N, first: Option<usize>) -> Option<usize> {
+        // Meld the siblings in pairs, from left to right, onto a stack linked through `next`.
+        let mut stack = None;
+        let mut next = first;
+        while let Some(a) = next {
+            let melded = match nodes.node(a).next {
+                Some(b) => {
+                    next = nodes.node(b).next;
+                    Self::meld(nodes, a, b)
+                }
+                None => {
+                    next = None;
+                    a
+                }
+            };
+            let node = nodes.node_mut(melded);
+            node.prev = None;
+            node.next = stack;
+            stack = Some(melded);
+        }
+
+        // Meld the pairs from right to left.
+        let mut root = None;
+        while let Some(a) = stack {
+            stack = nodes.node(a).next;
+            nodes.node_mut(a).next = None;
+            root = Self::meld_opt(nodes, root, Some(a));
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    impl Nodes for [Node] {
+        fn node(&self, key: usize) -> &Node {
+            &self[key]
+        }
+
+        fn node_mut(&mut self, key: usize) -> &mut Node {
+            &mut self[key]
+        }
+    }
+
+    fn queue(deadlines: &[i64], nodes: &mut [Node]) -> DeadlineQueue {
+        let mut queue = DeadlineQueue::default();
+        for key in 0..deadlines.len() {
+            queue.mark_changed(nodes, key);
+        }
+        queue.update(nodes, |_, key| {
+            Deadline::At(Instant::from_secs(deadlines[key]))
+        });
+        queue
+    }
+
+    /// Take the keys out of the queue in the order of their deadlines.
+    fn drain(queue: &mut DeadlineQueue, nodes: &mut [Node]) -> Vec<i64> {
+        let mut order = vec![];
+        while let Some(earliest) = queue.earliest(nodes) {
+            order.push(earliest.secs());
+            queue.mark_due(nodes, earliest);
+            let due: Vec<_> = queue.changed(nodes).collect();
+            queue.update(nodes, |_, _| Deadline::Never);
+            assert_eq!(due.len(), 1);
+        }
+        order
+    }
+
+    #[test]
+    fn test_order() {
+        let deadlines = [5, 3, 9, 1, 7, 2, 8, 6, 4, 0];
+        let nodes = &mut [Node::EMPTY; 10][..];
+        let mut queue = queue(&deadlines, nodes);
+        assert_eq!(queue.changed(nodes).count(), 0);
+        assert_eq!(drain(&mut queue, nodes), [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_remove() {
+        let deadlines = [5, 3, 9, 1, 7, 2, 8, 6, 4, 0];
+        let nodes = &mut [Node::EMPTY; 10][..];
+        let mut queue = queue(&deadlines, nodes);
+        // Taking the root out pairs its children, so that some keys removed below are not
+        // children of the root.
+        queue.mark_due(nodes, Instant::from_secs(0));
+        queue.update(nodes, |_, _| Deadline::At(Instant::from_secs(10)));
+
+        for &key in [3, 2, 6, 9].iter() {
+            queue.remove(nodes, key);
+        }
+        assert_eq!(drain(&mut queue, nodes), [2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_changed() {
+        let deadlines = [5, 3, 9, 1];
+        let nodes = &mut [Node::EMPTY; 4][..];
+        let mut queue = queue(&deadlines, nodes);
+
+        // A changed key leaves the heap, and is visited until its deadline is computed.
+        queue.mark_changed(nodes, 3);
+        queue.mark_changed(nodes, 3);
+        assert_eq!(queue.earliest(nodes), Some(Instant::from_secs(3)));
+        assert_eq!(queue.changed(nodes).collect::<Vec<_>>(), [3]);
+
+        // A key with an unknown deadline stays changed.
+        let mut visited = vec![];
+        queue.update(nodes, |_, key| {
+            visited.push(key);
+            Deadline::Unknown
+        });
+        assert_eq!(queue.changed(nodes).collect::<Vec<_>>(), [3]);
+        queue.update(nodes, |_, _| Deadline::At(Instant::from_secs(4)));
+        assert_eq!(visited, [3]);
+        assert_eq!(queue.changed(nodes).count(), 0);
+        assert_eq!(drain(&mut queue, nodes), [3, 4, 5, 9]);
+    }
+}
@@ -106,7 +106,7 @@ impl<'a> PacketAssembler<'a> {
     ///
     /// - Returns [`Error::PacketAssemblerNotInit`] when the assembler was not initialized (try initializing the
     /// assembler with [Self::start]).
-    pub(crate) fn expires_at(&self) -> Result<Instant> {
+    pub fn expires_at(&self) -> Result<Instant> {
         match self.assembler {
             AssemblerState::NotInit => Err(Error::PacketAssemblerNotInit),
             AssemblerState::Assembling { expires_at, .. } => Ok(expires_at),
@@ -197,7 +197,7 @@ impl<'a> PacketAssembler<'a> {
     ///
     /// - Returns [`Error::PacketAssemblerNotInit`] when the assembler was not initialized (try initializing the
     /// assembler with [`Self::start`]).
-    pub(crate) fn is_complete(&self) -> Result<bool> {
+    pub fn is_complete(&self) -> Result<bool> {
         match &self.assembler {
             AssemblerState::NotInit => Err(Error::PacketAssemblerNotInit),
             AssemblerState::Assembling {
@@ -211,6 +211,22 @@ impl<'a> PacketAssembler<'a> {
         }
     }
 
+    /// Returns the size of the packet being reassembled, if known yet.
+    pub fn total_size(&self) -> Option<usize> {
+        match self.assembler {
+            AssemblerState::NotInit => None,
+            AssemblerState::Assembling { total_size, .. } => total_size,
+        }
+    }
+
+    /// Returns the number of contiguous bytes received from the start of the packet.
+    pub fn received_len(&self) -> usize {
+        match &self.assembler {
+            AssemblerState::NotInit => 0,
+            AssemblerState::Assembling { assembler, .. } => assembler.peek_front().unwrap_or(0),
+        }
+    }
+
     /// Returns `true` when the packet assembler is free to use.
     fn is_free(&self) -> bool {
         self.assembler == AssemblerState::NotInit
@@ -281,6 +297,15 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
         }
     }
 
+    /// Iterate over the packets being reassembled, and their keys.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &PacketAssembler<'a>)> {
+        let packet_buffer = &self.packet_buffer;
+        self.index_buffer
+            .iter()
+            .map(move |(key, &index)| (key, &packet_buffer[index]))
+            .filter(|(_, assembler)| !assembler.is_free())
+    }
+
     /// Reserve a [`PacketAssembler`], which is linked to a specific key.
     /// Returns the reserved fragments assembler.
     ///
@@ -383,9 +408,9 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
 
     /// Mark all [`PacketAssembler`]s as discarded for which `f` returns `Ok(true)`.
     /// This does not remove them from the buffer.
-    pub fn mark_discarded_when<F>(&mut self, f: F) -> Result<()>
+    pub fn mark_discarded_when<F>(&mut self, mut f: F) -> Result<()>
     where
-        F: Fn(&mut PacketAssembler<'_>) -> Result<bool>,
+        F: FnMut(&mut PacketAssembler<'_>) -> Result<bool>,
     {
         for (_, i) in &mut self.index_buffer.iter() {
             let frag = &mut self.packet_buffer[*i as usize];
@@ -400,7 +425,7 @@ impl<'a, K: Eq + Ord + Clone + Copy> PacketAssemblerSet<'a, K> {
     /// Remove all [`PacketAssembler`]s for which `f` returns `Ok(true)`.
     pub fn remove_when<F>(&mut self, f: F) -> Result<()>
     where
-        F: Fn(&mut PacketAssembler<'_>) -> Result<bool>,
+        F: FnMut(&mut PacketAssembler<'_>) -> Result<bool>,
     {
         self.mark_discarded_when(f)?;
         self.remove_discarded();
@@ -575,4 +600,22 @@ mod tests {
             .unwrap();
         set.get_assembled_packet(&key).unwrap();
     }
+
+    #[test]
+    fn packet_assembler_set_iter() {
+        let mut set = PacketAssemblerSet::new(vec![], std::collections::BTreeMap::new());
+        assert_eq!(set.iter().count(), 0);
+
+        let key = Key { id: 1 };
+        let assembler = set.reserve_with_key(&key).unwrap();
+        assembler.start(Some(4), Instant::from_secs(1), 0).unwrap();
+        assembler.add(&[1, 2], 0).unwrap();
+
+        let (&iter_key, assembler) = set.iter().next().unwrap();
+        assert!(iter_key == key);
+        assert_eq!(assembler.total_size(), Some(4));
+        assert_eq!(assembler.received_len(), 2);
+        assert_eq!(assembler.expires_at(), Ok(Instant::from_secs(1)));
+        assert_eq!(assembler.is_complete(), Ok(false));
+    }
 }
@@ -2,21 +2,55 @@
 // of RFC 1122 that discuss Ethernet, ARP and IP for any IPv4 work
 // and RFCs 8200 and 4861 for any IPv6 and NDISC work.
 
-use core::cmp;
+use core::{cmp, fmt, mem};
 use managed::{ManagedMap, ManagedSlice};
 
+#[cfg(feature = "proto-ipv4-acd")]
+use super::acd::{
+    Acd, Action as AcdAction, AddressState as Ipv4AddressState, Config as AcdConfig,
+    Entry as AcdEntry,
+};
+#[cfg(feature = "proto-ipv4-autoip")]
+use super::autoip::{Action as AutoIpAction, AutoIp, Config as AutoIpConfig};
+#[cfg(feature = "proto-ipv6-dad")]
+use super::dad::{
+    Action as DadAction, AddressState as Ipv6AddressState, Config as DadConfig, Dad,
+    Entry as DadEntry,
+};
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 use super::fragmentation::PacketAssemblerSet;
-use super::socket_set::SocketSet;
+#[cfg(feature = "proto-lldp")]
+use super::lldp::{Config as LldpConfig, Lldp, Neighbor as LldpNeighbor};
+#[cfg(feature = "proto-igmp")]
+use super::multicast::{FilterMode, SourceFilter, MAX_SOURCES};
+#[cfg(feature = "proto-ipv6-ra")]
+use super::ra_dns::RaDns;
+#[cfg(feature = "proto-ipv6-ra")]
+use super::router_advert::{Config as RouterAdvertConfig, RouterAdvert};
+#[cfg(feature = "proto-ipv6-slaac")]
+use super::slaac::{Config as SlaacConfig, Entry as SlaacEntry, Slaac};
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+use super::socket_set::DemuxKey;
+use super::socket_set::{Item, SocketPriority, SocketSet};
+#[cfg(feature = "proto-ipv6")]
+use super::source_addr;
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+use crate::iface::ArpProtection;
 use crate::iface::Routes;
+use crate::iface::{IcmpKind, PacketKind, Stats};
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 use crate::iface::{NeighborAnswer, NeighborCache};
-use crate::phy::{ChecksumCapabilities, Device, DeviceCapabilities, Medium, RxToken, TxToken};
+use crate::phy::PacketMeta;
+use crate::phy::{
+    ChecksumCapabilities, Device, DeviceCapabilities, LinkState, Medium, RxToken, TxToken,
+};
 use crate::rand::Rand;
 #[cfg(feature = "socket-dhcpv4")]
 use crate::socket::dhcpv4;
 #[cfg(feature = "socket-dns")]
 use crate::socket::dns;
+#[cfg(feature = "socket-sntp")]
+use crate::socket::sntp;
 use crate::socket::*;
 use crate::time::{Duration, Instant};
 use crate::wire::*;
@@ -84,7 +118,7 @@ impl<'a> SixlowpanOutPacket<'a> {
 }
 
 macro_rules! check {
-    ($e:expr) => {
+    ($e:expr $(, $counter:expr)*) => {
         match $e {
             Ok(x) => x,
             Err(_) => {
@@ -93,12 +127,25 @@ macro_rules! check {
                 net_trace!(concat!("iface: malformed ", stringify!($e)));
                 #[cfg(feature = "defmt")]
                 net_trace!("iface: malformed");
+                $($counter += 1;)*
                 return Default::default();
             }
         }
     };
 }
 
+/// Counters of the Ethernet frames dropped because of their destination address.
+#[cfg(feature = "medium-ethernet")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct EthernetFilterStats {
+    /// Frames addressed to the hardware address of another host.
+    pub unicast: u64,
+    /// Frames addressed to a multicast address that the interface does not listen to.
+    pub multicast: u64,
+}
+
 /// A  network interface.
 ///
 /// The network interface logically owns a number of other data structures; to avoid
@@ -134,15 +181,137 @@ pub struct InterfaceInner<'a> {
     ip_addrs: ManagedSlice<'a, IpCidr>,
     #[cfg(feature = "proto-ipv4")]
     any_ip: bool,
+    egress_burst: usize,
+    /// The IP MTU of the interface, if it is lower than the one of the device.
+    mtu: Option<usize>,
+    ingress_burst: Option<usize>,
+    /// Whether the ingress burst was used up during the last poll, so that frames may
+    /// still be waiting in the device.
+    ingress_pending: bool,
+    /// The receive timestamp reported by the device for the packet being processed.
+    rx_timestamp: Option<Instant>,
+    /// The transmit timestamp reported by the device for the last packet dispatched.
+    tx_timestamp: Option<Instant>,
+    /// The DSCP value and 802.1Q priority of the socket being serviced during egress.
+    egress_dscp: Option<u8>,
+    egress_priority: Option<u8>,
+    /// The mark of the socket being serviced during egress.
+    egress_mark: Option<u32>,
+    index: u32,
+    /// Incremented whenever the interface changes in a way that may move the deadlines of
+    /// the sockets, so that the deadlines scheduled in a `SocketSet` are computed again.
+    generation: u32,
+    /// The time at which the deadlines of the sockets were last scheduled.
+    scheduled_at: Instant,
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    arp_protection: ArpProtection,
+    unknown_protocol_policy: UnknownProtocolPolicy,
+    parse_mode: ParseMode,
     routes: Routes<'a>,
     #[cfg(feature = "proto-igmp")]
-    ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, ()>,
+    ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, SourceFilter>,
     /// When to report for (all or) the next multicast group membership via IGMP
     #[cfg(feature = "proto-igmp")]
     igmp_report_state: IgmpReportState,
+    #[cfg(feature = "proto-lldp")]
+    lldp: Option<Lldp<'a>>,
+    #[cfg(feature = "proto-ipv6-ra")]
+    router_advert: Option<RouterAdvert<'a>>,
+    #[cfg(feature = "proto-ipv4-acd")]
+    acd: Option<Acd<'a>>,
+    #[cfg(feature = "proto-ipv4-autoip")]
+    autoip: Option<AutoIp>,
+    #[cfg(feature = "proto-ipv6-dad")]
+    dad: Option<Dad<'a>>,
+    #[cfg(feature = "proto-ipv6-slaac")]
+    slaac: Option<Slaac<'a>>,
+    #[cfg(feature = "proto-ipv6-ra")]
+    ra_dns: RaDns,
+    #[cfg(feature = "proto-wol")]
+    wake_on_lan_received: bool,
+    #[cfg(feature = "medium-ethernet")]
+    ethernet_filter_stats: EthernetFilterStats,
+    link_state: LinkState,
+    stats: Stats,
     rand: Rand,
 }
 
+/// Error returned by [`InterfaceBuilder::try_finalize`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BuildError {
+    /// The medium of the device requires a hardware address, which was not set.
+    HardwareAddrRequired,
+    /// The medium of the device requires a neighbor cache, which was not set.
+    NeighborCacheRequired,
+    /// A hardware address was set, but the medium of the device does not use one.
+    HardwareAddrUnused,
+    /// A neighbor cache was set, but the medium of the device does not use one.
+    NeighborCacheUnused,
+    /// The cache for incoming IPv4 fragments was not set.
+    Ipv4FragmentsCacheRequired,
+    /// The cache for incoming 6LoWPAN fragments was not set.
+    SixlowpanFragmentsCacheRequired,
+    /// The buffer for outgoing 6LoWPAN fragments was not set.
+    SixlowpanOutPacketCacheRequired,
+    /// Link-local IPv4 address autoconfiguration was enabled without Address Conflict
+    /// Detection.
+    AcdRequired,
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::HardwareAddrRequired => {
+                write!(f, "hardware_addr required option was not set")
+            }
+            BuildError::NeighborCacheRequired => {
+                write!(f, "neighbor_cache required option was not set")
+            }
+            BuildError::HardwareAddrUnused => {
+                write!(f, "hardware_addr is set, but device medium is IP")
+            }
+            BuildError::NeighborCacheUnused => {
+                write!(f, "neighbor_cache is set, but device medium is IP")
+            }
+            BuildError::Ipv4FragmentsCacheRequired => {
+                write!(f, "Cache for incoming IPv4 fragments is required")
+            }
+            BuildError::SixlowpanFragmentsCacheRequired => {
+                write!(f, "Cache for incoming 6LoWPAN fragments is required")
+            }
+            BuildError::SixlowpanOutPacketCacheRequired => {
+                write!(f, "Cache for outgoing 6LoWPAN fragments is required")
+            }
+            BuildError::AcdRequired => {
+                write!(f, "ipv4_acd is required by ipv4_autoip")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BuildError {}
+
+/// What the interface does with IP packets whose protocol it has no handler for.
+///
+/// Raw sockets receive a copy of every IP packet they accept, whatever the policy; the
+/// policy decides whether the interface also replies with an ICMP error, which is a
+/// Protocol Unreachable message for IPv4, and a Parameter Problem message pointing at the
+/// Next Header field for IPv6.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UnknownProtocolPolicy {
+    /// Drop the packet without replying.
+    Drop,
+    /// Reply with an ICMP error, even if a raw socket received the packet, e.g. when raw
+    /// sockets are only used to monitor the traffic.
+    IcmpError,
+    /// Leave the packet to the raw sockets that accept it, and reply with an ICMP error
+    /// only if there are none. This is the default.
+    DeliverToRawSocket,
+}
+
 /// A builder structure used for creating a network interface.
 pub struct InterfaceBuilder<'a> {
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
@@ -154,10 +323,31 @@ pub struct InterfaceBuilder<'a> {
     ip_addrs: ManagedSlice<'a, IpCidr>,
     #[cfg(feature = "proto-ipv4")]
     any_ip: bool,
+    egress_burst: usize,
+    /// The IP MTU of the interface, if it is lower than the one of the device.
+    mtu: Option<usize>,
+    ingress_burst: Option<usize>,
+    index: u32,
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    arp_protection: ArpProtection,
+    unknown_protocol_policy: UnknownProtocolPolicy,
+    parse_mode: ParseMode,
     routes: Routes<'a>,
     /// Does not share storage with `ipv6_multicast_groups` to avoid IPv6 size overhead.
     #[cfg(feature = "proto-igmp")]
-    ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, ()>,
+    ipv4_multicast_groups: ManagedMap<'a, Ipv4Address, SourceFilter>,
+    #[cfg(feature = "proto-lldp")]
+    lldp: Option<LldpConfig<'a>>,
+    #[cfg(feature = "proto-ipv6-ra")]
+    router_advert: Option<RouterAdvertConfig<'a>>,
+    #[cfg(feature = "proto-ipv4-acd")]
+    acd: Option<Acd<'a>>,
+    #[cfg(feature = "proto-ipv4-autoip")]
+    autoip: Option<AutoIpConfig>,
+    #[cfg(feature = "proto-ipv6-dad")]
+    dad: Option<Dad<'a>>,
+    #[cfg(feature = "proto-ipv6-slaac")]
+    slaac: Option<Slaac<'a>>,
     random_seed: u64,
 
     #[cfg(feature = "proto-ipv4-fragmentation")]
@@ -204,7 +394,7 @@ let builder = InterfaceBuilder::new()
 # #[cfg(feature = "proto-ipv4-fragmentation")]
 let builder = builder.ipv4_fragments_cache(ipv4_frag_cache);
 
-let iface = builder.finalize(&mut device);
+let iface = builder.try_finalize(&mut device).unwrap();
 ```
     "##
     )]
@@ -222,9 +412,29 @@ let iface = builder.finalize(&mut device);
             ip_addrs: ManagedSlice::Borrowed(&mut []),
             #[cfg(feature = "proto-ipv4")]
             any_ip: false,
+            egress_burst: DEFAULT_EGRESS_BURST,
+            mtu: None,
+            ingress_burst: None,
+            index: 0,
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            arp_protection: ArpProtection::Off,
+            unknown_protocol_policy: UnknownProtocolPolicy::DeliverToRawSocket,
+            parse_mode: ParseMode::Permissive,
             routes: Routes::new(ManagedMap::Borrowed(&mut [])),
             #[cfg(feature = "proto-igmp")]
             ipv4_multicast_groups: ManagedMap::Borrowed(&mut []),
+            #[cfg(feature = "proto-lldp")]
+            lldp: None,
+            #[cfg(feature = "proto-ipv6-ra")]
+            router_advert: None,
+            #[cfg(feature = "proto-ipv4-acd")]
+            acd: None,
+            #[cfg(feature = "proto-ipv4-autoip")]
+            autoip: None,
+            #[cfg(feature = "proto-ipv6-dad")]
+            dad: None,
+            #[cfg(feature = "proto-ipv6-slaac")]
+            slaac: None,
             random_seed: 0,
 
             #[cfg(feature = "proto-ipv4-fragmentation")]
@@ -307,6 +517,85 @@ let iface = builder.finalize(&mut device);
         self
     }
 
+    /// Set the maximum number of packets a socket may transmit each time it is
+    /// serviced by [poll], before the other sockets get a turn. See also
+    /// [egress_burst].
+    ///
+    /// The burst is further limited by the `max_burst_size` capability of the device.
+    /// A burst of at least one packet is always permitted.
+    ///
+    /// [poll]: struct.Interface.html#method.poll
+    /// [egress_burst]: struct.Interface.html#method.egress_burst
+    pub fn egress_burst(mut self, burst: usize) -> Self {
+        self.egress_burst = burst;
+        self
+    }
+
+    /// Set the IP MTU of the interface, when it is lower than the one of the device, e.g.
+    /// because the packets are encapsulated further on the path. See also [ip_mtu].
+    ///
+    /// The MTU limits the size of the TCP segments sent and the MSS advertised to the remote
+    /// endpoints. An MTU larger than the one of the device is ignored. The MTU of a route
+    /// overrides it for the destinations of the route; see [Route::mtu].
+    ///
+    /// [ip_mtu]: struct.Interface.html#method.ip_mtu
+    pub fn ip_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /// Set the maximum number of frames received from the device each time [poll] is called.
+    /// See also [ingress_burst].
+    ///
+    /// Once the burst is used up, [poll] stops receiving frames and returns after transmitting,
+    /// bounding the time it takes under load; the remaining frames are received by the next
+    /// call, which [poll_at] then requests right away. By default, every frame is received.
+    ///
+    /// [poll]: struct.Interface.html#method.poll
+    /// [poll_at]: struct.Interface.html#method.poll_at
+    /// [ingress_burst]: struct.Interface.html#method.ingress_burst
+    pub fn ingress_burst(mut self, burst: Option<usize>) -> Self {
+        self.ingress_burst = burst;
+        self
+    }
+
+    /// Set the index identifying the interface, when sockets are shared between several
+    /// interfaces. Sockets bound to an interface are only serviced by the interface with
+    /// the same index. The default index is 0.
+    pub fn index(mut self, index: u32) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Set how ARP packets may change the hardware address of neighbors. See also
+    /// [arp_protection].
+    ///
+    /// [arp_protection]: struct.Interface.html#method.arp_protection
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn arp_protection(mut self, protection: ArpProtection) -> Self {
+        self.arp_protection = protection;
+        self
+    }
+
+    /// Set what the interface does with IP packets whose protocol it has no handler for.
+    /// See also [unknown_protocol_policy].
+    ///
+    /// [unknown_protocol_policy]: struct.Interface.html#method.unknown_protocol_policy
+    pub fn unknown_protocol_policy(mut self, policy: UnknownProtocolPolicy) -> Self {
+        self.unknown_protocol_policy = policy;
+        self
+    }
+
+    /// Set how strictly the headers of received packets are checked. See also [parse_mode].
+    ///
+    /// By default, the interface is permissive, and ignores reserved bits and padding.
+    ///
+    /// [parse_mode]: struct.Interface.html#method.parse_mode
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.parse_mode = mode;
+        self
+    }
+
     /// Set the IP routes the interface will use. See also
     /// [routes].
     ///
@@ -328,16 +617,117 @@ let iface = builder.finalize(&mut device);
     /// storage, i.e. providing a non-empty storage to `ipv4_multicast_groups()`.
     /// Note that this way initial membership reports are **not** sent.
     ///
+    /// Each group is stored along with its source filter, see
+    /// [`join_multicast_group_with_filter()`].
+    ///
     /// [`join_multicast_group()`]: struct.Interface.html#method.join_multicast_group
+    /// [`join_multicast_group_with_filter()`]: struct.Interface.html#method.join_multicast_group_with_filter
     #[cfg(feature = "proto-igmp")]
     pub fn ipv4_multicast_groups<T>(mut self, ipv4_multicast_groups: T) -> Self
     where
-        T: Into<ManagedMap<'a, Ipv4Address, ()>>,
+        T: Into<ManagedMap<'a, Ipv4Address, SourceFilter>>,
     {
         self.ipv4_multicast_groups = ipv4_multicast_groups.into();
         self
     }
 
+    /// Enable the LLDP agent, which periodically advertises the given configuration
+    /// and records the most recently received advertisement. See also [lldp_neighbor].
+    ///
+    /// The agent is only active on Ethernet devices.
+    ///
+    /// [lldp_neighbor]: struct.Interface.html#method.lldp_neighbor
+    #[cfg(feature = "proto-lldp")]
+    pub fn lldp(mut self, config: LldpConfig<'a>) -> Self {
+        self.lldp = Some(config);
+        self
+    }
+
+    /// Act as an IPv6 router, periodically sending router advertisements with
+    /// the given configuration, and answering router solicitations.
+    ///
+    /// Router solicitations are only answered on Ethernet and IEEE 802.15.4 devices.
+    #[cfg(feature = "proto-ipv6-ra")]
+    pub fn router_advertisements(mut self, config: RouterAdvertConfig<'a>) -> Self {
+        self.router_advert = Some(config);
+        self
+    }
+
+    /// Enable Address Conflict Detection for the IPv4 addresses of the interface,
+    /// using `entries` as the storage for the state of each address.
+    ///
+    /// Unicast addresses, except for loopback addresses, are probed with ARP before being
+    /// used, then announced and defended; see [`Interface::ipv4_addr_state()`]. Addresses
+    /// added with [`Interface::update_ip_addrs()`] are probed as well, and a DHCP socket
+    /// probes leased addresses before reporting them.
+    ///
+    /// [`Interface::ipv4_addr_state()`]: struct.Interface.html#method.ipv4_addr_state
+    /// [`Interface::update_ip_addrs()`]: struct.Interface.html#method.update_ip_addrs
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub fn ipv4_acd<T>(mut self, config: AcdConfig, entries: T) -> Self
+    where
+        T: Into<ManagedMap<'a, Ipv4Address, AcdEntry>>,
+    {
+        self.acd = Some(Acd::new(config, entries.into()));
+        self
+    }
+
+    /// Enable link-local IPv4 address autoconfiguration, as specified in RFC 3927.
+    ///
+    /// When the interface has no routable IPv4 address for the time configured in `config`,
+    /// e.g. because a DHCP client could not obtain a lease, a pseudo-random address in
+    /// 169.254.0.0/16 is added to the interface, and replaced if another node uses it. The
+    /// address is removed once a routable address is added; see
+    /// [`Interface::autoip_addr()`].
+    ///
+    /// Address Conflict Detection must be enabled with [`ipv4_acd`](Self::ipv4_acd).
+    ///
+    /// [`Interface::autoip_addr()`]: struct.Interface.html#method.autoip_addr
+    #[cfg(feature = "proto-ipv4-autoip")]
+    pub fn ipv4_autoip(mut self, config: AutoIpConfig) -> Self {
+        self.autoip = Some(config);
+        self
+    }
+
+    /// Enable Duplicate Address Detection for the IPv6 addresses of the interface,
+    /// using `entries` as the storage for the state of each address.
+    ///
+    /// Unicast addresses, except for the loopback address, are tentative until they are
+    /// verified to be unique; see [`Interface::ipv6_addr_state()`]. Addresses added with
+    /// [`Interface::update_ip_addrs()`] are verified as well.
+    ///
+    /// [`Interface::ipv6_addr_state()`]: struct.Interface.html#method.ipv6_addr_state
+    /// [`Interface::update_ip_addrs()`]: struct.Interface.html#method.update_ip_addrs
+    #[cfg(feature = "proto-ipv6-dad")]
+    pub fn ipv6_dad<T>(mut self, config: DadConfig, entries: T) -> Self
+    where
+        T: Into<ManagedMap<'a, Ipv6Address, DadEntry>>,
+    {
+        self.dad = Some(Dad::new(config, entries.into()));
+        self
+    }
+
+    /// Enable Stateless Address Autoconfiguration, using `entries` as the storage for the
+    /// state of each formed address.
+    ///
+    /// Addresses are formed from the prefixes of the Router Advertisements received on the
+    /// link, and removed from the interface when their valid lifetime expires; see
+    /// [`Interface::slaac_addrs()`]. If enabled in `config`, temporary addresses are
+    /// generated and rotated as well, and preferred as the source of outgoing packets.
+    ///
+    /// The default routes and more-specific routes advertised by routers are added to the
+    /// routing table as well, resolving conflicts with the router preferences of RFC 4191.
+    ///
+    /// [`Interface::slaac_addrs()`]: struct.Interface.html#method.slaac_addrs
+    #[cfg(feature = "proto-ipv6-slaac")]
+    pub fn ipv6_slaac<T>(mut self, config: SlaacConfig, entries: T) -> Self
+    where
+        T: Into<ManagedMap<'a, Ipv6Address, SlaacEntry>>,
+    {
+        self.slaac = Some(Slaac::new(config, entries.into()));
+        self
+    }
+
     /// Set the Neighbor Cache the interface will use.
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     pub fn neighbor_cache(mut self, neighbor_cache: NeighborCache<'a>) -> Self {
@@ -372,15 +762,36 @@ let iface = builder.finalize(&mut device);
     /// Create a network interface using the previously provided configuration.
     ///
     /// # Panics
-    /// If a required option is not provided, this function will panic. Required
-    /// options are:
+    /// If the configuration does not suit the device, this function will panic.
     ///
-    /// - [ethernet_addr]
-    /// - [neighbor_cache]
+    /// [try_finalize] reports an invalid configuration as a `BuildError` instead.
     ///
-    /// [ethernet_addr]: #method.ethernet_addr
-    /// [neighbor_cache]: #method.neighbor_cache
+    /// [try_finalize]: #method.try_finalize
+    #[deprecated(
+        since = "0.9.0",
+        note = "use `try_finalize`, which returns an error instead of panicking"
+    )]
     pub fn finalize<D>(self, device: &mut D) -> Interface<'a>
+    where
+        D: for<'d> Device<'d>,
+    {
+        match self.try_finalize(device) {
+            Ok(iface) => iface,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Create a network interface using the previously provided configuration,
+    /// or return an error if the configuration does not suit the device.
+    ///
+    /// Required options are:
+    ///
+    /// - [hardware_addr], unless the medium is IP
+    /// - [neighbor_cache], unless the medium is IP
+    ///
+    /// [hardware_addr]: #method.hardware_addr
+    /// [neighbor_cache]: #method.neighbor_cache
+    pub fn try_finalize<D>(self, device: &mut D) -> core::result::Result<Interface<'a>, BuildError>
     where
         D: for<'d> Device<'d>,
     {
@@ -390,40 +801,44 @@ let iface = builder.finalize(&mut device);
         let (hardware_addr, neighbor_cache) = match caps.medium {
             #[cfg(feature = "medium-ethernet")]
             Medium::Ethernet => (
-                Some(
-                    self.hardware_addr
-                        .expect("hardware_addr required option was not set"),
-                ),
+                Some(self.hardware_addr.ok_or(BuildError::HardwareAddrRequired)?),
                 Some(
                     self.neighbor_cache
-                        .expect("neighbor_cache required option was not set"),
+                        .ok_or(BuildError::NeighborCacheRequired)?,
                 ),
             ),
             #[cfg(feature = "medium-ip")]
             Medium::Ip => {
-                assert!(
-                    self.hardware_addr.is_none(),
-                    "hardware_addr is set, but device medium is IP"
-                );
-                assert!(
-                    self.neighbor_cache.is_none(),
-                    "neighbor_cache is set, but device medium is IP"
-                );
+                if self.hardware_addr.is_some() {
+                    return Err(BuildError::HardwareAddrUnused);
+                }
+                if self.neighbor_cache.is_some() {
+                    return Err(BuildError::NeighborCacheUnused);
+                }
                 (None, None)
             }
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => (
-                Some(
-                    self.hardware_addr
-                        .expect("hardware_addr required option was not set"),
-                ),
+                Some(self.hardware_addr.ok_or(BuildError::HardwareAddrRequired)?),
                 Some(
                     self.neighbor_cache
-                        .expect("neighbor_cache required option was not set"),
+                        .ok_or(BuildError::NeighborCacheRequired)?,
                 ),
             ),
         };
 
+        #[cfg(feature = "proto-ipv4-autoip")]
+        if self.autoip.is_some() && self.acd.is_none() {
+            return Err(BuildError::AcdRequired);
+        }
+        #[cfg(feature = "proto-ipv4-autoip")]
+        let autoip = match (self.autoip, hardware_addr) {
+            (Some(config), Some(HardwareAddress::Ethernet(addr))) => {
+                Some(AutoIp::new(config, addr.as_bytes()))
+            }
+            _ => None,
+        };
+
         #[cfg(feature = "medium-ieee802154")]
         let mut rand = Rand::new(self.random_seed);
         #[cfg(not(feature = "medium-ieee802154"))]
@@ -450,16 +865,17 @@ let iface = builder.finalize(&mut device);
             }
         }
 
-        Interface {
+        #[allow(unused_mut)]
+        let mut iface = Interface {
             fragments: FragmentsBuffer {
                 #[cfg(feature = "proto-ipv4-fragmentation")]
                 ipv4_fragments: self
                     .ipv4_fragments
-                    .expect("Cache for incoming IPv4 fragments is required"),
+                    .ok_or(BuildError::Ipv4FragmentsCacheRequired)?,
                 #[cfg(feature = "proto-sixlowpan-fragmentation")]
                 sixlowpan_fragments: self
                     .sixlowpan_fragments
-                    .expect("Cache for incoming 6LoWPAN fragments is required"),
+                    .ok_or(BuildError::SixlowpanFragmentsCacheRequired)?,
 
                 #[cfg(not(any(
                     feature = "proto-ipv4-fragmentation",
@@ -471,7 +887,7 @@ let iface = builder.finalize(&mut device);
                 #[cfg(feature = "proto-sixlowpan-fragmentation")]
                 sixlowpan_out_packet: SixlowpanOutPacket::new(
                     self.sixlowpan_out_buffer
-                        .expect("Cache for outgoing 6LoWPAN fragments is required"),
+                        .ok_or(BuildError::SixlowpanOutPacketCacheRequired)?,
                 ),
 
                 #[cfg(not(feature = "proto-sixlowpan-fragmentation"))]
@@ -485,6 +901,22 @@ let iface = builder.finalize(&mut device);
                 ip_addrs: self.ip_addrs,
                 #[cfg(feature = "proto-ipv4")]
                 any_ip: self.any_ip,
+                egress_burst: self.egress_burst,
+                mtu: self.mtu,
+                ingress_burst: self.ingress_burst,
+                ingress_pending: false,
+                rx_timestamp: None,
+                tx_timestamp: None,
+                egress_dscp: None,
+                egress_mark: None,
+                egress_priority: None,
+                index: self.index,
+                generation: 0,
+                scheduled_at: Instant::ZERO,
+                #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+                arp_protection: self.arp_protection,
+                unknown_protocol_policy: self.unknown_protocol_policy,
+                parse_mode: self.parse_mode,
                 routes: self.routes,
                 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
                 neighbor_cache,
@@ -492,6 +924,26 @@ let iface = builder.finalize(&mut device);
                 ipv4_multicast_groups: self.ipv4_multicast_groups,
                 #[cfg(feature = "proto-igmp")]
                 igmp_report_state: IgmpReportState::Inactive,
+                #[cfg(feature = "proto-lldp")]
+                lldp: self.lldp.map(Lldp::new),
+                #[cfg(feature = "proto-ipv6-ra")]
+                router_advert: self.router_advert.map(RouterAdvert::new),
+                #[cfg(feature = "proto-ipv4-acd")]
+                acd: self.acd,
+                #[cfg(feature = "proto-ipv4-autoip")]
+                autoip,
+                #[cfg(feature = "proto-ipv6-dad")]
+                dad: self.dad,
+                #[cfg(feature = "proto-ipv6-slaac")]
+                slaac: self.slaac,
+                #[cfg(feature = "proto-ipv6-ra")]
+                ra_dns: RaDns::default(),
+                #[cfg(feature = "proto-wol")]
+                wake_on_lan_received: false,
+                #[cfg(feature = "medium-ethernet")]
+                ethernet_filter_stats: EthernetFilterStats::default(),
+                link_state: LinkState::UP,
+                stats: Stats::default(),
                 #[cfg(feature = "medium-ieee802154")]
                 sequence_no,
                 #[cfg(feature = "medium-ieee802154")]
@@ -500,13 +952,21 @@ let iface = builder.finalize(&mut device);
                 tag,
                 rand,
             },
-        }
+        };
+
+        #[cfg(feature = "proto-ipv4-acd")]
+        iface.inner.sync_acd();
+        #[cfg(feature = "proto-ipv6-dad")]
+        iface.inner.sync_dad();
+
+        Ok(iface)
     }
 }
 
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[cfg(feature = "medium-ethernet")]
+#[allow(clippy::large_enum_variant)]
 enum EthernetPacket<'a> {
     #[cfg(feature = "proto-ipv4")]
     Arp(ArpRepr),
@@ -519,13 +979,19 @@ pub(crate) enum IpPacket<'a> {
     #[cfg(feature = "proto-ipv4")]
     Icmpv4((Ipv4Repr, Icmpv4Repr<'a>)),
     #[cfg(feature = "proto-igmp")]
-    Igmp((Ipv4Repr, IgmpRepr)),
+    Igmp((Ipv4Repr, IgmpRepr<'a>)),
     #[cfg(feature = "proto-ipv6")]
     Icmpv6((Ipv6Repr, Icmpv6Repr<'a>)),
     #[cfg(feature = "socket-raw")]
     Raw((IpRepr, &'a [u8])),
-    #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+    #[cfg(any(
+        feature = "socket-udp",
+        feature = "socket-dns",
+        feature = "socket-sntp"
+    ))]
     Udp((IpRepr, UdpRepr, &'a [u8])),
+    #[cfg(feature = "proto-udplite")]
+    UdpLite((IpRepr, UdpLiteRepr, &'a [u8])),
     #[cfg(feature = "socket-tcp")]
     Tcp((IpRepr, TcpRepr<'a>)),
     #[cfg(feature = "socket-dhcpv4")]
@@ -543,8 +1009,14 @@ impl<'a> IpPacket<'a> {
             IpPacket::Icmpv6((ipv6_repr, _)) => IpRepr::Ipv6(*ipv6_repr),
             #[cfg(feature = "socket-raw")]
             IpPacket::Raw((ip_repr, _)) => ip_repr.clone(),
-            #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+            #[cfg(any(
+                feature = "socket-udp",
+                feature = "socket-dns",
+                feature = "socket-sntp"
+            ))]
             IpPacket::Udp((ip_repr, _, _)) => ip_repr.clone(),
+            #[cfg(feature = "proto-udplite")]
+            IpPacket::UdpLite((ip_repr, _, _)) => ip_repr.clone(),
             #[cfg(feature = "socket-tcp")]
             IpPacket::Tcp((ip_repr, _)) => ip_repr.clone(),
             #[cfg(feature = "socket-dhcpv4")]
@@ -552,6 +1024,35 @@ impl<'a> IpPacket<'a> {
         }
     }
 
+    pub(crate) fn stats_kind(&self) -> PacketKind {
+        match self {
+            #[cfg(feature = "proto-ipv4")]
+            IpPacket::Icmpv4((_, icmpv4_repr)) => {
+                PacketKind::Icmp(IcmpKind::of_icmpv4(icmpv4_repr))
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpPacket::Icmpv6((_, icmpv6_repr)) => {
+                PacketKind::Icmp(IcmpKind::of_icmpv6(icmpv6_repr))
+            }
+            #[cfg(any(
+                feature = "socket-udp",
+                feature = "socket-dns",
+                feature = "socket-sntp"
+            ))]
+            IpPacket::Udp(_) => PacketKind::Udp,
+            #[cfg(feature = "proto-udplite")]
+            IpPacket::UdpLite(_) => PacketKind::Udp,
+            #[cfg(feature = "socket-dhcpv4")]
+            IpPacket::Dhcpv4(_) => PacketKind::Udp,
+            #[cfg(feature = "socket-tcp")]
+            IpPacket::Tcp((_, tcp_repr)) => PacketKind::Tcp {
+                rst: tcp_repr.control == TcpControl::Rst,
+            },
+            #[allow(unreachable_patterns)]
+            _ => PacketKind::Other,
+        }
+    }
+
     pub(crate) fn emit_payload(
         &self,
         _ip_repr: IpRepr,
@@ -576,7 +1077,11 @@ impl<'a> IpPacket<'a> {
             ),
             #[cfg(feature = "socket-raw")]
             IpPacket::Raw((_, raw_packet)) => payload.copy_from_slice(raw_packet),
-            #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+            #[cfg(any(
+                feature = "socket-udp",
+                feature = "socket-dns",
+                feature = "socket-sntp"
+            ))]
             IpPacket::Udp((_, udp_repr, inner_payload)) => udp_repr.emit(
                 &mut UdpPacket::new_unchecked(payload),
                 &_ip_repr.src_addr(),
@@ -585,6 +1090,16 @@ impl<'a> IpPacket<'a> {
                 |buf| buf.copy_from_slice(inner_payload),
                 &caps.checksum,
             ),
+            #[cfg(feature = "proto-udplite")]
+            IpPacket::UdpLite((_, udplite_repr, inner_payload)) => udplite_repr.emit(
+                &mut UdpLitePacket::new_unchecked(
+                    &mut payload[..udplite_repr.header_len() + inner_payload.len()],
+                ),
+                &_ip_repr.src_addr(),
+                &_ip_repr.dst_addr(),
+                |buf| buf.copy_from_slice(inner_payload),
+                &caps.checksum,
+            ),
             #[cfg(feature = "socket-tcp")]
             IpPacket::Tcp((_, mut tcp_repr)) => {
                 // This is a terrible hack to make TCP performance more acceptable on systems
@@ -595,9 +1110,9 @@ impl<'a> IpPacket<'a> {
                 //
                 // I'm really not happy about this "solution" but I don't know what else to do.
                 if let Some(max_burst_size) = caps.max_burst_size {
-                    let mut max_segment_size = caps.max_transmission_unit;
-                    max_segment_size -= _ip_repr.buffer_len();
-                    max_segment_size -= tcp_repr.header_len();
+                    let max_segment_size = caps
+                        .ip_mtu()
+                        .saturating_sub(_ip_repr.header_len() + tcp_repr.header_len());
 
                     let max_window_size = max_burst_size * max_segment_size;
                     if tcp_repr.window_len as usize > max_window_size {
@@ -638,6 +1153,14 @@ fn icmp_reply_payload_len(len: usize, mtu: usize, header_len: usize) -> usize {
     cmp::min(len, mtu - header_len * 2 - 8)
 }
 
+/// Default number of packets a socket may transmit each time it is serviced.
+const DEFAULT_EGRESS_BURST: usize = 8;
+
+/// The size of the buffer IGMPv3 reports are assembled in: at most two group records,
+/// each with the maximum number of sources.
+#[cfg(feature = "proto-igmp")]
+const IGMP_REPORT_BUFFER_LEN: usize = 2 * (8 + 4 * MAX_SOURCES);
+
 #[cfg(feature = "proto-igmp")]
 enum IgmpReportState {
     Inactive,
@@ -702,6 +1225,7 @@ impl<'a> Interface<'a> {
 
         InterfaceInner::check_hardware_addr(&addr);
         self.inner.hardware_addr = Some(addr);
+        self.inner.touch();
     }
 
     /// Add an address to a list of subscribed multicast IP addresses.
@@ -722,23 +1246,7 @@ impl<'a> Interface<'a> {
         match addr.into() {
             #[cfg(feature = "proto-igmp")]
             IpAddress::Ipv4(addr) => {
-                let is_not_new = self
-                    .inner
-                    .ipv4_multicast_groups
-                    .insert(addr, ())
-                    .map_err(|_| Error::Exhausted)?
-                    .is_some();
-                if is_not_new {
-                    Ok(false)
-                } else if let Some(pkt) = self.inner.igmp_report_packet(IgmpVersion::Version2, addr)
-                {
-                    // Send initial membership report
-                    let tx_token = device.transmit().ok_or(Error::Exhausted)?;
-                    self.inner.dispatch_ip(tx_token, pkt, None)?;
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
+                self.join_multicast_group_with_filter(device, addr, SourceFilter::ANY, timestamp)
             }
             // Multicast is not yet implemented for other address families
             #[allow(unreachable_patterns)]
@@ -746,6 +1254,61 @@ impl<'a> Interface<'a> {
         }
     }
 
+    /// Add an address to a list of subscribed multicast IP addresses, only receiving
+    /// traffic from the sources accepted by `filter`, or change the source filter
+    /// of a group that has already been joined.
+    ///
+    /// Joining a group with any source filter other than [`MulticastSourceFilter::ANY`],
+    /// or changing the source filter, sends an IGMPv3 state change report. Joining a group
+    /// without a source filter sends an IGMPv2 membership report, which IGMPv3 routers
+    /// accept as well.
+    ///
+    /// Returns `Ok(announce_sent)` if the address was added successfully, where `annouce_sent`
+    /// indicates whether an immediate report has been sent.
+    ///
+    /// [`MulticastSourceFilter::ANY`]: crate::iface::MulticastSourceFilter::ANY
+    #[cfg(feature = "proto-igmp")]
+    pub fn join_multicast_group_with_filter<D>(
+        &mut self,
+        device: &mut D,
+        addr: Ipv4Address,
+        filter: SourceFilter,
+        timestamp: Instant,
+    ) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        self.inner.now = timestamp;
+
+        let old_filter = self
+            .inner
+            .ipv4_multicast_groups
+            .insert(addr, filter)
+            .map_err(|_| Error::Exhausted)?;
+        let mut buffer = [0; IGMP_REPORT_BUFFER_LEN];
+        let pkt = match old_filter {
+            Some(old_filter) if old_filter == filter => return Ok(false),
+            None if filter.is_any() => {
+                self.inner
+                    .igmp_report_packet(IgmpVersion::Version2, addr, &mut buffer)
+            }
+            old_filter => self.inner.igmp_state_change_packet(
+                addr,
+                &old_filter.unwrap_or(SourceFilter::NONE),
+                &filter,
+                &mut buffer,
+            ),
+        };
+        if let Some(pkt) = pkt {
+            // Send initial membership report
+            let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+            self.inner.dispatch_ip(tx_token, pkt, None)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Remove an address from the subscribed multicast IP addresses.
     ///
     /// Returns `Ok(leave_sent)` if the address was removed successfully, where `leave_sent`
@@ -764,10 +1327,19 @@ impl<'a> Interface<'a> {
         match addr.into() {
             #[cfg(feature = "proto-igmp")]
             IpAddress::Ipv4(addr) => {
-                let was_not_present = self.inner.ipv4_multicast_groups.remove(&addr).is_none();
-                if was_not_present {
-                    Ok(false)
-                } else if let Some(pkt) = self.inner.igmp_leave_packet(addr) {
+                let mut buffer = [0; IGMP_REPORT_BUFFER_LEN];
+                let pkt = match self.inner.ipv4_multicast_groups.remove(&addr) {
+                    None => return Ok(false),
+                    Some(filter) if filter.is_any() => self.inner.igmp_leave_packet(addr),
+                    // Leaving a group with a source filter is a change to INCLUDE {}
+                    Some(filter) => self.inner.igmp_state_change_packet(
+                        addr,
+                        &filter,
+                        &SourceFilter::NONE,
+                        &mut buffer,
+                    ),
+                };
+                if let Some(pkt) = pkt {
                     // Send group leave packet
                     let tx_token = device.transmit().ok_or(Error::Exhausted)?;
                     self.inner.dispatch_ip(tx_token, pkt, None)?;
@@ -787,11 +1359,87 @@ impl<'a> Interface<'a> {
         self.inner.has_multicast_group(addr)
     }
 
+    /// Get the most recently received LLDP advertisement, unless it has expired.
+    ///
+    /// Returns `None` if the LLDP agent is not enabled.
+    #[cfg(feature = "proto-lldp")]
+    pub fn lldp_neighbor(&self) -> Option<&LldpNeighbor> {
+        self.inner
+            .lldp
+            .as_ref()?
+            .neighbor
+            .as_ref()
+            .filter(|neighbor| neighbor.expires_at() > self.inner.now)
+    }
+
+    /// Start or stop acting as an IPv6 router, or change the configuration of the
+    /// router advertisements. See [InterfaceBuilder::router_advertisements].
+    ///
+    /// A new configuration is advertised immediately.
+    #[cfg(feature = "proto-ipv6-ra")]
+    pub fn set_router_advertisements(&mut self, config: Option<RouterAdvertConfig<'a>>) {
+        self.inner.router_advert = config.map(RouterAdvert::new);
+    }
+
+    /// Return whether a Wake-on-LAN magic packet addressed to the hardware address
+    /// of this interface was received since the last call, and clear the indication.
+    ///
+    /// Magic packets are recognized both as Ethernet frames with the Wake-on-LAN
+    /// EtherType, and as UDP datagrams sent to port 9 that are not accepted by any socket.
+    #[cfg(feature = "proto-wol")]
+    pub fn wake_on_lan_received(&mut self) -> bool {
+        core::mem::replace(&mut self.inner.wake_on_lan_received, false)
+    }
+
+    /// Get the counters of the Ethernet frames dropped because they were addressed
+    /// neither to the hardware address of this interface, nor to the broadcast address,
+    /// nor to a multicast address it listens to.
+    #[cfg(feature = "medium-ethernet")]
+    pub fn ethernet_filter_stats(&self) -> EthernetFilterStats {
+        self.inner.ethernet_filter_stats
+    }
+
+    /// Get the state of the link, as reported by the device during the last [poll].
+    ///
+    /// [poll]: #method.poll
+    pub fn link_state(&self) -> LinkState {
+        self.inner.link_state
+    }
+
+    /// Get the management counters of the interface.
+    pub fn stats(&self) -> &Stats {
+        &self.inner.stats
+    }
+
     /// Get the IP addresses of the interface.
     pub fn ip_addrs(&self) -> &[IpCidr] {
         self.inner.ip_addrs.as_ref()
     }
 
+    /// Get the neighbor cache of the interface, if its medium uses one.
+    #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+    pub fn neighbor_cache(&self) -> Option<&NeighborCache<'a>> {
+        self.inner.neighbor_cache.as_ref()
+    }
+
+    /// Iterate over the IPv4 multicast groups the interface joined, and their source filters.
+    #[cfg(feature = "proto-igmp")]
+    pub fn ipv4_multicast_groups(&self) -> impl Iterator<Item = (&Ipv4Address, &SourceFilter)> {
+        self.inner.ipv4_multicast_groups.iter()
+    }
+
+    /// Get the cache of the IPv4 packets being reassembled.
+    #[cfg(feature = "proto-ipv4-fragmentation")]
+    pub fn ipv4_fragments_cache(&self) -> &PacketAssemblerSet<'a, Ipv4FragKey> {
+        &self.fragments.ipv4_fragments
+    }
+
+    /// Get the cache of the 6LoWPAN packets being reassembled.
+    #[cfg(feature = "proto-sixlowpan-fragmentation")]
+    pub fn sixlowpan_fragments_cache(&self) -> &PacketAssemblerSet<'a, SixlowpanFragKey> {
+        &self.fragments.sixlowpan_fragments
+    }
+
     /// Get the first IPv4 address if present.
     #[cfg(feature = "proto-ipv4")]
     pub fn ipv4_addr(&self) -> Option<Ipv4Address> {
@@ -810,8 +1458,103 @@ impl<'a> Interface<'a> {
     /// This function panics if any of the addresses are not unicast.
     pub fn update_ip_addrs<F: FnOnce(&mut ManagedSlice<'a, IpCidr>)>(&mut self, f: F) {
         f(&mut self.inner.ip_addrs);
-        InterfaceInner::flush_cache(&mut self.inner);
-        InterfaceInner::check_ip_addrs(&self.inner.ip_addrs)
+        self.inner.ip_addrs_updated();
+    }
+
+    /// Add an IP address to the interface.
+    ///
+    /// The address takes the place of the first unspecified address; if there is none,
+    /// it is appended if the address storage is owned.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::Exhausted)` if there is no room for the address.
+    ///
+    /// # Panics
+    /// This function panics if the address is not unicast.
+    pub fn add_ip_addr(&mut self, cidr: IpCidr) -> Result<()> {
+        InterfaceInner::insert_ip_addr(&mut self.inner.ip_addrs, cidr)?;
+        self.inner.ip_addrs_updated();
+        Ok(())
+    }
+
+    /// Remove an IP address from the interface.
+    ///
+    /// The address is removed if the address storage is owned, and replaced with
+    /// the unspecified address otherwise. Returns whether the address was found.
+    pub fn remove_ip_addr(&mut self, addr: IpAddress) -> bool {
+        let removed = self.inner.delete_ip_addr(addr);
+        if removed {
+            self.inner.ip_addrs_updated();
+        }
+        removed
+    }
+
+    /// Return the Address Conflict Detection state of an IPv4 address of the interface.
+    ///
+    /// Returns `None` if Address Conflict Detection is not enabled, or the address is not
+    /// an address of the interface it applies to.
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub fn ipv4_addr_state(&self, addr: Ipv4Address) -> Option<Ipv4AddressState> {
+        self.inner.ipv4_addr_state(&addr)
+    }
+
+    /// Return the link-local IPv4 address configured by autoconfiguration, if any.
+    #[cfg(feature = "proto-ipv4-autoip")]
+    pub fn autoip_addr(&self) -> Option<Ipv4Address> {
+        self.inner
+            .autoip
+            .as_ref()
+            .and_then(|autoip| autoip.address())
+    }
+
+    /// Return the IPv4 addresses of the interface found to be used by another node.
+    ///
+    /// Such addresses are not used, and should be removed from the interface.
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub fn conflicting_ipv4_addrs(&self) -> impl Iterator<Item = Ipv4Address> + '_ {
+        self.inner.acd.iter().flat_map(|acd| acd.conflicts())
+    }
+
+    /// Return the Duplicate Address Detection state of an IPv6 address of the interface.
+    ///
+    /// Returns `None` if Duplicate Address Detection is not enabled, or the address is not
+    /// an address of the interface it applies to.
+    #[cfg(feature = "proto-ipv6-dad")]
+    pub fn ipv6_addr_state(&self, addr: Ipv6Address) -> Option<Ipv6AddressState> {
+        self.inner.ipv6_addr_state(&addr)
+    }
+
+    /// Return the IPv6 addresses of the interface found to be used by another node.
+    ///
+    /// Such addresses are not used, and should be removed from the interface.
+    #[cfg(feature = "proto-ipv6-dad")]
+    pub fn duplicate_ipv6_addrs(&self) -> impl Iterator<Item = Ipv6Address> + '_ {
+        self.inner.dad.iter().flat_map(|dad| dad.duplicates())
+    }
+
+    /// Return the IPv6 addresses of the interface formed by Stateless Address
+    /// Autoconfiguration, and their lifetimes.
+    #[cfg(feature = "proto-ipv6-slaac")]
+    pub fn slaac_addrs(&self) -> impl Iterator<Item = (Ipv6Address, SlaacEntry)> + '_ {
+        self.inner.slaac.iter().flat_map(|slaac| slaac.entries())
+    }
+
+    /// Return the IPv6 DNS servers advertised by routers, as specified in RFC 8106.
+    ///
+    /// They are recorded from the received Router Advertisements whether or not
+    /// [`InterfaceBuilder::ipv6_slaac()`] is used. DNS sockets without configured
+    /// servers use these servers.
+    ///
+    /// [`InterfaceBuilder::ipv6_slaac()`]: struct.InterfaceBuilder.html#method.ipv6_slaac
+    #[cfg(feature = "proto-ipv6-ra")]
+    pub fn ipv6_dns_servers(&self) -> impl Iterator<Item = Ipv6Address> + '_ {
+        self.inner.ipv6_dns_servers()
+    }
+
+    /// Return the DNS search domains advertised by routers, as specified in RFC 8106.
+    #[cfg(feature = "proto-ipv6-ra")]
+    pub fn ipv6_dns_search_list(&self) -> impl Iterator<Item = &str> + '_ {
+        self.inner.ra_dns.domains(self.inner.now)
     }
 
     /// Check whether the interface has the given IP address assigned.
@@ -825,17 +1568,96 @@ impl<'a> Interface<'a> {
         self.inner.ipv4_address()
     }
 
+    /// Get the routing table of the interface.
     pub fn routes(&self) -> &Routes<'a> {
         &self.inner.routes
     }
 
+    /// Get the routing table of the interface, to change the routes at runtime.
     pub fn routes_mut(&mut self) -> &mut Routes<'a> {
+        self.inner.touch();
         &mut self.inner.routes
     }
 
-    /// Transmit packets queued in the given sockets, and receive packets queued
-    /// in the device.
-    ///
+    /// Get the index identifying the interface. See [InterfaceBuilder::index].
+    pub fn index(&self) -> u32 {
+        self.inner.index
+    }
+
+    /// Get how ARP packets may change the hardware address of neighbors.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn arp_protection(&self) -> ArpProtection {
+        self.inner.arp_protection
+    }
+
+    /// Set how ARP packets may change the hardware address of neighbors.
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    pub fn set_arp_protection(&mut self, protection: ArpProtection) {
+        self.inner.arp_protection = protection;
+    }
+
+    /// Get what the interface does with IP packets whose protocol it has no handler for.
+    pub fn unknown_protocol_policy(&self) -> UnknownProtocolPolicy {
+        self.inner.unknown_protocol_policy
+    }
+
+    /// Set what the interface does with IP packets whose protocol it has no handler for.
+    pub fn set_unknown_protocol_policy(&mut self, policy: UnknownProtocolPolicy) {
+        self.inner.unknown_protocol_policy = policy;
+    }
+
+    /// Get how strictly the headers of received packets are checked.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.inner.parse_mode
+    }
+
+    /// Set how strictly the headers of received packets are checked.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.inner.parse_mode = mode;
+    }
+
+    /// Get the maximum number of packets a socket may transmit each time it is serviced.
+    pub fn egress_burst(&self) -> usize {
+        self.inner.egress_burst
+    }
+
+    /// Set the maximum number of packets a socket may transmit each time it is serviced.
+    /// See [InterfaceBuilder::egress_burst].
+    pub fn set_egress_burst(&mut self, burst: usize) {
+        self.inner.egress_burst = burst;
+    }
+
+    /// Get the IP MTU of the interface, which is the IP MTU of the device unless a lower
+    /// one was set.
+    pub fn ip_mtu(&self) -> usize {
+        self.inner.ip_mtu()
+    }
+
+    /// Set the IP MTU of the interface, or reset it to the one of the device with `None`.
+    /// See [InterfaceBuilder::ip_mtu].
+    pub fn set_ip_mtu(&mut self, mtu: Option<usize>) {
+        self.inner.mtu = mtu;
+        self.inner.touch();
+    }
+
+    /// Get the maximum number of frames received from the device each time [poll] is called.
+    ///
+    /// [poll]: #method.poll
+    pub fn ingress_burst(&self) -> Option<usize> {
+        self.inner.ingress_burst
+    }
+
+    /// Set the maximum number of frames received from the device each time [poll] is called.
+    /// See [InterfaceBuilder::ingress_burst].
+    ///
+    /// [poll]: #method.poll
+    pub fn set_ingress_burst(&mut self, burst: Option<usize>) {
+        self.inner.ingress_burst = burst;
+    }
+
+    /// Transmit packets queued in the given sockets, and receive packets queued
+    /// in the device.
+    ///
     /// This function returns a boolean value indicating whether any packets were
     /// processed or emitted, and thus, whether the readiness of any socket might
     /// have changed.
@@ -850,6 +1672,16 @@ impl<'a> Interface<'a> {
     /// packets containing any unsupported protocol, option, or form, which is
     /// a very common occurrence and on a production system it should not even
     /// be logged.
+    ///
+    /// While the [link state] of the device is down, no packets are processed or emitted.
+    /// When the link goes down, the neighbor cache is flushed; when it comes back up,
+    /// the uniqueness of the IPv6 addresses is verified again.
+    ///
+    /// Receiving and transmitting can also be done separately, with [ingress] and [egress].
+    ///
+    /// [link state]: ../phy/trait.Device.html#method.link_state
+    /// [ingress]: #method.ingress
+    /// [egress]: #method.egress
     pub fn poll<D>(
         &mut self,
         timestamp: Instant,
@@ -859,24 +1691,8 @@ impl<'a> Interface<'a> {
     where
         D: for<'d> Device<'d>,
     {
-        self.inner.now = timestamp;
-
-        #[cfg(feature = "proto-ipv4-fragmentation")]
-        if let Err(e) = self
-            .fragments
-            .ipv4_fragments
-            .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))
-        {
-            return Err(e);
-        }
-
-        #[cfg(feature = "proto-sixlowpan-fragmentation")]
-        if let Err(e) = self
-            .fragments
-            .sixlowpan_fragments
-            .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))
-        {
-            return Err(e);
+        if !self.poll_begin(timestamp, device)? {
+            return Ok(false);
         }
 
         #[cfg(feature = "proto-sixlowpan-fragmentation")]
@@ -887,13 +1703,13 @@ impl<'a> Interface<'a> {
         }
 
         let mut readiness_may_have_changed = false;
+        let mut ingress_budget = self.inner.ingress_burst;
+        self.inner.ingress_pending = false;
 
         loop {
-            let processed_any = self.socket_ingress(device, sockets);
+            let processed_any = self.socket_ingress(device, sockets, &mut ingress_budget);
             let emitted_any = self.socket_egress(device, sockets);
-
-            #[cfg(feature = "proto-igmp")]
-            self.igmp_egress(device)?;
+            self.protocol_egress(device)?;
 
             if processed_any || emitted_any {
                 readiness_may_have_changed = true;
@@ -901,36 +1717,258 @@ impl<'a> Interface<'a> {
                 break;
             }
         }
+        self.schedule_sockets(sockets);
 
         Ok(readiness_may_have_changed)
     }
 
+    /// Receive the packets queued in the device, up to the [ingress burst], and process
+    /// them, without transmitting the packets queued in the sockets.
+    ///
+    /// This is the receiving half of [poll], meant to be called as soon as frames arrive,
+    /// for example from an interrupt handler, while a lower priority task calls [egress].
+    /// Responses generated by the interface itself, such as ARP replies, ICMP errors or
+    /// TCP resets, are still transmitted right away.
+    ///
+    /// Returns whether any packet was processed, and thus, whether the readiness of
+    /// any socket might have changed. Errors are as for [poll].
+    ///
+    /// [ingress burst]: #method.set_ingress_burst
+    /// [poll]: #method.poll
+    /// [egress]: #method.egress
+    pub fn ingress<D>(
+        &mut self,
+        timestamp: Instant,
+        device: &mut D,
+        sockets: &mut SocketSet<'_>,
+    ) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        if !self.poll_begin(timestamp, device)? {
+            return Ok(false);
+        }
+
+        let mut ingress_budget = self.inner.ingress_burst;
+        self.inner.ingress_pending = false;
+        let processed_any = self.socket_ingress(device, sockets, &mut ingress_budget);
+        self.schedule_sockets(sockets);
+        Ok(processed_any)
+    }
+
+    /// Transmit the packets queued in the sockets, along with the packets of the protocols
+    /// run by the interface, without receiving the packets queued in the device.
+    ///
+    /// This is the transmitting half of [poll]; see also [ingress].
+    ///
+    /// Returns whether any packet was emitted, and thus, whether the readiness of
+    /// any socket might have changed. Errors are as for [poll].
+    ///
+    /// [poll]: #method.poll
+    /// [ingress]: #method.ingress
+    pub fn egress<D>(
+        &mut self,
+        timestamp: Instant,
+        device: &mut D,
+        sockets: &mut SocketSet<'_>,
+    ) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        if !self.poll_begin(timestamp, device)? {
+            return Ok(false);
+        }
+
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        match self.sixlowpan_egress(device) {
+            Ok(true) => return Ok(true),
+            Err(e) => return Err(e),
+            _ => (),
+        }
+
+        let emitted_any = self.socket_egress(device, sockets);
+        self.protocol_egress(device)?;
+        self.schedule_sockets(sockets);
+        Ok(emitted_any)
+    }
+
+    /// Compute the deadlines of the sockets that changed while polling, so that [poll_at]
+    /// does not have to.
+    ///
+    /// [poll_at]: #method.poll_at
+    fn schedule_sockets(&mut self, sockets: &mut SocketSet<'_>) {
+        let inner = &mut self.inner;
+        inner.expire_routes();
+        let now = inner.now;
+        sockets.schedule((inner.index, inner.generation), now, |item| {
+            inner.socket_poll_at(item)
+        });
+        inner.scheduled_at = now;
+    }
+
+    /// Update the link state and expire the timers shared by [poll], [ingress] and
+    /// [egress]. Returns whether the link is up.
+    ///
+    /// [poll]: #method.poll
+    /// [ingress]: #method.ingress
+    /// [egress]: #method.egress
+    fn poll_begin<D>(&mut self, timestamp: Instant, device: &mut D) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        self.inner.now = timestamp;
+
+        let link_state = device.link_state();
+        if link_state.is_up() != self.inner.link_state.is_up() {
+            self.inner.touch();
+            if link_state.is_up() {
+                net_debug!("iface: link up");
+                #[cfg(feature = "proto-ipv4-acd")]
+                if let Some(acd) = self.inner.acd.as_mut() {
+                    acd.restart(timestamp, &mut self.inner.rand);
+                }
+                #[cfg(feature = "proto-ipv6-dad")]
+                if let Some(dad) = self.inner.dad.as_mut() {
+                    dad.restart(timestamp);
+                }
+            } else {
+                net_debug!("iface: link down");
+                self.inner.flush_cache();
+            }
+        }
+        self.inner.link_state = link_state;
+        if !link_state.is_up() {
+            return Ok(false);
+        }
+
+        #[cfg(feature = "proto-ipv4-autoip")]
+        self.inner.sync_autoip();
+
+        #[cfg(feature = "proto-ipv6-slaac")]
+        self.inner.sync_slaac();
+
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        {
+            let stats = &mut self.inner.stats;
+            self.fragments.ipv4_fragments.remove_when(|frag| {
+                let expired = timestamp >= frag.expires_at()?;
+                if expired {
+                    stats.ip.reasm_fails += 1;
+                }
+                Ok(expired)
+            })?;
+        }
+
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        if let Err(e) = self
+            .fragments
+            .sixlowpan_fragments
+            .remove_when(|frag| Ok(timestamp >= frag.expires_at()?))
+        {
+            return Err(e);
+        }
+
+        Ok(true)
+    }
+
+    /// Transmit the packets of the protocols run by the interface itself.
+    fn protocol_egress<D>(&mut self, _device: &mut D) -> Result<()>
+    where
+        D: for<'d> Device<'d>,
+    {
+        #[cfg(feature = "proto-igmp")]
+        self.igmp_egress(_device)?;
+
+        #[cfg(feature = "proto-lldp")]
+        self.lldp_egress(_device)?;
+
+        #[cfg(feature = "proto-ipv6-ra")]
+        self.router_advert_egress(_device)?;
+
+        #[cfg(feature = "proto-ipv4-acd")]
+        self.acd_egress(_device)?;
+
+        #[cfg(feature = "proto-ipv6-dad")]
+        self.dad_egress(_device)?;
+
+        Ok(())
+    }
+
     /// Return a _soft deadline_ for calling [poll] the next time.
     /// The [Instant] returned is the time at which you should call [poll] next.
     /// It is harmless (but wastes energy) to call it before the [Instant], and
     /// potentially harmful (impacting quality of service) to call it after the
     /// [Instant]
     ///
+    /// While the link is down, timers are paused, and `None` is returned.
+    ///
     /// [poll]: #method.poll
     /// [Instant]: struct.Instant.html
     pub fn poll_at(&mut self, timestamp: Instant, sockets: &SocketSet<'_>) -> Option<Instant> {
         self.inner.now = timestamp;
 
+        if !self.inner.link_state.is_up() {
+            return None;
+        }
+
         let inner = &mut self.inner;
 
-        sockets
-            .items()
-            .filter_map(move |item| {
-                let socket_poll_at = item.socket.poll_at(inner);
-                match item
-                    .meta
-                    .poll_at(socket_poll_at, |ip_addr| inner.has_neighbor(&ip_addr))
-                {
-                    PollAt::Ingress => None,
-                    PollAt::Time(instant) => Some(instant),
-                    PollAt::Now => Some(Instant::from_millis(0)),
-                }
-            })
+        // Frames left in the device by a limited ingress burst should be received right away.
+        let ingress_poll_at = if inner.ingress_pending {
+            Some(Instant::from_millis(0))
+        } else {
+            None
+        };
+
+        #[cfg(feature = "proto-lldp")]
+        let lldp_poll_at = inner.lldp.as_ref().map(|lldp| lldp.next_advert);
+        #[cfg(not(feature = "proto-lldp"))]
+        let lldp_poll_at = None;
+
+        #[cfg(feature = "proto-ipv6-ra")]
+        let router_advert_poll_at = inner.router_advert.as_ref().map(|ra| ra.next_advert);
+        #[cfg(not(feature = "proto-ipv6-ra"))]
+        let router_advert_poll_at = None;
+
+        #[cfg(feature = "proto-ipv4-acd")]
+        let acd_poll_at = inner.acd.as_ref().and_then(|acd| acd.poll_at());
+        #[cfg(not(feature = "proto-ipv4-acd"))]
+        let acd_poll_at = None;
+
+        #[cfg(feature = "proto-ipv4-autoip")]
+        let autoip_poll_at = inner.autoip.as_ref().and_then(|autoip| autoip.poll_at());
+        #[cfg(not(feature = "proto-ipv4-autoip"))]
+        let autoip_poll_at = None;
+
+        #[cfg(feature = "proto-ipv6-dad")]
+        let dad_poll_at = inner.dad.as_ref().and_then(|dad| dad.poll_at());
+        #[cfg(not(feature = "proto-ipv6-dad"))]
+        let dad_poll_at = None;
+
+        #[cfg(feature = "proto-ipv6-slaac")]
+        let slaac_poll_at = inner
+            .slaac
+            .as_ref()
+            .and_then(|slaac| slaac.poll_at(timestamp));
+        #[cfg(not(feature = "proto-ipv6-slaac"))]
+        let slaac_poll_at = None;
+
+        // Only the sockets that changed since the last poll are visited, unless the
+        // interface changed too.
+        inner.expire_routes();
+        let sockets_poll_at = sockets.poll_at((inner.index, inner.generation), |item| {
+            inner.socket_poll_at(item)
+        });
+
+        sockets_poll_at
+            .into_iter()
+            .chain(ingress_poll_at)
+            .chain(lldp_poll_at)
+            .chain(router_advert_poll_at)
+            .chain(acd_poll_at)
+            .chain(autoip_poll_at)
+            .chain(dad_poll_at)
+            .chain(slaac_poll_at)
             .min()
     }
 
@@ -950,7 +1988,60 @@ impl<'a> Interface<'a> {
         }
     }
 
-    fn socket_ingress<D>(&mut self, device: &mut D, sockets: &mut SocketSet<'_>) -> bool
+    /// Transmit an Ethernet frame with the given destination, EtherType and payload,
+    /// without going through a socket.
+    ///
+    /// This is meant for the occasional frame of a protocol the stack does not implement,
+    /// e.g. a vendor discovery protocol. The frame is sent from the hardware address of
+    /// the interface right away, regardless of the sockets, and is counted in the interface
+    /// statistics like any other frame.
+    ///
+    /// This function returns `Err(Error::Illegal)` if the interface does not use
+    /// an Ethernet medium or its link is down, `Err(Error::Truncated)` if the frame does not
+    /// fit into the maximum transmission unit of the device, and `Err(Error::Exhausted)`
+    /// if the device has no transmit buffer available.
+    #[cfg(feature = "medium-ethernet")]
+    pub fn transmit_raw_frame<D>(
+        &mut self,
+        timestamp: Instant,
+        device: &mut D,
+        dst_addr: EthernetAddress,
+        ethertype: EthernetProtocol,
+        payload: &[u8],
+    ) -> Result<()>
+    where
+        D: for<'d> Device<'d>,
+    {
+        if self.inner.caps.medium != Medium::Ethernet || !device.link_state().is_up() {
+            return Err(Error::Illegal);
+        }
+        if EthernetFrame::<&[u8]>::buffer_len(payload.len()) > self.inner.caps.max_transmission_unit
+        {
+            return Err(Error::Truncated);
+        }
+
+        self.inner.now = timestamp;
+        let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+        net_debug!(
+            "iface: transmitting raw frame to {} ({}, {} octets)",
+            dst_addr,
+            ethertype,
+            payload.len()
+        );
+        self.inner
+            .dispatch_ethernet(tx_token, payload.len(), |mut frame| {
+                frame.set_dst_addr(dst_addr);
+                frame.set_ethertype(ethertype);
+                frame.payload_mut().copy_from_slice(payload);
+            })
+    }
+
+    fn socket_ingress<D>(
+        &mut self,
+        device: &mut D,
+        sockets: &mut SocketSet<'_>,
+        budget: &mut Option<usize>,
+    ) -> bool
     where
         D: for<'d> Device<'d>,
     {
@@ -961,8 +2052,30 @@ impl<'a> Interface<'a> {
             out_packets: _out_packets,
         } = self;
 
-        while let Some((rx_token, tx_token)) = device.receive() {
+        loop {
+            match budget {
+                Some(0) => {
+                    inner.ingress_pending = true;
+                    break;
+                }
+                Some(budget) => *budget -= 1,
+                None => (),
+            }
+            let (rx_token, tx_token) = match device.receive() {
+                Some(tokens) => tokens,
+                None => break,
+            };
+            let meta = rx_token.meta();
+            inner.rx_timestamp = meta.timestamp;
+            // Skip verifying the checksums the device has already verified.
+            let checksum = if meta.checksum_verified {
+                let tx_only = inner.caps.checksum.tx_only();
+                Some(mem::replace(&mut inner.caps.checksum, tx_only))
+            } else {
+                None
+            };
             let res = rx_token.consume(inner.now, |frame| {
+                inner.stats.interface.in_octets += frame.len() as u64;
                 match inner.caps.medium {
                     #[cfg(feature = "medium-ethernet")]
                     Medium::Ethernet => {
@@ -995,6 +2108,10 @@ impl<'a> Interface<'a> {
                 processed_any = true;
                 Ok(())
             });
+            if let Some(checksum) = checksum {
+                inner.caps.checksum = checksum;
+            }
+            inner.rx_timestamp = None;
 
             if let Err(err) = res {
                 net_debug!("Failed to consume RX token: {}", err);
@@ -1013,100 +2130,167 @@ impl<'a> Interface<'a> {
             out_packets: _out_packets,
             ..
         } = self;
-        let _caps = device.capabilities();
+        let caps = device.capabilities();
+        let burst = match caps.max_burst_size {
+            Some(max_burst_size) => inner.egress_burst.min(max_burst_size),
+            None => inner.egress_burst,
+        }
+        .max(1);
 
         let mut emitted_any = false;
-        for item in sockets.items_mut() {
-            if !item
-                .meta
-                .egress_permitted(inner.now, |ip_addr| inner.has_neighbor(&ip_addr))
-            {
-                continue;
-            }
+        let mut stopped = false;
+        // Service the sockets in strict priority order.
+        for &priority in SocketPriority::ALL.iter() {
+            // Only the sockets that transmitted or failed to are scheduled again, along with
+            // the ones that were due.
+            sockets.for_each_mut(|item| {
+                if stopped || item.meta.priority != priority {
+                    return false;
+                }
+                inner.egress_mark = item.meta.mark;
+                if !item
+                    .meta
+                    .egress_permitted(inner.now, |ip_addr| inner.has_neighbor(&ip_addr))
+                {
+                    return false;
+                }
+                inner.egress_dscp = item.meta.dscp;
+                inner.egress_priority = match priority.pcp() {
+                    0 => None,
+                    pcp => Some(pcp),
+                };
 
-            let mut neighbor_addr = None;
-            let mut respond = |inner: &mut InterfaceInner, response: IpPacket| {
-                neighbor_addr = Some(response.ip_repr().dst_addr());
-                match device.transmit().ok_or(Error::Exhausted) {
-                    Ok(_t) => {
-                        #[cfg(feature = "proto-sixlowpan-fragmentation")]
-                        if let Err(_e) = inner.dispatch_ip(_t, response, Some(_out_packets)) {
-                            net_debug!("failed to dispatch IP: {}", _e);
-                        }
+                // Let the socket transmit until it has nothing left to send, or its burst
+                // is used up; the remaining packets are sent the next time it is serviced.
+                let mut changed = false;
+                for _ in 0..burst {
+                    let mut emitted = false;
+                    let mut neighbor_addr = None;
+                    let mut respond = |inner: &mut InterfaceInner, response: IpPacket| {
+                        neighbor_addr = Some(response.ip_repr().dst_addr());
+                        match device.transmit().ok_or(Error::Exhausted) {
+                            Ok(_t) => {
+                                #[cfg(feature = "proto-sixlowpan-fragmentation")]
+                                if let Err(_e) = inner.dispatch_ip(_t, response, Some(_out_packets))
+                                {
+                                    net_debug!("failed to dispatch IP: {}", _e);
+                                }
 
-                        #[cfg(not(feature = "proto-sixlowpan-fragmentation"))]
-                        if let Err(_e) = inner.dispatch_ip(_t, response, None) {
-                            net_debug!("failed to dispatch IP: {}", _e);
+                                #[cfg(not(feature = "proto-sixlowpan-fragmentation"))]
+                                if let Err(_e) = inner.dispatch_ip(_t, response, None) {
+                                    net_debug!("failed to dispatch IP: {}", _e);
+                                }
+                                emitted = true;
+                            }
+                            Err(e) => {
+                                net_debug!("failed to transmit IP: {}", e);
+                            }
                         }
-                        emitted_any = true;
-                    }
-                    Err(e) => {
-                        net_debug!("failed to transmit IP: {}", e);
-                    }
-                }
 
-                Ok(())
-            };
+                        Ok(())
+                    };
 
-            let result = match &mut item.socket {
-                #[cfg(feature = "socket-raw")]
-                Socket::Raw(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Raw(response))
-                }),
-                #[cfg(feature = "socket-icmp")]
-                Socket::Icmp(socket) => socket.dispatch(inner, |inner, response| match response {
-                    #[cfg(feature = "proto-ipv4")]
-                    (IpRepr::Ipv4(ipv4_repr), IcmpRepr::Ipv4(icmpv4_repr)) => {
-                        respond(inner, IpPacket::Icmpv4((ipv4_repr, icmpv4_repr)))
-                    }
-                    #[cfg(feature = "proto-ipv6")]
-                    (IpRepr::Ipv6(ipv6_repr), IcmpRepr::Ipv6(icmpv6_repr)) => {
-                        respond(inner, IpPacket::Icmpv6((ipv6_repr, icmpv6_repr)))
+                    let result = match &mut item.socket {
+                        #[cfg(feature = "socket-raw")]
+                        Socket::Raw(socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Raw(response))
+                        }),
+                        #[cfg(feature = "socket-icmp")]
+                        Socket::Icmp(socket) => {
+                            socket.dispatch(inner, |inner, response| match response {
+                                #[cfg(feature = "proto-ipv4")]
+                                (IpRepr::Ipv4(ipv4_repr), IcmpRepr::Ipv4(icmpv4_repr)) => {
+                                    respond(inner, IpPacket::Icmpv4((ipv4_repr, icmpv4_repr)))
+                                }
+                                #[cfg(feature = "proto-ipv6")]
+                                (IpRepr::Ipv6(ipv6_repr), IcmpRepr::Ipv6(icmpv6_repr)) => {
+                                    respond(inner, IpPacket::Icmpv6((ipv6_repr, icmpv6_repr)))
+                                }
+                                #[allow(unreachable_patterns)]
+                                _ => unreachable!(),
+                            })
+                        }
+                        #[cfg(all(feature = "socket-udp", not(feature = "proto-udplite")))]
+                        Socket::Udp(socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Udp(response))
+                        }),
+                        #[cfg(feature = "proto-udplite")]
+                        Socket::Udp(socket) => {
+                            let checksum_coverage = socket.checksum_coverage();
+                            socket.dispatch(inner, |inner, response| match checksum_coverage {
+                                Some(checksum_coverage) => {
+                                    let (ip_repr, udp_repr, payload) = response;
+                                    let udplite_repr = UdpLiteRepr {
+                                        src_port: udp_repr.src_port,
+                                        dst_port: udp_repr.dst_port,
+                                        checksum_coverage,
+                                    };
+                                    respond(
+                                        inner,
+                                        IpPacket::UdpLite((ip_repr, udplite_repr, payload)),
+                                    )
+                                }
+                                None => respond(inner, IpPacket::Udp(response)),
+                            })
+                        }
+                        #[cfg(feature = "socket-tcp")]
+                        Socket::Tcp(socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Tcp(response))
+                        }),
+                        #[cfg(feature = "socket-dhcpv4")]
+                        Socket::Dhcpv4(socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Dhcpv4(response))
+                        }),
+                        #[cfg(feature = "socket-dns")]
+                        Socket::Dns(ref mut socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Udp(response))
+                        }),
+                        #[cfg(feature = "socket-sntp")]
+                        Socket::Sntp(socket) => socket.dispatch(inner, |inner, response| {
+                            respond(inner, IpPacket::Udp(response))
+                        }),
+                    };
+
+                    match result {
+                        Err(Error::Exhausted) => {
+                            // Device buffer full.
+                            stopped = true;
+                            return true;
+                        }
+                        Err(Error::Unaddressable) => {
+                            // `NeighborCache` already takes care of rate limiting the neighbor discovery
+                            // requests from the socket. However, without an additional rate limiting
+                            // mechanism, we would spin on every socket that has yet to discover its
+                            // neighbor.
+                            item.meta.neighbor_missing(
+                                inner.now,
+                                neighbor_addr.expect("non-IP response packet"),
+                            );
+                            stopped = true;
+                            return true;
+                        }
+                        Err(err) => {
+                            net_debug!("{}: cannot dispatch egress packet: {}", item.meta, err);
+                            changed = true;
+                        }
+                        Ok(()) => {}
                     }
-                    #[allow(unreachable_patterns)]
-                    _ => unreachable!(),
-                }),
-                #[cfg(feature = "socket-udp")]
-                Socket::Udp(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
-                #[cfg(feature = "socket-tcp")]
-                Socket::Tcp(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Tcp(response))
-                }),
-                #[cfg(feature = "socket-dhcpv4")]
-                Socket::Dhcpv4(socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Dhcpv4(response))
-                }),
-                #[cfg(feature = "socket-dns")]
-                Socket::Dns(ref mut socket) => socket.dispatch(inner, |inner, response| {
-                    respond(inner, IpPacket::Udp(response))
-                }),
-            };
 
-            match result {
-                Err(Error::Exhausted) => break, // Device buffer full.
-                Err(Error::Unaddressable) => {
-                    // `NeighborCache` already takes care of rate limiting the neighbor discovery
-                    // requests from the socket. However, without an additional rate limiting
-                    // mechanism, we would spin on every socket that has yet to discover its
-                    // neighbor.
-                    item.meta.neighbor_missing(
-                        inner.now,
-                        neighbor_addr.expect("non-IP response packet"),
-                    );
-                    break;
-                }
-                Err(err) => {
-                    net_debug!(
-                        "{}: cannot dispatch egress packet: {}",
-                        item.meta.handle,
-                        err
-                    );
+                    if !emitted {
+                        break;
+                    }
+                    emitted_any = true;
+                    changed = true;
                 }
-                Ok(()) => {}
+                changed
+            });
+            if stopped {
+                break;
             }
         }
+        inner.egress_dscp = None;
+        inner.egress_priority = None;
+        inner.egress_mark = None;
         emitted_any
     }
 
@@ -1123,7 +2307,8 @@ impl<'a> Interface<'a> {
                 timeout,
                 group,
             } if self.inner.now >= timeout => {
-                if let Some(pkt) = self.inner.igmp_report_packet(version, group) {
+                let mut buffer = [0; IGMP_REPORT_BUFFER_LEN];
+                if let Some(pkt) = self.inner.igmp_report_packet(version, group, &mut buffer) {
                     // Send initial membership report
                     let tx_token = device.transmit().ok_or(Error::Exhausted)?;
                     self.inner.dispatch_ip(tx_token, pkt, None)?;
@@ -1143,11 +2328,13 @@ impl<'a> Interface<'a> {
                     .ipv4_multicast_groups
                     .iter()
                     .nth(next_index)
-                    .map(|(addr, ())| *addr);
+                    .map(|(addr, _)| *addr);
 
                 match addr {
                     Some(addr) => {
-                        if let Some(pkt) = self.inner.igmp_report_packet(version, addr) {
+                        let mut buffer = [0; IGMP_REPORT_BUFFER_LEN];
+                        if let Some(pkt) = self.inner.igmp_report_packet(version, addr, &mut buffer)
+                        {
                             // Send initial membership report
                             let tx_token = device.transmit().ok_or(Error::Exhausted)?;
                             self.inner.dispatch_ip(tx_token, pkt, None)?;
@@ -1173,20 +2360,196 @@ impl<'a> Interface<'a> {
         }
     }
 
-    #[cfg(feature = "proto-sixlowpan-fragmentation")]
-    fn sixlowpan_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    /// Send an LLDP advertisement if the advertisement interval has elapsed.
+    #[cfg(feature = "proto-lldp")]
+    fn lldp_egress<D>(&mut self, device: &mut D) -> Result<bool>
     where
         D: for<'d> Device<'d>,
     {
-        let SixlowpanOutPacket {
-            packet_len,
-            sent_bytes,
-            ..
-        } = &self.out_packets.sixlowpan_out_packet;
+        let config = match self.inner.lldp {
+            Some(ref lldp) if self.inner.now >= lldp.next_advert => lldp.config,
+            _ => return Ok(false),
+        };
+        let hardware_addr = match self.inner.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) => addr,
+            _ => return Ok(false),
+        };
 
-        if *packet_len == 0 {
-            return Ok(false);
-        }
+        let repr = LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: hardware_addr.as_bytes(),
+            port_id_subtype: config.port_id_subtype,
+            port_id: config.port_id,
+            ttl: config.ttl(),
+            port_description: config.port_description,
+            system_name: config.system_name,
+            system_description: config.system_description,
+            system_capabilities: Some((config.capabilities, config.capabilities)),
+        };
+
+        let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+        self.inner
+            .dispatch_ethernet(tx_token, repr.buffer_len(), |mut frame| {
+                frame.set_dst_addr(LLDP_MULTICAST_ADDR);
+                frame.set_ethertype(EthernetProtocol::Lldp);
+                repr.emit(&mut LldpPacket::new_unchecked(frame.payload_mut()));
+            })?;
+
+        if let Some(lldp) = self.inner.lldp.as_mut() {
+            lldp.next_advert = self.inner.now + config.interval;
+        }
+        Ok(true)
+    }
+
+    /// Send router advertisements if the advertisement interval has elapsed, or
+    /// a router solicitation was received.
+    #[cfg(feature = "proto-ipv6-ra")]
+    fn router_advert_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        let config = match self.inner.router_advert {
+            Some(ref ra) if self.inner.now >= ra.next_advert => ra.config,
+            _ => return Ok(false),
+        };
+
+        // Router advertisements must be sent from a link-local address.
+        let src_addr = self
+            .inner
+            .ip_addrs
+            .iter()
+            .find_map(|cidr| match cidr.address() {
+                IpAddress::Ipv6(addr) if addr.is_link_local() => Some(addr),
+                _ => None,
+            });
+        #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+        let lladdr = self.inner.hardware_addr.map(|addr| addr.into());
+        #[cfg(not(any(feature = "medium-ethernet", feature = "medium-ieee802154")))]
+        let lladdr = None;
+
+        let mut emitted_any = false;
+        if let Some(src_addr) = src_addr {
+            // The wire format supports one prefix per advertisement, so each prefix is
+            // sent in a separate one.
+            for index in 0..config.prefixes.len().max(1) {
+                let advert = Icmpv6Repr::Ndisc(NdiscRepr::RouterAdvert {
+                    hop_limit: config.hop_limit,
+                    flags: config.flags,
+                    preference: config.preference,
+                    router_lifetime: config.router_lifetime,
+                    reachable_time: config.reachable_time,
+                    retrans_time: config.retrans_time,
+                    lladdr,
+                    mtu: config.mtu,
+                    prefix_info: config.prefixes.get(index).cloned(),
+                    route_info: None,
+                    rdnss: None,
+                    dnssl: None,
+                });
+                let ip_repr = Ipv6Repr {
+                    src_addr,
+                    dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+                    next_header: IpProtocol::Icmpv6,
+                    hop_limit: 0xff,
+                    payload_len: advert.buffer_len(),
+                };
+                let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+                self.inner
+                    .dispatch_ip(tx_token, IpPacket::Icmpv6((ip_repr, advert)), None)?;
+                emitted_any = true;
+            }
+        } else {
+            net_debug!("router advertisement: no link-local address");
+        }
+
+        if let Some(ra) = self.inner.router_advert.as_mut() {
+            ra.sent(self.inner.now);
+        }
+        Ok(emitted_any)
+    }
+
+    /// Send the ARP probes and announcements of Address Conflict Detection that are due.
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn acd_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        let mut emitted_any = false;
+        loop {
+            let now = self.inner.now;
+            let inner = &mut self.inner;
+            let action = match inner.acd.as_mut() {
+                Some(acd) => acd.next_action(now, &mut inner.rand),
+                None => None,
+            };
+            // The state of an address changes when its timer expires.
+            if action.is_some() {
+                inner.touch();
+            }
+            let arp_repr = match action {
+                Some(AcdAction::Probe(addr)) => {
+                    self.inner.acd_arp_repr(Ipv4Address::UNSPECIFIED, addr)
+                }
+                Some(AcdAction::Announce(addr)) => self.inner.acd_arp_repr(addr, addr),
+                Some(AcdAction::None) => continue,
+                None => break,
+            };
+            let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+            self.inner
+                .dispatch(tx_token, EthernetPacket::Arp(arp_repr))?;
+            emitted_any = true;
+        }
+        Ok(emitted_any)
+    }
+
+    /// Send the Neighbor Solicitations of Duplicate Address Detection that are due.
+    #[cfg(feature = "proto-ipv6-dad")]
+    fn dad_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        let mut emitted_any = false;
+        loop {
+            let now = self.inner.now;
+            let target_addr = match self.inner.dad.as_mut().and_then(|dad| dad.next_action(now)) {
+                Some(DadAction::Probe(addr)) => addr,
+                Some(DadAction::None) => continue,
+                None => break,
+            };
+
+            let solicit = Icmpv6Repr::Ndisc(NdiscRepr::NeighborSolicit {
+                target_addr,
+                lladdr: None,
+            });
+            let ip_repr = Ipv6Repr {
+                src_addr: Ipv6Address::UNSPECIFIED,
+                dst_addr: target_addr.solicited_node(),
+                next_header: IpProtocol::Icmpv6,
+                hop_limit: 0xff,
+                payload_len: solicit.buffer_len(),
+            };
+            let tx_token = device.transmit().ok_or(Error::Exhausted)?;
+            self.inner
+                .dispatch_ip(tx_token, IpPacket::Icmpv6((ip_repr, solicit)), None)?;
+            emitted_any = true;
+        }
+        Ok(emitted_any)
+    }
+
+    #[cfg(feature = "proto-sixlowpan-fragmentation")]
+    fn sixlowpan_egress<D>(&mut self, device: &mut D) -> Result<bool>
+    where
+        D: for<'d> Device<'d>,
+    {
+        let SixlowpanOutPacket {
+            packet_len,
+            sent_bytes,
+            ..
+        } = &self.out_packets.sixlowpan_out_packet;
+
+        if *packet_len == 0 {
+            return Ok(false);
+        }
 
         if *packet_len >= *sent_bytes {
             match device.transmit().ok_or(Error::Exhausted) {
@@ -1215,6 +2578,38 @@ impl<'a> InterfaceInner<'a> {
         self.now
     }
 
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Invalidate the socket deadlines scheduled for this interface.
+    fn touch(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Invalidate the socket deadlines scheduled for this interface if a route changed
+    /// with time since, which may change the addresses and MTUs the sockets see.
+    fn expire_routes(&mut self) {
+        if self.routes.expired_between(self.scheduled_at, self.now) {
+            self.touch();
+        }
+    }
+
+    /// Return when a socket has to be polled, or `None` if it only has to be polled when
+    /// a packet arrives.
+    fn socket_poll_at(&mut self, item: &Item) -> Option<Instant> {
+        let socket_poll_at = item.socket.poll_at(self);
+        match item
+            .meta
+            .poll_at(socket_poll_at, |ip_addr| self.has_neighbor(&ip_addr))
+        {
+            PollAt::Ingress => None,
+            PollAt::Time(instant) => Some(instant),
+            PollAt::Now => Some(Instant::from_millis(0)),
+        }
+    }
+
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn hardware_addr(&self) -> Option<HardwareAddress> {
@@ -1228,14 +2623,58 @@ impl<'a> InterfaceInner<'a> {
 
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn ip_mtu(&self) -> usize {
-        self.caps.ip_mtu()
+        match self.mtu {
+            Some(mtu) => mtu.min(self.caps.ip_mtu()),
+            None => self.caps.ip_mtu(),
+        }
+    }
+
+    /// Return the IP MTU of the path to `addr`: the MTU of the route to it, if it has one,
+    /// or else the MTU of the interface.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn path_mtu(&self, addr: &IpAddress) -> usize {
+        if !addr.is_unicast() || self.in_same_network(addr) {
+            return self.ip_mtu();
+        }
+        match self
+            .routes
+            .lookup_mtu(addr, None, self.egress_mark, self.now)
+        {
+            Some(mtu) => mtu.min(self.caps.ip_mtu()),
+            None => self.ip_mtu(),
+        }
+    }
+
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn tcp_segmentation_offload(&self) -> Option<usize> {
+        self.caps.tcp_segmentation_offload
+    }
+
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn rx_timestamp(&self) -> Option<Instant> {
+        self.rx_timestamp
+    }
+
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn tx_timestamp(&self) -> Option<Instant> {
+        self.tx_timestamp
     }
 
     #[allow(unused)] // unused depending on which sockets are enabled, and in tests
+    pub(crate) fn stats_mut(&mut self) -> &mut Stats {
+        &mut self.stats
+    }
+
     pub(crate) fn rand(&mut self) -> &mut Rand {
         &mut self.rand
     }
 
+    #[cfg(feature = "proto-ipv6-ra")]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn ipv6_dns_servers(&self) -> impl Iterator<Item = Ipv6Address> + '_ {
+        self.ra_dns.servers(self.now)
+    }
+
     #[allow(unused)] // unused depending on which sockets are enabled
     pub(crate) fn get_source_address(&mut self, dst_addr: IpAddress) -> Option<IpAddress> {
         let v = dst_addr.version();
@@ -1257,6 +2696,10 @@ impl<'a> InterfaceInner<'a> {
         for cidr in self.ip_addrs.iter() {
             #[allow(irrefutable_let_patterns)] // if only ipv4 is enabled
             if let IpCidr::Ipv4(cidr) = cidr {
+                #[cfg(feature = "proto-ipv4-acd")]
+                if !self.is_usable_ipv4_addr(&cidr.address()) {
+                    continue;
+                }
                 return Some(cidr.address());
             }
         }
@@ -1265,17 +2708,304 @@ impl<'a> InterfaceInner<'a> {
 
     #[cfg(feature = "proto-ipv6")]
     #[allow(unused)]
-    pub(crate) fn get_source_address_ipv6(
-        &mut self,
-        _dst_addr: Ipv6Address,
-    ) -> Option<Ipv6Address> {
+    /// Select the source address for `dst_addr` among the IPv6 addresses of the
+    /// interface, following RFC 6724 § 5.
+    ///
+    /// Optimistic addresses are treated as deprecated, as required by RFC 4429 § 3.3.
+    /// Temporary addresses are preferred if enabled in the autoconfiguration settings.
+    pub(crate) fn get_source_address_ipv6(&mut self, dst_addr: Ipv6Address) -> Option<Ipv6Address> {
+        let mut best: Option<source_addr::Candidate> = None;
         for cidr in self.ip_addrs.iter() {
             #[allow(irrefutable_let_patterns)] // if only ipv6 is enabled
             if let IpCidr::Ipv6(cidr) = cidr {
-                return Some(cidr.address());
+                #[cfg(feature = "proto-ipv6-dad")]
+                if !self.is_usable_ipv6_addr(&cidr.address()) {
+                    continue;
+                }
+                #[cfg(feature = "proto-ipv6-dad")]
+                #[allow(unused_mut)]
+                let mut deprecated =
+                    self.ipv6_addr_state(&cidr.address()) == Some(Ipv6AddressState::Optimistic);
+                #[cfg(not(feature = "proto-ipv6-dad"))]
+                #[allow(unused_mut)]
+                let mut deprecated = false;
+                #[allow(unused_mut)]
+                let mut temporary = false;
+
+                #[cfg(feature = "proto-ipv6-slaac")]
+                if let Some(slaac) = self.slaac.as_ref() {
+                    if let Some(entry) = slaac.entry(&cidr.address()) {
+                        deprecated |= entry.is_deprecated(self.now);
+                        temporary = entry.is_temporary() && slaac.prefers_temporary();
+                    }
+                }
+
+                let candidate = source_addr::Candidate {
+                    cidr: *cidr,
+                    deprecated,
+                    temporary,
+                };
+                // Ties are resolved in favor of the address that was configured first.
+                best = match best {
+                    Some(best) if source_addr::compare(&dst_addr, &candidate, &best).is_le() => {
+                        Some(best)
+                    }
+                    _ => Some(candidate),
+                };
             }
         }
-        None
+        best.map(|candidate| candidate.cidr.address())
+    }
+
+    /// Return the Address Conflict Detection state of `addr`, or `None` if it is not
+    /// tracked.
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub(crate) fn ipv4_addr_state(&self, addr: &Ipv4Address) -> Option<Ipv4AddressState> {
+        self.acd.as_ref().and_then(|acd| acd.state(addr))
+    }
+
+    /// Check whether `addr` can be used, i.e. it is not being probed or in conflict.
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn is_usable_ipv4_addr(&self, addr: &Ipv4Address) -> bool {
+        !matches!(self.ipv4_addr_state(addr), Some(state) if !state.is_usable())
+    }
+
+    /// Start probing `addr` before it is added to the interface, e.g. an address leased
+    /// by DHCP.
+    ///
+    /// Returns whether Address Conflict Detection is enabled and tracks the address.
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub(crate) fn acd_probe(&mut self, addr: Ipv4Address) -> bool {
+        self.touch();
+        match self.acd.as_mut() {
+            Some(acd) => acd.probe(addr, self.now, &mut self.rand),
+            None => false,
+        }
+    }
+
+    /// Stop probing `addr`, if it was started by [`acd_probe`](Self::acd_probe) and the
+    /// address was not added to the interface.
+    #[cfg(feature = "proto-ipv4-acd")]
+    pub(crate) fn acd_release(&mut self, addr: Ipv4Address) {
+        self.touch();
+        if let Some(acd) = self.acd.as_mut() {
+            acd.release(&addr);
+        }
+    }
+
+    /// Return an ARP probe, if `source_addr` is unspecified, or an announcement for
+    /// `target_addr`.
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn acd_arp_repr(&self, source_addr: Ipv4Address, target_addr: Ipv4Address) -> ArpRepr {
+        let source_hardware_addr = match self.hardware_addr {
+            Some(HardwareAddress::Ethernet(addr)) => addr,
+            _ => unreachable!(),
+        };
+        ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr,
+            source_protocol_addr: source_addr,
+            target_hardware_addr: EthernetAddress([0; 6]),
+            target_protocol_addr: target_addr,
+        }
+    }
+
+    /// Return the Duplicate Address Detection state of `addr`, or `None` if it is not
+    /// tracked.
+    #[cfg(feature = "proto-ipv6-dad")]
+    fn ipv6_addr_state(&self, addr: &Ipv6Address) -> Option<Ipv6AddressState> {
+        self.dad.as_ref().and_then(|dad| dad.state(addr))
+    }
+
+    /// Check whether `addr` can be used, i.e. it is not tentative or a duplicate.
+    #[cfg(feature = "proto-ipv6-dad")]
+    fn is_usable_ipv6_addr(&self, addr: &Ipv6Address) -> bool {
+        !matches!(self.ipv6_addr_state(addr), Some(state) if !state.is_usable())
+    }
+
+    /// Add `cidr` to the addresses of the interface, see [`Interface::add_ip_addr`].
+    fn insert_ip_addr(ip_addrs: &mut ManagedSlice<'a, IpCidr>, cidr: IpCidr) -> Result<()> {
+        if let Some(slot) = ip_addrs
+            .iter_mut()
+            .find(|slot| slot.address().is_unspecified())
+        {
+            *slot = cidr;
+        } else {
+            match ip_addrs {
+                #[cfg(any(feature = "std", feature = "alloc"))]
+                ManagedSlice::Owned(ip_addrs) => ip_addrs.push(cidr),
+                _ => return Err(Error::Exhausted),
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove `addr` from the addresses of the interface, see [`Interface::remove_ip_addr`].
+    fn delete_ip_addr(&mut self, addr: IpAddress) -> bool {
+        let ip_addrs = &mut self.ip_addrs;
+        let index = match ip_addrs.iter().position(|cidr| cidr.address() == addr) {
+            Some(index) => index,
+            None => return false,
+        };
+        match ip_addrs {
+            #[cfg(any(feature = "std", feature = "alloc"))]
+            ManagedSlice::Owned(ip_addrs) => {
+                ip_addrs.remove(index);
+            }
+            _ => {
+                let unspecified = match addr {
+                    #[cfg(feature = "proto-ipv4")]
+                    IpAddress::Ipv4(_) => IpCidr::new(IpAddress::Ipv4(Ipv4Address::UNSPECIFIED), 0),
+                    #[cfg(feature = "proto-ipv6")]
+                    IpAddress::Ipv6(_) => IpCidr::new(IpAddress::Ipv6(Ipv6Address::UNSPECIFIED), 0),
+                };
+                ip_addrs[index] = unspecified;
+            }
+        }
+        true
+    }
+
+    /// Update the state that depends on the addresses of the interface after they changed.
+    fn ip_addrs_updated(&mut self) {
+        self.touch();
+        self.flush_cache();
+        Self::check_ip_addrs(&self.ip_addrs);
+        #[cfg(feature = "proto-ipv4-acd")]
+        self.sync_acd();
+        #[cfg(feature = "proto-ipv6-dad")]
+        self.sync_dad();
+    }
+
+    /// Return the stable interface identifier IPv6 addresses are formed with, derived
+    /// from the hardware address as specified in RFC 4291 § 2.5.1.
+    #[cfg(feature = "proto-ipv6-slaac")]
+    fn interface_identifier(&self) -> Option<[u8; 8]> {
+        match self.hardware_addr? {
+            HardwareAddress::Ethernet(addr) => {
+                let bytes = addr.as_bytes();
+                Some([
+                    bytes[0] ^ 0x02,
+                    bytes[1],
+                    bytes[2],
+                    0xff,
+                    0xfe,
+                    bytes[3],
+                    bytes[4],
+                    bytes[5],
+                ])
+            }
+            #[cfg(feature = "medium-ieee802154")]
+            HardwareAddress::Ieee802154(addr) => addr.as_eui_64(),
+        }
+    }
+
+    /// Remove the autoconfigured addresses whose valid lifetime expired, generate the
+    /// temporary addresses that are due, and add the new addresses to the interface.
+    #[cfg(feature = "proto-ipv6-slaac")]
+    fn sync_slaac(&mut self) {
+        let now = self.now;
+        let slaac = match self.slaac.as_mut() {
+            Some(slaac) => slaac,
+            None => return,
+        };
+        slaac.regenerate(&mut self.rand, now);
+
+        let mut changed = false;
+        while let Some(addr) = self
+            .slaac
+            .as_mut()
+            .and_then(|slaac| slaac.next_expired(now))
+        {
+            changed |= self.delete_ip_addr(addr.into());
+        }
+        if let Some(slaac) = self.slaac.as_ref() {
+            for (addr, _) in slaac.entries() {
+                if self
+                    .ip_addrs
+                    .iter()
+                    .any(|cidr| cidr.address() == addr.into())
+                {
+                    continue;
+                }
+                let cidr = IpCidr::Ipv6(Slaac::cidr(addr));
+                match Self::insert_ip_addr(&mut self.ip_addrs, cidr) {
+                    Ok(()) => changed = true,
+                    Err(_) => net_debug!("slaac: no space to add {}", addr),
+                }
+            }
+        }
+        if changed {
+            self.ip_addrs_updated();
+        }
+    }
+
+    /// Add a link-local IPv4 address to the interface if it has no routable IPv4 address,
+    /// or remove it once it is not needed or used by another node.
+    #[cfg(feature = "proto-ipv4-autoip")]
+    fn sync_autoip(&mut self) {
+        loop {
+            let has_routable = self.ip_addrs.iter().any(|cidr| match cidr {
+                IpCidr::Ipv4(cidr) => {
+                    let addr = cidr.address();
+                    addr.is_unicast()
+                        && !addr.is_link_local()
+                        && !addr.is_loopback()
+                        && self.ipv4_addr_state(&addr) != Some(Ipv4AddressState::Conflict)
+                }
+                #[allow(unreachable_patterns)]
+                _ => false,
+            });
+            let conflict = match self.autoip.as_ref().and_then(|autoip| autoip.address()) {
+                Some(addr) => self.ipv4_addr_state(&addr) == Some(Ipv4AddressState::Conflict),
+                None => false,
+            };
+            let now = self.now;
+            let action = match self.autoip.as_mut() {
+                Some(autoip) => autoip.update(has_routable, conflict, now),
+                None => return,
+            };
+            match action {
+                Some(AutoIpAction::Add(cidr)) => {
+                    if Self::insert_ip_addr(&mut self.ip_addrs, cidr.into()).is_err() {
+                        net_debug!("autoip: no space to add {}", cidr.address());
+                        return;
+                    }
+                }
+                Some(AutoIpAction::Remove(addr)) => {
+                    self.delete_ip_addr(addr.into());
+                }
+                None => return,
+            }
+            self.ip_addrs_updated();
+        }
+    }
+
+    /// Start or stop Address Conflict Detection for the addresses that were added to or
+    /// removed from the interface.
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn sync_acd(&mut self) {
+        let addrs = self.ip_addrs.iter().filter_map(|cidr| match cidr {
+            IpCidr::Ipv4(cidr) => Some(cidr.address()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        });
+        if let Some(acd) = self.acd.as_mut() {
+            acd.sync(addrs, self.now, &mut self.rand);
+        }
+    }
+
+    /// Start or stop Duplicate Address Detection for the addresses that were added to or
+    /// removed from the interface.
+    #[cfg(feature = "proto-ipv6-dad")]
+    fn sync_dad(&mut self) {
+        let addrs = self.ip_addrs.iter().filter_map(|cidr| match cidr {
+            IpCidr::Ipv6(cidr) => Some(cidr.address()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        });
+        if let Some(dad) = self.dad.as_mut() {
+            dad.sync(addrs, self.now);
+        }
     }
 
     #[cfg(test)]
@@ -1296,6 +3026,7 @@ impl<'a> InterfaceInner<'a> {
                     udp: crate::phy::Checksum::Both,
                 },
                 max_burst_size: None,
+                tcp_segmentation_offload: None,
                 #[cfg(feature = "medium-ethernet")]
                 max_transmission_unit: 1514,
                 #[cfg(not(feature = "medium-ethernet"))]
@@ -1317,6 +3048,22 @@ impl<'a> InterfaceInner<'a> {
 
             #[cfg(feature = "proto-ipv4")]
             any_ip: false,
+            egress_burst: DEFAULT_EGRESS_BURST,
+            mtu: None,
+            ingress_burst: None,
+            ingress_pending: false,
+            rx_timestamp: None,
+            tx_timestamp: None,
+            egress_dscp: None,
+            egress_mark: None,
+            egress_priority: None,
+            index: 0,
+            generation: 0,
+            scheduled_at: Instant::ZERO,
+            #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+            arp_protection: ArpProtection::Off,
+            unknown_protocol_policy: UnknownProtocolPolicy::DeliverToRawSocket,
+            parse_mode: ParseMode::Permissive,
 
             #[cfg(feature = "medium-ieee802154")]
             pan_id: Some(crate::wire::Ieee802154Pan(0xabcd)),
@@ -1337,6 +3084,26 @@ impl<'a> InterfaceInner<'a> {
             igmp_report_state: IgmpReportState::Inactive,
             #[cfg(feature = "proto-igmp")]
             ipv4_multicast_groups: ManagedMap::Borrowed(&mut []),
+            #[cfg(feature = "proto-lldp")]
+            lldp: None,
+            #[cfg(feature = "proto-ipv6-ra")]
+            router_advert: None,
+            #[cfg(feature = "proto-ipv4-acd")]
+            acd: None,
+            #[cfg(feature = "proto-ipv4-autoip")]
+            autoip: None,
+            #[cfg(feature = "proto-ipv6-dad")]
+            dad: None,
+            #[cfg(feature = "proto-ipv6-slaac")]
+            slaac: None,
+            #[cfg(feature = "proto-ipv6-ra")]
+            ra_dns: RaDns::default(),
+            #[cfg(feature = "proto-wol")]
+            wake_on_lan_received: false,
+            #[cfg(feature = "medium-ethernet")]
+            ethernet_filter_stats: EthernetFilterStats::default(),
+            link_state: LinkState::UP,
+            stats: Stats::default(),
         }
     }
 
@@ -1346,6 +3113,46 @@ impl<'a> InterfaceInner<'a> {
         self.now = now
     }
 
+    #[cfg(test)]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn set_ip_addrs(&mut self, ip_addrs: Vec<IpCidr>) {
+        self.ip_addrs = ManagedSlice::Owned(ip_addrs)
+    }
+
+    #[cfg(test)]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn set_tcp_segmentation_offload(&mut self, max_len: Option<usize>) {
+        self.caps.tcp_segmentation_offload = max_len
+    }
+
+    #[cfg(test)]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn set_device_ip_mtu(&mut self, mtu: usize) {
+        self.caps.max_transmission_unit = self.caps.max_transmission_unit - self.caps.ip_mtu() + mtu
+    }
+
+    #[cfg(all(test, feature = "proto-ipv4-acd"))]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn set_acd(&mut self, config: AcdConfig) {
+        self.acd = Some(Acd::new(
+            config,
+            ManagedMap::Owned(std::collections::BTreeMap::new()),
+        ));
+    }
+
+    #[cfg(all(test, feature = "proto-ipv4-acd"))]
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn acd_process_arp(
+        &mut self,
+        source_addr: Ipv4Address,
+        target_addr: Ipv4Address,
+    ) -> Option<Ipv4Address> {
+        let now = self.now;
+        self.acd
+            .as_mut()?
+            .process_arp(source_addr, target_addr, now)
+    }
+
     #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
     fn check_hardware_addr(addr: &HardwareAddress) {
         if !addr.is_unicast() {
@@ -1395,11 +3202,37 @@ impl<'a> InterfaceInner<'a> {
     }
 
     /// Check whether the interface has the given IP address assigned.
-    fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
+    pub(crate) fn has_ip_addr<T: Into<IpAddress>>(&self, addr: T) -> bool {
         let addr = addr.into();
         self.ip_addrs.iter().any(|probe| probe.address() == addr)
     }
 
+    /// Check whether unicast packets sent to the given address are received, either because
+    /// the address is assigned to the interface, or through AnyIP.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn is_local_addr(&self, addr: IpAddress) -> bool {
+        if self.has_ip_addr(addr) {
+            return true;
+        }
+        match addr {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(addr) => self.is_any_ip_addr(addr),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Check whether AnyIP is enabled, and routes packets sent to the given address locally.
+    #[cfg(feature = "proto-ipv4")]
+    fn is_any_ip_addr(&self, addr: Ipv4Address) -> bool {
+        self.any_ip
+            && addr.is_unicast()
+            && matches!(
+                self.routes.lookup(&IpAddress::Ipv4(addr), self.now),
+                Some(router_addr) if self.has_ip_addr(router_addr)
+            )
+    }
+
     /// Get the first IPv4 address of the interface.
     #[cfg(feature = "proto-ipv4")]
     pub fn ipv4_address(&self) -> Option<Ipv4Address> {
@@ -1426,6 +3259,67 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Check whether the source filter of the multicast group `dst_addr`, if any,
+    /// accepts traffic from `src_addr`.
+    #[cfg(feature = "proto-igmp")]
+    fn accepts_multicast_source(&self, dst_addr: Ipv4Address, src_addr: Ipv4Address) -> bool {
+        match self.ipv4_multicast_groups.get(&dst_addr) {
+            Some(filter) => filter.accepts(src_addr),
+            None => true,
+        }
+    }
+
+    /// Check whether the interface listens to the given multicast hardware address,
+    /// i.e. it is derived from a multicast group the interface is a member of, or used
+    /// by a link-layer protocol the interface takes part in.
+    #[cfg(feature = "medium-ethernet")]
+    fn has_multicast_hardware_addr(&self, addr: EthernetAddress) -> bool {
+        let b = addr.as_bytes();
+
+        // IPv4 multicast groups map their lower 23 bits to 01:00:5E:00:00:00/25.
+        #[cfg(feature = "proto-ipv4")]
+        if b[..3] == [0x01, 0x00, 0x5e] {
+            let maps_to = |group: Ipv4Address| {
+                let g = group.as_bytes();
+                [g[1] & 0x7f, g[2], g[3]] == b[3..]
+            };
+            #[cfg(feature = "proto-igmp")]
+            if self
+                .ipv4_multicast_groups
+                .iter()
+                .any(|(group, _)| maps_to(*group))
+            {
+                return true;
+            }
+            return maps_to(Ipv4Address::MULTICAST_ALL_SYSTEMS);
+        }
+
+        // IPv6 multicast groups map their lower 32 bits to 33:33:00:00:00:00/16.
+        #[cfg(feature = "proto-ipv6")]
+        if b[..2] == [0x33, 0x33] {
+            let maps_to = |group: Ipv6Address| group.as_bytes()[12..] == b[2..];
+            if maps_to(Ipv6Address::LINK_LOCAL_ALL_NODES) {
+                return true;
+            }
+            #[cfg(feature = "proto-ipv6-ra")]
+            if self.router_advert.is_some() && maps_to(Ipv6Address::LINK_LOCAL_ALL_ROUTERS) {
+                return true;
+            }
+            return self.ip_addrs.iter().any(|cidr| match *cidr {
+                IpCidr::Ipv6(cidr) => maps_to(cidr.address().solicited_node()),
+                #[allow(unreachable_patterns)]
+                _ => false,
+            });
+        }
+
+        #[cfg(feature = "proto-lldp")]
+        if addr == LLDP_MULTICAST_ADDR {
+            return self.lldp.is_some();
+        }
+
+        false
+    }
+
     #[cfg(feature = "medium-ethernet")]
     fn process_ethernet<'frame, T: AsRef<[u8]>>(
         &mut self,
@@ -1433,13 +3327,30 @@ impl<'a> InterfaceInner<'a> {
         frame: &'frame T,
         _fragments: &'frame mut FragmentsBuffer<'a>,
     ) -> Option<EthernetPacket<'frame>> {
-        let eth_frame = check!(EthernetFrame::new_checked(frame));
+        let eth_frame = check!(
+            EthernetFrame::new_checked(frame),
+            self.stats.interface.in_errors
+        );
 
         // Ignore any packets not directed to our hardware address or any of the multicast groups.
-        if !eth_frame.dst_addr().is_broadcast()
-            && !eth_frame.dst_addr().is_multicast()
-            && HardwareAddress::Ethernet(eth_frame.dst_addr()) != self.hardware_addr.unwrap()
-        {
+        let dst_addr = eth_frame.dst_addr();
+        if dst_addr.is_unicast() {
+            self.stats.interface.in_ucast_pkts += 1;
+        } else {
+            self.stats.interface.in_nucast_pkts += 1;
+        }
+        if dst_addr.is_unicast()
+            && HardwareAddress::Ethernet(dst_addr) != self.hardware_addr.unwrap()
+        {
+            self.ethernet_filter_stats.unicast += 1;
+            return None;
+        }
+        if dst_addr.is_multicast()
+            && !dst_addr.is_broadcast()
+            && !self.has_multicast_hardware_addr(dst_addr)
+        {
+            net_trace!("iface: not listening to {}", dst_addr);
+            self.ethernet_filter_stats.multicast += 1;
             return None;
         }
 
@@ -1448,7 +3359,11 @@ impl<'a> InterfaceInner<'a> {
             EthernetProtocol::Arp => self.process_arp(self.now, &eth_frame),
             #[cfg(feature = "proto-ipv4")]
             EthernetProtocol::Ipv4 => {
-                let ipv4_packet = check!(Ipv4Packet::new_checked(eth_frame.payload()));
+                let ipv4_packet = check!(
+                    Ipv4Packet::new_checked(eth_frame.payload()),
+                    self.stats.ip.in_receives,
+                    self.stats.ip.in_hdr_errors
+                );
 
                 cfg_if::cfg_if! {
                 if #[cfg(feature = "proto-ipv4-fragmentation")] {
@@ -1460,15 +3375,87 @@ impl<'a> InterfaceInner<'a> {
             }
             #[cfg(feature = "proto-ipv6")]
             EthernetProtocol::Ipv6 => {
-                let ipv6_packet = check!(Ipv6Packet::new_checked(eth_frame.payload()));
+                let ipv6_packet = check!(
+                    Ipv6Packet::new_checked(eth_frame.payload()),
+                    self.stats.ip.in_receives,
+                    self.stats.ip.in_hdr_errors
+                );
                 self.process_ipv6(sockets, &ipv6_packet)
                     .map(EthernetPacket::Ip)
             }
+            #[cfg(feature = "proto-lldp")]
+            EthernetProtocol::Lldp => {
+                self.process_lldp(&eth_frame);
+                None
+            }
+            #[cfg(feature = "proto-wol")]
+            EthernetProtocol::WakeOnLan => {
+                self.process_wake_on_lan(eth_frame.payload());
+                None
+            }
             // Drop all other traffic.
-            _ => None,
+            _ => {
+                self.stats.interface.in_unknown_protos += 1;
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "proto-lldp")]
+    fn process_lldp<T: AsRef<[u8]>>(&mut self, eth_frame: &EthernetFrame<&T>) {
+        let now = self.now;
+        let our_addr = self.hardware_addr;
+        let lldp = match self.lldp.as_mut() {
+            Some(lldp) => lldp,
+            None => return,
+        };
+
+        // Ignore our own advertisements, e.g. when looped back by a hub.
+        if Some(HardwareAddress::Ethernet(eth_frame.src_addr())) == our_addr {
+            return;
+        }
+
+        let packet = match LldpPacket::new_checked(eth_frame.payload()) {
+            Ok(packet) => packet,
+            Err(_) => {
+                net_debug!("lldp: truncated LLDPDU");
+                return;
+            }
+        };
+        let repr = match LldpRepr::parse(&packet) {
+            Ok(repr) => repr,
+            Err(_) => {
+                net_debug!("lldp: malformed LLDPDU");
+                return;
+            }
+        };
+
+        if repr.ttl == 0 {
+            // A shutdown advertisement; forget about the neighbor.
+            if lldp.neighbor.as_ref().map(|n| n.hardware_addr()) == Some(eth_frame.src_addr()) {
+                lldp.neighbor = None;
+            }
+        } else {
+            lldp.neighbor = Some(LldpNeighbor::new(eth_frame.src_addr(), &repr, now));
         }
     }
 
+    /// Record the reception of a magic packet addressed to us. Returns whether
+    /// `payload` was such a packet.
+    #[cfg(feature = "proto-wol")]
+    fn process_wake_on_lan(&mut self, payload: &[u8]) -> bool {
+        let repr = match WolPacket::new_checked(payload).and_then(|p| WolRepr::parse(&p)) {
+            Ok(repr) => repr,
+            Err(_) => return false,
+        };
+        if self.hardware_addr != Some(HardwareAddress::Ethernet(repr.target_addr)) {
+            return false;
+        }
+        net_debug!("wake-on-lan magic packet received");
+        self.wake_on_lan_received = true;
+        true
+    }
+
     #[cfg(feature = "medium-ip")]
     fn process_ip<'frame, T: AsRef<[u8]>>(
         &mut self,
@@ -1476,10 +3463,15 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame T,
         _fragments: &'frame mut FragmentsBuffer<'a>,
     ) -> Option<IpPacket<'frame>> {
+        self.stats.interface.in_ucast_pkts += 1;
         match IpVersion::of_packet(ip_payload.as_ref()) {
             #[cfg(feature = "proto-ipv4")]
             Ok(IpVersion::Ipv4) => {
-                let ipv4_packet = check!(Ipv4Packet::new_checked(ip_payload));
+                let ipv4_packet = check!(
+                    Ipv4Packet::new_checked(ip_payload),
+                    self.stats.ip.in_receives,
+                    self.stats.ip.in_hdr_errors
+                );
                 cfg_if::cfg_if! {
                     if #[cfg(feature = "proto-ipv4-fragmentation")] {
                         self.process_ipv4(sockets, &ipv4_packet, Some(&mut _fragments.ipv4_fragments))
@@ -1491,11 +3483,18 @@ impl<'a> InterfaceInner<'a> {
             }
             #[cfg(feature = "proto-ipv6")]
             Ok(IpVersion::Ipv6) => {
-                let ipv6_packet = check!(Ipv6Packet::new_checked(ip_payload));
+                let ipv6_packet = check!(
+                    Ipv6Packet::new_checked(ip_payload),
+                    self.stats.ip.in_receives,
+                    self.stats.ip.in_hdr_errors
+                );
                 self.process_ipv6(sockets, &ipv6_packet)
             }
             // Drop all other traffic.
-            _ => None,
+            _ => {
+                self.stats.interface.in_unknown_protos += 1;
+                None
+            }
         }
     }
 
@@ -1506,8 +3505,18 @@ impl<'a> InterfaceInner<'a> {
         sixlowpan_payload: &'payload T,
         _fragments: &'output mut FragmentsBuffer<'a>,
     ) -> Option<IpPacket<'output>> {
-        let ieee802154_frame = check!(Ieee802154Frame::new_checked(sixlowpan_payload));
-        let ieee802154_repr = check!(Ieee802154Repr::parse(&ieee802154_frame));
+        let ieee802154_frame = check!(
+            Ieee802154Frame::new_checked(sixlowpan_payload),
+            self.stats.interface.in_errors
+        );
+        let ieee802154_repr = check!(
+            Ieee802154Repr::parse(&ieee802154_frame),
+            self.stats.interface.in_errors
+        );
+        match ieee802154_repr.dst_addr {
+            Some(addr) if addr.is_broadcast() => self.stats.interface.in_nucast_pkts += 1,
+            _ => self.stats.interface.in_ucast_pkts += 1,
+        }
 
         if ieee802154_repr.frame_type != Ieee802154FrameType::Data {
             return None;
@@ -1699,19 +3708,16 @@ impl<'a> InterfaceInner<'a> {
                         // NOTE(thvdveld): this is currently the same code as in self.process_udp.
                         // However, we cannot use that one because the payload passed to it is a
                         // normal IPv6 UDP payload, which is not what we have here.
-                        for udp_socket in sockets
-                            .items_mut()
-                            .filter_map(|i| udp::Socket::downcast_mut(&mut i.socket))
-                        {
-                            if udp_socket.accepts(self, &IpRepr::Ipv6(ipv6_repr), &udp_repr) {
-                                udp_socket.process(
-                                    self,
-                                    &IpRepr::Ipv6(ipv6_repr),
-                                    &udp_repr,
-                                    udp_packet.payload(),
-                                );
-                                return None;
-                            }
+                        if let Some(udp_socket) = sockets.find_mut(|udp_socket: &udp::Socket| {
+                            udp_socket.accepts(self, &IpRepr::Ipv6(ipv6_repr), &udp_repr)
+                        }) {
+                            udp_socket.process(
+                                self,
+                                &IpRepr::Ipv6(ipv6_repr),
+                                &udp_repr,
+                                udp_packet.payload(),
+                            );
+                            return None;
                         }
 
                         // When we are here then then there was no UDP socket that accepted the UDP
@@ -1766,11 +3772,35 @@ impl<'a> InterfaceInner<'a> {
                 target_protocol_addr,
                 ..
             } => {
+                #[cfg(feature = "proto-ipv4-acd")]
+                if let (Some(acd), Some(HardwareAddress::Ethernet(hardware_addr))) =
+                    (self.acd.as_mut(), self.hardware_addr)
+                {
+                    if source_hardware_addr != hardware_addr {
+                        let addrs = [source_protocol_addr, target_protocol_addr];
+                        let states = addrs.map(|addr| acd.state(&addr));
+                        let defended =
+                            acd.process_arp(source_protocol_addr, target_protocol_addr, timestamp);
+                        if addrs.map(|addr| acd.state(&addr)) != states {
+                            self.touch();
+                        }
+                        if let Some(addr) = defended {
+                            return Some(EthernetPacket::Arp(self.acd_arp_repr(addr, addr)));
+                        }
+                    }
+                }
+
                 // Only process ARP packets for us.
                 if !self.has_ip_addr(target_protocol_addr) {
                     return None;
                 }
 
+                // Do not answer for addresses that are being probed or in conflict.
+                #[cfg(feature = "proto-ipv4-acd")]
+                if !self.is_usable_ipv4_addr(&target_protocol_addr) {
+                    return None;
+                }
+
                 // Only process REQUEST and RESPONSE.
                 if let ArpOperation::Unknown(_) = operation {
                     net_debug!("arp: unknown operation code");
@@ -1788,22 +3818,62 @@ impl<'a> InterfaceInner<'a> {
                     return None;
                 }
 
+                let src_hardware_addr = match self.hardware_addr {
+                    Some(HardwareAddress::Ethernet(addr)) => addr,
+                    _ => unreachable!(),
+                };
+
+                let neighbor_cache = self.neighbor_cache.as_mut().unwrap();
+                let conflict = neighbor_cache.conflict(
+                    &source_protocol_addr.into(),
+                    source_hardware_addr.into(),
+                    timestamp,
+                );
+                match (self.arp_protection, conflict) {
+                    (ArpProtection::Off, _) | (_, None) => (),
+                    (ArpProtection::Strict, Some(_)) => {
+                        net_debug!(
+                            "arp: ignoring {} claiming {}",
+                            source_hardware_addr,
+                            source_protocol_addr
+                        );
+                        return None;
+                    }
+                    (ArpProtection::Probe, Some(HardwareAddress::Ethernet(current_addr))) => {
+                        // Give the current owner of the address a chance to confirm it.
+                        net_debug!(
+                            "arp: {} claiming {}, probing {}",
+                            source_hardware_addr,
+                            source_protocol_addr,
+                            current_addr
+                        );
+                        neighbor_cache.expire_by(
+                            &source_protocol_addr.into(),
+                            timestamp + ArpProtection::PROBE_TIMEOUT,
+                        );
+                        return Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
+                            operation: ArpOperation::Request,
+                            source_hardware_addr: src_hardware_addr,
+                            source_protocol_addr: target_protocol_addr,
+                            target_hardware_addr: current_addr,
+                            target_protocol_addr: source_protocol_addr,
+                        }));
+                    }
+                    #[allow(unreachable_patterns)]
+                    (ArpProtection::Probe, Some(_)) => return None,
+                }
+
                 // Fill the ARP cache from any ARP packet aimed at us (both request or response).
                 // We fill from requests too because if someone is requesting our address they
                 // are probably going to talk to us, so we avoid having to request their address
                 // when we later reply to them.
-                self.neighbor_cache.as_mut().unwrap().fill(
+                neighbor_cache.fill(
                     source_protocol_addr.into(),
                     source_hardware_addr.into(),
                     timestamp,
                 );
 
                 if operation == ArpOperation::Request {
-                    let src_hardware_addr = match self.hardware_addr {
-                        Some(HardwareAddress::Ethernet(addr)) => addr,
-                        _ => unreachable!(),
-                    };
-
                     Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
                         operation: ArpOperation::Reply,
                         source_hardware_addr: src_hardware_addr,
@@ -1828,32 +3898,63 @@ impl<'a> InterfaceInner<'a> {
         let mut handled_by_raw_socket = false;
 
         // Pass every IP packet to all raw sockets we have registered.
-        for raw_socket in sockets
-            .items_mut()
-            .filter_map(|i| raw::Socket::downcast_mut(&mut i.socket))
-        {
-            if raw_socket.accepts(ip_repr) {
+        sockets.for_each_mut(|item| match raw::Socket::downcast_mut(&mut item.socket) {
+            Some(raw_socket) if raw_socket.accepts(ip_repr) => {
                 raw_socket.process(self, ip_repr, ip_payload);
                 handled_by_raw_socket = true;
+                true
             }
-        }
+            _ => false,
+        });
         handled_by_raw_socket
     }
 
+    /// Account for a packet whose protocol has no handler, and return whether to reply
+    /// with an ICMP error, according to the unknown protocol policy.
+    fn unknown_protocol(&mut self, handled_by_raw_socket: bool) -> bool {
+        if !handled_by_raw_socket {
+            self.stats.ip.in_unknown_protos += 1;
+        }
+        match self.unknown_protocol_policy {
+            UnknownProtocolPolicy::Drop => false,
+            UnknownProtocolPolicy::IcmpError => true,
+            UnknownProtocolPolicy::DeliverToRawSocket => !handled_by_raw_socket,
+        }
+    }
+
     #[cfg(feature = "proto-ipv6")]
     fn process_ipv6<'frame, T: AsRef<[u8]> + ?Sized>(
         &mut self,
         sockets: &mut SocketSet,
         ipv6_packet: &Ipv6Packet<&'frame T>,
     ) -> Option<IpPacket<'frame>> {
-        let ipv6_repr = check!(Ipv6Repr::parse(ipv6_packet));
-
-        if !ipv6_repr.src_addr.is_unicast() {
+        self.stats.ip.in_receives += 1;
+        let ipv6_repr = check!(Ipv6Repr::parse(ipv6_packet), self.stats.ip.in_hdr_errors);
+
+        // Neighbor Solicitations of Duplicate Address Detection are sent from the
+        // unspecified address.
+        #[cfg(feature = "proto-ipv6-dad")]
+        let is_dad_probe = ipv6_repr.src_addr.is_unspecified()
+            && ipv6_repr.next_header == IpProtocol::Icmpv6
+            && Ipv6Cidr::SOLICITED_NODE_PREFIX.contains_addr(&ipv6_repr.dst_addr);
+        #[cfg(not(feature = "proto-ipv6-dad"))]
+        let is_dad_probe = false;
+
+        if !ipv6_repr.src_addr.is_unicast() && !is_dad_probe {
             // Discard packets with non-unicast source addresses.
             net_debug!("non-unicast source address");
             return None;
         }
 
+        // Discard packets sent to tentative addresses, except for Neighbor Discovery.
+        #[cfg(feature = "proto-ipv6-dad")]
+        if ipv6_repr.next_header != IpProtocol::Icmpv6
+            && !self.is_usable_ipv6_addr(&ipv6_repr.dst_addr)
+        {
+            net_debug!("destination address {} is tentative", ipv6_repr.dst_addr);
+            return None;
+        }
+
         let ip_payload = ipv6_packet.payload();
 
         #[cfg(feature = "socket-raw")]
@@ -1884,11 +3985,20 @@ impl<'a> InterfaceInner<'a> {
         match nxt_hdr {
             IpProtocol::Icmpv6 => self.process_icmpv6(sockets, ipv6_repr.into(), ip_payload),
 
-            #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+            #[cfg(any(
+                feature = "socket-udp",
+                feature = "socket-dns",
+                feature = "socket-sntp"
+            ))]
             IpProtocol::Udp => {
                 self.process_udp(sockets, ipv6_repr.into(), handled_by_raw_socket, ip_payload)
             }
 
+            #[cfg(feature = "proto-udplite")]
+            IpProtocol::UdpLite => {
+                self.process_udplite(sockets, ipv6_repr.into(), handled_by_raw_socket, ip_payload)
+            }
+
             #[cfg(feature = "socket-tcp")]
             IpProtocol::Tcp => self.process_tcp(sockets, ipv6_repr.into(), ip_payload),
 
@@ -1896,8 +4006,7 @@ impl<'a> InterfaceInner<'a> {
                 self.process_hopbyhop(sockets, ipv6_repr, handled_by_raw_socket, ip_payload)
             }
 
-            #[cfg(feature = "socket-raw")]
-            _ if handled_by_raw_socket => None,
+            _ if !self.unknown_protocol(handled_by_raw_socket) => None,
 
             _ => {
                 // Send back as much of the original payload as we can.
@@ -1922,8 +4031,17 @@ impl<'a> InterfaceInner<'a> {
         ipv4_packet: &Ipv4Packet<&'payload T>,
         _fragments: Option<&'output mut PacketAssemblerSet<'a, Ipv4FragKey>>,
     ) -> Option<IpPacket<'output>> {
-        let ipv4_repr = check!(Ipv4Repr::parse(ipv4_packet, &self.caps.checksum));
-        if !self.is_unicast_v4(ipv4_repr.src_addr) {
+        self.stats.ip.in_receives += 1;
+        let ipv4_repr = check!(
+            Ipv4Repr::parse(ipv4_packet, &self.caps.checksum, self.parse_mode),
+            self.stats.ip.in_hdr_errors
+        );
+        // Hosts being configured, e.g. DHCP clients, broadcast UDP packets from the
+        // unspecified address, see RFC 1122 § 3.2.1.3.
+        let is_unconfigured_broadcast = ipv4_repr.src_addr.is_unspecified()
+            && ipv4_repr.dst_addr.is_broadcast()
+            && ipv4_repr.next_header == IpProtocol::Udp;
+        if !self.is_unicast_v4(ipv4_repr.src_addr) && !is_unconfigured_broadcast {
             // Discard packets with non-unicast source addresses.
             net_debug!("non-unicast source address");
             return None;
@@ -1937,29 +4055,38 @@ impl<'a> InterfaceInner<'a> {
 
             if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
                 let key = ipv4_packet.get_key();
+                self.stats.ip.reasm_reqds += 1;
 
                 let f = match fragments.get_packet_assembler_mut(&key) {
                     Ok(f) => f,
                     Err(_) => {
-                        check!(check!(fragments.reserve_with_key(&key)).start(
-                            None,
-                            self.now + Duration::from_secs(REASSEMBLY_TIMEOUT),
-                            0,
-                        ));
+                        check!(
+                                check!(fragments.reserve_with_key(&key), self.stats.ip.reasm_fails)
+                                    .start(
+                                        None,
+                                        self.now + Duration::from_secs(REASSEMBLY_TIMEOUT),
+                                        0,
+                                    ),
+                                self.stats.ip.reasm_fails
+                            );
                         check!(fragments.get_packet_assembler_mut(&key))
                     }
                 };
 
                 if !ipv4_packet.more_frags() {
                     // This is the last fragment, so we know the total size
-                    check!(f.set_total_size(
-                        ipv4_packet.total_len() as usize - ipv4_packet.header_len() as usize
-                            + ipv4_packet.frag_offset() as usize,
-                    ));
+                    check!(
+                        f.set_total_size(
+                            ipv4_packet.total_len() as usize - ipv4_packet.header_len() as usize
+                                + ipv4_packet.frag_offset() as usize,
+                        ),
+                        self.stats.ip.reasm_fails
+                    );
                 }
 
                 match f.add(ipv4_packet.payload(), ipv4_packet.frag_offset() as usize) {
                     Ok(true) => {
+                        self.stats.ip.reasm_oks += 1;
                         // NOTE: according to the standard, the total length needs to be
                         // recomputed, as well as the checksum. However, we don't really use
                         // the IPv4 header after the packet is reassembled.
@@ -1973,6 +4100,7 @@ impl<'a> InterfaceInner<'a> {
                     }
                     Err(e) => {
                         net_debug!("fragmentation error: {}", e);
+                        self.stats.ip.reasm_fails += 1;
                         return None;
                     }
                 }
@@ -1996,62 +4124,80 @@ impl<'a> InterfaceInner<'a> {
             if ipv4_repr.next_header == IpProtocol::Udp && self.hardware_addr.is_some() {
                 // First check for source and dest ports, then do `UdpRepr::parse` if they match.
                 // This way we avoid validating the UDP checksum twice for all non-DHCP UDP packets (one here, one in `process_udp`)
-                let udp_packet = check!(UdpPacket::new_checked(ip_payload));
+                let udp_packet =
+                    check!(UdpPacket::new_checked(ip_payload), self.stats.udp.in_errors);
                 if udp_packet.src_port() == DHCP_SERVER_PORT
                     && udp_packet.dst_port() == DHCP_CLIENT_PORT
                 {
-                    if let Some(dhcp_socket) = sockets
-                        .items_mut()
-                        .find_map(|i| dhcpv4::Socket::downcast_mut(&mut i.socket))
-                    {
+                    if let Some(dhcp_socket) = sockets.find_mut(|_: &dhcpv4::Socket| true) {
                         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-                        let udp_repr = check!(UdpRepr::parse(
-                            &udp_packet,
-                            &src_addr,
-                            &dst_addr,
-                            &self.caps.checksum
-                        ));
+                        let udp_repr = check!(
+                            UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+                            self.stats.udp.in_errors
+                        );
                         let udp_payload = udp_packet.payload();
 
                         dhcp_socket.process(self, &ipv4_repr, &udp_repr, udp_payload);
+                        self.stats.udp.in_datagrams += 1;
                         return None;
                     }
                 }
             }
         }
 
+        // Discard packets sent to addresses that are being probed or in conflict.
+        #[cfg(feature = "proto-ipv4-acd")]
+        if !self.is_usable_ipv4_addr(&ipv4_repr.dst_addr) {
+            net_debug!("destination address {} is not usable", ipv4_repr.dst_addr);
+            return None;
+        }
+
         if !self.has_ip_addr(ipv4_repr.dst_addr)
             && !self.has_multicast_group(ipv4_repr.dst_addr)
             && !self.is_broadcast_v4(ipv4_repr.dst_addr)
         {
             // Ignore IP packets not directed at us, or broadcast, or any of the multicast groups.
             // If AnyIP is enabled, also check if the packet is routed locally.
-            if !self.any_ip
-                || !ipv4_repr.dst_addr.is_unicast()
-                || self
-                    .routes
-                    .lookup(&IpAddress::Ipv4(ipv4_repr.dst_addr), self.now)
-                    .map_or(true, |router_addr| !self.has_ip_addr(router_addr))
-            {
+            if !self.is_any_ip_addr(ipv4_repr.dst_addr) {
+                self.stats.ip.in_addr_errors += 1;
                 return None;
             }
         }
 
+        #[cfg(feature = "proto-igmp")]
+        if !self.accepts_multicast_source(ipv4_repr.dst_addr, ipv4_repr.src_addr) {
+            net_trace!(
+                "multicast source {} is filtered out for group {}",
+                ipv4_repr.src_addr,
+                ipv4_repr.dst_addr
+            );
+            return None;
+        }
+
         match ipv4_repr.next_header {
             IpProtocol::Icmp => self.process_icmpv4(sockets, ip_repr, ip_payload),
 
             #[cfg(feature = "proto-igmp")]
             IpProtocol::Igmp => self.process_igmp(ipv4_repr, ip_payload),
 
-            #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+            #[cfg(any(
+                feature = "socket-udp",
+                feature = "socket-dns",
+                feature = "socket-sntp"
+            ))]
             IpProtocol::Udp => {
                 self.process_udp(sockets, ip_repr, handled_by_raw_socket, ip_payload)
             }
 
+            #[cfg(feature = "proto-udplite")]
+            IpProtocol::UdpLite => {
+                self.process_udplite(sockets, ip_repr, handled_by_raw_socket, ip_payload)
+            }
+
             #[cfg(feature = "socket-tcp")]
             IpProtocol::Tcp => self.process_tcp(sockets, ip_repr, ip_payload),
 
-            _ if handled_by_raw_socket => None,
+            _ if !self.unknown_protocol(handled_by_raw_socket) => None,
 
             _ => {
                 // Send back as much of the original payload as we can.
@@ -2067,6 +4213,17 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Checks if an address is broadcast, taking into account subnet broadcast addresses.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn is_broadcast(&self, address: &IpAddress) -> bool {
+        match *address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(address) => self.is_broadcast_v4(address),
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(_) => false,
+        }
+    }
+
     /// Checks if an incoming packet has a broadcast address for the interfaces
     /// associated ipv4 addresses.
     #[cfg(feature = "proto-ipv4")]
@@ -2093,7 +4250,7 @@ impl<'a> InterfaceInner<'a> {
         address.is_unicast() && !self.is_subnet_broadcast(address)
     }
 
-    /// Host duties of the **IGMPv2** protocol.
+    /// Host duties of the **IGMPv2** and **IGMPv3** protocols.
     ///
     /// Sets up `igmp_report_state` for responding to IGMP general/specific membership queries.
     /// Membership must not be reported immediately in order to avoid flooding the network
@@ -2107,53 +4264,58 @@ impl<'a> InterfaceInner<'a> {
         let igmp_packet = check!(IgmpPacket::new_checked(ip_payload));
         let igmp_repr = check!(IgmpRepr::parse(&igmp_packet));
 
-        // FIXME: report membership after a delay
-        match igmp_repr {
+        let (group_addr, version, max_resp_time) = match igmp_repr {
             IgmpRepr::MembershipQuery {
                 group_addr,
                 version,
                 max_resp_time,
-            } => {
-                // General query
-                if group_addr.is_unspecified()
-                    && ipv4_repr.dst_addr == Ipv4Address::MULTICAST_ALL_SYSTEMS
-                {
-                    // Are we member in any groups?
-                    if self.ipv4_multicast_groups.iter().next().is_some() {
-                        let interval = match version {
-                            IgmpVersion::Version1 => Duration::from_millis(100),
-                            IgmpVersion::Version2 => {
-                                // No dependence on a random generator
-                                // (see [#24](https://github.com/m-labs/smoltcp/issues/24))
-                                // but at least spread reports evenly across max_resp_time.
-                                let intervals = self.ipv4_multicast_groups.len() as u32 + 1;
-                                max_resp_time / intervals
-                            }
-                        };
-                        self.igmp_report_state = IgmpReportState::ToGeneralQuery {
-                            version,
-                            timeout: self.now + interval,
-                            interval,
-                            next_index: 0,
-                        };
-                    }
-                } else {
-                    // Group-specific query
-                    if self.has_multicast_group(group_addr) && ipv4_repr.dst_addr == group_addr {
-                        // Don't respond immediately
-                        let timeout = max_resp_time / 4;
-                        self.igmp_report_state = IgmpReportState::ToSpecificQuery {
-                            version,
-                            timeout: self.now + timeout,
-                            group: group_addr,
-                        };
-                    }
-                }
-            }
+            } => (group_addr, version, max_resp_time),
+            // Group-and-source-specific queries are answered with the current state
+            // of the whole group.
+            IgmpRepr::MembershipQueryV3 {
+                group_addr,
+                max_resp_time,
+                ..
+            } => (group_addr, IgmpVersion::Version3, max_resp_time),
             // Ignore membership reports
-            IgmpRepr::MembershipReport { .. } => (),
+            IgmpRepr::MembershipReport { .. } | IgmpRepr::MembershipReportV3 { .. } => return None,
             // Ignore hosts leaving groups
-            IgmpRepr::LeaveGroup { .. } => (),
+            IgmpRepr::LeaveGroup { .. } => return None,
+        };
+
+        // FIXME: report membership after a delay
+        // General query
+        if group_addr.is_unspecified() && ipv4_repr.dst_addr == Ipv4Address::MULTICAST_ALL_SYSTEMS {
+            // Are we member in any groups?
+            if self.ipv4_multicast_groups.iter().next().is_some() {
+                let interval = match version {
+                    IgmpVersion::Version1 => Duration::from_millis(100),
+                    IgmpVersion::Version2 | IgmpVersion::Version3 => {
+                        // No dependence on a random generator
+                        // (see [#24](https://github.com/m-labs/smoltcp/issues/24))
+                        // but at least spread reports evenly across max_resp_time.
+                        let intervals = self.ipv4_multicast_groups.len() as u32 + 1;
+                        max_resp_time / intervals
+                    }
+                };
+                self.igmp_report_state = IgmpReportState::ToGeneralQuery {
+                    version,
+                    timeout: self.now + interval,
+                    interval,
+                    next_index: 0,
+                };
+            }
+        } else {
+            // Group-specific query
+            if self.has_multicast_group(group_addr) && ipv4_repr.dst_addr == group_addr {
+                // Don't respond immediately
+                let timeout = max_resp_time / 4;
+                self.igmp_report_state = IgmpReportState::ToSpecificQuery {
+                    version,
+                    timeout: self.now + timeout,
+                    group: group_addr,
+                };
+            }
         }
 
         None
@@ -2166,27 +4328,47 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv6Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv6Repr::parse(
-            &ip_repr.src_addr(),
-            &ip_repr.dst_addr(),
-            &icmp_packet,
-            &self.caps.checksum,
-        ));
+        let icmp_packet = check!(
+            Icmpv6Packet::new_checked(ip_payload),
+            self.stats.icmp.in_msgs,
+            self.stats.icmp.in_errors
+        );
+        let icmp_repr = check!(
+            Icmpv6Repr::parse(
+                &ip_repr.src_addr(),
+                &ip_repr.dst_addr(),
+                &icmp_packet,
+                &self.caps.checksum,
+                self.parse_mode,
+            ),
+            self.stats.icmp.in_msgs,
+            self.stats.icmp.in_errors
+        );
+        self.stats.icmp_received(IcmpKind::of_icmpv6(&icmp_repr));
+
+        // A router reporting that it has no route to a network may have lost its uplink.
+        if let Icmpv6Repr::DstUnreachable {
+            reason: Icmpv6DstUnreachable::NoRoute,
+            ..
+        } = icmp_repr
+        {
+            if self.routes.router_failed(&ip_repr.src_addr(), self.now) {
+                self.touch();
+            }
+        }
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
 
         #[cfg(all(feature = "socket-icmp", feature = "proto-ipv6"))]
-        for icmp_socket in _sockets
-            .items_mut()
-            .filter_map(|i| icmp::Socket::downcast_mut(&mut i.socket))
-        {
-            if icmp_socket.accepts(self, &ip_repr, &icmp_repr.into()) {
+        _sockets.for_each_mut(|item| match icmp::Socket::downcast_mut(&mut item.socket) {
+            Some(icmp_socket) if icmp_socket.accepts(self, &ip_repr, &icmp_repr.into()) => {
                 icmp_socket.process(self, &ip_repr, &icmp_repr.into());
                 handled_by_icmp_socket = true;
+                true
             }
-        }
+            _ => false,
+        });
 
         match icmp_repr {
             // Respond to echo requests.
@@ -2218,6 +4400,29 @@ impl<'a> InterfaceInner<'a> {
                 _ => unreachable!(),
             },
 
+            // Deliver errors to the socket that sent the packet they quote.
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            Icmpv6Repr::DstUnreachable {
+                reason,
+                header,
+                data,
+            } => {
+                if let Some(error) = IcmpError::from_icmpv6(reason) {
+                    self.process_icmp_error(_sockets, error, IpRepr::Ipv6(header), data);
+                }
+                None
+            }
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            Icmpv6Repr::TimeExceeded { header, data, .. } => {
+                self.process_icmp_error(
+                    _sockets,
+                    IcmpError::TimeExceeded,
+                    IpRepr::Ipv6(header),
+                    data,
+                );
+                None
+            }
+
             // Don't report an error if a packet with unknown type
             // has been handled by an ICMP socket
             #[cfg(feature = "socket-icmp")]
@@ -2243,6 +4448,14 @@ impl<'a> InterfaceInner<'a> {
                 target_addr,
                 flags,
             } => {
+                // Another node uses an address we are verifying the uniqueness of.
+                #[cfg(feature = "proto-ipv6-dad")]
+                if let Some(dad) = self.dad.as_mut() {
+                    if dad.conflict(&target_addr) {
+                        return None;
+                    }
+                }
+
                 let ip_addr = ip_repr.src_addr.into();
                 if let Some(lladdr) = lladdr {
                     let lladdr = check!(lladdr.parse(self.caps.medium));
@@ -2270,6 +4483,19 @@ impl<'a> InterfaceInner<'a> {
                 lladdr,
                 ..
             } => {
+                #[cfg(feature = "proto-ipv6-dad")]
+                match self.ipv6_addr_state(&target_addr) {
+                    // Another node is verifying the uniqueness of an address we are
+                    // verifying the uniqueness of as well.
+                    Some(state) if state.is_probing() && ip_repr.src_addr.is_unspecified() => {
+                        self.dad.as_mut().unwrap().conflict(&target_addr);
+                        return None;
+                    }
+                    // Tentative addresses are not defended.
+                    Some(state) if state != Ipv6AddressState::Preferred => return None,
+                    _ => (),
+                }
+
                 if let Some(lladdr) = lladdr {
                     let lladdr = check!(lladdr.parse(self.caps.medium));
                     if !lladdr.is_unicast() || !target_addr.is_unicast() {
@@ -2283,15 +4509,29 @@ impl<'a> InterfaceInner<'a> {
                 }
 
                 if self.has_solicited_node(ip_repr.dst_addr) && self.has_ip_addr(target_addr) {
+                    #[allow(unused_mut)]
+                    let mut flags = NdiscNeighborFlags::SOLICITED;
+                    #[cfg(feature = "proto-ipv6-ra")]
+                    if self.router_advert.is_some() {
+                        flags |= NdiscNeighborFlags::ROUTER;
+                    }
+                    // Defend the address against a node performing Duplicate Address
+                    // Detection, per RFC 4861 § 7.2.4.
+                    let mut dst_addr = ip_repr.src_addr;
+                    if dst_addr.is_unspecified() {
+                        flags.remove(NdiscNeighborFlags::SOLICITED);
+                        flags.insert(NdiscNeighborFlags::OVERRIDE);
+                        dst_addr = Ipv6Address::LINK_LOCAL_ALL_NODES;
+                    }
                     let advert = Icmpv6Repr::Ndisc(NdiscRepr::NeighborAdvert {
-                        flags: NdiscNeighborFlags::SOLICITED,
+                        flags,
                         target_addr,
                         #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
                         lladdr: Some(self.hardware_addr.unwrap().into()),
                     });
                     let ip_repr = Ipv6Repr {
                         src_addr: target_addr,
-                        dst_addr: ip_repr.src_addr,
+                        dst_addr,
                         next_header: IpProtocol::Icmpv6,
                         hop_limit: 0xff,
                         payload_len: advert.buffer_len(),
@@ -2301,6 +4541,93 @@ impl<'a> InterfaceInner<'a> {
                     None
                 }
             }
+            #[cfg(feature = "proto-ipv6-ra")]
+            NdiscRepr::RouterSolicit { lladdr } => {
+                let router_advert = self.router_advert.as_mut()?;
+                router_advert.solicited(self.now);
+                if let Some(lladdr) = lladdr {
+                    let lladdr = check!(lladdr.parse(self.caps.medium));
+                    if lladdr.is_unicast() {
+                        self.neighbor_cache.as_mut().unwrap().fill(
+                            ip_repr.src_addr.into(),
+                            lladdr,
+                            self.now,
+                        );
+                    }
+                }
+                None
+            }
+            #[cfg(any(feature = "proto-ipv6-slaac", feature = "proto-ipv6-ra"))]
+            NdiscRepr::RouterAdvert {
+                #[cfg(feature = "proto-ipv6-slaac")]
+                preference,
+                #[cfg(feature = "proto-ipv6-slaac")]
+                router_lifetime,
+                #[cfg(feature = "proto-ipv6-slaac")]
+                prefix_info,
+                #[cfg(feature = "proto-ipv6-slaac")]
+                route_info,
+                #[cfg(feature = "proto-ipv6-ra")]
+                rdnss,
+                #[cfg(feature = "proto-ipv6-ra")]
+                dnssl,
+                ..
+            } => {
+                // Routers advertise from their link-local address, per RFC 4861 § 6.1.2.
+                if !ip_repr.src_addr.is_link_local() {
+                    return None;
+                }
+
+                #[cfg(feature = "proto-ipv6-slaac")]
+                if self.slaac.is_some() {
+                    let router = ip_repr.src_addr;
+                    let default = Ipv6Cidr::new(Ipv6Address::UNSPECIFIED, 0);
+                    let routes = Some((default, preference, router_lifetime))
+                        .into_iter()
+                        .chain(route_info.map(|info| {
+                            let cidr = Ipv6Cidr::new(info.prefix, info.prefix_len);
+                            (cidr, info.preference, info.lifetime)
+                        }));
+                    for (cidr, preference, lifetime) in routes {
+                        match self
+                            .routes
+                            .update_advertised_route(cidr, router, preference, lifetime, self.now)
+                        {
+                            Ok(true) => self.touch(),
+                            Ok(false) => (),
+                            Err(e) => {
+                                net_debug!(
+                                    "router advertisement: cannot add route to {}: {}",
+                                    cidr,
+                                    e
+                                )
+                            }
+                        }
+                    }
+
+                    if let (Some(prefix_info), Some(iid)) =
+                        (prefix_info, self.interface_identifier())
+                    {
+                        if let Some(slaac) = self.slaac.as_mut() {
+                            slaac.process_prefix(&prefix_info, &iid, &mut self.rand, self.now);
+                        }
+                        self.sync_slaac();
+                    }
+                }
+
+                // The advertised DNS configuration is recorded whether or not addresses
+                // are autoconfigured.
+                #[cfg(feature = "proto-ipv6-ra")]
+                {
+                    if let Some(rdnss) = rdnss {
+                        self.ra_dns.process_rdnss(&rdnss, self.now);
+                    }
+                    if let Some(dnssl) = dnssl {
+                        self.ra_dns.process_dnssl(&dnssl, self.now);
+                    }
+                }
+                None
+            }
             _ => None,
         }
     }
@@ -2350,22 +4677,41 @@ impl<'a> InterfaceInner<'a> {
         ip_repr: IpRepr,
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
-        let icmp_packet = check!(Icmpv4Packet::new_checked(ip_payload));
-        let icmp_repr = check!(Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum));
+        let icmp_packet = check!(
+            Icmpv4Packet::new_checked(ip_payload),
+            self.stats.icmp.in_msgs,
+            self.stats.icmp.in_errors
+        );
+        let icmp_repr = check!(
+            Icmpv4Repr::parse(&icmp_packet, &self.caps.checksum, self.parse_mode),
+            self.stats.icmp.in_msgs,
+            self.stats.icmp.in_errors
+        );
+        self.stats.icmp_received(IcmpKind::of_icmpv4(&icmp_repr));
+
+        // A router reporting that it has no route to a network may have lost its uplink.
+        if let Icmpv4Repr::DstUnreachable {
+            reason: Icmpv4DstUnreachable::NetUnreachable,
+            ..
+        } = icmp_repr
+        {
+            if self.routes.router_failed(&ip_repr.src_addr(), self.now) {
+                self.touch();
+            }
+        }
 
         #[cfg(feature = "socket-icmp")]
         let mut handled_by_icmp_socket = false;
 
         #[cfg(all(feature = "socket-icmp", feature = "proto-ipv4"))]
-        for icmp_socket in _sockets
-            .items_mut()
-            .filter_map(|i| icmp::Socket::downcast_mut(&mut i.socket))
-        {
-            if icmp_socket.accepts(self, &ip_repr, &icmp_repr.into()) {
+        _sockets.for_each_mut(|item| match icmp::Socket::downcast_mut(&mut item.socket) {
+            Some(icmp_socket) if icmp_socket.accepts(self, &ip_repr, &icmp_repr.into()) => {
                 icmp_socket.process(self, &ip_repr, &icmp_repr.into());
                 handled_by_icmp_socket = true;
+                true
             }
-        }
+            _ => false,
+        });
 
         match icmp_repr {
             // Respond to echo requests.
@@ -2390,16 +4736,82 @@ impl<'a> InterfaceInner<'a> {
             // Ignore any echo replies.
             Icmpv4Repr::EchoReply { .. } => None,
 
+            // Deliver errors to the socket that sent the packet they quote.
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            Icmpv4Repr::DstUnreachable {
+                reason,
+                header,
+                data,
+            } => {
+                if let Some(error) = IcmpError::from_icmpv4(reason) {
+                    self.process_icmp_error(_sockets, error, IpRepr::Ipv4(header), data);
+                }
+                None
+            }
+            #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+            Icmpv4Repr::TimeExceeded { header, data, .. } => {
+                self.process_icmp_error(
+                    _sockets,
+                    IcmpError::TimeExceeded,
+                    IpRepr::Ipv4(header),
+                    data,
+                );
+                None
+            }
+
             // Don't report an error if a packet with unknown type
             // has been handled by an ICMP socket
             #[cfg(feature = "socket-icmp")]
+            #[allow(unreachable_patterns)]
             _ if handled_by_icmp_socket => None,
 
             // FIXME: do something correct here?
+            #[allow(unreachable_patterns)]
             _ => None,
         }
     }
 
+    /// Deliver an ICMP error to the TCP or UDP socket that sent the packet it quotes.
+    #[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+    fn process_icmp_error(
+        &mut self,
+        sockets: &mut SocketSet,
+        error: IcmpError,
+        header: IpRepr,
+        data: &[u8],
+    ) {
+        // The ports, and the sequence number of a TCP segment, are in the first
+        // eight octets of the quoted transport header.
+        if data.len() < 8 {
+            return;
+        }
+        let packet = UdpPacket::new_unchecked(data);
+        let local = IpEndpoint::new(header.src_addr(), packet.src_port());
+        let remote = IpEndpoint::new(header.dst_addr(), packet.dst_port());
+
+        #[cfg(feature = "socket-tcp")]
+        if header.next_header() == IpProtocol::Tcp {
+            let seq_no = TcpPacket::new_unchecked(data).seq_number();
+            if let Some(tcp_socket) = sockets.find_mut(|tcp_socket: &tcp::Socket| {
+                tcp_socket.accepts_icmp_error(self, local, remote, seq_no)
+            }) {
+                tcp_socket.process_icmp_error(self, error);
+            }
+            return;
+        }
+
+        #[cfg(feature = "socket-udp")]
+        sockets.for_each_mut(|item| match udp::Socket::downcast_mut(&mut item.socket) {
+            Some(udp_socket)
+                if udp_socket.accepts_icmp_error(self, header.next_header(), local) =>
+            {
+                udp_socket.process_icmp_error(remote, error);
+                true
+            }
+            _ => false,
+        });
+    }
+
     #[cfg(feature = "proto-ipv4")]
     fn icmpv4_reply<'frame, 'icmp: 'frame>(
         &self,
@@ -2463,7 +4875,11 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
-    #[cfg(any(feature = "socket-udp", feature = "socket-dns"))]
+    #[cfg(any(
+        feature = "socket-udp",
+        feature = "socket-dns",
+        feature = "socket-sntp"
+    ))]
     fn process_udp<'frame>(
         &mut self,
         sockets: &mut SocketSet,
@@ -2472,38 +4888,154 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-        let udp_packet = check!(UdpPacket::new_checked(ip_payload));
-        let udp_repr = check!(UdpRepr::parse(
-            &udp_packet,
-            &src_addr,
-            &dst_addr,
-            &self.caps.checksum
-        ));
+        let udp_packet = check!(UdpPacket::new_checked(ip_payload), self.stats.udp.in_errors);
+        let udp_repr = check!(
+            UdpRepr::parse(&udp_packet, &src_addr, &dst_addr, &self.caps.checksum),
+            self.stats.udp.in_errors
+        );
         let udp_payload = udp_packet.payload();
 
         #[cfg(feature = "socket-udp")]
-        for udp_socket in sockets
-            .items_mut()
-            .filter_map(|i| udp::Socket::downcast_mut(&mut i.socket))
-        {
-            if udp_socket.accepts(self, &ip_repr, &udp_repr) {
-                udp_socket.process(self, &ip_repr, &udp_repr, udp_payload);
-                return None;
-            }
+        if self.deliver_udp(sockets, &ip_repr, &udp_repr, udp_payload) {
+            self.stats.udp.in_datagrams += 1;
+            return None;
         }
 
         #[cfg(feature = "socket-dns")]
-        for dns_socket in sockets
-            .items_mut()
-            .filter_map(|i| dns::Socket::downcast_mut(&mut i.socket))
+        if let Some(dns_socket) = sockets
+            .find_mut(|dns_socket: &dns::Socket| dns_socket.accepts(self, &ip_repr, &udp_repr))
         {
-            if dns_socket.accepts(&ip_repr, &udp_repr) {
-                dns_socket.process(self, &ip_repr, &udp_repr, udp_payload);
-                return None;
+            dns_socket.process(self, &ip_repr, &udp_repr, udp_payload);
+            self.stats.udp.in_datagrams += 1;
+            return None;
+        }
+
+        #[cfg(feature = "socket-sntp")]
+        if let Some(sntp_socket) =
+            sockets.find_mut(|sntp_socket: &sntp::Socket| sntp_socket.accepts(&ip_repr, &udp_repr))
+        {
+            sntp_socket.process(self, &ip_repr, &udp_repr, udp_payload);
+            self.stats.udp.in_datagrams += 1;
+            return None;
+        }
+
+        #[cfg(feature = "proto-wol")]
+        if udp_repr.dst_port == WOL_UDP_PORT && self.process_wake_on_lan(udp_payload) {
+            return None;
+        }
+
+        // The packet wasn't handled by a socket, send an ICMP port unreachable packet.
+        self.stats.udp.no_ports += 1;
+        self.port_unreachable(ip_repr, handled_by_raw_socket, ip_payload)
+    }
+
+    /// Deliver a UDP or UDP-Lite datagram to the sockets accepting it, and return whether
+    /// there was any.
+    ///
+    /// Datagrams sent to a broadcast or multicast address are delivered to every accepting
+    /// socket with address reuse enabled; other datagrams only to the first accepting socket.
+    #[cfg(feature = "socket-udp")]
+    fn deliver_udp(
+        &mut self,
+        sockets: &mut SocketSet,
+        ip_repr: &IpRepr,
+        udp_repr: &UdpRepr,
+        payload: &[u8],
+    ) -> bool {
+        let dst_addr = ip_repr.dst_addr();
+        let shared = dst_addr.is_broadcast() || dst_addr.is_multicast();
+
+        // UDP sockets are only bound to a local endpoint.
+        let key = DemuxKey {
+            protocol: IpProtocol::Udp,
+            local: IpEndpoint::new(dst_addr, udp_repr.dst_port),
+            remote: None,
+        };
+        if !shared {
+            if let Some(udp_socket) = sockets
+                .lookup_mut(&key)
+                .and_then(|i| udp::Socket::downcast_mut(&mut i.socket))
+            {
+                if !udp_socket.reuse_addr() && udp_socket.accepts(self, ip_repr, udp_repr) {
+                    udp_socket.process(self, ip_repr, udp_repr, payload);
+                    return true;
+                }
+            }
+        }
+
+        let mut delivered = false;
+        let mut done = false;
+        let mut index = None;
+        sockets.for_each_mut(|item| {
+            let udp_socket = match udp::Socket::downcast_mut(&mut item.socket) {
+                Some(udp_socket) if !done => udp_socket,
+                _ => return false,
+            };
+            if delivered && !udp_socket.reuse_addr() {
+                return false;
+            }
+            if !udp_socket.accepts(self, ip_repr, udp_repr) {
+                return false;
+            }
+            udp_socket.process(self, ip_repr, udp_repr, payload);
+            delivered = true;
+            if !(shared && udp_socket.reuse_addr()) {
+                if !shared && !udp_socket.reuse_addr() {
+                    index = Some(item.meta.handle);
+                }
+                done = true;
             }
+            true
+        });
+        if let Some(handle) = index {
+            sockets.insert_index(&key, handle);
+        }
+        delivered
+    }
+
+    #[cfg(feature = "proto-udplite")]
+    fn process_udplite<'frame>(
+        &mut self,
+        sockets: &mut SocketSet,
+        ip_repr: IpRepr,
+        handled_by_raw_socket: bool,
+        ip_payload: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
+        let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
+        let udplite_packet = check!(
+            UdpLitePacket::new_checked(ip_payload),
+            self.stats.udp.in_errors
+        );
+        let udplite_repr = check!(
+            UdpLiteRepr::parse(&udplite_packet, &src_addr, &dst_addr, &self.caps.checksum),
+            self.stats.udp.in_errors
+        );
+        let udp_repr = UdpRepr {
+            src_port: udplite_repr.src_port,
+            dst_port: udplite_repr.dst_port,
+        };
+
+        if self.deliver_udp(sockets, &ip_repr, &udp_repr, udplite_packet.payload()) {
+            self.stats.udp.in_datagrams += 1;
+            return None;
         }
 
         // The packet wasn't handled by a socket, send an ICMP port unreachable packet.
+        self.stats.udp.no_ports += 1;
+        self.port_unreachable(ip_repr, handled_by_raw_socket, ip_payload)
+    }
+
+    #[cfg(any(
+        feature = "socket-udp",
+        feature = "socket-dns",
+        feature = "socket-sntp"
+    ))]
+    fn port_unreachable<'frame>(
+        &self,
+        ip_repr: IpRepr,
+        handled_by_raw_socket: bool,
+        ip_payload: &'frame [u8],
+    ) -> Option<IpPacket<'frame>> {
         match ip_repr {
             #[cfg(feature = "proto-ipv4")]
             IpRepr::Ipv4(_) if handled_by_raw_socket => None,
@@ -2542,17 +5074,27 @@ impl<'a> InterfaceInner<'a> {
         ip_payload: &'frame [u8],
     ) -> Option<IpPacket<'frame>> {
         let (src_addr, dst_addr) = (ip_repr.src_addr(), ip_repr.dst_addr());
-        let tcp_packet = check!(TcpPacket::new_checked(ip_payload));
-        let tcp_repr = check!(TcpRepr::parse(
-            &tcp_packet,
-            &src_addr,
-            &dst_addr,
-            &self.caps.checksum
-        ));
+        self.stats.tcp.in_segs += 1;
+        let tcp_packet = check!(TcpPacket::new_checked(ip_payload), self.stats.tcp.in_errs);
+        let tcp_repr = check!(
+            TcpRepr::parse(
+                &tcp_packet,
+                &src_addr,
+                &dst_addr,
+                &self.caps.checksum,
+                self.parse_mode
+            ),
+            self.stats.tcp.in_errs
+        );
 
-        for tcp_socket in sockets
-            .items_mut()
-            .filter_map(|i| tcp::Socket::downcast_mut(&mut i.socket))
+        let key = DemuxKey {
+            protocol: IpProtocol::Tcp,
+            local: IpEndpoint::new(dst_addr, tcp_repr.dst_port),
+            remote: Some(IpEndpoint::new(src_addr, tcp_repr.src_port)),
+        };
+        if let Some(tcp_socket) = sockets
+            .lookup_mut(&key)
+            .and_then(|i| tcp::Socket::downcast_mut(&mut i.socket))
         {
             if tcp_socket.accepts(self, &ip_repr, &tcp_repr) {
                 return tcp_socket
@@ -2561,6 +5103,27 @@ impl<'a> InterfaceInner<'a> {
             }
         }
 
+        let mut accepted = None;
+        for item in sockets.items() {
+            if let Some(tcp_socket) = tcp::Socket::downcast(&item.socket) {
+                if tcp_socket.accepts(self, &ip_repr, &tcp_repr) {
+                    let connected = tcp_socket.remote_endpoint().is_some();
+                    accepted = Some((item.meta.handle, connected));
+                    break;
+                }
+            }
+        }
+        if let Some((handle, connected)) = accepted {
+            // Only connected sockets are indexed; listening ones accept many keys.
+            if connected {
+                sockets.insert_index(&key, handle);
+            }
+            return sockets
+                .get_mut::<tcp::Socket>(handle)
+                .process(self, &ip_repr, &tcp_repr)
+                .map(IpPacket::Tcp);
+        }
+
         if tcp_repr.control == TcpControl::Rst {
             // Never reply to a TCP RST packet with another TCP RST packet.
             None
@@ -2579,6 +5142,11 @@ impl<'a> InterfaceInner<'a> {
             #[cfg(feature = "proto-ipv4")]
             EthernetPacket::Arp(arp_repr) => {
                 let dst_hardware_addr = match arp_repr {
+                    // Probes and announcements leave the target hardware address zeroed.
+                    ArpRepr::EthernetIpv4 {
+                        target_hardware_addr: EthernetAddress([0, 0, 0, 0, 0, 0]),
+                        ..
+                    } => EthernetAddress::BROADCAST,
                     ArpRepr::EthernetIpv4 {
                         target_hardware_addr,
                         ..
@@ -2604,43 +5172,61 @@ impl<'a> InterfaceInner<'a> {
         F: FnOnce(EthernetFrame<&mut [u8]>),
     {
         let tx_len = EthernetFrame::<&[u8]>::buffer_len(buffer_len);
-        tx_token.consume(self.now, tx_len, |tx_buffer| {
-            debug_assert!(tx_buffer.as_ref().len() == tx_len);
-            let mut frame = EthernetFrame::new_unchecked(tx_buffer);
+        let (dst_addr, tx_timestamp) =
+            tx_token.consume_timestamped(self.now, tx_len, |tx_buffer| {
+                debug_assert!(tx_buffer.as_ref().len() == tx_len);
+                let mut frame = EthernetFrame::new_unchecked(&mut tx_buffer[..]);
 
-            let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
-                addr
-            } else {
-                return Err(Error::Malformed);
-            };
+                let src_addr = if let Some(HardwareAddress::Ethernet(addr)) = self.hardware_addr {
+                    addr
+                } else {
+                    return Err(Error::Malformed);
+                };
 
-            frame.set_src_addr(src_addr);
+                frame.set_src_addr(src_addr);
 
-            f(frame);
+                f(frame);
 
-            Ok(())
-        })
+                Ok(EthernetFrame::new_unchecked(&tx_buffer[..]).dst_addr())
+            })?;
+        self.tx_timestamp = tx_timestamp;
+
+        self.stats.interface.out_octets += tx_len as u64;
+        if dst_addr.is_unicast() {
+            self.stats.interface.out_ucast_pkts += 1;
+        } else {
+            self.stats.interface.out_nucast_pkts += 1;
+        }
+        Ok(())
     }
 
     fn in_same_network(&self, addr: &IpAddress) -> bool {
         self.ip_addrs.iter().any(|cidr| cidr.contains_addr(addr))
     }
 
-    fn route(&self, addr: &IpAddress, timestamp: Instant) -> Result<IpAddress> {
+    fn route(
+        &self,
+        addr: &IpAddress,
+        src_addr: Option<&IpAddress>,
+        timestamp: Instant,
+    ) -> Result<IpAddress> {
         // Send directly.
         if self.in_same_network(addr) || addr.is_broadcast() {
             return Ok(*addr);
         }
 
         // Route via a router.
-        match self.routes.lookup(addr, timestamp) {
+        match self
+            .routes
+            .lookup_policy(addr, src_addr, self.egress_mark, timestamp)
+        {
             Some(router_addr) => Ok(router_addr),
             None => Err(Error::Unaddressable),
         }
     }
 
     fn has_neighbor(&self, addr: &IpAddress) -> bool {
-        match self.route(addr, self.now) {
+        match self.route(addr, None, self.now) {
             Ok(_routed_addr) => match self.caps.medium {
                 #[cfg(feature = "medium-ethernet")]
                 Medium::Ethernet => self
@@ -2719,7 +5305,13 @@ impl<'a> InterfaceInner<'a> {
             return Ok((hardware_addr, tx_token));
         }
 
-        let dst_addr = self.route(dst_addr, self.now)?;
+        let dst_addr = match self.route(dst_addr, Some(src_addr), self.now) {
+            Ok(addr) => addr,
+            Err(err) => {
+                self.stats.ip.out_no_routes += 1;
+                return Err(err);
+            }
+        };
 
         match self
             .neighbor_cache
@@ -2792,7 +5384,17 @@ impl<'a> InterfaceInner<'a> {
             _ => (),
         }
         // The request got dispatched, limit the rate on the cache.
-        self.neighbor_cache.as_mut().unwrap().limit_rate(self.now);
+        let failed_addr = self
+            .neighbor_cache
+            .as_mut()
+            .unwrap()
+            .requested(dst_addr, self.now);
+        // A router that does not answer discovery requests may be dead.
+        if let Some(failed_addr) = failed_addr {
+            if self.routes.router_failed(&failed_addr, self.now) {
+                self.touch();
+            }
+        }
         Err(Error::Unaddressable)
     }
 
@@ -2803,6 +5405,19 @@ impl<'a> InterfaceInner<'a> {
         }
     }
 
+    /// Return the payload size of the frames the device must split `packet` into,
+    /// if it is a TCP segment larger than the MTU.
+    fn segment_size(&self, _packet: &IpPacket) -> Option<usize> {
+        #[cfg(feature = "socket-tcp")]
+        if let IpPacket::Tcp((ip_repr, tcp_repr)) = _packet {
+            let mtu = self.path_mtu(&ip_repr.dst_addr());
+            if ip_repr.total_len() > mtu {
+                return Some(mtu - ip_repr.header_len() - tcp_repr.header_len());
+            }
+        }
+        None
+    }
+
     fn dispatch_ip<Tx: TxToken>(
         &mut self,
         tx_token: Tx,
@@ -2811,8 +5426,21 @@ impl<'a> InterfaceInner<'a> {
     ) -> Result<()> {
         let ip_repr = packet.ip_repr();
         assert!(!ip_repr.dst_addr().is_unspecified());
+        let kind = packet.stats_kind();
+        self.tx_timestamp = None;
 
-        match self.caps.medium {
+        let meta = PacketMeta {
+            segment_size: self.segment_size(&packet),
+            priority: self.egress_priority,
+            ..PacketMeta::default()
+        };
+        let mut tx_token = tx_token;
+        if meta != PacketMeta::default() {
+            tx_token.set_meta(meta);
+        }
+        let dscp = self.egress_dscp;
+
+        let result = match self.caps.medium {
             #[cfg(feature = "medium-ethernet")]
             Medium::Ethernet => {
                 let (dst_hardware_addr, tx_token) = match self.lookup_hardware_addr(
@@ -2836,6 +5464,9 @@ impl<'a> InterfaceInner<'a> {
                     }
 
                     ip_repr.emit(frame.payload_mut(), &caps.checksum);
+                    if let Some(dscp) = dscp {
+                        set_dscp(&ip_repr, frame.payload_mut(), dscp, &caps.checksum);
+                    }
 
                     let payload = &mut frame.payload_mut()[ip_repr.buffer_len()..];
                     packet.emit_payload(ip_repr, payload, &caps);
@@ -2844,16 +5475,30 @@ impl<'a> InterfaceInner<'a> {
             #[cfg(feature = "medium-ip")]
             Medium::Ip => {
                 let tx_len = ip_repr.total_len();
-                tx_token.consume(self.now, tx_len, |mut tx_buffer| {
-                    debug_assert!(tx_buffer.as_ref().len() == tx_len);
+                let unicast = ip_repr.dst_addr().is_unicast();
+                let ((), tx_timestamp) =
+                    tx_token.consume_timestamped(self.now, tx_len, |mut tx_buffer| {
+                        debug_assert!(tx_buffer.as_ref().len() == tx_len);
+
+                        ip_repr.emit(&mut tx_buffer, &self.caps.checksum);
+                        if let Some(dscp) = dscp {
+                            set_dscp(&ip_repr, tx_buffer, dscp, &self.caps.checksum);
+                        }
 
-                    ip_repr.emit(&mut tx_buffer, &self.caps.checksum);
+                        let payload = &mut tx_buffer[ip_repr.buffer_len()..];
+                        packet.emit_payload(ip_repr, payload, &self.caps);
 
-                    let payload = &mut tx_buffer[ip_repr.buffer_len()..];
-                    packet.emit_payload(ip_repr, payload, &self.caps);
+                        Ok(())
+                    })?;
+                self.tx_timestamp = tx_timestamp;
 
-                    Ok(())
-                })
+                self.stats.interface.out_octets += tx_len as u64;
+                if unicast {
+                    self.stats.interface.out_ucast_pkts += 1;
+                } else {
+                    self.stats.interface.out_nucast_pkts += 1;
+                }
+                Ok(())
             }
             #[cfg(feature = "medium-ieee802154")]
             Medium::Ieee802154 => {
@@ -2866,9 +5511,28 @@ impl<'a> InterfaceInner<'a> {
                     _ => unreachable!(),
                 };
 
-                self.dispatch_ieee802154(dst_hardware_addr, &ip_repr, tx_token, packet, _out_packet)
+                self.dispatch_ieee802154(
+                    dst_hardware_addr,
+                    &ip_repr,
+                    tx_token,
+                    packet,
+                    _out_packet,
+                )?;
+
+                self.stats.interface.out_octets += ip_repr.total_len() as u64;
+                if dst_hardware_addr.is_broadcast() {
+                    self.stats.interface.out_nucast_pkts += 1;
+                } else {
+                    self.stats.interface.out_ucast_pkts += 1;
+                }
+                Ok(())
             }
+        };
+
+        if result.is_ok() {
+            self.stats.ip_packet_sent(kind);
         }
+        result
     }
 
     #[cfg(all(feature = "medium-ieee802154", feature = "proto-sixlowpan"))]
@@ -3223,12 +5887,35 @@ impl<'a> InterfaceInner<'a> {
         )
     }
 
+    /// Build a membership report for `group_addr`. IGMPv3 reports describe the current
+    /// source filter of the group, and are assembled in `buffer`.
     #[cfg(feature = "proto-igmp")]
     fn igmp_report_packet<'any>(
         &self,
         version: IgmpVersion,
         group_addr: Ipv4Address,
+        buffer: &'any mut [u8],
     ) -> Option<IpPacket<'any>> {
+        if version == IgmpVersion::Version3 {
+            let filter = self
+                .ipv4_multicast_groups
+                .get(&group_addr)
+                .cloned()
+                .unwrap_or(SourceFilter::ANY);
+            let record_type = match filter.mode() {
+                FilterMode::Include => IgmpRecordType::ModeIsInclude,
+                FilterMode::Exclude => IgmpRecordType::ModeIsExclude,
+            };
+            let (_, len) = emit_igmp_group_record(
+                buffer,
+                record_type,
+                group_addr,
+                filter.sources().iter().cloned(),
+            );
+            let buffer: &'any [u8] = buffer;
+            return self.igmp_v3_report_packet(1, &buffer[..len]);
+        }
+
         let iface_addr = self.ipv4_address()?;
         let igmp_repr = IgmpRepr::MembershipReport {
             group_addr,
@@ -3250,6 +5937,87 @@ impl<'a> InterfaceInner<'a> {
         Some(pkt)
     }
 
+    /// Build an IGMPv3 report of the change of the source filter of `group_addr`
+    /// from `old` to `new`, as specified in [RFC 3376 § 5.1], in `buffer`.
+    ///
+    /// Returns `None` if the filters accept the same sources.
+    ///
+    /// [RFC 3376 § 5.1]: https://tools.ietf.org/html/rfc3376#section-5.1
+    #[cfg(feature = "proto-igmp")]
+    fn igmp_state_change_packet<'any>(
+        &self,
+        group_addr: Ipv4Address,
+        old: &SourceFilter,
+        new: &SourceFilter,
+        buffer: &'any mut [u8],
+    ) -> Option<IpPacket<'any>> {
+        let (mut len, mut num_group_records) = (0, 0);
+        if old.mode() == new.mode() {
+            let (allow, block) = match new.mode() {
+                FilterMode::Include => (new.difference(old), old.difference(new)),
+                FilterMode::Exclude => (old.difference(new), new.difference(old)),
+            };
+            let (num_srcs, record_len) =
+                emit_igmp_group_record(buffer, IgmpRecordType::AllowNewSources, group_addr, allow);
+            if num_srcs > 0 {
+                len += record_len;
+                num_group_records += 1;
+            }
+            let (num_srcs, record_len) = emit_igmp_group_record(
+                &mut buffer[len..],
+                IgmpRecordType::BlockOldSources,
+                group_addr,
+                block,
+            );
+            if num_srcs > 0 {
+                len += record_len;
+                num_group_records += 1;
+            }
+        } else {
+            let record_type = match new.mode() {
+                FilterMode::Include => IgmpRecordType::ChangeToInclude,
+                FilterMode::Exclude => IgmpRecordType::ChangeToExclude,
+            };
+            let (_, record_len) = emit_igmp_group_record(
+                buffer,
+                record_type,
+                group_addr,
+                new.sources().iter().cloned(),
+            );
+            len += record_len;
+            num_group_records += 1;
+        }
+
+        if num_group_records == 0 {
+            return None;
+        }
+        let buffer: &'any [u8] = buffer;
+        self.igmp_v3_report_packet(num_group_records, &buffer[..len])
+    }
+
+    #[cfg(feature = "proto-igmp")]
+    fn igmp_v3_report_packet<'any>(
+        &self,
+        num_group_records: u16,
+        data: &'any [u8],
+    ) -> Option<IpPacket<'any>> {
+        let iface_addr = self.ipv4_address()?;
+        let igmp_repr = IgmpRepr::MembershipReportV3 {
+            num_group_records,
+            data,
+        };
+        Some(IpPacket::Igmp((
+            Ipv4Repr {
+                src_addr: iface_addr,
+                dst_addr: Ipv4Address::MULTICAST_ALL_IGMPV3_ROUTERS,
+                next_header: IpProtocol::Igmp,
+                payload_len: igmp_repr.buffer_len(),
+                hop_limit: 1,
+            },
+            igmp_repr,
+        )))
+    }
+
     #[cfg(feature = "proto-igmp")]
     fn igmp_leave_packet<'any>(&self, group_addr: Ipv4Address) -> Option<IpPacket<'any>> {
         self.ipv4_address().map(|iface_addr| {
@@ -3268,6 +6036,50 @@ impl<'a> InterfaceInner<'a> {
     }
 }
 
+/// Set the DSCP field of the IP header emitted from `ip_repr` into `buffer`, keeping
+/// the ECN field.
+fn set_dscp(ip_repr: &IpRepr, buffer: &mut [u8], dscp: u8, _checksum_caps: &ChecksumCapabilities) {
+    match ip_repr {
+        #[cfg(feature = "proto-ipv4")]
+        IpRepr::Ipv4(_) => {
+            let mut packet = Ipv4Packet::new_unchecked(buffer);
+            packet.set_dscp(dscp);
+            if _checksum_caps.ipv4.tx() {
+                packet.fill_checksum();
+            }
+        }
+        #[cfg(feature = "proto-ipv6")]
+        IpRepr::Ipv6(_) => {
+            let mut packet = Ipv6Packet::new_unchecked(buffer);
+            let ecn = packet.traffic_class() & 0x03;
+            packet.set_traffic_class(dscp << 2 | ecn);
+        }
+    }
+}
+
+/// Emit an IGMPv3 group record with the given sources into `buffer`, returning the number
+/// of sources and the length of the record.
+#[cfg(feature = "proto-igmp")]
+fn emit_igmp_group_record(
+    buffer: &mut [u8],
+    record_type: IgmpRecordType,
+    group_addr: Ipv4Address,
+    sources: impl Iterator<Item = Ipv4Address>,
+) -> (u16, usize) {
+    let mut record = IgmpGroupRecord::new_unchecked(buffer);
+    record.set_record_type(record_type);
+    record.set_aux_data_len(0);
+    record.set_group_addr(group_addr);
+    let mut num_srcs = 0;
+    for source in sources {
+        record.set_num_srcs(num_srcs + 1);
+        record.set_source(num_srcs as usize, source);
+        num_srcs += 1;
+    }
+    record.set_num_srcs(num_srcs);
+    (num_srcs, record.record_len())
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::BTreeMap;
@@ -3279,7 +6091,15 @@ mod test {
     use crate::iface::Interface;
     #[cfg(feature = "medium-ethernet")]
     use crate::iface::NeighborCache;
-    use crate::phy::{ChecksumCapabilities, Loopback};
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    use crate::iface::Route;
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    use crate::iface::RoutePolicy;
+    use crate::phy::{ChecksumCapabilities, Loopback, PacketMeta};
     #[cfg(feature = "proto-igmp")]
     use crate::time::Instant;
     use crate::{Error, Result};
@@ -3314,13 +6134,18 @@ mod test {
 
         let iface_builder = InterfaceBuilder::new().ip_addrs(ip_addrs);
 
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(PacketAssemblerSet::new(vec![], BTreeMap::new()))
+            .sixlowpan_out_packet_cache(vec![]);
+
         #[cfg(feature = "proto-ipv4-fragmentation")]
         let iface_builder =
             iface_builder.ipv4_fragments_cache(PacketAssemblerSet::new(vec![], BTreeMap::new()));
 
         #[cfg(feature = "proto-igmp")]
         let iface_builder = iface_builder.ipv4_multicast_groups(BTreeMap::new());
-        let iface = iface_builder.finalize(&mut device);
+        let iface = iface_builder.try_finalize(&mut device).unwrap();
 
         (iface, SocketSet::new(vec![]), device)
     }
@@ -3354,12 +6179,12 @@ mod test {
 
         #[cfg(feature = "proto-igmp")]
         let iface_builder = iface_builder.ipv4_multicast_groups(BTreeMap::new());
-        let iface = iface_builder.finalize(&mut device);
+        let iface = iface_builder.try_finalize(&mut device).unwrap();
 
         (iface, SocketSet::new(vec![]), device)
     }
 
-    #[cfg(feature = "proto-igmp")]
+    #[cfg(any(feature = "medium-ethernet", feature = "proto-igmp"))]
     fn recv_all(device: &mut Loopback, timestamp: Instant) -> Vec<Vec<u8>> {
         let mut pkts = Vec::new();
         while let Some((rx, _tx)) = device.receive() {
@@ -3372,6 +6197,120 @@ mod test {
         pkts
     }
 
+    /// A loopback device that reports the given metadata for every received packet,
+    /// and the given transmit timestamp for every transmitted one.
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    struct MetaDevice {
+        inner: Loopback,
+        meta: PacketMeta,
+        tx_timestamp: Option<Instant>,
+    }
+
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    struct MetaRxToken<Rx: RxToken> {
+        token: Rx,
+        meta: PacketMeta,
+    }
+
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    impl<Rx: RxToken> RxToken for MetaRxToken<Rx> {
+        fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> Result<R>,
+        {
+            self.token.consume(timestamp, f)
+        }
+
+        fn meta(&self) -> PacketMeta {
+            self.meta
+        }
+    }
+
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    struct MetaTxToken<Tx: TxToken> {
+        token: Tx,
+        tx_timestamp: Option<Instant>,
+    }
+
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    impl<Tx: TxToken> TxToken for MetaTxToken<Tx> {
+        fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+        where
+            F: FnOnce(&mut [u8]) -> Result<R>,
+        {
+            self.token.consume(timestamp, len, f)
+        }
+
+        fn consume_timestamped<R, F>(
+            self,
+            timestamp: Instant,
+            len: usize,
+            f: F,
+        ) -> Result<(R, Option<Instant>)>
+        where
+            F: FnOnce(&mut [u8]) -> Result<R>,
+        {
+            let tx_timestamp = self.tx_timestamp;
+            self.consume(timestamp, len, f)
+                .map(|result| (result, tx_timestamp))
+        }
+    }
+
+    #[cfg(all(
+        feature = "medium-ip",
+        feature = "proto-ipv4",
+        any(feature = "socket-udp", feature = "socket-raw")
+    ))]
+    impl<'a> Device<'a> for MetaDevice {
+        type RxToken = MetaRxToken<<Loopback as Device<'a>>::RxToken>;
+        type TxToken = MetaTxToken<<Loopback as Device<'a>>::TxToken>;
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            self.inner.capabilities()
+        }
+
+        fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+            let meta = self.meta;
+            let tx_timestamp = self.tx_timestamp;
+            let (token, tx_token) = self.inner.receive()?;
+            Some((
+                MetaRxToken { token, meta },
+                MetaTxToken {
+                    token: tx_token,
+                    tx_timestamp,
+                },
+            ))
+        }
+
+        fn transmit(&'a mut self) -> Option<Self::TxToken> {
+            let tx_timestamp = self.tx_timestamp;
+            self.inner.transmit().map(|token| MetaTxToken {
+                token,
+                tx_timestamp,
+            })
+        }
+    }
+
     #[derive(Debug, PartialEq)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     struct MockTxToken;
@@ -3388,11 +6327,38 @@ mod test {
     #[test]
     #[should_panic(expected = "hardware_addr required option was not set")]
     #[cfg(all(feature = "medium-ethernet"))]
+    #[allow(deprecated)]
     fn test_builder_initialization_panic() {
         let mut device = Loopback::new(Medium::Ethernet);
         InterfaceBuilder::new().finalize(&mut device);
     }
 
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "medium-ip"))]
+    fn test_builder_try_finalize() {
+        let mut device = Loopback::new(Medium::Ethernet);
+        assert_eq!(
+            InterfaceBuilder::new().try_finalize(&mut device).err(),
+            Some(BuildError::HardwareAddrRequired)
+        );
+        assert_eq!(
+            InterfaceBuilder::new()
+                .hardware_addr(EthernetAddress::default().into())
+                .try_finalize(&mut device)
+                .err(),
+            Some(BuildError::NeighborCacheRequired)
+        );
+
+        let mut device = Loopback::new(Medium::Ip);
+        assert_eq!(
+            InterfaceBuilder::new()
+                .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+                .try_finalize(&mut device)
+                .err(),
+            Some(BuildError::NeighborCacheUnused)
+        );
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_no_icmp_no_unicast_ipv4() {
@@ -3525,21 +6491,301 @@ mod test {
     }
 
     #[test]
-    #[cfg(feature = "proto-ipv4")]
-    fn test_local_subnet_broadcasts() {
-        let (mut iface, _, _device) = create();
-        iface.update_ip_addrs(|addrs| {
-            addrs.iter_mut().next().map(|addr| {
-                *addr = IpCidr::Ipv4(Ipv4Cidr::new(Ipv4Address([192, 168, 1, 23]), 24));
-            });
-        });
+    #[cfg(all(feature = "proto-ipv4", feature = "socket-raw"))]
+    fn test_unknown_protocol_policy() {
+        use crate::wire::IpVersion;
 
-        assert!(iface
-            .inner
-            .is_subnet_broadcast(Ipv4Address([192, 168, 1, 255])),);
-        assert!(!iface
-            .inner
-            .is_subnet_broadcast(Ipv4Address([192, 168, 1, 254])),);
+        let (mut iface, mut sockets, _device) = create();
+        assert_eq!(
+            iface.unknown_protocol_policy(),
+            UnknownProtocolPolicy::DeliverToRawSocket
+        );
+
+        let repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Unknown(0x0c),
+            payload_len: 0,
+            hop_limit: 0x40,
+        });
+        let mut bytes = vec![0u8; 20];
+        repr.emit(&mut bytes, &ChecksumCapabilities::default());
+        let frame = Ipv4Packet::new_unchecked(&bytes);
+
+        let is_icmp_error = |packet: Option<IpPacket>| match packet {
+            Some(IpPacket::Icmpv4((_, Icmpv4Repr::DstUnreachable { reason, .. }))) => {
+                reason == Icmpv4DstUnreachable::ProtoUnreachable
+            }
+            None => false,
+            packet => panic!("unexpected reply {:?}", packet),
+        };
+
+        #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+        macro_rules! process {
+            () => {
+                is_icmp_error(iface.inner.process_ipv4(&mut sockets, &frame, None))
+            };
+        }
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        macro_rules! process {
+            () => {
+                is_icmp_error(iface.inner.process_ipv4(
+                    &mut sockets,
+                    &frame,
+                    Some(&mut iface.fragments.ipv4_fragments),
+                ))
+            };
+        }
+
+        // Without a raw socket, only the drop policy suppresses the ICMP error.
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::Drop);
+        assert!(!process!());
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::IcmpError);
+        assert!(process!());
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::DeliverToRawSocket);
+        assert!(process!());
+        assert_eq!(iface.inner.stats.ip.in_unknown_protos, 3);
+
+        let raw_socket = raw::Socket::new(
+            IpVersion::Ipv4,
+            IpProtocol::Unknown(0x0c),
+            raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 4], vec![0; 128]),
+            raw::PacketBuffer::new(vec![raw::PacketMetadata::EMPTY; 1], vec![0; 0]),
+        );
+        let handle = sockets.add(raw_socket);
+
+        // With a raw socket, the packet is delivered whatever the policy, and only the
+        // ICMP error policy replies.
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::Drop);
+        assert!(!process!());
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::IcmpError);
+        assert!(process!());
+        iface.set_unknown_protocol_policy(UnknownProtocolPolicy::DeliverToRawSocket);
+        assert!(!process!());
+        assert_eq!(iface.inner.stats.ip.in_unknown_protos, 3);
+
+        let socket = sockets.get_mut::<raw::Socket>(handle);
+        for _ in 0..3 {
+            assert_eq!(socket.recv(), Ok(&bytes[..]));
+        }
+        assert!(!socket.can_recv());
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_parse_mode() {
+        let (mut iface, mut sockets, _device) = create();
+        assert_eq!(iface.parse_mode(), ParseMode::Permissive);
+
+        let repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Unknown(0x0c),
+            payload_len: 0,
+            hop_limit: 0x40,
+        });
+        let mut bytes = vec![0u8; 20];
+        repr.emit(&mut bytes, &ChecksumCapabilities::default());
+        // Set the reserved flag.
+        bytes[6] |= 0x80;
+        let mut packet = Ipv4Packet::new_unchecked(&mut bytes);
+        packet.fill_checksum();
+        let frame = Ipv4Packet::new_unchecked(&bytes);
+
+        #[cfg(not(feature = "proto-ipv4-fragmentation"))]
+        macro_rules! process {
+            () => {
+                iface.inner.process_ipv4(&mut sockets, &frame, None)
+            };
+        }
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        macro_rules! process {
+            () => {
+                iface.inner.process_ipv4(
+                    &mut sockets,
+                    &frame,
+                    Some(&mut iface.fragments.ipv4_fragments),
+                )
+            };
+        }
+
+        // The permissive interface ignores the reserved flag, and reports the unknown protocol.
+        assert!(process!().is_some());
+        assert_eq!(iface.inner.stats.ip.in_hdr_errors, 0);
+
+        iface.set_parse_mode(ParseMode::Strict);
+        assert_eq!(process!(), None);
+        assert_eq!(iface.inner.stats.ip.in_hdr_errors, 1);
+    }
+
+    /// Emit `packet` to the interface, in a frame padded to the minimum Ethernet frame size
+    /// with nonzero octets.
+    #[cfg(feature = "medium-ethernet")]
+    fn padded_frame<P: crate::wire::builder::Layer>(packet: P) -> Vec<u8> {
+        use crate::wire::builder::{ethernet, Layer};
+
+        let frame = ethernet(
+            EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]),
+            EthernetAddress::default(),
+            packet,
+        );
+        let mut bytes = vec![0xa5; 60];
+        assert!(frame.emit(&mut bytes).unwrap() < bytes.len());
+        bytes
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    fn test_padded_frame_udp() {
+        use crate::wire::builder::{ipv4, udp};
+
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 64]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 0]);
+        let mut udp_socket = udp::Socket::new(rx_buffer, tx_buffer);
+        udp_socket.bind(68).unwrap();
+        let handle = sockets.add(udp_socket);
+
+        let frame = padded_frame(ipv4(
+            Ipv4Address([127, 0, 0, 2]),
+            Ipv4Address([127, 0, 0, 1]),
+            udp(67, 68, &b"hello"[..]),
+        ));
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+        // A runt frame, without the padding, is received as well.
+        let frame = &frame[..EthernetFrame::<&[u8]>::buffer_len(20 + 8 + 5)];
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+
+        let socket = sockets.get_mut::<udp::Socket>(handle);
+        let mut received = 0;
+        while let Ok((payload, _)) = socket.recv() {
+            assert_eq!(payload, b"hello");
+            received += 1;
+        }
+        assert_eq!(received, 2);
+        assert_eq!(iface.inner.stats.udp.in_errors, 0);
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-tcp"
+    ))]
+    fn test_padded_frame_tcp() {
+        use crate::wire::builder::{ipv4, tcp};
+
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        // A SYN carrying data to a closed port is reset, acknowledging the SYN and the data,
+        // but none of the padding.
+        let tcp_repr = TcpRepr {
+            src_port: 49152,
+            dst_port: 80,
+            control: TcpControl::Syn,
+            seq_number: TcpSeqNumber(1000),
+            ack_number: None,
+            window_len: 1024,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None, None, None],
+            payload: b"hello",
+        };
+        let frame = padded_frame(ipv4(
+            Ipv4Address([127, 0, 0, 2]),
+            Ipv4Address([127, 0, 0, 1]),
+            tcp(tcp_repr),
+        ));
+        match iface
+            .inner
+            .process_ethernet(&mut sockets, &frame, &mut iface.fragments)
+        {
+            Some(EthernetPacket::Ip(IpPacket::Tcp((_, tcp_repr)))) => {
+                assert_eq!(tcp_repr.control, TcpControl::Rst);
+                assert_eq!(tcp_repr.ack_number, Some(TcpSeqNumber(1000 + 1 + 5)));
+            }
+            packet => panic!("unexpected reply {:?}", packet),
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_padded_frame_icmp() {
+        use crate::wire::builder::{icmpv4, ipv4};
+
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        // The echo reply carries the data of the request, but none of the padding.
+        let frame = padded_frame(ipv4(
+            Ipv4Address([127, 0, 0, 2]),
+            Ipv4Address([127, 0, 0, 1]),
+            icmpv4(Icmpv4Repr::EchoRequest {
+                ident: 0x1234,
+                seq_no: 1,
+                data: b"ping",
+            }),
+        ));
+        match iface
+            .inner
+            .process_ethernet(&mut sockets, &frame, &mut iface.fragments)
+        {
+            Some(EthernetPacket::Ip(IpPacket::Icmpv4((ipv4_repr, icmp_repr)))) => {
+                assert_eq!(
+                    icmp_repr,
+                    Icmpv4Repr::EchoReply {
+                        ident: 0x1234,
+                        seq_no: 1,
+                        data: b"ping",
+                    }
+                );
+                assert_eq!(ipv4_repr.payload_len, icmp_repr.buffer_len());
+            }
+            packet => panic!("unexpected reply {:?}", packet),
+        }
+
+        // A frame truncating the IP packet is dropped.
+        let frame = &frame[..EthernetFrame::<&[u8]>::buffer_len(20 + 8 + 3)];
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+        assert_eq!(iface.inner.stats.ip.in_hdr_errors, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_local_subnet_broadcasts() {
+        let (mut iface, _, _device) = create();
+        iface.update_ip_addrs(|addrs| {
+            addrs.iter_mut().next().map(|addr| {
+                *addr = IpCidr::Ipv4(Ipv4Cidr::new(Ipv4Address([192, 168, 1, 23]), 24));
+            });
+        });
+
+        assert!(iface
+            .inner
+            .is_subnet_broadcast(Ipv4Address([192, 168, 1, 255])),);
+        assert!(!iface
+            .inner
+            .is_subnet_broadcast(Ipv4Address([192, 168, 1, 254])),);
 
         iface.update_ip_addrs(|addrs| {
             addrs.iter_mut().next().map(|addr| {
@@ -3578,6 +6824,74 @@ mod test {
             .is_subnet_broadcast(Ipv4Address([192, 255, 255, 255])),);
     }
 
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_icmp_error_to_udp_socket() {
+        let (mut iface, mut sockets, _device) = create();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let tx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let socket_handle = sockets.add(udp::Socket::new(rx_buffer, tx_buffer));
+        sockets
+            .get_mut::<udp::Socket>(socket_handle)
+            .bind(67)
+            .unwrap();
+
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+        let mut udp_bytes = vec![0u8; udp_repr.header_len()];
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(&mut udp_bytes),
+            &IpAddress::v4(127, 0, 0, 1),
+            &IpAddress::v4(127, 0, 0, 2),
+            0,
+            |_| (),
+            &ChecksumCapabilities::default(),
+        );
+
+        let icmp_repr = Icmpv4Repr::DstUnreachable {
+            reason: Icmpv4DstUnreachable::PortUnreachable,
+            header: Ipv4Repr {
+                src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+                dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+                next_header: IpProtocol::Udp,
+                payload_len: udp_repr.header_len(),
+                hop_limit: 64,
+            },
+            data: &udp_bytes,
+        };
+        let mut icmp_bytes = vec![0u8; icmp_repr.buffer_len()];
+        icmp_repr.emit(
+            &mut Icmpv4Packet::new_unchecked(&mut icmp_bytes),
+            &ChecksumCapabilities::default(),
+        );
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Icmp,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 64,
+        });
+
+        assert_eq!(
+            iface
+                .inner
+                .process_icmpv4(&mut sockets, ip_repr, &icmp_bytes),
+            None
+        );
+        let socket = sockets.get_mut::<udp::Socket>(socket_handle);
+        assert_eq!(
+            socket.take_icmp_error(),
+            Some((
+                IcmpError::PortUnreachable,
+                IpEndpoint::new(IpAddress::v4(127, 0, 0, 2), 68)
+            ))
+        );
+        assert_eq!(socket.take_icmp_error(), None);
+    }
+
     #[test]
     #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
     fn test_icmp_error_port_unreachable() {
@@ -3677,50 +6991,699 @@ mod test {
     }
 
     #[test]
-    #[cfg(feature = "socket-udp")]
-    fn test_handle_udp_broadcast() {
-        use crate::wire::IpEndpoint;
-
-        static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
-
-        let (mut iface, mut sockets, _device) = create();
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    fn test_egress_burst() {
+        let (mut iface, mut sockets, mut device) = create_ethernet();
 
-        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
-        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 8], vec![0; 64]);
+        let socket_handle = sockets.add(udp::Socket::new(rx_buffer, tx_buffer));
+        let socket = sockets.get_mut::<udp::Socket>(socket_handle);
+        socket.bind(68).unwrap();
+
+        let mut send_and_count = |iface: &mut Interface, count: usize| {
+            let socket = sockets.get_mut::<udp::Socket>(socket_handle);
+            for _ in 0..count {
+                socket
+                    .send_slice(b"burst", IpEndpoint::from((Ipv4Address::BROADCAST, 67)))
+                    .unwrap();
+            }
+            assert!(iface.socket_egress(&mut device, &mut sockets));
+            let mut frames = 0;
+            while device.receive().is_some() {
+                frames += 1;
+            }
+            frames
+        };
 
-        let udp_socket = udp::Socket::new(rx_buffer, tx_buffer);
+        // All queued datagrams are sent when servicing the socket once.
+        assert_eq!(iface.egress_burst(), DEFAULT_EGRESS_BURST);
+        assert_eq!(send_and_count(&mut iface, 4), 4);
 
-        let mut udp_bytes = vec![0u8; 13];
-        let mut packet = UdpPacket::new_unchecked(&mut udp_bytes);
+        // The rest of the queue is left for the next time.
+        iface.set_egress_burst(2);
+        assert_eq!(send_and_count(&mut iface, 3), 2);
+        assert_eq!(send_and_count(&mut iface, 0), 1);
+    }
 
-        let socket_handle = sockets.add(udp_socket);
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    fn test_egress_priority() {
+        let (mut iface, mut sockets, mut device) = create_ethernet();
+
+        let mut udp_socket = |port| {
+            let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+            let tx_buffer =
+                udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 2], vec![0; 16]);
+            let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+            socket.bind(port).unwrap();
+            for _ in 0..2 {
+                socket
+                    .send_slice(b"prio", IpEndpoint::from((Ipv4Address::BROADCAST, 67)))
+                    .unwrap();
+            }
+            sockets.add(socket)
+        };
+        udp_socket(68);
+        let handle = udp_socket(69);
+        sockets.set_priority(handle, SocketPriority::Control);
+
+        // The higher priority socket sends its whole queue first.
+        assert!(iface.socket_egress(&mut device, &mut sockets));
+        let mut ports = vec![];
+        while let Some((rx_token, _)) = device.receive() {
+            rx_token
+                .consume(Instant::from_millis(0), |frame| {
+                    let frame = EthernetFrame::new_checked(&*frame)?;
+                    let packet = Ipv4Packet::new_checked(frame.payload())?;
+                    let udp_packet = UdpPacket::new_checked(packet.payload())?;
+                    ports.push(udp_packet.src_port());
+                    Ok(())
+                })
+                .unwrap();
+        }
+        assert_eq!(ports, vec![69, 69, 68, 68]);
+    }
 
-        #[cfg(feature = "proto-ipv6")]
-        let src_ip = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
-        #[cfg(all(not(feature = "proto-ipv6"), feature = "proto-ipv4"))]
-        let src_ip = Ipv4Address::new(0x7f, 0x00, 0x00, 0x02);
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    fn test_egress_dscp() {
+        let (mut iface, mut sockets, mut device) = create_ethernet();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 1], vec![0; 16]);
+        let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+        socket.bind(68).unwrap();
+        socket
+            .send_slice(b"dscp", IpEndpoint::from((Ipv4Address::BROADCAST, 67)))
+            .unwrap();
+        let handle = sockets.add(socket);
+        sockets.set_dscp(handle, Some(46));
+
+        assert!(iface.socket_egress(&mut device, &mut sockets));
+        let (rx_token, _) = device.receive().unwrap();
+        rx_token
+            .consume(Instant::from_millis(0), |frame| {
+                let frame = EthernetFrame::new_checked(&*frame)?;
+                let packet = Ipv4Packet::new_checked(frame.payload())?;
+                assert_eq!(packet.dscp(), 46);
+                assert!(packet.verify_checksum());
+                Ok(())
+            })
+            .unwrap();
+    }
 
-        let udp_repr = UdpRepr {
-            src_port: 67,
-            dst_port: 68,
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        feature = "socket-udp"
+    ))]
+    fn test_egress_mark() {
+        let (mut iface, mut sockets, mut device) = create_ethernet();
+        let default_router = IpAddress::v4(127, 0, 0, 254);
+        let marked_router = Ipv4Address::new(127, 0, 0, 253);
+        let routes = iface.routes_mut();
+        routes.update(|storage| *storage = BTreeMap::new().into());
+        routes.set_policy_storage(BTreeMap::new());
+        routes
+            .add_default_ipv4_route(Ipv4Address::new(127, 0, 0, 254))
+            .unwrap();
+        let policy = RoutePolicy {
+            dst: IpCidr::new(IpAddress::v4(0, 0, 0, 0), 0),
+            src: None,
+            mark: Some(1),
         };
+        routes
+            .add_policy_route(policy, Route::new_ipv4_gateway(marked_router))
+            .unwrap();
 
-        #[cfg(feature = "proto-ipv6")]
-        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
-            src_addr: src_ip,
-            dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
-            next_header: IpProtocol::Udp,
-            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
-            hop_limit: 0x40,
-        });
-        #[cfg(all(not(feature = "proto-ipv6"), feature = "proto-ipv4"))]
-        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
-            src_addr: src_ip,
-            dst_addr: Ipv4Address::BROADCAST,
-            next_header: IpProtocol::Udp,
-            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
-            hop_limit: 0x40,
-        });
+        let remote_addr = IpAddress::v4(10, 0, 0, 1);
+        let local_addr = IpAddress::v4(127, 0, 0, 1);
+        assert_eq!(
+            iface
+                .inner
+                .route(&remote_addr, Some(&local_addr), Instant::ZERO),
+            Ok(default_router)
+        );
+
+        let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 1], vec![0; 16]);
+        let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+        socket.bind(68).unwrap();
+        socket
+            .send_slice(b"mark", IpEndpoint::from((remote_addr, 67)))
+            .unwrap();
+        let handle = sockets.add(socket);
+        sockets.set_mark(handle, Some(1));
+
+        // The router of the policy route is resolved.
+        iface.socket_egress(&mut device, &mut sockets);
+        let (rx_token, _) = device.receive().unwrap();
+        rx_token
+            .consume(Instant::ZERO, |frame| {
+                let frame = EthernetFrame::new_checked(&*frame)?;
+                let arp_repr = ArpRepr::parse(&ArpPacket::new_checked(frame.payload())?)?;
+                match arp_repr {
+                    ArpRepr::EthernetIpv4 {
+                        target_protocol_addr,
+                        ..
+                    } => assert_eq!(target_protocol_addr, marked_router),
+                    #[allow(unreachable_patterns)]
+                    _ => unreachable!(),
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_path_mtu() {
+        let (mut iface, _, _) = create_ethernet();
+        let local_addr = IpAddress::v4(127, 0, 0, 2);
+        let remote_addr = IpAddress::v4(10, 0, 0, 1);
+        let tunnel_addr = IpAddress::v4(10, 1, 0, 1);
+        let routes = iface.routes_mut();
+        routes.update(|storage| *storage = BTreeMap::new().into());
+        routes
+            .add_default_ipv4_route(Ipv4Address::new(127, 0, 0, 254))
+            .unwrap();
+        routes.update(|storage| {
+            let route = Route::new_ipv4_gateway(Ipv4Address::new(127, 0, 0, 253)).with_mtu(1400);
+            storage.insert(IpCidr::new(tunnel_addr, 16), route).unwrap();
+        });
+
+        let device_mtu = iface.ip_mtu();
+        assert_eq!(iface.inner.path_mtu(&local_addr), device_mtu);
+        assert_eq!(iface.inner.path_mtu(&remote_addr), device_mtu);
+        assert_eq!(iface.inner.path_mtu(&tunnel_addr), 1400);
+
+        // The route MTU overrides the interface MTU, but neither exceeds the device MTU.
+        iface.set_ip_mtu(Some(1300));
+        assert_eq!(iface.ip_mtu(), 1300);
+        assert_eq!(iface.inner.path_mtu(&local_addr), 1300);
+        assert_eq!(iface.inner.path_mtu(&remote_addr), 1300);
+        assert_eq!(iface.inner.path_mtu(&tunnel_addr), 1400);
+        iface.set_ip_mtu(Some(device_mtu + 100));
+        assert_eq!(iface.ip_mtu(), device_mtu);
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ethernet")]
+    fn test_ingress_burst() {
+        let (mut iface, mut sockets, mut device) = create_ethernet();
+
+        let enqueue = |device: &mut Loopback, count: usize| {
+            for _ in 0..count {
+                let tx_token = device.transmit().unwrap();
+                tx_token
+                    .consume(Instant::from_millis(0), 14, |buf| {
+                        let mut frame = EthernetFrame::new_unchecked(buf);
+                        frame.set_dst_addr(EthernetAddress::BROADCAST);
+                        frame.set_src_addr(EthernetAddress([0x02, 0, 0, 0, 0, 0x02]));
+                        frame.set_ethertype(EthernetProtocol::Unknown(0x88b5));
+                        Ok(())
+                    })
+                    .unwrap();
+            }
+        };
+
+        // Every frame is received by a single poll.
+        assert_eq!(iface.ingress_burst(), None);
+        enqueue(&mut device, 5);
+        assert_eq!(
+            iface.poll(Instant::from_millis(0), &mut device, &mut sockets),
+            Ok(true)
+        );
+        assert_eq!(
+            iface.poll(Instant::from_millis(0), &mut device, &mut sockets),
+            Ok(false)
+        );
+
+        // The rest of the frames are left for the next polls, which are due right away.
+        iface.set_ingress_burst(Some(2));
+        enqueue(&mut device, 5);
+        for _ in 0..2 {
+            assert_eq!(
+                iface.poll(Instant::from_millis(0), &mut device, &mut sockets),
+                Ok(true)
+            );
+            assert_eq!(
+                iface.poll_at(Instant::from_millis(0), &sockets),
+                Some(Instant::from_millis(0))
+            );
+        }
+        assert_eq!(
+            iface.poll(Instant::from_millis(0), &mut device, &mut sockets),
+            Ok(true)
+        );
+        assert_eq!(
+            iface.poll(Instant::from_millis(0), &mut device, &mut sockets),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4", feature = "medium-ip"))]
+    fn test_ingress_egress() {
+        let (mut iface, mut sockets, mut device) = create_ip();
+
+        let udp_socket = || {
+            let buffer = || udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 16]);
+            udp::Socket::new(buffer(), buffer())
+        };
+        let mut sender = udp_socket();
+        sender.bind(67).unwrap();
+        let mut receiver = udp_socket();
+        receiver.bind(68).unwrap();
+        let sender = sockets.add(sender);
+        let receiver = sockets.add(receiver);
+
+        let timestamp = Instant::from_millis(0);
+        let remote = IpEndpoint::from((IpAddress::v4(127, 0, 0, 1), 68));
+        sockets
+            .get_mut::<udp::Socket>(sender)
+            .send_slice(b"hello", remote)
+            .unwrap();
+
+        // Nothing is sent while receiving.
+        assert_eq!(
+            iface.ingress(timestamp, &mut device, &mut sockets),
+            Ok(false)
+        );
+        assert!(!sockets.get::<udp::Socket>(sender).can_send());
+
+        // The datagram is sent, and left in the device until it is received.
+        assert_eq!(iface.egress(timestamp, &mut device, &mut sockets), Ok(true));
+        assert!(sockets.get::<udp::Socket>(sender).can_send());
+        assert!(!sockets.get::<udp::Socket>(receiver).can_recv());
+        assert_eq!(
+            iface.egress(timestamp, &mut device, &mut sockets),
+            Ok(false)
+        );
+
+        assert_eq!(
+            iface.ingress(timestamp, &mut device, &mut sockets),
+            Ok(true)
+        );
+        let mut buf = [0; 16];
+        let (len, _) = sockets
+            .get_mut::<udp::Socket>(receiver)
+            .recv_slice(&mut buf)
+            .unwrap();
+        assert_eq!(&buf[..len], b"hello");
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_poll_at_cached() {
+        let (mut iface, mut sockets, mut device) = create();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 16]);
+        let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+        socket.bind(67).unwrap();
+        let handle = sockets.add(socket);
+
+        let timestamp = Instant::from_millis(0);
+        let idle = iface.poll_at(timestamp, &sockets);
+        assert_eq!(iface.poll_at(timestamp, &sockets), idle);
+
+        // Changing a socket invalidates the cached deadline.
+        let remote = IpEndpoint::from((IpAddress::v4(127, 0, 0, 1), 68));
+        sockets
+            .get_mut::<udp::Socket>(handle)
+            .send_slice(b"hello", remote)
+            .unwrap();
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(true));
+        assert_eq!(iface.poll_at(timestamp, &sockets), idle);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_poll_at_skips_idle_sockets() {
+        let (mut iface, mut sockets, mut device) = create();
+
+        let handles: Vec<_> = (0..8)
+            .map(|port| {
+                let rx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+                let tx_buffer =
+                    udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 16]);
+                let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+                socket.bind(1000 + port).unwrap();
+                sockets.add(socket)
+            })
+            .collect();
+        let visited = |iface: &mut Interface, sockets: &SocketSet| {
+            let mut visited = 0;
+            let key = (iface.inner.index, iface.inner.generation);
+            sockets.poll_at(key, |item| {
+                visited += 1;
+                iface.inner.socket_poll_at(item)
+            });
+            visited
+        };
+
+        let timestamp = Instant::from_millis(0);
+        assert_eq!(visited(&mut iface, &sockets), 8);
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(false));
+        assert_eq!(visited(&mut iface, &sockets), 0);
+
+        // Only the socket that changed is visited, and polling again does not visit the
+        // others either.
+        let remote = IpEndpoint::from((IpAddress::v4(127, 0, 0, 1), 68));
+        sockets
+            .get_mut::<udp::Socket>(handles[3])
+            .send_slice(b"hello", remote)
+            .unwrap();
+        assert_eq!(visited(&mut iface, &sockets), 1);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+        let generation = iface.inner.generation;
+        for millis in 0..3 {
+            let timestamp = Instant::from_millis(millis);
+            iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+            assert_eq!(visited(&mut iface, &sockets), 0);
+        }
+        assert_eq!(iface.inner.generation, generation);
+        assert_eq!(iface.poll_at(timestamp, &sockets), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_handle_udp_reuse_addr() {
+        let (mut iface, mut sockets, _device) = create();
+
+        let udp_socket = || {
+            let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+            let tx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+            let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+            socket.bind(68).unwrap();
+            socket.set_reuse_addr(true);
+            socket
+        };
+        let handles = [
+            sockets.add(udp_socket()),
+            sockets.add(udp_socket()),
+            sockets.add(udp_socket()),
+        ];
+        sockets
+            .get_mut::<udp::Socket>(handles[2])
+            .set_bound_interface(Some(1));
+
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+        let mut udp_bytes = vec![0u8; 13];
+        let mut send = |iface: &mut Interface, dst_addr| {
+            let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+                src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+                dst_addr,
+                next_header: IpProtocol::Udp,
+                payload_len: udp_repr.header_len() + 5,
+                hop_limit: 0x40,
+            });
+            let mut packet = UdpPacket::new_unchecked(&mut udp_bytes);
+            udp_repr.emit(
+                &mut packet,
+                &ip_repr.src_addr(),
+                &ip_repr.dst_addr(),
+                5,
+                |buf| buf.copy_from_slice(b"hello"),
+                &ChecksumCapabilities::default(),
+            );
+            iface
+                .inner
+                .process_udp(&mut sockets, ip_repr, false, packet.into_inner());
+            handles
+                .iter()
+                .map(|handle| sockets.get_mut::<udp::Socket>(*handle).recv().is_ok())
+                .collect::<Vec<_>>()
+        };
+
+        // Broadcasts are received by every socket sharing the port on this interface,
+        // unicasts only by the first one.
+        assert_eq!(
+            send(&mut iface, Ipv4Address::BROADCAST),
+            [true, true, false]
+        );
+        assert_eq!(
+            send(&mut iface, Ipv4Address::new(127, 0, 0, 1)),
+            [true, false, false]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4"))]
+    fn test_handle_udp_demux_index() {
+        let (mut iface, mut sockets, _device) = create();
+
+        let udp_socket = |port| {
+            let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+            let tx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+            let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+            socket.bind(port).unwrap();
+            socket
+        };
+        sockets.add(udp_socket(68));
+        sockets.add(udp_socket(69));
+        let handle = sockets.add(udp_socket(70));
+
+        let dst_addr = Ipv4Address::new(127, 0, 0, 1);
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 70,
+        };
+        let key = DemuxKey {
+            protocol: IpProtocol::Udp,
+            local: IpEndpoint::new(dst_addr.into(), 70),
+            remote: None,
+        };
+        let mut udp_bytes = vec![0u8; 13];
+        let mut send = |iface: &mut Interface, sockets: &mut SocketSet| {
+            let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+                src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+                dst_addr,
+                next_header: IpProtocol::Udp,
+                payload_len: udp_repr.header_len() + 5,
+                hop_limit: 0x40,
+            });
+            let mut packet = UdpPacket::new_unchecked(&mut udp_bytes);
+            udp_repr.emit(
+                &mut packet,
+                &ip_repr.src_addr(),
+                &ip_repr.dst_addr(),
+                5,
+                |buf| buf.copy_from_slice(b"hello"),
+                &ChecksumCapabilities::default(),
+            );
+            iface
+                .inner
+                .process_udp(sockets, ip_repr, false, packet.into_inner())
+                .is_none()
+        };
+
+        assert!(sockets.lookup_mut(&key).is_none());
+        assert!(send(&mut iface, &mut sockets));
+        assert_eq!(sockets.lookup_mut(&key).unwrap().meta.handle, handle);
+
+        // The index is used for the next datagram.
+        sockets.get_mut::<udp::Socket>(handle).recv().unwrap();
+        assert!(send(&mut iface, &mut sockets));
+        assert!(sockets.get_mut::<udp::Socket>(handle).recv().is_ok());
+
+        // Removing the socket invalidates the index.
+        sockets.remove(handle);
+        assert!(sockets.lookup_mut(&key).is_none());
+        assert!(!send(&mut iface, &mut sockets));
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-raw", feature = "proto-ipv4", feature = "medium-ip"))]
+    fn test_raw_socket_timestamps() {
+        let (mut iface, mut sockets, device) = create_ip();
+        let mut meta = PacketMeta::default();
+        meta.timestamp = Some(Instant::from_micros(1500));
+        let mut device = MetaDevice {
+            inner: device,
+            meta,
+            tx_timestamp: Some(Instant::from_micros(1000)),
+        };
+
+        let packets = 1;
+        let rx_buffer = raw::PacketBuffer::new(
+            vec![raw::PacketMetadata::EMPTY; packets],
+            vec![0; 48 * packets],
+        );
+        let tx_buffer = raw::PacketBuffer::new(
+            vec![raw::PacketMetadata::EMPTY; packets],
+            vec![0; 48 * packets],
+        );
+        let raw_socket = raw::Socket::new(
+            IpVersion::Ipv4,
+            IpProtocol::Unknown(63),
+            rx_buffer,
+            tx_buffer,
+        );
+        let handle = sockets.add(raw_socket);
+
+        // Send a packet to ourselves through the device.
+        let ip_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x01),
+            dst_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x01),
+            next_header: IpProtocol::Unknown(63),
+            payload_len: 4,
+            hop_limit: 0x40,
+        };
+        let mut bytes = vec![0; 24];
+        ip_repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        let socket = sockets.get_mut::<raw::Socket>(handle);
+        socket.send_slice(&bytes).unwrap();
+        assert_eq!(socket.tx_timestamp(), None);
+
+        iface
+            .poll(Instant::from_millis(0), &mut device, &mut sockets)
+            .unwrap();
+        iface
+            .poll(Instant::from_millis(0), &mut device, &mut sockets)
+            .unwrap();
+
+        let socket = sockets.get_mut::<raw::Socket>(handle);
+        assert_eq!(socket.tx_timestamp(), Some(Instant::from_micros(1000)));
+        let (_, timestamp) = socket.recv_timestamped().unwrap();
+        assert_eq!(timestamp, Some(Instant::from_micros(1500)));
+    }
+
+    #[test]
+    #[cfg(all(feature = "socket-udp", feature = "proto-ipv4", feature = "medium-ip"))]
+    fn test_rx_checksum_verified() {
+        let (mut iface, mut sockets, device) = create_ip();
+        let mut device = MetaDevice {
+            inner: device,
+            meta: PacketMeta::default(),
+            tx_timestamp: None,
+        };
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![], vec![]);
+        let mut socket = udp::Socket::new(rx_buffer, tx_buffer);
+        socket.bind(68).unwrap();
+        let handle = sockets.add(socket);
+
+        // Send a datagram with a corrupted UDP checksum.
+        let enqueue = |device: &mut MetaDevice| {
+            let ip_repr = Ipv4Repr {
+                src_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x02),
+                dst_addr: Ipv4Address::new(0x7f, 0x00, 0x00, 0x01),
+                next_header: IpProtocol::Udp,
+                payload_len: 8 + 5,
+                hop_limit: 0x40,
+            };
+            let udp_repr = UdpRepr {
+                src_port: 67,
+                dst_port: 68,
+            };
+            let tx_token = device.transmit().unwrap();
+            tx_token
+                .consume(Instant::from_millis(0), 20 + 8 + 5, |buf| {
+                    let mut packet = Ipv4Packet::new_unchecked(buf);
+                    ip_repr.emit(&mut packet, &ChecksumCapabilities::default());
+                    let mut udp_packet = UdpPacket::new_unchecked(packet.payload_mut());
+                    udp_repr.emit(
+                        &mut udp_packet,
+                        &ip_repr.src_addr.into(),
+                        &ip_repr.dst_addr.into(),
+                        5,
+                        |buf| buf.copy_from_slice(b"hello"),
+                        &ChecksumCapabilities::default(),
+                    );
+                    let checksum = udp_packet.checksum();
+                    udp_packet.set_checksum(!checksum);
+                    Ok(())
+                })
+                .unwrap();
+        };
+
+        // The interface drops the datagram...
+        enqueue(&mut device);
+        iface
+            .poll(Instant::from_millis(0), &mut device, &mut sockets)
+            .unwrap();
+        assert!(!sockets.get_mut::<udp::Socket>(handle).can_recv());
+
+        // ...unless the device reports the checksum as already verified.
+        device.meta.checksum_verified = true;
+        enqueue(&mut device);
+        iface
+            .poll(Instant::from_millis(0), &mut device, &mut sockets)
+            .unwrap();
+        assert!(sockets.get_mut::<udp::Socket>(handle).can_recv());
+        assert!(iface.inner.caps.checksum.udp.rx());
+    }
+
+    #[test]
+    #[cfg(feature = "socket-udp")]
+    fn test_handle_udp_broadcast() {
+        use crate::wire::IpEndpoint;
+
+        static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
+
+        let (mut iface, mut sockets, _device) = create();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+
+        let udp_socket = udp::Socket::new(rx_buffer, tx_buffer);
+
+        let mut udp_bytes = vec![0u8; 13];
+        let mut packet = UdpPacket::new_unchecked(&mut udp_bytes);
+
+        let socket_handle = sockets.add(udp_socket);
+
+        #[cfg(feature = "proto-ipv6")]
+        let src_ip = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        #[cfg(all(not(feature = "proto-ipv6"), feature = "proto-ipv4"))]
+        let src_ip = Ipv4Address::new(0x7f, 0x00, 0x00, 0x02);
+
+        let udp_repr = UdpRepr {
+            src_port: 67,
+            dst_port: 68,
+        };
+
+        #[cfg(feature = "proto-ipv6")]
+        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr: src_ip,
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+            hop_limit: 0x40,
+        });
+        #[cfg(all(not(feature = "proto-ipv6"), feature = "proto-ipv4"))]
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: src_ip,
+            dst_addr: Ipv4Address::BROADCAST,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + UDP_PAYLOAD.len(),
+            hop_limit: 0x40,
+        });
 
         // Bind the socket to port 68
         let socket = sockets.get_mut::<udp::Socket>(socket_handle);
@@ -3750,7 +7713,9 @@ mod test {
         let socket = sockets.get_mut::<udp::Socket>(socket_handle);
         assert!(socket.can_recv());
         assert_eq!(
-            socket.recv(),
+            socket
+                .recv()
+                .map(|(payload, meta)| (payload, meta.endpoint)),
             Ok((&UDP_PAYLOAD[..], IpEndpoint::new(src_ip.into(), 67)))
         );
     }
@@ -4024,7 +7989,7 @@ mod test {
         });
 
         let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
-        frame.set_dst_addr(EthernetAddress([0x33, 0x33, 0x00, 0x00, 0x00, 0x00]));
+        frame.set_dst_addr(EthernetAddress([0x33, 0x33, 0xff, 0x00, 0x00, 0x01]));
         frame.set_src_addr(remote_hw_addr);
         frame.set_ethertype(EthernetProtocol::Ipv6);
         ip_repr.emit(frame.payload_mut(), &ChecksumCapabilities::default());
@@ -4116,29 +8081,122 @@ mod test {
     }
 
     #[test]
-    #[cfg(all(
-        feature = "medium-ethernet",
-        feature = "proto-ipv4",
-        not(feature = "medium-ieee802154")
-    ))]
-    fn test_arp_flush_after_update_ip() {
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_arp_protection() {
         let (mut iface, mut sockets, _device) = create_ethernet();
 
-        let mut eth_bytes = vec![0u8; 42];
-
         let local_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x01]);
         let remote_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x02]);
         let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
         let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let spoofed_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x66]);
 
-        let repr = ArpRepr::EthernetIpv4 {
-            operation: ArpOperation::Request,
-            source_hardware_addr: remote_hw_addr,
-            source_protocol_addr: remote_ip_addr,
-            target_hardware_addr: EthernetAddress::default(),
-            target_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        let reply = |source_hardware_addr| {
+            let repr = ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Reply,
+                source_hardware_addr,
+                source_protocol_addr: remote_ip_addr,
+                target_hardware_addr: local_hw_addr,
+                target_protocol_addr: local_ip_addr,
+            };
+            let mut frame = EthernetFrame::new_unchecked(vec![0u8; 42]);
+            frame.set_dst_addr(local_hw_addr);
+            frame.set_src_addr(source_hardware_addr);
+            frame.set_ethertype(EthernetProtocol::Arp);
+            repr.emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+            frame.into_inner()
         };
-
+        let lookup = |iface: &mut Interface| {
+            iface.inner.lookup_hardware_addr(
+                MockTxToken,
+                &IpAddress::Ipv4(local_ip_addr),
+                &IpAddress::Ipv4(remote_ip_addr),
+            )
+        };
+
+        iface.set_arp_protection(ArpProtection::Strict);
+        let frame = reply(remote_hw_addr);
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+        assert_eq!(
+            lookup(&mut iface),
+            Ok((HardwareAddress::Ethernet(remote_hw_addr), MockTxToken))
+        );
+
+        // Ensure a reply claiming the address for another host is ignored
+        let frame = reply(spoofed_hw_addr);
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+        assert_eq!(
+            lookup(&mut iface),
+            Ok((HardwareAddress::Ethernet(remote_hw_addr), MockTxToken))
+        );
+
+        // Ensure the current owner of the address is probed instead
+        iface.set_arp_protection(ArpProtection::Probe);
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            Some(EthernetPacket::Arp(ArpRepr::EthernetIpv4 {
+                operation: ArpOperation::Request,
+                source_hardware_addr: local_hw_addr,
+                source_protocol_addr: local_ip_addr,
+                target_hardware_addr: remote_hw_addr,
+                target_protocol_addr: remote_ip_addr
+            }))
+        );
+        assert_eq!(
+            lookup(&mut iface),
+            Ok((HardwareAddress::Ethernet(remote_hw_addr), MockTxToken))
+        );
+
+        // Without a confirmation, the entry expires and the new address is accepted
+        iface.inner.now = Instant::ZERO + ArpProtection::PROBE_TIMEOUT;
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &frame, &mut iface.fragments),
+            None
+        );
+        assert_eq!(
+            lookup(&mut iface),
+            Ok((HardwareAddress::Ethernet(spoofed_hw_addr), MockTxToken))
+        );
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "medium-ethernet",
+        feature = "proto-ipv4",
+        not(feature = "medium-ieee802154")
+    ))]
+    fn test_arp_flush_after_update_ip() {
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        let mut eth_bytes = vec![0u8; 42];
+
+        let local_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x01]);
+        let remote_ip_addr = Ipv4Address([0x7f, 0x00, 0x00, 0x02]);
+        let local_hw_addr = EthernetAddress([0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+
+        let repr = ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr: remote_hw_addr,
+            source_protocol_addr: remote_ip_addr,
+            target_hardware_addr: EthernetAddress::default(),
+            target_protocol_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+        };
+
         let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
         frame.set_dst_addr(EthernetAddress::BROADCAST);
         frame.set_src_addr(remote_hw_addr);
@@ -4343,35 +8401,43 @@ mod test {
         );
     }
 
+    /// Receive the IGMP packets sent through the loopback device, returning their
+    /// IPv4 headers and IGMP payloads.
+    #[cfg(feature = "proto-igmp")]
+    fn recv_igmp(device: &mut Loopback, timestamp: Instant) -> Vec<(Ipv4Repr, Vec<u8>)> {
+        let caps = device.capabilities();
+        let checksum_caps = &caps.checksum;
+        recv_all(device, timestamp)
+            .iter()
+            .filter_map(|frame| {
+                let ipv4_packet = match caps.medium {
+                    #[cfg(feature = "medium-ethernet")]
+                    Medium::Ethernet => {
+                        let eth_frame = EthernetFrame::new_checked(frame).ok()?;
+                        Ipv4Packet::new_checked(eth_frame.payload()).ok()?
+                    }
+                    #[cfg(feature = "medium-ip")]
+                    Medium::Ip => Ipv4Packet::new_checked(&frame[..]).ok()?,
+                    #[cfg(feature = "medium-ieee802154")]
+                    Medium::Ieee802154 => todo!(),
+                };
+                let ipv4_repr =
+                    Ipv4Repr::parse(&ipv4_packet, checksum_caps, ParseMode::Permissive).ok()?;
+                let ip_payload = ipv4_packet.payload();
+                IgmpPacket::new_checked(ip_payload).ok()?;
+                Some((ipv4_repr, ip_payload.to_vec()))
+            })
+            .collect::<Vec<_>>()
+    }
+
+    #[cfg(feature = "proto-igmp")]
+    fn parse_igmp(payload: &[u8]) -> IgmpRepr<'_> {
+        IgmpRepr::parse(&IgmpPacket::new_checked(payload).unwrap()).unwrap()
+    }
+
     #[test]
     #[cfg(feature = "proto-igmp")]
     fn test_handle_igmp() {
-        fn recv_igmp(device: &mut Loopback, timestamp: Instant) -> Vec<(Ipv4Repr, IgmpRepr)> {
-            let caps = device.capabilities();
-            let checksum_caps = &caps.checksum;
-            recv_all(device, timestamp)
-                .iter()
-                .filter_map(|frame| {
-                    let ipv4_packet = match caps.medium {
-                        #[cfg(feature = "medium-ethernet")]
-                        Medium::Ethernet => {
-                            let eth_frame = EthernetFrame::new_checked(frame).ok()?;
-                            Ipv4Packet::new_checked(eth_frame.payload()).ok()?
-                        }
-                        #[cfg(feature = "medium-ip")]
-                        Medium::Ip => Ipv4Packet::new_checked(&frame[..]).ok()?,
-                        #[cfg(feature = "medium-ieee802154")]
-                        Medium::Ieee802154 => todo!(),
-                    };
-                    let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, checksum_caps).ok()?;
-                    let ip_payload = ipv4_packet.payload();
-                    let igmp_packet = IgmpPacket::new_checked(ip_payload).ok()?;
-                    let igmp_repr = IgmpRepr::parse(&igmp_packet).ok()?;
-                    Some((ipv4_repr, igmp_repr))
-                })
-                .collect::<Vec<_>>()
-        }
-
         let groups = [
             Ipv4Address::new(224, 0, 0, 22),
             Ipv4Address::new(224, 0, 0, 56),
@@ -4393,7 +8459,7 @@ mod test {
             assert_eq!(reports[i].0.next_header, IpProtocol::Igmp);
             assert_eq!(reports[i].0.dst_addr, *group_addr);
             assert_eq!(
-                reports[i].1,
+                parse_igmp(&reports[i].1),
                 IgmpRepr::MembershipReport {
                     group_addr: *group_addr,
                     version: IgmpVersion::Version2,
@@ -4423,7 +8489,7 @@ mod test {
         // loopback have been processed, including responses to
         // GENERAL_QUERY_BYTES. Therefore `recv_all()` would return 0
         // pkts that could be checked.
-        iface.socket_ingress(&mut device, &mut sockets);
+        iface.socket_ingress(&mut device, &mut sockets, &mut None);
 
         // Leave multicast groups
         let timestamp = Instant::now();
@@ -4438,8 +8504,133 @@ mod test {
         for (i, group_addr) in groups.iter().cloned().enumerate() {
             assert_eq!(leaves[i].0.next_header, IpProtocol::Igmp);
             assert_eq!(leaves[i].0.dst_addr, Ipv4Address::MULTICAST_ALL_ROUTERS);
-            assert_eq!(leaves[i].1, IgmpRepr::LeaveGroup { group_addr });
+            assert_eq!(
+                parse_igmp(&leaves[i].1),
+                IgmpRepr::LeaveGroup { group_addr }
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "proto-igmp")]
+    fn test_handle_igmpv3_source_filter() {
+        /// Return the group records of the single IGMPv3 report in `packets`.
+        fn group_records(
+            packets: &[(Ipv4Repr, Vec<u8>)],
+        ) -> Vec<(IgmpRecordType, Ipv4Address, Vec<Ipv4Address>)> {
+            assert_eq!(packets.len(), 1);
+            assert_eq!(
+                packets[0].0.dst_addr,
+                Ipv4Address::MULTICAST_ALL_IGMPV3_ROUTERS
+            );
+            let (num_group_records, mut data) = match parse_igmp(&packets[0].1) {
+                IgmpRepr::MembershipReportV3 {
+                    num_group_records,
+                    data,
+                } => (num_group_records, data),
+                repr => panic!("unexpected {}", repr),
+            };
+            let mut records = Vec::new();
+            for _ in 0..num_group_records {
+                let record = IgmpGroupRecord::new_checked(data).unwrap();
+                let sources = (0..record.num_srcs() as usize)
+                    .map(|i| record.source(i))
+                    .collect();
+                records.push((record.record_type(), record.group_addr(), sources));
+                data = &data[record.record_len()..];
+            }
+            records
         }
+
+        let group = Ipv4Address::new(232, 1, 1, 1);
+        let source_a = Ipv4Address::new(192, 0, 2, 1);
+        let source_b = Ipv4Address::new(192, 0, 2, 2);
+
+        let (mut iface, _sockets, mut device) = create();
+        let timestamp = Instant::now();
+
+        // Join a source-specific group
+        let filter = SourceFilter::new(FilterMode::Include, &[source_a]).unwrap();
+        assert_eq!(
+            iface.join_multicast_group_with_filter(&mut device, group, filter, timestamp),
+            Ok(true)
+        );
+        assert_eq!(
+            group_records(&recv_igmp(&mut device, timestamp)),
+            vec![(IgmpRecordType::AllowNewSources, group, vec![source_a])]
+        );
+        assert!(iface.inner.accepts_multicast_source(group, source_a));
+        assert!(!iface.inner.accepts_multicast_source(group, source_b));
+
+        // Joining again with the same filter doesn't send a report
+        assert_eq!(
+            iface.join_multicast_group_with_filter(&mut device, group, filter, timestamp),
+            Ok(false)
+        );
+        assert!(recv_igmp(&mut device, timestamp).is_empty());
+
+        // Replace the source
+        let filter = SourceFilter::new(FilterMode::Include, &[source_b]).unwrap();
+        iface
+            .join_multicast_group_with_filter(&mut device, group, filter, timestamp)
+            .unwrap();
+        assert_eq!(
+            group_records(&recv_igmp(&mut device, timestamp)),
+            vec![
+                (IgmpRecordType::AllowNewSources, group, vec![source_b]),
+                (IgmpRecordType::BlockOldSources, group, vec![source_a]),
+            ]
+        );
+
+        // Change the filter mode
+        let filter = SourceFilter::new(FilterMode::Exclude, &[source_a]).unwrap();
+        iface
+            .join_multicast_group_with_filter(&mut device, group, filter, timestamp)
+            .unwrap();
+        assert_eq!(
+            group_records(&recv_igmp(&mut device, timestamp)),
+            vec![(IgmpRecordType::ChangeToExclude, group, vec![source_a])]
+        );
+        assert!(!iface.inner.accepts_multicast_source(group, source_a));
+        assert!(iface.inner.accepts_multicast_source(group, source_b));
+
+        // Answer an IGMPv3 group-specific query with the current state
+        let query = IgmpRepr::MembershipQueryV3 {
+            max_resp_time: Duration::from_secs(1),
+            group_addr: group,
+            s_flag: false,
+            qrv: 2,
+            qqic: 125,
+            num_srcs: 0,
+            data: &[],
+        };
+        let mut bytes = vec![0; query.buffer_len()];
+        query.emit(&mut IgmpPacket::new_unchecked(&mut bytes[..]));
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new(192, 168, 1, 1),
+            dst_addr: group,
+            next_header: IpProtocol::Igmp,
+            payload_len: bytes.len(),
+            hop_limit: 1,
+        };
+        assert_eq!(iface.inner.process_igmp(ipv4_repr, &bytes), None);
+        let timestamp = timestamp + Duration::from_secs(1);
+        iface.inner.now = timestamp;
+        assert_eq!(iface.igmp_egress(&mut device), Ok(true));
+        assert_eq!(
+            group_records(&recv_igmp(&mut device, timestamp)),
+            vec![(IgmpRecordType::ModeIsExclude, group, vec![source_a])]
+        );
+
+        // Leave the group
+        assert_eq!(
+            iface.leave_multicast_group(&mut device, group, timestamp),
+            Ok(true)
+        );
+        assert_eq!(
+            group_records(&recv_igmp(&mut device, timestamp)),
+            vec![(IgmpRecordType::ChangeToInclude, group, vec![])]
+        );
     }
 
     #[test]
@@ -4612,8 +8803,1262 @@ mod test {
         let socket = sockets.get_mut::<udp::Socket>(udp_socket_handle);
         assert!(socket.can_recv());
         assert_eq!(
-            socket.recv(),
+            socket
+                .recv()
+                .map(|(payload, meta)| (payload, meta.endpoint)),
             Ok((&UDP_PAYLOAD[..], IpEndpoint::new(src_addr.into(), 67)))
         );
     }
+
+    #[test]
+    #[cfg(feature = "proto-lldp")]
+    fn test_lldp_advertise() {
+        let (iface, mut sockets, mut device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                lldp: Some(Lldp::new(LldpConfig::new(b"eth0"))),
+                ..iface.inner
+            },
+            ..iface
+        };
+
+        let timestamp = Instant::from_secs(0);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+
+        let frames = recv_all(&mut device, timestamp);
+        assert_eq!(frames.len(), 1);
+        let eth_frame = EthernetFrame::new_checked(&frames[0][..]).unwrap();
+        assert_eq!(eth_frame.dst_addr(), LLDP_MULTICAST_ADDR);
+        assert_eq!(eth_frame.ethertype(), EthernetProtocol::Lldp);
+        let packet = LldpPacket::new_checked(eth_frame.payload()).unwrap();
+        let repr = LldpRepr::parse(&packet).unwrap();
+        assert_eq!(repr.chassis_id_subtype, LldpChassisIdSubtype::MacAddress);
+        assert_eq!(repr.chassis_id, EthernetAddress::default().as_bytes());
+        assert_eq!(repr.port_id, b"eth0");
+        assert_eq!(repr.ttl, 120);
+
+        // Nothing more is sent until the interval elapses.
+        let next = timestamp + LldpConfig::DEFAULT_INTERVAL;
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(next));
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        assert!(recv_all(&mut device, timestamp).is_empty());
+        iface.poll(next, &mut device, &mut sockets).unwrap();
+        assert_eq!(recv_all(&mut device, next).len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ethernet")]
+    fn test_transmit_raw_frame() {
+        let (mut iface, _sockets, mut device) = create_ethernet();
+        let timestamp = Instant::from_secs(0);
+        let dst_addr = EthernetAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+        let ethertype = EthernetProtocol::Unknown(0x88b5);
+
+        assert_eq!(
+            iface.transmit_raw_frame(timestamp, &mut device, dst_addr, ethertype, b"hello"),
+            Ok(())
+        );
+        let frames = recv_all(&mut device, timestamp);
+        assert_eq!(frames.len(), 1);
+        let eth_frame = EthernetFrame::new_checked(&frames[0][..]).unwrap();
+        assert_eq!(eth_frame.dst_addr(), dst_addr);
+        assert_eq!(eth_frame.src_addr(), EthernetAddress::default());
+        assert_eq!(eth_frame.ethertype(), ethertype);
+        assert_eq!(eth_frame.payload(), b"hello");
+        assert_eq!(iface.stats().interface.out_nucast_pkts, 1);
+
+        // The frame must fit into the MTU of the device.
+        let payload = vec![0; 65535 - 14 + 1];
+        assert_eq!(
+            iface.transmit_raw_frame(timestamp, &mut device, dst_addr, ethertype, &payload),
+            Err(Error::Truncated)
+        );
+        assert!(recv_all(&mut device, timestamp).is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "medium-ip"))]
+    fn test_transmit_raw_frame_medium_ip() {
+        let (mut iface, _sockets, mut device) = create_ip();
+        assert_eq!(
+            iface.transmit_raw_frame(
+                Instant::from_secs(0),
+                &mut device,
+                EthernetAddress::BROADCAST,
+                EthernetProtocol::Unknown(0x88b5),
+                b"hello"
+            ),
+            Err(Error::Illegal)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-lldp")]
+    fn test_lldp_neighbor() {
+        let (iface, mut sockets, _device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                lldp: Some(Lldp::new(LldpConfig::new(b"eth0"))),
+                ..iface.inner
+            },
+            ..iface
+        };
+
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let mut repr = LldpRepr {
+            chassis_id_subtype: LldpChassisIdSubtype::MacAddress,
+            chassis_id: remote_hw_addr.as_bytes(),
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id: b"ge-0/0/1",
+            ttl: 120,
+            port_description: None,
+            system_name: Some(b"switch"),
+            system_description: None,
+            system_capabilities: Some((LldpCapabilities::BRIDGE, LldpCapabilities::BRIDGE)),
+        };
+        let frame_for = |repr: &LldpRepr| {
+            let mut eth_bytes = vec![0u8; 14 + repr.buffer_len()];
+            let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+            frame.set_dst_addr(LLDP_MULTICAST_ADDR);
+            frame.set_src_addr(remote_hw_addr);
+            frame.set_ethertype(EthernetProtocol::Lldp);
+            repr.emit(&mut LldpPacket::new_unchecked(frame.payload_mut()));
+            eth_bytes
+        };
+
+        let eth_bytes = frame_for(&repr);
+        iface.inner.now = Instant::from_secs(10);
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments),
+            None
+        );
+        let neighbor = iface.lldp_neighbor().unwrap();
+        assert_eq!(neighbor.hardware_addr(), remote_hw_addr);
+        assert_eq!(
+            neighbor.port_id(),
+            (LldpPortIdSubtype::InterfaceName, &b"ge-0/0/1"[..])
+        );
+        assert_eq!(neighbor.system_name(), Some(&b"switch"[..]));
+        assert_eq!(neighbor.expires_at(), Instant::from_secs(130));
+
+        // The advertisement expires after its TTL.
+        iface.inner.now = Instant::from_secs(130);
+        assert_eq!(iface.lldp_neighbor(), None);
+
+        // A shutdown advertisement removes the neighbor immediately.
+        iface.inner.now = Instant::from_secs(10);
+        repr.ttl = 0;
+        let eth_bytes = frame_for(&repr);
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments);
+        assert_eq!(iface.lldp_neighbor(), None);
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6-ra"))]
+    fn test_router_advert() {
+        let prefixes = [
+            NdiscPrefixInformation {
+                prefix_len: 64,
+                flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+                valid_lifetime: Duration::from_secs(86400),
+                preferred_lifetime: Duration::from_secs(14400),
+                prefix: Ipv6Address::new(0xfdbe, 0, 0, 1, 0, 0, 0, 0),
+            },
+            NdiscPrefixInformation {
+                prefix_len: 64,
+                flags: NdiscPrefixInfoFlags::ON_LINK,
+                valid_lifetime: Duration::from_secs(86400),
+                preferred_lifetime: Duration::from_secs(14400),
+                prefix: Ipv6Address::new(0xfdbe, 0, 0, 2, 0, 0, 0, 0),
+            },
+        ];
+        let link_local_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let config = RouterAdvertConfig {
+            mtu: Some(1280),
+            ..RouterAdvertConfig::new(&prefixes)
+        };
+
+        let (iface, mut sockets, mut device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                ip_addrs: vec![IpCidr::new(link_local_addr.into(), 64)].into(),
+                router_advert: Some(RouterAdvert::new(config)),
+                ..iface.inner
+            },
+            ..iface
+        };
+
+        let recv_adverts = |device: &mut Loopback, timestamp: Instant| {
+            recv_all(device, timestamp)
+                .iter()
+                .map(|frame| {
+                    let eth_frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+                    let ipv6_packet = Ipv6Packet::new_checked(eth_frame.payload()).unwrap();
+                    assert_eq!(ipv6_packet.src_addr(), link_local_addr);
+                    assert_eq!(ipv6_packet.dst_addr(), Ipv6Address::LINK_LOCAL_ALL_NODES);
+                    assert_eq!(ipv6_packet.hop_limit(), 0xff);
+                    let icmp_packet = Icmpv6Packet::new_checked(ipv6_packet.payload()).unwrap();
+                    match Icmpv6Repr::parse(
+                        &ipv6_packet.src_addr().into(),
+                        &ipv6_packet.dst_addr().into(),
+                        &icmp_packet,
+                        &ChecksumCapabilities::default(),
+                        ParseMode::Permissive,
+                    )
+                    .unwrap()
+                    {
+                        Icmpv6Repr::Ndisc(NdiscRepr::RouterAdvert {
+                            router_lifetime,
+                            mtu,
+                            prefix_info,
+                            ..
+                        }) => {
+                            assert_eq!(router_lifetime, Duration::from_secs(1800));
+                            assert_eq!(mtu, Some(1280));
+                            prefix_info.unwrap()
+                        }
+                        repr => panic!("unexpected {:?}", repr),
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Each prefix is advertised separately.
+        let timestamp = Instant::from_secs(0);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        assert_eq!(recv_adverts(&mut device, timestamp), prefixes);
+
+        // The initial advertisements are sent at a shorter interval.
+        let next = timestamp + Duration::from_secs(16);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(next));
+
+        // Router solicitations are answered, but rate limited.
+        let timestamp = Instant::from_secs(1);
+        iface.inner.now = timestamp;
+        let solicit_repr = Ipv6Repr {
+            src_addr: Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2),
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_ROUTERS,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: 8,
+            hop_limit: 0xff,
+        };
+        assert_eq!(
+            iface
+                .inner
+                .process_ndisc(solicit_repr, NdiscRepr::RouterSolicit { lladdr: None }),
+            None
+        );
+        assert_eq!(
+            iface.poll_at(timestamp, &sockets),
+            Some(Instant::from_secs(3))
+        );
+        iface
+            .poll(Instant::from_secs(3), &mut device, &mut sockets)
+            .unwrap();
+        assert_eq!(recv_adverts(&mut device, timestamp).len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn test_address_conflict_detection() {
+        let local_hw_addr = EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        let remote_hw_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]);
+        let local_addr = Ipv4Address::new(192, 168, 1, 1);
+        let remote_addr = Ipv4Address::new(192, 168, 1, 2);
+
+        let (iface, mut sockets, mut device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                hardware_addr: Some(local_hw_addr.into()),
+                ip_addrs: vec![IpCidr::new(local_addr.into(), 24)].into(),
+                acd: Some(Acd::new(
+                    AcdConfig {
+                        probe_wait: Duration::ZERO,
+                        probe_max: AcdConfig::DEFAULT_PROBE_MIN,
+                        ..AcdConfig::default()
+                    },
+                    ManagedMap::Owned(BTreeMap::new()),
+                )),
+                ..iface.inner
+            },
+            ..iface
+        };
+        iface.inner.sync_acd();
+
+        let recv_arp = |device: &mut Loopback, timestamp| {
+            recv_all(device, timestamp)
+                .iter()
+                .map(|frame| {
+                    let eth_frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+                    assert_eq!(eth_frame.dst_addr(), EthernetAddress::BROADCAST);
+                    let arp_packet = ArpPacket::new_checked(eth_frame.payload()).unwrap();
+                    ArpRepr::parse(&arp_packet).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        let arp_repr = |source_protocol_addr| ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr: local_hw_addr,
+            source_protocol_addr,
+            target_hardware_addr: EthernetAddress([0; 6]),
+            target_protocol_addr: local_addr,
+        };
+
+        // The address is not used while it is probed.
+        assert_eq!(
+            iface.ipv4_addr_state(local_addr),
+            Some(Ipv4AddressState::Probing)
+        );
+        assert_eq!(iface.inner.get_source_address_ipv4(remote_addr), None);
+
+        for secs in 0..3 {
+            let timestamp = Instant::from_secs(secs);
+            assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+            iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+            assert_eq!(
+                recv_arp(&mut device, timestamp),
+                [arp_repr(Ipv4Address::UNSPECIFIED)]
+            );
+        }
+
+        // Without an answer, the address is claimed and announced.
+        let timestamp = Instant::from_secs(4);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        assert_eq!(recv_arp(&mut device, timestamp), [arp_repr(local_addr)]);
+        assert_eq!(
+            iface.ipv4_addr_state(local_addr),
+            Some(Ipv4AddressState::Announcing)
+        );
+        assert_eq!(
+            iface.inner.get_source_address_ipv4(remote_addr),
+            Some(local_addr)
+        );
+
+        // A conflicting ARP packet is answered with an announcement once...
+        let mut eth_bytes = vec![0u8; 42];
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(remote_hw_addr);
+        frame.set_ethertype(EthernetProtocol::Arp);
+        ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Request,
+            source_hardware_addr: remote_hw_addr,
+            source_protocol_addr: local_addr,
+            target_hardware_addr: EthernetAddress([0; 6]),
+            target_protocol_addr: remote_addr,
+        }
+        .emit(&mut ArpPacket::new_unchecked(frame.payload_mut()));
+        let frame = EthernetFrame::new_checked(&eth_bytes).unwrap();
+        assert_eq!(
+            iface.inner.process_arp(timestamp, &frame),
+            Some(EthernetPacket::Arp(arp_repr(local_addr)))
+        );
+
+        // ... and the address is given up if the conflict persists.
+        assert_eq!(
+            iface
+                .inner
+                .process_arp(timestamp + Duration::from_secs(1), &frame),
+            None
+        );
+        assert_eq!(
+            iface.ipv4_addr_state(local_addr),
+            Some(Ipv4AddressState::Conflict)
+        );
+        assert_eq!(
+            iface.conflicting_ipv4_addrs().collect::<Vec<_>>(),
+            [local_addr]
+        );
+        assert_eq!(iface.inner.get_source_address_ipv4(remote_addr), None);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4-autoip")]
+    fn test_autoip() {
+        let local_hw_addr = EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        let routable_addr = Ipv4Address::new(192, 168, 1, 1);
+
+        let mut device = Loopback::new(Medium::Ethernet);
+        let mut sockets = SocketSet::new(vec![]);
+        let iface_builder = InterfaceBuilder::new()
+            .hardware_addr(local_hw_addr.into())
+            .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+            .ip_addrs(vec![])
+            .ipv4_acd(AcdConfig::default(), BTreeMap::new())
+            .ipv4_autoip(AutoIpConfig::default());
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder =
+            iface_builder.ipv4_fragments_cache(PacketAssemblerSet::new(vec![], BTreeMap::new()));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(PacketAssemblerSet::new(vec![], BTreeMap::new()))
+            .sixlowpan_out_packet_cache(vec![]);
+        let mut iface = iface_builder.try_finalize(&mut device).unwrap();
+
+        // Without a routable address, a link-local address is configured after a delay.
+        iface
+            .poll(Instant::from_secs(0), &mut device, &mut sockets)
+            .unwrap();
+        assert_eq!(iface.autoip_addr(), None);
+        let timestamp = Instant::ZERO + AutoIpConfig::DEFAULT_FALLBACK_DELAY;
+        assert_eq!(
+            iface.poll_at(Instant::from_secs(0), &sockets),
+            Some(timestamp)
+        );
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        let link_local_addr = iface.autoip_addr().unwrap();
+        assert!(link_local_addr.is_link_local());
+        assert!(iface.has_ip_addr(link_local_addr));
+        assert_eq!(
+            iface.ipv4_addr_state(link_local_addr),
+            Some(Ipv4AddressState::Probing)
+        );
+
+        // Another address is selected if another node uses it.
+        iface
+            .inner
+            .acd_process_arp(link_local_addr, link_local_addr);
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        let other_addr = iface.autoip_addr().unwrap();
+        assert_ne!(other_addr, link_local_addr);
+        assert!(!iface.has_ip_addr(link_local_addr));
+        assert!(iface.has_ip_addr(other_addr));
+        assert_eq!(iface.conflicting_ipv4_addrs().count(), 0);
+
+        // The link-local address is removed once a routable address is configured.
+        iface
+            .add_ip_addr(IpCidr::new(routable_addr.into(), 24))
+            .unwrap();
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        assert_eq!(iface.autoip_addr(), None);
+        assert!(!iface.has_ip_addr(other_addr));
+        assert!(iface.has_ip_addr(routable_addr));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6-dad")]
+    fn test_duplicate_address_detection() {
+        let link_local_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let global_addr = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1);
+        let remote_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2);
+
+        let (iface, mut sockets, mut device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                ip_addrs: vec![
+                    IpCidr::new(link_local_addr.into(), 64),
+                    IpCidr::new(global_addr.into(), 64),
+                ]
+                .into(),
+                dad: Some(Dad::new(
+                    DadConfig::default(),
+                    ManagedMap::Owned(BTreeMap::new()),
+                )),
+                ..iface.inner
+            },
+            ..iface
+        };
+        iface.inner.sync_dad();
+
+        // Tentative addresses are not used as source addresses.
+        assert_eq!(
+            iface.ipv6_addr_state(link_local_addr),
+            Some(Ipv6AddressState::Tentative)
+        );
+        assert_eq!(iface.inner.get_source_address_ipv6(remote_addr), None);
+
+        // Each address is probed from the unspecified address.
+        let timestamp = Instant::from_secs(0);
+        assert_eq!(iface.poll_at(timestamp, &sockets), Some(timestamp));
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        let mut targets = recv_all(&mut device, timestamp)
+            .iter()
+            .map(|frame| {
+                let eth_frame = EthernetFrame::new_checked(&frame[..]).unwrap();
+                let ipv6_packet = Ipv6Packet::new_checked(eth_frame.payload()).unwrap();
+                assert_eq!(ipv6_packet.src_addr(), Ipv6Address::UNSPECIFIED);
+                assert_eq!(ipv6_packet.hop_limit(), 0xff);
+                let icmp_packet = Icmpv6Packet::new_checked(ipv6_packet.payload()).unwrap();
+                match Icmpv6Repr::parse(
+                    &ipv6_packet.src_addr().into(),
+                    &ipv6_packet.dst_addr().into(),
+                    &icmp_packet,
+                    &ChecksumCapabilities::default(),
+                    ParseMode::Permissive,
+                )
+                .unwrap()
+                {
+                    Icmpv6Repr::Ndisc(NdiscRepr::NeighborSolicit {
+                        target_addr,
+                        lladdr: None,
+                    }) => {
+                        assert_eq!(ipv6_packet.dst_addr(), target_addr.solicited_node());
+                        target_addr
+                    }
+                    repr => panic!("unexpected {:?}", repr),
+                }
+            })
+            .collect::<Vec<_>>();
+        targets.sort();
+        assert_eq!(targets, [global_addr, link_local_addr]);
+
+        // Another node probing the global address at the same time is a conflict.
+        let solicit_repr = Ipv6Repr {
+            src_addr: Ipv6Address::UNSPECIFIED,
+            dst_addr: global_addr.solicited_node(),
+            next_header: IpProtocol::Icmpv6,
+            payload_len: 24,
+            hop_limit: 0xff,
+        };
+        let solicit = NdiscRepr::NeighborSolicit {
+            target_addr: global_addr,
+            lladdr: None,
+        };
+        assert_eq!(iface.inner.process_ndisc(solicit_repr, solicit), None);
+        assert_eq!(
+            iface.ipv6_addr_state(global_addr),
+            Some(Ipv6AddressState::Duplicate)
+        );
+        assert_eq!(
+            iface.duplicate_ipv6_addrs().collect::<Vec<_>>(),
+            [global_addr]
+        );
+
+        // Without a conflict, the address becomes preferred once the last probe times out.
+        let timestamp = Instant::from_secs(1);
+        assert_eq!(
+            iface.poll_at(Instant::from_secs(0), &sockets),
+            Some(timestamp)
+        );
+        iface.poll(timestamp, &mut device, &mut sockets).unwrap();
+        assert!(recv_all(&mut device, timestamp).is_empty());
+        assert_eq!(
+            iface.ipv6_addr_state(link_local_addr),
+            Some(Ipv6AddressState::Preferred)
+        );
+        assert_eq!(
+            iface.inner.get_source_address_ipv6(remote_addr),
+            Some(link_local_addr)
+        );
+
+        // The preferred address is defended against other nodes probing it.
+        iface.inner.now = timestamp;
+        let solicit_repr = Ipv6Repr {
+            dst_addr: link_local_addr.solicited_node(),
+            ..solicit_repr
+        };
+        let solicit = NdiscRepr::NeighborSolicit {
+            target_addr: link_local_addr,
+            lladdr: None,
+        };
+        match iface.inner.process_ndisc(solicit_repr, solicit) {
+            Some(IpPacket::Icmpv6((
+                ip_repr,
+                Icmpv6Repr::Ndisc(NdiscRepr::NeighborAdvert { flags, .. }),
+            ))) => {
+                assert_eq!(ip_repr.dst_addr, Ipv6Address::LINK_LOCAL_ALL_NODES);
+                assert_eq!(flags, NdiscNeighborFlags::OVERRIDE);
+            }
+            packet => panic!("unexpected {:?}", packet),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ethernet")]
+    fn test_ethernet_filter() {
+        let (mut iface, mut sockets, mut _device) = create_ethernet();
+
+        let mut eth_bytes = vec![0u8; 14];
+        let mut receive = |iface: &mut Interface, dst_addr: EthernetAddress| {
+            let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+            frame.set_dst_addr(dst_addr);
+            frame.set_src_addr(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]));
+            frame.set_ethertype(EthernetProtocol::Unknown(0x88b5));
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments);
+            iface.ethernet_filter_stats()
+        };
+
+        // Frames for our hardware address and for the broadcast address are accepted.
+        assert_eq!(
+            receive(&mut iface, EthernetAddress::default()),
+            EthernetFilterStats::default()
+        );
+        assert_eq!(
+            receive(&mut iface, EthernetAddress::BROADCAST),
+            EthernetFilterStats::default()
+        );
+
+        let stats = receive(&mut iface, EthernetAddress([0x02, 0, 0, 0, 0, 1]));
+        assert_eq!((stats.unicast, stats.multicast), (1, 0));
+        let stats = receive(
+            &mut iface,
+            EthernetAddress([0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]),
+        );
+        assert_eq!((stats.unicast, stats.multicast), (1, 1));
+
+        #[cfg(feature = "proto-ipv4")]
+        {
+            // All systems.
+            let stats = receive(
+                &mut iface,
+                EthernetAddress([0x01, 0x00, 0x5e, 0x00, 0x00, 0x01]),
+            );
+            assert_eq!(stats.multicast, 1);
+        }
+
+        #[cfg(feature = "proto-igmp")]
+        {
+            iface
+                .join_multicast_group(
+                    &mut _device,
+                    Ipv4Address::new(224, 0, 0, 251),
+                    Instant::from_millis(0),
+                )
+                .unwrap();
+            let stats = receive(
+                &mut iface,
+                EthernetAddress([0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]),
+            );
+            assert_eq!(stats.multicast, 1);
+        }
+
+        #[cfg(feature = "proto-ipv6")]
+        {
+            // All nodes, and the solicited-node group of fdbe::1.
+            let stats = receive(
+                &mut iface,
+                EthernetAddress([0x33, 0x33, 0x00, 0x00, 0x00, 0x01]),
+            );
+            assert_eq!(stats.multicast, 1);
+            let stats = receive(
+                &mut iface,
+                EthernetAddress([0x33, 0x33, 0xff, 0x00, 0x00, 0x01]),
+            );
+            assert_eq!(stats.multicast, 1);
+            let stats = receive(
+                &mut iface,
+                EthernetAddress([0x33, 0x33, 0xff, 0x00, 0x00, 0x02]),
+            );
+            assert_eq!(stats.multicast, 2);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "proto-wol")]
+    fn test_wake_on_lan_ethernet() {
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        let mut repr = WolRepr {
+            target_addr: EthernetAddress::default(),
+            password: None,
+        };
+        let mut eth_bytes = vec![0u8; 14 + repr.buffer_len()];
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x00]));
+        frame.set_ethertype(EthernetProtocol::WakeOnLan);
+        repr.emit(&mut WolPacket::new_unchecked(frame.payload_mut()));
+
+        assert!(!iface.wake_on_lan_received());
+        assert_eq!(
+            iface
+                .inner
+                .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments),
+            None
+        );
+        assert!(iface.wake_on_lan_received());
+        assert!(!iface.wake_on_lan_received());
+
+        // A magic packet for another host is ignored.
+        repr.target_addr = EthernetAddress([0x52, 0x54, 0x00, 0x00, 0x00, 0x01]);
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        repr.emit(&mut WolPacket::new_unchecked(frame.payload_mut()));
+        iface
+            .inner
+            .process_ethernet(&mut sockets, &eth_bytes, &mut iface.fragments);
+        assert!(!iface.wake_on_lan_received());
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-wol", feature = "proto-ipv4"))]
+    fn test_wake_on_lan_udp() {
+        let (mut iface, mut sockets, _device) = create_ethernet();
+
+        let wol_repr = WolRepr {
+            target_addr: EthernetAddress::default(),
+            password: None,
+        };
+        let udp_repr = UdpRepr {
+            src_port: 40000,
+            dst_port: WOL_UDP_PORT,
+        };
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Udp,
+            payload_len: udp_repr.header_len() + wol_repr.buffer_len(),
+            hop_limit: 64,
+        });
+
+        let mut udp_bytes = vec![0u8; udp_repr.header_len() + wol_repr.buffer_len()];
+        udp_repr.emit(
+            &mut UdpPacket::new_unchecked(&mut udp_bytes),
+            &ip_repr.src_addr(),
+            &ip_repr.dst_addr(),
+            wol_repr.buffer_len(),
+            |buf| wol_repr.emit(&mut WolPacket::new_unchecked(buf)),
+            &ChecksumCapabilities::default(),
+        );
+
+        // The magic packet is consumed instead of triggering a port unreachable error.
+        assert_eq!(
+            iface
+                .inner
+                .process_udp(&mut sockets, ip_repr, false, &udp_bytes),
+            None
+        );
+        assert!(iface.wake_on_lan_received());
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-udplite", feature = "proto-ipv4"))]
+    fn test_handle_udplite() {
+        use crate::wire::IpEndpoint;
+
+        static UDP_PAYLOAD: [u8; 5] = [0x48, 0x65, 0x6c, 0x6c, 0x6f];
+
+        let (mut iface, mut sockets, _device) = create();
+
+        let rx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let tx_buffer = udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 15]);
+        let mut udp_socket = udp::Socket::new(rx_buffer, tx_buffer);
+        udp_socket.set_checksum_coverage(Some(8));
+        assert_eq!(udp_socket.bind(68), Ok(()));
+        let socket_handle = sockets.add(udp_socket);
+
+        let src_ip = Ipv4Address::new(0x7f, 0x00, 0x00, 0x02);
+        let udplite_repr = UdpLiteRepr {
+            src_port: 67,
+            dst_port: 68,
+            checksum_coverage: 8,
+        };
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: src_ip,
+            dst_addr: iface.ipv4_address().unwrap(),
+            next_header: IpProtocol::UdpLite,
+            payload_len: udplite_repr.header_len() + UDP_PAYLOAD.len(),
+            hop_limit: 0x40,
+        });
+
+        let mut udplite_bytes = vec![0u8; 13];
+        udplite_repr.emit(
+            &mut UdpLitePacket::new_unchecked(&mut udplite_bytes),
+            &ip_repr.src_addr(),
+            &ip_repr.dst_addr(),
+            |buf| buf.copy_from_slice(&UDP_PAYLOAD),
+            &ChecksumCapabilities::default(),
+        );
+        // Corruption of the payload outside the checksum coverage is tolerated.
+        udplite_bytes[12] ^= 0xff;
+
+        assert_eq!(
+            iface
+                .inner
+                .process_udplite(&mut sockets, ip_repr, false, &udplite_bytes),
+            None
+        );
+
+        let socket = sockets.get_mut::<udp::Socket>(socket_handle);
+        let (payload, meta) = socket.recv().unwrap();
+        assert_eq!(&payload[..4], &UDP_PAYLOAD[..4]);
+        assert_eq!(payload[4], UDP_PAYLOAD[4] ^ 0xff);
+        assert_eq!(meta.endpoint, IpEndpoint::new(src_ip.into(), 67));
+    }
+
+    struct LinkDevice {
+        inner: Loopback,
+        link_state: LinkState,
+    }
+
+    impl<'a> Device<'a> for LinkDevice {
+        type RxToken = <Loopback as Device<'a>>::RxToken;
+        type TxToken = <Loopback as Device<'a>>::TxToken;
+
+        fn capabilities(&self) -> DeviceCapabilities {
+            self.inner.capabilities()
+        }
+
+        fn link_state(&self) -> LinkState {
+            self.link_state
+        }
+
+        fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+            self.inner.receive()
+        }
+
+        fn transmit(&'a mut self) -> Option<Self::TxToken> {
+            self.inner.transmit()
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_link_down() {
+        let (mut iface, mut sockets, device) = create_ethernet();
+        let mut device = LinkDevice {
+            inner: device,
+            link_state: LinkState::UP,
+        };
+        let timestamp = Instant::from_millis(0);
+        let neighbor = IpAddress::v4(127, 0, 0, 2);
+
+        iface.inner.neighbor_cache.as_mut().unwrap().fill(
+            neighbor,
+            EthernetAddress([0x02, 0, 0, 0, 0, 1]).into(),
+            timestamp,
+        );
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(false));
+        assert_eq!(iface.link_state(), LinkState::UP);
+
+        device.link_state = LinkState::Down;
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(false));
+        assert_eq!(iface.link_state(), LinkState::Down);
+        assert!(!iface
+            .inner
+            .neighbor_cache
+            .as_ref()
+            .unwrap()
+            .lookup(&neighbor, timestamp)
+            .found());
+        assert_eq!(iface.poll_at(timestamp, &sockets), None);
+
+        // Frames queued while the link is down are left in the device.
+        let mut eth_bytes = vec![0u8; 14];
+        let mut frame = EthernetFrame::new_unchecked(&mut eth_bytes);
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]));
+        frame.set_ethertype(EthernetProtocol::Unknown(0x88b5));
+        let tx = device.inner.transmit().unwrap();
+        tx.consume(timestamp, eth_bytes.len(), |buf| {
+            buf.copy_from_slice(&eth_bytes);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(false));
+        assert!(device.inner.receive().is_some());
+
+        device.link_state = LinkState::Up {
+            speed: Some(100),
+            full_duplex: Some(true),
+        };
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(false));
+        assert_eq!(
+            iface.link_state(),
+            LinkState::Up {
+                speed: Some(100),
+                full_duplex: Some(true)
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-igmp"))]
+    fn test_introspection() {
+        let (mut iface, _sockets, mut device) = create_ethernet();
+        let timestamp = Instant::from_millis(0);
+        let neighbor = IpAddress::v4(127, 0, 0, 2);
+        let neighbor_hw = EthernetAddress([0x02, 0, 0, 0, 0, 1]).into();
+
+        iface
+            .inner
+            .neighbor_cache
+            .as_mut()
+            .unwrap()
+            .fill(neighbor, neighbor_hw, timestamp);
+        let neighbors: Vec<_> = iface.neighbor_cache().unwrap().iter().collect();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(*neighbors[0].0, neighbor);
+        assert_eq!(neighbors[0].1.hardware_addr(), neighbor_hw);
+
+        let routes = iface.routes_mut();
+        routes.update(|storage| *storage = BTreeMap::new().into());
+        routes
+            .add_default_ipv4_route(Ipv4Address::new(127, 0, 0, 2))
+            .unwrap();
+        let routes: Vec<_> = iface.routes().iter().collect();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(*routes[0].0, IpCidr::new(IpAddress::v4(0, 0, 0, 0), 0));
+
+        let group = Ipv4Address::new(224, 0, 0, 251);
+        iface
+            .join_multicast_group(&mut device, group, timestamp)
+            .unwrap();
+        let groups: Vec<_> = iface.ipv4_multicast_groups().collect();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(*groups[0].0, group);
+        assert!(groups[0].1.is_any());
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_add_remove_ip_addr() {
+        let (mut iface, _sockets, _device) = create();
+        let addr = IpAddress::v4(192, 168, 1, 1);
+        let count = iface.ip_addrs().len();
+
+        assert_eq!(iface.add_ip_addr(IpCidr::new(addr, 24)), Ok(()));
+        assert!(iface.has_ip_addr(addr));
+        assert_eq!(iface.ip_addrs().len(), count + 1);
+        assert!(iface.remove_ip_addr(addr));
+        assert!(!iface.has_ip_addr(addr));
+        assert_eq!(iface.ip_addrs().len(), count);
+        assert!(!iface.remove_ip_addr(addr));
+
+        // Borrowed storage keeps its size, and uses unspecified addresses as free slots.
+        let mut storage = [IpCidr::new(IpAddress::v4(10, 0, 0, 1), 8)];
+        iface.update_ip_addrs(|ip_addrs| *ip_addrs = (&mut storage[..]).into());
+        assert_eq!(
+            iface.add_ip_addr(IpCidr::new(addr, 24)),
+            Err(Error::Exhausted)
+        );
+        assert!(iface.remove_ip_addr(IpAddress::v4(10, 0, 0, 1)));
+        assert_eq!(iface.ip_addrs().len(), 1);
+        assert!(iface.ip_addrs()[0].address().is_unspecified());
+        assert_eq!(iface.add_ip_addr(IpCidr::new(addr, 24)), Ok(()));
+        assert_eq!(iface.ip_addrs(), &[IpCidr::new(addr, 24)]);
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ip", feature = "proto-ipv4"))]
+    fn test_stats() {
+        let (mut iface, mut sockets, mut device) = create_ip();
+        let timestamp = Instant::from_millis(0);
+
+        let icmpv4_repr = Icmpv4Repr::EchoRequest {
+            ident: 0x1234,
+            seq_no: 0xabcd,
+            data: &[0xaa; 8],
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x02]),
+            dst_addr: Ipv4Address([0x7f, 0x00, 0x00, 0x01]),
+            next_header: IpProtocol::Icmp,
+            hop_limit: 64,
+            payload_len: icmpv4_repr.buffer_len(),
+        };
+        let mut bytes = vec![0u8; ipv4_repr.buffer_len() + icmpv4_repr.buffer_len()];
+        ipv4_repr.emit(
+            &mut Ipv4Packet::new_unchecked(&mut bytes),
+            &ChecksumCapabilities::default(),
+        );
+        icmpv4_repr.emit(
+            &mut Icmpv4Packet::new_unchecked(&mut bytes[ipv4_repr.buffer_len()..]),
+            &ChecksumCapabilities::default(),
+        );
+
+        let tx = device.transmit().unwrap();
+        tx.consume(timestamp, bytes.len(), |buf| {
+            buf.copy_from_slice(&bytes);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(iface.poll(timestamp, &mut device, &mut sockets), Ok(true));
+
+        // The echo reply is looped back to the interface, which discards it
+        // because it is addressed to 127.0.0.2.
+        let stats = iface.stats();
+        assert_eq!(stats.interface.in_octets, 2 * bytes.len() as u64);
+        assert_eq!(stats.interface.in_ucast_pkts, 2);
+        assert_eq!(stats.interface.out_octets, bytes.len() as u64);
+        assert_eq!(stats.interface.out_ucast_pkts, 1);
+        assert_eq!(stats.ip.in_receives, 2);
+        assert_eq!(stats.ip.in_addr_errors, 1);
+        assert_eq!(stats.ip.out_requests, 1);
+        assert_eq!(stats.icmp.in_msgs, 1);
+        assert_eq!(stats.icmp.in_echos, 1);
+        assert_eq!(stats.icmp.out_msgs, 1);
+        assert_eq!(stats.icmp.out_echo_reps, 1);
+        assert!(stats
+            .mib_objects()
+            .any(|object| object == ("icmpOutEchoReps", 1)));
+    }
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv6"))]
+    fn test_ipv6_source_address_selection() {
+        let (mut iface, _sockets, _device) = create_ethernet();
+        let loopback = Ipv6Address::LOOPBACK;
+        let ula = Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1);
+
+        assert_eq!(
+            iface.inner.get_source_address_ipv6(loopback),
+            Some(loopback)
+        );
+        assert_eq!(
+            iface
+                .inner
+                .get_source_address_ipv6(Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 2)),
+            Some(ula)
+        );
+        assert_eq!(
+            iface
+                .inner
+                .get_source_address_ipv6(Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+            Some(ula)
+        );
+        assert_eq!(
+            iface
+                .inner
+                .get_source_address_ipv6(Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2)),
+            Some(loopback)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6-slaac")]
+    fn test_slaac() {
+        let router_addr = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2);
+        let prefix = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0);
+        let stable_addr = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0x0200, 0x00ff, 0xfe00, 0);
+        let remote_addr = Ipv6Address::new(0x2001, 0xdb8, 0, 2, 0, 0, 0, 1);
+
+        let (iface, mut sockets, mut device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                slaac: Some(Slaac::new(
+                    SlaacConfig {
+                        temporary_addresses: true,
+                        ..SlaacConfig::default()
+                    },
+                    ManagedMap::Owned(BTreeMap::new()),
+                )),
+                ..iface.inner
+            },
+            ..iface
+        };
+
+        let advert = NdiscRepr::RouterAdvert {
+            hop_limit: 64,
+            flags: NdiscRouterFlags::empty(),
+            preference: NdiscRoutePreference::Medium,
+            router_lifetime: Duration::from_secs(1800),
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            lladdr: None,
+            mtu: None,
+            prefix_info: Some(NdiscPrefixInformation {
+                prefix_len: 64,
+                flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+                valid_lifetime: Duration::from_secs(3600),
+                preferred_lifetime: Duration::from_secs(1800),
+                prefix,
+            }),
+            route_info: None,
+
+            rdnss: None,
+            dnssl: None,
+        };
+        let ip_repr = |src_addr| Ipv6Repr {
+            src_addr,
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+            next_header: IpProtocol::Icmpv6,
+            hop_limit: 0xff,
+            payload_len: 0,
+        };
+
+        // Advertisements must come from a link-local address.
+        assert_eq!(
+            iface.inner.process_ndisc(ip_repr(remote_addr), advert),
+            None
+        );
+        assert_eq!(iface.slaac_addrs().count(), 0);
+
+        // A stable and a temporary address are formed from the prefix.
+        assert_eq!(
+            iface.inner.process_ndisc(ip_repr(router_addr), advert),
+            None
+        );
+        let addrs = iface.slaac_addrs().collect::<Vec<_>>();
+        assert_eq!(addrs.len(), 2);
+        let (temp_addr, _) = *addrs
+            .iter()
+            .find(|(_, entry)| entry.is_temporary())
+            .unwrap();
+        assert!(iface.has_ip_addr(stable_addr));
+        assert!(iface.has_ip_addr(temp_addr));
+
+        // The temporary address is preferred for outgoing packets.
+        assert_eq!(
+            iface.inner.get_source_address_ipv6(remote_addr),
+            Some(temp_addr)
+        );
+        assert_eq!(
+            iface.poll_at(Instant::ZERO, &sockets),
+            Some(Instant::from_secs(1795))
+        );
+
+        // The addresses are removed when their valid lifetime expires.
+        iface
+            .poll(Instant::from_secs(3600), &mut device, &mut sockets)
+            .unwrap();
+        assert_eq!(iface.slaac_addrs().count(), 0);
+        assert!(!iface.has_ip_addr(stable_addr));
+        assert!(!iface.has_ip_addr(temp_addr));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6-slaac")]
+    fn test_router_advert_routes() {
+        let router_a = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0xa);
+        let router_b = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0xb);
+        let remote_addr = IpAddress::v6(0x2001, 0xdb8, 0, 2, 0, 0, 0, 1);
+        let specific_addr = IpAddress::v6(0x2001, 0xdb8, 1, 0, 0, 0, 0, 1);
+
+        let (iface, _sockets, _device) = create_ethernet();
+        let mut iface = Interface {
+            inner: InterfaceInner {
+                routes: Routes::new(BTreeMap::new()),
+                slaac: Some(Slaac::new(
+                    SlaacConfig::default(),
+                    ManagedMap::Owned(BTreeMap::new()),
+                )),
+                ..iface.inner
+            },
+            ..iface
+        };
+
+        let advert = |preference, router_lifetime, route_info| NdiscRepr::RouterAdvert {
+            hop_limit: 64,
+            flags: NdiscRouterFlags::empty(),
+            preference,
+            router_lifetime,
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            lladdr: None,
+            mtu: None,
+            prefix_info: None,
+            route_info,
+            rdnss: None,
+            dnssl: None,
+        };
+        let ip_repr = |src_addr| Ipv6Repr {
+            src_addr,
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+            next_header: IpProtocol::Icmpv6,
+            hop_limit: 0xff,
+            payload_len: 0,
+        };
+        let lifetime = Duration::from_secs(1800);
+        let route_info = NdiscRouteInformation {
+            prefix_len: 48,
+            preference: NdiscRoutePreference::Medium,
+            lifetime,
+            prefix: Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 0),
+        };
+
+        // Router A is a low preference default router, router B advertises a
+        // more-specific route only.
+        let advert_a = advert(NdiscRoutePreference::Low, lifetime, None);
+        let advert_b = advert(NdiscRoutePreference::High, Duration::ZERO, Some(route_info));
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_a), advert_a), None);
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_b), advert_b), None);
+        let lookup = |iface: &Interface, addr| iface.inner.routes.lookup(&addr, Instant::ZERO);
+        assert_eq!(lookup(&iface, remote_addr), Some(router_a.into()));
+        assert_eq!(lookup(&iface, specific_addr), Some(router_b.into()));
+
+        // Router B becomes the preferred default router.
+        let advert_b = advert(NdiscRoutePreference::High, lifetime, None);
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_b), advert_b), None);
+        assert_eq!(lookup(&iface, remote_addr), Some(router_b.into()));
+
+        // A lower preference does not replace it, until it stops being a default router.
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_a), advert_a), None);
+        assert_eq!(lookup(&iface, remote_addr), Some(router_b.into()));
+        let advert_b = advert(NdiscRoutePreference::High, Duration::ZERO, None);
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_b), advert_b), None);
+        assert_eq!(lookup(&iface, remote_addr), None);
+        assert_eq!(iface.inner.process_ndisc(ip_repr(router_a), advert_a), None);
+        assert_eq!(lookup(&iface, remote_addr), Some(router_a.into()));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6-ra")]
+    fn test_router_advert_dns() {
+        let router = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 0xa);
+        let server = Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53);
+
+        // The DNS configuration is recorded without address autoconfiguration.
+        let (mut iface, _sockets, _device) = create_ethernet();
+
+        let advert = |lifetime| NdiscRepr::RouterAdvert {
+            hop_limit: 64,
+            flags: NdiscRouterFlags::empty(),
+            preference: NdiscRoutePreference::Medium,
+            router_lifetime: Duration::ZERO,
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            lladdr: None,
+            mtu: None,
+            prefix_info: None,
+            route_info: None,
+            rdnss: Some(NdiscRecursiveDnsServer {
+                lifetime,
+                addresses: server.as_bytes(),
+            }),
+            dnssl: Some(NdiscDnsSearchList {
+                lifetime,
+                domains: b"\x07example\x03com\x00\x00\x00\x00",
+            }),
+        };
+        let ip_repr = Ipv6Repr {
+            src_addr: router,
+            dst_addr: Ipv6Address::LINK_LOCAL_ALL_NODES,
+            next_header: IpProtocol::Icmpv6,
+            hop_limit: 0xff,
+            payload_len: 0,
+        };
+
+        let advert_a = advert(Duration::from_secs(1800));
+        assert_eq!(iface.inner.process_ndisc(ip_repr, advert_a), None);
+        assert_eq!(iface.ipv6_dns_servers().collect::<Vec<_>>(), [server]);
+        assert_eq!(
+            iface.ipv6_dns_search_list().collect::<Vec<_>>(),
+            ["example.com"]
+        );
+
+        // DNS sockets without configured servers use the advertised ones.
+        #[cfg(feature = "socket-dns")]
+        {
+            let socket = dns::Socket::new(&[], vec![]);
+            let ip_repr = IpRepr::new(
+                server.into(),
+                Ipv6Address::new(0xfdbe, 0, 0, 0, 0, 0, 0, 1).into(),
+                IpProtocol::Udp,
+                0,
+                64,
+            );
+            let udp_repr = UdpRepr {
+                src_port: 53,
+                dst_port: 49152,
+            };
+            assert!(socket.accepts(&iface.inner, &ip_repr, &udp_repr));
+        }
+
+        // A zero lifetime removes the configuration.
+        assert_eq!(
+            iface.inner.process_ndisc(ip_repr, advert(Duration::ZERO)),
+            None
+        );
+        assert_eq!(iface.ipv6_dns_servers().count(), 0);
+        assert_eq!(iface.ipv6_dns_search_list().count(), 0);
+    }
 }
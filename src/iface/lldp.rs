@@ -0,0 +1,138 @@
+//! Periodic LLDP advertisement, as specified in IEEE 802.1AB-2016 § 9.
+
+use heapless::Vec;
+
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    EthernetAddress, LldpCapabilities, LldpChassisIdSubtype, LldpPortIdSubtype, LldpRepr,
+};
+
+/// Maximum length of an identifier or string value carried in an LLDP TLV.
+pub const MAX_VALUE_LEN: usize = 255;
+
+/// Configuration of the LLDP advertiser of an interface.
+///
+/// The chassis is always identified by the hardware address of the interface.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config<'a> {
+    pub port_id_subtype: LldpPortIdSubtype,
+    pub port_id: &'a [u8],
+    pub port_description: Option<&'a [u8]>,
+    pub system_name: Option<&'a [u8]>,
+    pub system_description: Option<&'a [u8]>,
+    pub capabilities: LldpCapabilities,
+    /// Interval between advertisements. The advertised TTL is four times this value.
+    pub interval: Duration,
+}
+
+impl<'a> Config<'a> {
+    /// Default interval between advertisements, per IEEE 802.1AB `msgTxInterval`.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Create a configuration advertising the given interface name as the port ID,
+    /// and nothing else.
+    pub fn new(port_id: &'a [u8]) -> Config<'a> {
+        Config {
+            port_id_subtype: LldpPortIdSubtype::InterfaceName,
+            port_id,
+            port_description: None,
+            system_name: None,
+            system_description: None,
+            capabilities: LldpCapabilities::STATION,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+
+    /// Return the time to live advertised along with the configuration, in seconds.
+    pub(crate) fn ttl(&self) -> u16 {
+        let secs = self.interval.secs() * 4;
+        if secs > u16::MAX as u64 {
+            u16::MAX
+        } else {
+            secs as u16
+        }
+    }
+}
+
+/// The most recently received LLDP advertisement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    hardware_addr: EthernetAddress,
+    chassis_id_subtype: LldpChassisIdSubtype,
+    chassis_id: Vec<u8, MAX_VALUE_LEN>,
+    port_id_subtype: LldpPortIdSubtype,
+    port_id: Vec<u8, MAX_VALUE_LEN>,
+    system_name: Option<Vec<u8, MAX_VALUE_LEN>>,
+    capabilities: Option<(LldpCapabilities, LldpCapabilities)>,
+    expires_at: Instant,
+}
+
+fn copy_value(value: &[u8]) -> Vec<u8, MAX_VALUE_LEN> {
+    let len = value.len().min(MAX_VALUE_LEN);
+    // Cannot fail, the length is clamped to the capacity.
+    Vec::from_slice(&value[..len]).unwrap()
+}
+
+impl Neighbor {
+    pub(crate) fn new(hardware_addr: EthernetAddress, repr: &LldpRepr, timestamp: Instant) -> Self {
+        Neighbor {
+            hardware_addr,
+            chassis_id_subtype: repr.chassis_id_subtype,
+            chassis_id: copy_value(repr.chassis_id),
+            port_id_subtype: repr.port_id_subtype,
+            port_id: copy_value(repr.port_id),
+            system_name: repr.system_name.map(copy_value),
+            capabilities: repr.system_capabilities,
+            expires_at: timestamp + Duration::from_secs(repr.ttl as u64),
+        }
+    }
+
+    /// Return the source hardware address of the advertisement.
+    pub fn hardware_addr(&self) -> EthernetAddress {
+        self.hardware_addr
+    }
+
+    /// Return the chassis ID subtype and value.
+    pub fn chassis_id(&self) -> (LldpChassisIdSubtype, &[u8]) {
+        (self.chassis_id_subtype, &self.chassis_id)
+    }
+
+    /// Return the port ID subtype and value.
+    pub fn port_id(&self) -> (LldpPortIdSubtype, &[u8]) {
+        (self.port_id_subtype, &self.port_id)
+    }
+
+    /// Return the system name, if advertised.
+    pub fn system_name(&self) -> Option<&[u8]> {
+        self.system_name.as_deref()
+    }
+
+    /// Return the supported and enabled system capabilities, if advertised.
+    pub fn capabilities(&self) -> Option<(LldpCapabilities, LldpCapabilities)> {
+        self.capabilities
+    }
+
+    /// Return the instant at which the advertised information expires.
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+}
+
+/// State of the LLDP agent of an interface.
+#[derive(Debug)]
+pub(crate) struct Lldp<'a> {
+    pub(crate) config: Config<'a>,
+    pub(crate) next_advert: Instant,
+    pub(crate) neighbor: Option<Neighbor>,
+}
+
+impl<'a> Lldp<'a> {
+    pub(crate) fn new(config: Config<'a>) -> Self {
+        Lldp {
+            config,
+            next_advert: Instant::from_millis(0),
+            neighbor: None,
+        }
+    }
+}
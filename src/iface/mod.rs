@@ -4,25 +4,80 @@ The `iface` module deals with the *network interfaces*. It filters incoming fram
 provides lookup and caching of hardware addresses, and handles management packets.
 */
 
+#[cfg(feature = "proto-ipv4-acd")]
+mod acd;
+#[cfg(feature = "proto-ipv4-autoip")]
+mod autoip;
+#[cfg(feature = "proto-ipv6-dad")]
+mod dad;
+mod deadline_queue;
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 mod fragmentation;
 mod interface;
+#[cfg(feature = "proto-lldp")]
+mod lldp;
+#[cfg(feature = "proto-igmp")]
+mod multicast;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 mod neighbor;
+#[cfg(feature = "proto-ppp")]
+mod ppp;
+#[cfg(feature = "proto-ipv6-ra")]
+mod ra_dns;
 mod route;
+#[cfg(feature = "proto-ipv6-ra")]
+mod router_advert;
+#[cfg(feature = "critical-section")]
+mod shared;
+#[cfg(feature = "proto-ipv6-slaac")]
+mod slaac;
 mod socket_meta;
 mod socket_set;
+#[cfg(feature = "proto-ipv6")]
+mod source_addr;
+mod stats;
 
+#[cfg(feature = "proto-ipv4-acd")]
+pub use self::acd::{AddressState as Ipv4AddressState, Config as AcdConfig, Entry as AcdEntry};
+#[cfg(feature = "proto-ipv4-autoip")]
+pub use self::autoip::Config as AutoIpConfig;
+#[cfg(feature = "proto-ipv6-dad")]
+pub use self::dad::{AddressState as Ipv6AddressState, Config as DadConfig, Entry as DadEntry};
+#[cfg(feature = "proto-lldp")]
+pub use self::lldp::{Config as LldpConfig, Neighbor as LldpNeighbor};
+#[cfg(feature = "proto-igmp")]
+pub use self::multicast::{
+    FilterMode as MulticastFilterMode, SourceFilter as MulticastSourceFilter,
+    MAX_SOURCES as MULTICAST_MAX_SOURCES,
+};
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub(crate) use self::neighbor::Answer as NeighborAnswer;
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+pub use self::neighbor::ArpProtection;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub use self::neighbor::Cache as NeighborCache;
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 pub use self::neighbor::Neighbor;
-pub use self::route::{Route, Routes};
-pub use socket_set::{SocketHandle, SocketSet, SocketStorage};
+#[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+pub use self::neighbor::{Event as NeighborEvent, Stats as NeighborStats};
+#[cfg(feature = "proto-ppp")]
+pub use self::ppp::{Config as PppKeepaliveConfig, Keepalive as PppKeepalive};
+pub use self::route::{Route, RoutePolicy, Routes};
+#[cfg(feature = "proto-ipv6-ra")]
+pub use self::router_advert::Config as RouterAdvertConfig;
+#[cfg(feature = "critical-section")]
+pub use self::shared::SharedStack;
+#[cfg(feature = "proto-ipv6-slaac")]
+pub use self::slaac::{Config as SlaacConfig, Entry as SlaacEntry};
+pub use socket_set::{SocketHandle, SocketLabel, SocketPriority, SocketSet, SocketStorage};
+pub(crate) use stats::{IcmpKind, PacketKind};
+pub use stats::{IcmpStats, InterfaceStats, IpStats, Stats, TcpStats, UdpStats};
 
 #[cfg(any(feature = "proto-ipv4", feature = "proto-sixlowpan"))]
 pub use self::fragmentation::{PacketAssembler, PacketAssemblerSet as FragmentsCache};
 
-pub use self::interface::{Interface, InterfaceBuilder, InterfaceInner as Context};
+#[cfg(feature = "medium-ethernet")]
+pub use self::interface::EthernetFilterStats;
+pub use self::interface::{
+    BuildError, Interface, InterfaceBuilder, InterfaceInner as Context, UnknownProtocolPolicy,
+};
@@ -0,0 +1,186 @@
+/*! Source filters of IPv4 multicast group memberships.
+
+A [SourceFilter] restricts the sources a multicast group is received from, as defined in
+[RFC 3376 § 3]. Memberships with a source filter are reported using IGMPv3, which is required
+for Source-Specific Multicast (SSM, [RFC 4607]).
+
+[RFC 3376 § 3]: https://tools.ietf.org/html/rfc3376#section-3
+[RFC 4607]: https://tools.ietf.org/html/rfc4607
+*/
+
+use crate::wire::Ipv4Address;
+use crate::{Error, Result};
+
+/// The maximum number of sources in a source filter.
+pub const MAX_SOURCES: usize = 8;
+
+/// The filter mode of a multicast group membership.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FilterMode {
+    /// Only receive traffic from the listed sources.
+    Include,
+    /// Receive traffic from all sources except the listed ones.
+    Exclude,
+}
+
+/// The source filter of a multicast group membership.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SourceFilter {
+    mode: FilterMode,
+    sources: [Ipv4Address; MAX_SOURCES],
+    len: usize,
+}
+
+impl SourceFilter {
+    /// A filter accepting traffic from any source, i.e. EXCLUDE with no sources.
+    pub const ANY: SourceFilter = SourceFilter {
+        mode: FilterMode::Exclude,
+        sources: [Ipv4Address::UNSPECIFIED; MAX_SOURCES],
+        len: 0,
+    };
+
+    /// The state of a group that has not been joined, i.e. INCLUDE with no sources.
+    pub(crate) const NONE: SourceFilter = SourceFilter {
+        mode: FilterMode::Include,
+        ..SourceFilter::ANY
+    };
+
+    /// Create a source filter.
+    ///
+    /// Returns `Err(Error::Illegal)` if any of the sources is not a unicast address, or if
+    /// the filter mode is INCLUDE and there are no sources, since such a filter does not
+    /// receive any traffic; leave the group instead.
+    /// Returns `Err(Error::Exhausted)` if there are more than [MAX_SOURCES] distinct sources.
+    pub fn new(mode: FilterMode, sources: &[Ipv4Address]) -> Result<SourceFilter> {
+        if mode == FilterMode::Include && sources.is_empty() {
+            return Err(Error::Illegal);
+        }
+
+        let mut filter = SourceFilter {
+            mode,
+            ..SourceFilter::ANY
+        };
+        for source in sources {
+            if !source.is_unicast() {
+                return Err(Error::Illegal);
+            }
+            if filter.contains(*source) {
+                continue;
+            }
+            if filter.len == MAX_SOURCES {
+                return Err(Error::Exhausted);
+            }
+            filter.sources[filter.len] = *source;
+            filter.len += 1;
+        }
+        Ok(filter)
+    }
+
+    /// Return the filter mode.
+    pub fn mode(&self) -> FilterMode {
+        self.mode
+    }
+
+    /// Return the listed sources.
+    pub fn sources(&self) -> &[Ipv4Address] {
+        &self.sources[..self.len]
+    }
+
+    /// Query whether the filter accepts traffic from any source.
+    pub fn is_any(&self) -> bool {
+        self.mode == FilterMode::Exclude && self.len == 0
+    }
+
+    /// Query whether the filter accepts traffic from `source`.
+    pub fn accepts(&self, source: Ipv4Address) -> bool {
+        match self.mode {
+            FilterMode::Include => self.contains(source),
+            FilterMode::Exclude => !self.contains(source),
+        }
+    }
+
+    fn contains(&self, source: Ipv4Address) -> bool {
+        self.sources().contains(&source)
+    }
+
+    /// Return the sources listed in this filter, but not in `other`.
+    pub(crate) fn difference<'a>(
+        &'a self,
+        other: &'a SourceFilter,
+    ) -> impl Iterator<Item = Ipv4Address> + 'a {
+        self.sources()
+            .iter()
+            .cloned()
+            .filter(move |source| !other.contains(*source))
+    }
+}
+
+impl Default for SourceFilter {
+    fn default() -> Self {
+        SourceFilter::ANY
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SRC_A: Ipv4Address = Ipv4Address([192, 0, 2, 1]);
+    const SRC_B: Ipv4Address = Ipv4Address([192, 0, 2, 2]);
+
+    #[test]
+    fn test_any() {
+        assert!(SourceFilter::ANY.is_any());
+        assert!(SourceFilter::ANY.accepts(SRC_A));
+        assert_eq!(
+            SourceFilter::new(FilterMode::Exclude, &[]),
+            Ok(SourceFilter::ANY)
+        );
+    }
+
+    #[test]
+    fn test_include() {
+        let filter = SourceFilter::new(FilterMode::Include, &[SRC_A, SRC_A]).unwrap();
+        assert_eq!(filter.sources(), &[SRC_A]);
+        assert!(filter.accepts(SRC_A));
+        assert!(!filter.accepts(SRC_B));
+        assert_eq!(
+            SourceFilter::new(FilterMode::Include, &[]),
+            Err(Error::Illegal)
+        );
+    }
+
+    #[test]
+    fn test_exclude() {
+        let filter = SourceFilter::new(FilterMode::Exclude, &[SRC_A]).unwrap();
+        assert!(!filter.is_any());
+        assert!(!filter.accepts(SRC_A));
+        assert!(filter.accepts(SRC_B));
+    }
+
+    #[test]
+    fn test_invalid_sources() {
+        assert_eq!(
+            SourceFilter::new(FilterMode::Include, &[Ipv4Address::BROADCAST]),
+            Err(Error::Illegal)
+        );
+        let sources: std::vec::Vec<_> = (1..=MAX_SOURCES as u8 + 1)
+            .map(|i| Ipv4Address::new(192, 0, 2, i))
+            .collect();
+        assert_eq!(
+            SourceFilter::new(FilterMode::Include, &sources),
+            Err(Error::Exhausted)
+        );
+    }
+
+    #[test]
+    fn test_difference() {
+        let old = SourceFilter::new(FilterMode::Include, &[SRC_A]).unwrap();
+        let new = SourceFilter::new(FilterMode::Include, &[SRC_A, SRC_B]).unwrap();
+        assert!(new.difference(&new).next().is_none());
+        assert_eq!(new.difference(&old).collect::<std::vec::Vec<_>>(), &[SRC_B]);
+        assert!(old.difference(&new).next().is_none());
+    }
+}
@@ -17,6 +17,99 @@ pub struct Neighbor {
     expires_at: Instant,
 }
 
+impl Neighbor {
+    /// Return the hardware address of the neighbor.
+    pub fn hardware_addr(&self) -> HardwareAddress {
+        self.hardware_addr
+    }
+
+    /// Return the instant past which the mapping is discarded.
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+}
+
+/// How the hardware address of a neighbor is updated by ARP packets.
+///
+/// By default, any ARP packet aimed at the interface updates the neighbor cache, which makes
+/// it trivial for another host on the link to redirect traffic to itself. The other policies
+/// protect the hardware addresses of neighbors that are in the cache and not expired.
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ArpProtection {
+    /// Update the hardware address of neighbors from any ARP packet.
+    Off,
+    /// Ignore ARP packets that would change the hardware address of a neighbor,
+    /// until its cache entry expires.
+    Strict,
+    /// When an ARP packet would change the hardware address of a neighbor, ignore it and
+    /// send an ARP request to the current hardware address instead. If no reply is received
+    /// within [ArpProtection::PROBE_TIMEOUT], the cache entry expires, and the next ARP packet
+    /// from the neighbor updates its hardware address.
+    Probe,
+}
+
+#[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+impl ArpProtection {
+    /// Time the current hardware address of a neighbor has to reply to a probe.
+    pub const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+}
+
+/// Counters of a neighbor cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Stats {
+    /// Neighbors added to the cache.
+    pub insertions: u64,
+    /// Neighbors whose hardware address changed.
+    pub replacements: u64,
+    /// Neighbors removed to make room for another one, when the cache is full.
+    pub evictions: u64,
+    /// Discovery requests sent, such as ARP requests or Neighbor Solicitations.
+    pub requests: u64,
+    /// Discovery requests answered by the neighbor.
+    pub resolutions: u64,
+    /// Discovery requests that were not answered before the next request.
+    pub resolution_failures: u64,
+    /// Sum of the times between the discovery requests answered and their answers.
+    pub resolution_time_total: Duration,
+    /// Longest time between a discovery request and its answer.
+    pub resolution_time_max: Duration,
+}
+
+/// A change of a neighbor cache, reported to the event handler of the cache.
+///
+/// See [Cache::set_event_handler].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A neighbor was added to the cache.
+    Inserted {
+        protocol_addr: IpAddress,
+        hardware_addr: HardwareAddress,
+    },
+    /// The hardware address of a neighbor changed.
+    Replaced {
+        protocol_addr: IpAddress,
+        old_hardware_addr: HardwareAddress,
+        hardware_addr: HardwareAddress,
+    },
+    /// A neighbor was removed to make room for another one.
+    Evicted {
+        protocol_addr: IpAddress,
+        hardware_addr: HardwareAddress,
+    },
+    /// A neighbor answered a discovery request after `latency`.
+    Resolved {
+        protocol_addr: IpAddress,
+        latency: Duration,
+    },
+    /// A neighbor did not answer a discovery request.
+    ResolutionFailed { protocol_addr: IpAddress },
+}
+
 /// An answer to a neighbor cache lookup.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -65,6 +158,11 @@ pub struct Cache<'a> {
     silent_until: Instant,
     #[cfg(any(feature = "std", feature = "alloc"))]
     gc_threshold: usize,
+    /// The address of the last discovery request, if it is not answered yet,
+    /// and when it was sent.
+    pending: Option<(IpAddress, Instant)>,
+    stats: Stats,
+    event_handler: Option<fn(Event)>,
 }
 
 impl<'a> Cache<'a> {
@@ -94,6 +192,9 @@ impl<'a> Cache<'a> {
             #[cfg(any(feature = "std", feature = "alloc"))]
             gc_threshold: Self::GC_THRESHOLD,
             silent_until: Instant::from_millis(0),
+            pending: None,
+            stats: Stats::default(),
+            event_handler: None,
         }
     }
 
@@ -109,6 +210,9 @@ impl<'a> Cache<'a> {
             storage,
             gc_threshold,
             silent_until: Instant::from_millis(0),
+            pending: None,
+            stats: Stats::default(),
+            event_handler: None,
         }
     }
 
@@ -139,6 +243,20 @@ impl<'a> Cache<'a> {
                 }
             }
         };
+        if let Some((pending_addr, requested_at)) = self.pending {
+            if pending_addr == protocol_addr {
+                let latency = timestamp - requested_at;
+                self.pending = None;
+                self.stats.resolutions += 1;
+                self.stats.resolution_time_total += latency;
+                self.stats.resolution_time_max = self.stats.resolution_time_max.max(latency);
+                self.emit(Event::Resolved {
+                    protocol_addr,
+                    latency,
+                });
+            }
+        }
+
         let neighbor = Neighbor {
             expires_at: timestamp + Self::ENTRY_LIFETIME,
             hardware_addr,
@@ -152,10 +270,21 @@ impl<'a> Cache<'a> {
                         hardware_addr,
                         old_neighbor.hardware_addr
                     );
+                    self.stats.replacements += 1;
+                    self.emit(Event::Replaced {
+                        protocol_addr,
+                        old_hardware_addr: old_neighbor.hardware_addr,
+                        hardware_addr,
+                    });
                 }
             }
             Ok(None) => {
                 net_trace!("filled {} => {} (was empty)", protocol_addr, hardware_addr);
+                self.stats.insertions += 1;
+                self.emit(Event::Inserted {
+                    protocol_addr,
+                    hardware_addr,
+                });
             }
             Err((protocol_addr, neighbor)) => {
                 // If we're going down this branch, it means that a fixed-size cache storage
@@ -177,7 +306,7 @@ impl<'a> Cache<'a> {
                     ManagedMap::Owned(_) => unreachable!(),
                 };
 
-                let _old_neighbor = self.storage.remove(&old_protocol_addr).unwrap();
+                let old_neighbor = self.storage.remove(&old_protocol_addr).unwrap();
                 match self.storage.insert(protocol_addr, neighbor) {
                     Ok(None) => {
                         net_trace!(
@@ -185,12 +314,22 @@ impl<'a> Cache<'a> {
                             protocol_addr,
                             hardware_addr,
                             old_protocol_addr,
-                            _old_neighbor.hardware_addr
+                            old_neighbor.hardware_addr
                         );
                     }
                     // We've covered everything else above.
                     _ => unreachable!(),
                 }
+                self.stats.evictions += 1;
+                self.emit(Event::Evicted {
+                    protocol_addr: old_protocol_addr,
+                    hardware_addr: old_neighbor.hardware_addr,
+                });
+                self.stats.insertions += 1;
+                self.emit(Event::Inserted {
+                    protocol_addr,
+                    hardware_addr,
+                });
             }
         }
     }
@@ -215,13 +354,92 @@ impl<'a> Cache<'a> {
         }
     }
 
+    /// Return the hardware address of `protocol_addr` if it is in the cache, not expired,
+    /// and different from `hardware_addr`.
+    #[allow(unused)] // unused depending on which protocols are enabled
+    pub(crate) fn conflict(
+        &self,
+        protocol_addr: &IpAddress,
+        hardware_addr: HardwareAddress,
+        timestamp: Instant,
+    ) -> Option<HardwareAddress> {
+        match self.storage.get(protocol_addr) {
+            Some(neighbor)
+                if timestamp < neighbor.expires_at && neighbor.hardware_addr != hardware_addr =>
+            {
+                Some(neighbor.hardware_addr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Make the entry of `protocol_addr` expire at `expires_at` at the latest.
+    #[allow(unused)] // unused depending on which protocols are enabled
+    pub(crate) fn expire_by(&mut self, protocol_addr: &IpAddress, expires_at: Instant) {
+        if let Some(neighbor) = self.storage.get(protocol_addr) {
+            if neighbor.expires_at > expires_at {
+                let neighbor = Neighbor {
+                    expires_at,
+                    ..*neighbor
+                };
+                // Cannot fail, the address is already present.
+                let _ = self.storage.insert(*protocol_addr, neighbor);
+            }
+        }
+    }
+
     pub(crate) fn limit_rate(&mut self, timestamp: Instant) {
         self.silent_until = timestamp + Self::SILENT_TIME;
     }
 
+    /// Record that a discovery request for `protocol_addr` was sent, and limit the rate
+    /// of the next ones.
+    ///
+    /// Returns the address of the previous request, if it was not answered.
+    pub(crate) fn requested(
+        &mut self,
+        protocol_addr: IpAddress,
+        timestamp: Instant,
+    ) -> Option<IpAddress> {
+        let failed_addr = self.pending.map(|(pending_addr, _)| pending_addr);
+        if let Some(pending_addr) = failed_addr {
+            self.stats.resolution_failures += 1;
+            self.emit(Event::ResolutionFailed {
+                protocol_addr: pending_addr,
+            });
+        }
+        self.pending = Some((protocol_addr, timestamp));
+        self.stats.requests += 1;
+        self.limit_rate(timestamp);
+        failed_addr
+    }
+
+    /// Return the counters of the cache.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Set the function called on every change of the cache, and on every answer to,
+    /// or failure of, a discovery request.
+    pub fn set_event_handler(&mut self, handler: Option<fn(Event)>) {
+        self.event_handler = handler;
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(handler) = self.event_handler {
+            handler(event)
+        }
+    }
+
     pub(crate) fn flush(&mut self) {
         self.storage.clear()
     }
+
+    /// Iterate over the cached neighbors, including the expired ones that were not
+    /// discarded yet.
+    pub fn iter(&self) -> impl Iterator<Item = (&IpAddress, &Neighbor)> {
+        self.storage.iter()
+    }
 }
 
 #[cfg(test)]
@@ -288,6 +506,31 @@ mod test {
             .found(),);
     }
 
+    #[test]
+    fn test_conflict() {
+        let mut cache_storage = [Default::default(); 3];
+        let mut cache = Cache::new(&mut cache_storage[..]);
+
+        cache.fill(MOCK_IP_ADDR_1, HADDR_A, Instant::from_millis(0));
+        let timestamp = Instant::from_millis(0);
+        assert_eq!(cache.conflict(&MOCK_IP_ADDR_1, HADDR_A, timestamp), None);
+        assert_eq!(
+            cache.conflict(&MOCK_IP_ADDR_1, HADDR_B, timestamp),
+            Some(HADDR_A)
+        );
+        assert_eq!(cache.conflict(&MOCK_IP_ADDR_2, HADDR_B, timestamp), None);
+
+        cache.expire_by(&MOCK_IP_ADDR_1, Instant::from_millis(1000));
+        assert_eq!(
+            cache.conflict(&MOCK_IP_ADDR_1, HADDR_B, Instant::from_millis(999)),
+            Some(HADDR_A)
+        );
+        assert_eq!(
+            cache.conflict(&MOCK_IP_ADDR_1, HADDR_B, Instant::from_millis(1000)),
+            None
+        );
+    }
+
     #[test]
     fn test_replace() {
         let mut cache_storage = [Default::default(); 3];
@@ -354,6 +597,52 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_stats() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+        let mut cache_storage = [Default::default(); 2];
+        let mut cache = Cache::new(&mut cache_storage[..]);
+        cache.set_event_handler(Some(|_| {
+            EVENTS.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        cache.fill(MOCK_IP_ADDR_1, HADDR_A, Instant::from_millis(0));
+        cache.fill(MOCK_IP_ADDR_1, HADDR_B, Instant::from_millis(0));
+        cache.fill(MOCK_IP_ADDR_2, HADDR_B, Instant::from_millis(10));
+        cache.fill(MOCK_IP_ADDR_3, HADDR_C, Instant::from_millis(20));
+        assert_eq!(cache.stats().insertions, 3);
+        assert_eq!(cache.stats().replacements, 1);
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(EVENTS.load(Ordering::Relaxed), 5);
+
+        // A request answered, and one that is not.
+        assert_eq!(
+            cache.requested(MOCK_IP_ADDR_4, Instant::from_millis(1000)),
+            None
+        );
+        cache.fill(MOCK_IP_ADDR_4, HADDR_D, Instant::from_millis(1030));
+        assert_eq!(
+            cache.requested(MOCK_IP_ADDR_1, Instant::from_millis(2000)),
+            None
+        );
+        assert_eq!(
+            cache.requested(MOCK_IP_ADDR_1, Instant::from_millis(3000)),
+            Some(MOCK_IP_ADDR_1)
+        );
+        assert_eq!(cache.stats().requests, 3);
+        assert_eq!(cache.stats().resolutions, 1);
+        assert_eq!(cache.stats().resolution_failures, 1);
+        assert_eq!(
+            cache.stats().resolution_time_total,
+            Duration::from_millis(30)
+        );
+        assert_eq!(cache.stats().resolution_time_max, Duration::from_millis(30));
+        assert_eq!(EVENTS.load(Ordering::Relaxed), 9);
+    }
+
     #[test]
     fn test_hush() {
         let mut cache_storage = [Default::default(); 3];
@@ -0,0 +1,276 @@
+//! LCP echo keepalive of a PPP session, detecting a dead peer with the Echo-Request and
+//! Echo-Reply packets of RFC 1661 § 5.8.
+
+use crate::time::{Duration, Instant};
+use crate::wire::LcpRepr;
+
+/// Configuration of the LCP echo keepalive of a PPP session.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Interval between Echo-Requests.
+    pub interval: Duration,
+    /// Number of consecutive Echo-Requests left unanswered after which the peer is
+    /// considered dead.
+    pub max_failures: u8,
+}
+
+impl Config {
+    /// Default interval between Echo-Requests.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+    /// Default number of unanswered Echo-Requests after which the peer is considered dead.
+    pub const DEFAULT_MAX_FAILURES: u8 = 3;
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            interval: Self::DEFAULT_INTERVAL,
+            max_failures: Self::DEFAULT_MAX_FAILURES,
+        }
+    }
+}
+
+/// LCP echo keepalive and dead peer detection of a PPP or PPPoE session.
+///
+/// Carrier links often drop sessions silently, leaving both ends believing the session
+/// is still open. The keepalive sends an Echo-Request every interval, and considers the
+/// peer dead once too many of them are left unanswered in a row, at which point the
+/// callback registered with [set_dead_peer_callback] is called so that the session can
+/// be torn down and established again. It also answers the Echo-Requests of the peer.
+///
+/// The keepalive does not send or receive frames itself: the PPP implementation emits
+/// the packets returned by [poll] and [process] with the [Lcp] protocol number, and
+/// passes the Echo-Requests and Echo-Replies it receives to [process].
+///
+/// [set_dead_peer_callback]: #method.set_dead_peer_callback
+/// [poll]: #method.poll
+/// [process]: #method.process
+/// [Lcp]: crate::wire::PppProtocol::Lcp
+#[derive(Debug)]
+pub struct Keepalive {
+    config: Config,
+    /// The magic number negotiated for the local end of the link, or zero.
+    magic_number: u32,
+    /// Identifier of the last Echo-Request sent.
+    identifier: u8,
+    /// Number of consecutive Echo-Requests sent without a reply.
+    unanswered: u8,
+    next_echo: Instant,
+    dead: bool,
+    dead_peer_callback: Option<fn()>,
+}
+
+impl Keepalive {
+    /// Create a keepalive for a session whose local end negotiated the given magic number,
+    /// or zero if none was negotiated. The first Echo-Request is sent right away.
+    pub fn new(config: Config, magic_number: u32) -> Keepalive {
+        Keepalive {
+            config,
+            magic_number,
+            identifier: 0,
+            unanswered: 0,
+            next_echo: Instant::ZERO,
+            dead: false,
+            dead_peer_callback: None,
+        }
+    }
+
+    /// Set a function to be called once when the peer is found to be dead.
+    pub fn set_dead_peer_callback(&mut self, callback: Option<fn()>) {
+        self.dead_peer_callback = callback;
+    }
+
+    /// Query whether the peer was found to be dead.
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Restart the keepalive at `timestamp` for a new session whose local end negotiated
+    /// the given magic number.
+    pub fn restart(&mut self, magic_number: u32, timestamp: Instant) {
+        self.magic_number = magic_number;
+        self.unanswered = 0;
+        self.next_echo = timestamp;
+        self.dead = false;
+    }
+
+    /// Return the time at which [poll](#method.poll) should be called next, or `None`
+    /// if the peer is dead.
+    pub fn poll_at(&self) -> Option<Instant> {
+        if self.dead {
+            None
+        } else {
+            Some(self.next_echo)
+        }
+    }
+
+    /// Return the Echo-Request to send at `timestamp`, if one is due.
+    ///
+    /// Once the configured number of Echo-Requests are left unanswered, the peer is
+    /// considered dead, the dead peer callback is called, and nothing is sent anymore
+    /// until the keepalive is restarted.
+    pub fn poll(&mut self, timestamp: Instant) -> Option<LcpRepr<'static>> {
+        if self.dead || timestamp < self.next_echo {
+            return None;
+        }
+
+        if self.unanswered >= self.config.max_failures {
+            net_debug!(
+                "ppp: {} echo requests unanswered, peer is dead",
+                self.unanswered
+            );
+            self.dead = true;
+            if let Some(callback) = self.dead_peer_callback {
+                callback()
+            }
+            return None;
+        }
+
+        self.identifier = self.identifier.wrapping_add(1);
+        self.unanswered += 1;
+        self.next_echo = timestamp + self.config.interval;
+        Some(LcpRepr::EchoRequest {
+            identifier: self.identifier,
+            magic_number: self.magic_number,
+            data: &[],
+        })
+    }
+
+    /// Process an Echo-Request or Echo-Reply received from the peer, and return the
+    /// Echo-Reply to send, if any.
+    pub fn process<'a>(&mut self, repr: &LcpRepr<'a>) -> Option<LcpRepr<'a>> {
+        match *repr {
+            LcpRepr::EchoRequest {
+                identifier,
+                magic_number,
+                data,
+            } => {
+                if self.is_looped_back(magic_number) {
+                    net_debug!("ppp: echo request with our own magic number, link looped back");
+                    return None;
+                }
+                Some(LcpRepr::EchoReply {
+                    identifier,
+                    magic_number: self.magic_number,
+                    data,
+                })
+            }
+            LcpRepr::EchoReply {
+                identifier,
+                magic_number,
+                ..
+            } => {
+                if identifier == self.identifier && !self.is_looped_back(magic_number) {
+                    self.unanswered = 0;
+                }
+                None
+            }
+        }
+    }
+
+    /// RFC 1661 § 5.8: a packet carrying our own, non-zero, magic number was most likely
+    /// sent by us, and is silently discarded.
+    fn is_looped_back(&self, magic_number: u32) -> bool {
+        self.magic_number != 0 && magic_number == self.magic_number
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MAGIC: u32 = 0x1234_5678;
+    const PEER_MAGIC: u32 = 0x0bad_cafe;
+
+    fn reply_to(request: LcpRepr) -> LcpRepr {
+        match request {
+            LcpRepr::EchoRequest {
+                identifier, data, ..
+            } => LcpRepr::EchoReply {
+                identifier,
+                magic_number: PEER_MAGIC,
+                data,
+            },
+            repr => panic!("unexpected {:?}", repr),
+        }
+    }
+
+    #[test]
+    fn test_echo() {
+        let mut keepalive = Keepalive::new(Config::default(), MAGIC);
+        assert_eq!(keepalive.poll_at(), Some(Instant::ZERO));
+        let mut request = keepalive.poll(Instant::ZERO).unwrap();
+        assert_eq!(
+            request,
+            LcpRepr::EchoRequest {
+                identifier: 1,
+                magic_number: MAGIC,
+                data: &[],
+            }
+        );
+        assert_eq!(keepalive.poll(Instant::from_secs(5)), None);
+        assert_eq!(keepalive.poll_at(), Some(Instant::from_secs(10)));
+
+        // Replies keep the peer alive indefinitely.
+        for secs in 1..10 {
+            assert_eq!(keepalive.process(&reply_to(request)), None);
+            request = keepalive.poll(Instant::from_secs(secs * 10)).unwrap();
+        }
+        assert!(!keepalive.is_dead());
+    }
+
+    #[test]
+    fn test_dead_peer() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static DEAD: AtomicUsize = AtomicUsize::new(0);
+        fn callback() {
+            DEAD.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let mut keepalive = Keepalive::new(Config::default(), MAGIC);
+        keepalive.set_dead_peer_callback(Some(callback));
+        let first = keepalive.poll(Instant::ZERO).unwrap();
+        assert!(keepalive.poll(Instant::from_secs(10)).is_some());
+        // A late reply to an earlier request does not count.
+        assert_eq!(keepalive.process(&reply_to(first)), None);
+        assert!(keepalive.poll(Instant::from_secs(20)).is_some());
+        assert_eq!(keepalive.poll(Instant::from_secs(30)), None);
+        assert!(keepalive.is_dead());
+        assert_eq!(DEAD.load(Ordering::SeqCst), 1);
+        assert_eq!(keepalive.poll_at(), None);
+        assert_eq!(keepalive.poll(Instant::from_secs(40)), None);
+        assert_eq!(DEAD.load(Ordering::SeqCst), 1);
+
+        keepalive.restart(MAGIC, Instant::from_secs(50));
+        assert!(!keepalive.is_dead());
+        assert!(keepalive.poll(Instant::from_secs(50)).is_some());
+    }
+
+    #[test]
+    fn test_answer_echo_request() {
+        let mut keepalive = Keepalive::new(Config::default(), MAGIC);
+        let request = LcpRepr::EchoRequest {
+            identifier: 7,
+            magic_number: PEER_MAGIC,
+            data: b"ping",
+        };
+        assert_eq!(
+            keepalive.process(&request),
+            Some(LcpRepr::EchoReply {
+                identifier: 7,
+                magic_number: MAGIC,
+                data: b"ping",
+            })
+        );
+
+        // Our own requests, looped back by the link, are not answered.
+        let looped = LcpRepr::EchoRequest {
+            identifier: 1,
+            magic_number: MAGIC,
+            data: &[],
+        };
+        assert_eq!(keepalive.process(&looped), None);
+    }
+}
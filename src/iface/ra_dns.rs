@@ -0,0 +1,171 @@
+//! DNS configuration advertised by routers, recorded as described in RFC 8106 § 5.3.
+
+use heapless::{String, Vec};
+
+use crate::time::{Duration, Instant};
+use crate::wire::{Ipv6Address, NdiscDnsSearchList, NdiscRecursiveDnsServer};
+
+/// Maximum number of advertised DNS servers kept, per RFC 8106 § 5.3.1.
+const MAX_SERVER_COUNT: usize = 3;
+
+/// Maximum number of advertised search domains kept.
+const MAX_DOMAIN_COUNT: usize = 3;
+
+/// Maximum length of a domain name in text form, per RFC 1035 § 2.3.4.
+const MAX_DOMAIN_LEN: usize = 253;
+
+/// Update the expiry of `item` in `list`, replacing the entry that expires soonest
+/// if the list is full, per RFC 8106 § 5.3.1.
+fn update<T: PartialEq, const N: usize>(
+    list: &mut Vec<(T, Instant), N>,
+    item: T,
+    lifetime: Duration,
+    timestamp: Instant,
+) {
+    list.retain(|(_, expires_at)| *expires_at > timestamp);
+    let expires_at = timestamp + lifetime;
+    match list.iter().position(|(other, _)| *other == item) {
+        Some(_) if lifetime == Duration::ZERO => list.retain(|(other, _)| *other != item),
+        Some(index) => list[index].1 = expires_at,
+        None if lifetime == Duration::ZERO => (),
+        None => {
+            if let Err(item) = list.push((item, expires_at)) {
+                let soonest = list
+                    .iter_mut()
+                    .min_by_key(|(_, expires_at)| *expires_at)
+                    .unwrap();
+                if soonest.1 < expires_at {
+                    *soonest = item;
+                }
+            }
+        }
+    }
+}
+
+/// Return the text form of the domain name `name`, encoded as in RFC 1035 § 3.1
+/// without the terminating empty label.
+fn decode_domain(mut name: &[u8]) -> Option<String<MAX_DOMAIN_LEN>> {
+    let mut text = String::new();
+    while let Some((&len, rest)) = name.split_first() {
+        let label = rest.get(..len as usize)?;
+        if !label.iter().all(|c| c.is_ascii_graphic() && *c != b'.') {
+            return None;
+        }
+        if !text.is_empty() {
+            text.push('.').ok()?;
+        }
+        text.push_str(core::str::from_utf8(label).ok()?).ok()?;
+        name = &rest[len as usize..];
+    }
+    Some(text)
+}
+
+/// DNS configuration advertised to an interface by routers.
+#[derive(Debug, Default)]
+pub(crate) struct RaDns {
+    servers: Vec<(Ipv6Address, Instant), MAX_SERVER_COUNT>,
+    domains: Vec<(String<MAX_DOMAIN_LEN>, Instant), MAX_DOMAIN_COUNT>,
+}
+
+impl RaDns {
+    /// Update the DNS servers from a Recursive DNS Server option received at `timestamp`.
+    pub(crate) fn process_rdnss(&mut self, rdnss: &NdiscRecursiveDnsServer, timestamp: Instant) {
+        for addr in rdnss.addresses().filter(|addr| addr.is_unicast()) {
+            update(&mut self.servers, addr, rdnss.lifetime, timestamp);
+        }
+    }
+
+    /// Update the search domains from a DNS Search List option received at `timestamp`.
+    pub(crate) fn process_dnssl(&mut self, dnssl: &NdiscDnsSearchList, timestamp: Instant) {
+        for domain in dnssl.domains().filter_map(decode_domain) {
+            update(&mut self.domains, domain, dnssl.lifetime, timestamp);
+        }
+    }
+
+    /// Return the DNS servers valid at `timestamp`.
+    pub(crate) fn servers(&self, timestamp: Instant) -> impl Iterator<Item = Ipv6Address> + '_ {
+        self.servers
+            .iter()
+            .filter(move |(_, expires_at)| *expires_at > timestamp)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Return the search domains valid at `timestamp`.
+    pub(crate) fn domains(&self, timestamp: Instant) -> impl Iterator<Item = &str> + '_ {
+        self.domains
+            .iter()
+            .filter(move |(_, expires_at)| *expires_at > timestamp)
+            .map(|(domain, _)| domain.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SERVERS: [u8; 64] = [
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, //
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02, //
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x03, //
+        0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04,
+    ];
+
+    fn server(n: u16) -> Ipv6Address {
+        Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, n)
+    }
+
+    fn rdnss(addresses: &[u8], secs: u64) -> NdiscRecursiveDnsServer<'_> {
+        NdiscRecursiveDnsServer {
+            lifetime: Duration::from_secs(secs),
+            addresses,
+        }
+    }
+
+    #[test]
+    fn test_servers() {
+        let mut dns = RaDns::default();
+        dns.process_rdnss(&rdnss(&SERVERS[..32], 60), Instant::from_secs(0));
+        dns.process_rdnss(&rdnss(&SERVERS[32..48], 30), Instant::from_secs(0));
+        assert_eq!(
+            dns.servers(Instant::from_secs(10))
+                .collect::<std::vec::Vec<_>>(),
+            [server(1), server(2), server(3)]
+        );
+
+        // When full, the server expiring soonest is replaced.
+        dns.process_rdnss(&rdnss(&SERVERS[48..], 60), Instant::from_secs(10));
+        assert_eq!(
+            dns.servers(Instant::from_secs(10))
+                .collect::<std::vec::Vec<_>>(),
+            [server(1), server(2), server(4)]
+        );
+
+        // A zero lifetime removes the server.
+        dns.process_rdnss(&rdnss(&SERVERS[..16], 0), Instant::from_secs(20));
+        assert_eq!(
+            dns.servers(Instant::from_secs(20))
+                .collect::<std::vec::Vec<_>>(),
+            [server(2), server(4)]
+        );
+
+        // Servers expire.
+        assert_eq!(dns.servers(Instant::from_secs(60)).count(), 1);
+        assert_eq!(dns.servers(Instant::from_secs(70)).count(), 0);
+    }
+
+    #[test]
+    fn test_domains() {
+        let mut dns = RaDns::default();
+        let dnssl = NdiscDnsSearchList {
+            lifetime: Duration::from_secs(60),
+            domains: b"\x07example\x03com\x00\x03l.n\x00\x03lan\x00\x00\x00",
+        };
+        dns.process_dnssl(&dnssl, Instant::from_secs(0));
+        assert_eq!(
+            dns.domains(Instant::from_secs(0))
+                .collect::<std::vec::Vec<_>>(),
+            ["example.com", "lan"]
+        );
+        assert_eq!(dns.domains(Instant::from_secs(60)).count(), 0);
+    }
+}
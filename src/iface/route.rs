@@ -1,7 +1,12 @@
-use crate::time::Instant;
+use crate::time::{Duration, Instant};
 use core::ops::Bound;
 use managed::ManagedMap;
 
+#[cfg(all(
+    feature = "proto-ipv6",
+    any(feature = "medium-ethernet", feature = "medium-ieee802154")
+))]
+use crate::wire::NdiscRoutePreference;
 use crate::wire::{IpAddress, IpCidr};
 #[cfg(feature = "proto-ipv4")]
 use crate::wire::{Ipv4Address, Ipv4Cidr};
@@ -14,10 +19,23 @@ use crate::{Error, Result};
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Route {
     pub via_router: IpAddress,
+    /// A router to send the packets through while `via_router` is dead, e.g. the
+    /// gateway of a redundant uplink. See [Routes::is_router_dead].
+    pub backup_router: Option<IpAddress>,
+    /// The IP MTU of the path through the route, e.g. of a tunnel, if it is lower than
+    /// the MTU of the interface. `None` means the MTU of the interface.
+    pub mtu: Option<usize>,
     /// `None` means "forever".
     pub preferred_until: Option<Instant>,
     /// `None` means "forever".
     pub expires_at: Option<Instant>,
+    /// Preference of the router for the prefix, over the routers advertising the same
+    /// prefix, as specified in RFC 4191.
+    #[cfg(all(
+        feature = "proto-ipv6",
+        any(feature = "medium-ethernet", feature = "medium-ieee802154")
+    ))]
+    pub preference: NdiscRoutePreference,
 }
 
 impl Route {
@@ -26,8 +44,15 @@ impl Route {
     pub fn new_ipv4_gateway(gateway: Ipv4Address) -> Route {
         Route {
             via_router: gateway.into(),
+            backup_router: None,
+            mtu: None,
             preferred_until: None,
             expires_at: None,
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
         }
     }
 
@@ -36,10 +61,92 @@ impl Route {
     pub fn new_ipv6_gateway(gateway: Ipv6Address) -> Route {
         Route {
             via_router: gateway.into(),
+            backup_router: None,
+            mtu: None,
             preferred_until: None,
             expires_at: None,
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            preference: NdiscRoutePreference::Medium,
         }
     }
+
+    /// Returns the route, failing over to `router` while its router is dead.
+    pub fn with_backup_router(self, router: IpAddress) -> Route {
+        Route {
+            backup_router: Some(router),
+            ..self
+        }
+    }
+
+    /// Returns the route, with a path MTU of `mtu`.
+    pub fn with_mtu(self, mtu: usize) -> Route {
+        Route {
+            mtu: Some(mtu),
+            ..self
+        }
+    }
+}
+
+/// The packets a policy route applies to: those sent to `dst`, and, if they are given,
+/// sent from an address in `src`, or by a socket with the `mark`.
+///
+/// This is the equivalent of a Linux routing policy rule selecting a table with a single
+/// route, e.g. `ip rule add from 10.0.1.0/24 fwmark 1 table 1` followed by
+/// `ip route add 0.0.0.0/0 via 10.0.1.1 table 1`. See [Routes::add_policy_route].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RoutePolicy {
+    /// The prefix of the destination addresses.
+    pub dst: IpCidr,
+    /// The prefix of the source addresses, or `None` for any source address.
+    pub src: Option<IpCidr>,
+    /// The mark of the sending socket, or `None` for any socket. See
+    /// [SocketSet::set_mark](crate::iface::SocketSet::set_mark).
+    pub mark: Option<u32>,
+}
+
+impl RoutePolicy {
+    fn matches(
+        &self,
+        dst_addr: &IpAddress,
+        src_addr: Option<&IpAddress>,
+        mark: Option<u32>,
+    ) -> bool {
+        if !self.dst.contains_addr(dst_addr) {
+            return false;
+        }
+        if let Some(src) = self.src {
+            match src_addr {
+                Some(src_addr) if src.contains_addr(src_addr) => (),
+                _ => return false,
+            }
+        }
+        self.mark.is_none() || self.mark == mark
+    }
+
+    /// Return a key ordering the policies from the least to the most specific.
+    fn specificity(&self) -> (u8, u8, bool) {
+        (
+            self.dst.prefix_len(),
+            self.src.map_or(0, |src| src.prefix_len() + 1),
+            self.mark.is_some(),
+        )
+    }
+}
+
+/// The number of routers whose reachability is tracked at a time.
+const MAX_TRACKED_ROUTERS: usize = 4;
+
+/// Reachability of a router, inferred from the failures to reach it.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct RouterHealth {
+    router: IpAddress,
+    /// Number of failures in a row, each less than `FAILURE_WINDOW` after the previous one.
+    failures: u8,
+    last_failure: Instant,
+    /// The time until which the router is dead, if it is.
+    dead_until: Option<Instant>,
 }
 
 /// A routing table.
@@ -64,6 +171,8 @@ impl Route {
 #[derive(Debug)]
 pub struct Routes<'a> {
     storage: ManagedMap<'a, IpCidr, Route>,
+    policy_storage: ManagedMap<'a, RoutePolicy, Route>,
+    health: [Option<RouterHealth>; MAX_TRACKED_ROUTERS],
 }
 
 impl<'a> Routes<'a> {
@@ -74,9 +183,60 @@ impl<'a> Routes<'a> {
         T: Into<ManagedMap<'a, IpCidr, Route>>,
     {
         let storage = storage.into();
-        Routes { storage }
+        Routes {
+            storage,
+            policy_storage: ManagedMap::Borrowed(&mut []),
+            health: [None; MAX_TRACKED_ROUTERS],
+        }
+    }
+
+    /// Set the storage of the policy routes, replacing the current policy routes.
+    ///
+    /// By default, there is no storage, and no policy route can be added.
+    pub fn set_policy_storage<T>(&mut self, storage: T)
+    where
+        T: Into<ManagedMap<'a, RoutePolicy, Route>>,
+    {
+        self.policy_storage = storage.into();
+    }
+
+    /// Add a route for the packets matching `policy`.
+    ///
+    /// Policy routes take precedence over the other routes. Of the policy routes matching
+    /// a packet, the one with the longest destination prefix is used, then the one with the
+    /// longest source prefix, then the one with a mark. This allows e.g. the packets sent
+    /// from the address of a management VLAN to go through its own router, while the other
+    /// packets go through the default route.
+    ///
+    /// On success, returns the previous route for `policy`, if any.
+    pub fn add_policy_route(&mut self, policy: RoutePolicy, route: Route) -> Result<Option<Route>> {
+        match self.policy_storage.insert(policy, route) {
+            Ok(route) => Ok(route),
+            Err((_policy, _route)) => Err(Error::Exhausted),
+        }
+    }
+
+    /// Remove the route for the packets matching `policy`.
+    ///
+    /// On success, returns the removed route, if any.
+    pub fn remove_policy_route(&mut self, policy: &RoutePolicy) -> Option<Route> {
+        self.policy_storage.remove(policy)
     }
 
+    /// Iterate over the policy routes, including the expired ones.
+    pub fn iter_policy(&self) -> impl Iterator<Item = (&RoutePolicy, &Route)> {
+        self.policy_storage.iter()
+    }
+
+    /// Number of failures in a row after which a router is dead.
+    pub const DEAD_ROUTER_FAILURES: u8 = 3;
+
+    /// Failures further apart than this are not counted as in a row.
+    pub const FAILURE_WINDOW: Duration = Duration::from_secs(10);
+
+    /// Time during which a dead router is not used, before it is tried again.
+    pub const DEAD_ROUTER_HOLD_TIME: Duration = Duration::from_secs(30);
+
     /// Update the routes of this node.
     pub fn update<F: FnOnce(&mut ManagedMap<'a, IpCidr, Route>)>(&mut self, f: F) {
         f(&mut self.storage);
@@ -126,7 +286,239 @@ impl<'a> Routes<'a> {
         self.storage.remove(&cidr)
     }
 
+    /// Update the route to `cidr` advertised by `router` with `preference`, for `lifetime`,
+    /// following RFC 4191 § 3.1. A zero `lifetime` removes the route if it is via `router`.
+    ///
+    /// A route via another router is only replaced if it has expired, or has a lower or
+    /// equal preference. Routes that do not expire are never replaced.
+    ///
+    /// On success, returns whether the table was changed.
+    #[cfg(all(
+        feature = "proto-ipv6",
+        any(feature = "medium-ethernet", feature = "medium-ieee802154")
+    ))]
+    pub(crate) fn update_advertised_route(
+        &mut self,
+        cidr: Ipv6Cidr,
+        router: Ipv6Address,
+        preference: NdiscRoutePreference,
+        lifetime: Duration,
+        timestamp: Instant,
+    ) -> Result<bool> {
+        let key = IpCidr::Ipv6(cidr);
+        let via_router = IpAddress::Ipv6(router);
+        match self.storage.get(&key) {
+            Some(route) if route.via_router == via_router && lifetime == Duration::ZERO => {
+                self.storage.remove(&key);
+                return Ok(true);
+            }
+            Some(route) if route.via_router == via_router => (),
+            Some(route) => {
+                let expired =
+                    matches!(route.expires_at, Some(expires_at) if timestamp > expires_at);
+                if lifetime == Duration::ZERO
+                    || route.expires_at.is_none()
+                    || (!expired && route.preference > preference)
+                {
+                    return Ok(false);
+                }
+            }
+            None if lifetime == Duration::ZERO => return Ok(false),
+            None => (),
+        }
+
+        let route = Route {
+            via_router,
+            backup_router: None,
+            mtu: None,
+            preferred_until: None,
+            expires_at: Some(timestamp + lifetime),
+            preference,
+        };
+        match self.storage.insert(key, route) {
+            Ok(_) => Ok(true),
+            Err(_) => Err(Error::Exhausted),
+        }
+    }
+
+    /// Iterate over the routes, including the expired ones.
+    pub fn iter(&self) -> impl Iterator<Item = (&IpCidr, &Route)> {
+        self.storage.iter()
+    }
+
+    /// Query whether a router was found dead, and should be avoided.
+    ///
+    /// A router is dead once [DEAD_ROUTER_FAILURES] attempts to reach it failed in a row:
+    /// neighbor discovery requests left unanswered, or ICMP errors in which it reports
+    /// having no route to a network. The routes with a [backup router] then go through
+    /// the backup router, until [DEAD_ROUTER_HOLD_TIME] has passed. The router is then
+    /// used again, and is only found dead again after as many failures, so that the routes
+    /// do not flap between the routers.
+    ///
+    /// [DEAD_ROUTER_FAILURES]: #associatedconstant.DEAD_ROUTER_FAILURES
+    /// [DEAD_ROUTER_HOLD_TIME]: #associatedconstant.DEAD_ROUTER_HOLD_TIME
+    /// [backup router]: Route::backup_router
+    pub fn is_router_dead(&self, router: &IpAddress, timestamp: Instant) -> bool {
+        self.health.iter().flatten().any(|health| {
+            health.router == *router
+                && matches!(health.dead_until, Some(dead_until) if timestamp < dead_until)
+        })
+    }
+
+    /// Query whether a route expired, or a dead router was used again, between `since`
+    /// and `until`, changing the result of the lookups.
+    pub(crate) fn expired_between(&self, since: Instant, until: Instant) -> bool {
+        let between = |at: Option<Instant>| matches!(at, Some(at) if since <= at && at <= until);
+        self.storage
+            .iter()
+            .map(|(_, route)| route.expires_at)
+            .chain(
+                self.policy_storage
+                    .iter()
+                    .map(|(_, route)| route.expires_at),
+            )
+            .chain(self.health.iter().flatten().map(|health| health.dead_until))
+            .any(between)
+    }
+
+    /// Record a failure to reach `router`, and return whether it was found dead.
+    pub(crate) fn router_failed(&mut self, router: &IpAddress, timestamp: Instant) -> bool {
+        let has_backup = self
+            .storage
+            .iter()
+            .map(|(_, route)| route)
+            .chain(self.policy_storage.iter().map(|(_, route)| route))
+            .any(|route| route.via_router == *router && route.backup_router.is_some());
+        if !has_backup {
+            return false;
+        }
+
+        let index = match self
+            .health
+            .iter()
+            .position(|health| matches!(health, Some(health) if health.router == *router))
+        {
+            Some(index) => index,
+            // Forget about the router that failed the longest time ago, unless it is dead.
+            None => match self
+                .health
+                .iter()
+                .enumerate()
+                .filter(|(_, health)| match health {
+                    Some(health) => match health.dead_until {
+                        Some(dead_until) => timestamp >= dead_until,
+                        None => true,
+                    },
+                    None => true,
+                })
+                .min_by_key(|(_, health)| health.map(|health| health.last_failure))
+            {
+                Some((index, _)) => index,
+                None => return false,
+            },
+        };
+
+        let mut health = match self.health[index] {
+            Some(health) if health.router == *router => health,
+            _ => RouterHealth {
+                router: *router,
+                failures: 0,
+                last_failure: timestamp,
+                dead_until: None,
+            },
+        };
+        match health.dead_until {
+            Some(dead_until) if timestamp < dead_until => return false,
+            Some(_) => {
+                health.failures = 0;
+                health.dead_until = None;
+            }
+            None => (),
+        }
+        if timestamp - health.last_failure > Self::FAILURE_WINDOW {
+            health.failures = 0;
+        }
+        health.failures += 1;
+        health.last_failure = timestamp;
+        let dead = health.failures >= Self::DEAD_ROUTER_FAILURES;
+        if dead {
+            net_debug!("router {} is dead, failing over", router);
+            health.dead_until = Some(timestamp + Self::DEAD_ROUTER_HOLD_TIME);
+        }
+        self.health[index] = Some(health);
+        dead
+    }
+
+    /// Return the router to send a packet to `addr` through, from `src_addr` and by a
+    /// socket with `mark`, taking the policy routes into account.
+    pub(crate) fn lookup_policy(
+        &self,
+        addr: &IpAddress,
+        src_addr: Option<&IpAddress>,
+        mark: Option<u32>,
+        timestamp: Instant,
+    ) -> Option<IpAddress> {
+        self.find_policy_route(addr, src_addr, mark, timestamp)
+            .map(|route| self.router_of(route, timestamp))
+    }
+
+    /// Return the MTU of the route to `addr`, from `src_addr` and by a socket with `mark`,
+    /// if it has one.
+    pub(crate) fn lookup_mtu(
+        &self,
+        addr: &IpAddress,
+        src_addr: Option<&IpAddress>,
+        mark: Option<u32>,
+        timestamp: Instant,
+    ) -> Option<usize> {
+        self.find_policy_route(addr, src_addr, mark, timestamp)
+            .and_then(|route| route.mtu)
+    }
+
+    fn find_policy_route(
+        &self,
+        addr: &IpAddress,
+        src_addr: Option<&IpAddress>,
+        mark: Option<u32>,
+        timestamp: Instant,
+    ) -> Option<&Route> {
+        assert!(addr.is_unicast());
+
+        let policy_route = self
+            .policy_storage
+            .iter()
+            .filter(|(_, route)| !Self::is_expired(route, timestamp))
+            .filter(|(policy, _)| policy.matches(addr, src_addr, mark))
+            .max_by_key(|(policy, _)| policy.specificity());
+        match policy_route {
+            Some((_, route)) => Some(route),
+            None => self.find_route(addr, timestamp),
+        }
+    }
+
+    fn is_expired(route: &Route, timestamp: Instant) -> bool {
+        matches!(route.expires_at, Some(expires_at) if timestamp > expires_at)
+    }
+
+    /// Return the router of `route`, or its backup router while the router is dead.
+    fn router_of(&self, route: &Route, timestamp: Instant) -> IpAddress {
+        match route.backup_router {
+            Some(backup_router)
+                if self.is_router_dead(&route.via_router, timestamp)
+                    && !self.is_router_dead(&backup_router, timestamp) =>
+            {
+                backup_router
+            }
+            _ => route.via_router,
+        }
+    }
+
     pub(crate) fn lookup(&self, addr: &IpAddress, timestamp: Instant) -> Option<IpAddress> {
+        self.find_route(addr, timestamp)
+            .map(|route| self.router_of(route, timestamp))
+    }
+
+    fn find_route(&self, addr: &IpAddress, timestamp: Instant) -> Option<&Route> {
         assert!(addr.is_unicast());
 
         let cidr = match addr {
@@ -142,14 +534,12 @@ impl<'a> Routes<'a> {
             .rev()
         {
             // TODO: do something with route.preferred_until
-            if let Some(expires_at) = route.expires_at {
-                if timestamp > expires_at {
-                    continue;
-                }
+            if Self::is_expired(route, timestamp) {
+                continue;
             }
 
             if prefix.contains_addr(addr) {
-                return Some(route.via_router);
+                return Some(route);
             }
         }
 
@@ -235,8 +625,15 @@ mod test {
 
         let route = Route {
             via_router: ADDR_1A.into(),
+            backup_router: None,
+            mtu: None,
             preferred_until: None,
             expires_at: None,
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
         };
         routes.update(|storage| {
             storage.insert(cidr_1().into(), route).unwrap();
@@ -265,8 +662,15 @@ mod test {
 
         let route2 = Route {
             via_router: ADDR_2A.into(),
+            backup_router: None,
+            mtu: None,
             preferred_until: Some(Instant::from_millis(10)),
             expires_at: Some(Instant::from_millis(10)),
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
         };
         routes.update(|storage| {
             storage.insert(cidr_2().into(), route2).unwrap();
@@ -314,4 +718,264 @@ mod test {
             Some(ADDR_2A.into())
         );
     }
+
+    #[test]
+    fn test_policy_routes() {
+        let mut routes = Routes::new(std::collections::BTreeMap::new());
+        let policy = |src: Option<IpCidr>, mark| RoutePolicy {
+            dst: cidr_1().into(),
+            src,
+            mark,
+        };
+        let route = |router: IpAddress| Route {
+            via_router: router,
+            backup_router: None,
+            mtu: None,
+            preferred_until: None,
+            expires_at: None,
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
+        };
+
+        // There is no storage for policy routes by default.
+        assert_eq!(
+            routes
+                .add_policy_route(policy(None, Some(7)), route(ADDR_1C.into()))
+                .map(|_| ()),
+            Err(Error::Exhausted)
+        );
+        routes.set_policy_storage(std::collections::BTreeMap::new());
+
+        routes.update(|storage| {
+            storage
+                .insert(cidr_1().into(), route(ADDR_1A.into()))
+                .unwrap();
+        });
+        routes
+            .add_policy_route(policy(Some(cidr_2().into()), None), route(ADDR_2A.into()))
+            .unwrap();
+        routes
+            .add_policy_route(policy(None, Some(7)), route(ADDR_1C.into()))
+            .unwrap();
+
+        let lookup = |routes: &Routes, src: Option<IpAddress>, mark| {
+            routes.lookup_policy(&ADDR_1B.into(), src.as_ref(), mark, Instant::ZERO)
+        };
+        assert_eq!(lookup(&routes, None, None), Some(ADDR_1A.into()));
+        assert_eq!(
+            lookup(&routes, Some(ADDR_1A.into()), None),
+            Some(ADDR_1A.into())
+        );
+        assert_eq!(
+            lookup(&routes, Some(ADDR_2B.into()), None),
+            Some(ADDR_2A.into())
+        );
+        assert_eq!(lookup(&routes, None, Some(7)), Some(ADDR_1C.into()));
+        assert_eq!(lookup(&routes, None, Some(8)), Some(ADDR_1A.into()));
+        // A source prefix is more specific than a mark.
+        assert_eq!(
+            lookup(&routes, Some(ADDR_2B.into()), Some(7)),
+            Some(ADDR_2A.into())
+        );
+
+        assert!(routes.remove_policy_route(&policy(None, Some(7))).is_some());
+        assert_eq!(lookup(&routes, None, Some(7)), Some(ADDR_1A.into()));
+    }
+
+    #[test]
+    fn test_route_mtu() {
+        let mut routes = Routes::new(std::collections::BTreeMap::new());
+        routes.set_policy_storage(std::collections::BTreeMap::new());
+        let route = Route {
+            via_router: ADDR_1A.into(),
+            backup_router: None,
+            mtu: None,
+            preferred_until: None,
+            expires_at: None,
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
+        };
+        routes.update(|storage| {
+            storage.insert(cidr_1().into(), route).unwrap();
+            storage
+                .insert(cidr_2().into(), route.with_mtu(1280))
+                .unwrap();
+        });
+        let policy = RoutePolicy {
+            dst: cidr_1().into(),
+            src: None,
+            mark: Some(7),
+        };
+        routes
+            .add_policy_route(policy, route.with_mtu(1400))
+            .unwrap();
+
+        let lookup_mtu =
+            |addr: IpAddress, mark| routes.lookup_mtu(&addr, None, mark, Instant::ZERO);
+        assert_eq!(lookup_mtu(ADDR_1B.into(), None), None);
+        assert_eq!(lookup_mtu(ADDR_2B.into(), None), Some(1280));
+        assert_eq!(lookup_mtu(ADDR_1B.into(), Some(7)), Some(1400));
+    }
+
+    #[test]
+    fn test_dead_router_failover() {
+        let mut routes = Routes::new(std::collections::BTreeMap::new());
+        let route = Route {
+            via_router: ADDR_1A.into(),
+            backup_router: None,
+            mtu: None,
+            preferred_until: None,
+            expires_at: None,
+            #[cfg(all(
+                feature = "proto-ipv6",
+                any(feature = "medium-ethernet", feature = "medium-ieee802154")
+            ))]
+            preference: NdiscRoutePreference::Medium,
+        }
+        .with_backup_router(ADDR_2A.into());
+        routes.update(|storage| {
+            storage.insert(cidr_1().into(), route).unwrap();
+        });
+        let lookup = |routes: &Routes, timestamp| routes.lookup(&ADDR_1B.into(), timestamp);
+        let fail = |routes: &mut Routes, secs: i64| {
+            routes.router_failed(&ADDR_1A.into(), Instant::from_secs(secs))
+        };
+
+        // Failures further apart than the window are not in a row.
+        fail(&mut routes, 0);
+        fail(&mut routes, 1);
+        fail(&mut routes, 20);
+        assert!(!routes.is_router_dead(&ADDR_1A.into(), Instant::from_secs(20)));
+        assert_eq!(
+            lookup(&routes, Instant::from_secs(20)),
+            Some(ADDR_1A.into())
+        );
+
+        fail(&mut routes, 21);
+        fail(&mut routes, 22);
+        assert!(routes.is_router_dead(&ADDR_1A.into(), Instant::from_secs(22)));
+        assert_eq!(
+            lookup(&routes, Instant::from_secs(22)),
+            Some(ADDR_2A.into())
+        );
+
+        // The router is tried again after the hold time, and only found dead again
+        // after as many failures.
+        let retry_at = 22 + Routes::DEAD_ROUTER_HOLD_TIME.secs() as i64;
+        assert_eq!(
+            lookup(&routes, Instant::from_secs(retry_at)),
+            Some(ADDR_1A.into())
+        );
+        fail(&mut routes, retry_at);
+        fail(&mut routes, retry_at + 1);
+        assert_eq!(
+            lookup(&routes, Instant::from_secs(retry_at + 1)),
+            Some(ADDR_1A.into())
+        );
+        fail(&mut routes, retry_at + 2);
+        assert_eq!(
+            lookup(&routes, Instant::from_secs(retry_at + 2)),
+            Some(ADDR_2A.into())
+        );
+
+        // Routers without a backup are not tracked.
+        for secs in 0..3 {
+            routes.router_failed(&ADDR_2A.into(), Instant::from_secs(secs));
+        }
+        assert!(!routes.is_router_dead(&ADDR_2A.into(), Instant::from_secs(2)));
+    }
+
+    #[test]
+    #[cfg(all(
+        feature = "proto-ipv6",
+        any(feature = "medium-ethernet", feature = "medium-ieee802154")
+    ))]
+    fn test_advertised_routes() {
+        use crate::wire::NdiscRoutePreference as Preference;
+
+        let mut routes = Routes::new(std::collections::BTreeMap::new());
+        let lifetime = Duration::from_secs(10);
+        let update = |routes: &mut Routes, router, preference, lifetime, timestamp| {
+            routes
+                .update_advertised_route(cidr_1(), router, preference, lifetime, timestamp)
+                .unwrap()
+        };
+        let lookup = |routes: &Routes, timestamp| routes.lookup(&ADDR_1B.into(), timestamp);
+
+        assert!(update(
+            &mut routes,
+            ADDR_1A,
+            Preference::Medium,
+            lifetime,
+            Instant::ZERO
+        ));
+        assert_eq!(lookup(&routes, Instant::ZERO), Some(ADDR_1A.into()));
+
+        // A router with a lower preference does not replace the route...
+        assert!(!update(
+            &mut routes,
+            ADDR_2A,
+            Preference::Low,
+            lifetime,
+            Instant::ZERO
+        ));
+        assert_eq!(lookup(&routes, Instant::ZERO), Some(ADDR_1A.into()));
+        // ... unless it has expired,
+        let later = Instant::from_secs(11);
+        assert!(update(
+            &mut routes,
+            ADDR_2A,
+            Preference::Low,
+            lifetime,
+            later
+        ));
+        assert_eq!(lookup(&routes, later), Some(ADDR_2A.into()));
+        // ... and a router with a higher preference does.
+        assert!(update(
+            &mut routes,
+            ADDR_1A,
+            Preference::High,
+            lifetime,
+            later
+        ));
+        assert_eq!(lookup(&routes, later), Some(ADDR_1A.into()));
+
+        // A zero lifetime only removes the route of the same router.
+        assert!(!update(
+            &mut routes,
+            ADDR_2A,
+            Preference::High,
+            Duration::ZERO,
+            later
+        ));
+        assert!(update(
+            &mut routes,
+            ADDR_1A,
+            Preference::High,
+            Duration::ZERO,
+            later
+        ));
+        assert_eq!(lookup(&routes, later), None);
+
+        // Routes that do not expire are never replaced.
+        routes.update(|storage| {
+            storage
+                .insert(cidr_1().into(), Route::new_ipv6_gateway(ADDR_1C))
+                .unwrap();
+        });
+        assert!(!update(
+            &mut routes,
+            ADDR_1A,
+            Preference::High,
+            lifetime,
+            later
+        ));
+        assert_eq!(lookup(&routes, later), Some(ADDR_1C.into()));
+    }
 }
@@ -0,0 +1,137 @@
+//! Router Advertisements sent by an interface acting as an IPv6 router (RFC 4861 § 6.2).
+
+use crate::time::{Duration, Instant};
+use crate::wire::{NdiscPrefixInformation, NdiscRoutePreference, NdiscRouterFlags};
+
+/// Number of initial advertisements sent at a shorter interval, per RFC 4861 § 10.
+const MAX_INITIAL_RTR_ADVERTISEMENTS: u8 = 3;
+
+/// Maximum interval between the initial advertisements, per RFC 4861 § 10.
+const MAX_INITIAL_RTR_ADVERT_INTERVAL: Duration = Duration::from_secs(16);
+
+/// Minimum delay between advertisements sent to the all-nodes group, per RFC 4861 § 10.
+const MIN_DELAY_BETWEEN_RAS: Duration = Duration::from_secs(3);
+
+/// Configuration of the router advertisements sent by an interface.
+///
+/// Advertisements are sent to the all-nodes multicast group from the link-local
+/// address of the interface, periodically and in response to router solicitations.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config<'a> {
+    /// Hop limit hosts should use, or zero if unspecified.
+    pub hop_limit: u8,
+    /// Managed address configuration and other configuration flags.
+    pub flags: NdiscRouterFlags,
+    /// Preference of the interface as a default router, as specified in RFC 4191.
+    pub preference: NdiscRoutePreference,
+    /// Lifetime of the interface as a default router, or zero if it is not one.
+    pub router_lifetime: Duration,
+    /// Time hosts should assume a neighbor is reachable for, or zero if unspecified.
+    pub reachable_time: Duration,
+    /// Time between retransmitted neighbor solicitations, or zero if unspecified.
+    pub retrans_time: Duration,
+    /// MTU hosts should use on the link, if advertised.
+    pub mtu: Option<u32>,
+    /// Prefixes to advertise. Each prefix is advertised in a separate message.
+    pub prefixes: &'a [NdiscPrefixInformation],
+    /// Interval between unsolicited advertisements, `MaxRtrAdvInterval` in RFC 4861.
+    /// Must be between 4 and 1800 seconds.
+    pub interval: Duration,
+}
+
+impl<'a> Config<'a> {
+    /// Default interval between advertisements, per RFC 4861 § 6.2.1.
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(600);
+
+    /// Create a configuration advertising the interface as a default router for
+    /// the given prefixes, with the defaults of RFC 4861 § 6.2.1.
+    pub fn new(prefixes: &'a [NdiscPrefixInformation]) -> Config<'a> {
+        Config {
+            hop_limit: 64,
+            flags: NdiscRouterFlags::empty(),
+            preference: NdiscRoutePreference::Medium,
+            router_lifetime: Self::DEFAULT_INTERVAL * 3,
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            mtu: None,
+            prefixes,
+            interval: Self::DEFAULT_INTERVAL,
+        }
+    }
+}
+
+/// State of the router advertiser of an interface.
+#[derive(Debug)]
+pub(crate) struct RouterAdvert<'a> {
+    pub(crate) config: Config<'a>,
+    pub(crate) next_advert: Instant,
+    last_advert: Option<Instant>,
+    initial_adverts_left: u8,
+}
+
+impl<'a> RouterAdvert<'a> {
+    pub(crate) fn new(config: Config<'a>) -> Self {
+        RouterAdvert {
+            config,
+            next_advert: Instant::from_millis(0),
+            last_advert: None,
+            initial_adverts_left: MAX_INITIAL_RTR_ADVERTISEMENTS,
+        }
+    }
+
+    /// Schedule the next unsolicited advertisement after one was sent at `timestamp`.
+    pub(crate) fn sent(&mut self, timestamp: Instant) {
+        let interval = if self.initial_adverts_left > 0 {
+            self.initial_adverts_left -= 1;
+            self.config.interval.min(MAX_INITIAL_RTR_ADVERT_INTERVAL)
+        } else {
+            self.config.interval
+        };
+        self.last_advert = Some(timestamp);
+        self.next_advert = timestamp + interval;
+    }
+
+    /// Schedule an advertisement in response to a router solicitation received
+    /// at `timestamp`, rate limited per RFC 4861 § 6.2.6.
+    pub(crate) fn solicited(&mut self, timestamp: Instant) {
+        let earliest = match self.last_advert {
+            Some(last_advert) => (last_advert + MIN_DELAY_BETWEEN_RAS).max(timestamp),
+            None => timestamp,
+        };
+        self.next_advert = self.next_advert.min(earliest);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_initial_adverts() {
+        let mut ra = RouterAdvert::new(Config::new(&[]));
+        assert_eq!(ra.next_advert, Instant::from_secs(0));
+        let mut timestamp = Instant::from_secs(0);
+        for _ in 0..MAX_INITIAL_RTR_ADVERTISEMENTS {
+            ra.sent(timestamp);
+            timestamp += MAX_INITIAL_RTR_ADVERT_INTERVAL;
+            assert_eq!(ra.next_advert, timestamp);
+        }
+        ra.sent(timestamp);
+        assert_eq!(ra.next_advert, timestamp + Config::DEFAULT_INTERVAL);
+    }
+
+    #[test]
+    fn test_solicited_rate_limit() {
+        let mut ra = RouterAdvert::new(Config::new(&[]));
+        ra.sent(Instant::from_secs(10));
+        ra.solicited(Instant::from_secs(11));
+        assert_eq!(ra.next_advert, Instant::from_secs(13));
+        ra.solicited(Instant::from_secs(12));
+        assert_eq!(ra.next_advert, Instant::from_secs(13));
+
+        ra.sent(Instant::from_secs(13));
+        ra.solicited(Instant::from_secs(20));
+        assert_eq!(ra.next_advert, Instant::from_secs(20));
+    }
+}
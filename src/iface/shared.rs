@@ -0,0 +1,199 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use super::{Interface, SocketHandle, SocketSet};
+use crate::phy::Device;
+use crate::socket::AnySocket;
+use crate::time::{Duration, Instant};
+use crate::Result;
+
+struct Stack<'a, D> {
+    iface: Interface<'a>,
+    device: D,
+    sockets: SocketSet<'a>,
+}
+
+/// An interface, its device and its sockets, shared between tasks or interrupt handlers.
+///
+/// Every method takes `&self` and runs in a critical section, so that a `SharedStack`
+/// can be placed in a `static` and used from several RTIC tasks, or from a task and an
+/// interrupt handler, without wrapping it in a mutex by hand. The stack starts out empty,
+/// and is filled in with [init]; every other method panics until then.
+///
+/// Keep the closures passed to [with] and [with_socket] short, as interrupts are
+/// disabled while they run.
+///
+/// [init]: #method.init
+/// [with]: #method.with
+/// [with_socket]: #method.with_socket
+pub struct SharedStack<'a, D> {
+    inner: Mutex<RefCell<Option<Stack<'a, D>>>>,
+}
+
+impl<'a, D> SharedStack<'a, D> {
+    /// Create an empty shared stack.
+    pub const fn new() -> Self {
+        SharedStack {
+            inner: Mutex::new(RefCell::new(None)),
+        }
+    }
+}
+
+impl<'a, D> Default for SharedStack<'a, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, D> SharedStack<'a, D>
+where
+    D: for<'d> Device<'d>,
+{
+    /// Fill in the stack with an interface, its device and its sockets, returning the
+    /// ones it previously held, if any.
+    pub fn init(
+        &self,
+        iface: Interface<'a>,
+        device: D,
+        sockets: SocketSet<'a>,
+    ) -> Option<(Interface<'a>, D, SocketSet<'a>)> {
+        let stack = Stack {
+            iface,
+            device,
+            sockets,
+        };
+        critical_section::with(|cs| self.inner.borrow(cs).replace(Some(stack)))
+            .map(|stack| (stack.iface, stack.device, stack.sockets))
+    }
+
+    /// Query whether the stack was filled in with [init](#method.init).
+    pub fn is_init(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow(cs).borrow().is_some())
+    }
+
+    /// Call `f` with the interface, its device and its sockets.
+    ///
+    /// # Panics
+    /// This function panics if the stack is empty, or if it is called from within `f`.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Interface<'a>, &mut D, &mut SocketSet<'a>) -> R,
+    {
+        critical_section::with(|cs| {
+            let mut stack = self.inner.borrow(cs).borrow_mut();
+            let stack = stack.as_mut().expect("shared stack is not initialized");
+            f(&mut stack.iface, &mut stack.device, &mut stack.sockets)
+        })
+    }
+
+    /// Call `f` with a socket of the stack.
+    ///
+    /// # Panics
+    /// This function panics if the stack is empty, if it is called from within `f`, or if
+    /// the handle does not refer to a socket of type `T`.
+    pub fn with_socket<T, F, R>(&self, handle: SocketHandle, f: F) -> R
+    where
+        T: AnySocket<'a>,
+        F: FnOnce(&mut T) -> R,
+    {
+        self.with(|_, _, sockets| f(sockets.get_mut(handle)))
+    }
+
+    /// Add a socket to the stack, and return its handle. See [SocketSet::add].
+    pub fn add_socket<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
+        self.with(|_, _, sockets| sockets.add(socket))
+    }
+
+    /// Poll the interface. See [Interface::poll].
+    pub fn poll(&self, timestamp: Instant) -> Result<bool> {
+        self.with(|iface, device, sockets| iface.poll(timestamp, device, sockets))
+    }
+
+    /// Receive the packets queued in the device. See [Interface::ingress].
+    pub fn ingress(&self, timestamp: Instant) -> Result<bool> {
+        self.with(|iface, device, sockets| iface.ingress(timestamp, device, sockets))
+    }
+
+    /// Transmit the packets queued in the sockets. See [Interface::egress].
+    pub fn egress(&self, timestamp: Instant) -> Result<bool> {
+        self.with(|iface, device, sockets| iface.egress(timestamp, device, sockets))
+    }
+
+    /// Return the time at which the interface should be polled next. See
+    /// [Interface::poll_at].
+    pub fn poll_at(&self, timestamp: Instant) -> Option<Instant> {
+        self.with(|iface, _, sockets| iface.poll_at(timestamp, sockets))
+    }
+
+    /// Return the time left before the interface should be polled next. See
+    /// [Interface::poll_delay].
+    pub fn poll_delay(&self, timestamp: Instant) -> Option<Duration> {
+        self.with(|iface, _, sockets| iface.poll_delay(timestamp, sockets))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(any(
+        feature = "proto-sixlowpan-fragmentation",
+        feature = "proto-ipv4-fragmentation",
+        feature = "proto-igmp"
+    ))]
+    use std::collections::BTreeMap;
+
+    #[cfg(any(
+        feature = "proto-sixlowpan-fragmentation",
+        feature = "proto-ipv4-fragmentation"
+    ))]
+    use crate::iface::FragmentsCache;
+    use crate::iface::InterfaceBuilder;
+    use crate::phy::{Loopback, Medium};
+    use crate::socket::udp;
+    use crate::wire::{IpAddress, IpCidr, IpEndpoint};
+
+    #[test]
+    #[cfg(all(feature = "medium-ip", feature = "proto-ipv4", feature = "socket-udp"))]
+    fn test_shared_stack() {
+        static STACK: SharedStack<'static, Loopback> = SharedStack::new();
+        assert!(!STACK.is_init());
+
+        let mut device = Loopback::new(Medium::Ip);
+        let iface_builder =
+            InterfaceBuilder::new().ip_addrs(vec![IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8)]);
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let iface_builder = iface_builder
+            .sixlowpan_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()))
+            .sixlowpan_out_packet_cache(vec![]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let iface_builder =
+            iface_builder.ipv4_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()));
+        #[cfg(feature = "proto-igmp")]
+        let iface_builder = iface_builder.ipv4_multicast_groups(BTreeMap::new());
+        let iface = iface_builder.try_finalize(&mut device).unwrap();
+        assert!(STACK.init(iface, device, SocketSet::new(vec![])).is_none());
+
+        let udp_socket = |port| {
+            let buffer = || udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY], vec![0; 16]);
+            let mut socket = udp::Socket::new(buffer(), buffer());
+            socket.bind(port).unwrap();
+            socket
+        };
+        let sender = STACK.add_socket(udp_socket(67));
+        let receiver = STACK.add_socket(udp_socket(68));
+
+        let remote = IpEndpoint::from((IpAddress::v4(127, 0, 0, 1), 68));
+        STACK.with_socket(sender, |socket: &mut udp::Socket| {
+            socket.send_slice(b"hello", remote).unwrap()
+        });
+        assert_eq!(STACK.poll_at(Instant::ZERO), Some(Instant::ZERO));
+        assert_eq!(STACK.poll(Instant::ZERO), Ok(true));
+
+        let mut buf = [0; 16];
+        let (len, _) = STACK.with_socket(receiver, |socket: &mut udp::Socket| {
+            socket.recv_slice(&mut buf).unwrap()
+        });
+        assert_eq!(&buf[..len], b"hello");
+    }
+}
@@ -0,0 +1,465 @@
+//! IPv6 Stateless Address Autoconfiguration (RFC 4862 § 5.5), with the temporary
+//! addresses of RFC 4941 § 3.
+
+use managed::ManagedMap;
+
+use crate::rand::Rand;
+use crate::time::{Duration, Instant};
+use crate::wire::{Ipv6Address, Ipv6Cidr, NdiscPrefixInfoFlags, NdiscPrefixInformation};
+
+/// Length of the prefixes addresses are formed from, per RFC 4291 § 2.5.1.
+const PREFIX_LEN: u8 = 64;
+
+/// Configuration of Stateless Address Autoconfiguration.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    /// Whether temporary addresses are generated alongside the stable addresses, and
+    /// preferred as the source of outgoing packets, as specified in RFC 4941.
+    pub temporary_addresses: bool,
+    /// Preferred lifetime of temporary addresses, `TEMP_PREFERRED_LIFETIME` in RFC 4941.
+    pub temp_preferred_lifetime: Duration,
+    /// Valid lifetime of temporary addresses, `TEMP_VALID_LIFETIME` in RFC 4941.
+    pub temp_valid_lifetime: Duration,
+    /// Time before a temporary address is deprecated at which a new one is generated,
+    /// `REGEN_ADVANCE` in RFC 4941.
+    pub regen_advance: Duration,
+}
+
+impl Config {
+    /// Default preferred lifetime of temporary addresses, per RFC 4941 § 5.
+    pub const DEFAULT_TEMP_PREFERRED_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Default valid lifetime of temporary addresses, per RFC 4941 § 5.
+    pub const DEFAULT_TEMP_VALID_LIFETIME: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    /// Default time before deprecation at which temporary addresses are regenerated,
+    /// per RFC 4941 § 5.
+    pub const DEFAULT_REGEN_ADVANCE: Duration = Duration::from_secs(5);
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            temporary_addresses: false,
+            temp_preferred_lifetime: Self::DEFAULT_TEMP_PREFERRED_LIFETIME,
+            temp_valid_lifetime: Self::DEFAULT_TEMP_VALID_LIFETIME,
+            regen_advance: Self::DEFAULT_REGEN_ADVANCE,
+        }
+    }
+}
+
+/// An address formed by Stateless Address Autoconfiguration.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Entry {
+    temporary: bool,
+    preferred_until: Instant,
+    valid_until: Instant,
+}
+
+impl Entry {
+    /// Query whether the address is a temporary address, with a randomized interface
+    /// identifier.
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    /// Return the time at which the address becomes deprecated.
+    pub fn preferred_until(&self) -> Instant {
+        self.preferred_until
+    }
+
+    /// Return the time at which the address is removed from the interface.
+    pub fn valid_until(&self) -> Instant {
+        self.valid_until
+    }
+
+    /// Query whether the address should be avoided for new communications at `timestamp`.
+    pub fn is_deprecated(&self, timestamp: Instant) -> bool {
+        timestamp >= self.preferred_until
+    }
+}
+
+/// Return the address formed from the /64 `prefix` and `iid`.
+fn form_address(prefix: &Ipv6Address, iid: &[u8; 8]) -> Ipv6Address {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&prefix.as_bytes()[..8]);
+    bytes[8..].copy_from_slice(iid);
+    Ipv6Address(bytes)
+}
+
+/// Query whether `a` and `b` have the same /64 prefix.
+fn same_prefix(a: &Ipv6Address, b: &Ipv6Address) -> bool {
+    a.as_bytes()[..8] == b.as_bytes()[..8]
+}
+
+/// Stateless Address Autoconfiguration state of an interface.
+#[derive(Debug)]
+pub(crate) struct Slaac<'a> {
+    config: Config,
+    entries: ManagedMap<'a, Ipv6Address, Entry>,
+}
+
+impl<'a> Slaac<'a> {
+    pub(crate) fn new(config: Config, entries: ManagedMap<'a, Ipv6Address, Entry>) -> Self {
+        Slaac { config, entries }
+    }
+
+    /// Return the state of `addr`, or `None` if it was not formed by autoconfiguration.
+    pub(crate) fn entry(&self, addr: &Ipv6Address) -> Option<Entry> {
+        self.entries.get(addr).copied()
+    }
+
+    /// Query whether temporary addresses are preferred as the source of outgoing packets.
+    pub(crate) fn prefers_temporary(&self) -> bool {
+        self.config.temporary_addresses
+    }
+
+    /// Return the addresses formed by autoconfiguration.
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (Ipv6Address, Entry)> + '_ {
+        self.entries.iter().map(|(addr, entry)| (*addr, *entry))
+    }
+
+    /// Form or update the addresses for the Prefix Information option `info` of a
+    /// Router Advertisement, using `iid` as the stable interface identifier.
+    pub(crate) fn process_prefix(
+        &mut self,
+        info: &NdiscPrefixInformation,
+        iid: &[u8; 8],
+        rand: &mut Rand,
+        timestamp: Instant,
+    ) {
+        if !info.flags.contains(NdiscPrefixInfoFlags::ADDRCONF)
+            || info.prefix.is_link_local()
+            || info.preferred_lifetime > info.valid_lifetime
+        {
+            return;
+        }
+        if info.prefix_len != PREFIX_LEN {
+            net_debug!(
+                "slaac: ignoring prefix {}/{}, unsupported length",
+                info.prefix,
+                info.prefix_len
+            );
+            return;
+        }
+
+        let valid_until = timestamp + info.valid_lifetime;
+        let preferred_until = timestamp + info.preferred_lifetime;
+        let addr = form_address(&info.prefix, iid);
+        match self.entries.get(&addr).copied() {
+            Some(entry) => {
+                // Protect against a spoofed advertisement shortening the lifetime of the
+                // address, per RFC 4862 § 5.5.3 (e).
+                const TWO_HOURS: Duration = Duration::from_secs(2 * 60 * 60);
+                let remaining = if entry.valid_until > timestamp {
+                    entry.valid_until - timestamp
+                } else {
+                    Duration::ZERO
+                };
+                let valid_until =
+                    if info.valid_lifetime > TWO_HOURS || info.valid_lifetime > remaining {
+                        valid_until
+                    } else if remaining <= TWO_HOURS {
+                        entry.valid_until
+                    } else {
+                        timestamp + TWO_HOURS
+                    };
+                let entry = Entry {
+                    preferred_until: preferred_until.min(valid_until),
+                    valid_until,
+                    ..entry
+                };
+                // Cannot fail, the address is already present.
+                let _ = self.entries.insert(addr, entry);
+            }
+            None if info.valid_lifetime > Duration::ZERO => {
+                let entry = Entry {
+                    temporary: false,
+                    preferred_until,
+                    valid_until,
+                };
+                match self.entries.insert(addr, entry) {
+                    Ok(_) => net_debug!("slaac: formed {}", addr),
+                    Err(_) => {
+                        net_debug!("slaac: no space to track {}", addr);
+                        return;
+                    }
+                }
+            }
+            None => return,
+        }
+
+        // The lifetimes of temporary addresses never exceed those of their prefix,
+        // per RFC 4941 § 3.4.
+        for (temp_addr, entry) in self.entries.iter_mut() {
+            if entry.temporary && same_prefix(temp_addr, &addr) {
+                entry.valid_until = entry.valid_until.min(valid_until);
+                entry.preferred_until = entry.preferred_until.min(preferred_until);
+            }
+        }
+
+        self.regenerate(rand, timestamp);
+    }
+
+    /// Generate a temporary address for each prefix whose current temporary address is
+    /// about to be deprecated, or which has none.
+    pub(crate) fn regenerate(&mut self, rand: &mut Rand, timestamp: Instant) {
+        if !self.config.temporary_addresses {
+            return;
+        }
+        let config = self.config;
+        while let Some((stable_addr, stable)) = self
+            .entries
+            .iter()
+            .find(|(stable_addr, stable)| {
+                !stable.temporary
+                    && !stable.is_deprecated(timestamp)
+                    && !self.entries.iter().any(|(temp_addr, temp)| {
+                        temp.temporary
+                            && same_prefix(temp_addr, stable_addr)
+                            && temp.preferred_until > timestamp + config.regen_advance
+                    })
+            })
+            .map(|(addr, entry)| (*addr, *entry))
+        {
+            let entry = Entry {
+                temporary: true,
+                preferred_until: stable
+                    .preferred_until
+                    .min(timestamp + config.temp_preferred_lifetime),
+                valid_until: stable
+                    .valid_until
+                    .min(timestamp + config.temp_valid_lifetime),
+            };
+            // An address deprecated before it could be regenerated would be regenerated
+            // continuously, per RFC 4941 § 3.3.
+            if entry.preferred_until <= timestamp + config.regen_advance {
+                return;
+            }
+
+            let mut iid = [0; 8];
+            iid[..4].copy_from_slice(&rand.rand_u32().to_be_bytes());
+            iid[4..].copy_from_slice(&rand.rand_u32().to_be_bytes());
+            // The identifier is not globally unique.
+            iid[0] &= !0x02;
+            let addr = form_address(&stable_addr, &iid);
+            match self.entries.insert(addr, entry) {
+                Ok(_) => net_debug!("slaac: generated temporary address {}", addr),
+                Err(_) => {
+                    net_debug!("slaac: no space to track {}", addr);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Remove the first address whose valid lifetime expired at `timestamp`, or return
+    /// `None` if there is none.
+    pub(crate) fn next_expired(&mut self, timestamp: Instant) -> Option<Ipv6Address> {
+        let addr = self
+            .entries
+            .iter()
+            .find(|(_, entry)| entry.valid_until <= timestamp)
+            .map(|(addr, _)| *addr)?;
+        net_debug!("slaac: {} expired", addr);
+        self.entries.remove(&addr);
+        Some(addr)
+    }
+
+    /// Return the earliest time after `timestamp` at which an address expires, or a
+    /// temporary address must be regenerated.
+    pub(crate) fn poll_at(&self, timestamp: Instant) -> Option<Instant> {
+        let regen_advance = self.config.regen_advance;
+        self.entries
+            .iter()
+            .flat_map(|(_, entry)| {
+                let regen_at = if entry.temporary {
+                    Some(entry.preferred_until - regen_advance)
+                } else {
+                    None
+                };
+                regen_at.into_iter().chain(Some(entry.valid_until))
+            })
+            .filter(|at| *at > timestamp)
+            .min()
+    }
+
+    /// Return the address of `addr` as a CIDR block.
+    pub(crate) fn cidr(addr: Ipv6Address) -> Ipv6Cidr {
+        Ipv6Cidr::new(addr, PREFIX_LEN)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::vec::Vec;
+
+    const PREFIX: Ipv6Address =
+        Ipv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    const IID: [u8; 8] = [0x02, 0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01];
+    const STABLE: Ipv6Address = Ipv6Address([
+        0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 0, 0x02, 0x00, 0x00, 0xff, 0xfe, 0x00, 0x00, 0x01,
+    ]);
+
+    fn prefix_info(valid: u64, preferred: u64) -> NdiscPrefixInformation {
+        NdiscPrefixInformation {
+            prefix_len: 64,
+            flags: NdiscPrefixInfoFlags::ON_LINK | NdiscPrefixInfoFlags::ADDRCONF,
+            valid_lifetime: Duration::from_secs(valid),
+            preferred_lifetime: Duration::from_secs(preferred),
+            prefix: PREFIX,
+        }
+    }
+
+    fn slaac(temporary_addresses: bool) -> Slaac<'static> {
+        let config = Config {
+            temporary_addresses,
+            ..Config::default()
+        };
+        Slaac::new(config, ManagedMap::Owned(BTreeMap::new()))
+    }
+
+    fn temporaries(slaac: &Slaac) -> Vec<(Ipv6Address, Entry)> {
+        slaac
+            .entries()
+            .filter(|(_, entry)| entry.is_temporary())
+            .collect()
+    }
+
+    #[test]
+    fn test_stable_address() {
+        let mut slaac = slaac(false);
+        let mut rand = Rand::new(1234);
+        slaac.process_prefix(&prefix_info(3600, 1800), &IID, &mut rand, Instant::ZERO);
+
+        let entry = slaac.entry(&STABLE).unwrap();
+        assert!(!entry.is_temporary());
+        assert_eq!(entry.preferred_until(), Instant::from_secs(1800));
+        assert_eq!(entry.valid_until(), Instant::from_secs(3600));
+        assert!(temporaries(&slaac).is_empty());
+        assert_eq!(slaac.poll_at(Instant::ZERO), Some(Instant::from_secs(3600)));
+
+        assert_eq!(slaac.next_expired(Instant::from_secs(3599)), None);
+        assert_eq!(slaac.next_expired(Instant::from_secs(3600)), Some(STABLE));
+        assert_eq!(slaac.entry(&STABLE).map(|_| ()), None);
+    }
+
+    #[test]
+    fn test_ignored_prefixes() {
+        let mut slaac = slaac(false);
+        let mut rand = Rand::new(1234);
+
+        let mut info = prefix_info(3600, 1800);
+        info.flags = NdiscPrefixInfoFlags::ON_LINK;
+        slaac.process_prefix(&info, &IID, &mut rand, Instant::ZERO);
+
+        let mut info = prefix_info(3600, 1800);
+        info.prefix_len = 48;
+        slaac.process_prefix(&info, &IID, &mut rand, Instant::ZERO);
+
+        slaac.process_prefix(&prefix_info(1800, 3600), &IID, &mut rand, Instant::ZERO);
+        slaac.process_prefix(&prefix_info(0, 0), &IID, &mut rand, Instant::ZERO);
+
+        assert_eq!(slaac.entries().count(), 0);
+    }
+
+    #[test]
+    fn test_two_hour_rule() {
+        let mut slaac = slaac(false);
+        let mut rand = Rand::new(1234);
+        let info = prefix_info(86400, 86400);
+        slaac.process_prefix(&info, &IID, &mut rand, Instant::ZERO);
+
+        // A short lifetime is only accepted down to two hours.
+        slaac.process_prefix(&prefix_info(60, 0), &IID, &mut rand, Instant::ZERO);
+        let entry = slaac.entry(&STABLE).unwrap();
+        assert_eq!(entry.valid_until(), Instant::from_secs(7200));
+        assert!(entry.is_deprecated(Instant::ZERO));
+
+        // A longer lifetime is always accepted.
+        slaac.process_prefix(&prefix_info(10800, 10800), &IID, &mut rand, Instant::ZERO);
+        let entry = slaac.entry(&STABLE).unwrap();
+        assert_eq!(entry.valid_until(), Instant::from_secs(10800));
+        assert!(!entry.is_deprecated(Instant::ZERO));
+    }
+
+    #[test]
+    fn test_temporary_address() {
+        let mut slaac = slaac(true);
+        let mut rand = Rand::new(1234);
+        slaac.process_prefix(
+            &prefix_info(30 * 86400, 30 * 86400),
+            &IID,
+            &mut rand,
+            Instant::ZERO,
+        );
+
+        let temps = temporaries(&slaac);
+        assert_eq!(temps.len(), 1);
+        let (temp_addr, temp) = temps[0];
+        assert!(same_prefix(&temp_addr, &PREFIX));
+        assert_ne!(temp_addr, STABLE);
+        assert_eq!(temp_addr.as_bytes()[8] & 0x02, 0);
+        assert_eq!(temp.preferred_until(), Instant::from_secs(86400));
+        assert_eq!(temp.valid_until(), Instant::from_secs(7 * 86400));
+
+        // Processing the prefix again does not generate another address.
+        slaac.process_prefix(
+            &prefix_info(30 * 86400, 30 * 86400),
+            &IID,
+            &mut rand,
+            Instant::from_secs(60),
+        );
+        assert_eq!(temporaries(&slaac).len(), 1);
+
+        // A new address is generated shortly before the current one is deprecated.
+        let regen_at = Instant::from_secs(86400 - 5);
+        assert_eq!(slaac.poll_at(Instant::from_secs(60)), Some(regen_at));
+        slaac.regenerate(&mut rand, regen_at);
+        let temps = temporaries(&slaac);
+        assert_eq!(temps.len(), 2);
+        let (new_addr, new) = *temps.iter().find(|(addr, _)| *addr != temp_addr).unwrap();
+        assert_eq!(new.preferred_until(), regen_at + Duration::from_secs(86400));
+        assert!(!new.is_deprecated(Instant::from_secs(86400)));
+        assert!(slaac
+            .entry(&temp_addr)
+            .unwrap()
+            .is_deprecated(Instant::from_secs(86400)));
+
+        // The old address is removed when its valid lifetime expires.
+        assert_eq!(
+            slaac.next_expired(Instant::from_secs(7 * 86400)),
+            Some(temp_addr)
+        );
+        assert_eq!(slaac.next_expired(Instant::from_secs(7 * 86400)), None);
+        assert!(slaac.entry(&new_addr).is_some());
+    }
+
+    #[test]
+    fn test_temporary_lifetime_capped() {
+        let mut slaac = slaac(true);
+        let mut rand = Rand::new(1234);
+        slaac.process_prefix(&prefix_info(3600, 1800), &IID, &mut rand, Instant::ZERO);
+
+        let temps = temporaries(&slaac);
+        assert_eq!(temps.len(), 1);
+        assert_eq!(temps[0].1.preferred_until(), Instant::from_secs(1800));
+        assert_eq!(temps[0].1.valid_until(), Instant::from_secs(3600));
+
+        // A deprecated prefix deprecates its temporary addresses as well, and no new one
+        // is generated.
+        slaac.process_prefix(
+            &prefix_info(3600, 0),
+            &IID,
+            &mut rand,
+            Instant::from_secs(10),
+        );
+        let temps = temporaries(&slaac);
+        assert_eq!(temps.len(), 1);
+        assert!(temps[0].1.is_deprecated(Instant::from_secs(10)));
+    }
+}
@@ -1,4 +1,6 @@
-use super::SocketHandle;
+use core::fmt;
+
+use super::{SocketHandle, SocketLabel, SocketPriority};
 use crate::{
     socket::PollAt,
     time::{Duration, Instant},
@@ -39,10 +41,27 @@ pub(crate) struct Meta {
     /// Handle of this socket within its enclosing `SocketSet`.
     /// Mainly useful for debug output.
     pub(crate) handle: SocketHandle,
+    /// Label set by the application, for debug output.
+    pub(crate) label: Option<SocketLabel>,
+    /// Egress priority, set by the application.
+    pub(crate) priority: SocketPriority,
+    /// Differentiated services codepoint of the packets sent, set by the application.
+    pub(crate) dscp: Option<u8>,
+    /// Mark selecting the policy routes of the packets sent, set by the application.
+    pub(crate) mark: Option<u32>,
     /// See [NeighborState](struct.NeighborState.html).
     neighbor_state: NeighborState,
 }
 
+impl fmt::Display for Meta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.label {
+            Some(label) => write!(f, "{} ({})", self.handle, label),
+            None => write!(f, "{}", self.handle),
+        }
+    }
+}
+
 impl Meta {
     /// Minimum delay between neighbor discovery requests for this particular
     /// socket, in milliseconds.
@@ -61,6 +80,12 @@ impl Meta {
         }
     }
 
+    /// Return whether the socket waits for a neighbor, so that when it is polled depends
+    /// on the neighbor cache.
+    pub(crate) fn waiting_for_neighbor(&self) -> bool {
+        matches!(self.neighbor_state, NeighborState::Waiting { .. })
+    }
+
     pub(crate) fn egress_permitted<F>(&mut self, timestamp: Instant, has_neighbor: F) -> bool
     where
         F: Fn(IpAddress) -> bool,
@@ -72,17 +97,13 @@ impl Meta {
                 silent_until,
             } => {
                 if has_neighbor(neighbor) {
-                    net_trace!(
-                        "{}: neighbor {} discovered, unsilencing",
-                        self.handle,
-                        neighbor
-                    );
+                    net_trace!("{}: neighbor {} discovered, unsilencing", self, neighbor);
                     self.neighbor_state = NeighborState::Active;
                     true
                 } else if timestamp >= silent_until {
                     net_trace!(
                         "{}: neighbor {} silence timer expired, rediscovering",
-                        self.handle,
+                        self,
                         neighbor
                     );
                     true
@@ -96,7 +117,7 @@ impl Meta {
     pub(crate) fn neighbor_missing(&mut self, timestamp: Instant, neighbor: IpAddress) {
         net_trace!(
             "{}: neighbor {} missing, silencing until t+{}",
-            self.handle,
+            self,
             neighbor,
             Self::DISCOVERY_SILENT_TIME
         );
@@ -1,20 +1,66 @@
 use core::fmt;
 use managed::ManagedSlice;
 
+use super::deadline_queue::{Deadline, DeadlineQueue, Node, Nodes};
 use super::socket_meta::Meta;
 use crate::socket::{AnySocket, Socket};
+use crate::time::Instant;
+use crate::wire::{IpEndpoint, IpProtocol};
+
+/// Number of entries in the demultiplexing index of a socket set.
+const INDEX_SIZE: usize = 16;
 
 /// Opaque struct with space for storing one socket.
 ///
 /// This is public so you can use it to allocate space for storing
 /// sockets when creating an Interface.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct SocketStorage<'a> {
+    /// The socket stored at this position, in insertion order.
     inner: Option<Item<'a>>,
+    /// The state of the handle with the index of this storage.
+    handle: HandleState,
+    /// The generation of the handle with the index of this storage, incremented whenever
+    /// the handle is released.
+    generation: u32,
+    /// The node of the handle with the index of this storage in the deadline queue.
+    deadline: Node,
 }
 
 impl<'a> SocketStorage<'a> {
-    pub const EMPTY: Self = Self { inner: None };
+    pub const EMPTY: Self = Self {
+        inner: None,
+        handle: HandleState::Unused,
+        generation: 0,
+        deadline: Node::EMPTY,
+    };
+}
+
+impl<'a> Default for SocketStorage<'a> {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl<'a> Nodes for [SocketStorage<'a>] {
+    fn node(&self, handle: usize) -> &Node {
+        &self[handle].deadline
+    }
+
+    fn node_mut(&mut self, handle: usize) -> &mut Node {
+        &mut self[handle].deadline
+    }
+}
+
+/// The state of a socket handle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HandleState {
+    /// The handle was never allocated.
+    Unused,
+    /// The handle refers to the socket stored at the given position.
+    Live(usize),
+    /// The handle was released, and is followed by the given handle in the free list.
+    Free(Option<usize>),
 }
 
 /// An item of a socket set.
@@ -25,22 +71,192 @@ pub(crate) struct Item<'a> {
 }
 
 /// A handle, identifying a socket in an Interface.
+///
+/// The handle of a removed socket is never valid again, even once its index is reused by
+/// another socket, until its generation wraps around.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SocketHandle(usize);
+pub struct SocketHandle {
+    index: usize,
+    generation: u32,
+}
 
 impl fmt::Display for SocketHandle {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "#{}", self.0)
+        write!(f, "#{}", self.index)
+    }
+}
+
+/// A label attached to a socket in a `SocketSet`, included in log output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SocketLabel {
+    /// A name, such as `"http-server"`.
+    Name(&'static str),
+    /// A numeric tag, such as a connection or peer index.
+    Tag(u32),
+}
+
+impl fmt::Display for SocketLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SocketLabel::Name(name) => write!(f, "{}", name),
+            SocketLabel::Tag(tag) => write!(f, "{}", tag),
+        }
+    }
+}
+
+impl From<&'static str> for SocketLabel {
+    fn from(name: &'static str) -> Self {
+        SocketLabel::Name(name)
+    }
+}
+
+impl From<u32> for SocketLabel {
+    fn from(tag: u32) -> Self {
+        SocketLabel::Tag(tag)
+    }
+}
+
+/// The egress priority of a socket in a `SocketSet`.
+///
+/// When transmitting, the interface services every socket of a priority before any socket
+/// of a lower priority, so that latency-critical traffic is not queued behind bulk
+/// transfers on slow links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SocketPriority {
+    /// Network control traffic, such as ICMP and DHCP. The default for ICMP and
+    /// DHCPv4 sockets.
+    Control,
+    /// Latency-sensitive traffic, such as interactive sessions or telemetry.
+    Interactive,
+    /// Regular traffic. The default for every other socket.
+    Normal,
+    /// Bulk transfers, which only use the bandwidth left over by other traffic.
+    Bulk,
+}
+
+impl SocketPriority {
+    /// Every priority, from the highest to the lowest.
+    pub const ALL: [SocketPriority; 4] = [
+        SocketPriority::Control,
+        SocketPriority::Interactive,
+        SocketPriority::Normal,
+        SocketPriority::Bulk,
+    ];
+
+    /// Return the priority of traffic marked with the differentiated services codepoint
+    /// `dscp`, following the service classes of RFC 4594.
+    ///
+    /// Network control (CS6 and CS7) maps to `Control`; telephony, multimedia and
+    /// signaling (CS3 to CS5, AF3x, AF4x and EF) map to `Interactive`; low-priority data
+    /// (CS1 and AF1x) maps to `Bulk`; and everything else maps to `Normal`.
+    pub fn from_dscp(dscp: u8) -> SocketPriority {
+        match dscp >> 3 {
+            6 | 7 => SocketPriority::Control,
+            3..=5 => SocketPriority::Interactive,
+            1 => SocketPriority::Bulk,
+            _ => SocketPriority::Normal,
+        }
+    }
+
+    /// Return the 802.1Q priority code point frames of this priority are tagged with.
+    pub fn pcp(&self) -> u8 {
+        match self {
+            SocketPriority::Control => 6,
+            SocketPriority::Interactive => 5,
+            SocketPriority::Normal => 0,
+            SocketPriority::Bulk => 1,
+        }
+    }
+
+    fn default_for(socket: &Socket) -> SocketPriority {
+        match socket {
+            #[cfg(feature = "socket-icmp")]
+            Socket::Icmp(_) => SocketPriority::Control,
+            #[cfg(feature = "socket-dhcpv4")]
+            Socket::Dhcpv4(_) => SocketPriority::Control,
+            #[allow(unreachable_patterns)]
+            _ => SocketPriority::Normal,
+        }
+    }
+}
+
+#[allow(clippy::derivable_impls)] // `#[default]` requires a newer Rust than our MSRV
+impl Default for SocketPriority {
+    fn default() -> Self {
+        SocketPriority::Normal
+    }
+}
+
+/// The fields identifying the connection a packet belongs to, used to find the socket
+/// processing it without iterating over every socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(unused)] // unused depending on which sockets are enabled
+pub(crate) struct DemuxKey {
+    pub(crate) protocol: IpProtocol,
+    pub(crate) local: IpEndpoint,
+    pub(crate) remote: Option<IpEndpoint>,
+}
+
+impl DemuxKey {
+    /// FNV-1a hash of the key.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    fn hash(&self) -> u32 {
+        let mut hash = 0x811c9dc5u32;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= byte as u32;
+                hash = hash.wrapping_mul(0x01000193);
+            }
+        };
+        write(&[u8::from(self.protocol)]);
+        write(self.local.addr.as_bytes());
+        write(&self.local.port.to_be_bytes());
+        if let Some(remote) = self.remote {
+            write(remote.addr.as_bytes());
+            write(&remote.port.to_be_bytes());
+        }
+        hash
     }
 }
 
 /// An extensible set of sockets.
 ///
+/// The set is a slab: sockets are stored contiguously in insertion order, and their
+/// handles refer to them through a table that is kept alongside, so that adding and
+/// removing a socket take constant time while handles stay valid until the socket is
+/// removed. The table also keeps the generation of each handle, so that the handle of a
+/// removed socket is rejected once it is reused. Iterating over the set yields the
+/// sockets in insertion order.
+///
+/// The deadlines of the sockets are kept in a `DeadlineQueue`, whose nodes are stored
+/// alongside the handles. A socket accessed mutably leaves the queue, and its deadline is
+/// computed again at the end of the next poll, along with the deadlines of the sockets
+/// that were due; the deadlines of the other sockets are not computed again, unless the
+/// interface changed.
+///
 /// The lifetime `'a` is used when storing a `Socket<'a>`.
 #[derive(Debug)]
 pub struct SocketSet<'a> {
     sockets: ManagedSlice<'a, SocketStorage<'a>>,
+    /// Number of positions in use, including those of removed sockets.
+    len: usize,
+    /// Number of sockets in the set.
+    count: usize,
+    /// Number of handles ever allocated.
+    handles: usize,
+    /// The first handle of the free list.
+    free_handle: Option<usize>,
+    /// Direct-mapped cache from the hash of a `DemuxKey` to the socket that last accepted
+    /// a packet with that key. Entries are hints; the socket must still accept the packet.
+    index: [Option<(u32, SocketHandle)>; INDEX_SIZE],
+    /// The deadlines of the sockets, by handle.
+    queue: DeadlineQueue,
+    /// The index and generation of the interface the deadlines were computed for, or `None`
+    /// if they have to be computed again.
+    scheduled_for: Option<(u32, u32)>,
 }
 
 impl<'a> SocketSet<'a> {
@@ -50,7 +266,16 @@ impl<'a> SocketSet<'a> {
         SocketsT: Into<ManagedSlice<'a, SocketStorage<'a>>>,
     {
         let sockets = sockets.into();
-        SocketSet { sockets }
+        SocketSet {
+            sockets,
+            len: 0,
+            count: 0,
+            handles: 0,
+            free_handle: None,
+            index: [None; INDEX_SIZE],
+            queue: DeadlineQueue::default(),
+            scheduled_for: None,
+        }
     }
 
     /// Add a socket to the set, and return its handle.
@@ -58,34 +283,102 @@ impl<'a> SocketSet<'a> {
     /// # Panics
     /// This function panics if the storage is fixed-size (not a `Vec`) and is full.
     pub fn add<T: AnySocket<'a>>(&mut self, socket: T) -> SocketHandle {
-        fn put<'a>(index: usize, slot: &mut SocketStorage<'a>, socket: Socket<'a>) -> SocketHandle {
-            net_trace!("[{}]: adding", index);
-            let handle = SocketHandle(index);
-            let mut meta = Meta::default();
-            meta.handle = handle;
-            *slot = SocketStorage {
-                inner: Some(Item { meta, socket }),
-            };
-            handle
-        }
-
         let socket = socket.upcast();
+        self.index = [None; INDEX_SIZE];
 
-        for (index, slot) in self.sockets.iter_mut().enumerate() {
-            if slot.inner.is_none() {
-                return put(index, slot, socket);
-            }
+        if self.len == self.sockets.len() {
+            self.make_room();
         }
 
+        let handle = match self.free_handle {
+            Some(handle) => {
+                self.free_handle = match self.sockets[handle].handle {
+                    HandleState::Free(next) => next,
+                    _ => unreachable!(),
+                };
+                handle
+            }
+            None => {
+                self.handles += 1;
+                self.handles - 1
+            }
+        };
+        net_trace!("[{}]: adding", handle);
+
+        let handle = SocketHandle {
+            index: handle,
+            generation: self.sockets[handle].generation,
+        };
+        let mut meta = Meta::default();
+        meta.handle = handle;
+        meta.priority = SocketPriority::default_for(&socket);
+        self.sockets[self.len].inner = Some(Item { meta, socket });
+        self.sockets[handle.index].handle = HandleState::Live(self.len);
+        self.len += 1;
+        self.count += 1;
+        self.deadline_changed(handle.index);
+        handle
+    }
+
+    /// Make room for a socket at the end of the storage, either by growing it, or by
+    /// moving the sockets over the positions of removed ones.
+    fn make_room(&mut self) {
         match self.sockets {
-            ManagedSlice::Borrowed(_) => panic!("adding a socket to a full SocketSet"),
+            ManagedSlice::Borrowed(_) => {
+                if self.count == self.sockets.len() {
+                    panic!("adding a socket to a full SocketSet")
+                }
+            }
             #[cfg(any(feature = "std", feature = "alloc"))]
             ManagedSlice::Owned(ref mut sockets) => {
-                sockets.push(SocketStorage { inner: None });
-                let index = sockets.len() - 1;
-                put(index, &mut sockets[index], socket)
+                // Compacting only when more than half of the storage is unused keeps
+                // adding a socket constant time, amortized.
+                if self.count * 2 >= sockets.len() {
+                    sockets.push(SocketStorage::EMPTY);
+                    return;
+                }
             }
         }
+
+        let mut len = 0;
+        for position in 0..self.len {
+            if let Some(item) = self.sockets[position].inner.take() {
+                let handle = item.meta.handle.index;
+                self.sockets[len].inner = Some(item);
+                self.sockets[handle].handle = HandleState::Live(len);
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+
+    /// Return the position of the socket with the given handle.
+    fn position(&self, handle: SocketHandle) -> usize {
+        match self.sockets.get(handle.index) {
+            Some(SocketStorage {
+                handle: HandleState::Live(position),
+                generation,
+                ..
+            }) if *generation == handle.generation => *position,
+            _ => panic!("handle does not refer to a valid socket"),
+        }
+    }
+
+    fn item(&self, handle: SocketHandle) -> &Item<'a> {
+        match self.sockets[self.position(handle)].inner.as_ref() {
+            Some(item) => item,
+            None => unreachable!(),
+        }
+    }
+
+    /// Return the socket with the given handle, whose deadline has to be computed again.
+    fn item_mut(&mut self, handle: SocketHandle) -> &mut Item<'a> {
+        let position = self.position(handle);
+        self.deadline_changed(handle.index);
+        match self.sockets[position].inner.as_mut() {
+            Some(item) => item,
+            None => unreachable!(),
+        }
     }
 
     /// Get a socket from the set by its handle, as mutable.
@@ -94,12 +387,7 @@ impl<'a> SocketSet<'a> {
     /// This function may panic if the handle does not belong to this socket set
     /// or the socket has the wrong type.
     pub fn get<T: AnySocket<'a>>(&self, handle: SocketHandle) -> &T {
-        match self.sockets[handle.0].inner.as_ref() {
-            Some(item) => {
-                T::downcast(&item.socket).expect("handle refers to a socket of a wrong type")
-            }
-            None => panic!("handle does not refer to a valid socket"),
-        }
+        T::downcast(&self.item(handle).socket).expect("handle refers to a socket of a wrong type")
     }
 
     /// Get a mutable socket from the set by its handle, as mutable.
@@ -108,11 +396,8 @@ impl<'a> SocketSet<'a> {
     /// This function may panic if the handle does not belong to this socket set
     /// or the socket has the wrong type.
     pub fn get_mut<T: AnySocket<'a>>(&mut self, handle: SocketHandle) -> &mut T {
-        match self.sockets[handle.0].inner.as_mut() {
-            Some(item) => T::downcast_mut(&mut item.socket)
-                .expect("handle refers to a socket of a wrong type"),
-            None => panic!("handle does not refer to a valid socket"),
-        }
+        T::downcast_mut(&mut self.item_mut(handle).socket)
+            .expect("handle refers to a socket of a wrong type")
     }
 
     /// Remove a socket from the set, without changing its state.
@@ -120,11 +405,105 @@ impl<'a> SocketSet<'a> {
     /// # Panics
     /// This function may panic if the handle does not belong to this socket set.
     pub fn remove(&mut self, handle: SocketHandle) -> Socket<'a> {
-        net_trace!("[{}]: removing", handle.0);
-        match self.sockets[handle.0].inner.take() {
-            Some(item) => item.socket,
-            None => panic!("handle does not refer to a valid socket"),
+        let position = self.position(handle);
+        self.index = [None; INDEX_SIZE];
+        self.queue.remove(&mut *self.sockets, handle.index);
+        let item = match self.sockets[position].inner.take() {
+            Some(item) => item,
+            None => unreachable!(),
+        };
+        net_trace!("{}: removing", item.meta);
+
+        let storage = &mut self.sockets[handle.index];
+        storage.handle = HandleState::Free(self.free_handle);
+        storage.generation = storage.generation.wrapping_add(1);
+        self.free_handle = Some(handle.index);
+        self.count -= 1;
+        if position + 1 == self.len {
+            self.len -= 1;
         }
+        item.socket
+    }
+
+    /// Get the label of a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn label(&self, handle: SocketHandle) -> Option<SocketLabel> {
+        self.item(handle).meta.label
+    }
+
+    /// Set the label of a socket, which is included in log output alongside its handle.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn set_label(&mut self, handle: SocketHandle, label: Option<SocketLabel>) {
+        self.item_mut(handle).meta.label = label;
+    }
+
+    /// Get the egress priority of a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn priority(&self, handle: SocketHandle) -> SocketPriority {
+        self.item(handle).meta.priority
+    }
+
+    /// Set the egress priority of a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn set_priority(&mut self, handle: SocketHandle, priority: SocketPriority) {
+        self.item_mut(handle).meta.priority = priority;
+    }
+
+    /// Get the differentiated services codepoint of the packets sent by a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn dscp(&self, handle: SocketHandle) -> Option<u8> {
+        self.item(handle).meta.dscp
+    }
+
+    /// Set the differentiated services codepoint of the packets sent by a socket, and
+    /// its egress priority to the one of that codepoint. See [SocketPriority::from_dscp].
+    ///
+    /// Setting the codepoint to `None` sends packets with the default codepoint, and
+    /// leaves the egress priority unchanged.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set,
+    /// or if `dscp` does not fit into 6 bits.
+    pub fn set_dscp(&mut self, handle: SocketHandle, dscp: Option<u8>) {
+        if let Some(dscp) = dscp {
+            assert!(dscp < 64, "DSCP value out of range");
+        }
+        let meta = &mut self.item_mut(handle).meta;
+        meta.dscp = dscp;
+        if let Some(dscp) = dscp {
+            meta.priority = SocketPriority::from_dscp(dscp);
+        }
+    }
+
+    /// Get the mark of the packets sent by a socket.
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn mark(&self, handle: SocketHandle) -> Option<u32> {
+        self.item(handle).meta.mark
+    }
+
+    /// Set the mark of the packets sent by a socket, the equivalent of `SO_MARK`.
+    ///
+    /// The mark is not sent on the wire; it selects the [policy routes] the packets of
+    /// the socket are routed with.
+    ///
+    /// [policy routes]: crate::iface::Routes::add_policy_route
+    ///
+    /// # Panics
+    /// This function may panic if the handle does not belong to this socket set.
+    pub fn set_mark(&mut self, handle: SocketHandle, mark: Option<u32>) {
+        self.item_mut(handle).meta.mark = mark;
     }
 
     /// Get an iterator to the inner sockets.
@@ -139,11 +518,427 @@ impl<'a> SocketSet<'a> {
 
     /// Iterate every socket in this set.
     pub(crate) fn items(&self) -> impl Iterator<Item = &Item<'a>> + '_ {
-        self.sockets.iter().filter_map(|x| x.inner.as_ref())
+        self.sockets[..self.len]
+            .iter()
+            .filter_map(|x| x.inner.as_ref())
     }
 
     /// Iterate every socket in this set.
+    ///
+    /// The deadlines of every socket are computed again the next time they are scheduled.
     pub(crate) fn items_mut(&mut self) -> impl Iterator<Item = &mut Item<'a>> + '_ {
-        self.sockets.iter_mut().filter_map(|x| x.inner.as_mut())
+        self.scheduled_for = None;
+        self.sockets[..self.len]
+            .iter_mut()
+            .filter_map(|x| x.inner.as_mut())
+    }
+
+    /// Call `f` with every socket in this set, in insertion order. The deadlines of the
+    /// sockets for which `f` returns `true` are computed again the next time they are
+    /// scheduled.
+    pub(crate) fn for_each_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Item<'a>) -> bool,
+    {
+        for position in 0..self.len {
+            if let Some(item) = self.sockets[position].inner.as_mut() {
+                if f(item) {
+                    let handle = item.meta.handle;
+                    self.deadline_changed(handle.index);
+                }
+            }
+        }
+    }
+
+    /// Return the first socket of type `T` in this set, in insertion order, for which `f`
+    /// returns `true`. Its deadline is computed again the next time the deadlines are
+    /// scheduled.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn find_mut<T, F>(&mut self, mut f: F) -> Option<&mut T>
+    where
+        T: AnySocket<'a>,
+        F: FnMut(&T) -> bool,
+    {
+        let handle = self
+            .items()
+            .find(|item| matches!(T::downcast(&item.socket), Some(socket) if f(socket)))?
+            .meta
+            .handle;
+        Some(self.get_mut(handle))
+    }
+
+    /// Get the socket that last accepted a packet with the given key, if it is still known.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn lookup_mut(&mut self, key: &DemuxKey) -> Option<&mut Item<'a>> {
+        let hash = key.hash();
+        match self.index[hash as usize % INDEX_SIZE] {
+            // The index is cleared whenever a socket is added or removed, so the handle
+            // is still valid.
+            Some((entry_hash, handle)) if entry_hash == hash => Some(self.item_mut(handle)),
+            _ => None,
+        }
+    }
+
+    /// Return the earliest deadline of the sockets, with `poll_at` computing the deadline
+    /// of a socket.
+    ///
+    /// If the deadlines were scheduled for the interface identified by `interface`, only
+    /// the sockets that changed since are visited; otherwise, every socket is.
+    pub(crate) fn poll_at<F>(&self, interface: (u32, u32), poll_at: F) -> Option<Instant>
+    where
+        F: FnMut(&Item<'a>) -> Option<Instant>,
+    {
+        if self.scheduled_for != Some(interface) {
+            return self.items().filter_map(poll_at).min();
+        }
+
+        let sockets = &*self.sockets;
+        self.queue
+            .changed(sockets)
+            // Removed sockets are only unlinked the next time the deadlines are scheduled.
+            .filter_map(|handle| match sockets[handle].handle {
+                HandleState::Live(position) => sockets[position].inner.as_ref(),
+                _ => None,
+            })
+            .filter_map(poll_at)
+            .chain(self.queue.earliest(sockets))
+            .min()
+    }
+
+    /// Compute the deadlines of the sockets that changed, or were due at `now`, for the
+    /// interface identified by `interface`, with `poll_at` computing the deadline of a
+    /// socket. If the deadlines were scheduled for another interface, or a mutable iterator
+    /// was used since, the deadlines of every socket are computed again.
+    ///
+    /// Sockets waiting for a neighbor stay in the list of changed sockets, since their
+    /// deadline depends on the neighbor cache.
+    pub(crate) fn schedule<F>(&mut self, interface: (u32, u32), now: Instant, mut poll_at: F)
+    where
+        F: FnMut(&Item<'a>) -> Option<Instant>,
+    {
+        let sockets = &mut *self.sockets;
+        if self.scheduled_for == Some(interface) {
+            // The sockets that were due have been dispatched.
+            self.queue.mark_due(sockets, now);
+        } else {
+            self.queue = DeadlineQueue::default();
+            for handle in 0..self.handles {
+                sockets[handle].deadline = Node::EMPTY;
+                if let HandleState::Live(_) = sockets[handle].handle {
+                    self.queue.mark_changed(sockets, handle);
+                }
+            }
+        }
+
+        self.queue.update(sockets, |sockets, handle| {
+            let item = match sockets[handle].handle {
+                HandleState::Live(position) => match sockets[position].inner.as_ref() {
+                    Some(item) => item,
+                    None => unreachable!(),
+                },
+                _ => return Deadline::Never,
+            };
+            if item.meta.waiting_for_neighbor() {
+                return Deadline::Unknown;
+            }
+            match poll_at(item) {
+                Some(deadline) => Deadline::At(deadline),
+                None => Deadline::Never,
+            }
+        });
+        self.scheduled_for = Some(interface);
+    }
+
+    /// Move the socket with the given handle from the deadline queue to the list of
+    /// changed sockets, because its deadline may have changed.
+    fn deadline_changed(&mut self, handle: usize) {
+        if self.scheduled_for.is_none() {
+            return;
+        }
+        self.queue.mark_changed(&mut *self.sockets, handle);
+    }
+
+    /// Remember the socket that accepted a packet with the given key.
+    #[allow(unused)] // unused depending on which sockets are enabled
+    pub(crate) fn insert_index(&mut self, key: &DemuxKey, handle: SocketHandle) {
+        let hash = key.hash();
+        self.index[hash as usize % INDEX_SIZE] = Some((hash, handle));
+    }
+}
+
+#[cfg(all(test, feature = "socket-udp"))]
+mod test {
+    use super::*;
+    use crate::socket::udp;
+    use core::cell::Cell;
+
+    fn udp_socket() -> udp::Socket<'static> {
+        let buffer = || udp::PacketBuffer::new(vec![], vec![]);
+        udp::Socket::new(buffer(), buffer())
+    }
+
+    #[test]
+    fn test_label() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(sockets.label(handle), None);
+
+        sockets.set_label(handle, Some("dns-relay".into()));
+        assert_eq!(sockets.label(handle), Some(SocketLabel::Name("dns-relay")));
+        assert_eq!(
+            sockets.items().next().unwrap().meta.to_string(),
+            "#0 (dns-relay)"
+        );
+
+        sockets.set_label(handle, Some(7.into()));
+        assert_eq!(sockets.items().next().unwrap().meta.to_string(), "#0 (7)");
+
+        // Labels do not carry over to a socket reusing the storage.
+        sockets.remove(handle);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(sockets.label(handle), None);
+    }
+
+    #[test]
+    fn test_dscp() {
+        assert_eq!(SocketPriority::from_dscp(48), SocketPriority::Control);
+        assert_eq!(SocketPriority::from_dscp(46), SocketPriority::Interactive);
+        assert_eq!(SocketPriority::from_dscp(0), SocketPriority::Normal);
+        assert_eq!(SocketPriority::from_dscp(10), SocketPriority::Bulk);
+
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(sockets.dscp(handle), None);
+
+        sockets.set_dscp(handle, Some(46));
+        assert_eq!(sockets.dscp(handle), Some(46));
+        assert_eq!(sockets.priority(handle), SocketPriority::Interactive);
+
+        sockets.set_dscp(handle, None);
+        assert_eq!(sockets.dscp(handle), None);
+        assert_eq!(sockets.priority(handle), SocketPriority::Interactive);
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(sockets.priority(handle), SocketPriority::Normal);
+
+        sockets.set_priority(handle, SocketPriority::Bulk);
+        assert_eq!(sockets.priority(handle), SocketPriority::Bulk);
+
+        // Priorities do not carry over to a socket reusing the storage.
+        sockets.remove(handle);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(sockets.priority(handle), SocketPriority::Normal);
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handles: Vec<_> = (0..4).map(|_| sockets.add(udp_socket())).collect();
+        sockets.remove(handles[1]);
+        let handle = sockets.add(udp_socket());
+        // The index of the handle is reused, but the socket comes last.
+        assert_eq!(handle.index, handles[1].index);
+        assert_ne!(handle, handles[1]);
+        let order: Vec<_> = sockets.iter().map(|(handle, _)| handle).collect();
+        assert_eq!(order, [handles[0], handles[2], handles[3], handle]);
+    }
+
+    #[test]
+    fn test_compaction() {
+        let mut storage = [
+            SocketStorage::EMPTY,
+            SocketStorage::EMPTY,
+            SocketStorage::EMPTY,
+        ];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let a = sockets.add(udp_socket());
+        let b = sockets.add(udp_socket());
+        let c = sockets.add(udp_socket());
+        sockets.set_label(c, Some("c".into()));
+        sockets.remove(a);
+
+        // Adding a socket to the full storage moves the others, but not their handles.
+        let d = sockets.add(udp_socket());
+        assert_eq!(sockets.label(c), Some(SocketLabel::Name("c")));
+        let order: Vec<_> = sockets.iter().map(|(handle, _)| handle).collect();
+        assert_eq!(order, [b, c, d]);
+
+        sockets.remove(b);
+        sockets.remove(c);
+        assert_eq!(sockets.iter().count(), 1);
+        let e = sockets.add(udp_socket());
+        let order: Vec<_> = sockets.iter().map(|(handle, _)| handle).collect();
+        assert_eq!(order, [d, e]);
+    }
+
+    #[test]
+    #[should_panic(expected = "adding a socket to a full SocketSet")]
+    fn test_full() {
+        let mut storage = [SocketStorage::EMPTY];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        sockets.add(udp_socket());
+        sockets.add(udp_socket());
+    }
+
+    #[test]
+    #[should_panic(expected = "handle does not refer to a valid socket")]
+    fn test_removed_handle() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        sockets.remove(handle);
+        sockets.get::<udp::Socket>(handle);
+    }
+
+    #[test]
+    #[should_panic(expected = "handle does not refer to a valid socket")]
+    fn test_stale_handle_get_mut() {
+        let mut sockets = SocketSet::new(vec![]);
+        let stale = sockets.add(udp_socket());
+        sockets.remove(stale);
+        let handle = sockets.add(udp_socket());
+        assert_eq!(handle.index, stale.index);
+        sockets.get::<udp::Socket>(handle);
+        sockets.get_mut::<udp::Socket>(stale);
+    }
+
+    #[test]
+    #[should_panic(expected = "handle does not refer to a valid socket")]
+    fn test_stale_handle_remove() {
+        let mut storage = [SocketStorage::EMPTY];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let stale = sockets.add(udp_socket());
+        sockets.remove(stale);
+        sockets.add(udp_socket());
+        sockets.remove(stale);
+    }
+
+    #[test]
+    fn test_poll_at() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handles: Vec<_> = (0..8).map(|_| sockets.add(udp_socket())).collect();
+        let mut deadlines: Vec<_> = [0, 5, 3, 7, 1, 9, 2, 8]
+            .iter()
+            .map(|&secs| match secs {
+                0 => None,
+                secs => Some(Instant::from_secs(secs)),
+            })
+            .collect();
+        let visited = Cell::new(0);
+        let poll_at = |sockets: &SocketSet, deadlines: &[Option<Instant>]| {
+            sockets.poll_at((0, 0), |item| {
+                visited.set(visited.get() + 1);
+                deadlines[item.meta.handle.index]
+            })
+        };
+
+        // Every socket is visited until the deadlines are scheduled.
+        assert_eq!(poll_at(&sockets, &deadlines), Some(Instant::from_secs(1)));
+        sockets.schedule((0, 0), Instant::ZERO, |item| {
+            deadlines[item.meta.handle.index]
+        });
+        assert_eq!(poll_at(&sockets, &deadlines), Some(Instant::from_secs(1)));
+        assert_eq!(visited.get(), 8);
+
+        // A changed socket leaves the heap, and is visited until it is scheduled again.
+        deadlines[4] = Some(Instant::from_secs(10));
+        sockets.get_mut::<udp::Socket>(handles[4]);
+        assert_eq!(poll_at(&sockets, &deadlines), Some(Instant::from_secs(2)));
+        sockets.remove(handles[6]);
+        assert_eq!(poll_at(&sockets, &deadlines), Some(Instant::from_secs(3)));
+        assert_eq!(visited.get(), 10);
+        sockets.schedule((0, 0), Instant::ZERO, |item| {
+            deadlines[item.meta.handle.index]
+        });
+        assert_eq!(poll_at(&sockets, &deadlines), Some(Instant::from_secs(3)));
+        assert_eq!(visited.get(), 10);
+
+        // The heap yields the remaining deadlines in order.
+        for &(handle, secs) in [(2, 5), (1, 7), (3, 8), (7, 9), (5, 10), (4, 0)].iter() {
+            sockets.remove(handles[handle]);
+            let expected = match secs {
+                0 => None,
+                secs => Some(Instant::from_secs(secs)),
+            };
+            assert_eq!(poll_at(&sockets, &deadlines), expected);
+        }
+        assert_eq!(visited.get(), 10);
+    }
+
+    #[test]
+    fn test_poll_at_meta_changed() {
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        sockets.add(udp_socket());
+        sockets.schedule((0, 0), Instant::ZERO, |_| None);
+
+        // The mark selects the route, and so the MTU, the deadline depends on.
+        let mut visited = 0;
+        sockets.set_mark(handle, Some(1));
+        sockets.set_priority(handle, SocketPriority::Bulk);
+        sockets.poll_at((0, 0), |item| {
+            assert_eq!(item.meta.handle, handle);
+            visited += 1;
+            None
+        });
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn test_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<SocketSet>();
+    }
+
+    #[test]
+    fn test_poll_at_rescheduled() {
+        let mut sockets = SocketSet::new(vec![]);
+        sockets.add(udp_socket());
+        sockets.add(udp_socket());
+        sockets.schedule((0, 0), Instant::ZERO, |_| Some(Instant::from_secs(1)));
+
+        // The deadlines are computed again for another interface, after the interface
+        // changed, and after the sockets were iterated mutably.
+        let mut visited = 0;
+        for &interface in [(1, 0), (0, 1)].iter() {
+            sockets.poll_at(interface, |_| {
+                visited += 1;
+                None
+            });
+        }
+        assert_eq!(visited, 4);
+        for _ in sockets.items_mut() {}
+        assert_eq!(sockets.poll_at((0, 0), |_| None), None);
+        sockets.schedule((0, 0), Instant::ZERO, |_| None);
+        assert_eq!(
+            sockets.poll_at((0, 0), |_| Some(Instant::from_secs(1))),
+            None
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_index() {
+        use crate::wire::{IpAddress, Ipv4Address};
+
+        let mut sockets = SocketSet::new(vec![]);
+        let handle = sockets.add(udp_socket());
+        let key = |port| DemuxKey {
+            protocol: IpProtocol::Udp,
+            local: IpEndpoint::new(IpAddress::Ipv4(Ipv4Address::new(192, 168, 1, 1)), port),
+            remote: None,
+        };
+
+        assert!(sockets.lookup_mut(&key(53)).is_none());
+        sockets.insert_index(&key(53), handle);
+        assert_eq!(sockets.lookup_mut(&key(53)).unwrap().meta.handle, handle);
+        assert!(sockets.lookup_mut(&key(54)).is_none());
+
+        // Adding a socket, which may reuse the storage of a removed one, clears the index.
+        sockets.add(udp_socket());
+        assert!(sockets.lookup_mut(&key(53)).is_none());
     }
 }
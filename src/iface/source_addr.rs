@@ -0,0 +1,243 @@
+//! IPv6 source address selection, following the rules of RFC 6724 § 5.
+
+use core::cmp::Ordering;
+
+use crate::wire::{Ipv6Address, Ipv6Cidr};
+
+/// Scope values of RFC 4291 § 2.7, as used by RFC 6724 § 3.1.
+const SCOPE_LINK_LOCAL: u8 = 0x2;
+const SCOPE_SITE_LOCAL: u8 = 0x5;
+const SCOPE_GLOBAL: u8 = 0xe;
+
+/// The default policy table of RFC 6724 § 2.1, as `(prefix, prefix length, label)`.
+///
+/// Entries are sorted by decreasing prefix length, so the first match is the longest one.
+/// Precedence is only used for destination address selection, and is omitted.
+const POLICY_TABLE: &[(Ipv6Address, u8, u8)] = &[
+    (Ipv6Address::LOOPBACK, 128, 0),
+    (
+        Ipv6Address([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 0, 0]),
+        96,
+        4,
+    ),
+    (Ipv6Address::UNSPECIFIED, 96, 3),
+    (
+        Ipv6Address([0x20, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        32,
+        5,
+    ),
+    (
+        Ipv6Address([0x20, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        16,
+        2,
+    ),
+    (
+        Ipv6Address([0x3f, 0xfe, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        16,
+        12,
+    ),
+    (
+        Ipv6Address([0xfe, 0xc0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        10,
+        11,
+    ),
+    (
+        Ipv6Address([0xfc, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        7,
+        13,
+    ),
+    (Ipv6Address::UNSPECIFIED, 0, 1),
+];
+
+/// A candidate source address.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Candidate {
+    pub cidr: Ipv6Cidr,
+    /// Whether the address should be avoided for new communications.
+    pub deprecated: bool,
+    /// Whether the address is a temporary address that should be preferred.
+    pub temporary: bool,
+}
+
+/// Return the scope of `addr`.
+fn scope(addr: &Ipv6Address) -> u8 {
+    let bytes = addr.as_bytes();
+    if addr.is_multicast() {
+        bytes[1] & 0x0f
+    } else if addr.is_link_local() || addr.is_loopback() {
+        SCOPE_LINK_LOCAL
+    } else if bytes[0] == 0xfe && bytes[1] & 0xc0 == 0xc0 {
+        SCOPE_SITE_LOCAL
+    } else {
+        SCOPE_GLOBAL
+    }
+}
+
+/// Return the label of `addr` in the default policy table.
+fn label(addr: &Ipv6Address) -> u8 {
+    POLICY_TABLE
+        .iter()
+        .find(|(prefix, prefix_len, _)| {
+            common_prefix_len(addr.as_bytes(), prefix.as_bytes()) >= *prefix_len
+        })
+        .map_or(1, |&(_, _, label)| label)
+}
+
+/// Return the length of the longest common prefix of `a` and `b`, in bits.
+fn common_prefix_len(a: &[u8], b: &[u8]) -> u8 {
+    let mut len = 0;
+    for (a, b) in a.iter().zip(b.iter()) {
+        let diff = a ^ b;
+        if diff != 0 {
+            return len + diff.leading_zeros() as u8;
+        }
+        len += 8;
+    }
+    len
+}
+
+/// Compare two candidate source addresses for `dst_addr`, following the rules of
+/// RFC 6724 § 5. Returns `Ordering::Greater` if `a` is preferred over `b`.
+///
+/// Rules 4 (home addresses) and 5 (outgoing interface) do not apply, and are skipped.
+pub(crate) fn compare(dst_addr: &Ipv6Address, a: &Candidate, b: &Candidate) -> Ordering {
+    let (addr_a, addr_b) = (a.cidr.address(), b.cidr.address());
+
+    // Rule 1: Prefer same address.
+    if addr_a == *dst_addr || addr_b == *dst_addr {
+        return (addr_a == *dst_addr).cmp(&(addr_b == *dst_addr));
+    }
+
+    // Rule 2: Prefer appropriate scope.
+    let (scope_a, scope_b, scope_dst) = (scope(&addr_a), scope(&addr_b), scope(dst_addr));
+    if scope_a < scope_b {
+        return if scope_a < scope_dst {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+    }
+    if scope_b < scope_a {
+        return if scope_b < scope_dst {
+            Ordering::Greater
+        } else {
+            Ordering::Less
+        };
+    }
+
+    // Rule 3: Avoid deprecated addresses.
+    if a.deprecated != b.deprecated {
+        return b.deprecated.cmp(&a.deprecated);
+    }
+
+    // Rule 6: Prefer matching label.
+    let label_dst = label(dst_addr);
+    let (match_a, match_b) = (label(&addr_a) == label_dst, label(&addr_b) == label_dst);
+    if match_a != match_b {
+        return match_a.cmp(&match_b);
+    }
+
+    // Rule 7: Prefer temporary addresses.
+    if a.temporary != b.temporary {
+        return a.temporary.cmp(&b.temporary);
+    }
+
+    // Rule 8: Use longest matching prefix, up to the length of the prefix of the source.
+    let prefix_a =
+        common_prefix_len(addr_a.as_bytes(), dst_addr.as_bytes()).min(a.cidr.prefix_len());
+    let prefix_b =
+        common_prefix_len(addr_b.as_bytes(), dst_addr.as_bytes()).min(b.cidr.prefix_len());
+    prefix_a.cmp(&prefix_b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LINK_LOCAL: Ipv6Address =
+        Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    const ULA: Ipv6Address = Ipv6Address([0xfd, 0xbe, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    const GLOBAL_A: Ipv6Address =
+        Ipv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    const GLOBAL_B: Ipv6Address =
+        Ipv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    fn candidate(addr: Ipv6Address, deprecated: bool) -> Candidate {
+        Candidate {
+            cidr: Ipv6Cidr::new(addr, 64),
+            deprecated,
+            temporary: false,
+        }
+    }
+
+    #[test]
+    fn test_scope_and_label() {
+        assert_eq!(scope(&LINK_LOCAL), SCOPE_LINK_LOCAL);
+        assert_eq!(scope(&Ipv6Address::LOOPBACK), SCOPE_LINK_LOCAL);
+        assert_eq!(scope(&Ipv6Address::LINK_LOCAL_ALL_NODES), SCOPE_LINK_LOCAL);
+        assert_eq!(scope(&ULA), SCOPE_GLOBAL);
+        assert_eq!(label(&Ipv6Address::LOOPBACK), 0);
+        assert_eq!(label(&ULA), 13);
+        assert_eq!(label(&GLOBAL_A), 1);
+        assert_eq!(
+            label(&Ipv6Address([
+                0x20, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1
+            ])),
+            5
+        );
+        assert_eq!(label(&LINK_LOCAL), 1);
+    }
+
+    #[test]
+    fn test_same_address() {
+        let a = candidate(LINK_LOCAL, true);
+        let b = candidate(GLOBAL_A, false);
+        assert_eq!(compare(&LINK_LOCAL, &a, &b), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_appropriate_scope() {
+        let link_local = candidate(LINK_LOCAL, false);
+        let global = candidate(GLOBAL_A, false);
+        assert_eq!(compare(&GLOBAL_B, &link_local, &global), Ordering::Less);
+        let dst = Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(compare(&dst, &link_local, &global), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_avoid_deprecated() {
+        let deprecated = candidate(GLOBAL_A, true);
+        let preferred = candidate(ULA, false);
+        assert_eq!(compare(&GLOBAL_B, &deprecated, &preferred), Ordering::Less);
+    }
+
+    #[test]
+    fn test_matching_label() {
+        let ula = candidate(ULA, false);
+        let global = candidate(GLOBAL_A, false);
+        let dst = Ipv6Address([0xfd, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(compare(&dst, &ula, &global), Ordering::Greater);
+        assert_eq!(compare(&GLOBAL_B, &ula, &global), Ordering::Less);
+    }
+
+    #[test]
+    fn test_prefer_temporary() {
+        let stable = candidate(GLOBAL_B, false);
+        let temporary = Candidate {
+            temporary: true,
+            ..candidate(GLOBAL_A, false)
+        };
+        assert_eq!(compare(&GLOBAL_B, &temporary, &stable), Ordering::Less);
+        let dst = Ipv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9]);
+        assert_eq!(compare(&dst, &temporary, &stable), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_longest_matching_prefix() {
+        let a = candidate(GLOBAL_A, false);
+        let b = candidate(GLOBAL_B, false);
+        let dst = Ipv6Address([0x20, 0x01, 0x0d, 0xb8, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9]);
+        assert_eq!(compare(&dst, &a, &b), Ordering::Less);
+        assert_eq!(compare(&dst, &b, &a), Ordering::Greater);
+    }
+}
@@ -0,0 +1,303 @@
+/*! Management counters.
+
+The counters of an interface follow the objects of the interfaces, IP, ICMP, TCP and UDP
+groups of MIB-II, defined in [RFC 1213], so that an SNMP agent, or any other exporter,
+can be built on top of them. See [Stats::mib_objects].
+
+Unlike their MIB-II counterparts, the counters are 64-bit, and the ICMP and IP counters
+account for both IPv4 and IPv6.
+
+[RFC 1213]: https://tools.ietf.org/html/rfc1213
+*/
+
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Icmpv4Repr;
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::Icmpv6Repr;
+
+/// Counters of the interfaces group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct InterfaceStats {
+    /// Octets received in frames, `ifInOctets`.
+    pub in_octets: u64,
+    /// Frames received with a unicast link-layer destination, `ifInUcastPkts`.
+    /// On media without link-layer addresses, every packet received.
+    pub in_ucast_pkts: u64,
+    /// Frames received with a broadcast or multicast link-layer destination, `ifInNUcastPkts`.
+    pub in_nucast_pkts: u64,
+    /// Malformed frames received, `ifInErrors`.
+    pub in_errors: u64,
+    /// Frames received for an unsupported link-layer protocol, `ifInUnknownProtos`.
+    pub in_unknown_protos: u64,
+    /// Octets transmitted in frames, `ifOutOctets`.
+    pub out_octets: u64,
+    /// Frames transmitted to a unicast link-layer destination, `ifOutUcastPkts`.
+    pub out_ucast_pkts: u64,
+    /// Frames transmitted to a broadcast or multicast link-layer destination,
+    /// `ifOutNUcastPkts`.
+    pub out_nucast_pkts: u64,
+}
+
+/// Counters of the IP group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct IpStats {
+    /// Packets received, including those with errors, `ipInReceives`.
+    pub in_receives: u64,
+    /// Packets received with a malformed header, `ipInHdrErrors`.
+    pub in_hdr_errors: u64,
+    /// Packets received for an address that is not an address of the interface,
+    /// `ipInAddrErrors`.
+    pub in_addr_errors: u64,
+    /// Packets received for an unsupported protocol, `ipInUnknownProtos`.
+    pub in_unknown_protos: u64,
+    /// Packets transmitted, `ipOutRequests`.
+    pub out_requests: u64,
+    /// Packets that could not be transmitted because there is no route to their
+    /// destination, `ipOutNoRoutes`.
+    pub out_no_routes: u64,
+    /// Fragments received that needed reassembly, `ipReasmReqds`.
+    pub reasm_reqds: u64,
+    /// Packets reassembled, `ipReasmOKs`.
+    pub reasm_oks: u64,
+    /// Failures of the reassembly algorithm, `ipReasmFails`.
+    pub reasm_fails: u64,
+}
+
+/// Counters of the ICMP group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct IcmpStats {
+    /// Messages received, including those with errors, `icmpInMsgs`.
+    pub in_msgs: u64,
+    /// Malformed messages received, `icmpInErrors`.
+    pub in_errors: u64,
+    /// Destination Unreachable messages received, `icmpInDestUnreachs`.
+    pub in_dest_unreachs: u64,
+    /// Echo Request messages received, `icmpInEchos`.
+    pub in_echos: u64,
+    /// Echo Reply messages received, `icmpInEchoReps`.
+    pub in_echo_reps: u64,
+    /// Messages transmitted, `icmpOutMsgs`.
+    pub out_msgs: u64,
+    /// Destination Unreachable messages transmitted, `icmpOutDestUnreachs`.
+    pub out_dest_unreachs: u64,
+    /// Echo Request messages transmitted, `icmpOutEchos`.
+    pub out_echos: u64,
+    /// Echo Reply messages transmitted, `icmpOutEchoReps`.
+    pub out_echo_reps: u64,
+}
+
+/// Counters of the TCP group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct TcpStats {
+    /// Transitions from CLOSED to SYN-SENT, `tcpActiveOpens`.
+    pub active_opens: u64,
+    /// Transitions from LISTEN to SYN-RECEIVED, `tcpPassiveOpens`.
+    pub passive_opens: u64,
+    /// Transitions from SYN-SENT or SYN-RECEIVED to CLOSED or LISTEN, `tcpAttemptFails`.
+    pub attempt_fails: u64,
+    /// Transitions from ESTABLISHED or CLOSE-WAIT to CLOSED, `tcpEstabResets`.
+    pub estab_resets: u64,
+    /// Segments received, including those with errors, `tcpInSegs`.
+    pub in_segs: u64,
+    /// Segments transmitted, including retransmitted ones, `tcpOutSegs`.
+    pub out_segs: u64,
+    /// Segments retransmitted, `tcpRetransSegs`.
+    pub retrans_segs: u64,
+    /// Malformed segments received, `tcpInErrs`.
+    pub in_errs: u64,
+    /// Segments transmitted with the RST flag, `tcpOutRsts`.
+    pub out_rsts: u64,
+    /// Challenge ACKs transmitted in response to suspicious segments (RFC 5961).
+    pub challenge_acks: u64,
+}
+
+/// Counters of the UDP group.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct UdpStats {
+    /// Datagrams delivered to sockets, `udpInDatagrams`.
+    pub in_datagrams: u64,
+    /// Datagrams received for a port no socket is bound to, `udpNoPorts`.
+    pub no_ports: u64,
+    /// Malformed datagrams received, `udpInErrors`.
+    pub in_errors: u64,
+    /// Datagrams transmitted, `udpOutDatagrams`.
+    pub out_datagrams: u64,
+}
+
+/// Management counters of an interface.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct Stats {
+    /// Counters of the interfaces group.
+    pub interface: InterfaceStats,
+    /// Counters of the IP group.
+    pub ip: IpStats,
+    /// Counters of the ICMP group.
+    pub icmp: IcmpStats,
+    /// Counters of the TCP group.
+    pub tcp: TcpStats,
+    /// Counters of the UDP group.
+    pub udp: UdpStats,
+}
+
+type Getter = fn(&Stats) -> u64;
+
+const MIB_OBJECTS: &[(&str, Getter)] = &[
+    ("ifInOctets", |s| s.interface.in_octets),
+    ("ifInUcastPkts", |s| s.interface.in_ucast_pkts),
+    ("ifInNUcastPkts", |s| s.interface.in_nucast_pkts),
+    ("ifInErrors", |s| s.interface.in_errors),
+    ("ifInUnknownProtos", |s| s.interface.in_unknown_protos),
+    ("ifOutOctets", |s| s.interface.out_octets),
+    ("ifOutUcastPkts", |s| s.interface.out_ucast_pkts),
+    ("ifOutNUcastPkts", |s| s.interface.out_nucast_pkts),
+    ("ipInReceives", |s| s.ip.in_receives),
+    ("ipInHdrErrors", |s| s.ip.in_hdr_errors),
+    ("ipInAddrErrors", |s| s.ip.in_addr_errors),
+    ("ipInUnknownProtos", |s| s.ip.in_unknown_protos),
+    ("ipOutRequests", |s| s.ip.out_requests),
+    ("ipOutNoRoutes", |s| s.ip.out_no_routes),
+    ("ipReasmReqds", |s| s.ip.reasm_reqds),
+    ("ipReasmOKs", |s| s.ip.reasm_oks),
+    ("ipReasmFails", |s| s.ip.reasm_fails),
+    ("icmpInMsgs", |s| s.icmp.in_msgs),
+    ("icmpInErrors", |s| s.icmp.in_errors),
+    ("icmpInDestUnreachs", |s| s.icmp.in_dest_unreachs),
+    ("icmpInEchos", |s| s.icmp.in_echos),
+    ("icmpInEchoReps", |s| s.icmp.in_echo_reps),
+    ("icmpOutMsgs", |s| s.icmp.out_msgs),
+    ("icmpOutDestUnreachs", |s| s.icmp.out_dest_unreachs),
+    ("icmpOutEchos", |s| s.icmp.out_echos),
+    ("icmpOutEchoReps", |s| s.icmp.out_echo_reps),
+    ("tcpActiveOpens", |s| s.tcp.active_opens),
+    ("tcpPassiveOpens", |s| s.tcp.passive_opens),
+    ("tcpAttemptFails", |s| s.tcp.attempt_fails),
+    ("tcpEstabResets", |s| s.tcp.estab_resets),
+    ("tcpInSegs", |s| s.tcp.in_segs),
+    ("tcpOutSegs", |s| s.tcp.out_segs),
+    ("tcpRetransSegs", |s| s.tcp.retrans_segs),
+    ("tcpInErrs", |s| s.tcp.in_errs),
+    ("tcpOutRsts", |s| s.tcp.out_rsts),
+    ("udpInDatagrams", |s| s.udp.in_datagrams),
+    ("udpNoPorts", |s| s.udp.no_ports),
+    ("udpInErrors", |s| s.udp.in_errors),
+    ("udpOutDatagrams", |s| s.udp.out_datagrams),
+];
+
+impl Stats {
+    /// Iterate over the counters, along with the names of the MIB-II objects they
+    /// correspond to, e.g. `("ifInOctets", 1500)`.
+    pub fn mib_objects(&self) -> impl Iterator<Item = (&'static str, u64)> + '_ {
+        MIB_OBJECTS
+            .iter()
+            .map(move |&(name, getter)| (name, getter(self)))
+    }
+
+    pub(crate) fn icmp_received(&mut self, kind: IcmpKind) {
+        self.icmp.in_msgs += 1;
+        match kind {
+            IcmpKind::DstUnreachable => self.icmp.in_dest_unreachs += 1,
+            IcmpKind::EchoRequest => self.icmp.in_echos += 1,
+            IcmpKind::EchoReply => self.icmp.in_echo_reps += 1,
+            IcmpKind::Other => (),
+        }
+    }
+
+    pub(crate) fn ip_packet_sent(&mut self, kind: PacketKind) {
+        self.ip.out_requests += 1;
+        match kind {
+            PacketKind::Icmp(kind) => {
+                self.icmp.out_msgs += 1;
+                match kind {
+                    IcmpKind::DstUnreachable => self.icmp.out_dest_unreachs += 1,
+                    IcmpKind::EchoRequest => self.icmp.out_echos += 1,
+                    IcmpKind::EchoReply => self.icmp.out_echo_reps += 1,
+                    IcmpKind::Other => (),
+                }
+            }
+            PacketKind::Udp => self.udp.out_datagrams += 1,
+            PacketKind::Tcp { rst } => {
+                self.tcp.out_segs += 1;
+                if rst {
+                    self.tcp.out_rsts += 1;
+                }
+            }
+            PacketKind::Other => (),
+        }
+    }
+}
+
+/// The type of an ICMP message, as far as the counters are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IcmpKind {
+    DstUnreachable,
+    EchoRequest,
+    EchoReply,
+    Other,
+}
+
+impl IcmpKind {
+    #[cfg(feature = "proto-ipv4")]
+    pub(crate) fn of_icmpv4(repr: &Icmpv4Repr) -> IcmpKind {
+        match repr {
+            Icmpv4Repr::DstUnreachable { .. } => IcmpKind::DstUnreachable,
+            Icmpv4Repr::EchoRequest { .. } => IcmpKind::EchoRequest,
+            Icmpv4Repr::EchoReply { .. } => IcmpKind::EchoReply,
+            #[allow(unreachable_patterns)]
+            _ => IcmpKind::Other,
+        }
+    }
+
+    #[cfg(feature = "proto-ipv6")]
+    pub(crate) fn of_icmpv6(repr: &Icmpv6Repr) -> IcmpKind {
+        match repr {
+            Icmpv6Repr::DstUnreachable { .. } => IcmpKind::DstUnreachable,
+            Icmpv6Repr::EchoRequest { .. } => IcmpKind::EchoRequest,
+            Icmpv6Repr::EchoReply { .. } => IcmpKind::EchoReply,
+            _ => IcmpKind::Other,
+        }
+    }
+}
+
+/// The protocol of an IP packet, as far as the counters are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PacketKind {
+    Icmp(IcmpKind),
+    Udp,
+    Tcp { rst: bool },
+    Other,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mib_objects() {
+        let mut stats = Stats::default();
+        stats.interface.in_octets = 1500;
+        stats.ip_packet_sent(PacketKind::Tcp { rst: true });
+        stats.icmp_received(IcmpKind::EchoRequest);
+
+        let objects: std::vec::Vec<_> = stats.mib_objects().collect();
+        assert_eq!(objects.len(), MIB_OBJECTS.len());
+        assert!(objects.contains(&("ifInOctets", 1500)));
+        assert!(objects.contains(&("ipOutRequests", 1)));
+        assert!(objects.contains(&("tcpOutSegs", 1)));
+        assert!(objects.contains(&("tcpOutRsts", 1)));
+        assert!(objects.contains(&("icmpInMsgs", 1)));
+        assert!(objects.contains(&("icmpInEchos", 1)));
+        assert!(objects.contains(&("udpOutDatagrams", 0)));
+    }
+}
@@ -20,6 +20,11 @@
 //! stateful sockets) the state machines, but it is interface-agnostic. An application must
 //! use sockets together with a network interface.
 //!
+//! # The application layer
+//! The application layer APIs are provided in the module [apps](apps/index.html); currently,
+//! a TFTP client and server, and LLMNR and NetBIOS name service responders are provided.
+//! They operate on sockets owned by the application.
+//!
 //! # The interface layer
 //! The interface layer APIs are provided in the module [iface](iface/index.html); currently,
 //! Ethernet interface is provided.
@@ -106,9 +111,10 @@ compile_error!("You must enable at least one of the following features: proto-ip
         feature = "socket-icmp",
         feature = "socket-dhcp",
         feature = "socket-dns",
+        feature = "socket-sntp",
     ))
 ))]
-compile_error!("If you enable the socket feature, you must enable at least one of the following features: socket-raw, socket-udp, socket-tcp, socket-icmp, socket-dhcp, socket-dns");
+compile_error!("If you enable the socket feature, you must enable at least one of the following features: socket-raw, socket-udp, socket-tcp, socket-icmp, socket-dhcp, socket-dns, socket-sntp");
 
 #[cfg(all(
     feature = "socket",
@@ -130,6 +136,20 @@ mod macros;
 mod parsers;
 mod rand;
 
+#[cfg(any(
+    all(
+        any(
+            feature = "proto-dhcpv4",
+            feature = "proto-dns",
+            feature = "proto-tftp",
+            feature = "proto-llmnr",
+            feature = "proto-nbns"
+        ),
+        feature = "socket-udp"
+    ),
+    all(feature = "proto-http", feature = "socket-tcp")
+))]
+pub mod apps;
 #[cfg(any(
     feature = "medium-ethernet",
     feature = "medium-ip",
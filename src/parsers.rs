@@ -338,7 +338,8 @@ impl<'a> Parser<'a> {
     fn accept_ipv6_endpoint(&mut self) -> Result<IpEndpoint> {
         if self.lookahead_char(b'[') {
             self.accept_char(b'[')?;
-            let ip = self.accept_ipv6(false)?;
+            // Like in a CIDR, the address may end with "::" before the bracket.
+            let ip = self.accept_ipv6(true)?;
             self.accept_char(b']')?;
             self.accept_char(b':')?;
             let port = self.accept_number(5, 65535, false)?;
@@ -737,6 +738,33 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_endpoint_ipv4_round_trip() {
+        for s in ["10.0.0.1:80", "127.0.0.1:0"] {
+            assert_eq!(IpEndpoint::from_str(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_endpoint_ipv6_round_trip() {
+        for s in ["[fe80::1]:80", "[::]:0"] {
+            assert_eq!(IpEndpoint::from_str(s).unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ethernet")]
+    fn test_mac_round_trip() {
+        let mac = EthernetAddress::from_str("aa:bb:cc:dd:ee:ff").unwrap();
+        assert_eq!(
+            mac,
+            EthernetAddress::new(0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff)
+        );
+        assert_eq!(EthernetAddress::from_str(&mac.to_string()), Ok(mac));
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv6")]
     fn test_endpoint_ipv6() {
@@ -0,0 +1,524 @@
+//! Driver for the Microchip ENC28J60 Ethernet controller.
+//!
+//! Register accesses follow the ENC28J60 data sheet (DS39662), and several steps below work
+//! around its silicon errata (DS80349).
+
+use crate::phy::{EthernetController, SpiBus};
+use crate::wire::EthernetAddress;
+use crate::{Error, Result};
+
+mod opcode {
+    pub const READ_CONTROL: u8 = 0x00;
+    pub const READ_BUFFER: u8 = 0x3a;
+    pub const WRITE_CONTROL: u8 = 0x40;
+    pub const WRITE_BUFFER: u8 = 0x7a;
+    pub const BIT_SET: u8 = 0x80;
+    pub const BIT_CLEAR: u8 = 0xa0;
+    pub const SOFT_RESET: u8 = 0xff;
+}
+
+/// A control register, as its bank, its address, and whether it is a MAC or MII register,
+/// which are read with a dummy octet.
+#[derive(Debug, Clone, Copy)]
+struct Register(u8, u8, bool);
+
+mod reg {
+    use super::Register;
+
+    // Registers present in all banks; their bank is ignored.
+    pub const EIR: Register = Register(0, 0x1c, false);
+    pub const ESTAT: Register = Register(0, 0x1d, false);
+    pub const ECON2: Register = Register(0, 0x1e, false);
+    pub const ECON1: Register = Register(0, 0x1f, false);
+
+    pub const ERDPTL: Register = Register(0, 0x00, false);
+    pub const EWRPTL: Register = Register(0, 0x02, false);
+    pub const ETXSTL: Register = Register(0, 0x04, false);
+    pub const ETXNDL: Register = Register(0, 0x06, false);
+    pub const ERXSTL: Register = Register(0, 0x08, false);
+    pub const ERXNDL: Register = Register(0, 0x0a, false);
+    pub const ERXRDPTL: Register = Register(0, 0x0c, false);
+
+    pub const ERXFCON: Register = Register(1, 0x18, false);
+    pub const EPKTCNT: Register = Register(1, 0x19, false);
+
+    pub const MACON1: Register = Register(2, 0x00, true);
+    pub const MACON3: Register = Register(2, 0x02, true);
+    pub const MACON4: Register = Register(2, 0x03, true);
+    pub const MABBIPG: Register = Register(2, 0x04, true);
+    pub const MAIPGL: Register = Register(2, 0x06, true);
+    pub const MAMXFLL: Register = Register(2, 0x0a, true);
+    pub const MICMD: Register = Register(2, 0x12, true);
+    pub const MIREGADR: Register = Register(2, 0x14, true);
+    pub const MIWRL: Register = Register(2, 0x16, true);
+    pub const MIRDL: Register = Register(2, 0x18, true);
+
+    pub const MAADR5: Register = Register(3, 0x00, true);
+    pub const MISTAT: Register = Register(3, 0x0a, true);
+}
+
+mod phy_reg {
+    pub const PHCON2: u8 = 0x10;
+    pub const PHSTAT2: u8 = 0x11;
+}
+
+mod bits {
+    pub const ECON1_TXRST: u8 = 0x80;
+    pub const ECON1_TXRTS: u8 = 0x08;
+    pub const ECON1_RXEN: u8 = 0x04;
+    pub const ECON1_BSEL: u8 = 0x03;
+    pub const ECON2_AUTOINC: u8 = 0x80;
+    pub const ECON2_PKTDEC: u8 = 0x40;
+    pub const ESTAT_CLKRDY: u8 = 0x01;
+    pub const EIR_TXERIF: u8 = 0x02;
+    pub const EIR_TXIF: u8 = 0x08;
+    pub const ERXFCON_UCEN: u8 = 0x80;
+    pub const ERXFCON_CRCEN: u8 = 0x20;
+    pub const ERXFCON_MCEN: u8 = 0x02;
+    pub const ERXFCON_BCEN: u8 = 0x01;
+    pub const MACON1_TXPAUS: u8 = 0x08;
+    pub const MACON1_RXPAUS: u8 = 0x04;
+    pub const MACON1_MARXEN: u8 = 0x01;
+    pub const MACON3_PADCFG0: u8 = 0x20;
+    pub const MACON3_TXCRCEN: u8 = 0x10;
+    pub const MACON3_FRMLNEN: u8 = 0x02;
+    pub const MACON4_DEFER: u8 = 0x40;
+    pub const MICMD_MIIRD: u8 = 0x01;
+    pub const MISTAT_BUSY: u8 = 0x01;
+    pub const PHCON2_HDLDIS: u16 = 0x0100;
+    pub const PHSTAT2_LSTAT: u16 = 0x0400;
+    pub const RSV_RXOK: u8 = 0x80;
+}
+
+// The 8 KiB of buffer memory are split between the receive ring, which must start at
+// address zero (errata 5), and room for a single transmitted frame with its control octet
+// and transmit status vector.
+const RX_START: u16 = 0x0000;
+const RX_END: u16 = 0x19ff;
+const TX_START: u16 = 0x1a00;
+
+const MAX_FRAME_LEN: usize = 1514;
+// How many times to poll a status bit before giving up.
+const POLL_LIMIT: usize = 1000;
+
+/// A driver for Microchip ENC28J60 Ethernet controllers.
+///
+/// The controller is configured for half duplex operation, and receives unicast frames
+/// addressed to it, as well as multicast and broadcast frames. Frames with an invalid
+/// frame check sequence are discarded by the controller.
+pub struct Enc28j60<S: SpiBus> {
+    spi: S,
+    bank: u8,
+    next_packet: u16,
+}
+
+impl<S: SpiBus> Enc28j60<S> {
+    /// Reset and initialize the controller, with the hardware address `hardware_addr`.
+    ///
+    /// Returns `Err(Error::Illegal)` if the controller does not respond.
+    pub fn new(spi: S, hardware_addr: EthernetAddress) -> Result<Enc28j60<S>> {
+        let mut enc = Enc28j60 {
+            spi,
+            bank: 0,
+            next_packet: RX_START,
+        };
+        enc.spi.write(&[opcode::SOFT_RESET], &[])?;
+        enc.poll(reg::ESTAT, bits::ESTAT_CLKRDY, bits::ESTAT_CLKRDY)?;
+
+        enc.write_u16(reg::ERXSTL, RX_START)?;
+        enc.write_u16(reg::ERXNDL, RX_END)?;
+        enc.write_u16(reg::ERDPTL, RX_START)?;
+        enc.write_u16(reg::ERXRDPTL, RX_END)?;
+        enc.write_u16(reg::ETXSTL, TX_START)?;
+        enc.bit_set(reg::ECON2, bits::ECON2_AUTOINC)?;
+        enc.write(
+            reg::ERXFCON,
+            bits::ERXFCON_UCEN | bits::ERXFCON_CRCEN | bits::ERXFCON_MCEN | bits::ERXFCON_BCEN,
+        )?;
+
+        enc.write(
+            reg::MACON1,
+            bits::MACON1_MARXEN | bits::MACON1_TXPAUS | bits::MACON1_RXPAUS,
+        )?;
+        enc.write(
+            reg::MACON3,
+            bits::MACON3_PADCFG0 | bits::MACON3_TXCRCEN | bits::MACON3_FRMLNEN,
+        )?;
+        enc.write(reg::MACON4, bits::MACON4_DEFER)?;
+        enc.write_u16(reg::MAMXFLL, MAX_FRAME_LEN as u16 + 4)?;
+        enc.write(reg::MABBIPG, 0x12)?;
+        enc.write_u16(reg::MAIPGL, 0x0c12)?;
+        // Registers MAADR1 to MAADR6 are in the order 5, 6, 3, 4, 1, 2.
+        let addr = hardware_addr.as_bytes();
+        for (i, &index) in [4, 5, 2, 3, 0, 1].iter().enumerate() {
+            let Register(bank, address, mac) = reg::MAADR5;
+            enc.write(Register(bank, address + i as u8, mac), addr[index])?;
+        }
+        // Do not loop transmitted frames back in half duplex mode.
+        enc.write_phy(phy_reg::PHCON2, bits::PHCON2_HDLDIS)?;
+
+        enc.bit_set(reg::ECON1, bits::ECON1_RXEN)?;
+        Ok(enc)
+    }
+
+    /// Get a reference to the SPI device.
+    pub fn get_ref(&self) -> &S {
+        &self.spi
+    }
+
+    /// Get a mutable reference to the SPI device.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.spi
+    }
+
+    /// Return whether the link is up.
+    pub fn link_up(&mut self) -> Result<bool> {
+        Ok(self.read_phy(phy_reg::PHSTAT2)? & bits::PHSTAT2_LSTAT != 0)
+    }
+
+    fn select_bank(&mut self, register: Register) -> Result<()> {
+        let Register(bank, address, _) = register;
+        if address >= 0x1b || bank == self.bank {
+            return Ok(());
+        }
+        self.spi
+            .write(&[opcode::BIT_CLEAR | reg::ECON1.1, bits::ECON1_BSEL], &[])?;
+        self.spi
+            .write(&[opcode::BIT_SET | reg::ECON1.1, bank], &[])?;
+        self.bank = bank;
+        Ok(())
+    }
+
+    fn read(&mut self, register: Register) -> Result<u8> {
+        self.select_bank(register)?;
+        let Register(_, address, mac) = register;
+        let mut buffer = [0; 2];
+        let len = if mac { 2 } else { 1 };
+        self.spi
+            .read(&[opcode::READ_CONTROL | address], &mut buffer[..len])?;
+        Ok(buffer[len - 1])
+    }
+
+    fn write(&mut self, register: Register, value: u8) -> Result<()> {
+        self.select_bank(register)?;
+        self.spi
+            .write(&[opcode::WRITE_CONTROL | register.1, value], &[])
+    }
+
+    /// Write a pair of registers holding a little-endian value.
+    fn write_u16(&mut self, register: Register, value: u16) -> Result<()> {
+        let Register(bank, address, mac) = register;
+        let [low, high] = value.to_le_bytes();
+        self.write(register, low)?;
+        self.write(Register(bank, address + 1, mac), high)
+    }
+
+    fn bit_set(&mut self, register: Register, bits: u8) -> Result<()> {
+        self.select_bank(register)?;
+        self.spi.write(&[opcode::BIT_SET | register.1, bits], &[])
+    }
+
+    fn bit_clear(&mut self, register: Register, bits: u8) -> Result<()> {
+        self.select_bank(register)?;
+        self.spi.write(&[opcode::BIT_CLEAR | register.1, bits], &[])
+    }
+
+    /// Wait until the bits of `register` selected by `mask` are equal to `value`.
+    fn poll(&mut self, register: Register, mask: u8, value: u8) -> Result<()> {
+        for _ in 0..POLL_LIMIT {
+            if self.read(register)? & mask == value {
+                return Ok(());
+            }
+        }
+        net_debug!("enc28j60: timed out");
+        Err(Error::Illegal)
+    }
+
+    fn read_phy(&mut self, register: u8) -> Result<u16> {
+        self.write(reg::MIREGADR, register)?;
+        self.write(reg::MICMD, bits::MICMD_MIIRD)?;
+        self.poll(reg::MISTAT, bits::MISTAT_BUSY, 0)?;
+        self.write(reg::MICMD, 0)?;
+        let Register(bank, address, mac) = reg::MIRDL;
+        let low = self.read(reg::MIRDL)?;
+        let high = self.read(Register(bank, address + 1, mac))?;
+        Ok(u16::from_le_bytes([low, high]))
+    }
+
+    fn write_phy(&mut self, register: u8, value: u16) -> Result<()> {
+        let Register(bank, address, mac) = reg::MIWRL;
+        let [low, high] = value.to_le_bytes();
+        self.write(reg::MIREGADR, register)?;
+        self.write(reg::MIWRL, low)?;
+        // Writing the high octet starts the MII transaction.
+        self.write(Register(bank, address + 1, mac), high)?;
+        self.poll(reg::MISTAT, bits::MISTAT_BUSY, 0)
+    }
+}
+
+impl<S: SpiBus> EthernetController for Enc28j60<S> {
+    fn max_frame_len(&self) -> usize {
+        MAX_FRAME_LEN
+    }
+
+    fn receive_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>> {
+        if self.read(reg::EPKTCNT)? == 0 {
+            return Ok(None);
+        }
+
+        // Each frame is preceded by the address of the next one and a receive status vector.
+        self.write_u16(reg::ERDPTL, self.next_packet)?;
+        let mut header = [0; 6];
+        self.spi.read(&[opcode::READ_BUFFER], &mut header)?;
+        let next_packet = u16::from_le_bytes([header[0], header[1]]);
+        let len = usize::from(u16::from_le_bytes([header[2], header[3]])).saturating_sub(4);
+        let received_ok = header[4] & bits::RSV_RXOK != 0;
+
+        let result = if !received_ok || len > buffer.len() {
+            net_debug!("enc28j60: discarding frame of {} octets", len);
+            None
+        } else {
+            self.spi.read(&[opcode::READ_BUFFER], &mut buffer[..len])?;
+            Some(len)
+        };
+
+        // Free the memory of the frame. The read pointer must be odd (errata 14).
+        self.next_packet = next_packet;
+        let read_pointer = if next_packet == RX_START {
+            RX_END
+        } else {
+            next_packet - 1
+        };
+        self.write_u16(reg::ERXRDPTL, read_pointer)?;
+        self.bit_set(reg::ECON2, bits::ECON2_PKTDEC)?;
+        Ok(result)
+    }
+
+    fn can_transmit(&mut self) -> Result<bool> {
+        Ok(self.read(reg::ECON1)? & bits::ECON1_TXRTS == 0)
+    }
+
+    fn transmit_frame(&mut self, frame: &[u8]) -> Result<()> {
+        if frame.len() > MAX_FRAME_LEN {
+            return Err(Error::Exhausted);
+        }
+        // Reset the transmit logic, which may stall after an error (errata 12).
+        self.bit_set(reg::ECON1, bits::ECON1_TXRST)?;
+        self.bit_clear(reg::ECON1, bits::ECON1_TXRST)?;
+        self.bit_clear(reg::EIR, bits::EIR_TXIF | bits::EIR_TXERIF)?;
+
+        // The frame is preceded by a control octet; zero uses the settings of MACON3.
+        self.write_u16(reg::EWRPTL, TX_START)?;
+        self.spi.write(&[opcode::WRITE_BUFFER, 0], frame)?;
+        self.write_u16(reg::ETXNDL, TX_START + frame.len() as u16)?;
+        self.bit_set(reg::ECON1, bits::ECON1_TXRTS)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::{Device, RxToken, SpiEthernet, TxToken};
+    use crate::time::Instant;
+    use std::vec::Vec;
+
+    const HW_ADDR: EthernetAddress = EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+
+    /// A simulation of the parts of an ENC28J60 used by the driver.
+    struct MockEnc28j60 {
+        banks: [[u8; 32]; 4],
+        phy: [u16; 32],
+        memory: [u8; 8192],
+        /// Where the next received frame is written.
+        rx_write: u16,
+        transmitted: Vec<Vec<u8>>,
+    }
+
+    impl MockEnc28j60 {
+        fn new() -> MockEnc28j60 {
+            MockEnc28j60 {
+                banks: [[0; 32]; 4],
+                phy: [0; 32],
+                memory: [0; 8192],
+                rx_write: RX_START,
+                transmitted: Vec::new(),
+            }
+        }
+
+        fn register(&mut self, address: u8) -> &mut u8 {
+            let bank = if address >= 0x1b {
+                0
+            } else {
+                self.banks[0][0x1f] & 0x03
+            };
+            &mut self.banks[bank as usize][address as usize]
+        }
+
+        fn get_u16(&self, bank: usize, address: usize) -> u16 {
+            u16::from_le_bytes([self.banks[bank][address], self.banks[bank][address + 1]])
+        }
+
+        fn set_u16(&mut self, bank: usize, address: usize, value: u16) {
+            self.banks[bank][address..address + 2].copy_from_slice(&value.to_le_bytes());
+        }
+
+        /// Write a received frame and its header into the receive ring.
+        fn inject(&mut self, frame: &[u8], ok: bool) {
+            let mut data = Vec::new();
+            let len = frame.len() + 4;
+            // Frames start at even addresses.
+            let next = RX_START + ((self.rx_write - RX_START + 6 + len as u16 + 1) & !1);
+            let next = if next > RX_END {
+                next - (RX_END + 1 - RX_START)
+            } else {
+                next
+            };
+            data.extend_from_slice(&next.to_le_bytes());
+            data.extend_from_slice(&(len as u16).to_le_bytes());
+            data.extend_from_slice(&[if ok { 0x80 } else { 0 }, 0]);
+            data.extend_from_slice(frame);
+            data.extend_from_slice(&[0; 4]);
+            let mut address = self.rx_write;
+            for &octet in data.iter() {
+                self.memory[address as usize] = octet;
+                address = if address == RX_END {
+                    RX_START
+                } else {
+                    address + 1
+                };
+            }
+            self.rx_write = next;
+            self.banks[1][0x19] += 1;
+        }
+    }
+
+    impl SpiBus for MockEnc28j60 {
+        fn read(&mut self, header: &[u8], buffer: &mut [u8]) -> Result<()> {
+            match header[0] {
+                opcode::READ_BUFFER => {
+                    let mut pointer = self.get_u16(0, 0x00);
+                    for octet in buffer.iter_mut() {
+                        *octet = self.memory[pointer as usize];
+                        pointer = if pointer == RX_END {
+                            RX_START
+                        } else {
+                            pointer + 1
+                        };
+                    }
+                    self.set_u16(0, 0x00, pointer);
+                }
+                op if op & 0xe0 == opcode::READ_CONTROL => {
+                    let value = *self.register(op & 0x1f);
+                    *buffer.last_mut().unwrap() = value;
+                }
+                _ => unreachable!(),
+            }
+            Ok(())
+        }
+
+        fn write(&mut self, header: &[u8], data: &[u8]) -> Result<()> {
+            match header[0] {
+                opcode::SOFT_RESET => {
+                    *self = MockEnc28j60::new();
+                    self.banks[0][0x1d] = bits::ESTAT_CLKRDY;
+                }
+                opcode::WRITE_BUFFER => {
+                    let pointer = self.get_u16(0, 0x02) as usize;
+                    let data = [&header[1..], data].concat();
+                    self.memory[pointer..pointer + data.len()].copy_from_slice(&data);
+                    self.set_u16(0, 0x02, (pointer + data.len()) as u16);
+                }
+                op => {
+                    let address = op & 0x1f;
+                    let value = header[1];
+                    match op & 0xe0 {
+                        opcode::WRITE_CONTROL => *self.register(address) = value,
+                        opcode::BIT_SET => *self.register(address) |= value,
+                        opcode::BIT_CLEAR => *self.register(address) &= !value,
+                        _ => unreachable!(),
+                    }
+                    if self.banks[0][0x1e] & bits::ECON2_PKTDEC != 0 {
+                        self.banks[0][0x1e] &= !bits::ECON2_PKTDEC;
+                        self.banks[1][0x19] -= 1;
+                    }
+                    if self.banks[0][0x1f] & bits::ECON1_TXRTS != 0 {
+                        let start = self.get_u16(0, 0x04) as usize;
+                        let end = self.get_u16(0, 0x06) as usize;
+                        self.transmitted.push(self.memory[start + 1..=end].to_vec());
+                        self.banks[0][0x1f] &= !bits::ECON1_TXRTS;
+                    }
+                    let (bank, address) = (self.banks[0][0x1f] & 0x03, address as usize);
+                    if bank == 2 && address == 0x12 && value & bits::MICMD_MIIRD != 0 {
+                        let value = self.phy[self.banks[2][0x14] as usize];
+                        self.set_u16(2, 0x18, value);
+                    }
+                    if bank == 2 && address == 0x17 {
+                        let value = self.get_u16(2, 0x16);
+                        self.phy[self.banks[2][0x14] as usize] = value;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_init() {
+        let mut enc = Enc28j60::new(MockEnc28j60::new(), HW_ADDR).unwrap();
+        let mock = enc.get_ref();
+        assert_eq!(mock.banks[0][0x1f] & bits::ECON1_RXEN, bits::ECON1_RXEN);
+        assert_eq!(mock.get_u16(0, 0x08), RX_START);
+        assert_eq!(mock.get_u16(0, 0x0a), RX_END);
+        assert_eq!(mock.get_u16(0, 0x0c) % 2, 1);
+        assert_eq!(&mock.banks[3][0..6], &[0x00, 0x01, 0x00, 0x00, 0x02, 0x00]);
+        assert_eq!(mock.phy[phy_reg::PHCON2 as usize], bits::PHCON2_HDLDIS);
+
+        assert_eq!(enc.link_up(), Ok(false));
+        enc.get_mut().phy[phy_reg::PHSTAT2 as usize] = bits::PHSTAT2_LSTAT;
+        assert_eq!(enc.link_up(), Ok(true));
+    }
+
+    #[test]
+    fn test_receive() {
+        let mut enc = Enc28j60::new(MockEnc28j60::new(), HW_ADDR).unwrap();
+        let mut buffer = [0; 1514];
+        assert_eq!(enc.receive_frame(&mut buffer), Ok(None));
+
+        // Fill the ring past its end, so that frames wrap around.
+        for round in 0..10u8 {
+            let frame = [round; 1001];
+            enc.get_mut().inject(&frame, true);
+            enc.get_mut().inject(&[0xff; 60], false);
+            enc.get_mut().inject(&[0xee; 2000], true);
+            assert_eq!(enc.receive_frame(&mut buffer), Ok(Some(1001)));
+            assert_eq!(&buffer[..1001], &frame[..]);
+            assert_eq!(enc.receive_frame(&mut buffer), Ok(None));
+            assert_eq!(enc.receive_frame(&mut buffer), Ok(None));
+            assert_eq!(enc.get_ref().banks[1][0x19], 0);
+            assert_eq!(enc.get_ref().get_u16(0, 0x0c) % 2, 1);
+        }
+    }
+
+    #[test]
+    fn test_device() {
+        let enc = Enc28j60::new(MockEnc28j60::new(), HW_ADDR).unwrap();
+        let (mut rx_buffer, mut tx_buffer) = ([0; 1514], [0; 1514]);
+        let mut device = SpiEthernet::new(enc, &mut rx_buffer, &mut tx_buffer);
+        assert_eq!(device.capabilities().max_transmission_unit, 1514);
+        assert!(device.receive().is_none());
+
+        device.get_mut().get_mut().inject(&[0x42; 64], true);
+        let (rx, tx) = device.receive().unwrap();
+        rx.consume(Instant::ZERO, |buf| {
+            assert_eq!(buf, &[0x42; 64][..]);
+            Ok(())
+        })
+        .unwrap();
+        tx.consume(Instant::ZERO, 60, |buf| {
+            buf.fill(0x24);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(device.get_ref().get_ref().transmitted, [vec![0x24; 60]]);
+    }
+}
@@ -1,6 +1,6 @@
 use core::cell::RefCell;
 
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, LinkState};
 use crate::time::{Duration, Instant};
 use crate::{Error, Result};
 
@@ -207,9 +207,15 @@ where
         if caps.max_transmission_unit > MTU {
             caps.max_transmission_unit = MTU;
         }
+        // Dropped packets are built in a buffer of the MTU.
+        caps.tcp_segmentation_offload = None;
         caps
     }
 
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let &mut Self {
             ref mut inner,
@@ -295,6 +301,15 @@ impl<'a, Rx: phy::RxToken> phy::RxToken for RxToken<'a, Rx> {
             }
         })
     }
+
+    fn meta(&self) -> phy::PacketMeta {
+        let mut meta = self.token.meta();
+        if self.config.corrupt_pct > 0 {
+            // A corrupted packet no longer has the checksums the device has verified.
+            meta.checksum_verified = false;
+        }
+        meta
+    }
 }
 
 #[doc(hidden)]
@@ -0,0 +1,241 @@
+use heapless::spsc::{Consumer, Producer, Queue};
+use heapless::Vec;
+
+use crate::phy::{self, Device, DeviceCapabilities, LinkState};
+use crate::time::Instant;
+use crate::{Error, Result};
+
+/// A bounded queue of received frames, filled from an interrupt handler.
+///
+/// The queue is a lock-free single-producer, single-consumer queue that holds up to
+/// `N - 1` frames of at most `MTU` bytes each, without heap allocation. It is meant to be
+/// placed in a `static`, and [split] into a [FrameProducer], which the interrupt handler of
+/// a network driver pushes received frames into, and a [FrameConsumer], which a
+/// [FrameInjector] drains when the interface is polled.
+///
+/// [split]: #method.split
+#[derive(Debug)]
+pub struct FrameQueue<const MTU: usize, const N: usize> {
+    queue: Queue<Vec<u8, MTU>, N>,
+}
+
+impl<const MTU: usize, const N: usize> FrameQueue<MTU, N> {
+    /// Create an empty frame queue.
+    pub const fn new() -> Self {
+        FrameQueue {
+            queue: Queue::new(),
+        }
+    }
+
+    /// Split the queue into its producing and consuming ends.
+    pub fn split(&mut self) -> (FrameProducer<'_, MTU, N>, FrameConsumer<'_, MTU, N>) {
+        let (producer, consumer) = self.queue.split();
+        (FrameProducer { producer }, FrameConsumer { consumer })
+    }
+}
+
+impl<const MTU: usize, const N: usize> Default for FrameQueue<MTU, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The producing end of a [FrameQueue].
+pub struct FrameProducer<'q, const MTU: usize, const N: usize> {
+    producer: Producer<'q, Vec<u8, MTU>, N>,
+}
+
+impl<'q, const MTU: usize, const N: usize> FrameProducer<'q, MTU, N> {
+    /// Push a received frame into the queue.
+    ///
+    /// # Errors
+    /// Returns `Err(Error::Truncated)` if the frame is longer than `MTU` bytes, and
+    /// `Err(Error::Exhausted)` if the queue is full. The frame is dropped in both cases.
+    pub fn push(&mut self, frame: &[u8]) -> Result<()> {
+        let frame = Vec::from_slice(frame).map_err(|()| Error::Truncated)?;
+        self.producer.enqueue(frame).map_err(|_| Error::Exhausted)
+    }
+
+    /// Query whether a frame can be pushed into the queue.
+    pub fn is_ready(&self) -> bool {
+        self.producer.ready()
+    }
+}
+
+/// The consuming end of a [FrameQueue].
+pub struct FrameConsumer<'q, const MTU: usize, const N: usize> {
+    consumer: Consumer<'q, Vec<u8, MTU>, N>,
+}
+
+impl<'q, const MTU: usize, const N: usize> FrameConsumer<'q, MTU, N> {
+    /// Return the number of frames in the queue.
+    pub fn len(&self) -> usize {
+        self.consumer.len()
+    }
+
+    /// Query whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        !self.consumer.ready()
+    }
+}
+
+/// A device that receives the frames pushed into a [FrameQueue].
+///
+/// The injector passes the frames of the queue to the interface before the frames
+/// received by the underlying device, and transmits through the underlying device.
+/// This decouples the interrupt handler of a driver, which only copies frames into the
+/// queue, from the processing of the frames by the stack, which happens during
+/// [poll](crate::iface::Interface::poll).
+pub struct FrameInjector<'q, D: for<'a> Device<'a>, const MTU: usize, const N: usize> {
+    inner: D,
+    queue: FrameConsumer<'q, MTU, N>,
+}
+
+impl<'q, D: for<'a> Device<'a>, const MTU: usize, const N: usize> FrameInjector<'q, D, MTU, N> {
+    /// Create a frame injector device, draining the given queue.
+    pub fn new(inner: D, queue: FrameConsumer<'q, MTU, N>) -> Self {
+        FrameInjector { inner, queue }
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Get a reference to the consuming end of the queue.
+    pub fn queue(&self) -> &FrameConsumer<'q, MTU, N> {
+        &self.queue
+    }
+
+    /// Return the underlying device and the consuming end of the queue, consuming
+    /// the injector.
+    pub fn into_inner(self) -> (D, FrameConsumer<'q, MTU, N>) {
+        (self.inner, self.queue)
+    }
+}
+
+impl<'a, 'q, D, const MTU: usize, const N: usize> Device<'a> for FrameInjector<'q, D, MTU, N>
+where
+    D: for<'b> Device<'b>,
+{
+    type RxToken = RxToken<<D as Device<'a>>::RxToken, MTU>;
+    type TxToken = <D as Device<'a>>::TxToken;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let Self { inner, queue } = self;
+        if queue.is_empty() {
+            return inner
+                .receive()
+                .map(|(rx_token, tx_token)| (RxToken::Inner(rx_token), tx_token));
+        }
+        // Leave the frame in the queue until it can be answered.
+        let tx_token = inner.transmit()?;
+        let frame = queue.consumer.dequeue()?;
+        Some((RxToken::Injected(frame), tx_token))
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        self.inner.transmit()
+    }
+}
+
+#[doc(hidden)]
+pub enum RxToken<Rx: phy::RxToken, const MTU: usize> {
+    Injected(Vec<u8, MTU>),
+    Inner(Rx),
+}
+
+impl<Rx: phy::RxToken, const MTU: usize> phy::RxToken for RxToken<Rx, MTU> {
+    fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        match self {
+            RxToken::Injected(mut frame) => f(&mut frame),
+            RxToken::Inner(token) => token.consume(timestamp, f),
+        }
+    }
+
+    fn meta(&self) -> phy::PacketMeta {
+        match self {
+            RxToken::Injected(_) => phy::PacketMeta::default(),
+            RxToken::Inner(token) => token.meta(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    ))]
+    use crate::phy::{Loopback, Medium, RxToken, TxToken};
+
+    #[test]
+    fn test_push() {
+        let mut queue = FrameQueue::<4, 3>::new();
+        let (mut producer, consumer) = queue.split();
+        assert!(consumer.is_empty());
+
+        assert_eq!(producer.push(&[1, 2, 3, 4, 5]), Err(Error::Truncated));
+        assert_eq!(producer.push(&[1, 2]), Ok(()));
+        assert_eq!(producer.push(&[3]), Ok(()));
+        assert!(!producer.is_ready());
+        assert_eq!(producer.push(&[4]), Err(Error::Exhausted));
+        assert_eq!(consumer.len(), 2);
+    }
+
+    #[test]
+    #[cfg(any(
+        feature = "medium-ethernet",
+        feature = "medium-ip",
+        feature = "medium-ieee802154"
+    ))]
+    fn test_injector() {
+        let mut queue = FrameQueue::<16, 4>::new();
+        let (mut producer, consumer) = queue.split();
+        let mut device = FrameInjector::new(Loopback::new(Medium::default()), consumer);
+
+        // A frame received by the underlying device.
+        let tx_token = device.transmit().unwrap();
+        tx_token
+            .consume(Instant::ZERO, 1, |buf| {
+                buf[0] = 0xff;
+                Ok(())
+            })
+            .unwrap();
+        producer.push(b"first").unwrap();
+        producer.push(b"second").unwrap();
+
+        let mut received = std::vec::Vec::new();
+        while let Some((rx_token, _tx_token)) = device.receive() {
+            rx_token
+                .consume(Instant::ZERO, |buf| {
+                    received.push(buf.to_vec());
+                    Ok(())
+                })
+                .unwrap();
+        }
+        assert_eq!(
+            received,
+            [b"first".to_vec(), b"second".to_vec(), [0xff].to_vec()]
+        );
+        assert!(device.queue().is_empty());
+    }
+}
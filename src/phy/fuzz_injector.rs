@@ -1,4 +1,4 @@
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, LinkState};
 use crate::time::Instant;
 use crate::Result;
 
@@ -59,6 +59,10 @@ where
         caps
     }
 
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let &mut Self {
             ref mut inner,
@@ -108,6 +112,13 @@ impl<'a, Rx: phy::RxToken, FRx: Fuzzer> phy::RxToken for RxToken<'a, Rx, FRx> {
             f(buffer)
         })
     }
+
+    fn meta(&self) -> phy::PacketMeta {
+        let mut meta = self.token.meta();
+        // A fuzzed packet no longer has the checksums the device has verified.
+        meta.checksum_verified = false;
+        meta
+    }
 }
 
 #[doc(hidden)]
@@ -128,4 +139,8 @@ impl<'a, Tx: phy::TxToken, FTx: Fuzzer> phy::TxToken for TxToken<'a, Tx, FTx> {
             result
         })
     }
+
+    fn set_meta(&mut self, meta: phy::PacketMeta) {
+        self.token.set_meta(meta)
+    }
 }
@@ -0,0 +1,424 @@
+use crate::phy::{self, ChecksumCapabilities, Device, DeviceCapabilities, LinkState, Medium};
+use crate::time::Instant;
+use crate::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, GrePacket, GreRepr, IpProtocol, Ipv4Address,
+    Ipv4Packet, Ipv4Repr, ParseMode, ETHERNET_HEADER_LEN,
+};
+use crate::{Error, Result};
+
+/// Configuration of a GRE tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GreConfig {
+    /// Address of the local tunnel endpoint, on the underlying network.
+    pub local_addr: Ipv4Address,
+    /// Address of the remote tunnel endpoint, on the underlying network.
+    pub remote_addr: Ipv4Address,
+    /// Key included in transmitted packets, and required in received packets.
+    pub key: Option<u32>,
+    /// Whether to include sequence numbers in transmitted packets.
+    pub sequence: bool,
+    /// Whether to include checksums in transmitted packets.
+    pub checksum: bool,
+    /// Hop limit of the delivery header.
+    pub hop_limit: u8,
+    /// Local and next-hop hardware addresses, used when the underlying device
+    /// has an Ethernet medium. Address resolution is not performed on the underlying network.
+    pub hardware_addrs: Option<(EthernetAddress, EthernetAddress)>,
+}
+
+impl GreConfig {
+    /// Create a configuration for a tunnel between the given endpoints, without
+    /// any of the optional GRE fields.
+    pub fn new(local_addr: Ipv4Address, remote_addr: Ipv4Address) -> GreConfig {
+        GreConfig {
+            local_addr,
+            remote_addr,
+            key: None,
+            sequence: false,
+            checksum: false,
+            hop_limit: 64,
+            hardware_addrs: None,
+        }
+    }
+
+    fn gre_repr(&self, protocol_type: EthernetProtocol, sequence_number: u32) -> GreRepr {
+        GreRepr {
+            protocol_type,
+            checksum: self.checksum,
+            key: self.key,
+            sequence_number: if self.sequence {
+                Some(sequence_number)
+            } else {
+                None
+            },
+        }
+    }
+
+    fn link_header_len(&self, medium: Medium) -> usize {
+        match medium {
+            Medium::Ethernet => ETHERNET_HEADER_LEN,
+            _ => 0,
+        }
+    }
+}
+
+/// A GRE tunnel device.
+///
+/// A GRE tunnel is a virtual device with an IP medium, which encapsulates the
+/// IPv4 and IPv6 packets sent through it in GRE and IPv4 delivery headers, and
+/// transmits them to the remote endpoint using another device. Packets received
+/// from the remote endpoint are decapsulated; all other traffic arriving on the
+/// underlying device is dropped.
+///
+/// The underlying device must have an Ethernet or IP medium.
+pub struct GreTunnel<D: for<'a> Device<'a>> {
+    inner: D,
+    config: GreConfig,
+    sequence_number: u32,
+}
+
+impl<D: for<'a> Device<'a>> GreTunnel<D> {
+    /// Create a GRE tunnel device.
+    ///
+    /// # Panics
+    /// This function panics if the underlying device has an Ethernet medium, but
+    /// `config.hardware_addrs` is not set.
+    pub fn new(inner: D, config: GreConfig) -> GreTunnel<D> {
+        let medium = inner.capabilities().medium;
+        assert!(
+            medium != Medium::Ethernet || config.hardware_addrs.is_some(),
+            "hardware addresses are required for tunnels over Ethernet"
+        );
+        GreTunnel {
+            inner,
+            config,
+            sequence_number: 0,
+        }
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    ///
+    /// It is inadvisable to directly read from the device as doing so will circumvent the tunnel.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Return the underlying device, consuming the tunnel.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<'a, D> Device<'a> for GreTunnel<D>
+where
+    D: for<'b> Device<'b>,
+{
+    type RxToken = RxToken<<D as Device<'a>>::RxToken>;
+    type TxToken = TxToken<'a, <D as Device<'a>>::TxToken>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let inner = self.inner.capabilities();
+        let overhead = Ipv4Repr {
+            src_addr: self.config.local_addr,
+            dst_addr: self.config.remote_addr,
+            next_header: IpProtocol::Gre,
+            payload_len: 0,
+            hop_limit: self.config.hop_limit,
+        }
+        .buffer_len()
+            + self.config.gre_repr(EthernetProtocol::Ipv4, 0).buffer_len();
+
+        DeviceCapabilities {
+            medium: Medium::Ip,
+            max_transmission_unit: inner.ip_mtu().saturating_sub(overhead),
+            max_burst_size: inner.max_burst_size,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let &mut Self {
+            ref mut inner,
+            config,
+            ref mut sequence_number,
+        } = self;
+        let caps = inner.capabilities();
+        inner.receive().map(move |(rx_token, tx_token)| {
+            let rx = RxToken {
+                token: rx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum.clone(),
+            };
+            let tx = TxToken {
+                token: tx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum,
+                sequence_number,
+            };
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        let &mut Self {
+            ref mut inner,
+            config,
+            ref mut sequence_number,
+        } = self;
+        let caps = inner.capabilities();
+        inner.transmit().map(move |tx_token| TxToken {
+            token: tx_token,
+            config,
+            medium: caps.medium,
+            checksum: caps.checksum,
+            sequence_number,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<Rx: phy::RxToken> {
+    token: Rx,
+    config: GreConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+}
+
+impl<Rx: phy::RxToken> phy::RxToken for RxToken<Rx> {
+    fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+        } = self;
+        token.consume(timestamp, |buffer| {
+            let mut offset = 0;
+            if let (Medium::Ethernet, Some((local_hw_addr, _))) = (medium, config.hardware_addrs) {
+                let frame = EthernetFrame::new_checked(&buffer[..])?;
+                if frame.ethertype() != EthernetProtocol::Ipv4 || frame.dst_addr() != local_hw_addr
+                {
+                    return Err(Error::Unrecognized);
+                }
+                offset = ETHERNET_HEADER_LEN;
+            }
+
+            let ipv4_packet = Ipv4Packet::new_checked(&buffer[offset..])?;
+            let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, &checksum, ParseMode::Permissive)?;
+            if ipv4_repr.next_header != IpProtocol::Gre
+                || ipv4_repr.src_addr != config.remote_addr
+                || ipv4_repr.dst_addr != config.local_addr
+            {
+                return Err(Error::Unrecognized);
+            }
+            if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
+                return Err(Error::Fragmented);
+            }
+
+            let gre_packet = GrePacket::new_checked(ipv4_packet.payload())?;
+            let gre_repr = GreRepr::parse(&gre_packet)?;
+            if gre_repr.key != config.key {
+                return Err(Error::Unrecognized);
+            }
+            match gre_repr.protocol_type {
+                EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => (),
+                _ => return Err(Error::Unrecognized),
+            }
+
+            let start = offset + ipv4_packet.header_len() as usize + gre_packet.header_len();
+            let end = offset + ipv4_packet.total_len() as usize;
+            f(&mut buffer[start..end])
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<'a, Tx: phy::TxToken> {
+    token: Tx,
+    config: GreConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+    sequence_number: &'a mut u32,
+}
+
+impl<'a, Tx: phy::TxToken> phy::TxToken for TxToken<'a, Tx> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+            sequence_number,
+        } = self;
+
+        // The protocol type does not affect the header length.
+        let gre_len = config.gre_repr(EthernetProtocol::Ipv4, 0).buffer_len();
+        let ipv4_repr = Ipv4Repr {
+            src_addr: config.local_addr,
+            dst_addr: config.remote_addr,
+            next_header: IpProtocol::Gre,
+            payload_len: gre_len + len,
+            hop_limit: config.hop_limit,
+        };
+        let link_len = config.link_header_len(medium);
+        let total_len = link_len + ipv4_repr.buffer_len() + ipv4_repr.payload_len;
+
+        token.consume(timestamp, total_len, |buffer| {
+            let payload_start = link_len + ipv4_repr.buffer_len() + gre_len;
+            let result = f(&mut buffer[payload_start..])?;
+
+            let protocol_type = match buffer[payload_start] >> 4 {
+                4 => EthernetProtocol::Ipv4,
+                6 => EthernetProtocol::Ipv6,
+                _ => return Err(Error::Unrecognized),
+            };
+
+            if let Some((local_hw_addr, remote_hw_addr)) = config.hardware_addrs {
+                if medium == Medium::Ethernet {
+                    let mut frame = EthernetFrame::new_unchecked(&mut buffer[..]);
+                    frame.set_src_addr(local_hw_addr);
+                    frame.set_dst_addr(remote_hw_addr);
+                    frame.set_ethertype(EthernetProtocol::Ipv4);
+                }
+            }
+
+            let mut ipv4_packet = Ipv4Packet::new_unchecked(&mut buffer[link_len..]);
+            ipv4_repr.emit(&mut ipv4_packet, &checksum);
+
+            let gre_repr = config.gre_repr(protocol_type, *sequence_number);
+            let mut gre_packet = GrePacket::new_unchecked(ipv4_packet.payload_mut());
+            gre_repr.emit(&mut gre_packet);
+            if gre_repr.checksum {
+                gre_packet.fill_checksum();
+            }
+            if config.sequence {
+                *sequence_number = sequence_number.wrapping_add(1);
+            }
+
+            Ok(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::Loopback;
+    use crate::phy::{Device, RxToken, TxToken};
+    use crate::wire::Ipv4Address;
+
+    const LOCAL: Ipv4Address = Ipv4Address([192, 0, 2, 1]);
+    const REMOTE: Ipv4Address = Ipv4Address([192, 0, 2, 2]);
+
+    static INNER_PACKET: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x40, 0x00, 0x40, 0xfd, 0xe6, 0xe6, 0x0a, 0x00, 0x00,
+        0x01, 0x0a, 0x00, 0x00, 0x02,
+    ];
+
+    fn send(tunnel: &mut GreTunnel<Loopback>) {
+        let tx = tunnel.transmit().unwrap();
+        tx.consume(Instant::from_millis(0), INNER_PACKET.len(), |buf| {
+            buf.copy_from_slice(&INNER_PACKET);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn receive(tunnel: &mut GreTunnel<Loopback>) -> Result<Vec<u8>> {
+        let (rx, _tx) = tunnel.receive().unwrap();
+        rx.consume(Instant::from_millis(0), |buf| Ok(buf.to_vec()))
+    }
+
+    #[test]
+    fn test_encapsulate() {
+        let mut config = GreConfig::new(LOCAL, REMOTE);
+        config.key = Some(42);
+        config.sequence = true;
+        config.checksum = true;
+        let mut tunnel = GreTunnel::new(Loopback::new(Medium::Ip), config);
+        assert_eq!(tunnel.capabilities().medium, Medium::Ip);
+        assert_eq!(tunnel.capabilities().max_transmission_unit, 65535 - 20 - 16);
+
+        send(&mut tunnel);
+        send(&mut tunnel);
+
+        for expected_seq in 0..2 {
+            let (rx, _tx) = tunnel.get_mut().receive().unwrap();
+            rx.consume(Instant::from_millis(0), |buf| {
+                let ipv4_packet = Ipv4Packet::new_checked(&buf[..]).unwrap();
+                let ipv4_repr =
+                    Ipv4Repr::parse(&ipv4_packet, &Default::default(), ParseMode::Permissive)
+                        .unwrap();
+                assert_eq!(ipv4_repr.src_addr, LOCAL);
+                assert_eq!(ipv4_repr.dst_addr, REMOTE);
+                assert_eq!(ipv4_repr.next_header, IpProtocol::Gre);
+                let gre_packet = GrePacket::new_checked(ipv4_packet.payload()).unwrap();
+                assert_eq!(
+                    GreRepr::parse(&gre_packet),
+                    Ok(GreRepr {
+                        protocol_type: EthernetProtocol::Ipv4,
+                        checksum: true,
+                        key: Some(42),
+                        sequence_number: Some(expected_seq),
+                    })
+                );
+                assert_eq!(gre_packet.payload(), &INNER_PACKET[..]);
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_decapsulate() {
+        // Swap the endpoints, so that the looped back packets appear to come from the remote.
+        let mut sender = GreTunnel::new(Loopback::new(Medium::Ip), GreConfig::new(REMOTE, LOCAL));
+        send(&mut sender);
+        let mut tunnel = GreTunnel::new(sender.into_inner(), GreConfig::new(LOCAL, REMOTE));
+        assert_eq!(receive(&mut tunnel), Ok(INNER_PACKET.to_vec()));
+    }
+
+    #[test]
+    fn test_decapsulate_wrong_key() {
+        let mut config = GreConfig::new(REMOTE, LOCAL);
+        config.key = Some(1);
+        let mut sender = GreTunnel::new(Loopback::new(Medium::Ip), config);
+        send(&mut sender);
+        let mut tunnel = GreTunnel::new(sender.into_inner(), GreConfig::new(LOCAL, REMOTE));
+        assert_eq!(receive(&mut tunnel), Err(Error::Unrecognized));
+    }
+
+    #[test]
+    fn test_ethernet() {
+        let local_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 1]);
+        let remote_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 2]);
+
+        let mut config = GreConfig::new(REMOTE, LOCAL);
+        config.hardware_addrs = Some((remote_hw_addr, local_hw_addr));
+        let mut sender = GreTunnel::new(Loopback::new(Medium::Ethernet), config);
+        send(&mut sender);
+
+        let mut config = GreConfig::new(LOCAL, REMOTE);
+        config.hardware_addrs = Some((local_hw_addr, remote_hw_addr));
+        let mut tunnel = GreTunnel::new(sender.into_inner(), config);
+        assert_eq!(receive(&mut tunnel), Ok(INNER_PACKET.to_vec()));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::phy::{self, ChecksumCapabilities, Device, DeviceCapabilities, LinkState, Medium};
+use crate::time::Instant;
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::{EthernetAddress, EthernetFrame, EthernetProtocol, ETHERNET_HEADER_LEN};
+use crate::wire::{IpProtocol, Ipv4Address, Ipv4Packet, Ipv4Repr, ParseMode, IPV4_HEADER_LEN};
+use crate::{Error, Result};
+
+/// Configuration of an IP-in-IP tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IpTunnelConfig {
+    /// Address of the local tunnel endpoint, on the underlying network.
+    pub local_addr: Ipv4Address,
+    /// Address of the remote tunnel endpoint, on the underlying network.
+    pub remote_addr: Ipv4Address,
+    /// Hop limit of the delivery header.
+    pub hop_limit: u8,
+    /// Maximum transmission unit of the tunnel. If `None`, or if it exceeds what the
+    /// underlying device can carry, the largest MTU the underlying device can carry is used.
+    pub mtu: Option<usize>,
+    /// Local and next-hop hardware addresses, used when the underlying device
+    /// has an Ethernet medium. Address resolution is not performed on the underlying network.
+    #[cfg(feature = "medium-ethernet")]
+    pub hardware_addrs: Option<(EthernetAddress, EthernetAddress)>,
+}
+
+impl IpTunnelConfig {
+    /// Create a configuration for a tunnel between the given endpoints.
+    pub fn new(local_addr: Ipv4Address, remote_addr: Ipv4Address) -> IpTunnelConfig {
+        IpTunnelConfig {
+            local_addr,
+            remote_addr,
+            hop_limit: 64,
+            mtu: None,
+            #[cfg(feature = "medium-ethernet")]
+            hardware_addrs: None,
+        }
+    }
+
+    fn link_header_len(&self, medium: Medium) -> usize {
+        match medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => ETHERNET_HEADER_LEN,
+            _ => 0,
+        }
+    }
+}
+
+/// An IP-in-IP tunnel device.
+///
+/// An IP-in-IP tunnel is a virtual device with an IP medium, which encapsulates
+/// the packets sent through it in an IPv4 delivery header and transmits them to
+/// the remote endpoint using another device. IPv4 packets are carried as IP
+/// protocol 4 (IPIP, RFC 2003), and IPv6 packets as IP protocol 41 (6in4 or SIT,
+/// RFC 4213). Packets received from the remote endpoint are decapsulated;
+/// all other traffic arriving on the underlying device is dropped.
+///
+/// The underlying device must have an Ethernet or IP medium.
+pub struct IpTunnel<D: for<'a> Device<'a>> {
+    inner: D,
+    config: IpTunnelConfig,
+}
+
+impl<D: for<'a> Device<'a>> IpTunnel<D> {
+    /// Create an IP-in-IP tunnel device.
+    ///
+    /// # Panics
+    /// This function panics if the underlying device has an Ethernet medium, but
+    /// `config.hardware_addrs` is not set.
+    pub fn new(inner: D, config: IpTunnelConfig) -> IpTunnel<D> {
+        #[cfg(feature = "medium-ethernet")]
+        assert!(
+            inner.capabilities().medium != Medium::Ethernet || config.hardware_addrs.is_some(),
+            "hardware addresses are required for tunnels over Ethernet"
+        );
+        IpTunnel { inner, config }
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    ///
+    /// It is inadvisable to directly read from the device as doing so will circumvent the tunnel.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Return the underlying device, consuming the tunnel.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<'a, D> Device<'a> for IpTunnel<D>
+where
+    D: for<'b> Device<'b>,
+{
+    type RxToken = RxToken<<D as Device<'a>>::RxToken>;
+    type TxToken = TxToken<<D as Device<'a>>::TxToken>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let inner = self.inner.capabilities();
+        let max_mtu = inner.ip_mtu().saturating_sub(IPV4_HEADER_LEN);
+        DeviceCapabilities {
+            medium: Medium::Ip,
+            max_transmission_unit: self.config.mtu.map_or(max_mtu, |mtu| mtu.min(max_mtu)),
+            max_burst_size: inner.max_burst_size,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let &mut Self {
+            ref mut inner,
+            config,
+        } = self;
+        let caps = inner.capabilities();
+        inner.receive().map(move |(rx_token, tx_token)| {
+            let rx = RxToken {
+                token: rx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum.clone(),
+            };
+            let tx = TxToken {
+                token: tx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum,
+            };
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        let &mut Self {
+            ref mut inner,
+            config,
+        } = self;
+        let caps = inner.capabilities();
+        inner.transmit().map(move |tx_token| TxToken {
+            token: tx_token,
+            config,
+            medium: caps.medium,
+            checksum: caps.checksum,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<Rx: phy::RxToken> {
+    token: Rx,
+    config: IpTunnelConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+}
+
+impl<Rx: phy::RxToken> phy::RxToken for RxToken<Rx> {
+    fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+        } = self;
+        token.consume(timestamp, |buffer| {
+            let offset = config.link_header_len(medium);
+            #[cfg(feature = "medium-ethernet")]
+            if let (Medium::Ethernet, Some((local_hw_addr, _))) = (medium, config.hardware_addrs) {
+                let frame = EthernetFrame::new_checked(&buffer[..])?;
+                if frame.ethertype() != EthernetProtocol::Ipv4 || frame.dst_addr() != local_hw_addr
+                {
+                    return Err(Error::Unrecognized);
+                }
+            }
+
+            let ipv4_packet = Ipv4Packet::new_checked(&buffer[offset..])?;
+            let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, &checksum, ParseMode::Permissive)?;
+            if ipv4_repr.src_addr != config.remote_addr || ipv4_repr.dst_addr != config.local_addr {
+                return Err(Error::Unrecognized);
+            }
+            let version = match ipv4_repr.next_header {
+                IpProtocol::IpIp => 4,
+                IpProtocol::Ipv6 => 6,
+                _ => return Err(Error::Unrecognized),
+            };
+            if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
+                return Err(Error::Fragmented);
+            }
+
+            let start = offset + ipv4_packet.header_len() as usize;
+            let end = offset + ipv4_packet.total_len() as usize;
+            // The protocol number must agree with the version of the inner packet.
+            if start == end || buffer[start] >> 4 != version {
+                return Err(Error::Malformed);
+            }
+            f(&mut buffer[start..end])
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<Tx: phy::TxToken> {
+    token: Tx,
+    config: IpTunnelConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+}
+
+impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+        } = self;
+
+        let mut ipv4_repr = Ipv4Repr {
+            src_addr: config.local_addr,
+            dst_addr: config.remote_addr,
+            next_header: IpProtocol::IpIp,
+            payload_len: len,
+            hop_limit: config.hop_limit,
+        };
+        let link_len = config.link_header_len(medium);
+        let payload_start = link_len + ipv4_repr.buffer_len();
+
+        token.consume(timestamp, payload_start + len, |buffer| {
+            let result = f(&mut buffer[payload_start..])?;
+
+            ipv4_repr.next_header = match buffer.get(payload_start).map(|b| b >> 4) {
+                Some(4) => IpProtocol::IpIp,
+                Some(6) => IpProtocol::Ipv6,
+                _ => return Err(Error::Unrecognized),
+            };
+
+            #[cfg(feature = "medium-ethernet")]
+            if let (Medium::Ethernet, Some((local_hw_addr, remote_hw_addr))) =
+                (medium, config.hardware_addrs)
+            {
+                let mut frame = EthernetFrame::new_unchecked(&mut buffer[..]);
+                frame.set_src_addr(local_hw_addr);
+                frame.set_dst_addr(remote_hw_addr);
+                frame.set_ethertype(EthernetProtocol::Ipv4);
+            }
+
+            let mut ipv4_packet = Ipv4Packet::new_unchecked(&mut buffer[link_len..]);
+            ipv4_repr.emit(&mut ipv4_packet, &checksum);
+
+            Ok(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::Loopback;
+    use crate::phy::{Device, RxToken, TxToken};
+
+    const LOCAL: Ipv4Address = Ipv4Address([192, 0, 2, 1]);
+    const REMOTE: Ipv4Address = Ipv4Address([192, 0, 2, 2]);
+
+    static IPV4_PACKET: [u8; 20] = [
+        0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x40, 0x00, 0x40, 0xfd, 0xe6, 0xe6, 0x0a, 0x00, 0x00,
+        0x01, 0x0a, 0x00, 0x00, 0x02,
+    ];
+
+    static IPV6_PACKET: [u8; 40] = [
+        0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x40, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02,
+    ];
+
+    fn send(tunnel: &mut IpTunnel<Loopback>, packet: &[u8]) {
+        let tx = tunnel.transmit().unwrap();
+        tx.consume(Instant::from_millis(0), packet.len(), |buf| {
+            buf.copy_from_slice(packet);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn receive(tunnel: &mut IpTunnel<Loopback>) -> Result<Vec<u8>> {
+        let (rx, _tx) = tunnel.receive().unwrap();
+        rx.consume(Instant::from_millis(0), |buf| Ok(buf.to_vec()))
+    }
+
+    #[test]
+    fn test_mtu() {
+        let mut config = IpTunnelConfig::new(LOCAL, REMOTE);
+        let tunnel = IpTunnel::new(Loopback::new(Medium::Ip), config);
+        assert_eq!(tunnel.capabilities().max_transmission_unit, 65535 - 20);
+
+        config.mtu = Some(1280);
+        let tunnel = IpTunnel::new(tunnel.into_inner(), config);
+        assert_eq!(tunnel.capabilities().max_transmission_unit, 1280);
+
+        config.mtu = Some(100_000);
+        let tunnel = IpTunnel::new(tunnel.into_inner(), config);
+        assert_eq!(tunnel.capabilities().max_transmission_unit, 65535 - 20);
+    }
+
+    #[test]
+    fn test_encapsulate() {
+        let mut tunnel = IpTunnel::new(
+            Loopback::new(Medium::Ip),
+            IpTunnelConfig::new(LOCAL, REMOTE),
+        );
+        send(&mut tunnel, &IPV4_PACKET);
+        send(&mut tunnel, &IPV6_PACKET);
+
+        for (protocol, inner) in [
+            (IpProtocol::IpIp, &IPV4_PACKET[..]),
+            (IpProtocol::Ipv6, &IPV6_PACKET[..]),
+        ] {
+            let (rx, _tx) = tunnel.get_mut().receive().unwrap();
+            rx.consume(Instant::from_millis(0), |buf| {
+                let ipv4_packet = Ipv4Packet::new_checked(&buf[..]).unwrap();
+                let ipv4_repr =
+                    Ipv4Repr::parse(&ipv4_packet, &Default::default(), ParseMode::Permissive)
+                        .unwrap();
+                assert_eq!(ipv4_repr.src_addr, LOCAL);
+                assert_eq!(ipv4_repr.dst_addr, REMOTE);
+                assert_eq!(ipv4_repr.next_header, protocol);
+                assert_eq!(ipv4_packet.payload(), inner);
+                Ok(())
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_decapsulate() {
+        let mut sender = IpTunnel::new(
+            Loopback::new(Medium::Ip),
+            IpTunnelConfig::new(REMOTE, LOCAL),
+        );
+        send(&mut sender, &IPV6_PACKET);
+        send(&mut sender, &IPV4_PACKET);
+
+        let mut tunnel = IpTunnel::new(sender.into_inner(), IpTunnelConfig::new(LOCAL, REMOTE));
+        assert_eq!(receive(&mut tunnel), Ok(IPV6_PACKET.to_vec()));
+        assert_eq!(receive(&mut tunnel), Ok(IPV4_PACKET.to_vec()));
+    }
+
+    #[test]
+    fn test_decapsulate_wrong_endpoint() {
+        let mut sender = IpTunnel::new(
+            Loopback::new(Medium::Ip),
+            IpTunnelConfig::new(Ipv4Address([192, 0, 2, 3]), LOCAL),
+        );
+        send(&mut sender, &IPV4_PACKET);
+
+        let mut tunnel = IpTunnel::new(sender.into_inner(), IpTunnelConfig::new(LOCAL, REMOTE));
+        assert_eq!(receive(&mut tunnel), Err(Error::Unrecognized));
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ethernet")]
+    fn test_ethernet() {
+        let local_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 1]);
+        let remote_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 2]);
+
+        let mut config = IpTunnelConfig::new(REMOTE, LOCAL);
+        config.hardware_addrs = Some((remote_hw_addr, local_hw_addr));
+        let mut sender = IpTunnel::new(Loopback::new(Medium::Ethernet), config);
+        assert_eq!(sender.capabilities().max_transmission_unit, 65535 - 14 - 20);
+        send(&mut sender, &IPV6_PACKET);
+
+        let mut config = IpTunnelConfig::new(LOCAL, REMOTE);
+        config.hardware_addrs = Some((local_hw_addr, remote_hw_addr));
+        let mut tunnel = IpTunnel::new(sender.into_inner(), config);
+        assert_eq!(receive(&mut tunnel), Ok(IPV6_PACKET.to_vec()));
+    }
+}
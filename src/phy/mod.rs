@@ -7,9 +7,22 @@ and implementations of it:
   * the [_loopback_](struct.Loopback.html), for zero dependency testing;
   * _middleware_ [Tracer](struct.Tracer.html) and
     [FaultInjector](struct.FaultInjector.html), to facilitate debugging;
+  * _tunnels_ [GreTunnel](struct.GreTunnel.html) and [IpTunnel](struct.IpTunnel.html),
+    to carry IP packets over another device, and [VxlanTunnel](struct.VxlanTunnel.html),
+    to carry Ethernet frames over another device;
+  * the [VlanSwitch](struct.VlanSwitch.html), to split a device into 802.1Q VLANs;
   * _adapters_ [RawSocket](struct.RawSocket.html) and
     [TunTapInterface](struct.TunTapInterface.html), to transmit and receive frames
-    on the host OS.
+    on the host OS, and [PcapInterface](struct.PcapInterface.html), to do so through
+    libpcap;
+  * the [XdpSocket](struct.XdpSocket.html) adapter, to transmit and receive frames
+    on Linux without copying them through the kernel network stack;
+  * the [UsbEthernet](struct.UsbEthernet.html) gadget, to transmit and receive frames
+    over the bulk endpoints of a USB CDC-ECM or CDC-NCM function, and the
+    [Rndis](struct.Rndis.html) gadget, for RNDIS functions;
+  * the [SpiEthernet](struct.SpiEthernet.html) adapter, to transmit and receive frames
+    through Ethernet controllers attached over SPI, such as the
+    [Enc28j60](struct.Enc28j60.html).
 */
 #![cfg_attr(
     feature = "medium-ethernet",
@@ -92,45 +105,108 @@ impl<'a> phy::TxToken for StmPhyTxToken<'a> {
 use crate::time::Instant;
 use crate::Result;
 
-#[cfg(all(
-    any(feature = "phy-raw_socket", feature = "phy-tuntap_interface"),
-    unix
+#[cfg(any(
+    all(
+        any(
+            feature = "phy-raw_socket",
+            feature = "phy-tuntap_interface",
+            feature = "phy-pcap"
+        ),
+        unix
+    ),
+    all(feature = "phy-pcap", windows),
+    all(feature = "phy-xdp_socket", target_os = "linux")
 ))]
 mod sys;
 
+#[cfg(feature = "phy-spi_ethernet")]
+mod enc28j60;
 mod fault_injector;
+mod frame_queue;
 mod fuzz_injector;
+#[cfg(feature = "proto-gre")]
+mod gre_tunnel;
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ip"))]
+mod ip_tunnel;
 #[cfg(any(feature = "std", feature = "alloc"))]
 mod loopback;
+#[cfg(all(feature = "phy-pcap", any(unix, windows)))]
+mod pcap_interface;
 mod pcap_writer;
 #[cfg(all(feature = "phy-raw_socket", unix))]
 mod raw_socket;
+#[cfg(feature = "phy-usb_ethernet")]
+mod rndis;
+#[cfg(all(
+    feature = "phy-tuntap_interface",
+    any(target_os = "linux", target_os = "android")
+))]
+mod segmentation;
+#[cfg(feature = "phy-spi_ethernet")]
+mod spi_ethernet;
 mod tracer;
 #[cfg(all(
     feature = "phy-tuntap_interface",
     any(target_os = "linux", target_os = "android")
 ))]
 mod tuntap_interface;
-
-#[cfg(all(
-    any(feature = "phy-raw_socket", feature = "phy-tuntap_interface"),
-    unix
+#[cfg(feature = "phy-usb_ethernet")]
+mod usb_ethernet;
+#[cfg(feature = "medium-ethernet")]
+mod vlan;
+#[cfg(feature = "proto-vxlan")]
+mod vxlan_tunnel;
+#[cfg(all(feature = "phy-xdp_socket", target_os = "linux"))]
+mod xdp_socket;
+
+#[cfg(any(
+    all(
+        any(
+            feature = "phy-raw_socket",
+            feature = "phy-tuntap_interface",
+            feature = "phy-pcap"
+        ),
+        unix
+    ),
+    all(feature = "phy-pcap", windows),
+    all(feature = "phy-xdp_socket", target_os = "linux")
 ))]
 pub use self::sys::wait;
 
+#[cfg(feature = "phy-spi_ethernet")]
+pub use self::enc28j60::Enc28j60;
 pub use self::fault_injector::FaultInjector;
+pub use self::frame_queue::{FrameConsumer, FrameInjector, FrameProducer, FrameQueue};
 pub use self::fuzz_injector::{FuzzInjector, Fuzzer};
+#[cfg(feature = "proto-gre")]
+pub use self::gre_tunnel::{GreConfig, GreTunnel};
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ip"))]
+pub use self::ip_tunnel::{IpTunnel, IpTunnelConfig};
 #[cfg(any(feature = "std", feature = "alloc"))]
 pub use self::loopback::Loopback;
+#[cfg(all(feature = "phy-pcap", any(unix, windows)))]
+pub use self::pcap_interface::PcapInterface;
 pub use self::pcap_writer::{PcapLinkType, PcapMode, PcapSink, PcapWriter};
 #[cfg(all(feature = "phy-raw_socket", unix))]
 pub use self::raw_socket::RawSocket;
+#[cfg(feature = "phy-usb_ethernet")]
+pub use self::rndis::Rndis;
+#[cfg(feature = "phy-spi_ethernet")]
+pub use self::spi_ethernet::{EthernetController, SpiBus, SpiEthernet};
 pub use self::tracer::Tracer;
 #[cfg(all(
     feature = "phy-tuntap_interface",
     any(target_os = "linux", target_os = "android")
 ))]
 pub use self::tuntap_interface::TunTapInterface;
+#[cfg(feature = "phy-usb_ethernet")]
+pub use self::usb_ethernet::{UsbEndpoints, UsbEthernet, UsbEthernetMode};
+#[cfg(feature = "medium-ethernet")]
+pub use self::vlan::{VlanPort, VlanSwitch, VLAN_ID_MAX};
+#[cfg(feature = "proto-vxlan")]
+pub use self::vxlan_tunnel::{VxlanConfig, VxlanTunnel};
+#[cfg(all(feature = "phy-xdp_socket", target_os = "linux"))]
+pub use self::xdp_socket::XdpSocket;
 
 /// A description of checksum behavior for a particular protocol.
 #[derive(Debug, Clone, Copy)]
@@ -198,6 +274,27 @@ impl ChecksumCapabilities {
             icmpv6: Checksum::None,
         }
     }
+
+    /// Checksum behavior that computes the same checksums as `self` when sending,
+    /// but does not verify any of them when receiving.
+    pub(crate) fn tx_only(&self) -> Self {
+        fn tx_only(checksum: &Checksum) -> Checksum {
+            if checksum.tx() {
+                Checksum::Tx
+            } else {
+                Checksum::None
+            }
+        }
+        ChecksumCapabilities {
+            ipv4: tx_only(&self.ipv4),
+            udp: tx_only(&self.udp),
+            tcp: tx_only(&self.tcp),
+            #[cfg(feature = "proto-ipv4")]
+            icmpv4: tx_only(&self.icmpv4),
+            #[cfg(feature = "proto-ipv6")]
+            icmpv6: tx_only(&self.icmpv6),
+        }
+    }
 }
 
 /// A description of device capabilities.
@@ -227,6 +324,10 @@ pub struct DeviceCapabilities {
     /// devices. This is a common source of confusion.
     ///
     /// Most common IP MTU is 1500. Minimum is 576 (for IPv4) or 1280 (for IPv6). Maximum is 9216 octets.
+    /// Jumbo frames are supported by reporting a larger value, e.g. 9014 for an IP MTU of 9000;
+    /// the TCP maximum segment size is derived from it. In that case, the receive and transmit
+    /// buffers of the device, and the packet buffers of any sockets expected to carry full-sized
+    /// packets, must hold at least one frame of this size per slot.
     pub max_transmission_unit: usize,
 
     /// Maximum burst size, in terms of MTU.
@@ -243,6 +344,16 @@ pub struct DeviceCapabilities {
     /// If the network device is capable of verifying or computing checksums for some protocols,
     /// it can request that the stack not do so in software to improve performance.
     pub checksum: ChecksumCapabilities,
+
+    /// TCP segmentation offload, as the maximum length of an IP packet the device splits.
+    ///
+    /// If `Some`, TCP sockets may hand the device a single segment larger than the MTU,
+    /// with the payload size of the frames to split it into given in the [PacketMeta] set
+    /// on the transmit token. The device must replicate the IP and TCP headers for each
+    /// frame, adjusting the lengths, identifications, sequence numbers, flags and checksums.
+    ///
+    /// If `None`, the device does not support segmentation offload.
+    pub tcp_segmentation_offload: Option<usize>,
 }
 
 impl DeviceCapabilities {
@@ -303,6 +414,34 @@ impl Default for Medium {
     }
 }
 
+/// State of the link of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkState {
+    /// The link is down; no frames can be transmitted or received.
+    Down,
+    /// The link is up.
+    Up {
+        /// Speed of the link, in Mbit/s, if known.
+        speed: Option<u32>,
+        /// Whether the link is full duplex, if known.
+        full_duplex: Option<bool>,
+    },
+}
+
+impl LinkState {
+    /// A link that is up, with an unknown speed and duplex mode.
+    pub const UP: LinkState = LinkState::Up {
+        speed: None,
+        full_duplex: None,
+    };
+
+    /// Query whether the link is up.
+    pub fn is_up(&self) -> bool {
+        matches!(self, LinkState::Up { .. })
+    }
+}
+
 /// An interface for sending and receiving raw network frames.
 ///
 /// The interface is based on _tokens_, which are types that allow to receive/transmit a
@@ -325,6 +464,36 @@ pub trait Device<'a> {
 
     /// Get a description of device capabilities.
     fn capabilities(&self) -> DeviceCapabilities;
+
+    /// Get the state of the link.
+    ///
+    /// Devices that cannot detect the state of their link report it as always up.
+    fn link_state(&self) -> LinkState {
+        LinkState::UP
+    }
+}
+
+/// Metadata attached to a packet by the device when receiving it, or by the stack
+/// when transmitting it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub struct PacketMeta {
+    /// For a TCP segment larger than the MTU, the payload size of the frames the device
+    /// must split it into. See [DeviceCapabilities::tcp_segmentation_offload].
+    pub segment_size: Option<usize>,
+    /// Whether the device has already verified every checksum in a received packet,
+    /// in which case the interface does not verify them again.
+    pub checksum_verified: bool,
+    /// The VLAN identifier of a received frame whose 802.1Q tag was removed by the device.
+    pub vlan_id: Option<u16>,
+    /// The 802.1Q priority code point to tag a transmitted frame with.
+    pub priority: Option<u8>,
+    /// The time at which the device received the packet, if it records one.
+    ///
+    /// Devices that timestamp packets in hardware should report this time with the
+    /// finest resolution [Instant] allows, since it is used by applications such as PTP.
+    pub timestamp: Option<Instant>,
 }
 
 /// A token to receive a single network packet.
@@ -339,6 +508,13 @@ pub trait RxToken {
     fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
     where
         F: FnOnce(&mut [u8]) -> Result<R>;
+
+    /// Return the metadata the device received along with the packet.
+    ///
+    /// Devices that do not provide any metadata need not implement this method.
+    fn meta(&self) -> PacketMeta {
+        PacketMeta::default()
+    }
 }
 
 /// A token to transmit a single network packet.
@@ -355,4 +531,27 @@ pub trait TxToken {
     fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
     where
         F: FnOnce(&mut [u8]) -> Result<R>;
+
+    /// Consumes the token to send a single network packet, like `consume`, and returns
+    /// the time at which the device transmitted it along with the result, if the device
+    /// records one.
+    ///
+    /// Devices that do not record transmit timestamps need not implement this method.
+    fn consume_timestamped<R, F>(
+        self,
+        timestamp: Instant,
+        len: usize,
+        f: F,
+    ) -> Result<(R, Option<Instant>)>
+    where
+        Self: Sized,
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        self.consume(timestamp, len, f).map(|result| (result, None))
+    }
+
+    /// Attach metadata to the packet, before it is constructed by `consume`.
+    ///
+    /// Devices that do not use any metadata need not implement this method.
+    fn set_meta(&mut self, _meta: PacketMeta) {}
 }
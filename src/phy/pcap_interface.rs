@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, RawHandle};
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::phy::{self, sys, Device, DeviceCapabilities, Medium};
+use crate::time::Instant;
+use crate::Result;
+
+/// A live capture of a network interface through libpcap, which also transmits frames.
+///
+/// This is an alternative to [RawSocket](struct.RawSocket.html) and
+/// [TunTapInterface](struct.TunTapInterface.html) for platforms where neither is available,
+/// or where only libpcap is allowed to capture, such as Windows, where it is provided by
+/// [Npcap](https://npcap.com). The medium follows the link type of the network interface.
+#[derive(Debug)]
+pub struct PcapInterface {
+    lower: Rc<RefCell<sys::PcapDesc>>,
+    mtu: usize,
+    medium: Medium,
+}
+
+#[cfg(unix)]
+impl AsRawFd for PcapInterface {
+    fn as_raw_fd(&self) -> RawFd {
+        self.lower.borrow().as_raw_fd()
+    }
+}
+
+/// The handle of the event that libpcap signals when frames are received, which can be
+/// passed to [wait](fn.wait.html).
+#[cfg(windows)]
+impl AsRawHandle for PcapInterface {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.lower.borrow().as_raw_handle()
+    }
+}
+
+impl PcapInterface {
+    /// Opens a live capture of the interface called `name`, in promiscuous mode.
+    ///
+    /// This usually requires superuser privileges or a corresponding capability bit
+    /// set on the executable.
+    pub fn new(name: &str) -> io::Result<PcapInterface> {
+        let lower = sys::PcapDesc::new(name)?;
+        let medium = lower.medium();
+        // libpcap does not report the MTU of the interface; assume the usual one.
+        let mtu = match medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => 1500 + crate::wire::EthernetFrame::<&[u8]>::header_len(),
+            #[cfg(feature = "medium-ip")]
+            Medium::Ip => 1500,
+            #[cfg(feature = "medium-ieee802154")]
+            Medium::Ieee802154 => 127,
+        };
+        Ok(PcapInterface {
+            lower: Rc::new(RefCell::new(lower)),
+            mtu,
+            medium,
+        })
+    }
+
+    /// Opens a live capture of the interface called `name`, with the capture filter `filter`.
+    ///
+    /// See [set_filter](#method.set_filter).
+    pub fn with_filter(name: &str, filter: &str) -> io::Result<PcapInterface> {
+        let mut interface = Self::new(name)?;
+        interface.set_filter(filter)?;
+        Ok(interface)
+    }
+
+    /// Installs a capture filter, in the syntax of `pcap-filter(7)`, for example
+    /// `"ether host 02:00:00:00:00:01 or ether broadcast"`.
+    ///
+    /// The filter is compiled to BPF and runs in the kernel where possible, so that frames
+    /// that the interface has no use for never reach the process.
+    pub fn set_filter(&mut self, filter: &str) -> io::Result<()> {
+        self.lower.borrow_mut().set_filter(filter)
+    }
+}
+
+impl<'a> Device<'a> for PcapInterface {
+    type RxToken = RxToken;
+    type TxToken = TxToken;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            max_transmission_unit: self.mtu,
+            medium: self.medium,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let mut lower = self.lower.borrow_mut();
+        let mut buffer = vec![0; self.mtu];
+        match lower.recv(&mut buffer[..]) {
+            Ok(size) => {
+                buffer.resize(size, 0);
+                let rx = RxToken { buffer };
+                let tx = TxToken {
+                    lower: self.lower.clone(),
+                };
+                Some((rx, tx))
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => None,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        Some(TxToken {
+            lower: self.lower.clone(),
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken {
+    buffer: Vec<u8>,
+}
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(mut self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(&mut self.buffer[..])
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken {
+    lower: Rc<RefCell<sys::PcapDesc>>,
+}
+
+impl phy::TxToken for TxToken {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let mut lower = self.lower.borrow_mut();
+        let mut buffer = vec![0; len];
+        let result = f(&mut buffer);
+        lower.send(&buffer[..]).unwrap();
+        result
+    }
+}
@@ -4,7 +4,7 @@ use phy::Medium;
 #[cfg(feature = "std")]
 use std::io::Write;
 
-use crate::phy::{self, Device, DeviceCapabilities};
+use crate::phy::{self, Device, DeviceCapabilities, LinkState};
 use crate::time::Instant;
 use crate::Result;
 
@@ -174,6 +174,10 @@ where
         self.lower.capabilities()
     }
 
+    fn link_state(&self) -> LinkState {
+        self.lower.link_state()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let sink = &self.sink;
         let mode = self.mode;
@@ -221,6 +225,10 @@ impl<'a, Rx: phy::RxToken, S: PcapSink> phy::RxToken for RxToken<'a, Rx, S> {
             f(buffer)
         })
     }
+
+    fn meta(&self) -> phy::PacketMeta {
+        self.token.meta()
+    }
 }
 
 #[doc(hidden)]
@@ -232,11 +240,24 @@ pub struct TxToken<'a, Tx: phy::TxToken, S: PcapSink> {
 
 impl<'a, Tx: phy::TxToken, S: PcapSink> phy::TxToken for TxToken<'a, Tx, S> {
     fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        self.consume_timestamped(timestamp, len, f)
+            .map(|(result, _)| result)
+    }
+
+    fn consume_timestamped<R, F>(
+        self,
+        timestamp: Instant,
+        len: usize,
+        f: F,
+    ) -> Result<(R, Option<Instant>)>
     where
         F: FnOnce(&mut [u8]) -> Result<R>,
     {
         let Self { token, sink, mode } = self;
-        token.consume(timestamp, len, |buffer| {
+        token.consume_timestamped(timestamp, len, |buffer| {
             let result = f(buffer);
             match mode {
                 PcapMode::Both | PcapMode::TxOnly => sink.borrow_mut().packet(timestamp, buffer),
@@ -245,4 +266,8 @@ impl<'a, Tx: phy::TxToken, S: PcapSink> phy::TxToken for TxToken<'a, Tx, S> {
             result
         })
     }
+
+    fn set_meta(&mut self, meta: phy::PacketMeta) {
+        self.token.set_meta(meta)
+    }
 }
@@ -0,0 +1,519 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::phy::{
+    Device, DeviceCapabilities, LinkState, UsbEndpoints, UsbEthernet, UsbEthernetMode,
+};
+use crate::wire::EthernetAddress;
+use crate::{Error, Result};
+
+mod message {
+    pub const INITIALIZE: u32 = 0x0000_0002;
+    pub const HALT: u32 = 0x0000_0003;
+    pub const QUERY: u32 = 0x0000_0004;
+    pub const SET: u32 = 0x0000_0005;
+    pub const RESET: u32 = 0x0000_0006;
+    pub const KEEPALIVE: u32 = 0x0000_0008;
+    pub const COMPLETION: u32 = 0x8000_0000;
+}
+
+mod status {
+    pub const SUCCESS: u32 = 0x0000_0000;
+    pub const NOT_SUPPORTED: u32 = 0xc000_00bb;
+    pub const INVALID_DATA: u32 = 0xc001_0015;
+}
+
+mod oid {
+    pub const GEN_SUPPORTED_LIST: u32 = 0x0001_0101;
+    pub const GEN_HARDWARE_STATUS: u32 = 0x0001_0102;
+    pub const GEN_MEDIA_SUPPORTED: u32 = 0x0001_0103;
+    pub const GEN_MEDIA_IN_USE: u32 = 0x0001_0104;
+    pub const GEN_MAXIMUM_FRAME_SIZE: u32 = 0x0001_0106;
+    pub const GEN_LINK_SPEED: u32 = 0x0001_0107;
+    pub const GEN_TRANSMIT_BLOCK_SIZE: u32 = 0x0001_010a;
+    pub const GEN_RECEIVE_BLOCK_SIZE: u32 = 0x0001_010b;
+    pub const GEN_VENDOR_ID: u32 = 0x0001_010c;
+    pub const GEN_VENDOR_DESCRIPTION: u32 = 0x0001_010d;
+    pub const GEN_CURRENT_PACKET_FILTER: u32 = 0x0001_010e;
+    pub const GEN_MAXIMUM_TOTAL_SIZE: u32 = 0x0001_0111;
+    pub const GEN_MEDIA_CONNECT_STATUS: u32 = 0x0001_0114;
+    pub const GEN_PHYSICAL_MEDIUM: u32 = 0x0001_0202;
+    pub const GEN_XMIT_OK: u32 = 0x0002_0101;
+    pub const GEN_RCV_OK: u32 = 0x0002_0102;
+    pub const GEN_XMIT_ERROR: u32 = 0x0002_0103;
+    pub const GEN_RCV_ERROR: u32 = 0x0002_0104;
+    pub const GEN_RCV_NO_BUFFER: u32 = 0x0002_0105;
+    pub const ETH_PERMANENT_ADDRESS: u32 = 0x0101_0101;
+    pub const ETH_CURRENT_ADDRESS: u32 = 0x0101_0102;
+    pub const ETH_MULTICAST_LIST: u32 = 0x0101_0103;
+    pub const ETH_MAXIMUM_LIST_SIZE: u32 = 0x0101_0104;
+
+    pub const SUPPORTED: [u32; 23] = [
+        GEN_SUPPORTED_LIST,
+        GEN_HARDWARE_STATUS,
+        GEN_MEDIA_SUPPORTED,
+        GEN_MEDIA_IN_USE,
+        GEN_MAXIMUM_FRAME_SIZE,
+        GEN_LINK_SPEED,
+        GEN_TRANSMIT_BLOCK_SIZE,
+        GEN_RECEIVE_BLOCK_SIZE,
+        GEN_VENDOR_ID,
+        GEN_VENDOR_DESCRIPTION,
+        GEN_CURRENT_PACKET_FILTER,
+        GEN_MAXIMUM_TOTAL_SIZE,
+        GEN_MEDIA_CONNECT_STATUS,
+        GEN_PHYSICAL_MEDIUM,
+        GEN_XMIT_OK,
+        GEN_RCV_OK,
+        GEN_XMIT_ERROR,
+        GEN_RCV_ERROR,
+        GEN_RCV_NO_BUFFER,
+        ETH_PERMANENT_ADDRESS,
+        ETH_CURRENT_ADDRESS,
+        ETH_MULTICAST_LIST,
+        ETH_MAXIMUM_LIST_SIZE,
+    ];
+}
+
+const VENDOR_DESCRIPTION: &[u8] = b"smoltcp\0";
+// The link speed reported to the host, in units of 100 bit/s.
+const LINK_SPEED: u32 = 120_000;
+const RESPONSE_BUFFER_OFFSET: usize = 24;
+
+/// An RNDIS device.
+///
+/// RNDIS is the USB Ethernet function recognized by Windows hosts without extra drivers.
+/// This device wraps a [UsbEthernet](struct.UsbEthernet.html) device in RNDIS mode, which
+/// carries the data messages, and implements the control messages that the host sends with
+/// the `SEND_ENCAPSULATED_COMMAND` request; the responses must be returned by the USB class
+/// implementation with the `GET_ENCAPSULATED_RESPONSE` request, after a `RESPONSE_AVAILABLE`
+/// notification.
+///
+/// No frames are received or transmitted until the host has initialized the function and
+/// set a packet filter.
+pub struct Rndis<'a, E: UsbEndpoints> {
+    inner: UsbEthernet<'a, E>,
+    host_addr: EthernetAddress,
+    initialized: bool,
+    max_frame_size: u32,
+    max_transfer_size: u32,
+    packet_filter: u32,
+}
+
+impl<'a, E: UsbEndpoints> Rndis<'a, E> {
+    /// Create an RNDIS device.
+    ///
+    /// `host_addr` is the hardware address of the network adapter that the host creates
+    /// for the function; it must differ from the hardware address of the interface.
+    ///
+    /// # Panics
+    /// This function panics if the underlying device is not in RNDIS mode.
+    pub fn new(inner: UsbEthernet<'a, E>, host_addr: EthernetAddress) -> Rndis<'a, E> {
+        assert_eq!(
+            inner.mode(),
+            UsbEthernetMode::Rndis,
+            "the underlying device must be in RNDIS mode"
+        );
+        let caps = inner.capabilities();
+        let max_frame_size = caps.ip_mtu() as u32;
+        let max_transfer_size = (caps.max_transmission_unit + inner.mode().overhead()) as u32;
+        Rndis {
+            inner,
+            host_addr,
+            initialized: false,
+            max_frame_size,
+            max_transfer_size,
+            packet_filter: 0,
+        }
+    }
+
+    /// Return the hardware address of the network adapter of the host.
+    pub fn host_addr(&self) -> EthernetAddress {
+        self.host_addr
+    }
+
+    /// Return whether the host has initialized the function.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Return the packet filter set by the host, a combination of `NDIS_PACKET_TYPE_*` flags.
+    pub fn packet_filter(&self) -> u32 {
+        self.packet_filter
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &UsbEthernet<'a, E> {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    pub fn get_mut(&mut self) -> &mut UsbEthernet<'a, E> {
+        &mut self.inner
+    }
+
+    /// Return the underlying device, consuming the RNDIS device.
+    pub fn into_inner(self) -> UsbEthernet<'a, E> {
+        self.inner
+    }
+
+    /// Process a control message sent by the host, and write the response into `response`.
+    ///
+    /// Returns the length of the response, which is zero if the message has no response.
+    /// Returns `Err(Error::Truncated)` if the message is incomplete or `response` is too short,
+    /// and `Err(Error::Unrecognized)` if the message is not a known control message.
+    pub fn process_command(&mut self, command: &[u8], response: &mut [u8]) -> Result<usize> {
+        if command.len() < 12 {
+            return Err(Error::Truncated);
+        }
+        let message_type = LittleEndian::read_u32(&command[0..4]);
+        let message_len = LittleEndian::read_u32(&command[4..8]) as usize;
+        if message_len < 12 || message_len > command.len() {
+            return Err(Error::Truncated);
+        }
+        let command = &command[..message_len];
+        let request_id = LittleEndian::read_u32(&command[8..12]);
+
+        let (status, len) = match message_type {
+            message::INITIALIZE => {
+                if command.len() < 24 || response.len() < 52 {
+                    return Err(Error::Truncated);
+                }
+                net_debug!("rndis: initialized");
+                self.initialized = true;
+                self.packet_filter = 0;
+                let fields = [
+                    1,                      // MajorVersion
+                    0,                      // MinorVersion
+                    1,                      // DeviceFlags: RNDIS_DF_CONNECTIONLESS
+                    0,                      // Medium: 802.3
+                    1,                      // MaxPacketsPerTransfer
+                    self.max_transfer_size, // MaxTransferSize
+                    0,                      // PacketAlignmentFactor
+                    0,                      // AFListOffset
+                    0,                      // AFListSize
+                ];
+                for (i, &field) in fields.iter().enumerate() {
+                    LittleEndian::write_u32(&mut response[16 + i * 4..20 + i * 4], field);
+                }
+                (status::SUCCESS, 52)
+            }
+            message::HALT => {
+                net_debug!("rndis: halted");
+                self.initialized = false;
+                self.packet_filter = 0;
+                return Ok(0);
+            }
+            message::QUERY => {
+                if command.len() < 28 {
+                    return Err(Error::Truncated);
+                }
+                let oid = LittleEndian::read_u32(&command[12..16]);
+                let buffer = response
+                    .get_mut(RESPONSE_BUFFER_OFFSET..)
+                    .ok_or(Error::Truncated)?;
+                match self.query(oid, buffer)? {
+                    Some(len) => {
+                        LittleEndian::write_u32(&mut response[16..20], len as u32);
+                        LittleEndian::write_u32(
+                            &mut response[20..24],
+                            (RESPONSE_BUFFER_OFFSET - 8) as u32,
+                        );
+                        (status::SUCCESS, RESPONSE_BUFFER_OFFSET + len)
+                    }
+                    None => {
+                        net_debug!("rndis: unsupported query {:#010x}", oid);
+                        response[16..24].fill(0);
+                        (status::NOT_SUPPORTED, RESPONSE_BUFFER_OFFSET)
+                    }
+                }
+            }
+            message::SET => {
+                if command.len() < 28 || response.len() < 16 {
+                    return Err(Error::Truncated);
+                }
+                let oid = LittleEndian::read_u32(&command[12..16]);
+                let len = LittleEndian::read_u32(&command[16..20]) as usize;
+                let offset = 8 + LittleEndian::read_u32(&command[20..24]) as usize;
+                let status = match command.get(offset..offset + len) {
+                    Some(buffer) => self.set(oid, buffer),
+                    None => status::INVALID_DATA,
+                };
+                (status, 16)
+            }
+            message::RESET => {
+                if response.len() < 16 {
+                    return Err(Error::Truncated);
+                }
+                net_debug!("rndis: reset");
+                self.inner.reset();
+                self.packet_filter = 0;
+                // The request ID field is the AddressingReset field in this response.
+                LittleEndian::write_u32(&mut response[0..4], message::RESET | message::COMPLETION);
+                LittleEndian::write_u32(&mut response[4..8], 16);
+                LittleEndian::write_u32(&mut response[8..12], status::SUCCESS);
+                LittleEndian::write_u32(&mut response[12..16], 0);
+                return Ok(16);
+            }
+            message::KEEPALIVE => {
+                if response.len() < 16 {
+                    return Err(Error::Truncated);
+                }
+                (status::SUCCESS, 16)
+            }
+            _ => return Err(Error::Unrecognized),
+        };
+
+        LittleEndian::write_u32(&mut response[0..4], message_type | message::COMPLETION);
+        LittleEndian::write_u32(&mut response[4..8], len as u32);
+        LittleEndian::write_u32(&mut response[8..12], request_id);
+        LittleEndian::write_u32(&mut response[12..16], status);
+        Ok(len)
+    }
+
+    /// Write the value of `oid` into `buffer`, and return its length, or `None` if the
+    /// object is not supported.
+    fn query(&self, oid: u32, buffer: &mut [u8]) -> Result<Option<usize>> {
+        let value = match oid {
+            oid::GEN_SUPPORTED_LIST => {
+                let len = oid::SUPPORTED.len() * 4;
+                if buffer.len() < len {
+                    return Err(Error::Truncated);
+                }
+                LittleEndian::write_u32_into(&oid::SUPPORTED, &mut buffer[..len]);
+                return Ok(Some(len));
+            }
+            oid::GEN_VENDOR_DESCRIPTION => {
+                let len = VENDOR_DESCRIPTION.len();
+                if buffer.len() < len {
+                    return Err(Error::Truncated);
+                }
+                buffer[..len].copy_from_slice(VENDOR_DESCRIPTION);
+                return Ok(Some(len));
+            }
+            oid::ETH_PERMANENT_ADDRESS | oid::ETH_CURRENT_ADDRESS => {
+                if buffer.len() < 6 {
+                    return Err(Error::Truncated);
+                }
+                buffer[..6].copy_from_slice(self.host_addr.as_bytes());
+                return Ok(Some(6));
+            }
+            oid::GEN_MAXIMUM_FRAME_SIZE => self.max_frame_size,
+            oid::GEN_LINK_SPEED => LINK_SPEED,
+            oid::GEN_TRANSMIT_BLOCK_SIZE
+            | oid::GEN_RECEIVE_BLOCK_SIZE
+            | oid::GEN_MAXIMUM_TOTAL_SIZE => self.max_transfer_size,
+            oid::GEN_VENDOR_ID => 0x00ff_ffff,
+            oid::GEN_CURRENT_PACKET_FILTER => self.packet_filter,
+            oid::ETH_MAXIMUM_LIST_SIZE => 1,
+            // Hardware status, media and connect status are all zero when the link is up.
+            oid::GEN_HARDWARE_STATUS
+            | oid::GEN_MEDIA_SUPPORTED
+            | oid::GEN_MEDIA_IN_USE
+            | oid::GEN_MEDIA_CONNECT_STATUS
+            | oid::GEN_PHYSICAL_MEDIUM => 0,
+            // Statistics are not kept.
+            oid::GEN_XMIT_OK
+            | oid::GEN_RCV_OK
+            | oid::GEN_XMIT_ERROR
+            | oid::GEN_RCV_ERROR
+            | oid::GEN_RCV_NO_BUFFER
+            | oid::ETH_MULTICAST_LIST => 0,
+            _ => return Ok(None),
+        };
+        if buffer.len() < 4 {
+            return Err(Error::Truncated);
+        }
+        LittleEndian::write_u32(&mut buffer[..4], value);
+        Ok(Some(4))
+    }
+
+    /// Set the value of `oid` to `buffer`, and return the status of the operation.
+    fn set(&mut self, oid: u32, buffer: &[u8]) -> u32 {
+        match oid {
+            oid::GEN_CURRENT_PACKET_FILTER if buffer.len() == 4 => {
+                self.packet_filter = LittleEndian::read_u32(buffer);
+                net_debug!("rndis: packet filter {:#x}", self.packet_filter);
+                status::SUCCESS
+            }
+            oid::GEN_CURRENT_PACKET_FILTER => status::INVALID_DATA,
+            // Multicast filtering is left to the interface.
+            oid::ETH_MULTICAST_LIST => status::SUCCESS,
+            _ => {
+                net_debug!("rndis: unsupported set {:#010x}", oid);
+                status::NOT_SUPPORTED
+            }
+        }
+    }
+}
+
+impl<'a, 'b, E: UsbEndpoints + 'a> Device<'a> for Rndis<'b, E> {
+    type RxToken = <UsbEthernet<'b, E> as Device<'a>>::RxToken;
+    type TxToken = <UsbEthernet<'b, E> as Device<'a>>::TxToken;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn link_state(&self) -> LinkState {
+        if !self.initialized || self.packet_filter == 0 {
+            return LinkState::Down;
+        }
+        self.inner.link_state()
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        if !self.initialized || self.packet_filter == 0 {
+            return None;
+        }
+        self.inner.receive()
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        if !self.initialized || self.packet_filter == 0 {
+            return None;
+        }
+        self.inner.transmit()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::{RxToken, TxToken};
+    use crate::time::Instant;
+    use std::vec::Vec;
+
+    const HOST_ADDR: EthernetAddress = EthernetAddress([0x02, 0, 0, 0, 0, 2]);
+
+    /// Endpoints looping transmitted packets back.
+    #[derive(Default)]
+    struct LoopbackEndpoints {
+        packets: std::collections::VecDeque<Vec<u8>>,
+    }
+
+    impl UsbEndpoints for LoopbackEndpoints {
+        fn max_packet_size(&self) -> usize {
+            64
+        }
+
+        fn read_packet(&mut self, buffer: &mut [u8]) -> Option<usize> {
+            let packet = self.packets.pop_front()?;
+            buffer[..packet.len()].copy_from_slice(&packet);
+            Some(packet.len())
+        }
+
+        fn write_packet(&mut self, packet: &[u8]) -> bool {
+            self.packets.push_back(packet.to_vec());
+            true
+        }
+    }
+
+    fn command(message_type: u32, request_id: u32, fields: &[u32], data: &[u8]) -> Vec<u8> {
+        let mut command = vec![0; 12 + fields.len() * 4];
+        LittleEndian::write_u32(&mut command[0..4], message_type);
+        LittleEndian::write_u32(&mut command[8..12], request_id);
+        LittleEndian::write_u32_into(fields, &mut command[12..]);
+        command.extend_from_slice(data);
+        let len = command.len() as u32;
+        LittleEndian::write_u32(&mut command[4..8], len);
+        command
+    }
+
+    fn process(rndis: &mut Rndis<LoopbackEndpoints>, command: &[u8]) -> Vec<u8> {
+        let mut response = [0; 256];
+        let len = rndis.process_command(command, &mut response).unwrap();
+        response[..len].to_vec()
+    }
+
+    fn field(response: &[u8], index: usize) -> u32 {
+        LittleEndian::read_u32(&response[index * 4..index * 4 + 4])
+    }
+
+    #[test]
+    fn test_control() {
+        let (mut rx_buffer, mut tx_buffer) = ([0; 1514 + 44], [0; 1514 + 44]);
+        let inner = UsbEthernet::new(
+            LoopbackEndpoints::default(),
+            UsbEthernetMode::Rndis,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        let mut rndis = Rndis::new(inner, HOST_ADDR);
+        assert!(rndis.transmit().is_none());
+
+        let response = process(
+            &mut rndis,
+            &command(message::INITIALIZE, 1, &[1, 0, 0x4000], &[]),
+        );
+        assert_eq!(response.len(), 52);
+        assert_eq!(field(&response, 0), 0x8000_0002);
+        assert_eq!(field(&response, 2), 1);
+        assert_eq!(field(&response, 3), status::SUCCESS);
+        assert_eq!(field(&response, 9), 1514 + 44);
+        assert!(rndis.is_initialized());
+
+        let query = |oid| command(message::QUERY, 2, &[oid, 0, 20, 0], &[]);
+        let response = process(&mut rndis, &query(oid::ETH_PERMANENT_ADDRESS));
+        assert_eq!(field(&response, 0), 0x8000_0004);
+        assert_eq!(field(&response, 3), status::SUCCESS);
+        assert_eq!(field(&response, 4), 6);
+        assert_eq!(&response[24..], HOST_ADDR.as_bytes());
+        let response = process(&mut rndis, &query(oid::GEN_MAXIMUM_FRAME_SIZE));
+        assert_eq!(field(&response, 6), 1500);
+        let response = process(&mut rndis, &query(oid::GEN_SUPPORTED_LIST));
+        assert_eq!(field(&response, 4) as usize, oid::SUPPORTED.len() * 4);
+        let response = process(&mut rndis, &query(0x0001_0204));
+        assert_eq!(field(&response, 3), status::NOT_SUPPORTED);
+
+        let response = process(
+            &mut rndis,
+            &command(
+                message::SET,
+                3,
+                &[oid::GEN_CURRENT_PACKET_FILTER, 4, 20, 0],
+                &[0x0f, 0, 0, 0],
+            ),
+        );
+        assert_eq!(field(&response, 0), 0x8000_0005);
+        assert_eq!(field(&response, 2), 3);
+        assert_eq!(field(&response, 3), status::SUCCESS);
+        assert_eq!(rndis.packet_filter(), 0x0f);
+
+        let response = process(&mut rndis, &command(message::KEEPALIVE, 4, &[], &[]));
+        assert_eq!(field(&response, 0), 0x8000_0008);
+        assert_eq!(field(&response, 3), status::SUCCESS);
+
+        // Data flows once the packet filter is set.
+        let tx = rndis.transmit().unwrap();
+        tx.consume(Instant::ZERO, 60, |buf| {
+            buf.fill(0xaa);
+            Ok(())
+        })
+        .unwrap();
+        let (rx, _tx) = rndis.receive().unwrap();
+        rx.consume(Instant::ZERO, |buf| {
+            assert_eq!(buf, &[0xaa; 60][..]);
+            Ok(())
+        })
+        .unwrap();
+
+        let response = process(&mut rndis, &command(message::RESET, 0, &[], &[]));
+        assert_eq!(field(&response, 0), 0x8000_0006);
+        assert_eq!(rndis.packet_filter(), 0);
+        assert_eq!(
+            process(&mut rndis, &command(message::HALT, 5, &[], &[])),
+            []
+        );
+        assert!(!rndis.is_initialized());
+
+        let mut response = [0; 256];
+        assert_eq!(
+            rndis.process_command(&command(0x42, 6, &[], &[]), &mut response),
+            Err(Error::Unrecognized)
+        );
+        assert_eq!(
+            rndis.process_command(&[0; 8], &mut response),
+            Err(Error::Truncated)
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::vec::Vec;
+
+use crate::phy::Medium;
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Ipv4Packet;
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::Ipv6Packet;
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::{EthernetFrame, EthernetProtocol};
+use crate::wire::{IpAddress, IpProtocol, IpVersion, TcpPacket};
+
+/// Where the headers of a TCP segment are in a frame.
+struct Layout {
+    version: IpVersion,
+    ip_offset: usize,
+    tcp_offset: usize,
+    payload_offset: usize,
+    end: usize,
+    src_addr: IpAddress,
+    dst_addr: IpAddress,
+}
+
+impl Layout {
+    fn parse(medium: Medium, frame: &[u8]) -> Option<Layout> {
+        let ip_offset = match medium {
+            #[cfg(feature = "medium-ethernet")]
+            Medium::Ethernet => {
+                let eth_frame = EthernetFrame::new_checked(frame).ok()?;
+                match eth_frame.ethertype() {
+                    EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => {}
+                    _ => return None,
+                }
+                EthernetFrame::<&[u8]>::header_len()
+            }
+            #[cfg(feature = "medium-ip")]
+            Medium::Ip => 0,
+            #[allow(unreachable_patterns)]
+            _ => return None,
+        };
+
+        let ip_packet = frame.get(ip_offset..).filter(|data| !data.is_empty())?;
+        let version = IpVersion::of_packet(ip_packet).ok()?;
+        let (src_addr, dst_addr, tcp_offset, end) = match version {
+            #[cfg(feature = "proto-ipv4")]
+            IpVersion::Ipv4 => {
+                let packet = Ipv4Packet::new_checked(ip_packet).ok()?;
+                if packet.next_header() != IpProtocol::Tcp
+                    || packet.more_frags()
+                    || packet.frag_offset() != 0
+                {
+                    return None;
+                }
+                (
+                    IpAddress::Ipv4(packet.src_addr()),
+                    IpAddress::Ipv4(packet.dst_addr()),
+                    ip_offset + packet.header_len() as usize,
+                    ip_offset + packet.total_len() as usize,
+                )
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpVersion::Ipv6 => {
+                let packet = Ipv6Packet::new_checked(ip_packet).ok()?;
+                if packet.next_header() != IpProtocol::Tcp {
+                    return None;
+                }
+                (
+                    IpAddress::Ipv6(packet.src_addr()),
+                    IpAddress::Ipv6(packet.dst_addr()),
+                    ip_offset + packet.header_len(),
+                    ip_offset + packet.total_len(),
+                )
+            }
+            #[allow(unreachable_patterns)]
+            _ => return None,
+        };
+
+        let tcp_packet = TcpPacket::new_checked(&frame[tcp_offset..end]).ok()?;
+        Some(Layout {
+            version,
+            ip_offset,
+            tcp_offset,
+            payload_offset: tcp_offset + tcp_packet.header_len() as usize,
+            end,
+            src_addr,
+            dst_addr,
+        })
+    }
+}
+
+/// Split the TCP segment in `frame` into frames carrying at most `segment_size` octets
+/// of payload each, and pass them to `f` in order.
+///
+/// The link, IP and TCP headers are replicated in every frame, adjusting the lengths,
+/// the IPv4 identification, the sequence number, the FIN and PSH flags and the checksums.
+/// Frames that do not hold a TCP segment longer than `segment_size` are passed to `f`
+/// unchanged.
+pub(crate) fn segment_tcp<F>(medium: Medium, frame: &[u8], segment_size: usize, mut f: F)
+where
+    F: FnMut(&[u8]),
+{
+    let layout = match Layout::parse(medium, frame) {
+        Some(layout) if segment_size > 0 && layout.end - layout.payload_offset > segment_size => {
+            layout
+        }
+        _ => return f(frame),
+    };
+
+    let seq_number = TcpPacket::new_unchecked(&frame[layout.tcp_offset..]).seq_number();
+    let payload = &frame[layout.payload_offset..layout.end];
+    let mut buffer = Vec::with_capacity(layout.payload_offset + segment_size);
+    for (index, chunk) in payload.chunks(segment_size).enumerate() {
+        buffer.clear();
+        buffer.extend_from_slice(&frame[..layout.payload_offset]);
+        buffer.extend_from_slice(chunk);
+
+        let ip_len = buffer.len() - layout.ip_offset;
+        match layout.version {
+            #[cfg(feature = "proto-ipv4")]
+            IpVersion::Ipv4 => {
+                let mut packet = Ipv4Packet::new_unchecked(&mut buffer[layout.ip_offset..]);
+                packet.set_total_len(ip_len as u16);
+                packet.set_ident(packet.ident().wrapping_add(index as u16));
+                packet.fill_checksum();
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpVersion::Ipv6 => {
+                let mut packet = Ipv6Packet::new_unchecked(&mut buffer[layout.ip_offset..]);
+                let header_len = packet.header_len();
+                packet.set_payload_len((ip_len - header_len) as u16);
+            }
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        }
+
+        let mut packet = TcpPacket::new_unchecked(&mut buffer[layout.tcp_offset..]);
+        packet.set_seq_number(seq_number + index * segment_size);
+        if (index + 1) * segment_size < payload.len() {
+            packet.set_fin(false);
+            packet.set_psh(false);
+        }
+        packet.fill_checksum(&layout.src_addr, &layout.dst_addr);
+
+        f(&buffer);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::vec;
+
+    use super::*;
+    use crate::phy::ChecksumCapabilities;
+    use crate::wire::{EthernetAddress, IpRepr, ParseMode, TcpControl, TcpRepr, TcpSeqNumber};
+
+    const PAYLOAD: [u8; 2500] = [0x5a; 2500];
+    const ETH_LEN: usize = 14;
+
+    fn segment(src_addr: IpAddress, dst_addr: IpAddress, control: TcpControl) -> Vec<u8> {
+        let tcp_repr = TcpRepr {
+            src_port: 49152,
+            dst_port: 80,
+            control,
+            seq_number: TcpSeqNumber(0x1000),
+            ack_number: Some(TcpSeqNumber(0x2000)),
+            window_len: 1024,
+            window_scale: None,
+            max_seg_size: None,
+            sack_permitted: false,
+            sack_ranges: [None, None, None],
+            payload: &PAYLOAD,
+        };
+        let ip_repr = IpRepr::new(
+            src_addr,
+            dst_addr,
+            IpProtocol::Tcp,
+            tcp_repr.buffer_len(),
+            64,
+        );
+        let mut buffer = vec![0; ETH_LEN + ip_repr.total_len()];
+        let mut eth_frame = EthernetFrame::new_unchecked(&mut buffer[..]);
+        eth_frame.set_src_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]));
+        eth_frame.set_dst_addr(EthernetAddress([0x02, 0, 0, 0, 0, 2]));
+        eth_frame.set_ethertype(match ip_repr {
+            #[cfg(feature = "proto-ipv4")]
+            IpRepr::Ipv4(_) => EthernetProtocol::Ipv4,
+            #[cfg(feature = "proto-ipv6")]
+            IpRepr::Ipv6(_) => EthernetProtocol::Ipv6,
+        });
+        let caps = ChecksumCapabilities::default();
+        ip_repr.emit(&mut buffer[ETH_LEN..], &caps);
+        tcp_repr.emit(
+            &mut TcpPacket::new_unchecked(&mut buffer[ETH_LEN + ip_repr.header_len()..]),
+            &src_addr,
+            &dst_addr,
+            &caps,
+        );
+        buffer
+    }
+
+    fn check_split(src_addr: IpAddress, dst_addr: IpAddress) {
+        let original = segment(src_addr, dst_addr, TcpControl::Fin);
+        let mut frames = vec![];
+        segment_tcp(Medium::Ethernet, &original, 1000, |frame| {
+            frames.push(frame.to_vec())
+        });
+        assert_eq!(frames.len(), 3);
+
+        let caps = ChecksumCapabilities::default();
+        let mut received = vec![];
+        for (index, frame) in frames.iter().enumerate() {
+            assert_eq!(frame[..ETH_LEN], original[..ETH_LEN]);
+            let frame = &frame[ETH_LEN..];
+            let ip_repr = match IpVersion::of_packet(frame).unwrap() {
+                #[cfg(feature = "proto-ipv4")]
+                IpVersion::Ipv4 => {
+                    let packet = Ipv4Packet::new_checked(frame).unwrap();
+                    assert!(packet.verify_checksum());
+                    assert_eq!(packet.ident(), index as u16);
+                    IpRepr::Ipv4(
+                        crate::wire::Ipv4Repr::parse(&packet, &caps, ParseMode::Strict).unwrap(),
+                    )
+                }
+                #[cfg(feature = "proto-ipv6")]
+                IpVersion::Ipv6 => {
+                    let packet = Ipv6Packet::new_checked(frame).unwrap();
+                    IpRepr::Ipv6(crate::wire::Ipv6Repr::parse(&packet).unwrap())
+                }
+                #[allow(unreachable_patterns)]
+                _ => unreachable!(),
+            };
+            let packet = TcpPacket::new_checked(&frame[ip_repr.header_len()..]).unwrap();
+            let tcp_repr =
+                TcpRepr::parse(&packet, &src_addr, &dst_addr, &caps, ParseMode::Strict).unwrap();
+            assert_eq!(tcp_repr.seq_number, TcpSeqNumber(0x1000) + received.len());
+            assert_eq!(tcp_repr.control == TcpControl::Fin, index == 2);
+            received.extend_from_slice(tcp_repr.payload);
+        }
+        assert_eq!(received, &PAYLOAD[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_split_ipv4() {
+        check_split(IpAddress::v4(192, 168, 1, 1), IpAddress::v4(192, 168, 1, 2));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_split_ipv6() {
+        check_split(
+            IpAddress::v6(0xfe80, 0, 0, 0, 0, 0, 0, 1),
+            IpAddress::v6(0xfe80, 0, 0, 0, 0, 0, 0, 2),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_short_unchanged() {
+        let frame = segment(
+            IpAddress::v4(192, 168, 1, 1),
+            IpAddress::v4(192, 168, 1, 2),
+            TcpControl::None,
+        );
+        let mut frames = vec![];
+        segment_tcp(Medium::Ethernet, &frame, 2500, |frame| {
+            frames.push(frame.to_vec())
+        });
+        assert_eq!(frames, vec![frame]);
+    }
+}
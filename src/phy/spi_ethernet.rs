@@ -0,0 +1,166 @@
+use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::time::Instant;
+use crate::{Error, Result};
+
+/// An SPI device, with its chip select.
+///
+/// This trait maps directly onto the `SpiDevice::transaction` method of the [embedded-hal]
+/// crate, with a `Write` operation followed by a `Read` or another `Write` operation; bus
+/// errors should be reported as `Error::Illegal`.
+///
+/// [embedded-hal]: https://docs.rs/embedded-hal
+pub trait SpiBus {
+    /// Write `header`, then read into `buffer`, in a single transaction.
+    fn read(&mut self, header: &[u8], buffer: &mut [u8]) -> Result<()>;
+
+    /// Write `header`, then `data`, in a single transaction.
+    fn write(&mut self, header: &[u8], data: &[u8]) -> Result<()>;
+}
+
+/// An Ethernet controller, combining a MAC and a PHY, with its own frame buffers.
+///
+/// This trait is implemented by drivers for controllers attached over a serial bus, such as
+/// [Enc28j60](struct.Enc28j60.html), and used by the [SpiEthernet](struct.SpiEthernet.html)
+/// device.
+pub trait EthernetController {
+    /// Return the maximum length of a frame, excluding the frame check sequence.
+    fn max_frame_len(&self) -> usize;
+
+    /// Copy the next received frame into `buffer`, and return its length, or `None`
+    /// if no frame was received.
+    ///
+    /// Frames longer than `buffer` are discarded.
+    fn receive_frame(&mut self, buffer: &mut [u8]) -> Result<Option<usize>>;
+
+    /// Return whether a frame can be transmitted.
+    fn can_transmit(&mut self) -> Result<bool>;
+
+    /// Transmit `frame`, excluding its frame check sequence.
+    fn transmit_frame(&mut self, frame: &[u8]) -> Result<()>;
+}
+
+/// A device driving an Ethernet controller.
+///
+/// Frames are copied between the buffers of the controller and `rx_buffer` and `tx_buffer`,
+/// which should each be able to hold a frame of the maximum length of the controller.
+pub struct SpiEthernet<'a, C: EthernetController> {
+    controller: C,
+    rx_buffer: &'a mut [u8],
+    tx_buffer: &'a mut [u8],
+}
+
+impl<'a, C: EthernetController> SpiEthernet<'a, C> {
+    /// Create a device driving `controller`, which must already be initialized.
+    pub fn new(controller: C, rx_buffer: &'a mut [u8], tx_buffer: &'a mut [u8]) -> Self {
+        SpiEthernet {
+            controller,
+            rx_buffer,
+            tx_buffer,
+        }
+    }
+
+    /// Get a reference to the controller.
+    pub fn get_ref(&self) -> &C {
+        &self.controller
+    }
+
+    /// Get a mutable reference to the controller.
+    ///
+    /// It is inadvisable to directly receive frames from the controller as doing so
+    /// will circumvent the interface.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.controller
+    }
+
+    /// Return the controller, consuming the device.
+    pub fn into_inner(self) -> C {
+        self.controller
+    }
+}
+
+impl<'a, 'b, C: EthernetController + 'a> Device<'a> for SpiEthernet<'b, C> {
+    type RxToken = RxToken<'a>;
+    type TxToken = TxToken<'a, C>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            medium: Medium::Ethernet,
+            max_transmission_unit: self
+                .controller
+                .max_frame_len()
+                .min(self.rx_buffer.len())
+                .min(self.tx_buffer.len()),
+            max_burst_size: Some(1),
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let len = match self.controller.receive_frame(self.rx_buffer) {
+            Ok(Some(len)) => len,
+            Ok(None) => return None,
+            Err(err) => {
+                net_debug!("spi: cannot receive: {}", err);
+                return None;
+            }
+        };
+        let rx = RxToken {
+            buffer: &mut self.rx_buffer[..len],
+        };
+        let tx = TxToken {
+            controller: &mut self.controller,
+            buffer: self.tx_buffer,
+        };
+        Some((rx, tx))
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        match self.controller.can_transmit() {
+            Ok(true) => Some(TxToken {
+                controller: &mut self.controller,
+                buffer: self.tx_buffer,
+            }),
+            Ok(false) => None,
+            Err(err) => {
+                net_debug!("spi: cannot transmit: {}", err);
+                None
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<'a> {
+    buffer: &'a mut [u8],
+}
+
+impl<'a> phy::RxToken for RxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(self.buffer)
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<'a, C: EthernetController> {
+    controller: &'a mut C,
+    buffer: &'a mut [u8],
+}
+
+impl<'a, C: EthernetController> phy::TxToken for TxToken<'a, C> {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        // A token obtained from `receive` may find the controller still transmitting.
+        if !self.controller.can_transmit()? {
+            return Err(Error::Exhausted);
+        }
+        let frame = self.buffer.get_mut(..len).ok_or(Error::Truncated)?;
+        let result = f(frame)?;
+        self.controller.transmit_frame(frame)?;
+        Ok(result)
+    }
+}
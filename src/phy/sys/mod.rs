@@ -1,12 +1,20 @@
 #![allow(unsafe_code)]
 
 use crate::time::Duration;
+use std::io;
+#[cfg(unix)]
 use std::os::unix::io::RawFd;
-use std::{io, mem, ptr};
+#[cfg(windows)]
+use std::os::windows::io::RawHandle;
+#[cfg(unix)]
+use std::{mem, ptr};
 
 #[cfg(any(target_os = "linux", target_os = "android"))]
 #[path = "linux.rs"]
 mod imp;
+#[cfg(windows)]
+#[path = "windows.rs"]
+mod imp;
 
 #[cfg(all(
     feature = "phy-raw_socket",
@@ -14,6 +22,8 @@ mod imp;
     unix
 ))]
 pub mod bpf;
+#[cfg(all(feature = "phy-pcap", any(unix, windows)))]
+pub mod pcap;
 #[cfg(all(
     feature = "phy-raw_socket",
     any(target_os = "linux", target_os = "android")
@@ -24,6 +34,8 @@ pub mod raw_socket;
     any(target_os = "linux", target_os = "android")
 ))]
 pub mod tuntap_interface;
+#[cfg(all(feature = "phy-xdp_socket", target_os = "linux"))]
+pub mod xdp_socket;
 
 #[cfg(all(
     feature = "phy-raw_socket",
@@ -31,6 +43,8 @@ pub mod tuntap_interface;
     unix
 ))]
 pub use self::bpf::BpfDevice as RawSocketDesc;
+#[cfg(all(feature = "phy-pcap", any(unix, windows)))]
+pub use self::pcap::PcapDesc;
 #[cfg(all(
     feature = "phy-raw_socket",
     any(target_os = "linux", target_os = "android")
@@ -41,8 +55,11 @@ pub use self::raw_socket::RawSocketDesc;
     any(target_os = "linux", target_os = "android")
 ))]
 pub use self::tuntap_interface::TunTapInterfaceDesc;
+#[cfg(all(feature = "phy-xdp_socket", target_os = "linux"))]
+pub use self::xdp_socket::XdpSocketDesc;
 
 /// Wait until given file descriptor becomes readable, but no longer than given timeout.
+#[cfg(unix)]
 pub fn wait(fd: RawFd, duration: Option<Duration>) -> io::Result<()> {
     unsafe {
         let mut readfds = {
@@ -90,6 +107,20 @@ pub fn wait(fd: RawFd, duration: Option<Duration>) -> io::Result<()> {
     }
 }
 
+/// Wait until given event handle becomes signaled, but no longer than given timeout.
+#[cfg(windows)]
+pub fn wait(handle: RawHandle, duration: Option<Duration>) -> io::Result<()> {
+    let timeout = match duration {
+        Some(duration) => duration.total_millis().min(u64::from(imp::INFINITE - 1)) as u32,
+        None => imp::INFINITE,
+    };
+    let res = unsafe { imp::WaitForSingleObject(handle, timeout) };
+    if res == imp::WAIT_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
 #[cfg(all(
     any(feature = "phy-tuntap_interface", feature = "phy-raw_socket"),
     unix
@@ -0,0 +1,210 @@
+use crate::phy::Medium;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uchar, c_uint};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawHandle, RawHandle};
+use std::string::String;
+use std::{io, ptr, slice};
+
+const PCAP_ERRBUF_SIZE: usize = 256;
+const PCAP_NETMASK_UNKNOWN: u32 = 0xffffffff;
+const PCAP_D_IN: c_int = 1;
+
+#[cfg(feature = "medium-ethernet")]
+const DLT_EN10MB: c_int = 1;
+#[cfg(feature = "medium-ip")]
+const DLT_RAW: c_int = 12;
+#[cfg(feature = "medium-ip")]
+const DLT_RAW_OPENBSD: c_int = 14;
+#[cfg(feature = "medium-ip")]
+const LINKTYPE_RAW: c_int = 101;
+#[cfg(feature = "medium-ieee802154")]
+const DLT_IEEE802_15_4_NOFCS: c_int = 230;
+
+#[allow(non_camel_case_types)]
+enum pcap_t {}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct pcap_pkthdr {
+    ts: libc::timeval,
+    caplen: u32,
+    len: u32,
+}
+
+#[allow(non_camel_case_types)]
+#[repr(C)]
+struct bpf_program {
+    bf_len: c_uint,
+    bf_insns: *mut libc::c_void,
+}
+
+// On Windows, the libpcap API is provided by Npcap, as `wpcap`.
+#[cfg_attr(not(windows), link(name = "pcap"))]
+#[cfg_attr(windows, link(name = "wpcap"))]
+extern "C" {
+    fn pcap_open_live(
+        device: *const c_char,
+        snaplen: c_int,
+        promisc: c_int,
+        to_ms: c_int,
+        errbuf: *mut c_char,
+    ) -> *mut pcap_t;
+    fn pcap_close(p: *mut pcap_t);
+    fn pcap_setnonblock(p: *mut pcap_t, nonblock: c_int, errbuf: *mut c_char) -> c_int;
+    fn pcap_setdirection(p: *mut pcap_t, d: c_int) -> c_int;
+    fn pcap_datalink(p: *mut pcap_t) -> c_int;
+    #[cfg(unix)]
+    fn pcap_get_selectable_fd(p: *mut pcap_t) -> c_int;
+    #[cfg(windows)]
+    fn pcap_getevent(p: *mut pcap_t) -> RawHandle;
+    fn pcap_compile(
+        p: *mut pcap_t,
+        fp: *mut bpf_program,
+        str: *const c_char,
+        optimize: c_int,
+        netmask: u32,
+    ) -> c_int;
+    fn pcap_setfilter(p: *mut pcap_t, fp: *mut bpf_program) -> c_int;
+    fn pcap_freecode(fp: *mut bpf_program);
+    fn pcap_next_ex(
+        p: *mut pcap_t,
+        pkt_header: *mut *mut pcap_pkthdr,
+        pkt_data: *mut *const c_uchar,
+    ) -> c_int;
+    fn pcap_sendpacket(p: *mut pcap_t, buf: *const c_uchar, size: c_int) -> c_int;
+    fn pcap_geterr(p: *mut pcap_t) -> *mut c_char;
+}
+
+fn pcap_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+fn errbuf_error(errbuf: &[c_char; PCAP_ERRBUF_SIZE]) -> io::Error {
+    let message = unsafe { CStr::from_ptr(errbuf.as_ptr()) };
+    pcap_error(message.to_string_lossy().into_owned())
+}
+
+#[derive(Debug)]
+pub struct PcapDesc {
+    handle: *mut pcap_t,
+    medium: Medium,
+}
+
+#[cfg(unix)]
+impl AsRawFd for PcapDesc {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { pcap_get_selectable_fd(self.handle) }
+    }
+}
+
+#[cfg(windows)]
+impl AsRawHandle for PcapDesc {
+    fn as_raw_handle(&self) -> RawHandle {
+        unsafe { pcap_getevent(self.handle) }
+    }
+}
+
+impl PcapDesc {
+    pub fn new(name: &str) -> io::Result<PcapDesc> {
+        let name = CString::new(name)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid interface name"))?;
+        let mut errbuf = [0 as c_char; PCAP_ERRBUF_SIZE];
+
+        let handle = unsafe { pcap_open_live(name.as_ptr(), 65535, 1, 1, errbuf.as_mut_ptr()) };
+        if handle.is_null() {
+            return Err(errbuf_error(&errbuf));
+        }
+        // From here on, dropping `desc` closes the handle.
+        let mut desc = PcapDesc {
+            handle,
+            medium: Medium::default(),
+        };
+
+        desc.medium = match unsafe { pcap_datalink(handle) } {
+            #[cfg(feature = "medium-ethernet")]
+            DLT_EN10MB => Medium::Ethernet,
+            #[cfg(feature = "medium-ip")]
+            DLT_RAW | DLT_RAW_OPENBSD | LINKTYPE_RAW => Medium::Ip,
+            #[cfg(feature = "medium-ieee802154")]
+            DLT_IEEE802_15_4_NOFCS => Medium::Ieee802154,
+            link_type => return Err(pcap_error(format!("unsupported link type {}", link_type))),
+        };
+
+        if unsafe { pcap_setnonblock(handle, 1, errbuf.as_mut_ptr()) } == -1 {
+            return Err(errbuf_error(&errbuf));
+        }
+        // Not every platform can restrict the capture to incoming frames; where it cannot,
+        // the interface sees its own frames, and ignores them.
+        unsafe { pcap_setdirection(handle, PCAP_D_IN) };
+
+        Ok(desc)
+    }
+
+    fn last_error(&self) -> io::Error {
+        let message = unsafe { CStr::from_ptr(pcap_geterr(self.handle)) };
+        pcap_error(message.to_string_lossy().into_owned())
+    }
+
+    pub fn medium(&self) -> Medium {
+        self.medium
+    }
+
+    pub fn set_filter(&mut self, filter: &str) -> io::Result<()> {
+        let filter = CString::new(filter)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid filter"))?;
+        let mut program = bpf_program {
+            bf_len: 0,
+            bf_insns: ptr::null_mut(),
+        };
+        unsafe {
+            if pcap_compile(
+                self.handle,
+                &mut program,
+                filter.as_ptr(),
+                1,
+                PCAP_NETMASK_UNKNOWN,
+            ) == -1
+            {
+                return Err(self.last_error());
+            }
+            let res = pcap_setfilter(self.handle, &mut program);
+            pcap_freecode(&mut program);
+            if res == -1 {
+                return Err(self.last_error());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn recv(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let mut header: *mut pcap_pkthdr = ptr::null_mut();
+        let mut data: *const c_uchar = ptr::null();
+        match unsafe { pcap_next_ex(self.handle, &mut header, &mut data) } {
+            1 => {
+                let packet = unsafe { slice::from_raw_parts(data, (*header).caplen as usize) };
+                let len = packet.len().min(buffer.len());
+                buffer[..len].copy_from_slice(&packet[..len]);
+                Ok(len)
+            }
+            0 => Err(io::ErrorKind::WouldBlock.into()),
+            _ => Err(self.last_error()),
+        }
+    }
+
+    pub fn send(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let res = unsafe { pcap_sendpacket(self.handle, buffer.as_ptr(), buffer.len() as c_int) };
+        if res == -1 {
+            return Err(self.last_error());
+        }
+        Ok(buffer.len())
+    }
+}
+
+impl Drop for PcapDesc {
+    fn drop(&mut self) {
+        unsafe { pcap_close(self.handle) }
+    }
+}
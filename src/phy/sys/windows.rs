@@ -0,0 +1,11 @@
+#![allow(unused)]
+
+use std::os::windows::io::RawHandle;
+
+pub const INFINITE: u32 = 0xFFFFFFFF;
+pub const WAIT_FAILED: u32 = 0xFFFFFFFF;
+
+#[link(name = "kernel32")]
+extern "system" {
+    pub fn WaitForSingleObject(handle: RawHandle, milliseconds: u32) -> u32;
+}
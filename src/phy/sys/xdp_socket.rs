@@ -0,0 +1,376 @@
+use super::*;
+use std::ffi::CString;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::vec::Vec;
+use std::{io, mem, ptr};
+
+/// A ring shared with the kernel, as mapped from the socket.
+#[derive(Debug)]
+struct Ring<T> {
+    map: *mut libc::c_void,
+    map_len: usize,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    flags: *const AtomicU32,
+    descs: *mut T,
+    size: u32,
+}
+
+impl<T: Copy> Ring<T> {
+    fn map(
+        fd: libc::c_int,
+        offsets: &imp::xdp_ring_offset,
+        size: u32,
+        page_offset: libc::off_t,
+    ) -> io::Result<Ring<T>> {
+        let map_len = offsets.desc as usize + size as usize * mem::size_of::<T>();
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                page_offset,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let at = |offset: u64| unsafe { (map as *mut u8).add(offset as usize) };
+        Ok(Ring {
+            map,
+            map_len,
+            producer: at(offsets.producer) as *const AtomicU32,
+            consumer: at(offsets.consumer) as *const AtomicU32,
+            flags: at(offsets.flags) as *const AtomicU32,
+            descs: at(offsets.desc) as *mut T,
+            size,
+        })
+    }
+
+    fn producer(&self) -> &AtomicU32 {
+        unsafe { &*self.producer }
+    }
+
+    fn consumer(&self) -> &AtomicU32 {
+        unsafe { &*self.consumer }
+    }
+
+    fn needs_wakeup(&self) -> bool {
+        unsafe { (*self.flags).load(Ordering::Relaxed) & imp::XDP_RING_NEED_WAKEUP != 0 }
+    }
+
+    /// Return the number of entries that can be consumed, for a ring produced by the kernel.
+    fn available(&self) -> u32 {
+        let producer = self.producer().load(Ordering::Acquire);
+        producer.wrapping_sub(self.consumer().load(Ordering::Relaxed))
+    }
+
+    /// Return the number of entries that can be produced, for a ring consumed by the kernel.
+    fn free(&self) -> u32 {
+        let consumer = self.consumer().load(Ordering::Acquire);
+        self.size
+            - self
+                .producer()
+                .load(Ordering::Relaxed)
+                .wrapping_sub(consumer)
+    }
+
+    /// Consume an entry; there must be one available.
+    fn pop(&mut self) -> T {
+        let consumer = self.consumer().load(Ordering::Relaxed);
+        let desc = unsafe { *self.descs.add((consumer & (self.size - 1)) as usize) };
+        self.consumer()
+            .store(consumer.wrapping_add(1), Ordering::Release);
+        desc
+    }
+
+    /// Produce an entry; there must be room for it.
+    fn push(&mut self, desc: T) {
+        let producer = self.producer().load(Ordering::Relaxed);
+        unsafe { *self.descs.add((producer & (self.size - 1)) as usize) = desc };
+        self.producer()
+            .store(producer.wrapping_add(1), Ordering::Release);
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.map, self.map_len);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct XdpSocketDesc {
+    lower: libc::c_int,
+    umem: *mut u8,
+    umem_len: usize,
+    frame_size: u32,
+    rx: Ring<imp::xdp_desc>,
+    tx: Ring<imp::xdp_desc>,
+    fill: Ring<u64>,
+    completion: Ring<u64>,
+    tx_frames: Vec<u64>,
+}
+
+impl AsRawFd for XdpSocketDesc {
+    fn as_raw_fd(&self) -> RawFd {
+        self.lower
+    }
+}
+
+fn setsockopt<T>(fd: libc::c_int, name: libc::c_int, value: &T) -> io::Result<()> {
+    let res = unsafe {
+        libc::setsockopt(
+            fd,
+            imp::SOL_XDP,
+            name,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if res == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl XdpSocketDesc {
+    /// Create a socket bound to queue `queue_id` of the interface called `name`, with a
+    /// umem of `frame_count` frames of `frame_size` octets, half of which are used
+    /// for reception, and rings of `frame_count / 2` entries.
+    pub fn new(
+        name: &str,
+        queue_id: u32,
+        frame_count: u32,
+        frame_size: u32,
+    ) -> io::Result<XdpSocketDesc> {
+        let invalid = |msg| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        if !frame_count.is_power_of_two() || frame_count < 2 {
+            return Err(invalid("frame count must be a power of two"));
+        }
+        if !frame_size.is_power_of_two() || frame_size < 2048 {
+            return Err(invalid(
+                "frame size must be a power of two of at least 2048",
+            ));
+        }
+        let ifname = CString::new(name).map_err(|_| invalid("invalid interface name"))?;
+        let ifindex = unsafe { libc::if_nametoindex(ifname.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let lower = unsafe { libc::socket(imp::AF_XDP, libc::SOCK_RAW, 0) };
+        if lower == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let umem_len = frame_count as usize * frame_size as usize;
+        let umem = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                umem_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if umem == libc::MAP_FAILED {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(lower) };
+            return Err(err);
+        }
+        let desc = match Self::setup(lower, umem, umem_len, frame_count, frame_size) {
+            Ok(desc) => desc,
+            Err(err) => {
+                unsafe {
+                    libc::munmap(umem, umem_len);
+                    libc::close(lower);
+                }
+                return Err(err);
+            }
+        };
+
+        // From here on, dropping `desc` releases the socket and the umem.
+        let sockaddr = imp::sockaddr_xdp {
+            sxdp_family: imp::AF_XDP as u16,
+            sxdp_flags: imp::XDP_USE_NEED_WAKEUP,
+            sxdp_ifindex: ifindex,
+            sxdp_queue_id: queue_id,
+            sxdp_shared_umem_fd: 0,
+        };
+        let res = unsafe {
+            libc::bind(
+                lower,
+                &sockaddr as *const imp::sockaddr_xdp as *const libc::sockaddr,
+                mem::size_of::<imp::sockaddr_xdp>() as libc::socklen_t,
+            )
+        };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(desc)
+    }
+
+    fn setup(
+        lower: libc::c_int,
+        umem: *mut libc::c_void,
+        umem_len: usize,
+        frame_count: u32,
+        frame_size: u32,
+    ) -> io::Result<XdpSocketDesc> {
+        let ring_size = frame_count / 2;
+        setsockopt(
+            lower,
+            imp::XDP_UMEM_REG,
+            &imp::xdp_umem_reg {
+                addr: umem as u64,
+                len: umem_len as u64,
+                chunk_size: frame_size,
+                headroom: 0,
+                flags: 0,
+            },
+        )?;
+        setsockopt(lower, imp::XDP_UMEM_FILL_RING, &ring_size)?;
+        setsockopt(lower, imp::XDP_UMEM_COMPLETION_RING, &ring_size)?;
+        setsockopt(lower, imp::XDP_RX_RING, &ring_size)?;
+        setsockopt(lower, imp::XDP_TX_RING, &ring_size)?;
+
+        let mut offsets: imp::xdp_mmap_offsets = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<imp::xdp_mmap_offsets>() as libc::socklen_t;
+        let res = unsafe {
+            libc::getsockopt(
+                lower,
+                imp::SOL_XDP,
+                imp::XDP_MMAP_OFFSETS,
+                &mut offsets as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut desc = XdpSocketDesc {
+            lower,
+            umem: umem as *mut u8,
+            umem_len,
+            frame_size,
+            rx: Ring::map(lower, &offsets.rx, ring_size, imp::XDP_PGOFF_RX_RING)?,
+            tx: Ring::map(lower, &offsets.tx, ring_size, imp::XDP_PGOFF_TX_RING)?,
+            fill: Ring::map(lower, &offsets.fr, ring_size, imp::XDP_UMEM_PGOFF_FILL_RING)?,
+            completion: Ring::map(
+                lower,
+                &offsets.cr,
+                ring_size,
+                imp::XDP_UMEM_PGOFF_COMPLETION_RING,
+            )?,
+            tx_frames: Vec::with_capacity(ring_size as usize),
+        };
+        // The first half of the frames receive, the second half transmit.
+        for index in 0..ring_size {
+            desc.fill.push(u64::from(index * frame_size));
+            desc.tx_frames
+                .push(u64::from((ring_size + index) * frame_size));
+        }
+        Ok(desc)
+    }
+
+    /// Return the largest frame that can be received or transmitted.
+    pub fn frame_size(&self) -> usize {
+        self.frame_size as usize
+    }
+
+    fn frame(&mut self, addr: u64, len: usize) -> &mut [u8] {
+        assert!(addr as usize + len <= self.umem_len);
+        unsafe { std::slice::from_raw_parts_mut(self.umem.add(addr as usize), len) }
+    }
+
+    /// Return whether a frame was received.
+    pub fn can_recv(&mut self) -> bool {
+        if self.rx.available() == 0 && self.fill.needs_wakeup() {
+            // Let the kernel know that the fill ring has entries.
+            unsafe {
+                libc::recvfrom(
+                    self.lower,
+                    ptr::null_mut(),
+                    0,
+                    libc::MSG_DONTWAIT,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+            }
+        }
+        self.rx.available() > 0
+    }
+
+    /// Call `f` with the next received frame, and return its frame to the fill ring.
+    pub fn recv<R, F: FnOnce(&mut [u8]) -> R>(&mut self, f: F) -> Option<R> {
+        if self.rx.available() == 0 {
+            return None;
+        }
+        let desc = self.rx.pop();
+        let result = f(self.frame(desc.addr, desc.len as usize));
+        // The fill ring has as many entries as there are frames for reception.
+        let frame = desc.addr - desc.addr % u64::from(self.frame_size);
+        self.fill.push(frame);
+        Some(result)
+    }
+
+    /// Return whether a frame can be transmitted, reclaiming the frames that the kernel
+    /// finished transmitting.
+    pub fn can_send(&mut self) -> bool {
+        while self.completion.available() > 0 {
+            let addr = self.completion.pop();
+            self.tx_frames.push(addr);
+        }
+        !self.tx_frames.is_empty() && self.tx.free() > 0
+    }
+
+    /// Call `f` with a free frame of `len` octets, and transmit it if `f` succeeds.
+    pub fn send<R, E, F>(&mut self, len: usize, f: F) -> Option<core::result::Result<R, E>>
+    where
+        F: FnOnce(&mut [u8]) -> core::result::Result<R, E>,
+    {
+        if len > self.frame_size() || !self.can_send() {
+            return None;
+        }
+        let addr = self.tx_frames.pop()?;
+        let result = f(self.frame(addr, len));
+        if result.is_err() {
+            self.tx_frames.push(addr);
+            return Some(result);
+        }
+        self.tx.push(imp::xdp_desc {
+            addr,
+            len: len as u32,
+            options: 0,
+        });
+        if self.tx.needs_wakeup() {
+            unsafe {
+                libc::sendto(
+                    self.lower,
+                    ptr::null(),
+                    0,
+                    libc::MSG_DONTWAIT,
+                    ptr::null(),
+                    0,
+                );
+            }
+        }
+        Some(result)
+    }
+}
+
+impl Drop for XdpSocketDesc {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.lower);
+            libc::munmap(self.umem as *mut libc::c_void, self.umem_len);
+        }
+    }
+}
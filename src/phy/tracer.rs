@@ -1,6 +1,6 @@
 use core::fmt;
 
-use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::phy::{self, Device, DeviceCapabilities, LinkState, Medium};
 use crate::time::Instant;
 use crate::{
     wire::pretty_print::{PrettyIndent, PrettyPrint},
@@ -55,6 +55,10 @@ where
         self.inner.capabilities()
     }
 
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
     fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
         let &mut Self {
             ref mut inner,
@@ -115,11 +119,16 @@ impl<Rx: phy::RxToken> phy::RxToken for RxToken<Rx> {
                     buffer,
                     medium,
                     prefix: "<- ",
+                    compact: false,
                 },
             );
             f(buffer)
         })
     }
+
+    fn meta(&self) -> phy::PacketMeta {
+        self.token.meta()
+    }
 }
 
 #[doc(hidden)]
@@ -131,6 +140,19 @@ pub struct TxToken<Tx: phy::TxToken> {
 
 impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
     fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        self.consume_timestamped(timestamp, len, f)
+            .map(|(result, _)| result)
+    }
+
+    fn consume_timestamped<R, F>(
+        self,
+        timestamp: Instant,
+        len: usize,
+        f: F,
+    ) -> Result<(R, Option<Instant>)>
     where
         F: FnOnce(&mut [u8]) -> Result<R>,
     {
@@ -139,7 +161,7 @@ impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
             writer,
             medium,
         } = self;
-        token.consume(timestamp, len, |buffer| {
+        token.consume_timestamped(timestamp, len, |buffer| {
             let result = f(buffer);
             writer(
                 timestamp,
@@ -147,22 +169,42 @@ impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
                     buffer,
                     medium,
                     prefix: "-> ",
+                    compact: false,
                 },
             );
             result
         })
     }
+
+    fn set_meta(&mut self, meta: phy::PacketMeta) {
+        self.token.set_meta(meta)
+    }
 }
 
 pub struct Packet<'a> {
     buffer: &'a [u8],
     medium: Medium,
     prefix: &'static str,
+    compact: bool,
+}
+
+impl<'a> Packet<'a> {
+    /// Print the packet on a single line, with nested packets separated by ` > `.
+    pub fn compact(self) -> Packet<'a> {
+        Packet {
+            compact: true,
+            ..self
+        }
+    }
 }
 
 impl<'a> fmt::Display for Packet<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut indent = PrettyIndent::new(self.prefix);
+        let mut indent = if self.compact {
+            PrettyIndent::new_compact(self.prefix)
+        } else {
+            PrettyIndent::new(self.prefix)
+        };
         match self.medium {
             #[cfg(feature = "medium-ethernet")]
             Medium::Ethernet => crate::wire::EthernetFrame::<&'static [u8]>::pretty_print(
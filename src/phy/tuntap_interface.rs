@@ -4,16 +4,20 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::rc::Rc;
 use std::vec::Vec;
 
-use crate::phy::{self, sys, Device, DeviceCapabilities, Medium};
+use crate::phy::{self, segmentation, sys, Device, DeviceCapabilities, Medium, PacketMeta};
 use crate::time::Instant;
 use crate::Result;
 
+/// Maximum length of an IP packet split by the device with TCP segmentation offload.
+const MAX_SEGMENTATION_LEN: usize = 65535;
+
 /// A virtual TUN (IP) or TAP (Ethernet) interface.
 #[derive(Debug)]
 pub struct TunTapInterface {
     lower: Rc<RefCell<sys::TunTapInterfaceDesc>>,
     mtu: usize,
     medium: Medium,
+    tcp_segmentation_offload: bool,
 }
 
 impl AsRawFd for TunTapInterface {
@@ -36,8 +40,33 @@ impl TunTapInterface {
             lower: Rc::new(RefCell::new(lower)),
             mtu,
             medium,
+            tcp_segmentation_offload: false,
         })
     }
+
+    /// Return whether TCP segmentation offload is enabled.
+    ///
+    /// See also [set_tcp_segmentation_offload](#method.set_tcp_segmentation_offload).
+    pub fn tcp_segmentation_offload(&self) -> bool {
+        self.tcp_segmentation_offload
+    }
+
+    /// Enable or disable TCP segmentation offload.
+    ///
+    /// When enabled, TCP sockets hand the device segments of up to 64 KiB, which it splits
+    /// into frames of the MTU before writing them to the interface. This is disabled
+    /// by default.
+    pub fn set_tcp_segmentation_offload(&mut self, enabled: bool) {
+        self.tcp_segmentation_offload = enabled
+    }
+
+    fn tx_token(&self) -> TxToken {
+        TxToken {
+            lower: self.lower.clone(),
+            medium: self.medium,
+            meta: PacketMeta::default(),
+        }
+    }
 }
 
 impl<'a> Device<'a> for TunTapInterface {
@@ -48,6 +77,11 @@ impl<'a> Device<'a> for TunTapInterface {
         DeviceCapabilities {
             max_transmission_unit: self.mtu,
             medium: self.medium,
+            tcp_segmentation_offload: if self.tcp_segmentation_offload {
+                Some(MAX_SEGMENTATION_LEN)
+            } else {
+                None
+            },
             ..DeviceCapabilities::default()
         }
     }
@@ -59,10 +93,7 @@ impl<'a> Device<'a> for TunTapInterface {
             Ok(size) => {
                 buffer.resize(size, 0);
                 let rx = RxToken { buffer };
-                let tx = TxToken {
-                    lower: self.lower.clone(),
-                };
-                Some((rx, tx))
+                Some((rx, self.tx_token()))
             }
             Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => None,
             Err(err) => panic!("{}", err),
@@ -70,9 +101,7 @@ impl<'a> Device<'a> for TunTapInterface {
     }
 
     fn transmit(&'a mut self) -> Option<Self::TxToken> {
-        Some(TxToken {
-            lower: self.lower.clone(),
-        })
+        Some(self.tx_token())
     }
 }
 
@@ -93,6 +122,8 @@ impl phy::RxToken for RxToken {
 #[doc(hidden)]
 pub struct TxToken {
     lower: Rc<RefCell<sys::TunTapInterfaceDesc>>,
+    medium: Medium,
+    meta: PacketMeta,
 }
 
 impl phy::TxToken for TxToken {
@@ -103,7 +134,20 @@ impl phy::TxToken for TxToken {
         let mut lower = self.lower.borrow_mut();
         let mut buffer = vec![0; len];
         let result = f(&mut buffer);
-        lower.send(&buffer[..]).unwrap();
+        match self.meta.segment_size {
+            Some(segment_size) => {
+                segmentation::segment_tcp(self.medium, &buffer, segment_size, |frame| {
+                    lower.send(frame).unwrap();
+                })
+            }
+            None => {
+                lower.send(&buffer[..]).unwrap();
+            }
+        }
         result
     }
+
+    fn set_meta(&mut self, meta: PacketMeta) {
+        self.meta = meta
+    }
 }
@@ -0,0 +1,743 @@
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::phy::{self, Device, DeviceCapabilities, Medium};
+use crate::time::Instant;
+use crate::{Error, Result};
+
+/// The bulk endpoints of the data interface of a USB Ethernet function.
+///
+/// This trait maps directly onto the `EndpointOut::read` and `EndpointIn::write` methods of
+/// the [usb-device] crate, with `UsbError::WouldBlock` translated to `None` and `false`;
+/// the class descriptors and control requests of the function are left to the USB class
+/// implementation.
+///
+/// [usb-device]: https://docs.rs/usb-device
+pub trait UsbEndpoints {
+    /// Return the maximum packet size of the bulk endpoints.
+    fn max_packet_size(&self) -> usize;
+
+    /// Read a packet from the OUT endpoint into `buffer`, which is at least
+    /// `max_packet_size()` octets long.
+    ///
+    /// Returns the length of the packet, or `None` if no packet is available.
+    fn read_packet(&mut self, buffer: &mut [u8]) -> Option<usize>;
+
+    /// Write a packet, at most `max_packet_size()` octets long, to the IN endpoint.
+    ///
+    /// Returns `false` if the endpoint is busy, in which case the packet must be written again.
+    fn write_packet(&mut self, packet: &[u8]) -> bool;
+}
+
+/// Framing of Ethernet frames in USB transfers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsbEthernetMode {
+    /// CDC-ECM: each transfer carries a single Ethernet frame.
+    Ecm,
+    /// CDC-NCM: each transfer carries an NTB-16 Transfer Block, with any number of
+    /// Ethernet frames.
+    Ncm,
+    /// RNDIS: each transfer carries one or more `REMOTE_NDIS_PACKET_MSG` messages, each
+    /// with an Ethernet frame. The control channel is handled by [Rndis](struct.Rndis.html).
+    Rndis,
+}
+
+impl UsbEthernetMode {
+    /// Return the length of the headers preceding a transmitted frame.
+    pub(crate) fn overhead(&self) -> usize {
+        match self {
+            UsbEthernetMode::Ecm => 0,
+            UsbEthernetMode::Ncm => NTB_OVERHEAD,
+            UsbEthernetMode::Rndis => RNDIS_PACKET_HEADER_LEN,
+        }
+    }
+}
+
+const NTH16_SIGNATURE: u32 = 0x484d_434e; // "NCMH"
+const NTH16_LEN: usize = 12;
+const NDP16_SIGNATURE: u32 = 0x304d_434e; // "NCM0"
+                                          // An NDP16 with a single datagram pointer and the terminating null entry.
+const NDP16_LEN: usize = 16;
+const NTB_OVERHEAD: usize = NTH16_LEN + NDP16_LEN;
+const RNDIS_PACKET_MSG: u32 = 0x0000_0001;
+const RNDIS_PACKET_HEADER_LEN: usize = 44;
+
+/// A USB CDC-ECM or CDC-NCM device.
+///
+/// This device carries Ethernet frames over the bulk endpoints of a USB Ethernet function,
+/// allowing USB gadget firmware to use the host as its link partner. Transfers are
+/// reassembled into `rx_buffer` and sent from `tx_buffer`, both of which must be able to
+/// hold a whole transfer; in NCM mode a received NTB may contain several frames, but each
+/// transmitted NTB contains a single frame.
+///
+/// Transmission of a transfer continues in later calls to `receive` and `transmit` if the IN
+/// endpoint is busy, and no transmit token is returned until it is complete.
+pub struct UsbEthernet<'a, E: UsbEndpoints> {
+    endpoints: E,
+    mode: UsbEthernetMode,
+    rx_buffer: &'a mut [u8],
+    rx_len: usize,
+    rx_complete: bool,
+    rx_discard: bool,
+    rx_cursor: Option<NtbCursor>,
+    tx_buffer: &'a mut [u8],
+    tx: TxState,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct NtbCursor {
+    ndp_index: usize,
+    entry: usize,
+}
+
+#[derive(Debug, Default)]
+struct TxState {
+    len: usize,
+    sent: usize,
+    zlp: bool,
+    sequence: u16,
+}
+
+impl<'a, E: UsbEndpoints> UsbEthernet<'a, E> {
+    /// Create a USB Ethernet device.
+    ///
+    /// The maximum frame size of the device is the length of the transmit buffer, less the
+    /// framing overhead of the mode. Jumbo frames are supported if the transmit buffer holds
+    /// them, and the receive buffer holds them rounded up to the maximum packet size of the
+    /// endpoints.
+    ///
+    /// # Panics
+    /// This function panics if either buffer is shorter than the maximum packet size of
+    /// the endpoints.
+    pub fn new(
+        endpoints: E,
+        mode: UsbEthernetMode,
+        rx_buffer: &'a mut [u8],
+        tx_buffer: &'a mut [u8],
+    ) -> UsbEthernet<'a, E> {
+        let max_packet_size = endpoints.max_packet_size();
+        assert!(
+            rx_buffer.len() >= max_packet_size && tx_buffer.len() >= max_packet_size,
+            "buffers must hold at least one USB packet"
+        );
+        UsbEthernet {
+            endpoints,
+            mode,
+            rx_buffer,
+            rx_len: 0,
+            rx_complete: false,
+            rx_discard: false,
+            rx_cursor: None,
+            tx_buffer,
+            tx: TxState::default(),
+        }
+    }
+
+    /// Return the framing mode.
+    pub fn mode(&self) -> UsbEthernetMode {
+        self.mode
+    }
+
+    /// Get a reference to the endpoints.
+    pub fn get_ref(&self) -> &E {
+        &self.endpoints
+    }
+
+    /// Get a mutable reference to the endpoints.
+    ///
+    /// It is inadvisable to directly read from the endpoints as doing so will corrupt
+    /// the reassembly of transfers.
+    pub fn get_mut(&mut self) -> &mut E {
+        &mut self.endpoints
+    }
+
+    /// Discard any partially received or transmitted transfer, e.g. after the host reset
+    /// or reconfigured the function.
+    pub fn reset(&mut self) {
+        self.rx_len = 0;
+        self.rx_complete = false;
+        self.rx_discard = false;
+        self.rx_cursor = None;
+        self.tx.len = 0;
+        self.tx.sent = 0;
+        self.tx.zlp = false;
+    }
+
+    /// Read packets from the OUT endpoint until a transfer is complete or no packet
+    /// is available.
+    fn fill(&mut self) {
+        let max_packet_size = self.endpoints.max_packet_size();
+        while !self.rx_complete {
+            if self.rx_buffer.len() - self.rx_len < max_packet_size {
+                // The transfer does not fit; drop it, reusing the buffer for its remainder.
+                net_debug!("usb: transfer too long, discarding");
+                self.rx_len = 0;
+                self.rx_discard = true;
+            }
+            let len = match self
+                .endpoints
+                .read_packet(&mut self.rx_buffer[self.rx_len..])
+            {
+                Some(len) => len,
+                None => return,
+            };
+            self.rx_len += len;
+            if len < max_packet_size {
+                if self.rx_discard {
+                    self.rx_len = 0;
+                    self.rx_discard = false;
+                } else if self.rx_len > 0 {
+                    self.rx_complete = true;
+                    self.rx_cursor = None;
+                }
+            }
+        }
+    }
+
+    /// Return the range of the next frame in the received transfer, or `None` if all of
+    /// its frames have been received.
+    fn next_frame(&mut self) -> Option<(usize, usize)> {
+        let transfer = &self.rx_buffer[..self.rx_len];
+        match self.mode {
+            UsbEthernetMode::Ecm => {
+                if self.rx_cursor.is_some() {
+                    return None;
+                }
+                self.rx_cursor = Some(NtbCursor::default());
+                Some((0, self.rx_len))
+            }
+            UsbEthernetMode::Ncm => {
+                let mut cursor = match self.rx_cursor {
+                    Some(cursor) => cursor,
+                    None => match parse_nth16(transfer) {
+                        Ok(ndp_index) => NtbCursor {
+                            ndp_index,
+                            entry: 0,
+                        },
+                        Err(_) => {
+                            net_debug!("usb: invalid NTB header");
+                            return None;
+                        }
+                    },
+                };
+                loop {
+                    match parse_ndp16_entry(transfer, cursor) {
+                        Ok(NdpEntry::Datagram(start, len)) => {
+                            cursor.entry += 1;
+                            self.rx_cursor = Some(cursor);
+                            return Some((start, len));
+                        }
+                        Ok(NdpEntry::Next(0)) => return None,
+                        Ok(NdpEntry::Next(ndp_index)) if ndp_index > cursor.ndp_index => {
+                            cursor = NtbCursor {
+                                ndp_index,
+                                entry: 0,
+                            }
+                        }
+                        Ok(NdpEntry::Next(_)) | Err(_) => {
+                            net_debug!("usb: invalid NDP");
+                            return None;
+                        }
+                    }
+                }
+            }
+            UsbEthernetMode::Rndis => {
+                let offset = self.rx_cursor.map(|cursor| cursor.ndp_index).unwrap_or(0);
+                match parse_rndis_packet(transfer, offset) {
+                    Ok(Some((start, len, next))) => {
+                        self.rx_cursor = Some(NtbCursor {
+                            ndp_index: next,
+                            entry: 0,
+                        });
+                        Some((start, len))
+                    }
+                    Ok(None) => None,
+                    Err(_) => {
+                        net_debug!("usb: invalid RNDIS packet message");
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write the pending transfer to the IN endpoint, until it is complete or the
+    /// endpoint is busy.
+    ///
+    /// Returns whether no transfer is pending.
+    fn flush(endpoints: &mut E, tx_buffer: &[u8], tx: &mut TxState) -> bool {
+        let max_packet_size = endpoints.max_packet_size();
+        while tx.sent < tx.len || tx.zlp {
+            let end = tx.len.min(tx.sent + max_packet_size);
+            if !endpoints.write_packet(&tx_buffer[tx.sent..end]) {
+                return false;
+            }
+            if tx.sent == end {
+                tx.zlp = false;
+            }
+            tx.sent = end;
+        }
+        true
+    }
+}
+
+impl<'a, 'b, E: UsbEndpoints + 'a> Device<'a> for UsbEthernet<'b, E> {
+    type RxToken = RxToken<'a>;
+    type TxToken = TxToken<'a, E>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            medium: Medium::Ethernet,
+            max_transmission_unit: self.tx_buffer.len().saturating_sub(self.mode.overhead()),
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let range = loop {
+            self.fill();
+            if !self.rx_complete {
+                return None;
+            }
+            match self.next_frame() {
+                Some(range) => break range,
+                None => {
+                    self.rx_len = 0;
+                    self.rx_complete = false;
+                }
+            }
+        };
+
+        let Self {
+            ref mut endpoints,
+            mode,
+            ref mut rx_buffer,
+            ref mut tx_buffer,
+            ref mut tx,
+            ..
+        } = *self;
+        Self::flush(endpoints, tx_buffer, tx);
+        let rx = RxToken {
+            buffer: &mut rx_buffer[range.0..range.0 + range.1],
+        };
+        let tx = TxToken {
+            endpoints,
+            mode,
+            buffer: tx_buffer,
+            state: tx,
+        };
+        Some((rx, tx))
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        let Self {
+            ref mut endpoints,
+            mode,
+            ref mut tx_buffer,
+            ref mut tx,
+            ..
+        } = *self;
+        if !Self::flush(endpoints, tx_buffer, tx) {
+            return None;
+        }
+        Some(TxToken {
+            endpoints,
+            mode,
+            buffer: tx_buffer,
+            state: tx,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<'a> {
+    buffer: &'a mut [u8],
+}
+
+impl<'a> phy::RxToken for RxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(self.buffer)
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<'a, E: UsbEndpoints> {
+    endpoints: &'a mut E,
+    mode: UsbEthernetMode,
+    buffer: &'a mut [u8],
+    state: &'a mut TxState,
+}
+
+impl<'a, E: UsbEndpoints> phy::TxToken for TxToken<'a, E> {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let TxToken {
+            endpoints,
+            mode,
+            buffer,
+            state,
+        } = self;
+        // A transfer may still be pending if this token was obtained from `receive`.
+        if !UsbEthernet::flush(endpoints, buffer, state) {
+            return Err(Error::Exhausted);
+        }
+
+        let offset = mode.overhead();
+        if offset + len > buffer.len() {
+            return Err(Error::Truncated);
+        }
+        let result = f(&mut buffer[offset..offset + len])?;
+
+        match mode {
+            UsbEthernetMode::Ecm => (),
+            UsbEthernetMode::Ncm => {
+                emit_ntb16_header(buffer, state.sequence, len);
+                state.sequence = state.sequence.wrapping_add(1);
+            }
+            UsbEthernetMode::Rndis => emit_rndis_packet_header(buffer, len),
+        }
+        state.len = offset + len;
+        state.sent = 0;
+        // A transfer that is a multiple of the packet size is terminated by a short packet.
+        state.zlp = state.len % endpoints.max_packet_size() == 0;
+        UsbEthernet::flush(endpoints, buffer, state);
+        Ok(result)
+    }
+}
+
+enum NdpEntry {
+    /// The offset and length of a datagram.
+    Datagram(usize, usize),
+    /// The offset of the next NDP, or zero if this is the last one.
+    Next(usize),
+}
+
+/// Check an NTH16 header and return the offset of the first NDP16.
+fn parse_nth16(transfer: &[u8]) -> Result<usize> {
+    if transfer.len() < NTH16_LEN
+        || LittleEndian::read_u32(&transfer[0..4]) != NTH16_SIGNATURE
+        || usize::from(LittleEndian::read_u16(&transfer[4..6])) != NTH16_LEN
+    {
+        return Err(Error::Unrecognized);
+    }
+    let block_len = usize::from(LittleEndian::read_u16(&transfer[8..10]));
+    if block_len > transfer.len() {
+        return Err(Error::Truncated);
+    }
+    Ok(usize::from(LittleEndian::read_u16(&transfer[10..12])))
+}
+
+/// Read the datagram pointer at `cursor` in an NDP16.
+fn parse_ndp16_entry(transfer: &[u8], cursor: NtbCursor) -> Result<NdpEntry> {
+    let ndp = transfer.get(cursor.ndp_index..).ok_or(Error::Truncated)?;
+    if ndp.len() < 8 || LittleEndian::read_u32(&ndp[0..4]) != NDP16_SIGNATURE {
+        return Err(Error::Unrecognized);
+    }
+    let ndp_len = usize::from(LittleEndian::read_u16(&ndp[4..6]));
+    let next = usize::from(LittleEndian::read_u16(&ndp[6..8]));
+    let entry = 8 + cursor.entry * 4;
+    if entry + 4 > ndp_len || ndp_len > ndp.len() {
+        return Ok(NdpEntry::Next(next));
+    }
+    let start = usize::from(LittleEndian::read_u16(&ndp[entry..entry + 2]));
+    let len = usize::from(LittleEndian::read_u16(&ndp[entry + 2..entry + 4]));
+    if start == 0 || len == 0 {
+        return Ok(NdpEntry::Next(next));
+    }
+    if start + len > transfer.len() {
+        return Err(Error::Truncated);
+    }
+    Ok(NdpEntry::Datagram(start, len))
+}
+
+/// Emit the NTH16 and NDP16 of an NTB carrying a single datagram of `len` octets.
+fn emit_ntb16_header(buffer: &mut [u8], sequence: u16, len: usize) {
+    let (nth, ndp) = buffer[..NTB_OVERHEAD].split_at_mut(NTH16_LEN);
+    LittleEndian::write_u32(&mut nth[0..4], NTH16_SIGNATURE);
+    LittleEndian::write_u16(&mut nth[4..6], NTH16_LEN as u16);
+    LittleEndian::write_u16(&mut nth[6..8], sequence);
+    LittleEndian::write_u16(&mut nth[8..10], (NTB_OVERHEAD + len) as u16);
+    LittleEndian::write_u16(&mut nth[10..12], NTH16_LEN as u16);
+    LittleEndian::write_u32(&mut ndp[0..4], NDP16_SIGNATURE);
+    LittleEndian::write_u16(&mut ndp[4..6], NDP16_LEN as u16);
+    LittleEndian::write_u16(&mut ndp[6..8], 0);
+    LittleEndian::write_u16(&mut ndp[8..10], NTB_OVERHEAD as u16);
+    LittleEndian::write_u16(&mut ndp[10..12], len as u16);
+    LittleEndian::write_u32(&mut ndp[12..16], 0);
+}
+
+/// Parse the `REMOTE_NDIS_PACKET_MSG` at `offset`, and return the offset and length of
+/// its frame and the offset of the next message, or `None` if there are no more messages.
+fn parse_rndis_packet(transfer: &[u8], offset: usize) -> Result<Option<(usize, usize, usize)>> {
+    // Hosts may pad transfers to avoid sending a zero-length packet.
+    let message = match transfer.get(offset..) {
+        Some(message) if message.len() >= 8 => message,
+        _ => return Ok(None),
+    };
+    if LittleEndian::read_u32(&message[0..4]) != RNDIS_PACKET_MSG {
+        return Err(Error::Unrecognized);
+    }
+    let message_len = LittleEndian::read_u32(&message[4..8]) as usize;
+    if message_len < RNDIS_PACKET_HEADER_LEN || message_len > message.len() {
+        return Err(Error::Truncated);
+    }
+    let data_offset = 8 + LittleEndian::read_u32(&message[8..12]) as usize;
+    let data_len = LittleEndian::read_u32(&message[12..16]) as usize;
+    if data_offset < RNDIS_PACKET_HEADER_LEN || data_offset + data_len > message_len {
+        return Err(Error::Malformed);
+    }
+    Ok(Some((offset + data_offset, data_len, offset + message_len)))
+}
+
+/// Emit the header of a `REMOTE_NDIS_PACKET_MSG` carrying a frame of `len` octets.
+fn emit_rndis_packet_header(buffer: &mut [u8], len: usize) {
+    let header = &mut buffer[..RNDIS_PACKET_HEADER_LEN];
+    header.fill(0);
+    LittleEndian::write_u32(&mut header[0..4], RNDIS_PACKET_MSG);
+    LittleEndian::write_u32(&mut header[4..8], (RNDIS_PACKET_HEADER_LEN + len) as u32);
+    LittleEndian::write_u32(&mut header[8..12], (RNDIS_PACKET_HEADER_LEN - 8) as u32);
+    LittleEndian::write_u32(&mut header[12..16], len as u32);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::{RxToken, TxToken};
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    const MAX_PACKET_SIZE: usize = 64;
+
+    /// Endpoints exchanging packets with a simulated host.
+    #[derive(Default)]
+    struct MockEndpoints {
+        out_packets: VecDeque<Vec<u8>>,
+        in_packets: Vec<Vec<u8>>,
+        busy: bool,
+    }
+
+    impl MockEndpoints {
+        /// Queue a transfer from the host, split into packets.
+        fn host_send(&mut self, transfer: &[u8]) {
+            // A transfer that does not end with a short packet ends with a zero-length one.
+            let mut short = false;
+            for packet in transfer.chunks(MAX_PACKET_SIZE) {
+                self.out_packets.push_back(packet.to_vec());
+                short = packet.len() < MAX_PACKET_SIZE;
+            }
+            if !short {
+                self.out_packets.push_back(Vec::new());
+            }
+        }
+    }
+
+    impl UsbEndpoints for MockEndpoints {
+        fn max_packet_size(&self) -> usize {
+            MAX_PACKET_SIZE
+        }
+
+        fn read_packet(&mut self, buffer: &mut [u8]) -> Option<usize> {
+            let packet = self.out_packets.pop_front()?;
+            buffer[..packet.len()].copy_from_slice(&packet);
+            Some(packet.len())
+        }
+
+        fn write_packet(&mut self, packet: &[u8]) -> bool {
+            if self.busy {
+                return false;
+            }
+            self.in_packets.push(packet.to_vec());
+            true
+        }
+    }
+
+    fn frame(len: usize, fill: u8) -> Vec<u8> {
+        vec![fill; len]
+    }
+
+    fn receive(device: &mut UsbEthernet<MockEndpoints>) -> Option<Vec<u8>> {
+        let (rx, _tx) = device.receive()?;
+        Some(rx.consume(Instant::ZERO, |buf| Ok(buf.to_vec())).unwrap())
+    }
+
+    fn send(device: &mut UsbEthernet<MockEndpoints>, frame: &[u8]) {
+        let tx = device.transmit().unwrap();
+        tx.consume(Instant::ZERO, frame.len(), |buf| {
+            buf.copy_from_slice(frame);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_ecm() {
+        let (mut rx_buffer, mut tx_buffer) = ([0; 1514], [0; 1514]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Ecm,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        assert_eq!(device.capabilities().max_transmission_unit, 1514);
+
+        device.get_mut().host_send(&frame(100, 1));
+        device.get_mut().host_send(&frame(128, 2));
+        assert_eq!(receive(&mut device), Some(frame(100, 1)));
+        assert_eq!(receive(&mut device), Some(frame(128, 2)));
+        assert_eq!(receive(&mut device), None);
+
+        send(&mut device, &frame(128, 3));
+        let packets = &device.get_ref().in_packets;
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[1], frame(64, 3));
+        assert!(packets[2].is_empty());
+    }
+
+    #[test]
+    fn test_ecm_jumbo() {
+        let (mut rx_buffer, mut tx_buffer) = (vec![0; 9014 + MAX_PACKET_SIZE], vec![0; 9014]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Ecm,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        assert_eq!(device.capabilities().max_transmission_unit, 9014);
+        assert_eq!(device.capabilities().ip_mtu(), 9000);
+
+        device.get_mut().host_send(&frame(9014, 1));
+        assert_eq!(receive(&mut device), Some(frame(9014, 1)));
+        send(&mut device, &frame(9014, 2));
+        let packets = core::mem::take(&mut device.get_mut().in_packets);
+        assert_eq!(packets.concat(), frame(9014, 2));
+    }
+
+    #[test]
+    fn test_ecm_too_long() {
+        let (mut rx_buffer, mut tx_buffer) = ([0; 128], [0; 128]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Ecm,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        device.get_mut().host_send(&frame(200, 1));
+        device.get_mut().host_send(&frame(60, 2));
+        assert_eq!(receive(&mut device), Some(frame(60, 2)));
+    }
+
+    /// The offset of an NDP, the offset of the next one, and the start and length of its
+    /// datagrams.
+    type Ndp<'a> = (usize, usize, &'a [(u16, u16)]);
+
+    #[test]
+    fn test_ncm_receive() {
+        // An NTB with two datagrams in the first NDP and one in a chained NDP.
+        let mut ntb = vec![0; 200];
+        LittleEndian::write_u32(&mut ntb[0..4], NTH16_SIGNATURE);
+        LittleEndian::write_u16(&mut ntb[4..6], 12);
+        LittleEndian::write_u16(&mut ntb[8..10], 200);
+        LittleEndian::write_u16(&mut ntb[10..12], 12);
+        let ndps: [Ndp; 2] = [(12, 40, &[(64, 20), (100, 30)]), (40, 0, &[(140, 50)])];
+        for &(offset, next, datagrams) in ndps.iter() {
+            LittleEndian::write_u32(&mut ntb[offset..offset + 4], NDP16_SIGNATURE);
+            LittleEndian::write_u16(&mut ntb[offset + 4..offset + 6], 16);
+            LittleEndian::write_u16(&mut ntb[offset + 6..offset + 8], next as u16);
+            for (i, &(start, len)) in datagrams.iter().enumerate() {
+                let entry = offset + 8 + i * 4;
+                LittleEndian::write_u16(&mut ntb[entry..entry + 2], start);
+                LittleEndian::write_u16(&mut ntb[entry + 2..entry + 4], len);
+                let start = usize::from(start);
+                ntb[start..start + usize::from(len)].fill(i as u8 + offset as u8);
+            }
+        }
+
+        let (mut rx_buffer, mut tx_buffer) = ([0; 512], [0; 512]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Ncm,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        device.get_mut().host_send(&ntb);
+        device.get_mut().host_send(b"garbage");
+        assert_eq!(receive(&mut device), Some(frame(20, 12)));
+        assert_eq!(receive(&mut device), Some(frame(30, 13)));
+        assert_eq!(receive(&mut device), Some(frame(50, 40)));
+        assert_eq!(receive(&mut device), None);
+        assert!(device.get_ref().out_packets.is_empty());
+    }
+
+    #[test]
+    fn test_ncm_transmit() {
+        let (mut rx_buffer, mut tx_buffer) = ([0; 512], [0; 512]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Ncm,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        assert_eq!(
+            device.capabilities().max_transmission_unit,
+            512 - NTB_OVERHEAD
+        );
+
+        device.get_mut().busy = true;
+        send(&mut device, &frame(101, 7));
+        assert!(device.transmit().is_none());
+        device.get_mut().busy = false;
+        assert!(device.transmit().is_some());
+        send(&mut device, &frame(36, 8));
+
+        let packets = core::mem::take(&mut device.get_mut().in_packets);
+        assert_eq!(packets.len(), 5);
+        let first: Vec<u8> = packets[..3].concat();
+        assert_eq!(first.len(), NTB_OVERHEAD + 101);
+        // The second NTB is exactly one packet long, and is followed by a zero-length packet.
+        assert_eq!(packets[3].len(), MAX_PACKET_SIZE);
+        assert!(packets[4].is_empty());
+
+        // Loop the NTBs back.
+        device.get_mut().host_send(&first);
+        device.get_mut().host_send(&packets[3]);
+        assert_eq!(receive(&mut device), Some(frame(101, 7)));
+        assert_eq!(receive(&mut device), Some(frame(36, 8)));
+        assert_eq!(LittleEndian::read_u16(&packets[3][6..8]), 1);
+    }
+
+    #[test]
+    fn test_rndis() {
+        let (mut rx_buffer, mut tx_buffer) = ([0; 512], [0; 512]);
+        let mut device = UsbEthernet::new(
+            MockEndpoints::default(),
+            UsbEthernetMode::Rndis,
+            &mut rx_buffer,
+            &mut tx_buffer,
+        );
+        assert_eq!(
+            device.capabilities().max_transmission_unit,
+            512 - RNDIS_PACKET_HEADER_LEN
+        );
+
+        send(&mut device, &frame(60, 1));
+        send(&mut device, &frame(80, 2));
+        let packets = core::mem::take(&mut device.get_mut().in_packets);
+        let mut transfer = packets.concat();
+        assert_eq!(transfer.len(), 2 * RNDIS_PACKET_HEADER_LEN + 140);
+        assert_eq!(LittleEndian::read_u32(&transfer[4..8]), 104);
+        assert_eq!(LittleEndian::read_u32(&transfer[8..12]), 36);
+        assert_eq!(LittleEndian::read_u32(&transfer[12..16]), 60);
+
+        // Loop both messages back in a single transfer, padded by one octet.
+        transfer.push(0);
+        device.get_mut().host_send(&transfer);
+        assert_eq!(receive(&mut device), Some(frame(60, 1)));
+        assert_eq!(receive(&mut device), Some(frame(80, 2)));
+        assert_eq!(receive(&mut device), None);
+    }
+}
@@ -0,0 +1,419 @@
+use byteorder::{ByteOrder, NetworkEndian};
+use managed::ManagedMap;
+
+use crate::phy::{self, Device, DeviceCapabilities, LinkState, Medium, RxToken as _};
+use crate::storage::PacketBuffer;
+use crate::time::Instant;
+use crate::wire::{EthernetFrame, EthernetProtocol};
+use crate::{Error, Result};
+
+/// The length of an 802.1Q tag.
+const TAG_LEN: usize = 4;
+/// The offset of the tag in a tagged frame.
+const TAG_OFFSET: usize = 12;
+
+/// The largest valid VLAN identifier; 4095 is reserved.
+pub const VLAN_ID_MAX: u16 = 4094;
+
+/// An 802.1Q switch, which splits a device into sub-interfaces keyed by VLAN identifier.
+///
+/// Each VLAN the switch is a member of has a port, obtained with [port](#method.port),
+/// which is a device with an Ethernet medium, and is intended to be polled by its own
+/// [Interface](../iface/struct.Interface.html), with its own addresses and neighbor cache.
+/// The port for VLAN 0 carries untagged and priority-tagged frames.
+///
+/// The frames received on the underlying device are demultiplexed once, by
+/// [poll](#method.poll), into the receive queue of the port they belong to, with their
+/// tag removed; frames for other VLANs, or that do not fit into the queue, are dropped.
+/// If the device has already removed the tag, the switch uses the VLAN identifier
+/// in the [metadata](../phy/struct.PacketMeta.html#structfield.vlan_id) of the frame.
+/// The frames transmitted through a port are tagged, with the priority code point in
+/// their [metadata](../phy/struct.PacketMeta.html#structfield.priority), and transmitted
+/// directly on the underlying device.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// switch.poll(timestamp);
+/// iface_10.poll(timestamp, &mut switch.port(10).unwrap(), &mut sockets_10);
+/// iface_20.poll(timestamp, &mut switch.port(20).unwrap(), &mut sockets_20);
+/// ```
+#[derive(Debug)]
+pub struct VlanSwitch<'a, D: for<'d> Device<'d>> {
+    inner: D,
+    ports: ManagedMap<'a, u16, PacketBuffer<'a, ()>>,
+    dropped: u64,
+}
+
+impl<'a, D: for<'d> Device<'d>> VlanSwitch<'a, D> {
+    /// Create a switch over the device `inner`, with storage for the ports in `ports`.
+    ///
+    /// # Panics
+    /// This function panics if the underlying device does not have an Ethernet medium.
+    pub fn new<P>(inner: D, ports: P) -> VlanSwitch<'a, D>
+    where
+        P: Into<ManagedMap<'a, u16, PacketBuffer<'a, ()>>>,
+    {
+        assert_eq!(
+            inner.capabilities().medium,
+            Medium::Ethernet,
+            "VLANs require a device with an Ethernet medium"
+        );
+        VlanSwitch {
+            inner,
+            ports: ports.into(),
+            dropped: 0,
+        }
+    }
+
+    /// Join the VLAN `vlan_id`, queueing the frames received on it into `rx_buffer`.
+    ///
+    /// Returns `Err(Error::Illegal)` if `vlan_id` is not a valid VLAN identifier,
+    /// or if the switch already has a port for it, and `Err(Error::Exhausted)` if there
+    /// is no storage left for the port.
+    pub fn add_port(&mut self, vlan_id: u16, rx_buffer: PacketBuffer<'a, ()>) -> Result<()> {
+        if vlan_id > VLAN_ID_MAX || self.ports.get(&vlan_id).is_some() {
+            return Err(Error::Illegal);
+        }
+        match self.ports.insert(vlan_id, rx_buffer) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Error::Exhausted),
+        }
+    }
+
+    /// Leave the VLAN `vlan_id`, and return the receive queue of its port, if any.
+    pub fn remove_port(&mut self, vlan_id: u16) -> Option<PacketBuffer<'a, ()>> {
+        self.ports.remove(&vlan_id)
+    }
+
+    /// Return the port for the VLAN `vlan_id`, or `None` if the switch is not a member of it.
+    pub fn port(&mut self, vlan_id: u16) -> Option<VlanPort<'_, 'a, D>> {
+        self.ports.get(&vlan_id)?;
+        Some(VlanPort {
+            switch: self,
+            vlan_id,
+        })
+    }
+
+    /// Return the number of received frames dropped because the switch is not a member
+    /// of their VLAN, or because the receive queue of its port was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    ///
+    /// It is inadvisable to directly read from the device as doing so will circumvent the switch.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Return the underlying device, consuming the switch.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+
+    /// Receive all frames available on the underlying device, and queue each of them
+    /// at the port of its VLAN.
+    ///
+    /// Returns whether any frame was received.
+    pub fn poll(&mut self, timestamp: Instant) -> bool {
+        let &mut Self {
+            ref mut inner,
+            ref mut ports,
+            ref mut dropped,
+        } = self;
+        let mut received = false;
+        while let Some((rx_token, _tx_token)) = inner.receive() {
+            received = true;
+            let stripped_vlan_id = rx_token.meta().vlan_id;
+            let result = rx_token.consume(timestamp, |frame| {
+                let (vlan_id, tagged) = match stripped_vlan_id {
+                    Some(vlan_id) => (vlan_id, false),
+                    None => parse_tag(frame)?,
+                };
+                let queue = match ports.get_mut(&vlan_id) {
+                    Some(queue) => queue,
+                    None => return Err(Error::Unrecognized),
+                };
+                if !tagged {
+                    let buffer = queue
+                        .enqueue(frame.len(), ())
+                        .map_err(|_| Error::Exhausted)?;
+                    buffer.copy_from_slice(frame);
+                } else {
+                    let len = frame.len() - TAG_LEN;
+                    let buffer = queue.enqueue(len, ()).map_err(|_| Error::Exhausted)?;
+                    buffer[..TAG_OFFSET].copy_from_slice(&frame[..TAG_OFFSET]);
+                    buffer[TAG_OFFSET..].copy_from_slice(&frame[TAG_OFFSET + TAG_LEN..]);
+                }
+                Ok(())
+            });
+            if let Err(err) = result {
+                net_trace!("vlan: dropped frame: {}", err);
+                *dropped += 1;
+            }
+        }
+        received
+    }
+}
+
+/// Return the VLAN identifier of `frame`, and whether it is tagged.
+fn parse_tag(frame: &[u8]) -> Result<(u16, bool)> {
+    let eth_frame = EthernetFrame::new_checked(frame)?;
+    if eth_frame.ethertype() != EthernetProtocol::Vlan {
+        return Ok((0, false));
+    }
+    if frame.len() < EthernetFrame::<&[u8]>::header_len() + TAG_LEN {
+        return Err(Error::Truncated);
+    }
+    let tci = NetworkEndian::read_u16(&frame[TAG_OFFSET + 2..]);
+    Ok((tci & 0x0fff, true))
+}
+
+/// A port of a [VlanSwitch](struct.VlanSwitch.html), carrying the frames of a single VLAN.
+#[derive(Debug)]
+pub struct VlanPort<'s, 'a, D: for<'d> Device<'d>> {
+    switch: &'s mut VlanSwitch<'a, D>,
+    vlan_id: u16,
+}
+
+impl<'s, 'a, D: for<'d> Device<'d>> VlanPort<'s, 'a, D> {
+    /// Return the VLAN identifier of the port.
+    pub fn vlan_id(&self) -> u16 {
+        self.vlan_id
+    }
+}
+
+impl<'t, 's, 'a, D: for<'d> Device<'d>> Device<'t> for VlanPort<'s, 'a, D> {
+    type RxToken = RxToken<'t>;
+    type TxToken = TxToken<<D as Device<'t>>::TxToken>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let mut caps = self.switch.inner.capabilities();
+        if self.vlan_id != 0 {
+            caps.max_transmission_unit -= TAG_LEN;
+            // The lower device cannot find the IP header of a segment behind the tag.
+            caps.tcp_segmentation_offload = None;
+        }
+        caps
+    }
+
+    fn link_state(&self) -> LinkState {
+        self.switch.inner.link_state()
+    }
+
+    fn receive(&'t mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let vlan_id = self.vlan_id;
+        let VlanSwitch {
+            ref mut inner,
+            ref mut ports,
+            ..
+        } = *self.switch;
+        let queue = ports.get_mut(&vlan_id)?;
+        if queue.is_empty() {
+            return None;
+        }
+        let tx_token = inner.transmit()?;
+        let ((), buffer) = queue.dequeue().ok()?;
+        let rx = RxToken { buffer };
+        let tx = TxToken {
+            token: tx_token,
+            vlan_id,
+            pcp: 0,
+        };
+        Some((rx, tx))
+    }
+
+    fn transmit(&'t mut self) -> Option<Self::TxToken> {
+        let vlan_id = self.vlan_id;
+        self.switch.inner.transmit().map(|token| TxToken {
+            token,
+            vlan_id,
+            pcp: 0,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<'a> {
+    buffer: &'a mut [u8],
+}
+
+impl<'a> phy::RxToken for RxToken<'a> {
+    fn consume<R, F>(self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(self.buffer)
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<Tx: phy::TxToken> {
+    token: Tx,
+    vlan_id: u16,
+    pcp: u8,
+}
+
+impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let vlan_id = self.vlan_id;
+        let pcp = self.pcp;
+        if vlan_id == 0 {
+            return self.token.consume(timestamp, len, f);
+        }
+        if len < EthernetFrame::<&[u8]>::header_len() {
+            return Err(Error::Truncated);
+        }
+        self.token.consume(timestamp, len + TAG_LEN, |buffer| {
+            let result = f(&mut buffer[TAG_LEN..])?;
+            // Move the addresses in front of the tag, keeping the EtherType after it.
+            buffer.copy_within(TAG_LEN..TAG_LEN + TAG_OFFSET, 0);
+            NetworkEndian::write_u16(&mut buffer[TAG_OFFSET..], EthernetProtocol::Vlan.into());
+            NetworkEndian::write_u16(&mut buffer[TAG_OFFSET + 2..], (pcp as u16) << 13 | vlan_id);
+            Ok(result)
+        })
+    }
+
+    fn set_meta(&mut self, meta: phy::PacketMeta) {
+        self.pcp = meta.priority.unwrap_or(0);
+        self.token.set_meta(meta)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::phy::{Loopback, TxToken as _};
+    use crate::storage::PacketMetadata;
+    use crate::wire::EthernetAddress;
+
+    fn switch<'a>() -> VlanSwitch<'a, Loopback> {
+        let mut switch = VlanSwitch::new(Loopback::new(Medium::Ethernet), BTreeMap::new());
+        for vlan_id in [0, 10, 20].iter() {
+            let rx_buffer = PacketBuffer::new(vec![PacketMetadata::EMPTY; 4], vec![0; 4 * 1514]);
+            switch.add_port(*vlan_id, rx_buffer).unwrap();
+        }
+        switch
+    }
+
+    fn send(port: &mut VlanPort<Loopback>, ethertype: u16) {
+        let token = port.transmit().unwrap();
+        token
+            .consume(Instant::from_millis(0), 20, |buffer| {
+                let mut frame = EthernetFrame::new_unchecked(buffer);
+                frame.set_dst_addr(EthernetAddress([0x02, 0, 0, 0, 0, 1]));
+                frame.set_src_addr(EthernetAddress([0x02, 0, 0, 0, 0, 2]));
+                frame.set_ethertype(EthernetProtocol::Unknown(ethertype));
+                frame.payload_mut().copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    fn recv(port: &mut VlanPort<Loopback>) -> Option<Vec<u8>> {
+        let (token, _) = port.receive()?;
+        token
+            .consume(Instant::from_millis(0), |buffer| Ok(buffer.to_vec()))
+            .ok()
+    }
+
+    #[test]
+    fn test_tagging() {
+        let mut switch = switch();
+        send(&mut switch.port(10).unwrap(), 0x88b5);
+
+        let (token, _) = switch.get_mut().receive().unwrap();
+        let frame = token
+            .consume(Instant::from_millis(0), |buffer| Ok(buffer.to_vec()))
+            .unwrap();
+        assert_eq!(
+            frame,
+            vec![
+                0x02, 0, 0, 0, 0, 1, 0x02, 0, 0, 0, 0, 2, 0x81, 0x00, 0x00, 0x0a, 0x88, 0xb5, 1, 2,
+                3, 4, 5, 6
+            ]
+        );
+    }
+
+    #[test]
+    fn test_priority_tagging() {
+        let mut switch = switch();
+        let mut port = switch.port(10).unwrap();
+        let mut token = port.transmit().unwrap();
+        token.set_meta(phy::PacketMeta {
+            priority: Some(5),
+            ..phy::PacketMeta::default()
+        });
+        token
+            .consume(Instant::from_millis(0), 14, |buffer| {
+                let mut frame = EthernetFrame::new_unchecked(buffer);
+                frame.set_ethertype(EthernetProtocol::Unknown(0x88b5));
+                Ok(())
+            })
+            .unwrap();
+
+        let (token, _) = switch.get_mut().receive().unwrap();
+        let frame = token
+            .consume(Instant::from_millis(0), |buffer| Ok(buffer.to_vec()))
+            .unwrap();
+        assert_eq!(
+            &frame[TAG_OFFSET..TAG_OFFSET + TAG_LEN],
+            &[0x81, 0x00, 0xa0, 0x0a]
+        );
+    }
+
+    #[test]
+    fn test_demultiplexing() {
+        let mut switch = switch();
+        send(&mut switch.port(20).unwrap(), 0x88b5);
+        send(&mut switch.port(0).unwrap(), 0x88b6);
+        send(&mut switch.port(10).unwrap(), 0x88b7);
+        assert!(switch.poll(Instant::from_millis(0)));
+        assert_eq!(switch.dropped(), 0);
+
+        let frame = recv(&mut switch.port(10).unwrap()).unwrap();
+        assert_eq!(
+            EthernetFrame::new_unchecked(&frame).ethertype(),
+            EthernetProtocol::Unknown(0x88b7)
+        );
+        assert_eq!(frame.len(), 20);
+        assert_eq!(recv(&mut switch.port(10).unwrap()), None);
+
+        let frame = recv(&mut switch.port(20).unwrap()).unwrap();
+        assert_eq!(
+            EthernetFrame::new_unchecked(&frame).ethertype(),
+            EthernetProtocol::Unknown(0x88b5)
+        );
+        let frame = recv(&mut switch.port(0).unwrap()).unwrap();
+        assert_eq!(
+            EthernetFrame::new_unchecked(&frame).ethertype(),
+            EthernetProtocol::Unknown(0x88b6)
+        );
+    }
+
+    #[test]
+    fn test_unknown_vlan() {
+        let mut switch = switch();
+        send(&mut switch.port(20).unwrap(), 0x88b5);
+        switch.remove_port(20).unwrap();
+        assert!(switch.port(20).is_none());
+        switch.poll(Instant::from_millis(0));
+        assert_eq!(switch.dropped(), 1);
+        assert_eq!(recv(&mut switch.port(0).unwrap()), None);
+        assert_eq!(recv(&mut switch.port(10).unwrap()), None);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::phy::{self, ChecksumCapabilities, Device, DeviceCapabilities, LinkState, Medium};
+use crate::time::Instant;
+use crate::wire::{
+    EthernetAddress, EthernetFrame, EthernetProtocol, IpProtocol, Ipv4Address, Ipv4Packet,
+    Ipv4Repr, ParseMode, UdpPacket, UdpRepr, VxlanPacket, VxlanRepr, ETHERNET_HEADER_LEN,
+    IPV4_HEADER_LEN, UDP_HEADER_LEN, VXLAN_HEADER_LEN, VXLAN_UDP_PORT,
+};
+use crate::{Error, Result};
+
+/// Configuration of a VXLAN tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VxlanConfig {
+    /// Address of the local VXLAN tunnel endpoint (VTEP), on the underlying network.
+    pub local_addr: Ipv4Address,
+    /// Address of the remote VTEP, on the underlying network.
+    pub remote_addr: Ipv4Address,
+    /// VXLAN network identifier of the overlay segment; only the lower 24 bits are used.
+    pub vni: u32,
+    /// UDP source port of the encapsulated packets.
+    pub src_port: u16,
+    /// UDP port the remote VTEP listens on, and the local VTEP accepts packets on.
+    pub dst_port: u16,
+    /// Hop limit of the delivery header.
+    pub hop_limit: u8,
+    /// Local and next-hop hardware addresses, used when the underlying device
+    /// has an Ethernet medium. Address resolution is not performed on the underlying network.
+    pub hardware_addrs: Option<(EthernetAddress, EthernetAddress)>,
+}
+
+impl VxlanConfig {
+    /// Create a configuration for a tunnel between the given VTEPs, carrying the given segment.
+    pub fn new(local_addr: Ipv4Address, remote_addr: Ipv4Address, vni: u32) -> VxlanConfig {
+        VxlanConfig {
+            local_addr,
+            remote_addr,
+            vni,
+            src_port: VXLAN_UDP_PORT,
+            dst_port: VXLAN_UDP_PORT,
+            hop_limit: 64,
+            hardware_addrs: None,
+        }
+    }
+
+    fn link_header_len(&self, medium: Medium) -> usize {
+        match medium {
+            Medium::Ethernet => ETHERNET_HEADER_LEN,
+            _ => 0,
+        }
+    }
+}
+
+/// A VXLAN tunnel device.
+///
+/// A VXLAN tunnel is a virtual device with an Ethernet medium, which encapsulates
+/// the frames sent through it in VXLAN, UDP and IPv4 headers (RFC 7348) and transmits
+/// them to the remote VTEP using another device. Frames received from the remote VTEP
+/// on the configured segment are decapsulated; all other traffic arriving on
+/// the underlying device is dropped.
+///
+/// The underlying device must have an Ethernet or IP medium. The UDP checksum of
+/// transmitted packets is always omitted.
+pub struct VxlanTunnel<D: for<'a> Device<'a>> {
+    inner: D,
+    config: VxlanConfig,
+}
+
+impl<D: for<'a> Device<'a>> VxlanTunnel<D> {
+    /// Create a VXLAN tunnel device.
+    ///
+    /// # Panics
+    /// This function panics if the underlying device has an Ethernet medium, but
+    /// `config.hardware_addrs` is not set.
+    pub fn new(inner: D, config: VxlanConfig) -> VxlanTunnel<D> {
+        assert!(
+            inner.capabilities().medium != Medium::Ethernet || config.hardware_addrs.is_some(),
+            "hardware addresses are required for tunnels over Ethernet"
+        );
+        VxlanTunnel { inner, config }
+    }
+
+    /// Get a reference to the underlying device.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying device.
+    ///
+    /// It is inadvisable to directly read from the device as doing so will circumvent the tunnel.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Return the underlying device, consuming the tunnel.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<'a, D> Device<'a> for VxlanTunnel<D>
+where
+    D: for<'b> Device<'b>,
+{
+    type RxToken = RxToken<<D as Device<'a>>::RxToken>;
+    type TxToken = TxToken<<D as Device<'a>>::TxToken>;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        let inner = self.inner.capabilities();
+        DeviceCapabilities {
+            medium: Medium::Ethernet,
+            max_transmission_unit: inner
+                .ip_mtu()
+                .saturating_sub(IPV4_HEADER_LEN + UDP_HEADER_LEN + VXLAN_HEADER_LEN),
+            max_burst_size: inner.max_burst_size,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn link_state(&self) -> LinkState {
+        self.inner.link_state()
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let &mut Self {
+            ref mut inner,
+            config,
+        } = self;
+        let caps = inner.capabilities();
+        inner.receive().map(move |(rx_token, tx_token)| {
+            let rx = RxToken {
+                token: rx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum.clone(),
+            };
+            let tx = TxToken {
+                token: tx_token,
+                config,
+                medium: caps.medium,
+                checksum: caps.checksum,
+            };
+            (rx, tx)
+        })
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        let &mut Self {
+            ref mut inner,
+            config,
+        } = self;
+        let caps = inner.capabilities();
+        inner.transmit().map(move |tx_token| TxToken {
+            token: tx_token,
+            config,
+            medium: caps.medium,
+            checksum: caps.checksum,
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken<Rx: phy::RxToken> {
+    token: Rx,
+    config: VxlanConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+}
+
+impl<Rx: phy::RxToken> phy::RxToken for RxToken<Rx> {
+    fn consume<R, F>(self, timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+        } = self;
+        token.consume(timestamp, |buffer| {
+            let offset = config.link_header_len(medium);
+            if let (Medium::Ethernet, Some((local_hw_addr, _))) = (medium, config.hardware_addrs) {
+                let frame = EthernetFrame::new_checked(&buffer[..])?;
+                if frame.ethertype() != EthernetProtocol::Ipv4 || frame.dst_addr() != local_hw_addr
+                {
+                    return Err(Error::Unrecognized);
+                }
+            }
+
+            let ipv4_packet = Ipv4Packet::new_checked(&buffer[offset..])?;
+            let ipv4_repr = Ipv4Repr::parse(&ipv4_packet, &checksum, ParseMode::Permissive)?;
+            if ipv4_repr.src_addr != config.remote_addr
+                || ipv4_repr.dst_addr != config.local_addr
+                || ipv4_repr.next_header != IpProtocol::Udp
+            {
+                return Err(Error::Unrecognized);
+            }
+            if ipv4_packet.more_frags() || ipv4_packet.frag_offset() != 0 {
+                return Err(Error::Fragmented);
+            }
+
+            let udp_packet = UdpPacket::new_checked(ipv4_packet.payload())?;
+            let udp_repr = UdpRepr::parse(
+                &udp_packet,
+                &ipv4_repr.src_addr.into(),
+                &ipv4_repr.dst_addr.into(),
+                &checksum,
+            )?;
+            if udp_repr.dst_port != config.dst_port {
+                return Err(Error::Unrecognized);
+            }
+
+            let vxlan_packet = VxlanPacket::new_checked(udp_packet.payload())?;
+            let vxlan_repr = VxlanRepr::parse(&vxlan_packet)?;
+            if vxlan_repr.vni != config.vni {
+                return Err(Error::Unrecognized);
+            }
+
+            let start = offset + ipv4_packet.header_len() as usize + UDP_HEADER_LEN;
+            let end = start + udp_packet.len() as usize - UDP_HEADER_LEN;
+            f(&mut buffer[start + VXLAN_HEADER_LEN..end])
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken<Tx: phy::TxToken> {
+    token: Tx,
+    config: VxlanConfig,
+    medium: Medium,
+    checksum: ChecksumCapabilities,
+}
+
+impl<Tx: phy::TxToken> phy::TxToken for TxToken<Tx> {
+    fn consume<R, F>(self, timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let Self {
+            token,
+            config,
+            medium,
+            checksum,
+        } = self;
+
+        let udp_len = UDP_HEADER_LEN + VXLAN_HEADER_LEN + len;
+        let ipv4_repr = Ipv4Repr {
+            src_addr: config.local_addr,
+            dst_addr: config.remote_addr,
+            next_header: IpProtocol::Udp,
+            payload_len: udp_len,
+            hop_limit: config.hop_limit,
+        };
+        let link_len = config.link_header_len(medium);
+        let udp_start = link_len + ipv4_repr.buffer_len();
+        let vxlan_start = udp_start + UDP_HEADER_LEN;
+        let payload_start = vxlan_start + VXLAN_HEADER_LEN;
+
+        token.consume(timestamp, udp_start + udp_len, |buffer| {
+            let result = f(&mut buffer[payload_start..])?;
+
+            if let (Medium::Ethernet, Some((local_hw_addr, remote_hw_addr))) =
+                (medium, config.hardware_addrs)
+            {
+                let mut frame = EthernetFrame::new_unchecked(&mut buffer[..]);
+                frame.set_src_addr(local_hw_addr);
+                frame.set_dst_addr(remote_hw_addr);
+                frame.set_ethertype(EthernetProtocol::Ipv4);
+            }
+
+            let mut ipv4_packet = Ipv4Packet::new_unchecked(&mut buffer[link_len..]);
+            ipv4_repr.emit(&mut ipv4_packet, &checksum);
+
+            // RFC 7348 § 5: the UDP checksum should be transmitted as zero.
+            let mut udp_packet = UdpPacket::new_unchecked(&mut buffer[udp_start..]);
+            udp_packet.set_src_port(config.src_port);
+            udp_packet.set_dst_port(config.dst_port);
+            udp_packet.set_len(udp_len as u16);
+            udp_packet.set_checksum(0);
+
+            let mut vxlan_packet = VxlanPacket::new_unchecked(&mut buffer[vxlan_start..]);
+            VxlanRepr { vni: config.vni }.emit(&mut vxlan_packet);
+
+            Ok(result)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::phy::Loopback;
+    use crate::phy::{Device, RxToken, TxToken};
+
+    const LOCAL: Ipv4Address = Ipv4Address([192, 0, 2, 1]);
+    const REMOTE: Ipv4Address = Ipv4Address([192, 0, 2, 2]);
+    const VNI: u32 = 0x123456;
+
+    static FRAME: [u8; 18] = [
+        0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x88, 0xb5, 0xaa,
+        0x00, 0x00, 0xff,
+    ];
+
+    fn send(tunnel: &mut VxlanTunnel<Loopback>, frame: &[u8]) {
+        let tx = tunnel.transmit().unwrap();
+        tx.consume(Instant::from_millis(0), frame.len(), |buf| {
+            buf.copy_from_slice(frame);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    fn receive(tunnel: &mut VxlanTunnel<Loopback>) -> Result<Vec<u8>> {
+        let (rx, _tx) = tunnel.receive().unwrap();
+        rx.consume(Instant::from_millis(0), |buf| Ok(buf.to_vec()))
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ip")]
+    fn test_encapsulate() {
+        let mut tunnel = VxlanTunnel::new(
+            Loopback::new(Medium::Ip),
+            VxlanConfig::new(LOCAL, REMOTE, VNI),
+        );
+        assert_eq!(tunnel.capabilities().medium, Medium::Ethernet);
+        assert_eq!(
+            tunnel.capabilities().max_transmission_unit,
+            65535 - 20 - 8 - 8
+        );
+        send(&mut tunnel, &FRAME);
+
+        let (rx, _tx) = tunnel.get_mut().receive().unwrap();
+        rx.consume(Instant::from_millis(0), |buf| {
+            let ipv4_packet = Ipv4Packet::new_checked(&buf[..]).unwrap();
+            let ipv4_repr =
+                Ipv4Repr::parse(&ipv4_packet, &Default::default(), ParseMode::Permissive).unwrap();
+            assert_eq!(ipv4_repr.src_addr, LOCAL);
+            assert_eq!(ipv4_repr.dst_addr, REMOTE);
+            assert_eq!(ipv4_repr.next_header, IpProtocol::Udp);
+
+            let udp_packet = UdpPacket::new_checked(ipv4_packet.payload()).unwrap();
+            assert_eq!(udp_packet.dst_port(), VXLAN_UDP_PORT);
+            assert_eq!(udp_packet.checksum(), 0);
+
+            let vxlan_packet = VxlanPacket::new_checked(udp_packet.payload()).unwrap();
+            assert_eq!(VxlanRepr::parse(&vxlan_packet), Ok(VxlanRepr { vni: VNI }));
+            assert_eq!(vxlan_packet.payload(), &FRAME[..]);
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ip")]
+    fn test_decapsulate() {
+        let mut sender = VxlanTunnel::new(
+            Loopback::new(Medium::Ip),
+            VxlanConfig::new(REMOTE, LOCAL, VNI),
+        );
+        send(&mut sender, &FRAME);
+
+        let mut tunnel =
+            VxlanTunnel::new(sender.into_inner(), VxlanConfig::new(LOCAL, REMOTE, VNI));
+        assert_eq!(receive(&mut tunnel), Ok(FRAME.to_vec()));
+    }
+
+    #[test]
+    #[cfg(feature = "medium-ip")]
+    fn test_decapsulate_wrong_vni() {
+        let mut sender = VxlanTunnel::new(
+            Loopback::new(Medium::Ip),
+            VxlanConfig::new(REMOTE, LOCAL, VNI + 1),
+        );
+        send(&mut sender, &FRAME);
+
+        let mut tunnel =
+            VxlanTunnel::new(sender.into_inner(), VxlanConfig::new(LOCAL, REMOTE, VNI));
+        assert_eq!(receive(&mut tunnel), Err(Error::Unrecognized));
+    }
+
+    #[test]
+    fn test_ethernet() {
+        let local_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 0x11]);
+        let remote_hw_addr = EthernetAddress([0x02, 0, 0, 0, 0, 0x12]);
+
+        let mut config = VxlanConfig::new(REMOTE, LOCAL, VNI);
+        config.hardware_addrs = Some((remote_hw_addr, local_hw_addr));
+        let mut sender = VxlanTunnel::new(Loopback::new(Medium::Ethernet), config);
+        assert_eq!(
+            sender.capabilities().max_transmission_unit,
+            65535 - 14 - 20 - 8 - 8
+        );
+        send(&mut sender, &FRAME);
+
+        let mut config = VxlanConfig::new(LOCAL, REMOTE, VNI);
+        config.hardware_addrs = Some((local_hw_addr, remote_hw_addr));
+        let mut tunnel = VxlanTunnel::new(sender.into_inner(), config);
+        assert_eq!(receive(&mut tunnel), Ok(FRAME.to_vec()));
+    }
+}
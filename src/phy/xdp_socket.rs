@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::rc::Rc;
+
+use crate::phy::{self, sys, Device, DeviceCapabilities, Medium};
+use crate::time::Instant;
+use crate::{Error, Result};
+
+/// The number of frames in the umem, half of which are used for reception.
+const FRAME_COUNT: u32 = 4096;
+/// The size of a frame in the umem.
+const FRAME_SIZE: u32 = 2048;
+
+/// An AF_XDP socket, transmitting and receiving frames directly from a queue of a network
+/// interface, through memory shared with the kernel.
+///
+/// Received frames are passed to the interface in place, without being copied. The socket
+/// only receives the frames that an XDP program attached to the network interface redirects
+/// to it through an `XSKMAP`; attaching such a program, for example with `xdp-loader` or
+/// libxdp, is left to the application.
+#[derive(Debug)]
+pub struct XdpSocket {
+    lower: Rc<RefCell<sys::XdpSocketDesc>>,
+    mtu: usize,
+}
+
+impl AsRawFd for XdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.lower.borrow().as_raw_fd()
+    }
+}
+
+impl XdpSocket {
+    /// Creates an AF_XDP socket, bound to queue `queue_id` of the interface called `name`.
+    ///
+    /// This requires superuser privileges or a corresponding capability bit
+    /// set on the executable.
+    pub fn new(name: &str, queue_id: u32) -> io::Result<XdpSocket> {
+        let lower = sys::XdpSocketDesc::new(name, queue_id, FRAME_COUNT, FRAME_SIZE)?;
+        let mtu = lower.frame_size();
+        Ok(XdpSocket {
+            lower: Rc::new(RefCell::new(lower)),
+            mtu,
+        })
+    }
+}
+
+impl<'a> Device<'a> for XdpSocket {
+    type RxToken = RxToken;
+    type TxToken = TxToken;
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            max_transmission_unit: self.mtu,
+            medium: Medium::Ethernet,
+            ..DeviceCapabilities::default()
+        }
+    }
+
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        if !self.lower.borrow_mut().can_recv() {
+            return None;
+        }
+        let rx = RxToken {
+            lower: self.lower.clone(),
+        };
+        let tx = TxToken {
+            lower: self.lower.clone(),
+        };
+        Some((rx, tx))
+    }
+
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        if !self.lower.borrow_mut().can_send() {
+            return None;
+        }
+        Some(TxToken {
+            lower: self.lower.clone(),
+        })
+    }
+}
+
+#[doc(hidden)]
+pub struct RxToken {
+    lower: Rc<RefCell<sys::XdpSocketDesc>>,
+}
+
+impl phy::RxToken for RxToken {
+    fn consume<R, F>(self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let mut lower = self.lower.borrow_mut();
+        lower.recv(f).unwrap_or(Err(Error::Exhausted))
+    }
+}
+
+#[doc(hidden)]
+pub struct TxToken {
+    lower: Rc<RefCell<sys::XdpSocketDesc>>,
+}
+
+impl phy::TxToken for TxToken {
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let mut lower = self.lower.borrow_mut();
+        if len > lower.frame_size() {
+            return Err(Error::Truncated);
+        }
+        lower.send(len, f).unwrap_or(Err(Error::Exhausted))
+    }
+}
@@ -1,13 +1,22 @@
 #[cfg(feature = "async")]
 use core::task::Waker;
 
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::boxed::Box;
+use heapless::String;
+#[cfg(feature = "std")]
+use std::boxed::Box;
+
 use crate::iface::Context;
+#[cfg(feature = "proto-ipv4-acd")]
+use crate::iface::Ipv4AddressState;
 use crate::time::{Duration, Instant};
 use crate::wire::dhcpv4::field as dhcpv4_field;
 use crate::wire::HardwareAddress;
 use crate::wire::{
-    DhcpMessageType, DhcpPacket, DhcpRepr, IpAddress, IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Repr,
-    UdpRepr, DHCP_CLIENT_PORT, DHCP_MAX_DNS_SERVER_COUNT, DHCP_SERVER_PORT, UDP_HEADER_LEN,
+    DhcpClientFqdn, DhcpFqdnFlags, DhcpMessageType, DhcpOption, DhcpPacket, DhcpRepr, IpAddress,
+    IpProtocol, Ipv4Address, Ipv4Cidr, Ipv4Repr, UdpRepr, DHCP_CLIENT_PORT,
+    DHCP_MAX_DNS_SERVER_COUNT, DHCP_SERVER_PORT, UDP_HEADER_LEN,
 };
 
 #[cfg(feature = "async")]
@@ -26,12 +35,34 @@ const MIN_RENEW_TIMEOUT: Duration = Duration::from_secs(60);
 
 const DEFAULT_LEASE_DURATION: Duration = Duration::from_secs(120);
 
+// RFC 2131 § 3.1.5: wait at least ten seconds before restarting after a DECLINE.
+#[cfg(feature = "proto-ipv4-acd")]
+const DECLINE_WAIT: Duration = Duration::from_secs(10);
+
+/// Maximum length of the host name of the client, a single DNS label per RFC 1035 § 2.3.4.
+pub const MAX_HOSTNAME_LEN: usize = 63;
+
+/// Maximum length of a domain name in text form, per RFC 1035 § 2.3.4.
+const MAX_DOMAIN_NAME_LEN: usize = 253;
+
 const PARAMETER_REQUEST_LIST: &[u8] = &[
     dhcpv4_field::OPT_SUBNET_MASK,
     dhcpv4_field::OPT_ROUTER,
     dhcpv4_field::OPT_DOMAIN_NAME_SERVER,
 ];
 
+const BOOT_PARAMETER_REQUEST_LIST: &[u8] = &[
+    dhcpv4_field::OPT_SUBNET_MASK,
+    dhcpv4_field::OPT_ROUTER,
+    dhcpv4_field::OPT_DOMAIN_NAME_SERVER,
+    dhcpv4_field::OPT_TFTP_SERVER_NAME,
+    dhcpv4_field::OPT_BOOTFILE_NAME,
+];
+
+/// Maximum length of a boot file name, the size of the `file` field without its
+/// terminating null octet.
+pub const MAX_BOOT_FILE_LEN: usize = 127;
+
 /// IPv4 configuration data provided by the DHCP server.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -45,6 +76,18 @@ pub struct Config {
     pub dns_servers: [Option<Ipv4Address>; DHCP_MAX_DNS_SERVER_COUNT],
 }
 
+/// Network boot parameters provided by the DHCP server, as used by PXE.
+#[derive(Debug, Eq, PartialEq, Clone, Default)]
+pub struct BootParams {
+    /// Address of the server to boot from (called `siaddr` in the specification).
+    pub next_server: Option<Ipv4Address>,
+    /// Name of the server to boot from, from the `sname` field or the TFTP server
+    /// name option. This is often an address in dotted decimal notation.
+    pub server_name: Option<String<MAX_DOMAIN_NAME_LEN>>,
+    /// Name of the file to boot, from the `file` field or the bootfile name option.
+    pub boot_file: Option<String<MAX_BOOT_FILE_LEN>>,
+}
+
 /// Information on how to reach a DHCP server.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -76,7 +119,7 @@ struct RequestState {
     requested_ip: Ipv4Address,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct RenewState {
     /// Server that gave us the lease
@@ -100,6 +143,9 @@ enum ClientState {
     Discovering(DiscoverState),
     /// Requesting an address
     Requesting(RequestState),
+    /// Having an address, verifying that no other node uses it before reporting it.
+    #[cfg(feature = "proto-ipv4-acd")]
+    Probing(RenewState),
     /// Having an address, refresh it periodically.
     Renewing(RenewState),
 }
@@ -114,8 +160,22 @@ pub enum Event {
     Configured(Config),
 }
 
+/// Names sent to and received from the DHCP server.
+///
+/// These are much larger than the rest of the socket state, so they are kept out of line
+/// when heap allocation is available.
+#[derive(Debug, Default)]
+struct Names {
+    /// Host name sent to the server.
+    hostname: Option<String<MAX_HOSTNAME_LEN>>,
+    /// Name assigned or confirmed by the server in the last ACK.
+    confirmed_hostname: Option<String<MAX_DOMAIN_NAME_LEN>>,
+    /// Boot parameters of the last ACK.
+    boot_params: BootParams,
+}
+
 #[derive(Debug)]
-pub struct Socket {
+pub struct Socket<'a> {
     /// State of the DHCP client.
     state: ClientState,
     /// Set to true on config/state change, cleared back to false by the `config` function.
@@ -130,6 +190,29 @@ pub struct Socket {
     /// Ignore NAKs.
     ignore_naks: bool,
 
+    /// Host name, confirmed host name and boot parameters.
+    #[cfg(not(any(feature = "std", feature = "alloc")))]
+    names: Names,
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    names: Box<Names>,
+    /// Whether the host name is sent in a Client FQDN option instead of a Host Name option.
+    use_client_fqdn: bool,
+
+    /// Options added to outgoing messages.
+    outgoing_options: &'a [DhcpOption<'a>],
+    /// Buffer for the options of the last OFFER or ACK.
+    received_options: &'a mut [u8],
+    /// Length of the options in `received_options`.
+    received_options_len: usize,
+
+    /// Whether the TFTP server name and bootfile name options are requested.
+    request_boot_params: bool,
+
+    /// Address probed by Address Conflict Detection on behalf of this socket, until it is
+    /// reported or declined.
+    #[cfg(feature = "proto-ipv4-acd")]
+    probed_addr: Option<Ipv4Address>,
+
     /// Waker registration
     #[cfg(feature = "async")]
     waker: WakerRegistration,
@@ -140,7 +223,7 @@ pub struct Socket {
 /// The socket acquires an IP address configuration through DHCP autonomously.
 /// You must query the configuration with `.poll()` after every call to `Interface::poll()`,
 /// and apply the configuration to the `Interface`.
-impl Socket {
+impl<'a> Socket<'a> {
     /// Create a DHCPv4 socket
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
@@ -152,6 +235,14 @@ impl Socket {
             transaction_id: 1,
             max_lease_duration: None,
             ignore_naks: false,
+            names: Default::default(),
+            use_client_fqdn: false,
+            outgoing_options: &[],
+            received_options: &mut [],
+            received_options_len: 0,
+            request_boot_params: false,
+            #[cfg(feature = "proto-ipv4-acd")]
+            probed_addr: None,
             #[cfg(feature = "async")]
             waker: WakerRegistration::new(),
         }
@@ -192,11 +283,148 @@ impl Socket {
         self.ignore_naks = ignore_naks;
     }
 
+    /// Get the host name sent to the server.
+    ///
+    /// See also [`Self::set_hostname()`]
+    pub fn hostname(&self) -> Option<&str> {
+        self.names.hostname.as_deref()
+    }
+
+    /// Set the host name sent to the server.
+    ///
+    /// Servers commonly register the name in DNS or display it in their lease table.
+    /// The name takes effect from the next message sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hostname` is not a valid host name label: between 1 and
+    /// `MAX_HOSTNAME_LEN` letters, digits and hyphens.
+    pub fn set_hostname(&mut self, hostname: Option<&str>) {
+        self.names.hostname = hostname.map(|hostname| {
+            assert!(
+                !hostname.is_empty()
+                    && hostname
+                        .bytes()
+                        .all(|c| c.is_ascii_alphanumeric() || c == b'-'),
+                "invalid host name"
+            );
+            String::from(hostname)
+        });
+    }
+
+    /// Get whether the host name is sent in a Client FQDN option.
+    ///
+    /// See also [`Self::set_use_client_fqdn()`]
+    pub fn use_client_fqdn(&self) -> bool {
+        self.use_client_fqdn
+    }
+
+    /// Set whether the host name is sent in a Client FQDN option, as specified in
+    /// RFC 4702, instead of a Host Name option.
+    ///
+    /// The option asks the server to register the name, qualified with the domain
+    /// of the network, in DNS.
+    pub fn set_use_client_fqdn(&mut self, use_client_fqdn: bool) {
+        self.use_client_fqdn = use_client_fqdn;
+    }
+
+    /// Get the name assigned or confirmed by the server, if any.
+    ///
+    /// This is the domain name of the Client FQDN option of the last ACK, or its
+    /// Host Name option otherwise. It is cleared when the lease is lost.
+    pub fn confirmed_hostname(&self) -> Option<&str> {
+        self.names.confirmed_hostname.as_deref()
+    }
+
+    /// Set the options added to outgoing messages, such as a vendor class identifier or
+    /// vendor-specific information.
+    ///
+    /// The options are emitted after the ones the socket adds itself.
+    pub fn set_outgoing_options(&mut self, options: &'a [DhcpOption<'a>]) {
+        self.outgoing_options = options;
+    }
+
+    /// Set the buffer the options of received OFFER and ACK messages are copied to.
+    ///
+    /// Options are copied whole, as long as they fit, and are available with
+    /// [`Self::received_options()`].
+    pub fn set_received_options_buffer(&mut self, buffer: &'a mut [u8]) {
+        self.received_options = buffer;
+        self.received_options_len = 0;
+    }
+
+    /// Return the options of the last OFFER or ACK received, including the ones the
+    /// socket handles itself.
+    ///
+    /// No options are returned unless a buffer was set with
+    /// [`Self::set_received_options_buffer()`].
+    pub fn received_options(&self) -> impl Iterator<Item = DhcpOption<'_>> {
+        let mut options = &self.received_options[..self.received_options_len];
+        core::iter::from_fn(move || {
+            let (next_options, option) = DhcpOption::parse(options).ok()?;
+            options = next_options;
+            Some(option)
+        })
+    }
+
+    /// Get whether the TFTP server name and bootfile name options are requested.
+    ///
+    /// See also [`Self::set_request_boot_params()`]
+    pub fn request_boot_params(&self) -> bool {
+        self.request_boot_params
+    }
+
+    /// Set whether the TFTP server name and bootfile name options are requested.
+    ///
+    /// Servers may provide boot parameters in the `sname` and `file` fields
+    /// regardless, but some only do so when asked.
+    pub fn set_request_boot_params(&mut self, request_boot_params: bool) {
+        self.request_boot_params = request_boot_params;
+    }
+
+    /// Get the network boot parameters of the last ACK.
+    ///
+    /// Names that are too long are ignored. The parameters are cleared when the
+    /// lease is lost.
+    pub fn boot_params(&self) -> &BootParams {
+        &self.names.boot_params
+    }
+
+    /// Copy the options of `packet` that fit to `buffer`, and return their length.
+    fn copy_options(buffer: &mut [u8], packet: &DhcpPacket<&[u8]>) -> usize {
+        let mut len = 0;
+        let mut options = packet.options().unwrap_or(&[]);
+        while let Ok((next_options, option)) = DhcpOption::parse(options) {
+            match option {
+                DhcpOption::EndOfList => break,
+                DhcpOption::Pad => {}
+                _ => {
+                    let option = &options[..options.len() - next_options.len()];
+                    match buffer.get_mut(len..len + option.len()) {
+                        Some(buffer) => buffer.copy_from_slice(option),
+                        None => break,
+                    }
+                    len += option.len();
+                }
+            }
+            options = next_options;
+        }
+        len
+    }
+
     pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
         let t = match &self.state {
             ClientState::Discovering(state) => state.retry_at,
             ClientState::Requesting(state) => state.retry_at,
             ClientState::Renewing(state) => state.renew_at.min(state.expires_at),
+            #[cfg(feature = "proto-ipv4-acd")]
+            ClientState::Probing(state) => {
+                // Probing ends when an ARP packet is received or the interface timer expires.
+                return match _cx.ipv4_addr_state(&state.config.address.address()) {
+                    Some(state) if state.is_probing() => PollAt::Ingress,
+                    _ => PollAt::Now,
+                };
+            }
         };
         PollAt::Time(t)
     }
@@ -272,17 +500,34 @@ impl Socket {
                     },
                     requested_ip: dhcp_repr.your_ip, // use the offered ip
                 });
+                self.received_options_len = Self::copy_options(self.received_options, &dhcp_packet);
             }
             (ClientState::Requesting(state), DhcpMessageType::Ack) => {
                 if let Some((config, renew_at, expires_at)) =
                     Self::parse_ack(cx.now(), &dhcp_repr, self.max_lease_duration)
                 {
-                    self.state = ClientState::Renewing(RenewState {
+                    let state = RenewState {
                         server: state.server,
                         config,
                         renew_at,
                         expires_at,
-                    });
+                    };
+                    self.names.confirmed_hostname = Self::parse_hostname(&dhcp_repr);
+                    self.names.boot_params = Self::parse_boot_params(&dhcp_repr);
+                    self.received_options_len =
+                        Self::copy_options(self.received_options, &dhcp_packet);
+
+                    // RFC 2131 § 4.4.1: check that the address is not in use before
+                    // accepting the lease.
+                    #[cfg(feature = "proto-ipv4-acd")]
+                    if cx.acd_probe(config.address.address()) {
+                        net_debug!("DHCP probing {}", config.address.address());
+                        self.state = ClientState::Probing(state);
+                        self.probed_addr = Some(config.address.address());
+                        return;
+                    }
+
+                    self.state = ClientState::Renewing(state);
                     self.config_changed();
                 }
             }
@@ -297,6 +542,10 @@ impl Socket {
                 {
                     state.renew_at = renew_at;
                     state.expires_at = expires_at;
+                    self.names.confirmed_hostname = Self::parse_hostname(&dhcp_repr);
+                    self.names.boot_params = Self::parse_boot_params(&dhcp_repr);
+                    self.received_options_len =
+                        Self::copy_options(self.received_options, &dhcp_packet);
                     if state.config != config {
                         state.config = config;
                         self.config_changed();
@@ -377,6 +626,51 @@ impl Socket {
         Some((config, renew_at, expires_at))
     }
 
+    /// Return the name assigned or confirmed by the server in `dhcp_repr`.
+    fn parse_boot_params(dhcp_repr: &DhcpRepr) -> BootParams {
+        fn to_string<const N: usize>(name: &str) -> Option<String<N>> {
+            let mut string = String::new();
+            string.push_str(name).ok()?;
+            Some(string)
+        }
+
+        BootParams {
+            next_server: Some(dhcp_repr.server_ip).filter(|addr| addr.is_unicast()),
+            server_name: dhcp_repr.server_name.and_then(to_string),
+            boot_file: dhcp_repr.boot_file.and_then(to_string),
+        }
+    }
+
+    fn parse_hostname(dhcp_repr: &DhcpRepr) -> Option<String<MAX_DOMAIN_NAME_LEN>> {
+        let mut name = String::new();
+        match (dhcp_repr.client_fqdn, dhcp_repr.host_name) {
+            (Some(fqdn), _) if fqdn.flags.contains(DhcpFqdnFlags::ENCODED) => {
+                let mut labels = fqdn.domain_name;
+                while let Some((&len, rest)) = labels.split_first() {
+                    let label = rest.get(..len as usize)?;
+                    if label.is_empty() {
+                        break;
+                    }
+                    if !name.is_empty() {
+                        name.push('.').ok()?;
+                    }
+                    name.push_str(core::str::from_utf8(label).ok()?).ok()?;
+                    labels = &rest[label.len()..];
+                }
+            }
+            (Some(fqdn), _) => {
+                name.push_str(core::str::from_utf8(fqdn.domain_name).ok()?)
+                    .ok()?;
+            }
+            (None, Some(host_name)) => name.push_str(host_name).ok()?,
+            (None, None) => return None,
+        }
+        if name.is_empty() {
+            return None;
+        }
+        Some(name)
+    }
+
     #[cfg(not(test))]
     fn random_transaction_id(cx: &mut Context) -> u32 {
         cx.rand().rand_u32()
@@ -407,6 +701,35 @@ impl Socket {
         // may fail. We only want to update state after succesfully sending.
         let next_transaction_id = Self::random_transaction_id(cx);
 
+        // Stop probing an address that was given up, e.g. after a reset.
+        #[cfg(feature = "proto-ipv4-acd")]
+        if !matches!(self.state, ClientState::Probing(_)) {
+            if let Some(addr) = self.probed_addr.take() {
+                cx.acd_release(addr);
+            }
+        }
+
+        // The host name is sent as a partial name in the canonical wire format.
+        let mut fqdn_name = [0; MAX_HOSTNAME_LEN + 1];
+        let (host_name, client_fqdn) = match self.names.hostname.as_deref() {
+            Some(hostname) if self.use_client_fqdn => {
+                fqdn_name[0] = hostname.len() as u8;
+                fqdn_name[1..=hostname.len()].copy_from_slice(hostname.as_bytes());
+                let fqdn = DhcpClientFqdn {
+                    flags: DhcpFqdnFlags::SERVER_UPDATE | DhcpFqdnFlags::ENCODED,
+                    domain_name: &fqdn_name[..=hostname.len()],
+                };
+                (None, Some(fqdn))
+            }
+            hostname => (hostname, None),
+        };
+
+        let parameter_request_list = if self.request_boot_params {
+            BOOT_PARAMETER_REQUEST_LIST
+        } else {
+            PARAMETER_REQUEST_LIST
+        };
+
         let mut dhcp_repr = DhcpRepr {
             message_type: DhcpMessageType::Discover,
             transaction_id: next_transaction_id,
@@ -421,10 +744,15 @@ impl Socket {
             requested_ip: None,
             client_identifier: Some(ethernet_addr),
             server_identifier: None,
-            parameter_request_list: Some(PARAMETER_REQUEST_LIST),
+            parameter_request_list: Some(parameter_request_list),
             max_size: Some((cx.ip_mtu() - MAX_IPV4_HEADER_LEN - UDP_HEADER_LEN) as u16),
             lease_duration: None,
             dns_servers: None,
+            host_name,
+            client_fqdn,
+            server_name: None,
+            boot_file: None,
+            additional_options: self.outgoing_options,
         };
 
         let udp_repr = UdpRepr {
@@ -522,6 +850,46 @@ impl Socket {
                 self.transaction_id = next_transaction_id;
                 Ok(())
             }
+            #[cfg(feature = "proto-ipv4-acd")]
+            ClientState::Probing(state) => {
+                let addr = state.config.address.address();
+                match cx.ipv4_addr_state(&addr) {
+                    Some(Ipv4AddressState::Probing) => Ok(()),
+                    Some(Ipv4AddressState::Conflict) => {
+                        // RFC 2131 § 3.1.5: decline the address and start over.
+                        dhcp_repr.message_type = DhcpMessageType::Decline;
+                        dhcp_repr.requested_ip = Some(addr);
+                        dhcp_repr.server_identifier = Some(state.server.identifier);
+                        dhcp_repr.parameter_request_list = None;
+                        dhcp_repr.max_size = None;
+                        dhcp_repr.host_name = None;
+                        dhcp_repr.client_fqdn = None;
+
+                        net_debug!(
+                            "DHCP send decline to {}: {:?}",
+                            ipv4_repr.dst_addr,
+                            dhcp_repr
+                        );
+                        ipv4_repr.payload_len = udp_repr.header_len() + dhcp_repr.buffer_len();
+                        emit(cx, (ipv4_repr, udp_repr, dhcp_repr))?;
+
+                        self.reset();
+                        self.state = ClientState::Discovering(DiscoverState {
+                            retry_at: cx.now() + DECLINE_WAIT,
+                        });
+                        self.probed_addr = None;
+                        cx.acd_release(addr);
+                        Ok(())
+                    }
+                    _ => {
+                        net_debug!("DHCP {} is not in use", addr);
+                        self.state = ClientState::Renewing(*state);
+                        self.probed_addr = None;
+                        self.config_changed();
+                        Ok(())
+                    }
+                }
+            }
         }
     }
 
@@ -534,6 +902,9 @@ impl Socket {
         if let ClientState::Renewing(_) = &self.state {
             self.config_changed();
         }
+        self.names.confirmed_hostname = None;
+        self.names.boot_params = BootParams::default();
+        self.received_options_len = 0;
         self.state = ClientState::Discovering(DiscoverState {
             retry_at: Instant::from_millis(0),
         });
@@ -587,6 +958,8 @@ mod test {
     use std::ops::{Deref, DerefMut};
 
     use super::*;
+    #[cfg(feature = "proto-ipv4-acd")]
+    use crate::iface::AcdConfig;
     use crate::wire::EthernetAddress;
     use crate::Error;
 
@@ -594,12 +967,12 @@ mod test {
     // Helper functions
 
     struct TestSocket {
-        socket: Socket,
+        socket: Socket<'static>,
         cx: Context<'static>,
     }
 
     impl Deref for TestSocket {
-        type Target = Socket;
+        type Target = Socket<'static>;
         fn deref(&self) -> &Self::Target {
             &self.socket
         }
@@ -754,6 +1127,11 @@ mod test {
         server_identifier: None,
         parameter_request_list: None,
         dns_servers: None,
+        host_name: None,
+        client_fqdn: None,
+        server_name: None,
+        boot_file: None,
+        additional_options: &[],
         max_size: None,
         lease_duration: None,
     };
@@ -791,6 +1169,15 @@ mod test {
         ..DHCP_DEFAULT
     };
 
+    #[cfg(feature = "proto-ipv4-acd")]
+    const DHCP_DECLINE: DhcpRepr = DhcpRepr {
+        message_type: DhcpMessageType::Decline,
+        client_identifier: Some(MY_MAC),
+        server_identifier: Some(SERVER_IP),
+        requested_ip: Some(MY_IP),
+        ..DHCP_DEFAULT
+    };
+
     const DHCP_ACK: DhcpRepr = DhcpRepr {
         message_type: DhcpMessageType::Ack,
         server_ip: SERVER_IP,
@@ -885,6 +1272,53 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn test_bind_probe() {
+        let mut s = socket();
+        s.cx.set_acd(AcdConfig {
+            probe_num: 0,
+            ..AcdConfig::default()
+        });
+
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_REQUEST)]);
+        send!(s, (IP_RECV, UDP_RECV, DHCP_ACK));
+        assert!(matches!(s.state, ClientState::Probing(_)));
+        assert_eq!(s.poll(), None);
+
+        // The address is reported once no other node answered the probes.
+        recv!(s, []);
+        assert!(matches!(s.state, ClientState::Renewing(_)));
+        assert!(matches!(s.poll(), Some(Event::Configured(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4-acd")]
+    fn test_bind_probe_conflict() {
+        let mut s = socket();
+        s.cx.set_acd(AcdConfig::default());
+
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(s, [(IP_BROADCAST, UDP_SEND, DHCP_REQUEST)]);
+        send!(s, (IP_RECV, UDP_RECV, DHCP_ACK));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+        assert_eq!(
+            s.cx.ipv4_addr_state(&MY_IP),
+            Some(Ipv4AddressState::Probing)
+        );
+
+        // Another node answers for the address: decline it, and start over later.
+        s.cx.acd_process_arp(MY_IP, MY_IP);
+        recv!(s, time 1_000, [(IP_BROADCAST, UDP_SEND, DHCP_DECLINE)]);
+        assert_eq!(s.poll(), None);
+        assert_eq!(s.cx.ipv4_addr_state(&MY_IP), None);
+        recv!(s, time 10_000, []);
+        recv!(s, time 11_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
+    }
+
     #[test]
     fn test_discover_retransmit() {
         let mut s = socket();
@@ -1034,4 +1468,221 @@ mod test {
         send!(s, time 500_000, (IP_SERVER_BROADCAST, UDP_RECV, DHCP_NAK));
         recv!(s, time 500_000, [(IP_BROADCAST, UDP_SEND, DHCP_DISCOVER)]);
     }
+
+    #[test]
+    fn test_hostname() {
+        let mut s = socket();
+        s.set_hostname(Some("sensor"));
+
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    host_name: Some("sensor"),
+                    ..DHCP_DISCOVER
+                }
+            )]
+        );
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    host_name: Some("sensor"),
+                    ..DHCP_REQUEST
+                }
+            )]
+        );
+        send!(
+            s,
+            (
+                IP_RECV,
+                UDP_RECV,
+                DhcpRepr {
+                    host_name: Some("sensor-1"),
+                    ..DHCP_ACK
+                }
+            )
+        );
+        assert_eq!(s.confirmed_hostname(), Some("sensor-1"));
+
+        s.reset();
+        assert_eq!(s.confirmed_hostname(), None);
+    }
+
+    #[test]
+    fn test_hostname_client_fqdn() {
+        let mut s = socket();
+        s.set_hostname(Some("sensor"));
+        s.set_use_client_fqdn(true);
+
+        let fqdn = DhcpClientFqdn {
+            flags: DhcpFqdnFlags::SERVER_UPDATE | DhcpFqdnFlags::ENCODED,
+            domain_name: b"\x06sensor",
+        };
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    client_fqdn: Some(fqdn),
+                    ..DHCP_DISCOVER
+                }
+            )]
+        );
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    client_fqdn: Some(fqdn),
+                    ..DHCP_REQUEST
+                }
+            )]
+        );
+        let fqdn = DhcpClientFqdn {
+            flags: DhcpFqdnFlags::SERVER_UPDATE | DhcpFqdnFlags::ENCODED,
+            domain_name: b"\x06sensor\x07example\x03com\x00",
+        };
+        send!(
+            s,
+            (
+                IP_RECV,
+                UDP_RECV,
+                DhcpRepr {
+                    client_fqdn: Some(fqdn),
+                    ..DHCP_ACK
+                }
+            )
+        );
+        assert_eq!(s.confirmed_hostname(), Some("sensor.example.com"));
+    }
+
+    #[test]
+    fn test_options_passthrough() {
+        const VENDOR_CLASS: &[DhcpOption] = &[DhcpOption::Other {
+            kind: dhcpv4_field::OPT_VENDOR_CLASS_ID,
+            data: b"PXEClient",
+        }];
+        const VENDOR_INFO: &[DhcpOption] = &[DhcpOption::Other {
+            kind: dhcpv4_field::OPT_VENDOR_SPECIFIC_INFO,
+            data: &[6, 1, 8],
+        }];
+
+        let mut s = socket();
+        s.set_outgoing_options(VENDOR_CLASS);
+        s.set_received_options_buffer(vec![0; 64].leak());
+
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    additional_options: VENDOR_CLASS,
+                    ..DHCP_DISCOVER
+                }
+            )]
+        );
+        send!(
+            s,
+            (
+                IP_RECV,
+                UDP_RECV,
+                DhcpRepr {
+                    additional_options: VENDOR_INFO,
+                    ..DHCP_OFFER
+                }
+            )
+        );
+        assert!(s.received_options().any(|option| option == VENDOR_INFO[0]));
+        assert!(s
+            .received_options()
+            .any(|option| option == DhcpOption::ServerIdentifier(SERVER_IP)));
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    additional_options: VENDOR_CLASS,
+                    ..DHCP_REQUEST
+                }
+            )]
+        );
+        send!(s, (IP_RECV, UDP_RECV, DHCP_ACK));
+        assert!(!s.received_options().any(|option| option == VENDOR_INFO[0]));
+
+        // Options that do not fit are dropped.
+        s.set_received_options_buffer(vec![0; 8].leak());
+        send!(s, (IP_RECV, UDP_RECV, DHCP_ACK));
+        assert_eq!(
+            s.received_options().collect::<Vec<_>>(),
+            [DhcpOption::MessageType(DhcpMessageType::Ack)]
+        );
+    }
+
+    #[test]
+    fn test_boot_params() {
+        let mut s = socket();
+        s.set_request_boot_params(true);
+
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    parameter_request_list: Some(&[1, 3, 6, 66, 67]),
+                    ..DHCP_DISCOVER
+                }
+            )]
+        );
+        send!(s, (IP_RECV, UDP_RECV, DHCP_OFFER));
+        recv!(
+            s,
+            [(
+                IP_BROADCAST,
+                UDP_SEND,
+                DhcpRepr {
+                    parameter_request_list: Some(&[1, 3, 6, 66, 67]),
+                    ..DHCP_REQUEST
+                }
+            )]
+        );
+        send!(
+            s,
+            (
+                IP_RECV,
+                UDP_RECV,
+                DhcpRepr {
+                    server_name: Some("tftp.example.com"),
+                    boot_file: Some("pxelinux.0"),
+                    ..DHCP_ACK
+                }
+            )
+        );
+        assert_eq!(s.boot_params().next_server, Some(SERVER_IP));
+        assert_eq!(
+            s.boot_params().server_name.as_deref(),
+            Some("tftp.example.com")
+        );
+        assert_eq!(s.boot_params().boot_file.as_deref(), Some("pxelinux.0"));
+
+        s.reset();
+        assert_eq!(s.boot_params(), &BootParams::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid host name")]
+    fn test_hostname_invalid() {
+        socket().set_hostname(Some("sensor.example.com"));
+    }
 }
@@ -311,22 +311,39 @@ impl<'a> Socket<'a> {
             .register(waker);
     }
 
-    pub(crate) fn accepts(&self, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> bool {
-        udp_repr.src_port == DNS_PORT
-            && self
-                .servers
-                .iter()
-                .any(|server| *server == ip_repr.src_addr())
+    /// Return the server at `index`, falling back to the servers advertised to the
+    /// interface if none are configured.
+    fn server(servers: &[IpAddress], _cx: &Context, index: usize) -> Option<IpAddress> {
+        #[cfg(feature = "proto-ipv6-ra")]
+        if servers.is_empty() {
+            return _cx.ipv6_dns_servers().nth(index).map(IpAddress::Ipv6);
+        }
+        servers.get(index).copied()
+    }
+
+    pub(crate) fn accepts(&self, _cx: &Context, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> bool {
+        if udp_repr.src_port != DNS_PORT {
+            return false;
+        }
+        #[cfg(feature = "proto-ipv6-ra")]
+        if self.servers.is_empty() {
+            return _cx
+                .ipv6_dns_servers()
+                .any(|server| IpAddress::Ipv6(server) == ip_repr.src_addr());
+        }
+        self.servers
+            .iter()
+            .any(|server| *server == ip_repr.src_addr())
     }
 
     pub(crate) fn process(
         &mut self,
-        _cx: &mut Context,
+        cx: &mut Context,
         ip_repr: &IpRepr,
         udp_repr: &UdpRepr,
         payload: &[u8],
     ) {
-        debug_assert!(self.accepts(ip_repr, udp_repr));
+        debug_assert!(self.accepts(cx, ip_repr, udp_repr));
 
         let size = payload.len();
 
@@ -501,14 +518,17 @@ impl<'a> Socket<'a> {
                 }
 
                 // Check if we've run out of servers to try.
-                if pq.server_idx >= self.servers.len() {
-                    net_trace!("already tried all servers.");
-                    q.set_state(State::Failure);
-                    continue;
-                }
+                let dst_addr = match Self::server(&self.servers, cx, pq.server_idx) {
+                    Some(dst_addr) => dst_addr,
+                    None => {
+                        net_trace!("already tried all servers.");
+                        q.set_state(State::Failure);
+                        continue;
+                    }
+                };
 
                 // Check so the IP address is valid
-                if self.servers[pq.server_idx].is_unspecified() {
+                if dst_addr.is_unspecified() {
                     net_trace!("invalid unspecified DNS server addr.");
                     q.set_state(State::Failure);
                     continue;
@@ -538,7 +558,6 @@ impl<'a> Socket<'a> {
                     dst_port: 53,
                 };
 
-                let dst_addr = self.servers[pq.server_idx];
                 let src_addr = cx.get_source_address(dst_addr).unwrap(); // TODO remove unwrap
                 let ip_repr = IpRepr::new(
                     src_addr,
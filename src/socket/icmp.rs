@@ -1,3 +1,4 @@
+use byteorder::{ByteOrder, NetworkEndian};
 use core::cmp;
 #[cfg(feature = "async")]
 use core::task::Waker;
@@ -8,12 +9,13 @@ use crate::socket::WakerRegistration;
 use crate::socket::{Context, PollAt};
 
 use crate::storage::Empty;
+use crate::time::{Duration, Instant};
 use crate::wire::IcmpRepr;
 #[cfg(feature = "proto-ipv4")]
-use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Repr};
+use crate::wire::{Icmpv4Message, Icmpv4Packet, Icmpv4Repr, Ipv4Repr};
 #[cfg(feature = "proto-ipv6")]
-use crate::wire::{Icmpv6Packet, Icmpv6Repr, Ipv6Repr};
-use crate::wire::{IpAddress, IpListenEndpoint, IpProtocol, IpRepr};
+use crate::wire::{Icmpv6Message, Icmpv6Packet, Icmpv6Repr, Ipv6Address, Ipv6Repr};
+use crate::wire::{IpAddress, IpListenEndpoint, IpProtocol, IpRepr, ParseMode};
 use crate::wire::{UdpPacket, UdpRepr};
 
 /// Error returned by [`Socket::bind`]
@@ -67,6 +69,106 @@ impl Default for Endpoint {
     }
 }
 
+/// A response to an Echo Request sent with [Socket::send_probe].
+///
+/// Each variant carries the sequence number of the probe it answers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ProbeReply {
+    /// The destination answered the probe with an Echo Reply.
+    EchoReply { seq_no: u16 },
+    /// A router discarded the probe because its hop limit reached zero.
+    TimeExceeded { seq_no: u16 },
+    /// A router or the destination reported that the probe could not be delivered.
+    DstUnreachable { seq_no: u16 },
+}
+
+impl ProbeReply {
+    /// Return the sequence number of the probe this is a response to.
+    pub fn seq_no(&self) -> u16 {
+        match *self {
+            ProbeReply::EchoReply { seq_no }
+            | ProbeReply::TimeExceeded { seq_no }
+            | ProbeReply::DstUnreachable { seq_no } => seq_no,
+        }
+    }
+
+    #[cfg(feature = "proto-ipv4")]
+    fn parse_icmpv4(ident: u16, buffer: &[u8]) -> Option<ProbeReply> {
+        let packet = Icmpv4Packet::new_checked(buffer).ok()?;
+        match Icmpv4Repr::parse(
+            &packet,
+            &ChecksumCapabilities::ignored(),
+            ParseMode::Permissive,
+        )
+        .ok()?
+        {
+            Icmpv4Repr::EchoReply {
+                ident: reply_ident,
+                seq_no,
+                ..
+            } if reply_ident == ident => Some(ProbeReply::EchoReply { seq_no }),
+            Icmpv4Repr::TimeExceeded { data, .. } => {
+                Self::probe_seq_no_v4(ident, data).map(|seq_no| ProbeReply::TimeExceeded { seq_no })
+            }
+            Icmpv4Repr::DstUnreachable { data, .. } => Self::probe_seq_no_v4(ident, data)
+                .map(|seq_no| ProbeReply::DstUnreachable { seq_no }),
+            _ => None,
+        }
+    }
+
+    /// Return the sequence number of the Echo Request quoted in an ICMPv4 error message,
+    /// if it was sent with the given identifier.
+    #[cfg(feature = "proto-ipv4")]
+    fn probe_seq_no_v4(ident: u16, data: &[u8]) -> Option<u16> {
+        let packet = Icmpv4Packet::new_checked(data).ok()?;
+        if packet.msg_type() == Icmpv4Message::EchoRequest && packet.echo_ident() == ident {
+            Some(packet.echo_seq_no())
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "proto-ipv6")]
+    fn parse_icmpv6(ident: u16, buffer: &[u8]) -> Option<ProbeReply> {
+        let packet = Icmpv6Packet::new_checked(buffer).ok()?;
+        let unspecified = IpAddress::Ipv6(Ipv6Address::UNSPECIFIED);
+        match Icmpv6Repr::parse(
+            &unspecified,
+            &unspecified,
+            &packet,
+            &ChecksumCapabilities::ignored(),
+            ParseMode::Permissive,
+        )
+        .ok()?
+        {
+            Icmpv6Repr::EchoReply {
+                ident: reply_ident,
+                seq_no,
+                ..
+            } if reply_ident == ident => Some(ProbeReply::EchoReply { seq_no }),
+            Icmpv6Repr::TimeExceeded { data, .. } => {
+                Self::probe_seq_no_v6(ident, data).map(|seq_no| ProbeReply::TimeExceeded { seq_no })
+            }
+            Icmpv6Repr::DstUnreachable { data, .. } => Self::probe_seq_no_v6(ident, data)
+                .map(|seq_no| ProbeReply::DstUnreachable { seq_no }),
+            _ => None,
+        }
+    }
+
+    /// Return the sequence number of the Echo Request quoted in an ICMPv6 error message,
+    /// if it was sent with the given identifier.
+    #[cfg(feature = "proto-ipv6")]
+    fn probe_seq_no_v6(ident: u16, data: &[u8]) -> Option<u16> {
+        let packet = Icmpv6Packet::new_checked(data).ok()?;
+        if packet.msg_type() == Icmpv6Message::EchoRequest && packet.echo_ident() == ident {
+            Some(packet.echo_seq_no())
+        } else {
+            None
+        }
+    }
+}
+
 /// An ICMP packet metadata.
 pub type PacketMetadata = crate::storage::PacketMetadata<IpAddress>;
 
@@ -90,6 +192,8 @@ pub struct Socket<'a> {
     endpoint: Endpoint,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// The hop limit of a probe enqueued with `send_probe` that has not been sent yet.
+    probe_hop_limit: Option<u8>,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -104,6 +208,7 @@ impl<'a> Socket<'a> {
             tx_buffer: tx_buffer,
             endpoint: Default::default(),
             hop_limit: None,
+            probe_hop_limit: None,
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -346,6 +451,158 @@ impl<'a> Socket<'a> {
         Ok(())
     }
 
+    /// Enqueue an Echo Request probe with the given hop limit, sequence number and payload.
+    ///
+    /// This is the building block of traceroute: sending probes with an increasing hop limit
+    /// makes every router along the path answer with a Time Exceeded message, which can be
+    /// matched to its probe using [recv_probe_reply](#method.recv_probe_reply). The hop limit
+    /// only applies to the probe itself; it does not change the [hop_limit](#method.hop_limit)
+    /// of the socket.
+    ///
+    /// The probe uses the identifier the socket is bound to, so this function returns
+    /// `Err(SendError::Unaddressable)` if the socket is not bound to an
+    /// [Endpoint::Ident], or if `endpoint` is unspecified. Because the hop limit is applied
+    /// when the probe is sent, only one probe can be in the transmit buffer at a time;
+    /// this function returns `Err(SendError::BufferFull)` if the transmit buffer is not empty.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a hop limit value of 0 is given.
+    pub fn send_probe(
+        &mut self,
+        hop_limit: u8,
+        seq_no: u16,
+        data: &[u8],
+        endpoint: IpAddress,
+    ) -> Result<(), SendError> {
+        if hop_limit == 0 {
+            panic!("the time-to-live value of a packet must not be zero")
+        }
+
+        if !self.tx_buffer.is_empty() {
+            return Err(SendError::BufferFull);
+        }
+        self.send_echo_request(seq_no, data, endpoint)?;
+        self.probe_hop_limit = Some(hop_limit);
+        Ok(())
+    }
+
+    /// Enqueue an Echo Request to be sent to the given remote address, carrying the
+    /// identifier the socket is bound to.
+    ///
+    /// The request is an ICMPv4 or an ICMPv6 Echo Request depending on the family of
+    /// `endpoint`, and is sent with the hop limit of the socket. Its checksum is computed
+    /// when it is sent. The replies can be received with
+    /// [recv_echo_reply](#method.recv_echo_reply).
+    ///
+    /// This function returns `Err(SendError::Unaddressable)` if the socket is not bound
+    /// to an [Endpoint::Ident], or if `endpoint` is unspecified, and
+    /// `Err(SendError::BufferFull)` if the transmit buffer is full.
+    pub fn send_echo_request(
+        &mut self,
+        seq_no: u16,
+        data: &[u8],
+        endpoint: IpAddress,
+    ) -> Result<(), SendError> {
+        let ident = match self.endpoint {
+            Endpoint::Ident(ident) => ident,
+            _ => return Err(SendError::Unaddressable),
+        };
+
+        match endpoint {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(_) => {
+                let repr = Icmpv4Repr::EchoRequest {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                let packet_buf = self.send(repr.buffer_len(), endpoint)?;
+                repr.emit(
+                    &mut Icmpv4Packet::new_unchecked(packet_buf),
+                    &ChecksumCapabilities::default(),
+                );
+            }
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(_) => {
+                let repr = Icmpv6Repr::EchoRequest {
+                    ident,
+                    seq_no,
+                    data,
+                };
+                let packet_buf = self.send(repr.buffer_len(), endpoint)?;
+                // The checksum is computed when the probe is sent, once the
+                // source address is known.
+                repr.emit(
+                    &IpAddress::Ipv6(Ipv6Address::UNSPECIFIED),
+                    &endpoint,
+                    &mut Icmpv6Packet::new_unchecked(packet_buf),
+                    &ChecksumCapabilities::ignored(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Dequeue the next response to a probe sent with [send_probe](#method.send_probe),
+    /// and return it along with the address of the host that sent it.
+    ///
+    /// Received packets that are not a response to a probe, e.g. Echo Requests carrying
+    /// the identifier the socket is bound to, are discarded.
+    ///
+    /// This function returns `Err(RecvError::Exhausted)` if the receive buffer contains
+    /// no response to a probe.
+    pub fn recv_probe_reply(&mut self) -> Result<(ProbeReply, IpAddress), RecvError> {
+        let ident = match self.endpoint {
+            Endpoint::Ident(ident) => ident,
+            _ => return Err(RecvError::Exhausted),
+        };
+
+        loop {
+            let (packet_buf, endpoint) = self.recv()?;
+            let reply = match endpoint {
+                #[cfg(feature = "proto-ipv4")]
+                IpAddress::Ipv4(_) => ProbeReply::parse_icmpv4(ident, packet_buf),
+                #[cfg(feature = "proto-ipv6")]
+                IpAddress::Ipv6(_) => ProbeReply::parse_icmpv6(ident, packet_buf),
+            };
+            if let Some(reply) = reply {
+                return Ok((reply, endpoint));
+            }
+            net_trace!("icmp:{}: not a probe reply, dropping", endpoint);
+        }
+    }
+
+    /// Dequeue the next Echo Reply carrying the identifier the socket is bound to, and
+    /// return its sequence number, its payload and the address of the host that sent it.
+    ///
+    /// ICMPv4 and ICMPv6 Echo Replies are both returned. Received packets that are not
+    /// an Echo Reply, e.g. error messages, are discarded; use
+    /// [recv_probe_reply](#method.recv_probe_reply) to receive these as well.
+    ///
+    /// This function returns `Err(RecvError::Exhausted)` if the receive buffer contains
+    /// no Echo Reply.
+    pub fn recv_echo_reply(&mut self) -> Result<(u16, &[u8], IpAddress), RecvError> {
+        let ident = match self.endpoint {
+            Endpoint::Ident(ident) => ident,
+            _ => return Err(RecvError::Exhausted),
+        };
+
+        loop {
+            let (&endpoint, packet_buf) =
+                self.rx_buffer.peek().map_err(|_| RecvError::Exhausted)?;
+            match parse_echo_reply(endpoint, packet_buf) {
+                Some((reply_ident, _, _)) if reply_ident == ident => break,
+                _ => net_trace!("icmp:{}: not an echo reply, dropping", endpoint),
+            }
+            let _ = self.rx_buffer.dequeue();
+        }
+
+        let (packet_buf, endpoint) = self.recv()?;
+        let (_, seq_no, data) = parse_echo_reply(endpoint, packet_buf).unwrap();
+        Ok((seq_no, data, endpoint))
+    }
+
     /// Dequeue a packet received from a remote endpoint, and return the `IpAddress` as well
     /// as a pointer to the payload.
     ///
@@ -439,6 +696,22 @@ impl<'a> Socket<'a> {
                 &Endpoint::Ident(bound_ident),
                 &IcmpRepr::Ipv6(Icmpv6Repr::EchoReply { ident, .. }),
             ) => ident == bound_ident,
+            // Also accept errors caused by Echo Requests carrying the bound identifier,
+            // so that the sender of a probe can find out where it was dropped.
+            #[cfg(feature = "proto-ipv4")]
+            (
+                &Endpoint::Ident(bound_ident),
+                &IcmpRepr::Ipv4(
+                    Icmpv4Repr::DstUnreachable { data, .. } | Icmpv4Repr::TimeExceeded { data, .. },
+                ),
+            ) => ProbeReply::probe_seq_no_v4(bound_ident, data).is_some(),
+            #[cfg(feature = "proto-ipv6")]
+            (
+                &Endpoint::Ident(bound_ident),
+                &IcmpRepr::Ipv6(
+                    Icmpv6Repr::DstUnreachable { data, .. } | Icmpv6Repr::TimeExceeded { data, .. },
+                ),
+            ) => ProbeReply::probe_seq_no_v6(bound_ident, data).is_some(),
             _ => false,
         }
     }
@@ -489,7 +762,7 @@ impl<'a> Socket<'a> {
     where
         F: FnOnce(&mut Context, (IpRepr, IcmpRepr)) -> Result<(), E>,
     {
-        let hop_limit = self.hop_limit.unwrap_or(64);
+        let hop_limit = self.probe_hop_limit.or(self.hop_limit).unwrap_or(64);
         let res = self.tx_buffer.dequeue_with(|remote_endpoint, packet_buf| {
             net_trace!(
                 "icmp:{}: sending {} octets",
@@ -510,7 +783,11 @@ impl<'a> Socket<'a> {
                         }
                     };
                     let packet = Icmpv4Packet::new_unchecked(&*packet_buf);
-                    let repr = match Icmpv4Repr::parse(&packet, &ChecksumCapabilities::ignored()) {
+                    let repr = match Icmpv4Repr::parse(
+                        &packet,
+                        &ChecksumCapabilities::ignored(),
+                        ParseMode::Permissive,
+                    ) {
                         Ok(x) => x,
                         Err(_) => {
                             net_trace!(
@@ -547,6 +824,7 @@ impl<'a> Socket<'a> {
                         &dst_addr.into(),
                         &packet,
                         &ChecksumCapabilities::ignored(),
+                        ParseMode::Permissive,
                     ) {
                         Ok(x) => x,
                         Err(_) => {
@@ -572,6 +850,7 @@ impl<'a> Socket<'a> {
             Err(Empty) => Ok(()),
             Ok(Err(e)) => Err(e),
             Ok(Ok(())) => {
+                self.probe_hop_limit = None;
                 #[cfg(feature = "async")]
                 self.tx_waker.wake();
                 Ok(())
@@ -588,6 +867,236 @@ impl<'a> Socket<'a> {
     }
 }
 
+/// Round-trip statistics collected by a [Pinger].
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PingStats {
+    /// Number of Echo Requests sent.
+    pub transmitted: u16,
+    /// Number of Echo Replies received before their request timed out.
+    pub received: u16,
+    /// Shortest round-trip time.
+    pub rtt_min: Option<Duration>,
+    /// Longest round-trip time.
+    pub rtt_max: Option<Duration>,
+    /// Sum of all round-trip times.
+    pub rtt_total: Duration,
+}
+
+impl PingStats {
+    /// Return the number of Echo Requests that were not answered.
+    ///
+    /// Requests that are still awaiting a reply are counted as lost.
+    pub fn lost(&self) -> u16 {
+        self.transmitted.saturating_sub(self.received)
+    }
+
+    /// Return the average round-trip time.
+    pub fn rtt_avg(&self) -> Option<Duration> {
+        match self.received {
+            0 => None,
+            received => Some(self.rtt_total / received as u32),
+        }
+    }
+
+    fn record_rtt(&mut self, rtt: Duration) {
+        self.received += 1;
+        self.rtt_total += rtt;
+        self.rtt_min = Some(self.rtt_min.map_or(rtt, |min| cmp::min(min, rtt)));
+        self.rtt_max = Some(self.rtt_max.map_or(rtt, |max| cmp::max(max, rtt)));
+    }
+}
+
+/// A helper sending a batch of Echo Requests through an ICMP socket and measuring
+/// the round-trip time of the replies.
+///
+/// The pinger does not own the socket; it is passed to [poll](#method.poll), which sends
+/// the next Echo Request when it is due and consumes the received Echo Replies. The socket
+/// must be bound to an [Endpoint::Ident] and should not be used for anything else while
+/// the pinger runs, since every packet it receives is consumed.
+///
+/// The time at which each request is sent is carried in its payload, so the pinger needs
+/// no storage for outstanding requests. Replies arriving after `timeout` are counted as lost.
+#[derive(Debug)]
+pub struct Pinger {
+    remote_addr: IpAddress,
+    count: u16,
+    interval: Duration,
+    timeout: Duration,
+    next_seq_no: u16,
+    next_send_at: Option<Instant>,
+    last_sent_at: Option<Instant>,
+    stats: PingStats,
+}
+
+impl Pinger {
+    /// Create a pinger sending `count` Echo Requests to `remote_addr`, one every `interval`,
+    /// and waiting at most `timeout` for each reply.
+    pub fn new(
+        remote_addr: IpAddress,
+        count: u16,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Pinger {
+        Pinger {
+            remote_addr,
+            count,
+            interval,
+            timeout,
+            next_seq_no: 0,
+            next_send_at: None,
+            last_sent_at: None,
+            stats: PingStats::default(),
+        }
+    }
+
+    /// Return the statistics collected so far.
+    pub fn stats(&self) -> &PingStats {
+        &self.stats
+    }
+
+    /// Query whether every Echo Request has been sent, and either answered or timed out.
+    pub fn is_finished(&self, timestamp: Instant) -> bool {
+        if self.next_seq_no < self.count {
+            return false;
+        }
+        match self.last_sent_at {
+            Some(last_sent_at) => {
+                self.stats.received == self.stats.transmitted
+                    || timestamp >= last_sent_at + self.timeout
+            }
+            None => true,
+        }
+    }
+
+    /// Return the time at which [poll](#method.poll) should be called next, or `None`
+    /// if the pinger is finished.
+    ///
+    /// Received replies are processed whenever `poll` is called, so it should also be
+    /// called after the socket becomes readable.
+    pub fn poll_at(&self, timestamp: Instant) -> Option<Instant> {
+        if self.is_finished(timestamp) {
+            None
+        } else if self.next_seq_no < self.count {
+            Some(self.next_send_at.unwrap_or(timestamp))
+        } else {
+            self.last_sent_at
+                .map(|last_sent_at| last_sent_at + self.timeout)
+        }
+    }
+
+    /// Process the Echo Replies received by `socket`, and send the next Echo Request
+    /// if it is due.
+    ///
+    /// This function returns `Err(SendError::Unaddressable)` if the socket is not bound
+    /// to an [Endpoint::Ident]. If the transmit buffer of the socket is full, the request
+    /// is sent on a later call.
+    pub fn poll(&mut self, timestamp: Instant, socket: &mut Socket) -> Result<(), SendError> {
+        let ident = match socket.endpoint {
+            Endpoint::Ident(ident) => ident,
+            _ => return Err(SendError::Unaddressable),
+        };
+
+        while let Ok((packet_buf, remote_addr)) = socket.recv() {
+            if remote_addr != self.remote_addr {
+                continue;
+            }
+            if let Some((seq_no, sent_at)) = Self::parse_reply(ident, remote_addr, packet_buf) {
+                if seq_no >= self.next_seq_no || sent_at > timestamp {
+                    continue;
+                }
+                let rtt = timestamp - sent_at;
+                if rtt <= self.timeout {
+                    net_trace!(
+                        "icmp:{}: echo reply seq={} rtt={}",
+                        remote_addr,
+                        seq_no,
+                        rtt
+                    );
+                    self.stats.record_rtt(rtt);
+                }
+            }
+        }
+
+        let due = match self.next_send_at {
+            Some(next_send_at) => next_send_at <= timestamp,
+            None => true,
+        };
+        if self.next_seq_no < self.count && due {
+            let mut data = [0; 8];
+            NetworkEndian::write_i64(&mut data, timestamp.total_micros());
+            match socket.send_echo_request(self.next_seq_no, &data, self.remote_addr) {
+                Ok(()) => {
+                    self.next_seq_no += 1;
+                    self.stats.transmitted += 1;
+                    self.next_send_at = Some(timestamp + self.interval);
+                    self.last_sent_at = Some(timestamp);
+                }
+                Err(SendError::BufferFull) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the sequence number of an Echo Reply sent by the pinger, along with the
+    /// time at which the corresponding request was sent.
+    fn parse_reply(ident: u16, remote_addr: IpAddress, buffer: &[u8]) -> Option<(u16, Instant)> {
+        let (reply_ident, seq_no, data) = parse_echo_reply(remote_addr, buffer)?;
+        if reply_ident != ident || data.len() < 8 {
+            return None;
+        }
+        Some((seq_no, Instant::from_micros(NetworkEndian::read_i64(data))))
+    }
+}
+
+/// Return the identifier, sequence number and payload of an ICMPv4 or ICMPv6 Echo Reply
+/// received from `remote_addr`.
+fn parse_echo_reply(remote_addr: IpAddress, buffer: &[u8]) -> Option<(u16, u16, &[u8])> {
+    match remote_addr {
+        #[cfg(feature = "proto-ipv4")]
+        IpAddress::Ipv4(_) => {
+            let packet = Icmpv4Packet::new_checked(buffer).ok()?;
+            match Icmpv4Repr::parse(
+                &packet,
+                &ChecksumCapabilities::ignored(),
+                ParseMode::Permissive,
+            )
+            .ok()?
+            {
+                Icmpv4Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                } => Some((ident, seq_no, data)),
+                _ => None,
+            }
+        }
+        #[cfg(feature = "proto-ipv6")]
+        IpAddress::Ipv6(_) => {
+            let packet = Icmpv6Packet::new_checked(buffer).ok()?;
+            let unspecified = IpAddress::Ipv6(Ipv6Address::UNSPECIFIED);
+            match Icmpv6Repr::parse(
+                &unspecified,
+                &unspecified,
+                &packet,
+                &ChecksumCapabilities::ignored(),
+                ParseMode::Permissive,
+            )
+            .ok()?
+            {
+                Icmpv6Repr::EchoReply {
+                    ident,
+                    seq_no,
+                    data,
+                } => Some((ident, seq_no, data)),
+                _ => None,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests_common {
     pub use super::*;
@@ -618,7 +1127,8 @@ mod tests_common {
 #[cfg(all(test, feature = "proto-ipv4"))]
 mod test_ipv4 {
     use super::tests_common::*;
-    use crate::wire::{Icmpv4DstUnreachable, IpEndpoint, Ipv4Address};
+    use crate::time::{Duration, Instant};
+    use crate::wire::{Icmpv4DstUnreachable, Icmpv4TimeExceeded, IpEndpoint, Ipv4Address};
     use crate::Error;
 
     const REMOTE_IPV4: Ipv4Address = Ipv4Address([192, 168, 1, 2]);
@@ -854,13 +1364,218 @@ mod test_ipv4 {
         );
         assert!(!socket.can_recv());
     }
+
+    #[test]
+    fn test_send_probe() {
+        let mut socket = socket(buffer(0), buffer(2));
+        let mut cx = Context::mock();
+
+        assert_eq!(
+            socket.send_probe(1, 0x5678, &[0xff; 16], REMOTE_IPV4.into()),
+            Err(SendError::Unaddressable)
+        );
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+        assert_eq!(
+            socket.send_probe(1, 0x5678, &[0xff; 16], REMOTE_IPV4.into()),
+            Ok(())
+        );
+        assert_eq!(
+            socket.send_probe(2, 0x5679, &[0xff; 16], REMOTE_IPV4.into()),
+            Err(SendError::BufferFull)
+        );
+
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
+                assert_eq!(ip_repr.hop_limit(), 1);
+                assert_eq!(icmp_repr, ECHOV4_REPR.into());
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+
+        // The hop limit of the probe does not outlive it.
+        let mut bytes = [0xff; 24];
+        let mut packet = Icmpv4Packet::new_unchecked(&mut bytes);
+        ECHOV4_REPR.emit(&mut packet, &ChecksumCapabilities::default());
+        assert_eq!(
+            socket.send_slice(&packet.into_inner()[..], REMOTE_IPV4.into()),
+            Ok(())
+        );
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, _)| {
+                assert_eq!(ip_repr, LOCAL_IPV4_REPR);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_probe_time_exceeded() {
+        let mut socket = socket(buffer(2), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+
+        let checksum = ChecksumCapabilities::default();
+        let mut bytes = [0xff; 24];
+        let mut packet = Icmpv4Packet::new_unchecked(&mut bytes);
+        ECHOV4_REPR.emit(&mut packet, &checksum);
+
+        // Only the first 8 octets of the probe are quoted in the error.
+        let router = Ipv4Address([10, 0, 0, 1]);
+        let icmp_repr = Icmpv4Repr::TimeExceeded {
+            reason: Icmpv4TimeExceeded::TtlExpired,
+            header: Ipv4Repr {
+                src_addr: LOCAL_IPV4,
+                dst_addr: REMOTE_IPV4,
+                next_header: IpProtocol::Icmp,
+                payload_len: 8,
+                hop_limit: 1,
+            },
+            data: &packet.into_inner()[..8],
+        };
+        let ip_repr = IpRepr::Ipv4(Ipv4Repr {
+            src_addr: router,
+            dst_addr: LOCAL_IPV4,
+            next_header: IpProtocol::Icmp,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 0x40,
+        });
+        assert!(socket.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+        socket.process(&mut cx, &ip_repr, &icmp_repr.into());
+
+        let reply_repr = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 0x5679,
+            data: &[0xff; 16],
+        };
+        assert!(socket.accepts(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into()));
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into());
+
+        assert_eq!(
+            socket.recv_probe_reply(),
+            Ok((ProbeReply::TimeExceeded { seq_no: 0x5678 }, router.into()))
+        );
+        assert_eq!(
+            socket.recv_probe_reply(),
+            Ok((ProbeReply::EchoReply { seq_no: 0x5679 }, REMOTE_IPV4.into()))
+        );
+        assert_eq!(socket.recv_probe_reply(), Err(RecvError::Exhausted));
+
+        // Ensure that errors caused by probes with another identifier
+        // are not accepted
+        let mut other = Socket::new(buffer(1), buffer(1));
+        assert_eq!(other.bind(Endpoint::Ident(0x4321)), Ok(()));
+        assert!(!other.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+    }
+
+    #[test]
+    fn test_echo_request_reply() {
+        let mut socket = socket(buffer(2), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+        assert_eq!(
+            socket.send_echo_request(0x5678, &[0xff; 16], REMOTE_IPV4.into()),
+            Ok(())
+        );
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
+                assert_eq!(ip_repr, LOCAL_IPV4_REPR);
+                assert_eq!(icmp_repr, ECHOV4_REPR.into());
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+
+        let reply_repr = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 0x5678,
+            data: &[0xff; 16],
+        };
+        // An Echo Request carrying our identifier is not a reply, and is dropped.
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &ECHOV4_REPR.into());
+        assert!(socket.accepts(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into()));
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into());
+
+        assert_eq!(
+            socket.recv_echo_reply(),
+            Ok((0x5678, &[0xff; 16][..], REMOTE_IPV4.into()))
+        );
+        assert_eq!(socket.recv_echo_reply(), Err(RecvError::Exhausted));
+    }
+
+    #[test]
+    fn test_pinger() {
+        let mut socket = socket(buffer(2), buffer(2));
+        let mut cx = Context::mock();
+        let mut pinger = Pinger::new(
+            REMOTE_IPV4.into(),
+            2,
+            Duration::from_millis(100),
+            Duration::from_millis(500),
+        );
+
+        assert_eq!(
+            pinger.poll(Instant::from_millis(0), &mut socket),
+            Err(SendError::Unaddressable)
+        );
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+
+        // Send the first request, and reply to it 20ms later.
+        assert_eq!(pinger.poll(Instant::from_millis(0), &mut socket), Ok(()));
+        assert_eq!(
+            pinger.poll_at(Instant::from_millis(0)),
+            Some(Instant::from_millis(100))
+        );
+        let mut data = [0; 8];
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (_, icmp_repr)| {
+                match icmp_repr {
+                    IcmpRepr::Ipv4(Icmpv4Repr::EchoRequest {
+                        ident: 0x1234,
+                        seq_no: 0,
+                        data: request_data,
+                    }) => data.copy_from_slice(request_data),
+                    _ => panic!("expected an echo request, got {:?}", icmp_repr),
+                }
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+        let reply_repr = Icmpv4Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 0,
+            data: &data,
+        };
+        assert!(socket.accepts(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into()));
+        socket.process(&mut cx, &REMOTE_IPV4_REPR, &reply_repr.into());
+        assert_eq!(pinger.poll(Instant::from_millis(20), &mut socket), Ok(()));
+        assert_eq!(pinger.stats().received, 1);
+
+        // Send the second request, which is never answered.
+        assert_eq!(pinger.poll(Instant::from_millis(100), &mut socket), Ok(()));
+        assert!(!pinger.is_finished(Instant::from_millis(100)));
+        assert_eq!(
+            pinger.poll_at(Instant::from_millis(100)),
+            Some(Instant::from_millis(600))
+        );
+        assert!(pinger.is_finished(Instant::from_millis(600)));
+        assert_eq!(pinger.poll_at(Instant::from_millis(600)), None);
+
+        let stats = pinger.stats();
+        assert_eq!(stats.transmitted, 2);
+        assert_eq!(stats.lost(), 1);
+        assert_eq!(stats.rtt_min, Some(Duration::from_millis(20)));
+        assert_eq!(stats.rtt_max, Some(Duration::from_millis(20)));
+        assert_eq!(stats.rtt_avg(), Some(Duration::from_millis(20)));
+    }
 }
 
 #[cfg(all(test, feature = "proto-ipv6"))]
 mod test_ipv6 {
     use super::tests_common::*;
 
-    use crate::wire::{Icmpv6DstUnreachable, IpEndpoint, Ipv6Address};
+    use crate::wire::{Icmpv6DstUnreachable, Icmpv6TimeExceeded, IpEndpoint, Ipv6Address};
     use crate::Error;
 
     const REMOTE_IPV6: Ipv6Address =
@@ -1122,4 +1837,100 @@ mod test_ipv6 {
         );
         assert!(!socket.can_recv());
     }
+
+    #[test]
+    fn test_probe_time_exceeded() {
+        let mut socket = socket(buffer(2), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+        assert_eq!(
+            socket.send_probe(1, 0x5678, &[0xff; 16], REMOTE_IPV6.into()),
+            Ok(())
+        );
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
+                assert_eq!(ip_repr.hop_limit(), 1);
+                assert_eq!(icmp_repr, ECHOV6_REPR.into());
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+
+        let checksum = ChecksumCapabilities::default();
+        let mut bytes = [0xff; 24];
+        let mut packet = Icmpv6Packet::new_unchecked(&mut bytes);
+        ECHOV6_REPR.emit(
+            &LOCAL_IPV6.into(),
+            &REMOTE_IPV6.into(),
+            &mut packet,
+            &checksum,
+        );
+
+        let router = Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xfe]);
+        let icmp_repr = Icmpv6Repr::TimeExceeded {
+            reason: Icmpv6TimeExceeded::HopLimitExceeded,
+            header: Ipv6Repr {
+                src_addr: LOCAL_IPV6,
+                dst_addr: REMOTE_IPV6,
+                next_header: IpProtocol::Icmpv6,
+                payload_len: 24,
+                hop_limit: 1,
+            },
+            data: &packet.into_inner()[..],
+        };
+        let ip_repr = IpRepr::Ipv6(Ipv6Repr {
+            src_addr: router,
+            dst_addr: LOCAL_IPV6,
+            next_header: IpProtocol::Icmpv6,
+            payload_len: icmp_repr.buffer_len(),
+            hop_limit: 0x40,
+        });
+        assert!(socket.accepts(&mut cx, &ip_repr, &icmp_repr.into()));
+        socket.process(&mut cx, &ip_repr, &icmp_repr.into());
+
+        assert_eq!(
+            socket.recv_probe_reply(),
+            Ok((ProbeReply::TimeExceeded { seq_no: 0x5678 }, router.into()))
+        );
+        assert_eq!(socket.recv_probe_reply(), Err(RecvError::Exhausted));
+    }
+
+    #[test]
+    fn test_echo_request_reply() {
+        let mut socket = socket(buffer(2), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(
+            socket.send_echo_request(0x5678, &[0xff; 16], REMOTE_IPV6.into()),
+            Err(SendError::Unaddressable)
+        );
+        assert_eq!(socket.bind(Endpoint::Ident(0x1234)), Ok(()));
+        assert_eq!(
+            socket.send_echo_request(0x5678, &[0xff; 16], REMOTE_IPV6.into()),
+            Ok(())
+        );
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, (ip_repr, icmp_repr)| {
+                assert_eq!(ip_repr, LOCAL_IPV6_REPR);
+                assert_eq!(icmp_repr, ECHOV6_REPR.into());
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+
+        let reply_repr = Icmpv6Repr::EchoReply {
+            ident: 0x1234,
+            seq_no: 0x5678,
+            data: &[0xff; 16],
+        };
+        // An Echo Request carrying our identifier is not a reply, and is dropped.
+        socket.process(&mut cx, &REMOTE_IPV6_REPR, &ECHOV6_REPR.into());
+        assert!(socket.accepts(&mut cx, &REMOTE_IPV6_REPR, &reply_repr.into()));
+        socket.process(&mut cx, &REMOTE_IPV6_REPR, &reply_repr.into());
+
+        assert_eq!(
+            socket.recv_echo_reply(),
+            Ok((0x5678, &[0xff; 16][..], REMOTE_IPV6.into()))
+        );
+        assert_eq!(socket.recv_echo_reply(), Err(RecvError::Exhausted));
+    }
 }
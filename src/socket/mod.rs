@@ -11,8 +11,21 @@ The interface implemented by this module uses explicit buffering: you decide on
 size for a buffer, allocate it, and let the networking stack use it.
 */
 
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+use core::fmt;
+
 use crate::iface::Context;
 use crate::time::Instant;
+#[cfg(all(
+    any(feature = "socket-tcp", feature = "socket-udp"),
+    feature = "proto-ipv4"
+))]
+use crate::wire::Icmpv4DstUnreachable;
+#[cfg(all(
+    any(feature = "socket-tcp", feature = "socket-udp"),
+    feature = "proto-ipv6"
+))]
+use crate::wire::Icmpv6DstUnreachable;
 
 #[cfg(feature = "socket-dhcpv4")]
 pub mod dhcpv4;
@@ -22,6 +35,8 @@ pub mod dns;
 pub mod icmp;
 #[cfg(feature = "socket-raw")]
 pub mod raw;
+#[cfg(feature = "socket-sntp")]
+pub mod sntp;
 #[cfg(feature = "socket-tcp")]
 pub mod tcp;
 #[cfg(feature = "socket-udp")]
@@ -33,6 +48,110 @@ mod waker;
 #[cfg(feature = "async")]
 pub(crate) use self::waker::WakerRegistration;
 
+/// A change in the fill level of a socket buffer, reported to the callback registered
+/// with `set_buffer_callback` on TCP and UDP sockets.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BufferEvent {
+    /// The receive buffer holds at least as much data as its high watermark.
+    Readable,
+    /// The transmit buffer holds less data than its low watermark.
+    Writable,
+}
+
+/// An ICMP error reported by a router or the remote host about a packet sent by a TCP
+/// or UDP socket.
+///
+/// ICMPv4 and ICMPv6 errors are both reported with this type. TCP sockets abort a
+/// connection being opened on a [hard](#method.is_hard) error, and record other errors
+/// without closing the connection; UDP sockets record the last error they received.
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IcmpError {
+    /// There is no route to the destination network.
+    NetUnreachable,
+    /// The destination host is unreachable.
+    HostUnreachable,
+    /// The destination host does not support the transport protocol.
+    ProtoUnreachable,
+    /// No socket is listening on the destination port.
+    PortUnreachable,
+    /// Communication with the destination is administratively prohibited.
+    Prohibited,
+    /// The hop limit of the packet reached zero in transit.
+    TimeExceeded,
+}
+
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+impl IcmpError {
+    /// Query whether the error is permanent, as opposed to a condition that may go away
+    /// before the connection times out, as per [RFC 1122 § 4.2.3.9].
+    ///
+    /// [RFC 1122 § 4.2.3.9]: https://tools.ietf.org/html/rfc1122#section-4.2.3.9
+    pub fn is_hard(&self) -> bool {
+        matches!(
+            self,
+            IcmpError::ProtoUnreachable | IcmpError::PortUnreachable | IcmpError::Prohibited
+        )
+    }
+
+    /// Return the error reported by an ICMPv4 Destination Unreachable message, or `None`
+    /// if it is not an error, e.g. a request to fragment the packet.
+    #[cfg(feature = "proto-ipv4")]
+    pub(crate) fn from_icmpv4(reason: Icmpv4DstUnreachable) -> Option<IcmpError> {
+        match reason {
+            Icmpv4DstUnreachable::NetUnreachable
+            | Icmpv4DstUnreachable::DstNetUnknown
+            | Icmpv4DstUnreachable::NetUnreachToS => Some(IcmpError::NetUnreachable),
+            Icmpv4DstUnreachable::HostUnreachable
+            | Icmpv4DstUnreachable::SrcRouteFailed
+            | Icmpv4DstUnreachable::DstHostUnknown
+            | Icmpv4DstUnreachable::SrcHostIsolated
+            | Icmpv4DstUnreachable::HostUnreachToS => Some(IcmpError::HostUnreachable),
+            Icmpv4DstUnreachable::ProtoUnreachable => Some(IcmpError::ProtoUnreachable),
+            Icmpv4DstUnreachable::PortUnreachable => Some(IcmpError::PortUnreachable),
+            Icmpv4DstUnreachable::NetProhibited
+            | Icmpv4DstUnreachable::HostProhibited
+            | Icmpv4DstUnreachable::CommProhibited
+            | Icmpv4DstUnreachable::HostPrecedViol
+            | Icmpv4DstUnreachable::PrecedCutoff => Some(IcmpError::Prohibited),
+            Icmpv4DstUnreachable::FragRequired | Icmpv4DstUnreachable::Unknown(_) => None,
+        }
+    }
+
+    /// Return the error reported by an ICMPv6 Destination Unreachable message, or `None`
+    /// if its code is unknown.
+    #[cfg(feature = "proto-ipv6")]
+    pub(crate) fn from_icmpv6(reason: Icmpv6DstUnreachable) -> Option<IcmpError> {
+        match reason {
+            Icmpv6DstUnreachable::NoRoute => Some(IcmpError::NetUnreachable),
+            Icmpv6DstUnreachable::BeyondScope | Icmpv6DstUnreachable::AddrUnreachable => {
+                Some(IcmpError::HostUnreachable)
+            }
+            Icmpv6DstUnreachable::PortUnreachable => Some(IcmpError::PortUnreachable),
+            Icmpv6DstUnreachable::AdminProhibit
+            | Icmpv6DstUnreachable::FailedPolicy
+            | Icmpv6DstUnreachable::RejectRoute => Some(IcmpError::Prohibited),
+            Icmpv6DstUnreachable::Unknown(_) => None,
+        }
+    }
+}
+
+#[cfg(any(feature = "socket-tcp", feature = "socket-udp"))]
+impl fmt::Display for IcmpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IcmpError::NetUnreachable => write!(f, "network unreachable"),
+            IcmpError::HostUnreachable => write!(f, "host unreachable"),
+            IcmpError::ProtoUnreachable => write!(f, "protocol unreachable"),
+            IcmpError::PortUnreachable => write!(f, "port unreachable"),
+            IcmpError::Prohibited => write!(f, "administratively prohibited"),
+            IcmpError::TimeExceeded => write!(f, "time exceeded"),
+        }
+    }
+}
+
 /// Gives an indication on the next time the socket should be polled.
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -66,9 +185,11 @@ pub enum Socket<'a> {
     #[cfg(feature = "socket-tcp")]
     Tcp(tcp::Socket<'a>),
     #[cfg(feature = "socket-dhcpv4")]
-    Dhcpv4(dhcpv4::Socket),
+    Dhcpv4(dhcpv4::Socket<'a>),
     #[cfg(feature = "socket-dns")]
     Dns(dns::Socket<'a>),
+    #[cfg(feature = "socket-sntp")]
+    Sntp(sntp::Socket),
 }
 
 impl<'a> Socket<'a> {
@@ -86,6 +207,8 @@ impl<'a> Socket<'a> {
             Socket::Dhcpv4(s) => s.poll_at(cx),
             #[cfg(feature = "socket-dns")]
             Socket::Dns(s) => s.poll_at(cx),
+            #[cfg(feature = "socket-sntp")]
+            Socket::Sntp(s) => s.poll_at(cx),
         }
     }
 }
@@ -132,6 +255,8 @@ from_socket!(udp::Socket<'a>, Udp);
 #[cfg(feature = "socket-tcp")]
 from_socket!(tcp::Socket<'a>, Tcp);
 #[cfg(feature = "socket-dhcpv4")]
-from_socket!(dhcpv4::Socket, Dhcpv4);
+from_socket!(dhcpv4::Socket<'a>, Dhcpv4);
 #[cfg(feature = "socket-dns")]
 from_socket!(dns::Socket<'a>, Dns);
+#[cfg(feature = "socket-sntp")]
+from_socket!(sntp::Socket, Sntp);
@@ -8,9 +8,10 @@ use crate::socket::PollAt;
 use crate::socket::WakerRegistration;
 
 use crate::storage::Empty;
+use crate::time::Instant;
 use crate::wire::{IpProtocol, IpRepr, IpVersion};
 #[cfg(feature = "proto-ipv4")]
-use crate::wire::{Ipv4Packet, Ipv4Repr};
+use crate::wire::{Ipv4Packet, Ipv4Repr, ParseMode};
 #[cfg(feature = "proto-ipv6")]
 use crate::wire::{Ipv6Packet, Ipv6Repr};
 
@@ -37,10 +38,10 @@ pub enum RecvError {
 }
 
 /// A UDP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<()>;
+pub type PacketMetadata = crate::storage::PacketMetadata<Option<Instant>>;
 
 /// A UDP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, ()>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, Option<Instant>>;
 
 /// A raw IP socket.
 ///
@@ -52,6 +53,7 @@ pub struct Socket<'a> {
     ip_protocol: IpProtocol,
     rx_buffer: PacketBuffer<'a>,
     tx_buffer: PacketBuffer<'a>,
+    tx_timestamp: Option<Instant>,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -72,6 +74,7 @@ impl<'a> Socket<'a> {
             ip_protocol,
             rx_buffer,
             tx_buffer,
+            tx_timestamp: None,
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -162,6 +165,13 @@ impl<'a> Socket<'a> {
         self.tx_buffer.payload_capacity()
     }
 
+    /// Return the time at which the device transmitted the last packet sent through
+    /// the socket, if the device records one.
+    #[inline]
+    pub fn tx_timestamp(&self) -> Option<Instant> {
+        self.tx_timestamp
+    }
+
     /// Enqueue a packet to send, and return a pointer to its payload.
     ///
     /// This function returns `Err(Error::Exhausted)` if the transmit buffer is full,
@@ -176,7 +186,7 @@ impl<'a> Socket<'a> {
     pub fn send(&mut self, size: usize) -> Result<&mut [u8], SendError> {
         let packet_buf = self
             .tx_buffer
-            .enqueue(size, ())
+            .enqueue(size, None)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -198,7 +208,7 @@ impl<'a> Socket<'a> {
     {
         let size = self
             .tx_buffer
-            .enqueue_with_infallible(max_size, (), f)
+            .enqueue_with_infallible(max_size, None, f)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
@@ -226,7 +236,15 @@ impl<'a> Socket<'a> {
     /// **Note:** The IP header is parsed and re-serialized, and may not match
     /// the header actually received bit for bit.
     pub fn recv(&mut self) -> Result<&[u8], RecvError> {
-        let ((), packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
+        self.recv_timestamped().map(|(packet_buf, _)| packet_buf)
+    }
+
+    /// Dequeue a packet, and return a pointer to the payload, along with the time at which
+    /// the device received it, if the device records one.
+    ///
+    /// See also [recv](#method.recv).
+    pub fn recv_timestamped(&mut self) -> Result<(&[u8], Option<Instant>), RecvError> {
+        let (timestamp, packet_buf) = self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
         net_trace!(
             "raw:{}:{}: receive {} buffered octets",
@@ -234,7 +252,7 @@ impl<'a> Socket<'a> {
             self.ip_protocol,
             packet_buf.len()
         );
-        Ok(packet_buf)
+        Ok((packet_buf, timestamp))
     }
 
     /// Dequeue a packet, and copy the payload into the given slice.
@@ -271,7 +289,7 @@ impl<'a> Socket<'a> {
             total_len
         );
 
-        match self.rx_buffer.enqueue(total_len, ()) {
+        match self.rx_buffer.enqueue(total_len, cx.rx_timestamp()) {
             Ok(buf) => {
                 ip_repr.emit(&mut buf[..header_len], &cx.checksum_caps());
                 buf[header_len..].copy_from_slice(payload);
@@ -294,7 +312,8 @@ impl<'a> Socket<'a> {
         let ip_protocol = self.ip_protocol;
         let ip_version = self.ip_version;
         let _checksum_caps = &cx.checksum_caps();
-        let res = self.tx_buffer.dequeue_with(|&mut (), buffer| {
+        let mut emitted = false;
+        let res = self.tx_buffer.dequeue_with(|_, buffer| {
             match IpVersion::of_packet(buffer) {
                 #[cfg(feature = "proto-ipv4")]
                 Ok(IpVersion::Ipv4) => {
@@ -318,14 +337,16 @@ impl<'a> Socket<'a> {
                     }
 
                     let packet = Ipv4Packet::new_unchecked(&*packet.into_inner());
-                    let ipv4_repr = match Ipv4Repr::parse(&packet, _checksum_caps) {
-                        Ok(x) => x,
-                        Err(_) => {
-                            net_trace!("raw: malformed ipv4 packet in queue, dropping.");
-                            return Ok(());
-                        }
-                    };
+                    let ipv4_repr =
+                        match Ipv4Repr::parse(&packet, _checksum_caps, ParseMode::Permissive) {
+                            Ok(x) => x,
+                            Err(_) => {
+                                net_trace!("raw: malformed ipv4 packet in queue, dropping.");
+                                return Ok(());
+                            }
+                        };
                     net_trace!("raw:{}:{}: sending", ip_version, ip_protocol);
+                    emitted = true;
                     emit(cx, (IpRepr::Ipv4(ipv4_repr), packet.payload()))
                 }
                 #[cfg(feature = "proto-ipv6")]
@@ -351,6 +372,7 @@ impl<'a> Socket<'a> {
                     };
 
                     net_trace!("raw:{}:{}: sending", ip_version, ip_protocol);
+                    emitted = true;
                     emit(cx, (IpRepr::Ipv6(ipv6_repr), packet.payload()))
                 }
                 Err(_) => {
@@ -363,6 +385,9 @@ impl<'a> Socket<'a> {
             Err(Empty) => Ok(()),
             Ok(Err(e)) => Err(e),
             Ok(Ok(())) => {
+                if emitted {
+                    self.tx_timestamp = cx.tx_timestamp();
+                }
                 #[cfg(feature = "async")]
                 self.tx_waker.wake();
                 Ok(())
@@ -0,0 +1,495 @@
+#[cfg(feature = "async")]
+use core::task::Waker;
+
+use crate::iface::Context;
+use crate::time::{Duration, Instant};
+use crate::wire::{
+    IpAddress, IpProtocol, IpRepr, NtpLeapIndicator, NtpPacket, NtpProtocolMode, NtpRepr,
+    NtpTimestamp, UdpRepr, NTP_SERVER_PORT,
+};
+
+#[cfg(feature = "async")]
+use super::WakerRegistration;
+
+use super::PollAt;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1024);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An in-flight request to the server.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+struct PendingRequest {
+    /// Local port the request was sent from.
+    port: u16,
+    /// Transmit timestamp of the request, which the server echoes as the origin timestamp.
+    ///
+    /// As recommended by RFC 5905 § 9.1, this is a random nonce rather than the local time,
+    /// since the local clock is not synchronized.
+    nonce: NtpTimestamp,
+    /// When the request was sent.
+    sent_at: Instant,
+}
+
+/// SNTP client socket.
+///
+/// The socket periodically queries an NTP server, as described in RFC 4330,
+/// and computes the offset between the local clock (the [Instant] passed to
+/// `Interface::poll()`) and UTC.
+///
+/// The offset can be queried with `.offset()`, or delivered through a callback
+/// registered with `.set_offset_callback()` each time a valid response arrives.
+#[derive(Debug)]
+pub struct Socket {
+    server: IpAddress,
+    poll_interval: Duration,
+    hop_limit: Option<u8>,
+    offset_callback: Option<fn(i64)>,
+
+    /// Offset between the local clock and the Unix epoch, in microseconds.
+    offset: Option<i64>,
+    /// When to send the next request.
+    next_request_at: Instant,
+    pending: Option<PendingRequest>,
+
+    /// Waker registration
+    #[cfg(feature = "async")]
+    waker: WakerRegistration,
+}
+
+impl Socket {
+    /// Create an SNTP socket that queries the given server.
+    pub fn new(server: IpAddress) -> Self {
+        Socket {
+            server,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            hop_limit: None,
+            offset_callback: None,
+            offset: None,
+            next_request_at: Instant::ZERO,
+            pending: None,
+            #[cfg(feature = "async")]
+            waker: WakerRegistration::new(),
+        }
+    }
+
+    /// Return the address of the NTP server.
+    pub fn server(&self) -> IpAddress {
+        self.server
+    }
+
+    /// Set the address of the NTP server.
+    ///
+    /// Any outstanding request is abandoned and a new one is sent immediately.
+    pub fn set_server(&mut self, server: IpAddress) {
+        self.server = server;
+        self.pending = None;
+        self.next_request_at = Instant::ZERO;
+    }
+
+    /// Return the interval between successful requests.
+    ///
+    /// See also the [set_poll_interval](#method.set_poll_interval) method
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Set the interval between successful requests.
+    ///
+    /// The default is 1024 seconds. RFC 4330 § 10 asks clients not to poll
+    /// a server more often than once every 15 seconds.
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Return the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
+    ///
+    /// See also the [set_hop_limit](#method.set_hop_limit) method
+    pub fn hop_limit(&self) -> Option<u8> {
+        self.hop_limit
+    }
+
+    /// Set the time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
+    ///
+    /// A socket without an explicitly set hop limit value uses the default [IANA recommended]
+    /// value (64).
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a hop limit value of 0 is given. See [RFC 1122 § 3.2.1.7].
+    ///
+    /// [IANA recommended]: https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml
+    /// [RFC 1122 § 3.2.1.7]: https://tools.ietf.org/html/rfc1122#section-3.2.1.7
+    pub fn set_hop_limit(&mut self, hop_limit: Option<u8>) {
+        // A host MUST NOT send a datagram with a hop limit value of 0
+        if let Some(0) = hop_limit {
+            panic!("the time-to-live value of a packet must not be zero")
+        }
+
+        self.hop_limit = hop_limit
+    }
+
+    /// Set a function to be called with the new clock offset whenever a valid response arrives.
+    ///
+    /// The offset has the same meaning as the return value of [offset](#method.offset).
+    pub fn set_offset_callback(&mut self, callback: Option<fn(i64)>) {
+        self.offset_callback = callback;
+    }
+
+    /// Return the offset between the local clock and the Unix epoch, in microseconds,
+    /// or `None` if no valid response has been received yet.
+    ///
+    /// Adding the offset to `Instant::total_micros()` gives the number of microseconds
+    /// since the Unix epoch.
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+
+    /// Return the number of microseconds since the Unix epoch at the given local time,
+    /// or `None` if no valid response has been received yet.
+    pub fn unix_micros(&self, now: Instant) -> Option<i64> {
+        self.offset.map(|offset| now.total_micros() + offset)
+    }
+
+    /// Forget the current offset and send a new request immediately.
+    pub fn reset(&mut self) {
+        net_trace!("SNTP reset");
+        self.offset = None;
+        self.pending = None;
+        self.next_request_at = Instant::ZERO;
+    }
+
+    /// Register a waker.
+    ///
+    /// The waker is woken when a valid response updates the clock offset.
+    ///
+    /// Notes:
+    ///
+    /// - Only one waker can be registered at a time. If another waker was previously registered,
+    ///   it is overwritten and will no longer be woken.
+    /// - The Waker is woken only once. Once woken, you must register it again to receive more wakes.
+    #[cfg(feature = "async")]
+    pub fn register_waker(&mut self, waker: &Waker) {
+        self.waker.register(waker)
+    }
+
+    pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
+        PollAt::Time(self.next_request_at)
+    }
+
+    pub(crate) fn accepts(&self, ip_repr: &IpRepr, udp_repr: &UdpRepr) -> bool {
+        match &self.pending {
+            Some(pending) => {
+                udp_repr.src_port == NTP_SERVER_PORT
+                    && udp_repr.dst_port == pending.port
+                    && ip_repr.src_addr() == self.server
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn process(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        udp_repr: &UdpRepr,
+        payload: &[u8],
+    ) {
+        debug_assert!(self.accepts(ip_repr, udp_repr));
+
+        let pending = match &self.pending {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        let ntp_packet = match NtpPacket::new_checked(payload) {
+            Ok(ntp_packet) => ntp_packet,
+            Err(e) => {
+                net_debug!("SNTP invalid pkt from {}: {:?}", ip_repr.src_addr(), e);
+                return;
+            }
+        };
+        let ntp_repr = match NtpRepr::parse(&ntp_packet) {
+            Ok(ntp_repr) => ntp_repr,
+            Err(e) => {
+                net_debug!(
+                    "SNTP error parsing pkt from {}: {:?}",
+                    ip_repr.src_addr(),
+                    e
+                );
+                return;
+            }
+        };
+
+        if ntp_repr.mode != NtpProtocolMode::Server {
+            net_debug!("SNTP ignoring {} packet", ntp_repr.mode);
+            return;
+        }
+        if ntp_repr.orig_timestamp != pending.nonce {
+            net_debug!("SNTP ignoring response with mismatched origin timestamp");
+            return;
+        }
+
+        let sent_at = pending.sent_at;
+        self.pending = None;
+        self.next_request_at = cx.now() + self.poll_interval;
+
+        if ntp_repr.stratum == 0 {
+            // Kiss-o'-death; the reference ID holds the kiss code. RFC 4330 § 8 asks
+            // clients to stop sending to the server on "DENY" and "RSTR", and to back
+            // off on anything else, but this socket only knows about a single server.
+            net_debug!("SNTP kiss-o'-death {:?}", ntp_repr.ref_id);
+            return;
+        }
+        if ntp_repr.leap_indicator == NtpLeapIndicator::Unsynchronized
+            || ntp_repr.xmit_timestamp == NtpTimestamp::ZERO
+        {
+            net_debug!("SNTP ignoring response from unsynchronized server");
+            return;
+        }
+
+        // RFC 4330 § 5: offset = ((T2 - T1) + (T3 - T4)) / 2, where T1 and T4 are
+        // local times and T2 and T3 are server times.
+        let t1 = sent_at.total_micros();
+        let t2 = ntp_repr.recv_timestamp.unix_micros();
+        let t3 = ntp_repr.xmit_timestamp.unix_micros();
+        let t4 = cx.now().total_micros();
+        let offset = ((t2 - t1) + (t3 - t4)) / 2;
+
+        net_debug!(
+            "SNTP offset {}us, round trip {}us",
+            offset,
+            (t4 - t1) - (t3 - t2)
+        );
+
+        self.offset = Some(offset);
+        if let Some(callback) = self.offset_callback {
+            callback(offset);
+        }
+        #[cfg(feature = "async")]
+        self.waker.wake();
+    }
+
+    pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
+    where
+        F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
+    {
+        if cx.now() < self.next_request_at {
+            return Ok(());
+        }
+
+        let src_addr = match cx.get_source_address(self.server) {
+            Some(src_addr) => src_addr,
+            None => {
+                net_trace!("SNTP no source address for {}", self.server);
+                self.next_request_at = cx.now() + REQUEST_TIMEOUT;
+                return Ok(());
+            }
+        };
+
+        let pending = PendingRequest {
+            port: cx.rand().rand_source_port(),
+            nonce: NtpTimestamp {
+                seconds: cx.rand().rand_u32(),
+                fraction: cx.rand().rand_u32(),
+            },
+            sent_at: cx.now(),
+        };
+
+        let ntp_repr = NtpRepr::client_request(pending.nonce);
+        let mut payload = [0u8; crate::wire::NTP_PACKET_LEN];
+        ntp_repr.emit(&mut NtpPacket::new_unchecked(&mut payload[..]));
+
+        let udp_repr = UdpRepr {
+            src_port: pending.port,
+            dst_port: NTP_SERVER_PORT,
+        };
+        let ip_repr = IpRepr::new(
+            src_addr,
+            self.server,
+            IpProtocol::Udp,
+            udp_repr.header_len() + payload.len(),
+            self.hop_limit.unwrap_or(64),
+        );
+
+        net_trace!("SNTP sending request to {}", self.server);
+        emit(cx, (ip_repr, udp_repr, &payload[..]))?;
+
+        // Only update state after successfully sending.
+        self.next_request_at = cx.now() + REQUEST_TIMEOUT;
+        self.pending = Some(pending);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "proto-ipv4"))]
+mod test {
+    use super::*;
+    use crate::wire::Ipv4Address;
+    use crate::Error;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    const SERVER_IP: IpAddress = IpAddress::Ipv4(Ipv4Address([192, 168, 1, 123]));
+    const MY_IP: IpAddress = IpAddress::Ipv4(Ipv4Address([192, 168, 1, 1]));
+
+    // 2022-01-01 00:00:00 UTC
+    const UNIX_BASE: i64 = 1_640_995_200_000_000;
+
+    fn send_request(s: &mut Socket, cx: &mut Context, now: Instant) -> (UdpRepr, NtpRepr) {
+        cx.set_now(now);
+        let mut sent = None;
+        s.dispatch(cx, |_, (ip_repr, udp_repr, payload)| {
+            assert_eq!(ip_repr.src_addr(), MY_IP);
+            assert_eq!(ip_repr.dst_addr(), SERVER_IP);
+            assert_eq!(udp_repr.dst_port, NTP_SERVER_PORT);
+            let repr = NtpRepr::parse(&NtpPacket::new_checked(payload).unwrap()).unwrap();
+            sent = Some((udp_repr, repr));
+            Ok::<_, Error>(())
+        })
+        .unwrap();
+        sent.expect("no request sent")
+    }
+
+    fn response(request: &NtpRepr, recv_micros: i64, xmit_micros: i64) -> NtpRepr {
+        NtpRepr {
+            leap_indicator: NtpLeapIndicator::NoWarning,
+            mode: NtpProtocolMode::Server,
+            stratum: 2,
+            ref_id: [192, 0, 2, 1],
+            orig_timestamp: request.xmit_timestamp,
+            recv_timestamp: NtpTimestamp::from_unix_micros(recv_micros),
+            xmit_timestamp: NtpTimestamp::from_unix_micros(xmit_micros),
+            ..NtpRepr::client_request(NtpTimestamp::ZERO)
+        }
+    }
+
+    fn recv_response(
+        s: &mut Socket,
+        cx: &mut Context,
+        now: Instant,
+        request_udp: &UdpRepr,
+        repr: &NtpRepr,
+    ) {
+        cx.set_now(now);
+        let ip_repr = IpRepr::new(SERVER_IP, MY_IP, IpProtocol::Udp, 0, 64);
+        let udp_repr = UdpRepr {
+            src_port: NTP_SERVER_PORT,
+            dst_port: request_udp.src_port,
+        };
+        let mut payload = vec![0; repr.buffer_len()];
+        repr.emit(&mut NtpPacket::new_unchecked(&mut payload));
+        assert!(s.accepts(&ip_repr, &udp_repr));
+        s.process(cx, &ip_repr, &udp_repr, &payload);
+    }
+
+    #[test]
+    fn test_offset() {
+        static CALLBACK_OFFSET: AtomicI64 = AtomicI64::new(0);
+        fn callback(offset: i64) {
+            CALLBACK_OFFSET.store(offset, Ordering::SeqCst)
+        }
+
+        let mut cx = Context::mock();
+        let mut s = Socket::new(SERVER_IP);
+        s.set_offset_callback(Some(callback));
+        s.set_poll_interval(Duration::from_secs(64));
+        assert_eq!(s.poll_at(&mut cx), PollAt::Time(Instant::ZERO));
+
+        // Request at local time 10s, response at 10.2s; the server clock
+        // reads UNIX_BASE + 0.1s on receipt and UNIX_BASE + 0.1s on transmit.
+        let (udp_repr, request) = send_request(&mut s, &mut cx, Instant::from_secs(10));
+        assert_eq!(request.mode, NtpProtocolMode::Client);
+        let reply = response(&request, UNIX_BASE + 100_000, UNIX_BASE + 100_000);
+        recv_response(
+            &mut s,
+            &mut cx,
+            Instant::from_millis(10_200),
+            &udp_repr,
+            &reply,
+        );
+
+        let offset = UNIX_BASE - 10_000_000;
+        assert_eq!(s.offset(), Some(offset));
+        assert_eq!(CALLBACK_OFFSET.load(Ordering::SeqCst), offset);
+        assert_eq!(
+            s.unix_micros(Instant::from_secs(11)),
+            Some(UNIX_BASE + 1_000_000)
+        );
+        assert_eq!(
+            s.poll_at(&mut cx),
+            PollAt::Time(Instant::from_millis(74_200))
+        );
+    }
+
+    #[test]
+    fn test_retransmit() {
+        let mut cx = Context::mock();
+        let mut s = Socket::new(SERVER_IP);
+
+        let (_, request) = send_request(&mut s, &mut cx, Instant::from_secs(0));
+        assert_eq!(s.poll_at(&mut cx), PollAt::Time(Instant::from_secs(5)));
+
+        // Nothing is sent before the timeout.
+        cx.set_now(Instant::from_secs(1));
+        s.dispatch(&mut cx, |_, _| -> Result<(), Error> {
+            panic!("sent too early")
+        })
+        .unwrap();
+
+        let (udp_repr2, request2) = send_request(&mut s, &mut cx, Instant::from_secs(5));
+        assert_ne!(request.xmit_timestamp, request2.xmit_timestamp);
+
+        // A late response to the first request no longer matches.
+        let reply = response(&request, UNIX_BASE, UNIX_BASE);
+        recv_response(&mut s, &mut cx, Instant::from_secs(6), &udp_repr2, &reply);
+        assert_eq!(s.offset(), None);
+    }
+
+    #[test]
+    fn test_kiss_of_death() {
+        let mut cx = Context::mock();
+        let mut s = Socket::new(SERVER_IP);
+
+        let (udp_repr, request) = send_request(&mut s, &mut cx, Instant::from_secs(0));
+        let reply = NtpRepr {
+            stratum: 0,
+            ref_id: *b"RATE",
+            ..response(&request, UNIX_BASE, UNIX_BASE)
+        };
+        recv_response(&mut s, &mut cx, Instant::from_secs(1), &udp_repr, &reply);
+        assert_eq!(s.offset(), None);
+        assert_eq!(
+            s.poll_at(&mut cx),
+            PollAt::Time(Instant::from_secs(1) + DEFAULT_POLL_INTERVAL)
+        );
+    }
+
+    #[test]
+    fn test_accepts() {
+        let mut cx = Context::mock();
+        let mut s = Socket::new(SERVER_IP);
+        let ip_repr = IpRepr::new(SERVER_IP, MY_IP, IpProtocol::Udp, 0, 64);
+
+        let (udp_repr, _) = send_request(&mut s, &mut cx, Instant::from_secs(0));
+        let reply_udp = UdpRepr {
+            src_port: NTP_SERVER_PORT,
+            dst_port: udp_repr.src_port,
+        };
+        assert!(s.accepts(&ip_repr, &reply_udp));
+
+        let other_ip = IpRepr::new(
+            IpAddress::Ipv4(Ipv4Address([192, 168, 1, 2])),
+            MY_IP,
+            IpProtocol::Udp,
+            0,
+            64,
+        );
+        assert!(!s.accepts(&other_ip, &reply_udp));
+
+        let other_port = UdpRepr {
+            src_port: 124,
+            ..reply_udp
+        };
+        assert!(!s.accepts(&ip_repr, &other_port));
+    }
+}
@@ -6,10 +6,12 @@ use core::fmt::Display;
 #[cfg(feature = "async")]
 use core::task::Waker;
 use core::{cmp, fmt, mem};
+use managed::ManagedSlice;
 
+use crate::iface::{SocketHandle, SocketSet};
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
-use crate::socket::{Context, PollAt};
+use crate::socket::{BufferEvent, Context, IcmpError, PollAt};
 use crate::storage::{Assembler, RingBuffer};
 use crate::time::{Duration, Instant};
 use crate::wire::{
@@ -55,6 +57,73 @@ pub enum RecvError {
 /// A TCP socket ring buffer.
 pub type SocketBuffer<'a> = RingBuffer<'a, u8>;
 
+/// The reason a TCP connection was closed before both endpoints closed it gracefully.
+///
+/// See [`Socket::disconnect_reason`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DisconnectReason {
+    /// The remote endpoint refused the connection with a RST in response to our SYN.
+    Refused,
+    /// The remote endpoint reset the connection.
+    Reset,
+    /// The remote endpoint did not acknowledge a SYN, data or FIN within the
+    /// [timeout](Socket::set_timeout).
+    Timeout,
+    /// The remote endpoint did not respond to keep-alive packets within the
+    /// [timeout](Socket::set_timeout).
+    KeepAliveTimeout,
+    /// The remote endpoint did not close the connection within the
+    /// [FIN timeout](Socket::set_fin_timeout).
+    FinTimeout,
+    /// The connection was aborted locally with [`Socket::abort`].
+    Aborted,
+    /// The local address of the connection was removed from the interface. See
+    /// [`AddressLossPolicy::Abort`].
+    AddressLost,
+    /// A router or the remote host answered our SYN with an ICMP error that is
+    /// [hard](IcmpError::is_hard).
+    Icmp(IcmpError),
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DisconnectReason::Refused => write!(f, "connection refused"),
+            DisconnectReason::Reset => write!(f, "connection reset"),
+            DisconnectReason::Timeout => write!(f, "timed out"),
+            DisconnectReason::KeepAliveTimeout => write!(f, "keep-alive timed out"),
+            DisconnectReason::FinTimeout => write!(f, "FIN timed out"),
+            DisconnectReason::Aborted => write!(f, "aborted"),
+            DisconnectReason::AddressLost => write!(f, "local address removed"),
+            DisconnectReason::Icmp(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// What a connected socket does when its local address is removed from the interface,
+/// for example when a DHCP lease is renewed with a different address.
+///
+/// See [`Socket::set_address_loss_policy`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressLossPolicy {
+    /// Stop transmitting until the address is added back, at which point the connection
+    /// resumes. The connection is still aborted once the [timeout](Socket::set_timeout)
+    /// expires.
+    Keep,
+    /// Abort the connection right away, without sending a reset, with the
+    /// [`DisconnectReason::AddressLost`] reason.
+    Abort,
+}
+
+#[allow(clippy::derivable_impls)] // `#[default]` requires a newer Rust than our MSRV
+impl Default for AddressLossPolicy {
+    fn default() -> Self {
+        AddressLossPolicy::Keep
+    }
+}
+
 /// The state of a TCP socket, according to [RFC 793].
 ///
 /// [RFC 793]: https://tools.ietf.org/html/rfc793
@@ -200,6 +269,196 @@ impl RttEstimator {
     }
 }
 
+/// Metadata of a segment in the retransmission queue of a socket.
+///
+/// See [Socket::set_retransmit_queue].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SegmentMetadata {
+    seq_number: TcpSeqNumber,
+    len: usize,
+    sent_at: Instant,
+    retransmitted: bool,
+    sacked: bool,
+}
+
+impl SegmentMetadata {
+    /// Empty segment metadata, to initialize the storage of a retransmission queue with.
+    pub const EMPTY: SegmentMetadata = SegmentMetadata {
+        seq_number: TcpSeqNumber(0),
+        len: 0,
+        sent_at: Instant::ZERO,
+        retransmitted: false,
+        sacked: false,
+    };
+
+    fn end(&self) -> TcpSeqNumber {
+        self.seq_number + self.len
+    }
+}
+
+/// The segments sent but not yet acknowledged, oldest first.
+///
+/// Only the data segments sent while there was room in the queue are tracked; with
+/// no storage, the queue is disabled, and nothing is tracked.
+#[derive(Debug)]
+struct RetransmitQueue<'a> {
+    storage: ManagedSlice<'a, SegmentMetadata>,
+    len: usize,
+}
+
+impl<'a> RetransmitQueue<'a> {
+    fn new<S>(storage: S) -> RetransmitQueue<'a>
+    where
+        S: Into<ManagedSlice<'a, SegmentMetadata>>,
+    {
+        RetransmitQueue {
+            storage: storage.into(),
+            len: 0,
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.storage.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.len == self.storage.len()
+    }
+
+    fn segments(&self) -> &[SegmentMetadata] {
+        &self.storage[..self.len]
+    }
+
+    fn segments_mut(&mut self) -> &mut [SegmentMetadata] {
+        &mut self.storage[..self.len]
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Return the segment starting at `seq_number`, if it is tracked.
+    fn get(&self, seq_number: TcpSeqNumber) -> Option<&SegmentMetadata> {
+        self.segments()
+            .iter()
+            .find(|segment| segment.seq_number == seq_number)
+    }
+
+    /// Query whether sending data at `seq_number` would send new data that cannot be
+    /// tracked, because the queue is full.
+    fn is_exhausted(&self, seq_number: TcpSeqNumber) -> bool {
+        match self.segments().last() {
+            Some(last) => self.is_full() && seq_number >= last.end(),
+            None => false,
+        }
+    }
+
+    /// Record that `len` octets were sent at `seq_number` at `timestamp`.
+    fn on_send(&mut self, seq_number: TcpSeqNumber, len: usize, timestamp: Instant) {
+        let index = match self
+            .segments()
+            .iter()
+            .position(|segment| segment.seq_number == seq_number)
+        {
+            Some(index) => index,
+            None => {
+                let is_new = match self.segments().last() {
+                    Some(last) => seq_number >= last.end(),
+                    None => true,
+                };
+                if is_new && !self.is_full() {
+                    self.storage[self.len] = SegmentMetadata {
+                        seq_number,
+                        len,
+                        sent_at: timestamp,
+                        ..SegmentMetadata::EMPTY
+                    };
+                    self.len += 1;
+                }
+                return;
+            }
+        };
+
+        // A segment was retransmitted. If only part of it could be sent, e.g. because the
+        // remote window shrunk, keep track of the rest separately if possible.
+        let segment = self.storage[index];
+        if len < segment.len && !self.is_full() {
+            self.storage[index + 1..=self.len].rotate_right(1);
+            self.len += 1;
+            self.storage[index + 1] = SegmentMetadata {
+                seq_number: seq_number + len,
+                len: segment.len - len,
+                ..segment
+            };
+            self.storage[index].len = len;
+        }
+        let segment = &mut self.storage[index];
+        segment.sent_at = timestamp;
+        segment.retransmitted = true;
+    }
+
+    /// Forget the segments acknowledged by `ack_number`, and return the round-trip time
+    /// of the most recent one that was not retransmitted, if any.
+    fn on_ack(&mut self, ack_number: TcpSeqNumber, timestamp: Instant) -> Option<Duration> {
+        let mut rtt = None;
+        let acked = self
+            .segments()
+            .iter()
+            .take_while(|segment| segment.end() <= ack_number)
+            .count();
+        for segment in &self.segments()[..acked] {
+            if !segment.retransmitted && !segment.sacked {
+                rtt = Some(timestamp - segment.sent_at);
+            }
+        }
+        self.storage[..self.len].rotate_left(acked);
+        self.len -= acked;
+
+        if let Some(segment) = self.segments_mut().first_mut() {
+            if segment.seq_number < ack_number {
+                segment.len -= ack_number - segment.seq_number;
+                segment.seq_number = ack_number;
+            }
+        }
+        rtt
+    }
+
+    /// Mark the segments entirely covered by the selective acknowledgement `ranges` as
+    /// received by the remote.
+    fn on_sack(&mut self, ranges: &[Option<(u32, u32)>]) {
+        for (left, right) in ranges.iter().flatten() {
+            let left = TcpSeqNumber(*left as i32);
+            let right = TcpSeqNumber(*right as i32);
+            for segment in self.segments_mut() {
+                if segment.seq_number >= left && segment.end() <= right {
+                    segment.sacked = true;
+                }
+            }
+        }
+    }
+
+    /// Forget all selective acknowledgements, since the remote may discard the data it
+    /// selectively acknowledged, per RFC 2018 § 8.
+    fn clear_sacked(&mut self) {
+        for segment in self.segments_mut() {
+            segment.sacked = false;
+        }
+    }
+
+    /// Return the first sequence number at or after `seq_number` that was not selectively
+    /// acknowledged.
+    fn skip_sacked(&self, mut seq_number: TcpSeqNumber) -> TcpSeqNumber {
+        while let Some(segment) = self.get(seq_number) {
+            if !segment.sacked {
+                break;
+            }
+            seq_number = segment.end();
+        }
+        seq_number
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 enum Timer {
@@ -349,6 +608,90 @@ impl Display for Tuple {
     }
 }
 
+/// Maximum segment sizes that can be encoded in a SYN cookie.
+const SYN_COOKIE_MSS: [u16; 8] = [216, 536, 1200, 1360, 1400, 1440, 1460, 8960];
+
+/// Period after which the counter hashed into SYN cookies is incremented. A cookie is valid
+/// for one to two periods.
+const SYN_COOKIE_PERIOD: u64 = 64;
+
+/// SipHash-2-4 of `data` keyed with `key`.
+fn siphash(key: &[u64; 2], data: &[u8]) -> u64 {
+    let mut v = [
+        key[0] ^ 0x736f6d6570736575,
+        key[1] ^ 0x646f72616e646f6d,
+        key[0] ^ 0x6c7967656e657261,
+        key[1] ^ 0x7465646279746573,
+    ];
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    let mut compress = |m: u64| {
+        v[3] ^= m;
+        round(&mut v);
+        round(&mut v);
+        v[0] ^= m;
+    };
+
+    let chunks = data.chunks_exact(8);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(chunk);
+        compress(u64::from_le_bytes(bytes));
+    }
+    let mut last = (data.len() as u64) << 56;
+    for (i, &byte) in remainder.iter().enumerate() {
+        last |= (byte as u64) << (8 * i);
+    }
+    compress(last);
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Compute the SYN cookie for the connection attempt described by an incoming SYN
+/// (or the ACK completing it, with `remote_isn` adjusted accordingly).
+///
+/// The low 8 bits of the cookie carry `data`, the encoded options of the SYN; the high 24 bits
+/// authenticate them along with the 4-tuple, the remote initial sequence number and `counter`.
+fn syn_cookie(
+    secret: &[u64; 2],
+    ip_repr: &IpRepr,
+    repr: &TcpRepr,
+    remote_isn: TcpSeqNumber,
+    counter: u32,
+    data: u8,
+) -> TcpSeqNumber {
+    let mut message = [0; 45];
+    let src_addr = ip_repr.src_addr();
+    let dst_addr = ip_repr.dst_addr();
+    message[..src_addr.as_bytes().len()].copy_from_slice(src_addr.as_bytes());
+    message[16..16 + dst_addr.as_bytes().len()].copy_from_slice(dst_addr.as_bytes());
+    message[32..34].copy_from_slice(&repr.src_port.to_le_bytes());
+    message[34..36].copy_from_slice(&repr.dst_port.to_le_bytes());
+    message[36..40].copy_from_slice(&remote_isn.0.to_le_bytes());
+    message[40..44].copy_from_slice(&counter.to_le_bytes());
+    message[44] = data;
+
+    let hash = siphash(secret, &message) as u32;
+    TcpSeqNumber(((hash & !0xff) | data as u32) as i32)
+}
+
 /// A Transmission Control Protocol socket.
 ///
 /// A TCP socket may passively listen for connections or actively connect to another endpoint.
@@ -360,6 +703,7 @@ pub struct Socket<'a> {
     state: State,
     timer: Timer,
     rtte: RttEstimator,
+    retransmit_queue: RetransmitQueue<'a>,
     assembler: Assembler,
     rx_buffer: SocketBuffer<'a>,
     rx_fin_received: bool,
@@ -368,8 +712,26 @@ pub struct Socket<'a> {
     timeout: Option<Duration>,
     /// Interval at which keep-alive packets will be sent.
     keep_alive: Option<Duration>,
+    /// Maximum time spent in the FIN-WAIT-2 and LAST-ACK states before the connection is reset.
+    fin_timeout: Option<Duration>,
+    /// The time the socket was first dispatched in its current FIN-WAIT-2 or LAST-ACK state.
+    fin_wait_since: Option<Instant>,
+    /// Why the last connection was closed abnormally, if it was.
+    disconnect_reason: Option<DisconnectReason>,
+    /// The last ICMP error reported for the connection that did not close it.
+    icmp_error: Option<IcmpError>,
+    /// What to do when the local address is removed from the interface.
+    address_loss_policy: AddressLossPolicy,
+    /// Whether to answer SYNs in the LISTEN state with SYN cookies.
+    syn_cookies: bool,
+    /// Key used to authenticate SYN cookies, generated on the first SYN answered with one.
+    syn_cookie_secret: Option<[u64; 2]>,
+    /// Predicate deciding whether a remote endpoint may connect to the listening socket.
+    accept_filter: Option<fn(IpEndpoint) -> bool>,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// The index of the interface the socket is bound to, if any.
+    bound_interface: Option<u32>,
     /// Address passed to listen(). Listen address is set when listen() is called and
     /// used every time the socket is reset back to the LISTEN state.
     listen_endpoint: IpListenEndpoint,
@@ -395,6 +757,8 @@ pub struct Socket<'a> {
     /// The remote window size, relative to local_seq_no
     /// I.e. we're allowed to send octets until local_seq_no+remote_win_len
     remote_win_len: usize,
+    /// The largest window the remote has advertised.
+    remote_win_max: usize,
     /// The receive window scaling factor for remotes which support RFC 1323, None if unsupported.
     remote_win_scale: Option<u8>,
     /// Whether or not the remote supports selective ACK as described in RFC 2018.
@@ -423,6 +787,12 @@ pub struct Socket<'a> {
     /// Nagle's Algorithm enabled.
     nagle: bool,
 
+    /// Amount of data in the receive buffer above which the socket is readable.
+    recv_high_watermark: Option<usize>,
+    /// Amount of data in the transmit buffer below which the socket is writable.
+    send_low_watermark: Option<usize>,
+    buffer_callback: Option<fn(BufferEvent)>,
+
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -454,13 +824,23 @@ impl<'a> Socket<'a> {
             state: State::Closed,
             timer: Timer::new(),
             rtte: RttEstimator::default(),
+            retransmit_queue: RetransmitQueue::new(ManagedSlice::Borrowed(&mut [])),
             assembler: Assembler::new(rx_buffer.capacity()),
             tx_buffer,
             rx_buffer,
             rx_fin_received: false,
             timeout: None,
             keep_alive: None,
+            fin_timeout: None,
+            fin_wait_since: None,
+            disconnect_reason: None,
+            icmp_error: None,
+            address_loss_policy: AddressLossPolicy::default(),
+            syn_cookies: false,
+            syn_cookie_secret: None,
+            accept_filter: None,
             hop_limit: None,
+            bound_interface: None,
             listen_endpoint: IpListenEndpoint::default(),
             tuple: None,
             local_seq_no: TcpSeqNumber::default(),
@@ -469,6 +849,7 @@ impl<'a> Socket<'a> {
             remote_last_ack: None,
             remote_last_win: 0,
             remote_win_len: 0,
+            remote_win_max: 0,
             remote_win_shift: rx_cap_log2.saturating_sub(16) as u8,
             remote_win_scale: None,
             remote_has_sack: false,
@@ -481,6 +862,9 @@ impl<'a> Socket<'a> {
             ack_delay_timer: AckDelayTimer::Idle,
             challenge_ack_timer: Instant::from_secs(0),
             nagle: true,
+            recv_high_watermark: None,
+            send_low_watermark: None,
+            buffer_callback: None,
 
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
@@ -531,6 +915,27 @@ impl<'a> Socket<'a> {
         self.timeout
     }
 
+    /// Return the FIN timeout duration.
+    ///
+    /// See also the [set_fin_timeout](#method.set_fin_timeout) method.
+    pub fn fin_timeout(&self) -> Option<Duration> {
+        self.fin_timeout
+    }
+
+    /// Return what the socket does when its local address is removed from the interface.
+    ///
+    /// See also the [set_address_loss_policy](#method.set_address_loss_policy) method.
+    pub fn address_loss_policy(&self) -> AddressLossPolicy {
+        self.address_loss_policy
+    }
+
+    /// Return whether SYN cookies are enabled.
+    ///
+    /// See also the [set_syn_cookies](#method.set_syn_cookies) method.
+    pub fn syn_cookies(&self) -> bool {
+        self.syn_cookies
+    }
+
     /// Return the ACK delay duration.
     ///
     /// See also the [set_ack_delay](#method.set_ack_delay) method.
@@ -572,6 +977,51 @@ impl<'a> Socket<'a> {
         self.timeout = duration
     }
 
+    /// Set the FIN timeout duration.
+    ///
+    /// A socket with a FIN timeout duration set will reset the connection if it stays in
+    /// the `FIN-WAIT-2` or `LAST-ACK` state for longer than the specified duration. This
+    /// frees the socket when the remote endpoint never closes its half of the connection,
+    /// or never acknowledges the closing of ours.
+    ///
+    /// By default, there is no FIN timeout.
+    pub fn set_fin_timeout(&mut self, duration: Option<Duration>) {
+        self.fin_timeout = duration
+    }
+
+    /// Set what the socket does when the local address of its connection is removed from
+    /// the interface. See [AddressLossPolicy].
+    ///
+    /// By default, the connection is kept.
+    pub fn set_address_loss_policy(&mut self, policy: AddressLossPolicy) {
+        self.address_loss_policy = policy
+    }
+
+    /// Enable or disable SYN cookies.
+    ///
+    /// When enabled, a listening socket answers every SYN with a SYN|ACK whose sequence number
+    /// encodes the connection parameters, and stays in the `LISTEN` state. It only leaves it
+    /// once the remote endpoint acknowledges that SYN|ACK with a valid cookie, so that a flood
+    /// of SYNs from spoofed addresses cannot keep it in the `SYN-RECEIVED` state.
+    ///
+    /// The SYN|ACK is not retransmitted, any data in the SYN is discarded, and the remote MSS
+    /// is rounded down to one of a few common values.
+    ///
+    /// By default, SYN cookies are disabled.
+    pub fn set_syn_cookies(&mut self, enabled: bool) {
+        self.syn_cookies = enabled
+    }
+
+    /// Set a function deciding whether a remote endpoint may connect to the listening socket.
+    ///
+    /// The function is called with the source endpoint of every segment arriving in the
+    /// `LISTEN` state. If it returns `false`, the segment is not accepted by this socket, and
+    /// unless another socket accepts it, the connection attempt is reset right away, without
+    /// this socket leaving the `LISTEN` state.
+    pub fn set_accept_filter(&mut self, filter: Option<fn(IpEndpoint) -> bool>) {
+        self.accept_filter = filter;
+    }
+
     /// Set the ACK delay duration.
     ///
     /// By default, the ACK delay is set to 10ms.
@@ -595,6 +1045,104 @@ impl<'a> Socket<'a> {
         self.nagle = enabled
     }
 
+    /// Return the receive buffer high watermark, in octets.
+    ///
+    /// See also the [set_recv_high_watermark](#method.set_recv_high_watermark) method.
+    pub fn recv_high_watermark(&self) -> usize {
+        self.recv_high_watermark
+            .unwrap_or(1)
+            .min(self.rx_buffer.capacity())
+    }
+
+    /// Set the receive buffer high watermark, in octets, or reset it with `None`.
+    ///
+    /// Arriving data only wakes the receive waker and reports [BufferEvent::Readable] to the
+    /// buffer callback once the receive buffer holds at least this many octets, so that
+    /// an application can wait for a useful amount of data instead of being woken for every
+    /// segment. Closing the connection still wakes the receive waker.
+    ///
+    /// A watermark larger than the receive buffer is treated as its capacity.
+    /// By default, the high watermark is one octet.
+    pub fn set_recv_high_watermark(&mut self, watermark: Option<usize>) {
+        self.recv_high_watermark = watermark
+    }
+
+    /// Return the transmit buffer low watermark, in octets.
+    ///
+    /// See also the [set_send_low_watermark](#method.set_send_low_watermark) method.
+    pub fn send_low_watermark(&self) -> usize {
+        self.send_low_watermark
+            .unwrap_or(usize::MAX)
+            .min(self.tx_buffer.capacity())
+    }
+
+    /// Set the transmit buffer low watermark, in octets, or reset it with `None`.
+    ///
+    /// Acknowledged data only wakes the send waker and reports [BufferEvent::Writable] to the
+    /// buffer callback once the transmit buffer holds fewer than this many octets, so that
+    /// an application producing data can pause while the remote endpoint is slow to consume
+    /// it, and resume once enough room is available. Closing the connection still wakes
+    /// the send waker.
+    ///
+    /// A watermark larger than the transmit buffer is treated as its capacity.
+    /// By default, the low watermark is the capacity of the transmit buffer.
+    pub fn set_send_low_watermark(&mut self, watermark: Option<usize>) {
+        self.send_low_watermark = watermark
+    }
+
+    /// Set a function to be called when the fill level of a buffer crosses its watermark.
+    ///
+    /// The function is called with [BufferEvent::Readable] whenever data arrives while the
+    /// receive buffer is at or above its high watermark, and with [BufferEvent::Writable]
+    /// whenever data is acknowledged while the transmit buffer is below its low watermark.
+    /// It is called at the same points the receive and send wakers are woken.
+    pub fn set_buffer_callback(&mut self, callback: Option<fn(BufferEvent)>) {
+        self.buffer_callback = callback;
+    }
+
+    /// Return the index of the interface the socket is bound to.
+    ///
+    /// See also the [set_bound_interface](#method.set_bound_interface) method.
+    pub fn bound_interface(&self) -> Option<u32> {
+        self.bound_interface
+    }
+
+    /// Bind the socket to the interface with the given index, the equivalent of
+    /// `SO_BINDTODEVICE`, or unbind it with `None`.
+    ///
+    /// A socket bound to an interface only receives and sends segments when polled by
+    /// that interface. See [InterfaceBuilder::index].
+    ///
+    /// [InterfaceBuilder::index]: crate::iface::InterfaceBuilder::index
+    pub fn set_bound_interface(&mut self, index: Option<u32>) {
+        self.bound_interface = index
+    }
+
+    fn is_serviced_by(&self, cx: &Context) -> bool {
+        !matches!(self.bound_interface, Some(index) if index != cx.index())
+    }
+
+    /// Query whether the local address of the connection was removed from the interface.
+    fn local_addr_lost(&self, cx: &Context) -> bool {
+        matches!(self.tuple, Some(tuple) if !cx.is_local_addr(tuple.local.addr))
+    }
+
+    /// Set the storage of the retransmission queue.
+    ///
+    /// The retransmission queue keeps track of the boundaries and transmission time of every
+    /// segment sent but not yet acknowledged. With it, segments are retransmitted with their
+    /// original boundaries, segments selectively acknowledged by the remote are not
+    /// retransmitted, and the round-trip time is sampled for every segment instead of once
+    /// per window. At most as many segments as there is storage for are in flight at a time.
+    ///
+    /// By default, there is no retransmission queue.
+    pub fn set_retransmit_queue<S>(&mut self, storage: S)
+    where
+        S: Into<ManagedSlice<'a, SegmentMetadata>>,
+    {
+        self.retransmit_queue = RetransmitQueue::new(storage);
+    }
+
     /// Return the keep-alive interval.
     ///
     /// See also the [set_keep_alive](#method.set_keep_alive) method.
@@ -684,13 +1232,18 @@ impl<'a> Socket<'a> {
         self.local_seq_no = TcpSeqNumber::default();
         self.remote_seq_no = TcpSeqNumber::default();
         self.remote_last_seq = TcpSeqNumber::default();
+        self.retransmit_queue.clear();
         self.remote_last_ack = None;
         self.remote_last_win = 0;
         self.remote_win_len = 0;
+        self.remote_win_max = 0;
         self.remote_win_scale = None;
         self.remote_win_shift = rx_cap_log2.saturating_sub(16) as u8;
         self.remote_mss = DEFAULT_MSS;
         self.remote_last_ts = None;
+        self.fin_wait_since = None;
+        self.disconnect_reason = None;
+        self.icmp_error = None;
         self.ack_delay_timer = AckDelayTimer::Idle;
         self.challenge_ack_timer = Instant::from_secs(0);
 
@@ -789,6 +1342,7 @@ impl<'a> Socket<'a> {
             remote: remote_endpoint,
         });
         self.set_state(State::SynSent);
+        cx.stats_mut().tcp.active_opens += 1;
 
         let seq = Self::random_seq_no(cx);
         self.local_seq_no = seq;
@@ -842,9 +1396,30 @@ impl<'a> Socket<'a> {
     /// In terms of the TCP state machine, the socket may be in any state and is moved to
     /// the `CLOSED` state.
     pub fn abort(&mut self) {
+        if self.tuple.is_some() && self.state != State::Closed {
+            self.disconnect_reason = Some(DisconnectReason::Aborted);
+        }
         self.set_state(State::Closed);
     }
 
+    /// Return why the last connection was closed abnormally, if it was.
+    ///
+    /// The reason is kept after the socket reaches the `CLOSED` state, and cleared when it
+    /// is opened again with [listen](#method.listen) or [connect](#method.connect).
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason
+    }
+
+    /// Return the last ICMP error reported for the connection that did not close it.
+    ///
+    /// Only [hard](IcmpError::is_hard) errors reported while the connection is being
+    /// opened abort it; other errors are recorded here, since the condition may go away
+    /// before the connection times out. The error is cleared when the socket is opened
+    /// again with [listen](#method.listen) or [connect](#method.connect).
+    pub fn icmp_error(&self) -> Option<IcmpError> {
+        self.icmp_error
+    }
+
     /// Return whether the socket is passively listening for incoming connections.
     ///
     /// In terms of the TCP state machine, the socket must be in the `LISTEN` state.
@@ -970,6 +1545,52 @@ impl<'a> Socket<'a> {
         !self.rx_buffer.is_empty()
     }
 
+    /// Check whether the receive half of the connection is open, and the receive buffer
+    /// holds at least as many octets as its high watermark.
+    ///
+    /// See also the [set_recv_high_watermark](#method.set_recv_high_watermark) method.
+    #[inline]
+    pub fn is_readable(&self) -> bool {
+        self.can_recv() && self.rx_buffer.len() >= self.recv_high_watermark()
+    }
+
+    /// Check whether the transmit half of the connection is open, and the transmit buffer
+    /// holds fewer octets than its low watermark.
+    ///
+    /// See also the [set_send_low_watermark](#method.set_send_low_watermark) method.
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.can_send() && self.tx_buffer.len() < self.send_low_watermark()
+    }
+
+    /// Wake the receive waker and notify the buffer callback if the receive buffer
+    /// reached its high watermark.
+    fn notify_readable(&mut self) {
+        if self.rx_buffer.len() < self.recv_high_watermark() {
+            return;
+        }
+
+        #[cfg(feature = "async")]
+        self.rx_waker.wake();
+        if let Some(callback) = self.buffer_callback {
+            callback(BufferEvent::Readable)
+        }
+    }
+
+    /// Wake the send waker and notify the buffer callback if the transmit buffer
+    /// drained below its low watermark.
+    fn notify_writable(&mut self) {
+        if self.tx_buffer.len() >= self.send_low_watermark() {
+            return;
+        }
+
+        #[cfg(feature = "async")]
+        self.tx_waker.wake();
+        if let Some(callback) = self.buffer_callback {
+            callback(BufferEvent::Writable)
+        }
+    }
+
     fn send_impl<'b, F, R>(&'b mut self, f: F) -> Result<R, SendError>
     where
         F: FnOnce(&'b mut SocketBuffer<'a>) -> (usize, R),
@@ -1132,6 +1753,7 @@ impl<'a> Socket<'a> {
     fn set_state(&mut self, state: State) {
         if self.state != state {
             tcp_trace!("state={}=>{}", self.state, state);
+            self.fin_wait_since = None;
         }
 
         self.state = state;
@@ -1146,6 +1768,49 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Update the management counters for a connection that is about to be aborted,
+    /// either by a RST or by a timeout.
+    fn count_abort(&self, cx: &mut Context) {
+        match self.state {
+            State::SynSent | State::SynReceived => cx.stats_mut().tcp.attempt_fails += 1,
+            State::Established | State::CloseWait => cx.stats_mut().tcp.estab_resets += 1,
+            _ => (),
+        }
+    }
+
+    /// Query whether an ICMP error quoting a segment with the given endpoints and sequence
+    /// number is about this connection.
+    pub(crate) fn accepts_icmp_error(
+        &self,
+        cx: &mut Context,
+        local: IpEndpoint,
+        remote: IpEndpoint,
+        seq_no: TcpSeqNumber,
+    ) -> bool {
+        if !self.is_serviced_by(cx) {
+            return false;
+        }
+        match self.tuple {
+            Some(tuple) if tuple.local == local && tuple.remote == remote => (),
+            _ => return false,
+        }
+        // Only trust errors quoting a segment that is in flight, as per RFC 5927.
+        seq_no >= self.local_seq_no && seq_no < self.remote_last_seq
+    }
+
+    pub(crate) fn process_icmp_error(&mut self, cx: &mut Context, error: IcmpError) {
+        if self.state == State::SynSent && error.is_hard() {
+            net_debug!("{}: ICMP error: {}, aborting", self.tuple.unwrap(), error);
+            self.count_abort(cx);
+            self.disconnect_reason = Some(DisconnectReason::Icmp(error));
+            self.set_state(State::Closed);
+            self.tuple = None;
+        } else {
+            net_debug!("{}: ICMP error: {}", self.tuple.unwrap(), error);
+            self.icmp_error = Some(error);
+        }
+    }
+
     pub(crate) fn reply(ip_repr: &IpRepr, repr: &TcpRepr) -> (IpRepr, TcpRepr<'static>) {
         let reply_repr = TcpRepr {
             src_port: repr.dst_port,
@@ -1255,19 +1920,23 @@ impl<'a> Socket<'a> {
 
         // Rate-limit to 1 per second max.
         self.challenge_ack_timer = cx.now() + Duration::from_secs(1);
+        cx.stats_mut().tcp.challenge_acks += 1;
 
         return Some(self.ack_reply(ip_repr, repr));
     }
 
-    pub(crate) fn accepts(&self, _cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
-        if self.state == State::Closed {
+    pub(crate) fn accepts(&self, cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
+        if self.state == State::Closed || !self.is_serviced_by(cx) {
             return false;
         }
 
         // If we're still listening for SYNs and the packet has an ACK, it cannot
         // be destined to this socket, but another one may well listen on the same
-        // local endpoint.
-        if self.state == State::Listen && repr.ack_number.is_some() {
+        // local endpoint. The exception is the ACK of a SYN cookie.
+        if self.state == State::Listen
+            && repr.ack_number.is_some()
+            && self.check_syn_cookie(cx.now(), ip_repr, repr).is_none()
+        {
             return false;
         }
 
@@ -1283,8 +1952,106 @@ impl<'a> Socket<'a> {
                 Some(addr) => ip_repr.dst_addr() == addr,
                 None => true,
             };
-            addr_ok && repr.dst_port != 0 && repr.dst_port == self.listen_endpoint.port
+            if !addr_ok || repr.dst_port == 0 || repr.dst_port != self.listen_endpoint.port {
+                return false;
+            }
+            match self.accept_filter {
+                Some(filter) => filter(IpEndpoint::new(ip_repr.src_addr(), repr.src_port)),
+                None => true,
+            }
+        }
+    }
+
+    /// Return the encoded SYN options if the segment acknowledges a SYN|ACK sent
+    /// with a SYN cookie that has not yet expired.
+    fn check_syn_cookie(&self, timestamp: Instant, ip_repr: &IpRepr, repr: &TcpRepr) -> Option<u8> {
+        if !self.syn_cookies || !matches!(repr.control, TcpControl::None | TcpControl::Psh) {
+            return None;
+        }
+        let secret = self.syn_cookie_secret.as_ref()?;
+        let cookie = repr.ack_number? - 1;
+        let data = cookie.0 as u8;
+        let counter = (timestamp.total_millis() as u64 / 1000 / SYN_COOKIE_PERIOD) as u32;
+        let remote_isn = repr.seq_number - 1;
+        let valid = [counter, counter.wrapping_sub(1)]
+            .iter()
+            .any(|&counter| syn_cookie(secret, ip_repr, repr, remote_isn, counter, data) == cookie);
+        if valid {
+            Some(data)
+        } else {
+            None
+        }
+    }
+
+    /// Answer a SYN in the LISTEN state with a SYN|ACK carrying a SYN cookie.
+    fn syn_cookie_reply(
+        &mut self,
+        cx: &mut Context,
+        ip_repr: &IpRepr,
+        repr: &TcpRepr,
+    ) -> (IpRepr, TcpRepr<'static>) {
+        let secret = match self.syn_cookie_secret {
+            Some(secret) => secret,
+            None => {
+                let mut word = || (cx.rand().rand_u32() as u64) << 32 | cx.rand().rand_u32() as u64;
+                let secret = [word(), word()];
+                self.syn_cookie_secret = Some(secret);
+                secret
+            }
+        };
+
+        let remote_mss = repr.max_seg_size.unwrap_or(DEFAULT_MSS as u16);
+        let mss_index = SYN_COOKIE_MSS
+            .iter()
+            .rposition(|&mss| mss <= remote_mss)
+            .unwrap_or(0);
+        let win_scale = repr.window_scale.map_or(15, |scale| scale.min(14));
+        let data = mss_index as u8 | win_scale << 3 | (repr.sack_permitted as u8) << 7;
+        let counter = (cx.now().total_millis() as u64 / 1000 / SYN_COOKIE_PERIOD) as u32;
+
+        let (mut ip_reply_repr, mut reply_repr) = Self::reply(ip_repr, repr);
+        reply_repr.control = TcpControl::Syn;
+        reply_repr.seq_number = syn_cookie(&secret, ip_repr, repr, repr.seq_number, counter, data);
+        reply_repr.ack_number = Some(repr.seq_number + 1);
+        // window len must NOT be scaled in SYNs.
+        reply_repr.window_len = self.rx_buffer.window().min((1 << 16) - 1) as u16;
+        reply_repr.window_scale = repr.window_scale.map(|_| self.remote_win_shift);
+        reply_repr.sack_permitted = repr.sack_permitted;
+        let max_segment_size =
+            cx.path_mtu(&ip_reply_repr.dst_addr()) - ip_reply_repr.header_len() - TCP_HEADER_LEN;
+        reply_repr.max_seg_size = Some(max_segment_size as u16);
+        ip_reply_repr.set_payload_len(reply_repr.buffer_len());
+        (ip_reply_repr, reply_repr)
+    }
+
+    /// Restore the SYN-RECEIVED state from the SYN cookie acknowledged by the segment.
+    fn open_from_syn_cookie(&mut self, cx: &mut Context, ip_repr: &IpRepr, repr: &TcpRepr) -> bool {
+        let data = match self.check_syn_cookie(cx.now(), ip_repr, repr) {
+            Some(data) => data,
+            None => return false,
+        };
+        tcp_trace!("received ACK of SYN cookie");
+
+        self.tuple = Some(Tuple {
+            local: IpEndpoint::new(ip_repr.dst_addr(), repr.dst_port),
+            remote: IpEndpoint::new(ip_repr.src_addr(), repr.src_port),
+        });
+        self.local_seq_no = repr.ack_number.unwrap() - 1;
+        self.remote_seq_no = repr.seq_number;
+        self.remote_last_seq = self.local_seq_no + 1;
+        self.remote_mss = SYN_COOKIE_MSS[(data & 0x7) as usize] as usize;
+        self.remote_win_scale = match (data >> 3) & 0xf {
+            15 => None,
+            scale => Some(scale),
+        };
+        // Remote doesn't support window scaling, don't do it.
+        if self.remote_win_scale.is_none() {
+            self.remote_win_shift = 0;
         }
+        self.remote_has_sack = data & 0x80 != 0;
+        self.set_state(State::SynReceived);
+        cx.stats_mut().tcp.passive_opens += 1;
+        true
     }
 
     pub(crate) fn process(
@@ -1295,6 +2062,31 @@ impl<'a> Socket<'a> {
     ) -> Option<(IpRepr, TcpRepr<'static>)> {
         debug_assert!(self.accepts(cx, ip_repr, repr));
 
+        // An ACK in the LISTEN state completes a handshake started with a SYN cookie.
+        if self.state == State::Listen
+            && repr.ack_number.is_some()
+            && !self.open_from_syn_cookie(cx, ip_repr, repr)
+        {
+            return None;
+        }
+
+        // In a simultaneous open, the remote acknowledges our SYN with a SYN|ACK repeating
+        // the SYN we already received in the SYN-SENT state (RFC 793 § 3.4, figure 8).
+        // Process it as a plain ACK.
+        let syn_repeated = self.state == State::SynReceived
+            && repr.control == TcpControl::Syn
+            && repr.ack_number.is_some()
+            && repr.seq_number + 1 == self.remote_seq_no;
+        let repr = &if syn_repeated {
+            TcpRepr {
+                control: TcpControl::None,
+                seq_number: self.remote_seq_no,
+                ..*repr
+            }
+        } else {
+            *repr
+        };
+
         // Consider how much the sequence number space differs from the transmit buffer space.
         let (sent_syn, sent_fin) = match self.state {
             // In SYN-SENT or SYN-RECEIVED, we've just sent a SYN.
@@ -1307,6 +2099,19 @@ impl<'a> Socket<'a> {
         };
         let control_len = (sent_syn as usize) + (sent_fin as usize);
 
+        // A SYN in a synchronized state may have been blindly injected, regardless of its
+        // sequence number. Only acknowledge it; a legitimate peer that restarted will respond
+        // with an RST (RFC 5961 § 4.2).
+        if repr.control == TcpControl::Syn
+            && !matches!(
+                self.state,
+                State::Listen | State::SynSent | State::SynReceived
+            )
+        {
+            net_debug!("SYN in a synchronized state, will send challenge ACK");
+            return self.challenge_ack_reply(cx, ip_repr, repr);
+        }
+
         // Reject unacceptable acknowledgements.
         match (self.state, repr.control, repr.ack_number) {
             // An RST received in response to initial SYN is acceptable if it acknowledges
@@ -1323,6 +2128,8 @@ impl<'a> Socket<'a> {
             }
             // Any other RST need only have a valid sequence number.
             (_, TcpControl::Rst, _) => (),
+            // A SYN without an ACK in the SYN-SENT state is a simultaneous open.
+            (State::SynSent, TcpControl::Syn, None) => (),
             // The initial SYN cannot contain an acknowledgement.
             (State::Listen, _, None) => (),
             // This case is handled in `accepts()`.
@@ -1382,6 +2189,18 @@ impl<'a> Socket<'a> {
                     ack_min += 1;
                 }
 
+                // Acknowledgements older than the largest window ever advertised by the remote
+                // cannot be legitimate (RFC 5961 § 5.2).
+                if ack_number + self.remote_win_max < ack_min {
+                    net_debug!(
+                        "unacceptable old ACK ({} not in {}...{})",
+                        ack_number,
+                        ack_min,
+                        ack_max
+                    );
+                    return self.challenge_ack_reply(cx, ip_repr, repr);
+                }
+
                 if ack_number < ack_min {
                     net_debug!(
                         "duplicate ACK ({} not in {}...{})",
@@ -1456,6 +2275,21 @@ impl<'a> Socket<'a> {
             }
         }
 
+        // An RST in the receive window, but not exactly at the next expected sequence number,
+        // may have been blindly injected. Only acknowledge it; a legitimate peer will respond
+        // with an RST at the right sequence number (RFC 5961 § 3.2).
+        if repr.control == TcpControl::Rst
+            && !matches!(self.state, State::Listen | State::SynSent)
+            && segment_start != window_start
+        {
+            net_debug!(
+                "RST not at the start of the receive window ({} != {}), will send challenge ACK",
+                segment_start,
+                window_start
+            );
+            return self.challenge_ack_reply(cx, ip_repr, repr);
+        }
+
         // Compute the amount of acknowledged octets, removing the SYN and FIN bits
         // from the sequence space.
         let mut ack_len = 0;
@@ -1479,7 +2313,16 @@ impl<'a> Socket<'a> {
                     }
                 }
 
-                self.rtte.on_ack(cx.now(), ack_number);
+                if self.retransmit_queue.is_enabled() {
+                    if let Some(rtt) = self.retransmit_queue.on_ack(ack_number, cx.now()) {
+                        self.rtte.sample(rtt.total_millis() as u32);
+                    }
+                    if self.remote_has_sack {
+                        self.retransmit_queue.on_sack(&repr.sack_ranges);
+                    }
+                } else {
+                    self.rtte.on_ack(cx.now(), ack_number);
+                }
             }
         }
 
@@ -1498,9 +2341,11 @@ impl<'a> Socket<'a> {
             // RSTs are not accepted in the LISTEN state.
             (State::Listen, TcpControl::Rst) => return None,
 
-            // RSTs in SYN-RECEIVED flip the socket back to the LISTEN state.
-            (State::SynReceived, TcpControl::Rst) => {
+            // RSTs in SYN-RECEIVED flip the socket back to the LISTEN state, if it was
+            // listening. Otherwise, the connection was refused after a simultaneous open.
+            (State::SynReceived, TcpControl::Rst) if self.listen_endpoint.port != 0 => {
                 tcp_trace!("received RST");
+                self.count_abort(cx);
                 self.tuple = None;
                 self.set_state(State::Listen);
                 return None;
@@ -1509,6 +2354,11 @@ impl<'a> Socket<'a> {
             // RSTs in any other state close the socket.
             (_, TcpControl::Rst) => {
                 tcp_trace!("received RST");
+                self.count_abort(cx);
+                self.disconnect_reason = Some(match self.state {
+                    State::SynSent | State::SynReceived => DisconnectReason::Refused,
+                    _ => DisconnectReason::Reset,
+                });
                 self.set_state(State::Closed);
                 self.tuple = None;
                 return None;
@@ -1522,7 +2372,14 @@ impl<'a> Socket<'a> {
                         tcp_trace!("received SYNACK with zero MSS, ignoring");
                         return None;
                     }
-                    self.remote_mss = max_seg_size as usize
+                    if !self.syn_cookies {
+                        self.remote_mss = max_seg_size as usize
+                    }
+                }
+
+                if self.syn_cookies {
+                    tcp_trace!("sending SYN cookie");
+                    return Some(self.syn_cookie_reply(cx, ip_repr, repr));
                 }
 
                 self.tuple = Some(Tuple {
@@ -1540,6 +2397,7 @@ impl<'a> Socket<'a> {
                 }
                 self.set_state(State::SynReceived);
                 self.timer.set_for_idle(cx.now(), self.keep_alive);
+                cx.stats_mut().tcp.passive_opens += 1;
             }
 
             // ACK packets in the SYN-RECEIVED state change it to ESTABLISHED.
@@ -1558,6 +2416,31 @@ impl<'a> Socket<'a> {
                 self.timer.set_for_idle(cx.now(), self.keep_alive);
             }
 
+            // SYN packets in the SYN-SENT state change it to SYN-RECEIVED, and we
+            // acknowledge the remote SYN while repeating ours.
+            (State::SynSent, TcpControl::Syn) if repr.ack_number.is_none() => {
+                tcp_trace!("received SYN, simultaneous open");
+                if let Some(max_seg_size) = repr.max_seg_size {
+                    if max_seg_size == 0 {
+                        tcp_trace!("received SYN with zero MSS, ignoring");
+                        return None;
+                    }
+                    self.remote_mss = max_seg_size as usize;
+                }
+
+                self.remote_seq_no = repr.seq_number + 1;
+                self.remote_last_seq = self.local_seq_no;
+                self.remote_has_sack = repr.sack_permitted;
+                self.remote_win_scale = repr.window_scale;
+                // Remote doesn't support window scaling, don't do it.
+                if self.remote_win_scale.is_none() {
+                    self.remote_win_shift = 0;
+                }
+
+                self.set_state(State::SynReceived);
+                self.timer.set_for_idle(cx.now(), self.keep_alive);
+            }
+
             // SYN|ACK packets in the SYN-SENT state change it to ESTABLISHED.
             (State::SynSent, TcpControl::Syn) => {
                 tcp_trace!("received SYN|ACK");
@@ -1583,11 +2466,16 @@ impl<'a> Socket<'a> {
             }
 
             // ACK packets in ESTABLISHED state reset the retransmit timer,
-            // except for duplicate ACK packets which preserve it.
+            // except for duplicate ACK packets which preserve it. If the ACK leaves
+            // some data unacknowledged, the timer is restarted, per RFC 6298 § 5.3.
             (State::Established, TcpControl::None) => {
                 if !self.timer.is_retransmit() || ack_len != 0 {
                     self.timer.set_for_idle(cx.now(), self.keep_alive);
                 }
+                if ack_len != 0 && repr.ack_number < Some(self.remote_last_seq) {
+                    self.timer
+                        .set_for_retransmit(cx.now(), self.rtte.retransmission_timeout());
+                }
             }
 
             // FIN packets in ESTABLISHED state indicate the remote side has closed.
@@ -1673,9 +2561,11 @@ impl<'a> Socket<'a> {
         // exception of SYN segments, is left-shifted by Snd.Wind.Scale bits before updating SND.WND.
         let scale = match repr.control {
             TcpControl::Syn => 0,
+            _ if syn_repeated => 0,
             _ => self.remote_win_scale.unwrap_or(0),
         };
         self.remote_win_len = (repr.window_len as usize) << (scale as usize);
+        self.remote_win_max = cmp::max(self.remote_win_max, self.remote_win_len);
 
         if ack_len > 0 {
             // Dequeue acknowledged octets.
@@ -1688,8 +2578,7 @@ impl<'a> Socket<'a> {
             self.tx_buffer.dequeue_allocated(ack_len);
 
             // There's new room available in tx_buffer, wake the waiting task if any.
-            #[cfg(feature = "async")]
-            self.tx_waker.wake();
+            self.notify_writable();
         }
 
         if let Some(ack_number) = repr.ack_number {
@@ -1782,7 +2671,8 @@ impl<'a> Socket<'a> {
             }
         }
 
-        if let Some(contig_len) = self.assembler.remove_front() {
+        let contig_len = self.assembler.remove_front();
+        if let Some(contig_len) = contig_len {
             debug_assert!(self.assembler.total_size() == self.rx_buffer.capacity());
             // Enqueue the contiguous data octets in front of the buffer.
             tcp_trace!(
@@ -1793,8 +2683,7 @@ impl<'a> Socket<'a> {
             self.rx_buffer.enqueue_unallocated(contig_len);
 
             // There's new data in rx_buffer, notify waiting task if any.
-            #[cfg(feature = "async")]
-            self.rx_waker.wake();
+            self.notify_readable();
         }
 
         if !self.assembler.is_empty() {
@@ -1830,6 +2719,14 @@ impl<'a> Socket<'a> {
         //  1) an out-of-order segment is received, or
         //  2) a segment arrives that fills in all or part of a gap in sequence space.
         if !self.assembler.is_empty() || !assembler_was_empty {
+            if contig_len.is_some() && self.seq_to_transmit(cx) {
+                // The acknowledgement is not a duplicate one, and we are about to send
+                // a segment anyway; let that segment carry it instead of sending both.
+                tcp_trace!("piggybacking ACK on outgoing segment");
+                self.ack_delay_timer = AckDelayTimer::Immediate;
+                return None;
+            }
+
             // Note that we change the transmitter state here.
             // This is fine because smoltcp assumes that it can always transmit zero or one
             // packets for every packet it receives.
@@ -1847,6 +2744,26 @@ impl<'a> Socket<'a> {
         }
     }
 
+    /// Return the reason for a timeout in the current state.
+    fn timeout_reason(&self) -> DisconnectReason {
+        match self.state {
+            // With nothing left to acknowledge, only keep-alive packets went unanswered.
+            State::Established | State::CloseWait | State::FinWait2
+                if self.keep_alive.is_some() && self.tx_buffer.is_empty() =>
+            {
+                DisconnectReason::KeepAliveTimeout
+            }
+            _ => DisconnectReason::Timeout,
+        }
+    }
+
+    fn fin_timed_out(&self, timestamp: Instant) -> bool {
+        match (self.fin_wait_since, self.fin_timeout) {
+            (Some(fin_wait_since), Some(fin_timeout)) => timestamp >= fin_wait_since + fin_timeout,
+            (_, _) => false,
+        }
+    }
+
     fn seq_to_transmit(&self, cx: &mut Context) -> bool {
         let ip_header_len = match self.tuple.unwrap().local.addr {
             #[cfg(feature = "proto-ipv4")]
@@ -1856,7 +2773,8 @@ impl<'a> Socket<'a> {
         };
 
         // Max segment size we're able to send due to MTU limitations.
-        let local_mss = cx.ip_mtu() - ip_header_len - TCP_HEADER_LEN;
+        let local_mss =
+            cx.path_mtu(&self.tuple.unwrap().remote.addr) - ip_header_len - TCP_HEADER_LEN;
 
         // The effective max segment size, taking into account our and remote's limits.
         let effective_mss = local_mss.min(self.remote_mss);
@@ -1897,6 +2815,20 @@ impl<'a> Socket<'a> {
             can_send = false;
         }
 
+        // Don't send more segments than the retransmission queue can keep track of.
+        if self.retransmit_queue.is_exhausted(self.remote_last_seq) {
+            can_send = false;
+        }
+
+        // Sender-side silly window syndrome avoidance, per RFC 1122 § 4.2.3.4: if the remote
+        // window only lets us send a small part of the queued data, wait for it to open up,
+        // unless nothing is in flight, in which case no window update might ever come.
+        let window_limited = max_send_seq < self.local_seq_no + self.tx_buffer.len();
+        if data_in_flight && window_limited && !can_send_full && max_send < self.remote_win_max / 2
+        {
+            can_send = false;
+        }
+
         // Can we actually send the FIN? We can send it if:
         // 1. We have unsent data that fits in the remote window.
         // 2. We have no unsent data.
@@ -1928,7 +2860,15 @@ impl<'a> Socket<'a> {
             | State::SynReceived
             | State::Established
             | State::FinWait1
-            | State::FinWait2 => self.scaled_window() > self.remote_last_win,
+            | State::FinWait2 => {
+                // Receiver-side silly window syndrome avoidance, per RFC 1122 § 4.2.3.3: only
+                // advertise a larger window once it has grown by a significant amount.
+                let shift = self.remote_win_shift as usize;
+                let window = (self.scaled_window() as usize) << shift;
+                let last_window = (self.remote_last_win as usize) << shift;
+                let threshold = cmp::min(self.rx_buffer.capacity() / 2, self.remote_mss).max(1);
+                window >= last_window + threshold
+            }
             _ => false,
         }
     }
@@ -1937,7 +2877,27 @@ impl<'a> Socket<'a> {
     where
         F: FnOnce(&mut Context, (IpRepr, TcpRepr)) -> Result<(), E>,
     {
-        if self.tuple.is_none() {
+        if self.tuple.is_none() || !self.is_serviced_by(cx) {
+            return Ok(());
+        }
+
+        if self.local_addr_lost(cx) {
+            if self.state == State::Closed {
+                // The reset cannot be sent from an address we no longer have.
+                self.tuple = None;
+            } else if self.address_loss_policy == AddressLossPolicy::Abort {
+                net_debug!("local address removed, aborting");
+                self.count_abort(cx);
+                self.disconnect_reason = Some(DisconnectReason::AddressLost);
+                self.set_state(State::Closed);
+                self.tuple = None;
+            } else if self.timed_out(cx.now()) {
+                net_debug!("timeout exceeded while the local address is removed");
+                self.count_abort(cx);
+                self.disconnect_reason = Some(self.timeout_reason());
+                self.set_state(State::Closed);
+                self.tuple = None;
+            }
             return Ok(());
         }
 
@@ -1952,16 +2912,35 @@ impl<'a> Socket<'a> {
             self.remote_last_ts = Some(cx.now());
         }
 
+        if self.fin_wait_since.is_none() && matches!(self.state, State::FinWait2 | State::LastAck) {
+            // Likewise, start counting the FIN timeout from the first dispatch in the state.
+            self.fin_wait_since = Some(cx.now());
+        }
+
         // Check if any state needs to be changed because of a timer.
         if self.timed_out(cx.now()) {
             // If a timeout expires, we should abort the connection.
             net_debug!("timeout exceeded");
+            self.count_abort(cx);
+            self.disconnect_reason = Some(self.timeout_reason());
+            self.set_state(State::Closed);
+        } else if self.fin_timed_out(cx.now()) {
+            // If the remote takes too long to close the connection, abort it.
+            net_debug!("FIN timeout exceeded");
+            self.count_abort(cx);
+            self.disconnect_reason = Some(DisconnectReason::FinTimeout);
             self.set_state(State::Closed);
         } else if !self.seq_to_transmit(cx) {
             if let Some(retransmit_delta) = self.timer.should_retransmit(cx.now()) {
                 // If a retransmit timer expired, we should resend data starting at the last ACK.
                 net_debug!("retransmitting at t+{}", retransmit_delta);
 
+                // After a retransmission timeout, the remote may have discarded the data it
+                // selectively acknowledged, so send it again too.
+                if self.timer != Timer::FastRetransmit {
+                    self.retransmit_queue.clear_sacked();
+                }
+
                 // Rewind "last sequence number sent", as if we never
                 // had sent them. This will cause all data in the queue
                 // to be sent again.
@@ -1978,6 +2957,9 @@ impl<'a> Socket<'a> {
             }
         }
 
+        // Don't retransmit the segments the remote selectively acknowledged.
+        self.remote_last_seq = self.retransmit_queue.skip_sacked(self.remote_last_seq);
+
         // Decide whether we're sending a packet.
         if self.seq_to_transmit(cx) {
             // If we have data to transmit and it fits into partner's window, do it.
@@ -2086,10 +3068,25 @@ impl<'a> Socket<'a> {
                 // Maximum size we're allowed to send. This can be limited by 3 factors:
                 // 1. remote window
                 // 2. MSS the remote is willing to accept, probably determined by their MTU
-                // 3. MSS we can send, determined by our MTU.
-                let size = win_limit
-                    .min(self.remote_mss)
-                    .min(cx.ip_mtu() - ip_repr.buffer_len() - TCP_HEADER_LEN);
+                // 3. MSS we can send, determined by our MTU, or the MTU of the route.
+                let local_mss =
+                    cx.path_mtu(&ip_repr.dst_addr()) - ip_repr.buffer_len() - TCP_HEADER_LEN;
+                let mut size = win_limit.min(self.remote_mss).min(local_mss);
+
+                // With segmentation offload, hand the device a larger segment to split into
+                // frames of the local MSS, as long as the remote accepts those.
+                if let Some(max_len) = cx.tcp_segmentation_offload() {
+                    if self.remote_mss >= local_mss {
+                        let max_size =
+                            max_len.saturating_sub(ip_repr.buffer_len() + TCP_HEADER_LEN);
+                        size = win_limit.min(max_size.max(local_mss));
+                    }
+                }
+
+                // Retransmit segments with the same boundaries as they were first sent with.
+                if let Some(segment) = self.retransmit_queue.get(self.remote_last_seq) {
+                    size = size.min(segment.len);
+                }
 
                 let offset = self.remote_last_seq - self.local_seq_no;
                 repr.payload = self.tx_buffer.get_allocated(offset, size);
@@ -2151,7 +3148,8 @@ impl<'a> Socket<'a> {
 
         if repr.control == TcpControl::Syn {
             // Fill the MSS option. See RFC 6691 for an explanation of this calculation.
-            let max_segment_size = cx.ip_mtu() - ip_repr.buffer_len() - TCP_HEADER_LEN;
+            let max_segment_size =
+                cx.path_mtu(&ip_repr.dst_addr()) - ip_repr.buffer_len() - TCP_HEADER_LEN;
             repr.max_seg_size = Some(max_segment_size as u16);
         }
 
@@ -2193,9 +3191,20 @@ impl<'a> Socket<'a> {
         self.remote_last_win = repr.window_len;
 
         if repr.segment_len() > 0 {
+            let retransmission = matches!(
+                self.rtte.max_seq_sent,
+                Some(max_seq_sent) if repr.seq_number < max_seq_sent
+            );
+            if retransmission {
+                cx.stats_mut().tcp.retrans_segs += 1;
+            }
             self.rtte
                 .on_send(cx.now(), repr.seq_number + repr.segment_len());
         }
+        if !repr.payload.is_empty() {
+            self.retransmit_queue
+                .on_send(repr.seq_number, repr.payload.len(), cx.now());
+        }
 
         if !self.seq_to_transmit(cx) && repr.segment_len() > 0 {
             // If we've transmitted all data we could (and there was something at all,
@@ -2215,12 +3224,28 @@ impl<'a> Socket<'a> {
     #[allow(clippy::if_same_then_else)]
     pub(crate) fn poll_at(&self, cx: &mut Context) -> PollAt {
         // The logic here mirrors the beginning of dispatch() closely.
-        if self.tuple.is_none() {
+        if self.tuple.is_none() || !self.is_serviced_by(cx) {
             // No one to talk to, nothing to transmit.
             PollAt::Ingress
+        } else if self.local_addr_lost(cx) {
+            // Nothing can be transmitted until the address is added back, but the connection
+            // may have to be aborted.
+            match (self.state, self.address_loss_policy) {
+                (State::Closed, _) | (_, AddressLossPolicy::Abort) => PollAt::Now,
+                _ => match (self.remote_last_ts, self.timeout) {
+                    (Some(remote_last_ts), Some(timeout)) => PollAt::Time(remote_last_ts + timeout),
+                    (_, _) => PollAt::Ingress,
+                },
+            }
         } else if self.remote_last_ts.is_none() {
             // Socket stopped being quiet recently, we need to acquire a timestamp.
             PollAt::Now
+        } else if self.fin_timeout.is_some()
+            && self.fin_wait_since.is_none()
+            && matches!(self.state, State::FinWait2 | State::LastAck)
+        {
+            // Socket started closing recently, we need to acquire a timestamp.
+            PollAt::Now
         } else if self.state == State::Closed {
             // Socket was aborted, we have an RST packet to transmit.
             PollAt::Now
@@ -2245,11 +3270,23 @@ impl<'a> Socket<'a> {
                 (_, _) => PollAt::Ingress,
             };
 
+            let fin_timeout_poll_at = match (self.fin_wait_since, self.fin_timeout) {
+                (Some(fin_wait_since), Some(fin_timeout)) => {
+                    PollAt::Time(fin_wait_since + fin_timeout)
+                }
+                (_, _) => PollAt::Ingress,
+            };
+
             // We wait for the earliest of our timers to fire.
-            *[self.timer.poll_at(), timeout_poll_at, delayed_ack_poll_at]
-                .iter()
-                .min()
-                .unwrap_or(&PollAt::Ingress)
+            *[
+                self.timer.poll_at(),
+                timeout_poll_at,
+                fin_timeout_poll_at,
+                delayed_ack_poll_at,
+            ]
+            .iter()
+            .min()
+            .unwrap_or(&PollAt::Ingress)
         }
     }
 }
@@ -2265,10 +3302,193 @@ impl<'a> fmt::Write for Socket<'a> {
     }
 }
 
+/// The default delay between the IPv6 and the IPv4 connection attempts of [HappyEyeballs],
+/// as recommended by [RFC 8305 § 5].
+///
+/// [RFC 8305 § 5]: https://tools.ietf.org/html/rfc8305#section-5
+pub const HAPPY_EYEBALLS_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The progress of a [HappyEyeballs] connection race.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HappyEyeballsStatus {
+    /// No connection attempt has completed yet.
+    Connecting,
+    /// The socket with the given handle established a connection; the other attempt
+    /// was aborted.
+    Connected(SocketHandle),
+    /// Every connection attempt failed.
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attempt {
+    handle: SocketHandle,
+    remote_endpoint: IpEndpoint,
+    started: bool,
+}
+
+impl Attempt {
+    fn start(
+        &mut self,
+        cx: &mut Context,
+        sockets: &mut SocketSet,
+        local_port: u16,
+    ) -> Result<(), ConnectError> {
+        if !self.started {
+            tcp_trace!("happy eyeballs: connecting to {}", self.remote_endpoint);
+            sockets
+                .get_mut::<Socket>(self.handle)
+                .connect(cx, self.remote_endpoint, local_port)?;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    fn state(&self, sockets: &SocketSet) -> Option<State> {
+        if self.started {
+            Some(sockets.get::<Socket>(self.handle).state())
+        } else {
+            None
+        }
+    }
+}
+
+/// A helper racing an IPv6 and an IPv4 connection attempt, as described in [RFC 8305].
+///
+/// The IPv6 attempt is started first. The IPv4 attempt is started once the IPv6 attempt
+/// fails, or after the [attempt delay](#method.set_attempt_delay) elapses without the IPv6
+/// attempt completing. The first socket to establish a connection wins, and the other
+/// attempt is aborted.
+///
+/// The helper does not own the sockets: they are identified by their handles in the
+/// [SocketSet], and must be closed when the race starts. Either address may be omitted,
+/// e.g. when name resolution only returned addresses of one family.
+///
+/// [RFC 8305]: https://tools.ietf.org/html/rfc8305
+#[derive(Debug)]
+pub struct HappyEyeballs {
+    local_port: u16,
+    ipv6: Option<Attempt>,
+    ipv4: Option<Attempt>,
+    attempt_delay: Duration,
+    started_at: Option<Instant>,
+    winner: Option<SocketHandle>,
+}
+
+impl HappyEyeballs {
+    /// Create a connection race from `local_port`, using the given socket and remote
+    /// endpoint for each address family.
+    pub fn new(
+        local_port: u16,
+        ipv6: Option<(SocketHandle, IpEndpoint)>,
+        ipv4: Option<(SocketHandle, IpEndpoint)>,
+    ) -> HappyEyeballs {
+        let attempt = |(handle, remote_endpoint)| Attempt {
+            handle,
+            remote_endpoint,
+            started: false,
+        };
+        HappyEyeballs {
+            local_port,
+            ipv6: ipv6.map(attempt),
+            ipv4: ipv4.map(attempt),
+            attempt_delay: HAPPY_EYEBALLS_ATTEMPT_DELAY,
+            started_at: None,
+            winner: None,
+        }
+    }
+
+    /// Return the delay between the start of the IPv6 and the IPv4 connection attempts.
+    ///
+    /// See also the [set_attempt_delay](#method.set_attempt_delay) method.
+    pub fn attempt_delay(&self) -> Duration {
+        self.attempt_delay
+    }
+
+    /// Set the delay between the start of the IPv6 and the IPv4 connection attempts.
+    ///
+    /// The default is [HAPPY_EYEBALLS_ATTEMPT_DELAY].
+    pub fn set_attempt_delay(&mut self, delay: Duration) {
+        self.attempt_delay = delay
+    }
+
+    /// Start the connection attempts that are due, and check whether one of them
+    /// has completed.
+    ///
+    /// This function should be called after every interface poll, and at the time
+    /// returned by [poll_at](#method.poll_at). It returns an error if a socket could
+    /// not start connecting; see [Socket::connect].
+    pub fn poll(
+        &mut self,
+        cx: &mut Context,
+        sockets: &mut SocketSet,
+    ) -> Result<HappyEyeballsStatus, ConnectError> {
+        if let Some(winner) = self.winner {
+            return Ok(HappyEyeballsStatus::Connected(winner));
+        }
+
+        let now = cx.now();
+        let started_at = *self.started_at.get_or_insert(now);
+
+        if let Some(ipv6) = self.ipv6.as_mut() {
+            ipv6.start(cx, sockets, self.local_port)?;
+        }
+        let ipv6_failed = match self.ipv6 {
+            Some(ipv6) => ipv6.state(sockets) == Some(State::Closed),
+            None => true,
+        };
+        if let Some(ipv4) = self.ipv4.as_mut() {
+            if ipv6_failed || now >= started_at + self.attempt_delay {
+                ipv4.start(cx, sockets, self.local_port)?;
+            }
+        }
+
+        for &(winner, loser) in [(self.ipv6, self.ipv4), (self.ipv4, self.ipv6)].iter() {
+            let winner = match winner {
+                Some(winner) if winner.state(sockets) == Some(State::Established) => winner,
+                _ => continue,
+            };
+            if let Some(loser) = loser.filter(|loser| loser.started) {
+                tcp_trace!(
+                    "happy eyeballs: connected to {}, aborting connection to {}",
+                    winner.remote_endpoint,
+                    loser.remote_endpoint
+                );
+                sockets.get_mut::<Socket>(loser.handle).abort();
+            }
+            self.winner = Some(winner.handle);
+            return Ok(HappyEyeballsStatus::Connected(winner.handle));
+        }
+
+        let failed = [self.ipv6, self.ipv4]
+            .iter()
+            .flatten()
+            .all(|attempt| attempt.state(sockets) == Some(State::Closed));
+        if failed {
+            Ok(HappyEyeballsStatus::Failed)
+        } else {
+            Ok(HappyEyeballsStatus::Connecting)
+        }
+    }
+
+    /// Return the time at which the next connection attempt should be started, if any.
+    pub fn poll_at(&self) -> Option<Instant> {
+        match (self.started_at, self.ipv4) {
+            (Some(started_at), Some(ipv4)) if !ipv4.started && self.winner.is_none() => {
+                Some(started_at + self.attempt_delay)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::wire::IpRepr;
+    use crate::wire::{IpCidr, IpRepr};
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+    use crate::wire::{Ipv4Address, Ipv6Address};
     use crate::Error;
     use core::i32;
     use std::ops::{Deref, DerefMut};
@@ -2509,6 +3729,7 @@ mod test {
         s.remote_seq_no = REMOTE_SEQ + 1;
         s.remote_last_seq = LOCAL_SEQ;
         s.remote_win_len = 256;
+        s.remote_win_max = 256;
         s
     }
 
@@ -2710,6 +3931,31 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_listen_syn_jumbo_mss() {
+        let mut s = socket_listen();
+        s.cx.set_device_ip_mtu(9000);
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS + 7500),
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
     #[test]
     fn test_listen_syn_win_scale_buffers() {
         for (buffer_size, shift_amt) in &[
@@ -2806,6 +4052,147 @@ mod test {
         assert_eq!(s.state, State::Listen);
     }
 
+    #[test]
+    fn test_listen_accept_filter() {
+        let mut s = socket_listen();
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 20,
+            hop_limit: 64,
+        });
+        let tcp_repr = TcpRepr {
+            control: TcpControl::Syn,
+            seq_number: REMOTE_SEQ,
+            ack_number: None,
+            ..SEND_TEMPL
+        };
+
+        s.set_accept_filter(Some(|remote| remote.port != REMOTE_PORT));
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &tcp_repr));
+        assert_eq!(s.state, State::Listen);
+
+        s.set_accept_filter(Some(|remote| remote == REMOTE_END));
+        send!(s, tcp_repr);
+        sanity!(s, socket_syn_received());
+    }
+
+    #[test]
+    fn test_listen_syn_cookie() {
+        let mut s = socket_listen();
+        s.set_syn_cookies(true);
+        let syn_ack = send(
+            &mut s,
+            Instant::from_millis(0),
+            &TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                max_seg_size: Some(1400),
+                window_scale: Some(2),
+                sack_permitted: true,
+                ..SEND_TEMPL
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            syn_ack,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: syn_ack.seq_number,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                window_scale: Some(0),
+                sack_permitted: true,
+                ..RECV_TEMPL
+            }
+        );
+        // No state was kept.
+        assert_eq!(s.state, State::Listen);
+        assert_eq!(s.tuple, None);
+        recv!(s, []);
+
+        send!(
+            s,
+            time 1000,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(syn_ack.seq_number + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.tuple, Some(TUPLE));
+        assert_eq!(s.local_seq_no, syn_ack.seq_number + 1);
+        assert_eq!(s.remote_mss, 1400);
+        assert_eq!(s.remote_win_scale, Some(2));
+        assert!(s.remote_has_sack);
+        assert_eq!(s.rx_buffer.dequeue_many(3), &b"abc"[..]);
+    }
+
+    #[test]
+    fn test_listen_syn_cookie_reject_ack() {
+        let mut s = socket_listen();
+        s.set_syn_cookies(true);
+        let syn_ack = send(
+            &mut s,
+            Instant::from_millis(0),
+            &TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                ..SEND_TEMPL
+            },
+        )
+        .unwrap();
+
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Tcp,
+            payload_len: 20,
+            hop_limit: 64,
+        });
+        let ack = TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(syn_ack.seq_number + 1),
+            ..SEND_TEMPL
+        };
+
+        // Forged cookie.
+        let forged = TcpRepr {
+            ack_number: Some(syn_ack.seq_number + 1 + 0x100),
+            ..ack
+        };
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &forged));
+
+        // Different remote initial sequence number.
+        let forged = TcpRepr {
+            seq_number: REMOTE_SEQ + 2,
+            ..ack
+        };
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &forged));
+
+        // Expired cookie.
+        s.cx.set_now(Instant::from_secs(2 * SYN_COOKIE_PERIOD as i64));
+        assert!(!s.socket.accepts(&mut s.cx, &ip_repr, &ack));
+        s.cx.set_now(Instant::from_secs(2 * SYN_COOKIE_PERIOD as i64 - 1));
+        assert!(s.socket.accepts(&mut s.cx, &ip_repr, &ack));
+
+        assert_eq!(s.state, State::Listen);
+    }
+
+    #[test]
+    fn test_siphash() {
+        // Test vectors from the SipHash reference implementation.
+        let key = [0x0706050403020100, 0x0f0e0d0c0b0a0908];
+        assert_eq!(siphash(&key, &[]), 0x726fdb47dd0e0e31);
+        let data = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14];
+        assert_eq!(siphash(&key, &data), 0xa129ca6149be45e5);
+    }
+
     #[test]
     fn test_listen_rst() {
         let mut s = socket_listen();
@@ -2988,9 +4375,36 @@ mod test {
     }
 
     #[test]
-    fn test_syn_received_no_window_scaling() {
-        let mut s = socket_listen();
-        send!(
+    fn test_syn_received_rst_simultaneous_open() {
+        let mut s = socket_syn_received();
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Refused));
+        assert_eq!(s.tuple, None);
+    }
+
+    #[test]
+    fn test_syn_received_no_window_scaling() {
+        let mut s = socket_listen();
+        send!(
             s,
             TcpRepr {
                 control: TcpControl::Syn,
@@ -3255,6 +4669,7 @@ mod test {
             }
         );
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Refused));
     }
 
     #[test]
@@ -3396,6 +4811,60 @@ mod test {
         assert_eq!(s.state, State::Closed);
     }
 
+    #[test]
+    fn test_syn_sent_syn_simultaneous_open() {
+        let mut s = socket_syn_sent();
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: None,
+                max_seg_size: Some(BASE_MSS),
+                window_scale: Some(0),
+                sack_permitted: true,
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: None,
+                max_seg_size: Some(BASE_MSS - 80),
+                window_scale: Some(0),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::SynReceived);
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: LOCAL_SEQ,
+                ack_number: Some(REMOTE_SEQ + 1),
+                max_seg_size: Some(BASE_MSS),
+                window_scale: Some(0),
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ,
+                ack_number: Some(LOCAL_SEQ + 1),
+                max_seg_size: Some(BASE_MSS - 80),
+                window_scale: Some(0),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Established);
+        recv!(s, time 1000, Err(Error::Exhausted));
+        sanity!(s, socket_established());
+    }
+
     #[test]
     fn test_syn_sent_win_scale_buffers() {
         for (buffer_size, shift_amt) in &[
@@ -3529,6 +4998,88 @@ mod test {
         assert_eq!(s.rx_buffer.dequeue_many(6), &b"abcdef"[..]);
     }
 
+    #[test]
+    fn test_watermarks() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static READABLE: AtomicUsize = AtomicUsize::new(0);
+        static WRITABLE: AtomicUsize = AtomicUsize::new(0);
+        fn callback(event: BufferEvent) {
+            match event {
+                BufferEvent::Readable => READABLE.fetch_add(1, Ordering::SeqCst),
+                BufferEvent::Writable => WRITABLE.fetch_add(1, Ordering::SeqCst),
+            };
+        }
+
+        let mut s = socket_established();
+        assert_eq!(s.recv_high_watermark(), 1);
+        assert_eq!(s.send_low_watermark(), 64);
+        s.set_recv_high_watermark(Some(8));
+        s.set_send_low_watermark(Some(4));
+        s.set_buffer_callback(Some(callback));
+
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.can_recv());
+        assert!(!s.is_readable());
+        assert_eq!(READABLE.load(Ordering::SeqCst), 0);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 6,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"ghijkl"[..],
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.is_readable());
+        assert_eq!(READABLE.load(Ordering::SeqCst), 1);
+        assert_eq!(s.rx_buffer.dequeue_many(12), &b"abcdefghijkl"[..]);
+
+        let mut s = socket_established();
+        s.set_send_low_watermark(Some(4));
+        s.set_buffer_callback(Some(callback));
+        s.send_slice(b"abcdef012345").unwrap();
+        assert!(s.can_send());
+        assert!(!s.is_writable());
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef012345"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(!s.is_writable());
+        assert_eq!(WRITABLE.load(Ordering::SeqCst), 0);
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 12),
+                ..SEND_TEMPL
+            }
+        );
+        assert!(s.is_writable());
+        assert_eq!(WRITABLE.load(Ordering::SeqCst), 1);
+    }
+
     fn setup_rfc2018_cases() -> (TestSocket, Vec<u8>) {
         // This is a utility function used by the tests for RFC 2018 cases. It configures a socket
         // in a particular way suitable for those cases.
@@ -4023,6 +5574,7 @@ mod test {
             }
         );
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Reset));
     }
 
     #[test]
@@ -4116,6 +5668,96 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_established_rst_in_window_challenge_ack() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1 + 10, // in window, but not RCV.NXT
+                ack_number: None,
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            })
+        );
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.cx.stats_mut().tcp.challenge_acks, 1);
+
+        // Challenge ACKs are rate-limited.
+        send!(
+            s,
+            time 500,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1 + 10,
+                ack_number: None,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.cx.stats_mut().tcp.challenge_acks, 1);
+
+        // An RST exactly at RCV.NXT resets the connection.
+        send!(
+            s,
+            time 500,
+            TcpRepr {
+                control: TcpControl::Rst,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: None,
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Closed);
+    }
+
+    #[test]
+    fn test_established_syn_challenge_ack() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Syn,
+                seq_number: REMOTE_SEQ + 1000, // any sequence number
+                ack_number: None,
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            })
+        );
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.cx.stats_mut().tcp.challenge_acks, 1);
+    }
+
+    #[test]
+    fn test_established_old_ack_challenge_ack() {
+        let mut s = socket_established();
+        // Older than the largest window advertised by the remote.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 - 1000),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            })
+        );
+        assert_eq!(s.rx_buffer.len(), 0);
+        assert_eq!(s.cx.stats_mut().tcp.challenge_acks, 1);
+    }
+
     // =========================================================================================//
     // Tests for the FIN-WAIT-1 state.
     // =========================================================================================//
@@ -4666,57 +6308,184 @@ mod test {
         recv!(s, []);
     }
 
-    #[test]
-    fn test_simultaneous_close_combined_fin_ack() {
-        let mut s = socket_established();
-        s.close();
-        assert_eq!(s.state, State::FinWait1);
-        recv!(
-            s,
-            [TcpRepr {
-                control: TcpControl::Fin,
-                seq_number: LOCAL_SEQ + 1,
-                ack_number: Some(REMOTE_SEQ + 1),
-                ..RECV_TEMPL
-            }]
-        );
-        send!(
-            s,
-            TcpRepr {
-                control: TcpControl::Fin,
-                seq_number: REMOTE_SEQ + 1,
-                ack_number: Some(LOCAL_SEQ + 1 + 1),
-                ..SEND_TEMPL
-            }
-        );
-        assert_eq!(s.state, State::TimeWait);
-        recv!(
-            s,
-            [TcpRepr {
-                seq_number: LOCAL_SEQ + 1 + 1,
-                ack_number: Some(REMOTE_SEQ + 1 + 1),
-                ..RECV_TEMPL
-            }]
-        );
-    }
+    /// A socket in the state a transition starts from, the segment it receives, and the
+    /// state it ends up in.
+    type Transition = (fn() -> TestSocket, TcpRepr<'static>, State);
 
     #[test]
-    fn test_simultaneous_close_raced() {
-        let mut s = socket_established();
-        s.close();
-        assert_eq!(s.state, State::FinWait1);
-
-        // Socket receives FIN before it has a chance to send its own FIN
-        send!(
-            s,
-            TcpRepr {
-                control: TcpControl::Fin,
-                seq_number: REMOTE_SEQ + 1,
-                ack_number: Some(LOCAL_SEQ + 1),
-                ..SEND_TEMPL
-            }
-        );
-        assert_eq!(s.state, State::Closing);
+    fn test_rfc793_transitions() {
+        // Transitions of the state diagram in RFC 793 § 3.2, figure 6, that are driven
+        // by an incoming segment.
+        let table: &[Transition] = &[
+            (
+                socket_listen,
+                TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: REMOTE_SEQ,
+                    ack_number: None,
+                    ..SEND_TEMPL
+                },
+                State::SynReceived,
+            ),
+            (
+                socket_syn_sent,
+                TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: REMOTE_SEQ,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    ..SEND_TEMPL
+                },
+                State::Established,
+            ),
+            (
+                socket_syn_sent,
+                TcpRepr {
+                    control: TcpControl::Syn,
+                    seq_number: REMOTE_SEQ,
+                    ack_number: None,
+                    ..SEND_TEMPL
+                },
+                State::SynReceived,
+            ),
+            (
+                socket_syn_received,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    ..SEND_TEMPL
+                },
+                State::Established,
+            ),
+            (
+                socket_established,
+                TcpRepr {
+                    control: TcpControl::Fin,
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    ..SEND_TEMPL
+                },
+                State::CloseWait,
+            ),
+            (
+                socket_fin_wait_1,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1 + 1),
+                    ..SEND_TEMPL
+                },
+                State::FinWait2,
+            ),
+            (
+                socket_fin_wait_1,
+                TcpRepr {
+                    control: TcpControl::Fin,
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1),
+                    ..SEND_TEMPL
+                },
+                State::Closing,
+            ),
+            (
+                socket_fin_wait_1,
+                TcpRepr {
+                    control: TcpControl::Fin,
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1 + 1),
+                    ..SEND_TEMPL
+                },
+                State::TimeWait,
+            ),
+            (
+                socket_fin_wait_2,
+                TcpRepr {
+                    control: TcpControl::Fin,
+                    seq_number: REMOTE_SEQ + 1,
+                    ack_number: Some(LOCAL_SEQ + 1 + 1),
+                    ..SEND_TEMPL
+                },
+                State::TimeWait,
+            ),
+            (
+                socket_closing,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1 + 1,
+                    ack_number: Some(LOCAL_SEQ + 1 + 1),
+                    ..SEND_TEMPL
+                },
+                State::TimeWait,
+            ),
+            (
+                socket_last_ack,
+                TcpRepr {
+                    seq_number: REMOTE_SEQ + 1 + 1,
+                    ack_number: Some(LOCAL_SEQ + 1 + 1),
+                    ..SEND_TEMPL
+                },
+                State::Closed,
+            ),
+        ];
+
+        for (make_socket, repr, next_state) in table {
+            let mut s = make_socket();
+            let state = s.state;
+            // Flush any SYN or FIN that the state requires to have been sent.
+            recv(&mut s, Instant::from_millis(0), |_| ());
+            send(&mut s, Instant::from_millis(0), repr);
+            assert_eq!(s.state, *next_state, "from {} on {}", state, repr);
+        }
+    }
+
+    #[test]
+    fn test_simultaneous_close_combined_fin_ack() {
+        let mut s = socket_established();
+        s.close();
+        assert_eq!(s.state, State::FinWait1);
+        recv!(
+            s,
+            [TcpRepr {
+                control: TcpControl::Fin,
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Fin,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 1),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::TimeWait);
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 1),
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_simultaneous_close_raced() {
+        let mut s = socket_established();
+        s.close();
+        assert_eq!(s.state, State::FinWait1);
+
+        // Socket receives FIN before it has a chance to send its own FIN
+        send!(
+            s,
+            TcpRepr {
+                control: TcpControl::Fin,
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Closing);
 
         // FIN + ack-of-FIN
         recv!(
@@ -4960,6 +6729,159 @@ mod test {
         recv!(s, time 1550, Err(Error::Exhausted));
     }
 
+    #[test]
+    fn test_data_retransmit_after_partial_ack() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.send_slice(b"abcdef012345").unwrap();
+
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::None,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }), exact);
+        recv!(s, time 0, Ok(TcpRepr {
+            control:    TcpControl::Psh,
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"012345"[..],
+            ..RECV_TEMPL
+        }), exact);
+
+        // Only the first segment is acknowledged; the second one is still in flight,
+        // so the retransmit timer is restarted rather than stopped.
+        send!(s, time 100, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 6),
+            ..SEND_TEMPL
+        });
+        assert!(match s.timer {
+            Timer::Retransmit { expires_at, .. } => expires_at > Instant::from_millis(100),
+            _ => false,
+        });
+        recv!(s, time 1100, Ok(TcpRepr {
+            control:    TcpControl::Psh,
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"012345"[..],
+            ..RECV_TEMPL
+        }), exact);
+    }
+
+    #[test]
+    fn test_retransmit_queue_boundaries() {
+        let mut s = socket_established();
+        s.set_retransmit_queue(vec![SegmentMetadata::EMPTY; 4]);
+        s.set_nagle_enabled(false);
+        s.send_slice(b"abc").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        s.send_slice(b"def").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 3,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"def"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 0, Err(Error::Exhausted));
+
+        // The segments are retransmitted as they were sent, not coalesced.
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 3,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"def"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1000, Err(Error::Exhausted));
+    }
+
+    #[test]
+    fn test_retransmit_queue_exhausted() {
+        let mut s = socket_established();
+        s.set_retransmit_queue(vec![SegmentMetadata::EMPTY; 1]);
+        s.set_nagle_enabled(false);
+        s.send_slice(b"abc").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        s.send_slice(b"def").unwrap();
+        recv!(s, time 0, Err(Error::Exhausted));
+        send!(s, time 10, TcpRepr {
+            seq_number: REMOTE_SEQ + 1,
+            ack_number: Some(LOCAL_SEQ + 1 + 3),
+            ..SEND_TEMPL
+        });
+        recv!(s, time 10, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 3,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"def"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_retransmit_queue_sack() {
+        let mut s = socket_established();
+        s.set_retransmit_queue(vec![SegmentMetadata::EMPTY; 4]);
+        s.remote_has_sack = true;
+        s.remote_mss = 3;
+        s.send_slice(b"abcdefghi").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 3,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"def"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1 + 6,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"ghi"[..],
+            ..RECV_TEMPL
+        }));
+
+        // The first segment is lost, the others are selectively acknowledged.
+        for _ in 0..4 {
+            send!(s, time 10, TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                sack_ranges: [
+                    Some(((LOCAL_SEQ + 1 + 3).0 as u32, (LOCAL_SEQ + 1 + 9).0 as u32)),
+                    None,
+                    None,
+                ],
+                ..SEND_TEMPL
+            });
+        }
+        recv!(s, time 10, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abc"[..],
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 10, Err(Error::Exhausted));
+    }
+
     #[test]
     fn test_send_data_after_syn_ack_retransmit() {
         let mut s = socket_syn_received();
@@ -5586,6 +7508,53 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_segmentation_offload() {
+        let mut s = socket_established_with_buffer_sizes(8192, 64);
+        s.remote_mss = BASE_MSS as usize;
+        s.remote_win_len = 8192;
+        s.cx.set_tcp_segmentation_offload(Some(4000));
+        s.send_slice(&[0; 5000][..]).unwrap();
+
+        // The first segment fills the offload limit, the rest is sent as usual.
+        let first = 4000 - (1500 - BASE_MSS as usize);
+        recv!(
+            s,
+            [
+                TcpRepr {
+                    seq_number: LOCAL_SEQ + 1,
+                    ack_number: Some(REMOTE_SEQ + 1),
+                    payload: &[0; 8192][..first],
+                    ..RECV_TEMPL
+                },
+                TcpRepr {
+                    seq_number: LOCAL_SEQ + 1 + first,
+                    ack_number: Some(REMOTE_SEQ + 1),
+                    payload: &[0; 8192][..5000 - first],
+                    ..RECV_TEMPL
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_segmentation_offload_small_remote_mss() {
+        let mut s = socket_established_with_buffer_sizes(8192, 64);
+        s.remote_mss = 1000;
+        s.remote_win_len = 8192;
+        s.cx.set_tcp_segmentation_offload(Some(4000));
+        s.send_slice(&[0; 1200][..]).unwrap();
+        recv!(
+            s,
+            Ok(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &[0; 1000][..],
+                ..RECV_TEMPL
+            })
+        );
+    }
+
     #[test]
     fn test_close_wait_no_window_update() {
         let mut s = socket_established();
@@ -5792,10 +7761,111 @@ mod test {
     }
 
     #[test]
-    fn test_fill_peer_window() {
+    fn test_window_update_sws_avoidance() {
         let mut s = socket_established();
-        s.remote_mss = 6;
-        s.send_slice(b"abcdef123456!@#$%^").unwrap();
+        s.rx_buffer = SocketBuffer::new(vec![0; 6]);
+        s.assembler = Assembler::new(s.rx_buffer.capacity());
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 0,
+                ..RECV_TEMPL
+            }]
+        );
+        // Growing the window by less than half the buffer is not advertised.
+        s.recv(|_| (2, ())).unwrap();
+        recv!(s, time 0, Err(Error::Exhausted));
+        s.recv(|_| (1, ())).unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 6),
+            window_len: 3,
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_send_sws_avoidance() {
+        let mut s = socket_established();
+        s.set_nagle_enabled(false);
+        s.send_slice(b"abcdef").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                window_len: 64,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"ghijkl").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 6,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"ghijkl"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        // The window shrinks; don't send a small segment while data is in flight.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 6),
+                window_len: 10,
+                ..SEND_TEMPL
+            }
+        );
+        s.send_slice(b"mnopqr").unwrap();
+        recv!(s, time 0, Err(Error::Exhausted));
+        // The window opens up again.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 12),
+                window_len: 64,
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1 + 12,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"mnopqr"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
+    #[test]
+    fn test_fill_peer_window() {
+        let mut s = socket_established();
+        s.remote_mss = 6;
+        s.send_slice(b"abcdef123456!@#$%^").unwrap();
         recv!(
             s,
             [
@@ -5935,6 +8005,7 @@ mod test {
             ..RECV_TEMPL
         }));
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Timeout));
     }
 
     #[test]
@@ -5975,6 +8046,124 @@ mod test {
             ..RECV_TEMPL
         }));
         assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Timeout));
+    }
+
+    #[test]
+    fn test_address_lost_keep() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        s.cx.set_ip_addrs(vec![]);
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+        recv!(s, time 10, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Established);
+
+        // The connection resumes once the address is added back.
+        s.cx.set_ip_addrs(vec![IpCidr::new(LOCAL_ADDR.into_address(), 24)]);
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 20, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+    }
+
+    #[test]
+    fn test_address_lost_keep_timeout() {
+        let mut s = socket_established();
+        s.set_timeout(Some(Duration::from_millis(1000)));
+        s.send_slice(b"abcdef").unwrap();
+        recv!(s, time 0, Ok(TcpRepr {
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            payload:    &b"abcdef"[..],
+            ..RECV_TEMPL
+        }));
+        s.cx.set_ip_addrs(vec![]);
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1000))
+        );
+        recv!(s, time 500, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Established);
+
+        // No reset is sent from the removed address.
+        recv!(s, time 1000, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Timeout));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+    }
+
+    #[test]
+    fn test_address_lost_abort() {
+        let mut s = socket_established();
+        s.set_address_loss_policy(AddressLossPolicy::Abort);
+        s.cx.set_ip_addrs(vec![]);
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 0, Err(Error::Exhausted));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::AddressLost));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Ingress);
+    }
+
+    #[test]
+    fn test_syn_sent_icmp_hard_error() {
+        let mut s = socket_syn_sent();
+        // The SYN was sent.
+        s.remote_last_seq = LOCAL_SEQ + 1;
+        assert!(!s
+            .socket
+            .accepts_icmp_error(&mut s.cx, LOCAL_END, REMOTE_END, LOCAL_SEQ + 1));
+        assert!(!s
+            .socket
+            .accepts_icmp_error(&mut s.cx, REMOTE_END, LOCAL_END, LOCAL_SEQ));
+        assert!(s
+            .socket
+            .accepts_icmp_error(&mut s.cx, LOCAL_END, REMOTE_END, LOCAL_SEQ));
+        s.socket
+            .process_icmp_error(&mut s.cx, IcmpError::PortUnreachable);
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(
+            s.disconnect_reason(),
+            Some(DisconnectReason::Icmp(IcmpError::PortUnreachable))
+        );
+    }
+
+    #[test]
+    fn test_syn_sent_icmp_soft_error() {
+        let mut s = socket_syn_sent();
+        s.remote_last_seq = LOCAL_SEQ + 1;
+        s.socket
+            .process_icmp_error(&mut s.cx, IcmpError::HostUnreachable);
+        assert_eq!(s.state, State::SynSent);
+        assert_eq!(s.icmp_error(), Some(IcmpError::HostUnreachable));
+        assert_eq!(s.disconnect_reason(), None);
+    }
+
+    #[test]
+    fn test_established_icmp_error() {
+        let mut s = socket_established();
+        s.send_slice(b"abcdef").unwrap();
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                payload: &b"abcdef"[..],
+                ..RECV_TEMPL
+            }]
+        );
+        assert!(!s
+            .socket
+            .accepts_icmp_error(&mut s.cx, LOCAL_END, REMOTE_END, LOCAL_SEQ));
+        assert!(s
+            .socket
+            .accepts_icmp_error(&mut s.cx, LOCAL_END, REMOTE_END, LOCAL_SEQ + 1));
+        s.socket
+            .process_icmp_error(&mut s.cx, IcmpError::PortUnreachable);
+        assert_eq!(s.state, State::Established);
+        assert_eq!(s.icmp_error(), Some(IcmpError::PortUnreachable));
     }
 
     #[test]
@@ -6022,6 +8211,10 @@ mod test {
         }));
         recv!(s, time 205, Err(Error::Exhausted));
         assert_eq!(s.state, State::Closed);
+        assert_eq!(
+            s.disconnect_reason(),
+            Some(DisconnectReason::KeepAliveTimeout)
+        );
     }
 
     #[test]
@@ -6062,12 +8255,76 @@ mod test {
         assert_eq!(s.state, State::Closed);
     }
 
+    #[test]
+    fn test_fin_wait_2_fin_timeout() {
+        let mut s = socket_fin_wait_2();
+        s.set_fin_timeout(Some(Duration::from_millis(1000)));
+        assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
+        recv!(s, time 100, Err(Error::Exhausted));
+        assert_eq!(
+            s.socket.poll_at(&mut s.cx),
+            PollAt::Time(Instant::from_millis(1100))
+        );
+        recv!(s, time 1099, Err(Error::Exhausted));
+        assert_eq!(s.state, State::FinWait2);
+        recv!(s, time 1100, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1 + 1,
+            ack_number: Some(REMOTE_SEQ + 1),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::FinTimeout));
+    }
+
+    #[test]
+    fn test_last_ack_fin_timeout() {
+        let mut s = socket_last_ack();
+        s.set_fin_timeout(Some(Duration::from_millis(1000)));
+        recv!(s, time 100, Ok(TcpRepr {
+            control:    TcpControl::Fin,
+            seq_number: LOCAL_SEQ + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 1),
+            ..RECV_TEMPL
+        }));
+        recv!(s, time 1100, Ok(TcpRepr {
+            control:    TcpControl::Rst,
+            seq_number: LOCAL_SEQ + 1 + 1,
+            ack_number: Some(REMOTE_SEQ + 1 + 1),
+            ..RECV_TEMPL
+        }));
+        assert_eq!(s.state, State::Closed);
+    }
+
+    #[test]
+    fn test_disconnect_reason_cleared() {
+        let mut s = socket_established();
+        s.abort();
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Aborted));
+        s.listen(LOCAL_END).unwrap();
+        assert_eq!(s.disconnect_reason(), None);
+
+        // Closing gracefully leaves no reason.
+        let mut s = socket_last_ack();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 1,
+                ack_number: Some(LOCAL_SEQ + 1 + 1),
+                ..SEND_TEMPL
+            }
+        );
+        assert_eq!(s.state, State::Closed);
+        assert_eq!(s.disconnect_reason(), None);
+    }
+
     #[test]
     fn test_closed_timeout() {
         let mut s = socket_established();
         s.set_timeout(Some(Duration::from_millis(200)));
         s.remote_last_ts = Some(Instant::from_millis(100));
         s.abort();
+        assert_eq!(s.disconnect_reason(), Some(DisconnectReason::Aborted));
         assert_eq!(s.socket.poll_at(&mut s.cx), PollAt::Now);
         recv!(s, time 100, Ok(TcpRepr {
             control:    TcpControl::Rst,
@@ -6234,6 +8491,46 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_out_of_order_ack_piggybacked() {
+        let mut s = socket_established();
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1 + 3,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"def"[..],
+                ..SEND_TEMPL
+            },
+            Some(TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1),
+                ..RECV_TEMPL
+            })
+        );
+        s.send_slice(b"xyz").unwrap();
+        // Filling the hole is acknowledged by the data segment, not by a separate ACK.
+        send!(
+            s,
+            TcpRepr {
+                seq_number: REMOTE_SEQ + 1,
+                ack_number: Some(LOCAL_SEQ + 1),
+                payload: &b"abc"[..],
+                ..SEND_TEMPL
+            }
+        );
+        recv!(
+            s,
+            [TcpRepr {
+                seq_number: LOCAL_SEQ + 1,
+                ack_number: Some(REMOTE_SEQ + 1 + 6),
+                window_len: 58,
+                payload: &b"xyz"[..],
+                ..RECV_TEMPL
+            }]
+        );
+    }
+
     #[test]
     fn test_buffer_wraparound_rx() {
         let mut s = socket_established();
@@ -6410,7 +8707,7 @@ mod test {
             s,
             TcpRepr {
                 control: TcpControl::Rst,
-                seq_number: REMOTE_SEQ + 1 + 9,
+                seq_number: REMOTE_SEQ + 1 + 3,
                 ack_number: Some(LOCAL_SEQ + 1),
                 ..SEND_TEMPL
             }
@@ -6480,7 +8777,7 @@ mod test {
             s,
             TcpRepr {
                 control: TcpControl::Rst,
-                seq_number: REMOTE_SEQ + 1 + 9,
+                seq_number: REMOTE_SEQ + 1 + 3,
                 ack_number: Some(LOCAL_SEQ + 1),
                 ..SEND_TEMPL
             }
@@ -6838,6 +9135,137 @@ mod test {
         assert_eq!(r.should_retransmit(Instant::from_millis(1350)), None);
     }
 
+    #[test]
+    fn test_retransmit_queue() {
+        let mut q = RetransmitQueue::new(vec![SegmentMetadata::EMPTY; 3]);
+        let seq = |n| TcpSeqNumber(n);
+        q.on_send(seq(0), 3, Instant::from_millis(0));
+        q.on_send(seq(3), 3, Instant::from_millis(10));
+        q.on_send(seq(6), 3, Instant::from_millis(20));
+        assert!(q.is_exhausted(seq(9)));
+        assert!(!q.is_exhausted(seq(3)));
+
+        // The first segment is acknowledged, and the second one partially.
+        assert_eq!(
+            q.on_ack(seq(4), Instant::from_millis(50)),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(q.get(seq(4)).map(|segment| segment.len), Some(2));
+
+        // Part of the second segment is retransmitted, the rest is tracked separately.
+        q.on_send(seq(4), 1, Instant::from_millis(60));
+        assert_eq!(q.get(seq(4)).map(|segment| segment.len), Some(1));
+        assert_eq!(q.get(seq(5)).map(|segment| segment.len), Some(1));
+
+        // Retransmitted segments are not sampled.
+        assert_eq!(
+            q.on_ack(seq(9), Instant::from_millis(80)),
+            Some(Duration::from_millis(60))
+        );
+        assert!(q.segments().is_empty());
+    }
+
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+    fn happy_eyeballs() -> (
+        HappyEyeballs,
+        SocketSet<'static>,
+        SocketHandle,
+        SocketHandle,
+    ) {
+        let mut sockets = SocketSet::new(vec![]);
+        let ipv6 = sockets.add(socket().socket);
+        let ipv4 = sockets.add(socket().socket);
+        let race = HappyEyeballs::new(
+            LOCAL_PORT,
+            Some((
+                ipv6,
+                IpEndpoint::new(
+                    Ipv6Address([0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]).into(),
+                    REMOTE_PORT,
+                ),
+            )),
+            Some((
+                ipv4,
+                IpEndpoint::new(Ipv4Address([192, 168, 1, 2]).into(), REMOTE_PORT),
+            )),
+        );
+        (race, sockets, ipv6, ipv4)
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+    fn test_happy_eyeballs_ipv4_wins() {
+        let (mut race, mut sockets, ipv6, ipv4) = happy_eyeballs();
+        let mut cx = Context::mock();
+
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Connecting)
+        );
+        assert_eq!(sockets.get::<Socket>(ipv6).state(), State::SynSent);
+        assert_eq!(sockets.get::<Socket>(ipv4).state(), State::Closed);
+        assert_eq!(race.poll_at(), Some(Instant::from_millis(250)));
+
+        cx.set_now(Instant::from_millis(250));
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Connecting)
+        );
+        assert_eq!(sockets.get::<Socket>(ipv4).state(), State::SynSent);
+        assert_eq!(race.poll_at(), None);
+
+        sockets
+            .get_mut::<Socket>(ipv4)
+            .set_state(State::Established);
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Connected(ipv4))
+        );
+        assert_eq!(sockets.get::<Socket>(ipv6).state(), State::Closed);
+        assert_eq!(sockets.get::<Socket>(ipv4).state(), State::Established);
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+    fn test_happy_eyeballs_ipv6_fails() {
+        let (mut race, mut sockets, ipv6, ipv4) = happy_eyeballs();
+        let mut cx = Context::mock();
+
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Connecting)
+        );
+
+        // The IPv4 attempt starts as soon as the IPv6 attempt fails.
+        cx.set_now(Instant::from_millis(10));
+        sockets.get_mut::<Socket>(ipv6).set_state(State::Closed);
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Connecting)
+        );
+        assert_eq!(sockets.get::<Socket>(ipv4).state(), State::SynSent);
+
+        sockets.get_mut::<Socket>(ipv4).set_state(State::Closed);
+        assert_eq!(
+            race.poll(&mut cx, &mut sockets),
+            Ok(HappyEyeballsStatus::Failed)
+        );
+    }
+
+    #[test]
+    fn test_retransmit_queue_sacked() {
+        let mut q = RetransmitQueue::new(vec![SegmentMetadata::EMPTY; 3]);
+        let seq = |n| TcpSeqNumber(n);
+        q.on_send(seq(0), 3, Instant::ZERO);
+        q.on_send(seq(3), 3, Instant::ZERO);
+        q.on_send(seq(6), 3, Instant::ZERO);
+        q.on_sack(&[Some((3, 7)), None, None]);
+        assert_eq!(q.skip_sacked(seq(0)), seq(0));
+        assert_eq!(q.skip_sacked(seq(3)), seq(6));
+        q.clear_sacked();
+        assert_eq!(q.skip_sacked(seq(3)), seq(3));
+    }
+
     #[test]
     fn test_rtt_estimator() {
         let mut r = RttEstimator::default();
@@ -6852,4 +9280,391 @@ mod test {
             assert_eq!(r.retransmission_timeout(), Duration::from_millis(rto));
         }
     }
+
+    // =========================================================================================//
+    // Table-driven tests of the state transitions of RFC 793 and RFC 1122.
+    // =========================================================================================//
+
+    /// A segment, with its sequence and acknowledgement numbers relative to the initial
+    /// sequence numbers of its sender and of its receiver, and its payload length.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Seg {
+        control: TcpControl,
+        seq: i32,
+        ack: Option<i32>,
+        len: usize,
+    }
+
+    fn seg(control: TcpControl, seq: i32, ack: Option<i32>, len: usize) -> Seg {
+        Seg {
+            control,
+            seq,
+            ack,
+            len,
+        }
+    }
+
+    /// A step of a [Scenario].
+    enum Step {
+        /// Call the application interface of the socket.
+        Call(fn(&mut TestSocket)),
+        /// Advance the clock to the given number of milliseconds.
+        Time(u64),
+        /// Receive a segment from the remote endpoint, and check the immediate reply.
+        Send(Seg, Option<Seg>),
+        /// Check that the socket does not accept a segment, which the interface then
+        /// answers with a RST.
+        Reject(Seg),
+        /// Check that the socket transmits exactly the given segments.
+        Recv(Vec<Seg>),
+        /// Check the state of the socket.
+        Expect(State),
+    }
+
+    /// A sequence of steps starting from a socket, named after the transitions it covers.
+    struct Scenario {
+        name: &'static str,
+        socket: fn() -> TestSocket,
+        steps: Vec<Step>,
+    }
+
+    fn run_scenario(scenario: &Scenario) {
+        let mut s = (scenario.socket)();
+        let mut time = 0;
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let at = format!("{}, step {}", scenario.name, index);
+            match step {
+                Step::Call(f) => f(&mut s),
+                Step::Time(millis) => time = *millis,
+                Step::Send(segment, reply) => {
+                    let repr = remote_repr(segment);
+                    let actual = send(&mut s, Instant::from_millis(time as i64), &repr)
+                        .map(|repr| local_seg(&repr));
+                    assert_eq!(actual, *reply, "{}: reply", at);
+                }
+                Step::Reject(segment) => {
+                    let repr = remote_repr(segment);
+                    let ip_repr = IpReprIpvX(IpvXRepr {
+                        src_addr: REMOTE_ADDR,
+                        dst_addr: LOCAL_ADDR,
+                        next_header: IpProtocol::Tcp,
+                        payload_len: repr.buffer_len(),
+                        hop_limit: 64,
+                    });
+                    s.cx.set_now(Instant::from_millis(time as i64));
+                    assert!(
+                        !s.socket.accepts(&mut s.cx, &ip_repr, &repr),
+                        "{}: accepted",
+                        at
+                    );
+                }
+                Step::Recv(segments) => {
+                    let mut actual = Vec::new();
+                    for _ in 0..=segments.len() {
+                        recv(&mut s, Instant::from_millis(time as i64), |result| {
+                            if let Ok(repr) = result {
+                                actual.push(local_seg(&repr))
+                            }
+                        });
+                    }
+                    assert_eq!(actual, *segments, "{}: transmitted", at);
+                }
+                Step::Expect(state) => assert_eq!(s.state, *state, "{}: state", at),
+            }
+        }
+    }
+
+    fn remote_repr(segment: &Seg) -> TcpRepr<'static> {
+        static PAYLOAD: [u8; 32] = [0xaa; 32];
+
+        TcpRepr {
+            control: segment.control,
+            seq_number: TcpSeqNumber(REMOTE_SEQ.0.wrapping_add(segment.seq)),
+            ack_number: segment
+                .ack
+                .map(|ack| TcpSeqNumber(LOCAL_SEQ.0.wrapping_add(ack))),
+            payload: &PAYLOAD[..segment.len],
+            ..SEND_TEMPL
+        }
+    }
+
+    fn local_seg(repr: &TcpRepr) -> Seg {
+        Seg {
+            control: repr.control.quash_psh(),
+            seq: repr.seq_number.0.wrapping_sub(LOCAL_SEQ.0),
+            ack: repr.ack_number.map(|ack| ack.0.wrapping_sub(REMOTE_SEQ.0)),
+            len: repr.payload.len(),
+        }
+    }
+
+    fn connect(s: &mut TestSocket) {
+        s.local_seq_no = LOCAL_SEQ;
+        s.socket
+            .connect(&mut s.cx, REMOTE_END, LOCAL_END.port)
+            .unwrap();
+    }
+
+    fn scenarios() -> Vec<Scenario> {
+        use self::Step::*;
+        use crate::wire::TcpControl::{Fin, None as Ack, Rst, Syn};
+
+        vec![
+            Scenario {
+                name: "CLOSED -> LISTEN -> CLOSED",
+                socket,
+                steps: vec![
+                    Call(|s| s.listen(LISTEN_END).unwrap()),
+                    Expect(State::Listen),
+                    Recv(vec![]),
+                    Call(|s| s.close()),
+                    Expect(State::Closed),
+                ],
+            },
+            Scenario {
+                name: "LISTEN -> SYN-RECEIVED -> ESTABLISHED (passive open)",
+                socket: socket_listen,
+                steps: vec![
+                    Send(seg(Syn, 0, None, 0), None),
+                    Expect(State::SynReceived),
+                    Recv(vec![seg(Syn, 0, Some(1), 0)]),
+                    Send(seg(Ack, 1, Some(1), 0), None),
+                    Expect(State::Established),
+                ],
+            },
+            Scenario {
+                name: "LISTEN ignores RST",
+                socket: socket_listen,
+                steps: vec![
+                    Send(seg(Rst, 0, None, 0), None),
+                    Expect(State::Listen),
+                    Recv(vec![]),
+                ],
+            },
+            Scenario {
+                name: "LISTEN rejects ACK",
+                socket: socket_listen,
+                steps: vec![
+                    Reject(seg(Ack, 0, Some(1), 0)),
+                    Reject(seg(Syn, 0, Some(1), 0)),
+                    Expect(State::Listen),
+                ],
+            },
+            Scenario {
+                name: "CLOSED -> SYN-SENT -> ESTABLISHED (active open)",
+                socket,
+                steps: vec![
+                    Call(connect),
+                    Expect(State::SynSent),
+                    Recv(vec![seg(Syn, 0, None, 0)]),
+                    Send(seg(Syn, 0, Some(1), 0), None),
+                    Expect(State::Established),
+                    Recv(vec![seg(Ack, 1, Some(1), 0)]),
+                ],
+            },
+            Scenario {
+                name: "SYN-SENT -> SYN-RECEIVED -> ESTABLISHED (simultaneous open)",
+                socket: socket_syn_sent,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, None, 0)]),
+                    Send(seg(Syn, 0, None, 0), None),
+                    Expect(State::SynReceived),
+                    Recv(vec![seg(Syn, 0, Some(1), 0)]),
+                    Send(seg(Ack, 1, Some(1), 0), None),
+                    Expect(State::Established),
+                ],
+            },
+            Scenario {
+                name: "SYN-SENT -> CLOSED (close)",
+                socket: socket_syn_sent,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, None, 0)]),
+                    Call(|s| s.close()),
+                    Expect(State::Closed),
+                    // The remote endpoint may have received the SYN. The RST acknowledges
+                    // the initial, unsynchronized, remote sequence number.
+                    Recv(vec![seg(Rst, 1, Some(REMOTE_SEQ.0.wrapping_neg()), 0)]),
+                ],
+            },
+            Scenario {
+                name: "SYN-SENT -> CLOSED (RST)",
+                socket: socket_syn_sent,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, None, 0)]),
+                    Send(seg(Rst, 0, Some(1), 0), None),
+                    Expect(State::Closed),
+                ],
+            },
+            Scenario {
+                name: "SYN-SENT rejects unacceptable ACK with RST",
+                socket: socket_syn_sent,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, None, 0)]),
+                    Send(seg(Syn, 0, Some(10), 0), Some(seg(Rst, 10, None, 0))),
+                    Expect(State::SynSent),
+                ],
+            },
+            Scenario {
+                name: "SYN-RECEIVED -> LISTEN (RST after passive open)",
+                socket: socket_listen,
+                steps: vec![
+                    Send(seg(Syn, 0, None, 0), None),
+                    Recv(vec![seg(Syn, 0, Some(1), 0)]),
+                    Send(seg(Rst, 1, None, 0), None),
+                    Expect(State::Listen),
+                ],
+            },
+            Scenario {
+                name: "SYN-RECEIVED -> FIN-WAIT-1 (close)",
+                socket: socket_syn_received,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, Some(1), 0)]),
+                    Call(|s| s.close()),
+                    Expect(State::FinWait1),
+                    // The FIN is not sent before the SYN is acknowledged.
+                    Recv(vec![]),
+                ],
+            },
+            Scenario {
+                name: "SYN-RECEIVED -> CLOSE-WAIT (FIN)",
+                socket: socket_syn_received,
+                steps: vec![
+                    Recv(vec![seg(Syn, 0, Some(1), 0)]),
+                    Send(seg(Fin, 1, Some(1), 0), None),
+                    Expect(State::CloseWait),
+                    Recv(vec![seg(Ack, 1, Some(2), 0)]),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED -> FIN-WAIT-1 -> FIN-WAIT-2 -> TIME-WAIT -> CLOSED",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| s.close()),
+                    Expect(State::FinWait1),
+                    Recv(vec![seg(Fin, 1, Some(1), 0)]),
+                    Send(seg(Ack, 1, Some(2), 0), None),
+                    Expect(State::FinWait2),
+                    Send(seg(Fin, 1, Some(2), 0), None),
+                    Expect(State::TimeWait),
+                    Recv(vec![seg(Ack, 2, Some(2), 0)]),
+                    Time(CLOSE_DELAY.total_millis()),
+                    Recv(vec![]),
+                    Expect(State::Closed),
+                ],
+            },
+            Scenario {
+                name: "FIN-WAIT-1 -> CLOSING -> TIME-WAIT (simultaneous close)",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| s.close()),
+                    Recv(vec![seg(Fin, 1, Some(1), 0)]),
+                    Send(seg(Fin, 1, Some(1), 0), None),
+                    Expect(State::Closing),
+                    Recv(vec![seg(Ack, 2, Some(2), 0)]),
+                    Send(seg(Ack, 2, Some(2), 0), None),
+                    Expect(State::TimeWait),
+                ],
+            },
+            Scenario {
+                name: "FIN-WAIT-1 -> TIME-WAIT (FIN acknowledging our FIN)",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| s.close()),
+                    Recv(vec![seg(Fin, 1, Some(1), 0)]),
+                    Send(seg(Fin, 1, Some(2), 0), None),
+                    Expect(State::TimeWait),
+                    Recv(vec![seg(Ack, 2, Some(2), 0)]),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED -> CLOSE-WAIT -> LAST-ACK -> CLOSED",
+                socket: socket_established,
+                steps: vec![
+                    Send(seg(Fin, 1, Some(1), 0), None),
+                    Expect(State::CloseWait),
+                    Recv(vec![seg(Ack, 1, Some(2), 0)]),
+                    Call(|s| s.close()),
+                    Expect(State::LastAck),
+                    Recv(vec![seg(Fin, 1, Some(2), 0)]),
+                    Send(seg(Ack, 2, Some(2), 0), None),
+                    Expect(State::Closed),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED -> CLOSED (RST)",
+                socket: socket_established,
+                steps: vec![
+                    Send(seg(Rst, 1, None, 0), None),
+                    Expect(State::Closed),
+                    Recv(vec![]),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED -> CLOSED (abort)",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| s.abort()),
+                    Expect(State::Closed),
+                    Recv(vec![seg(Rst, 1, Some(1), 0)]),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED acknowledges data and unacceptable segments",
+                socket: socket_established,
+                steps: vec![
+                    Send(seg(Ack, 1, Some(1), 6), None),
+                    Recv(vec![seg(Ack, 1, Some(7), 0)]),
+                    // RFC 793 p. 69: an unacceptable segment is answered with an ACK.
+                    Send(seg(Ack, 1000, Some(1), 6), Some(seg(Ack, 1, Some(7), 0))),
+                    Expect(State::Established),
+                ],
+            },
+            Scenario {
+                name: "ESTABLISHED sends data and retransmits it",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| {
+                        s.send_slice(b"abcdef").unwrap();
+                    }),
+                    Recv(vec![seg(Ack, 1, Some(1), 6)]),
+                    Time(1000),
+                    Recv(vec![seg(Ack, 1, Some(1), 6)]),
+                    Send(seg(Ack, 1, Some(7), 0), None),
+                    Recv(vec![]),
+                    Expect(State::Established),
+                ],
+            },
+            Scenario {
+                name: "CLOSE-WAIT -> CLOSED (RST)",
+                socket: socket_close_wait,
+                steps: vec![Send(seg(Rst, 2, None, 0), None), Expect(State::Closed)],
+            },
+            Scenario {
+                name: "TIME-WAIT acknowledges a retransmitted FIN",
+                socket: socket_established,
+                steps: vec![
+                    Call(|s| s.close()),
+                    Recv(vec![seg(Fin, 1, Some(1), 0)]),
+                    Send(seg(Fin, 1, Some(2), 0), None),
+                    Recv(vec![seg(Ack, 2, Some(2), 0)]),
+                    Time(1000),
+                    Send(seg(Fin, 1, Some(2), 0), Some(seg(Ack, 2, Some(2), 0))),
+                    Expect(State::TimeWait),
+                    // The retransmitted FIN restarts the 2 MSL timer.
+                    Time(CLOSE_DELAY.total_millis()),
+                    Recv(vec![]),
+                    Expect(State::TimeWait),
+                    Time(1000 + CLOSE_DELAY.total_millis()),
+                    Recv(vec![]),
+                    Expect(State::Closed),
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_state_machine_scenarios() {
+        for scenario in scenarios().iter() {
+            run_scenario(scenario);
+        }
+    }
 }
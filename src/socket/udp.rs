@@ -3,17 +3,61 @@ use core::cmp::min;
 use core::task::Waker;
 
 use crate::iface::Context;
-use crate::socket::PollAt;
 #[cfg(feature = "async")]
 use crate::socket::WakerRegistration;
+use crate::socket::{BufferEvent, IcmpError, PollAt};
 use crate::storage::Empty;
-use crate::wire::{IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+use crate::time::{Duration, Instant};
+use crate::wire::{IpAddress, IpEndpoint, IpListenEndpoint, IpProtocol, IpRepr, UdpRepr};
+
+/// Metadata of a UDP datagram: its remote endpoint, and ancillary data.
+///
+/// Received datagrams carry the address they were sent to and their hop limit, which
+/// tell apart datagrams sent to one of several local addresses, or to a broadcast or
+/// multicast address. Passing the metadata of a received datagram to [Socket::send]
+/// replies to its source, from the address it was sent to if that is a unicast address.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UdpMetadata {
+    /// The source endpoint of a received datagram, or the destination endpoint of
+    /// a datagram to send.
+    pub endpoint: IpEndpoint,
+    /// The destination address of a received datagram, or the source address of
+    /// a datagram to send.
+    ///
+    /// When sending, an address that is not one of the unicast addresses of the
+    /// interface is ignored, and the source address is selected as if it were `None`.
+    /// The address the socket is bound to, if any, takes precedence.
+    pub local_address: Option<IpAddress>,
+    /// The hop limit (IPv4 time-to-live) of a received datagram. Ignored when sending.
+    pub hop_limit: Option<u8>,
+    /// Whether a received datagram was sent to a broadcast address, including the subnet
+    /// broadcast address of an IPv4 address of the interface. Ignored when sending.
+    pub broadcast: bool,
+}
+
+impl From<IpEndpoint> for UdpMetadata {
+    fn from(endpoint: IpEndpoint) -> UdpMetadata {
+        UdpMetadata {
+            endpoint,
+            local_address: None,
+            hop_limit: None,
+            broadcast: false,
+        }
+    }
+}
+
+impl core::fmt::Display for UdpMetadata {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.endpoint)
+    }
+}
 
 /// A UDP packet metadata.
-pub type PacketMetadata = crate::storage::PacketMetadata<IpEndpoint>;
+pub type PacketMetadata = crate::storage::PacketMetadata<UdpMetadata>;
 
 /// A UDP packet ring buffer.
-pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, IpEndpoint>;
+pub type PacketBuffer<'a> = crate::storage::PacketBuffer<'a, UdpMetadata>;
 
 /// Error returned by [`Socket::bind`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -49,6 +93,25 @@ pub struct Socket<'a> {
     tx_buffer: PacketBuffer<'a>,
     /// The time-to-live (IPv4) or hop limit (IPv6) value used in outgoing packets.
     hop_limit: Option<u8>,
+    /// The UDP-Lite checksum coverage; if `None`, the socket uses plain UDP.
+    #[cfg(feature = "proto-udplite")]
+    checksum_coverage: Option<u16>,
+    /// Duration without incoming datagrams after which the socket is idle.
+    idle_timeout: Option<Duration>,
+    /// Time at which the socket becomes idle, if the idle timer is running.
+    idle_at: Option<Instant>,
+    idle: bool,
+    reuse_addr: bool,
+    /// The index of the interface the socket is bound to, if any.
+    bound_interface: Option<u32>,
+    /// Amount of payload in the receive buffer above which the socket is readable.
+    recv_high_watermark: Option<usize>,
+    /// Amount of payload in the transmit buffer below which the socket is writable.
+    send_low_watermark: Option<usize>,
+    buffer_callback: Option<fn(BufferEvent)>,
+    /// The last ICMP error received about a datagram sent by the socket, and the remote
+    /// endpoint of that datagram.
+    icmp_error: Option<(IcmpError, IpEndpoint)>,
     #[cfg(feature = "async")]
     rx_waker: WakerRegistration,
     #[cfg(feature = "async")]
@@ -63,6 +126,17 @@ impl<'a> Socket<'a> {
             rx_buffer,
             tx_buffer,
             hop_limit: None,
+            #[cfg(feature = "proto-udplite")]
+            checksum_coverage: None,
+            idle_timeout: None,
+            idle_at: None,
+            idle: false,
+            reuse_addr: false,
+            bound_interface: None,
+            recv_high_watermark: None,
+            send_low_watermark: None,
+            buffer_callback: None,
+            icmp_error: None,
             #[cfg(feature = "async")]
             rx_waker: WakerRegistration::new(),
             #[cfg(feature = "async")]
@@ -138,6 +212,193 @@ impl<'a> Socket<'a> {
         self.hop_limit = hop_limit
     }
 
+    /// Return the UDP-Lite checksum coverage of outgoing packets, or `None` if the socket
+    /// uses plain UDP.
+    ///
+    /// See also the [set_checksum_coverage](#method.set_checksum_coverage) method
+    #[cfg(feature = "proto-udplite")]
+    pub fn checksum_coverage(&self) -> Option<u16> {
+        self.checksum_coverage
+    }
+
+    /// Set the UDP-Lite checksum coverage of outgoing packets.
+    ///
+    /// A socket with a checksum coverage set sends and receives UDP-Lite packets
+    /// ([RFC 3828]) instead of UDP packets. Only the first `coverage` octets of
+    /// outgoing packets, including the 8-octet header, are protected by the checksum,
+    /// so that corruption of the rest of the payload does not cause the packet to be dropped.
+    /// A coverage of 0, or one that exceeds the length of a packet, covers the entire packet.
+    /// Incoming packets are accepted regardless of the coverage they were sent with.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a coverage smaller than the header length, other than 0, is given.
+    ///
+    /// [RFC 3828]: https://tools.ietf.org/html/rfc3828
+    #[cfg(feature = "proto-udplite")]
+    pub fn set_checksum_coverage(&mut self, coverage: Option<u16>) {
+        if let Some(1..=7) = coverage {
+            panic!("the checksum coverage must include the UDP-Lite header")
+        }
+
+        self.checksum_coverage = coverage
+    }
+
+    /// Return the idle timeout.
+    ///
+    /// See also the [set_idle_timeout](#method.set_idle_timeout) method.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Set the idle timeout.
+    ///
+    /// An open socket with an idle timeout set becomes idle when no datagram has been
+    /// received for that duration, starting from the first poll after it was set or the
+    /// socket was bound. The receive waker is woken when that happens, and the socket stays
+    /// idle until the next datagram is received. This makes it easy to detect a peer that
+    /// stopped sending heartbeats.
+    ///
+    /// See also the [is_idle](#method.is_idle) method.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+        self.idle_at = None;
+        self.idle = false;
+    }
+
+    /// Check whether no datagram has been received for the idle timeout.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Return whether address reuse is enabled.
+    ///
+    /// See also the [set_reuse_addr](#method.set_reuse_addr) method.
+    pub fn reuse_addr(&self) -> bool {
+        self.reuse_addr
+    }
+
+    /// Enable or disable address reuse, the equivalent of `SO_REUSEADDR`.
+    ///
+    /// Datagrams sent to a broadcast or multicast address are delivered to every socket
+    /// bound to their port with address reuse enabled, instead of only the first one, so
+    /// that several sockets can receive the same multicast group. Unicast datagrams are
+    /// always delivered to a single socket.
+    pub fn set_reuse_addr(&mut self, enabled: bool) {
+        self.reuse_addr = enabled
+    }
+
+    /// Return the index of the interface the socket is bound to.
+    ///
+    /// See also the [set_bound_interface](#method.set_bound_interface) method.
+    pub fn bound_interface(&self) -> Option<u32> {
+        self.bound_interface
+    }
+
+    /// Bind the socket to the interface with the given index, the equivalent of
+    /// `SO_BINDTODEVICE`, or unbind it with `None`.
+    ///
+    /// A socket bound to an interface only receives and sends datagrams when polled by
+    /// that interface. See [InterfaceBuilder::index].
+    ///
+    /// [InterfaceBuilder::index]: crate::iface::InterfaceBuilder::index
+    pub fn set_bound_interface(&mut self, index: Option<u32>) {
+        self.bound_interface = index
+    }
+
+    /// Return the receive buffer high watermark, in octets of payload.
+    ///
+    /// See also the [set_recv_high_watermark](#method.set_recv_high_watermark) method.
+    pub fn recv_high_watermark(&self) -> usize {
+        self.recv_high_watermark
+            .unwrap_or(1)
+            .min(self.rx_buffer.payload_capacity())
+    }
+
+    /// Set the receive buffer high watermark, in octets of payload, or reset it with `None`.
+    ///
+    /// Arriving datagrams only wake the receive waker and report [BufferEvent::Readable] to
+    /// the buffer callback once the receive buffer holds at least this many octets. The
+    /// payload buffer is a ring, so the octets left unused at its end when a datagram wraps
+    /// around count as well.
+    ///
+    /// A watermark larger than the payload buffer is treated as its capacity.
+    /// By default, the high watermark is one octet.
+    pub fn set_recv_high_watermark(&mut self, watermark: Option<usize>) {
+        self.recv_high_watermark = watermark
+    }
+
+    /// Return the transmit buffer low watermark, in octets of payload.
+    ///
+    /// See also the [set_send_low_watermark](#method.set_send_low_watermark) method.
+    pub fn send_low_watermark(&self) -> usize {
+        self.send_low_watermark
+            .unwrap_or(usize::MAX)
+            .min(self.tx_buffer.payload_capacity())
+    }
+
+    /// Set the transmit buffer low watermark, in octets of payload, or reset it with `None`.
+    ///
+    /// Sent datagrams only wake the send waker and report [BufferEvent::Writable] to the
+    /// buffer callback once the transmit buffer holds fewer than this many octets.
+    ///
+    /// A watermark larger than the payload buffer is treated as its capacity.
+    /// By default, the low watermark is the capacity of the payload buffer.
+    pub fn set_send_low_watermark(&mut self, watermark: Option<usize>) {
+        self.send_low_watermark = watermark
+    }
+
+    /// Set a function to be called when the fill level of a buffer crosses its watermark.
+    ///
+    /// The function is called with [BufferEvent::Readable] whenever a datagram arrives while
+    /// the receive buffer is at or above its high watermark, and with [BufferEvent::Writable]
+    /// whenever a datagram is sent while the transmit buffer is below its low watermark.
+    /// It is called at the same points the receive and send wakers are woken.
+    pub fn set_buffer_callback(&mut self, callback: Option<fn(BufferEvent)>) {
+        self.buffer_callback = callback;
+    }
+
+    /// Wake the receive waker and notify the buffer callback if the receive buffer
+    /// reached its high watermark.
+    fn notify_readable(&mut self) {
+        if self.rx_buffer.payload_len() < self.recv_high_watermark() {
+            return;
+        }
+
+        #[cfg(feature = "async")]
+        self.rx_waker.wake();
+        if let Some(callback) = self.buffer_callback {
+            callback(BufferEvent::Readable)
+        }
+    }
+
+    /// Wake the send waker and notify the buffer callback if the transmit buffer
+    /// drained below its low watermark.
+    fn notify_writable(&mut self) {
+        if self.tx_buffer.payload_len() >= self.send_low_watermark() {
+            return;
+        }
+
+        #[cfg(feature = "async")]
+        self.tx_waker.wake();
+        if let Some(callback) = self.buffer_callback {
+            callback(BufferEvent::Writable)
+        }
+    }
+
+    fn is_serviced_by(&self, cx: &Context) -> bool {
+        !matches!(self.bound_interface, Some(index) if index != cx.index())
+    }
+
+    fn protocol(&self) -> IpProtocol {
+        #[cfg(feature = "proto-udplite")]
+        if self.checksum_coverage.is_some() {
+            return IpProtocol::UdpLite;
+        }
+        IpProtocol::Udp
+    }
+
     /// Bind the socket to the given endpoint.
     ///
     /// This function returns `Err(Error::Illegal)` if the socket was open
@@ -154,6 +415,8 @@ impl<'a> Socket<'a> {
         }
 
         self.endpoint = endpoint;
+        self.idle_at = None;
+        self.idle = false;
 
         #[cfg(feature = "async")]
         {
@@ -173,6 +436,10 @@ impl<'a> Socket<'a> {
         self.tx_buffer.reset();
         self.rx_buffer.reset();
 
+        self.idle_at = None;
+        self.idle = false;
+        self.icmp_error = None;
+
         #[cfg(feature = "async")]
         {
             self.rx_waker.wake();
@@ -198,6 +465,23 @@ impl<'a> Socket<'a> {
         !self.rx_buffer.is_empty()
     }
 
+    /// Check whether the receive buffer holds at least as many octets as its high watermark.
+    ///
+    /// See also the [set_recv_high_watermark](#method.set_recv_high_watermark) method.
+    #[inline]
+    pub fn is_readable(&self) -> bool {
+        self.can_recv() && self.rx_buffer.payload_len() >= self.recv_high_watermark()
+    }
+
+    /// Check whether the transmit buffer is not full, and holds fewer octets than its
+    /// low watermark.
+    ///
+    /// See also the [set_send_low_watermark](#method.set_send_low_watermark) method.
+    #[inline]
+    pub fn is_writable(&self) -> bool {
+        self.can_send() && self.tx_buffer.payload_len() < self.send_low_watermark()
+    }
+
     /// Return the maximum number packets the socket can receive.
     #[inline]
     pub fn packet_recv_capacity(&self) -> usize {
@@ -232,32 +516,38 @@ impl<'a> Socket<'a> {
     pub fn send(
         &mut self,
         size: usize,
-        remote_endpoint: IpEndpoint,
+        meta: impl Into<UdpMetadata>,
     ) -> Result<&mut [u8], SendError> {
-        if self.endpoint.port == 0 {
-            return Err(SendError::Unaddressable);
-        }
-        if remote_endpoint.addr.is_unspecified() {
-            return Err(SendError::Unaddressable);
-        }
-        if remote_endpoint.port == 0 {
-            return Err(SendError::Unaddressable);
-        }
+        let meta = meta.into();
+        self.check_send(&meta)?;
 
         let payload_buf = self
             .tx_buffer
-            .enqueue(size, remote_endpoint)
+            .enqueue(size, meta)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
             "udp:{}:{}: buffer to send {} octets",
             self.endpoint,
-            remote_endpoint,
+            meta.endpoint,
             size
         );
         Ok(payload_buf)
     }
 
+    fn check_send(&self, meta: &UdpMetadata) -> Result<(), SendError> {
+        if self.endpoint.port == 0 {
+            return Err(SendError::Unaddressable);
+        }
+        if meta.endpoint.addr.is_unspecified() {
+            return Err(SendError::Unaddressable);
+        }
+        if meta.endpoint.port == 0 {
+            return Err(SendError::Unaddressable);
+        }
+        Ok(())
+    }
+
     /// Enqueue a packet to be send to a given remote endpoint and pass the buffer
     /// to the provided closure. The closure then returns the size of the data written
     /// into the buffer.
@@ -266,31 +556,24 @@ impl<'a> Socket<'a> {
     pub fn send_with<F>(
         &mut self,
         max_size: usize,
-        remote_endpoint: IpEndpoint,
+        meta: impl Into<UdpMetadata>,
         f: F,
     ) -> Result<usize, SendError>
     where
         F: FnOnce(&mut [u8]) -> usize,
     {
-        if self.endpoint.port == 0 {
-            return Err(SendError::Unaddressable);
-        }
-        if remote_endpoint.addr.is_unspecified() {
-            return Err(SendError::Unaddressable);
-        }
-        if remote_endpoint.port == 0 {
-            return Err(SendError::Unaddressable);
-        }
+        let meta = meta.into();
+        self.check_send(&meta)?;
 
         let size = self
             .tx_buffer
-            .enqueue_with_infallible(max_size, remote_endpoint, f)
+            .enqueue_with_infallible(max_size, meta, f)
             .map_err(|_| SendError::BufferFull)?;
 
         net_trace!(
             "udp:{}:{}: buffer to send {} octets",
             self.endpoint,
-            remote_endpoint,
+            meta.endpoint,
             size
         );
         Ok(size)
@@ -302,10 +585,9 @@ impl<'a> Socket<'a> {
     pub fn send_slice(
         &mut self,
         data: &[u8],
-        remote_endpoint: IpEndpoint,
+        meta: impl Into<UdpMetadata>,
     ) -> Result<(), SendError> {
-        self.send(data.len(), remote_endpoint)?
-            .copy_from_slice(data);
+        self.send(data.len(), meta)?.copy_from_slice(data);
         Ok(())
     }
 
@@ -313,7 +595,7 @@ impl<'a> Socket<'a> {
     /// as a pointer to the payload.
     ///
     /// This function returns `Err(Error::Exhausted)` if the receive buffer is empty.
-    pub fn recv(&mut self) -> Result<(&[u8], IpEndpoint), RecvError> {
+    pub fn recv(&mut self) -> Result<(&[u8], UdpMetadata), RecvError> {
         let (remote_endpoint, payload_buf) =
             self.rx_buffer.dequeue().map_err(|_| RecvError::Exhausted)?;
 
@@ -330,7 +612,7 @@ impl<'a> Socket<'a> {
     /// and return the amount of octets copied as well as the endpoint.
     ///
     /// See also [recv](#method.recv).
-    pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<(usize, IpEndpoint), RecvError> {
+    pub fn recv_slice(&mut self, data: &mut [u8]) -> Result<(usize, UdpMetadata), RecvError> {
         let (buffer, endpoint) = self.recv().map_err(|_| RecvError::Exhausted)?;
         let length = min(data.len(), buffer.len());
         data[..length].copy_from_slice(&buffer[..length]);
@@ -342,7 +624,7 @@ impl<'a> Socket<'a> {
     /// This function otherwise behaves identically to [recv](#method.recv).
     ///
     /// It returns `Err(Error::Exhausted)` if the receive buffer is empty.
-    pub fn peek(&mut self) -> Result<(&[u8], &IpEndpoint), RecvError> {
+    pub fn peek(&mut self) -> Result<(&[u8], &UdpMetadata), RecvError> {
         let endpoint = self.endpoint;
         self.rx_buffer.peek().map_err(|_| RecvError::Exhausted).map(
             |(remote_endpoint, payload_buf)| {
@@ -363,14 +645,52 @@ impl<'a> Socket<'a> {
     /// This function otherwise behaves identically to [recv_slice](#method.recv_slice).
     ///
     /// See also [peek](#method.peek).
-    pub fn peek_slice(&mut self, data: &mut [u8]) -> Result<(usize, &IpEndpoint), RecvError> {
+    pub fn peek_slice(&mut self, data: &mut [u8]) -> Result<(usize, &UdpMetadata), RecvError> {
         let (buffer, endpoint) = self.peek()?;
         let length = min(data.len(), buffer.len());
         data[..length].copy_from_slice(&buffer[..length]);
         Ok((length, endpoint))
     }
 
-    pub(crate) fn accepts(&self, _cx: &mut Context, ip_repr: &IpRepr, repr: &UdpRepr) -> bool {
+    /// Return and clear the last ICMP error received about a datagram sent by the socket,
+    /// along with the remote endpoint the datagram was sent to.
+    ///
+    /// Routers and remote hosts report with an ICMP error that a datagram could not be
+    /// delivered, e.g. because no socket is listening on the remote port. Only the last
+    /// error is kept. The receive waker is woken when an error is received.
+    pub fn take_icmp_error(&mut self) -> Option<(IcmpError, IpEndpoint)> {
+        self.icmp_error.take()
+    }
+
+    /// Query whether an ICMP error quoting a datagram with the given protocol and source
+    /// endpoint is about a datagram sent by the socket.
+    pub(crate) fn accepts_icmp_error(
+        &self,
+        cx: &mut Context,
+        protocol: IpProtocol,
+        local: IpEndpoint,
+    ) -> bool {
+        self.is_serviced_by(cx)
+            && self.protocol() == protocol
+            && self.endpoint.port == local.port
+            && (self.endpoint.addr.is_none() || self.endpoint.addr == Some(local.addr))
+    }
+
+    pub(crate) fn process_icmp_error(&mut self, remote: IpEndpoint, error: IcmpError) {
+        net_trace!("udp:{}:{}: ICMP error: {}", self.endpoint, remote, error);
+        self.icmp_error = Some((error, remote));
+
+        #[cfg(feature = "async")]
+        self.rx_waker.wake();
+    }
+
+    pub(crate) fn accepts(&self, cx: &mut Context, ip_repr: &IpRepr, repr: &UdpRepr) -> bool {
+        if !self.is_serviced_by(cx) {
+            return false;
+        }
+        if self.protocol() != ip_repr.next_header() {
+            return false;
+        }
         if self.endpoint.port != repr.dst_port {
             return false;
         }
@@ -408,7 +728,14 @@ impl<'a> Socket<'a> {
             size
         );
 
-        match self.rx_buffer.enqueue(size, remote_endpoint) {
+        let meta = UdpMetadata {
+            endpoint: remote_endpoint,
+            local_address: Some(ip_repr.dst_addr()),
+            hop_limit: Some(ip_repr.hop_limit()),
+            broadcast: cx.is_broadcast(&ip_repr.dst_addr()),
+        };
+
+        match self.rx_buffer.enqueue(size, meta) {
             Ok(buf) => buf.copy_from_slice(payload),
             Err(_) => net_trace!(
                 "udp:{}:{}: buffer full, dropped incoming packet",
@@ -417,19 +744,45 @@ impl<'a> Socket<'a> {
             ),
         }
 
-        #[cfg(feature = "async")]
-        self.rx_waker.wake();
+        self.idle_at = self.idle_timeout.map(|timeout| cx.now() + timeout);
+        self.idle = false;
+
+        self.notify_readable();
     }
 
     pub(crate) fn dispatch<F, E>(&mut self, cx: &mut Context, emit: F) -> Result<(), E>
     where
         F: FnOnce(&mut Context, (IpRepr, UdpRepr, &[u8])) -> Result<(), E>,
     {
+        if !self.is_serviced_by(cx) {
+            return Ok(());
+        }
+
+        if let (Some(timeout), true) = (self.idle_timeout, self.is_open()) {
+            match self.idle_at {
+                None => self.idle_at = Some(cx.now() + timeout),
+                Some(idle_at) if !self.idle && cx.now() >= idle_at => {
+                    net_trace!("udp:{}: idle", self.endpoint);
+                    self.idle = true;
+
+                    #[cfg(feature = "async")]
+                    self.rx_waker.wake();
+                }
+                Some(_) => (),
+            }
+        }
+
         let endpoint = self.endpoint;
         let hop_limit = self.hop_limit.unwrap_or(64);
-
-        let res = self.tx_buffer.dequeue_with(|remote_endpoint, payload_buf| {
-            let src_addr = match endpoint.addr {
+        let protocol = self.protocol();
+
+        let res = self.tx_buffer.dequeue_with(|meta, payload_buf| {
+            let remote_endpoint = meta.endpoint;
+            let local_address = meta
+                .local_address
+                .filter(|addr| addr.version() == remote_endpoint.addr.version())
+                .filter(|addr| cx.has_ip_addr(*addr));
+            let src_addr = match endpoint.addr.or(local_address) {
                 Some(addr) => addr,
                 None => match cx.get_source_address(remote_endpoint.addr) {
                     Some(addr) => addr,
@@ -458,7 +811,7 @@ impl<'a> Socket<'a> {
             let ip_repr = IpRepr::new(
                 src_addr,
                 remote_endpoint.addr,
-                IpProtocol::Udp,
+                protocol,
                 repr.header_len() + payload_buf.len(),
                 hop_limit,
             );
@@ -468,18 +821,25 @@ impl<'a> Socket<'a> {
             Err(Empty) => Ok(()),
             Ok(Err(e)) => Err(e),
             Ok(Ok(())) => {
-                #[cfg(feature = "async")]
-                self.tx_waker.wake();
+                self.notify_writable();
                 Ok(())
             }
         }
     }
 
-    pub(crate) fn poll_at(&self, _cx: &mut Context) -> PollAt {
-        if self.tx_buffer.is_empty() {
-            PollAt::Ingress
-        } else {
-            PollAt::Now
+    pub(crate) fn poll_at(&self, cx: &mut Context) -> PollAt {
+        if !self.is_serviced_by(cx) {
+            return PollAt::Ingress;
+        }
+        if !self.tx_buffer.is_empty() {
+            return PollAt::Now;
+        }
+
+        match (self.idle_timeout, self.idle_at) {
+            (Some(_), _) if self.idle || !self.is_open() => PollAt::Ingress,
+            (Some(_), None) => PollAt::Now,
+            (Some(_), Some(idle_at)) => PollAt::Time(idle_at),
+            (None, _) => PollAt::Ingress,
         }
     }
 }
@@ -539,6 +899,13 @@ mod test {
         port: REMOTE_PORT,
     };
 
+    const REMOTE_META: UdpMetadata = UdpMetadata {
+        endpoint: REMOTE_END,
+        local_address: Some(LOCAL_ADDR.into_address()),
+        hop_limit: Some(64),
+        broadcast: false,
+    };
+
     pub const LOCAL_IP_REPR: IpRepr = IpReprIpvX(IpvXRepr {
         src_addr: LOCAL_ADDR,
         dst_addr: REMOTE_ADDR,
@@ -687,10 +1054,189 @@ mod test {
         assert!(socket.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
         socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
 
-        assert_eq!(socket.recv(), Ok((&b"abcdef"[..], REMOTE_END)));
+        assert_eq!(socket.recv(), Ok((&b"abcdef"[..], REMOTE_META)));
         assert!(!socket.can_recv());
     }
 
+    #[test]
+    fn test_recv_metadata() {
+        let mut socket = socket(buffer(2), buffer(0));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        let ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::Udp,
+            payload_len: 8 + 6,
+            hop_limit: 3,
+        });
+        socket.process(&mut cx, &ip_repr, &REMOTE_UDP_REPR, PAYLOAD);
+        assert_eq!(
+            socket.recv(),
+            Ok((
+                &b"abcdef"[..],
+                UdpMetadata {
+                    hop_limit: Some(3),
+                    ..REMOTE_META
+                }
+            ))
+        );
+
+        #[cfg(feature = "proto-ipv4")]
+        {
+            let broadcast = IpvXAddress([192, 168, 1, 255]);
+            let ip_repr = IpReprIpvX(IpvXRepr {
+                src_addr: REMOTE_ADDR,
+                dst_addr: broadcast,
+                next_header: IpProtocol::Udp,
+                payload_len: 8 + 6,
+                hop_limit: 64,
+            });
+            assert!(socket.accepts(&mut cx, &ip_repr, &REMOTE_UDP_REPR));
+            socket.process(&mut cx, &ip_repr, &REMOTE_UDP_REPR, PAYLOAD);
+            let (_, meta) = socket.recv().unwrap();
+            assert_eq!(meta.local_address, Some(broadcast.into()));
+            assert!(meta.broadcast);
+        }
+    }
+
+    #[test]
+    fn test_send_metadata() {
+        let mut socket = socket(buffer(0), buffer(2));
+        let mut cx = Context::mock();
+
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        // Replying with the metadata of a received datagram sends from the address
+        // it was sent to, and ignores its hop limit.
+        let reply = UdpMetadata {
+            hop_limit: Some(1),
+            ..REMOTE_META
+        };
+        assert_eq!(socket.send_slice(PAYLOAD, reply), Ok(()));
+        // An address that is not one of the interface is ignored.
+        let other = UdpMetadata {
+            local_address: Some(OTHER_ADDR.into()),
+            ..REMOTE_META
+        };
+        assert_eq!(socket.send_slice(PAYLOAD, other), Ok(()));
+
+        for _ in 0..2 {
+            assert_eq!(
+                socket.dispatch(&mut cx, |_, (ip_repr, udp_repr, payload)| {
+                    assert_eq!(ip_repr, LOCAL_IP_REPR);
+                    assert_eq!(udp_repr, LOCAL_UDP_REPR);
+                    assert_eq!(payload, PAYLOAD);
+                    Ok::<_, Error>(())
+                }),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_idle_timeout() {
+        let mut socket = socket(buffer(1), buffer(0));
+        let mut cx = Context::mock();
+        socket.set_idle_timeout(Some(Duration::from_secs(5)));
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+
+        // The idle timer starts at the first poll.
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Now);
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Time(Instant::from_secs(5)));
+
+        // Receiving a datagram restarts it.
+        cx.set_now(Instant::from_secs(3));
+        socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Time(Instant::from_secs(8)));
+
+        cx.set_now(Instant::from_secs(8));
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+        assert!(socket.is_idle());
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Ingress);
+
+        cx.set_now(Instant::from_secs(9));
+        socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        assert!(!socket.is_idle());
+        assert_eq!(
+            socket.poll_at(&mut cx),
+            PollAt::Time(Instant::from_secs(14))
+        );
+    }
+
+    #[test]
+    fn test_watermarks() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static READABLE: AtomicUsize = AtomicUsize::new(0);
+        static WRITABLE: AtomicUsize = AtomicUsize::new(0);
+        fn callback(event: BufferEvent) {
+            match event {
+                BufferEvent::Readable => READABLE.fetch_add(1, Ordering::SeqCst),
+                BufferEvent::Writable => WRITABLE.fetch_add(1, Ordering::SeqCst),
+            };
+        }
+
+        let mut socket = socket(buffer(3), buffer(3));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(LOCAL_END), Ok(()));
+        assert_eq!(socket.recv_high_watermark(), 1);
+        assert_eq!(socket.send_low_watermark(), 48);
+        socket.set_recv_high_watermark(Some(12));
+        socket.set_send_low_watermark(Some(7));
+        socket.set_buffer_callback(Some(callback));
+
+        socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        assert!(socket.can_recv());
+        assert!(!socket.is_readable());
+        assert_eq!(READABLE.load(Ordering::SeqCst), 0);
+        socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
+        assert!(socket.is_readable());
+        assert_eq!(READABLE.load(Ordering::SeqCst), 1);
+
+        assert_eq!(socket.send_slice(PAYLOAD, REMOTE_END), Ok(()));
+        assert_eq!(socket.send_slice(PAYLOAD, REMOTE_END), Ok(()));
+        assert!(socket.can_send());
+        assert!(!socket.is_writable());
+        assert_eq!(socket.dispatch(&mut cx, |_, _| Ok::<_, Error>(())), Ok(()));
+        assert_eq!(WRITABLE.load(Ordering::SeqCst), 1);
+        assert!(socket.is_writable());
+
+        // A watermark beyond the capacity of the buffer is clamped to it.
+        socket.set_recv_high_watermark(Some(1000));
+        assert_eq!(socket.recv_high_watermark(), 48);
+    }
+
+    #[test]
+    fn test_bound_interface() {
+        let mut socket = socket(buffer(1), buffer(1));
+        let mut cx = Context::mock();
+        assert_eq!(socket.bind(LOCAL_PORT), Ok(()));
+        socket.set_bound_interface(Some(1));
+        assert!(!socket.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+
+        // Datagrams are only sent by the interface the socket is bound to.
+        assert_eq!(socket.send_slice(b"abcdef", REMOTE_END), Ok(()));
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Ingress);
+        assert_eq!(
+            socket.dispatch(&mut cx, |_, _| unreachable!()),
+            Ok::<_, Error>(())
+        );
+
+        socket.set_bound_interface(Some(0));
+        assert!(socket.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+        assert_eq!(socket.poll_at(&mut cx), PollAt::Now);
+    }
+
     #[test]
     fn test_peek_process() {
         let mut socket = socket(buffer(1), buffer(0));
@@ -701,8 +1247,8 @@ mod test {
         assert_eq!(socket.peek(), Err(RecvError::Exhausted));
 
         socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
-        assert_eq!(socket.peek(), Ok((&b"abcdef"[..], &REMOTE_END)));
-        assert_eq!(socket.recv(), Ok((&b"abcdef"[..], REMOTE_END)));
+        assert_eq!(socket.peek(), Ok((&b"abcdef"[..], &REMOTE_META)));
+        assert_eq!(socket.recv(), Ok((&b"abcdef"[..], REMOTE_META)));
         assert_eq!(socket.peek(), Err(RecvError::Exhausted));
     }
 
@@ -717,7 +1263,7 @@ mod test {
         socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
 
         let mut slice = [0; 4];
-        assert_eq!(socket.recv_slice(&mut slice[..]), Ok((4, REMOTE_END)));
+        assert_eq!(socket.recv_slice(&mut slice[..]), Ok((4, REMOTE_META)));
         assert_eq!(&slice, b"abcd");
     }
 
@@ -731,9 +1277,9 @@ mod test {
         socket.process(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR, PAYLOAD);
 
         let mut slice = [0; 4];
-        assert_eq!(socket.peek_slice(&mut slice[..]), Ok((4, &REMOTE_END)));
+        assert_eq!(socket.peek_slice(&mut slice[..]), Ok((4, &REMOTE_META)));
         assert_eq!(&slice, b"abcd");
-        assert_eq!(socket.recv_slice(&mut slice[..]), Ok((4, REMOTE_END)));
+        assert_eq!(socket.recv_slice(&mut slice[..]), Ok((4, REMOTE_META)));
         assert_eq!(&slice, b"abcd");
         assert_eq!(socket.peek_slice(&mut slice[..]), Err(RecvError::Exhausted));
     }
@@ -818,7 +1364,7 @@ mod test {
             dst_port: LOCAL_PORT,
         };
         socket.process(&mut cx, &REMOTE_IP_REPR, &repr, &[]);
-        assert_eq!(socket.recv(), Ok((&[][..], REMOTE_END)));
+        assert_eq!(socket.recv(), Ok((&[][..], REMOTE_META)));
     }
 
     #[test]
@@ -831,4 +1377,54 @@ mod test {
         socket.close();
         assert!(!socket.is_open());
     }
+
+    #[test]
+    #[cfg(feature = "proto-udplite")]
+    #[should_panic(expected = "the checksum coverage must include the UDP-Lite header")]
+    fn test_set_checksum_coverage_too_small() {
+        let mut s = socket(buffer(0), buffer(1));
+        s.set_checksum_coverage(Some(4));
+    }
+
+    #[test]
+    #[cfg(feature = "proto-udplite")]
+    fn test_udplite_dispatch() {
+        let mut s = socket(buffer(0), buffer(1));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_END), Ok(()));
+
+        s.set_checksum_coverage(Some(8));
+        assert_eq!(s.send_slice(b"abcdef", REMOTE_END), Ok(()));
+        assert_eq!(
+            s.dispatch(&mut cx, |_, (ip_repr, _, _)| {
+                assert_eq!(ip_repr.next_header(), IpProtocol::UdpLite);
+                Ok::<_, Error>(())
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "proto-udplite")]
+    fn test_udplite_accepts() {
+        let mut s = socket(buffer(1), buffer(0));
+        let mut cx = Context::mock();
+
+        assert_eq!(s.bind(LOCAL_PORT), Ok(()));
+
+        let udplite_ip_repr = IpReprIpvX(IpvXRepr {
+            src_addr: REMOTE_ADDR,
+            dst_addr: LOCAL_ADDR,
+            next_header: IpProtocol::UdpLite,
+            payload_len: 8 + 6,
+            hop_limit: 64,
+        });
+        assert!(s.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+        assert!(!s.accepts(&mut cx, &udplite_ip_repr, &REMOTE_UDP_REPR));
+
+        s.set_checksum_coverage(Some(0));
+        assert!(!s.accepts(&mut cx, &REMOTE_IP_REPR, &REMOTE_UDP_REPR));
+        assert!(s.accepts(&mut cx, &udplite_ip_repr, &REMOTE_UDP_REPR));
+    }
 }
@@ -228,7 +228,7 @@ impl<'a, H> PacketBuffer<'a, H> {
     pub fn peek(&mut self) -> Result<(&H, &[u8]), Empty> {
         self.dequeue_padding();
 
-        if let Some(metadata) = self.metadata_ring.get_allocated(0, 1).first() {
+        if let Some(metadata) = self.metadata_ring.get(0) {
             Ok((
                 metadata.header.as_ref().unwrap(),
                 self.payload_ring.get_allocated(0, metadata.size),
@@ -248,6 +248,12 @@ impl<'a, H> PacketBuffer<'a, H> {
         self.payload_ring.capacity()
     }
 
+    /// Return the number of bytes used in the payload ring buffer, including the
+    /// padding left at its end when a packet did not fit there.
+    pub fn payload_len(&self) -> usize {
+        self.payload_ring.len()
+    }
+
     /// Reset the packet buffer and clear any staged.
     #[allow(unused)]
     pub(crate) fn reset(&mut self) {
@@ -1,4 +1,4 @@
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, write_u16};
 use core::fmt;
 
 use super::{Error, Result};
@@ -108,7 +108,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn hardware_type(&self) -> Hardware {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::HTYPE]);
+        let raw = read_u16(data, field::HTYPE);
         Hardware::from(raw)
     }
 
@@ -116,7 +116,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn protocol_type(&self) -> Protocol {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::PTYPE]);
+        let raw = read_u16(data, field::PTYPE);
         Protocol::from(raw)
     }
 
@@ -138,7 +138,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn operation(&self) -> Operation {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::OPER]);
+        let raw = read_u16(data, field::OPER);
         Operation::from(raw)
     }
 
@@ -172,14 +172,14 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_hardware_type(&mut self, value: Hardware) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::HTYPE], value.into())
+        write_u16(data, field::HTYPE, value.into())
     }
 
     /// Set the protocol type field.
     #[inline]
     pub fn set_protocol_type(&mut self, value: Protocol) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::PTYPE], value.into())
+        write_u16(data, field::PTYPE, value.into())
     }
 
     /// Set the hardware length field.
@@ -200,7 +200,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_operation(&mut self, value: Operation) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::OPER], value.into())
+        write_u16(data, field::OPER, value.into())
     }
 
     /// Set the source hardware address field.
@@ -390,6 +390,8 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
 
     static PACKET_BYTES: [u8; 28] = [
         0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x01, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x21,
@@ -460,4 +462,21 @@ mod test {
         packet_repr().emit(&mut packet);
         assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::arp_repr()) {
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(&mut Packet::new_unchecked(&mut bytes));
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&packet).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..64)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&packet);
+            }
+        }
+    }
 }
@@ -0,0 +1,604 @@
+/*! Construction of whole packets from stacked layers.
+
+The `builder` module emits a packet made of several layers in one pass, e.g. an
+Ethernet frame carrying an IPv4 packet carrying a UDP datagram. Each layer is
+given only the fields that can't be derived from the layers it carries: the
+lengths, the protocol numbers and the checksums are filled in automatically.
+
+# Example
+
+```rust
+# #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+# {
+use smoltcp::wire::builder::{ethernet, ipv4, udp, Layer};
+use smoltcp::wire::*;
+
+let packet = ethernet(
+    EthernetAddress([0x02, 0, 0, 0, 0, 1]),
+    EthernetAddress::BROADCAST,
+    ipv4(
+        Ipv4Address::new(10, 0, 0, 1),
+        Ipv4Address::new(10, 0, 0, 2),
+        udp(1234, 5678, &b"hello"[..]),
+    ),
+);
+let mut buffer = vec![0; packet.buffer_len()];
+packet.emit(&mut buffer).unwrap();
+
+let frame = EthernetFrame::new_checked(&buffer).unwrap();
+assert_eq!(frame.ethertype(), EthernetProtocol::Ipv4);
+# }
+```
+*/
+
+use super::{Error, Result};
+use crate::phy::ChecksumCapabilities;
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::{EthernetAddress, EthernetFrame, EthernetProtocol, EthernetRepr};
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::{Icmpv4Packet, Icmpv4Repr, Ipv4Address, Ipv4Packet, Ipv4Repr};
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::{Icmpv6Packet, Icmpv6Repr, Ipv6Address, Ipv6Packet, Ipv6Repr};
+use crate::wire::{IpAddress, IpProtocol, TcpPacket, TcpRepr, UdpPacket, UdpRepr};
+
+/// The hop limit of the IP packets, unless another one is given.
+pub const DEFAULT_HOP_LIMIT: u8 = 64;
+
+/// A layer of a packet, and the layers it carries.
+pub trait Layer {
+    /// Return the length of the layer, including the layers it carries.
+    fn buffer_len(&self) -> usize;
+
+    /// Return the EtherType identifying the layer, if it has one.
+    #[cfg(feature = "medium-ethernet")]
+    fn ethertype(&self) -> Option<EthernetProtocol> {
+        None
+    }
+
+    /// Return the IP protocol number identifying the layer, if it has one.
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        None
+    }
+
+    /// Emit the layer into `buffer`, which is exactly `buffer_len()` octets long.
+    ///
+    /// `ip_addrs` are the source and destination addresses of the IP packet carrying
+    /// the layer, if any, for computing the checksums covering a pseudo-header.
+    fn emit_layer(&self, buffer: &mut [u8], ip_addrs: Option<(IpAddress, IpAddress)>)
+        -> Result<()>;
+
+    /// Emit the packet into the beginning of `buffer`, and return its length.
+    ///
+    /// Returns `Err(Error)` if `buffer` is shorter than `buffer_len()`, if the protocol of
+    /// a layer is unknown to the layer carrying it (see e.g. [Ethernet::with_ethertype]),
+    /// or if a TCP, UDP or ICMPv6 layer is not carried by an IP packet.
+    fn emit(&self, buffer: &mut [u8]) -> Result<usize> {
+        let len = self.buffer_len();
+        if buffer.len() < len {
+            return Err(Error);
+        }
+        self.emit_layer(&mut buffer[..len], None)?;
+        Ok(len)
+    }
+}
+
+impl Layer for &[u8] {
+    fn buffer_len(&self) -> usize {
+        self.len()
+    }
+
+    fn emit_layer(&self, buffer: &mut [u8], _: Option<(IpAddress, IpAddress)>) -> Result<()> {
+        buffer.copy_from_slice(self);
+        Ok(())
+    }
+}
+
+/// An Ethernet II frame. See [ethernet].
+#[cfg(feature = "medium-ethernet")]
+#[derive(Debug, Clone)]
+pub struct Ethernet<P> {
+    src_addr: EthernetAddress,
+    dst_addr: EthernetAddress,
+    ethertype: Option<EthernetProtocol>,
+    payload: P,
+}
+
+/// Return an Ethernet II frame carrying `payload`.
+#[cfg(feature = "medium-ethernet")]
+pub fn ethernet<P: Layer>(
+    src_addr: EthernetAddress,
+    dst_addr: EthernetAddress,
+    payload: P,
+) -> Ethernet<P> {
+    Ethernet {
+        src_addr,
+        dst_addr,
+        ethertype: None,
+        payload,
+    }
+}
+
+#[cfg(feature = "medium-ethernet")]
+impl<P: Layer> Ethernet<P> {
+    /// Return the frame, with the EtherType set to `ethertype` instead of the one of the
+    /// payload, e.g. for a payload of raw octets.
+    pub fn with_ethertype(self, ethertype: EthernetProtocol) -> Ethernet<P> {
+        Ethernet {
+            ethertype: Some(ethertype),
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "medium-ethernet")]
+impl<P: Layer> Layer for Ethernet<P> {
+    fn buffer_len(&self) -> usize {
+        EthernetFrame::<&[u8]>::buffer_len(self.payload.buffer_len())
+    }
+
+    fn emit_layer(&self, buffer: &mut [u8], _: Option<(IpAddress, IpAddress)>) -> Result<()> {
+        let repr = EthernetRepr {
+            src_addr: self.src_addr,
+            dst_addr: self.dst_addr,
+            ethertype: self
+                .ethertype
+                .or_else(|| self.payload.ethertype())
+                .ok_or(Error)?,
+        };
+        let mut frame = EthernetFrame::new_unchecked(buffer);
+        repr.emit(&mut frame);
+        self.payload.emit_layer(frame.payload_mut(), None)
+    }
+}
+
+/// An IPv4 packet. See [ipv4].
+#[cfg(feature = "proto-ipv4")]
+#[derive(Debug, Clone)]
+pub struct Ipv4<P> {
+    src_addr: Ipv4Address,
+    dst_addr: Ipv4Address,
+    protocol: Option<IpProtocol>,
+    hop_limit: u8,
+    payload: P,
+}
+
+/// Return an IPv4 packet carrying `payload`, with a hop limit of [DEFAULT_HOP_LIMIT].
+#[cfg(feature = "proto-ipv4")]
+pub fn ipv4<P: Layer>(src_addr: Ipv4Address, dst_addr: Ipv4Address, payload: P) -> Ipv4<P> {
+    Ipv4 {
+        src_addr,
+        dst_addr,
+        protocol: None,
+        hop_limit: DEFAULT_HOP_LIMIT,
+        payload,
+    }
+}
+
+#[cfg(feature = "proto-ipv4")]
+impl<P: Layer> Ipv4<P> {
+    /// Return the packet, with the protocol set to `protocol` instead of the one of the
+    /// payload, e.g. for a payload of raw octets.
+    pub fn with_protocol(self, protocol: IpProtocol) -> Ipv4<P> {
+        Ipv4 {
+            protocol: Some(protocol),
+            ..self
+        }
+    }
+
+    /// Return the packet, with a hop limit of `hop_limit`.
+    pub fn with_hop_limit(self, hop_limit: u8) -> Ipv4<P> {
+        Ipv4 { hop_limit, ..self }
+    }
+
+    fn repr(&self) -> Result<Ipv4Repr> {
+        Ok(Ipv4Repr {
+            src_addr: self.src_addr,
+            dst_addr: self.dst_addr,
+            next_header: self
+                .protocol
+                .or_else(|| self.payload.ip_protocol())
+                .ok_or(Error)?,
+            payload_len: self.payload.buffer_len(),
+            hop_limit: self.hop_limit,
+        })
+    }
+}
+
+#[cfg(feature = "proto-ipv4")]
+impl<P: Layer> Layer for Ipv4<P> {
+    fn buffer_len(&self) -> usize {
+        crate::wire::IPV4_HEADER_LEN + self.payload.buffer_len()
+    }
+
+    #[cfg(feature = "medium-ethernet")]
+    fn ethertype(&self) -> Option<EthernetProtocol> {
+        Some(EthernetProtocol::Ipv4)
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::IpIp)
+    }
+
+    fn emit_layer(&self, buffer: &mut [u8], _: Option<(IpAddress, IpAddress)>) -> Result<()> {
+        let repr = self.repr()?;
+        let mut packet = Ipv4Packet::new_unchecked(buffer);
+        repr.emit(&mut packet, &ChecksumCapabilities::default());
+        let ip_addrs = (self.src_addr.into(), self.dst_addr.into());
+        self.payload
+            .emit_layer(packet.payload_mut(), Some(ip_addrs))
+    }
+}
+
+/// An IPv6 packet. See [ipv6].
+#[cfg(feature = "proto-ipv6")]
+#[derive(Debug, Clone)]
+pub struct Ipv6<P> {
+    src_addr: Ipv6Address,
+    dst_addr: Ipv6Address,
+    next_header: Option<IpProtocol>,
+    hop_limit: u8,
+    payload: P,
+}
+
+/// Return an IPv6 packet carrying `payload`, with a hop limit of [DEFAULT_HOP_LIMIT].
+#[cfg(feature = "proto-ipv6")]
+pub fn ipv6<P: Layer>(src_addr: Ipv6Address, dst_addr: Ipv6Address, payload: P) -> Ipv6<P> {
+    Ipv6 {
+        src_addr,
+        dst_addr,
+        next_header: None,
+        hop_limit: DEFAULT_HOP_LIMIT,
+        payload,
+    }
+}
+
+#[cfg(feature = "proto-ipv6")]
+impl<P: Layer> Ipv6<P> {
+    /// Return the packet, with the next header set to `next_header` instead of the
+    /// protocol of the payload, e.g. for a payload of raw octets.
+    pub fn with_next_header(self, next_header: IpProtocol) -> Ipv6<P> {
+        Ipv6 {
+            next_header: Some(next_header),
+            ..self
+        }
+    }
+
+    /// Return the packet, with a hop limit of `hop_limit`.
+    pub fn with_hop_limit(self, hop_limit: u8) -> Ipv6<P> {
+        Ipv6 { hop_limit, ..self }
+    }
+
+    fn repr(&self) -> Result<Ipv6Repr> {
+        Ok(Ipv6Repr {
+            src_addr: self.src_addr,
+            dst_addr: self.dst_addr,
+            next_header: self
+                .next_header
+                .or_else(|| self.payload.ip_protocol())
+                .ok_or(Error)?,
+            payload_len: self.payload.buffer_len(),
+            hop_limit: self.hop_limit,
+        })
+    }
+}
+
+#[cfg(feature = "proto-ipv6")]
+impl<P: Layer> Layer for Ipv6<P> {
+    fn buffer_len(&self) -> usize {
+        crate::wire::IPV6_HEADER_LEN + self.payload.buffer_len()
+    }
+
+    #[cfg(feature = "medium-ethernet")]
+    fn ethertype(&self) -> Option<EthernetProtocol> {
+        Some(EthernetProtocol::Ipv6)
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::Ipv6)
+    }
+
+    fn emit_layer(&self, buffer: &mut [u8], _: Option<(IpAddress, IpAddress)>) -> Result<()> {
+        let repr = self.repr()?;
+        let mut packet = Ipv6Packet::new_unchecked(buffer);
+        repr.emit(&mut packet);
+        let ip_addrs = (self.src_addr.into(), self.dst_addr.into());
+        self.payload
+            .emit_layer(packet.payload_mut(), Some(ip_addrs))
+    }
+}
+
+/// A UDP datagram. See [udp].
+#[derive(Debug, Clone)]
+pub struct Udp<P> {
+    src_port: u16,
+    dst_port: u16,
+    payload: P,
+}
+
+/// Return a UDP datagram carrying `payload`.
+pub fn udp<P: Layer>(src_port: u16, dst_port: u16, payload: P) -> Udp<P> {
+    Udp {
+        src_port,
+        dst_port,
+        payload,
+    }
+}
+
+impl<P: Layer> Layer for Udp<P> {
+    fn buffer_len(&self) -> usize {
+        crate::wire::UDP_HEADER_LEN + self.payload.buffer_len()
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::Udp)
+    }
+
+    fn emit_layer(
+        &self,
+        buffer: &mut [u8],
+        ip_addrs: Option<(IpAddress, IpAddress)>,
+    ) -> Result<()> {
+        let (src_addr, dst_addr) = ip_addrs.ok_or(Error)?;
+        let repr = UdpRepr {
+            src_port: self.src_port,
+            dst_port: self.dst_port,
+        };
+        let mut result = Ok(());
+        repr.emit(
+            &mut UdpPacket::new_unchecked(buffer),
+            &src_addr,
+            &dst_addr,
+            self.payload.buffer_len(),
+            |payload| result = self.payload.emit_layer(payload, None),
+            &ChecksumCapabilities::default(),
+        );
+        result
+    }
+}
+
+/// A TCP segment. See [tcp].
+#[derive(Debug, Clone)]
+pub struct Tcp<'a> {
+    repr: TcpRepr<'a>,
+}
+
+/// Return a TCP segment with the header and the payload of `repr`.
+pub fn tcp(repr: TcpRepr) -> Tcp {
+    Tcp { repr }
+}
+
+impl<'a> Layer for Tcp<'a> {
+    fn buffer_len(&self) -> usize {
+        self.repr.buffer_len()
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::Tcp)
+    }
+
+    fn emit_layer(
+        &self,
+        buffer: &mut [u8],
+        ip_addrs: Option<(IpAddress, IpAddress)>,
+    ) -> Result<()> {
+        let (src_addr, dst_addr) = ip_addrs.ok_or(Error)?;
+        self.repr.emit(
+            &mut TcpPacket::new_unchecked(buffer),
+            &src_addr,
+            &dst_addr,
+            &ChecksumCapabilities::default(),
+        );
+        Ok(())
+    }
+}
+
+/// An ICMPv4 message. See [icmpv4].
+#[cfg(feature = "proto-ipv4")]
+#[derive(Debug, Clone)]
+pub struct Icmpv4<'a> {
+    repr: Icmpv4Repr<'a>,
+}
+
+/// Return the ICMPv4 message of `repr`.
+#[cfg(feature = "proto-ipv4")]
+pub fn icmpv4(repr: Icmpv4Repr) -> Icmpv4 {
+    Icmpv4 { repr }
+}
+
+#[cfg(feature = "proto-ipv4")]
+impl<'a> Layer for Icmpv4<'a> {
+    fn buffer_len(&self) -> usize {
+        self.repr.buffer_len()
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::Icmp)
+    }
+
+    fn emit_layer(&self, buffer: &mut [u8], _: Option<(IpAddress, IpAddress)>) -> Result<()> {
+        self.repr.emit(
+            &mut Icmpv4Packet::new_unchecked(buffer),
+            &ChecksumCapabilities::default(),
+        );
+        Ok(())
+    }
+}
+
+/// An ICMPv6 message. See [icmpv6].
+#[cfg(feature = "proto-ipv6")]
+#[derive(Debug, Clone)]
+pub struct Icmpv6<'a> {
+    repr: Icmpv6Repr<'a>,
+}
+
+/// Return the ICMPv6 message of `repr`.
+#[cfg(feature = "proto-ipv6")]
+pub fn icmpv6(repr: Icmpv6Repr) -> Icmpv6 {
+    Icmpv6 { repr }
+}
+
+#[cfg(feature = "proto-ipv6")]
+impl<'a> Layer for Icmpv6<'a> {
+    fn buffer_len(&self) -> usize {
+        self.repr.buffer_len()
+    }
+
+    fn ip_protocol(&self) -> Option<IpProtocol> {
+        Some(IpProtocol::Icmpv6)
+    }
+
+    fn emit_layer(
+        &self,
+        buffer: &mut [u8],
+        ip_addrs: Option<(IpAddress, IpAddress)>,
+    ) -> Result<()> {
+        let (src_addr, dst_addr) = ip_addrs.ok_or(Error)?;
+        self.repr.emit(
+            &src_addr,
+            &dst_addr,
+            &mut Icmpv6Packet::new_unchecked(buffer),
+            &ChecksumCapabilities::default(),
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire::ParseMode;
+
+    #[cfg(feature = "proto-ipv4")]
+    const SRC_ADDR: Ipv4Address = Ipv4Address([10, 0, 0, 1]);
+    #[cfg(feature = "proto-ipv4")]
+    const DST_ADDR: Ipv4Address = Ipv4Address([10, 0, 0, 2]);
+
+    #[test]
+    #[cfg(all(feature = "medium-ethernet", feature = "proto-ipv4"))]
+    fn test_ethernet_ipv4_udp() {
+        let packet = ethernet(
+            EthernetAddress([0x02, 0, 0, 0, 0, 1]),
+            EthernetAddress::BROADCAST,
+            ipv4(SRC_ADDR, DST_ADDR, udp(1234, 5678, &b"hello"[..])).with_hop_limit(5),
+        );
+        assert_eq!(packet.buffer_len(), 14 + 20 + 8 + 5);
+        let mut buffer = vec![0xa5; 64];
+        assert_eq!(packet.emit(&mut buffer), Ok(47));
+
+        let frame = EthernetFrame::new_checked(&buffer[..47]).unwrap();
+        assert_eq!(frame.ethertype(), EthernetProtocol::Ipv4);
+        let ip_packet = Ipv4Packet::new_checked(frame.payload()).unwrap();
+        let ip_repr = Ipv4Repr::parse(
+            &ip_packet,
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        )
+        .unwrap();
+        assert_eq!(
+            ip_repr,
+            Ipv4Repr {
+                src_addr: SRC_ADDR,
+                dst_addr: DST_ADDR,
+                next_header: IpProtocol::Udp,
+                payload_len: 13,
+                hop_limit: 5,
+            }
+        );
+        let udp_packet = UdpPacket::new_checked(ip_packet.payload()).unwrap();
+        let udp_repr = UdpRepr::parse(
+            &udp_packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+        )
+        .unwrap();
+        assert_eq!(udp_repr.src_port, 1234);
+        assert_eq!(udp_repr.dst_port, 5678);
+        assert_eq!(udp_packet.payload(), b"hello");
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_ipv4_tcp() {
+        use crate::wire::{TcpControl, TcpSeqNumber};
+
+        let tcp_repr = TcpRepr {
+            src_port: 49152,
+            dst_port: 80,
+            control: TcpControl::Syn,
+            seq_number: TcpSeqNumber(1),
+            ack_number: None,
+            window_len: 1024,
+            window_scale: None,
+            max_seg_size: Some(1460),
+            sack_permitted: false,
+            sack_ranges: [None, None, None],
+            payload: &[],
+        };
+        let packet = ipv4(SRC_ADDR, DST_ADDR, tcp(tcp_repr));
+        let mut buffer = vec![0; packet.buffer_len()];
+        packet.emit(&mut buffer).unwrap();
+
+        let ip_packet = Ipv4Packet::new_checked(&buffer).unwrap();
+        assert_eq!(ip_packet.next_header(), IpProtocol::Tcp);
+        let tcp_packet = TcpPacket::new_checked(ip_packet.payload()).unwrap();
+        let parsed = TcpRepr::parse(
+            &tcp_packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        )
+        .unwrap();
+        assert_eq!(parsed, tcp_repr);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv6")]
+    fn test_ipv6_icmpv6() {
+        let src_addr = Ipv6Address::LOOPBACK;
+        let dst_addr = Ipv6Address::LINK_LOCAL_ALL_NODES;
+        let icmp_repr = Icmpv6Repr::EchoRequest {
+            ident: 1,
+            seq_no: 2,
+            data: b"ping",
+        };
+        let packet = ipv6(src_addr, dst_addr, icmpv6(icmp_repr));
+        let mut buffer = vec![0; packet.buffer_len()];
+        packet.emit(&mut buffer).unwrap();
+
+        let ip_packet = Ipv6Packet::new_checked(&buffer).unwrap();
+        assert_eq!(ip_packet.next_header(), IpProtocol::Icmpv6);
+        assert_eq!(ip_packet.payload_len(), 12);
+        let icmp_packet = Icmpv6Packet::new_checked(ip_packet.payload()).unwrap();
+        let parsed = Icmpv6Repr::parse(
+            &src_addr.into(),
+            &dst_addr.into(),
+            &icmp_packet,
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        )
+        .unwrap();
+        assert_eq!(parsed, icmp_repr);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_invalid_stack() {
+        // The protocol of raw octets must be given.
+        let packet = ipv4(SRC_ADDR, DST_ADDR, &[0u8; 4][..]);
+        let mut buffer = vec![0; packet.buffer_len()];
+        assert_eq!(packet.emit(&mut buffer), Err(Error));
+        let packet = packet.with_protocol(IpProtocol::Unknown(253));
+        assert_eq!(packet.emit(&mut buffer), Ok(24));
+
+        // UDP must be carried by IP.
+        let packet = udp(1, 2, &[0u8; 4][..]);
+        assert_eq!(packet.emit(&mut buffer), Err(Error));
+
+        // The buffer must be long enough.
+        let packet = ipv4(SRC_ADDR, DST_ADDR, udp(1, 2, &[0u8; 4][..]));
+        assert_eq!(packet.buffer_len(), 32);
+        assert_eq!(packet.emit(&mut buffer), Err(Error));
+    }
+}
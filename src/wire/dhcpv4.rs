@@ -41,6 +41,21 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// The flags of the Client FQDN option, see RFC 4702 § 2.1.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct FqdnFlags: u8 {
+        /// The server should perform the A RR update.
+        const SERVER_UPDATE = 0b0000_0001;
+        /// The server has overridden the client's preference for the S flag.
+        const OVERRIDE      = 0b0000_0010;
+        /// The domain name is in the canonical wire format of RFC 1035 § 3.1.
+        const ENCODED       = 0b0000_0100;
+        /// The server should not perform any DNS updates.
+        const NO_UPDATE     = 0b0000_1000;
+    }
+}
+
 impl MessageType {
     fn opcode(&self) -> OpCode {
         match *self {
@@ -55,6 +70,16 @@ impl MessageType {
     }
 }
 
+/// The content of a Client FQDN option, see RFC 4702.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClientFqdn<'a> {
+    pub flags: FqdnFlags,
+    /// The domain name, encoded as in RFC 1035 § 3.1 if the `ENCODED` flag is set, and
+    /// in ASCII otherwise. A partial name lacks the terminating empty label.
+    pub domain_name: &'a [u8],
+}
+
 /// A representation of a single DHCP option.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -241,7 +266,8 @@ pub(crate) mod field {
     pub const SIADDR: Field = 20..24;
     pub const GIADDR: Field = 24..28;
     pub const CHADDR: Field = 28..34;
-    pub const SNAME: Field = 34..108;
+    pub const CHADDR_PADDING: Field = 34..44;
+    pub const SNAME: Field = 44..108;
     pub const FILE: Field = 108..236;
     pub const MAGIC_NUMBER: Field = 236..240;
     pub const OPTIONS: Rest = 240..;
@@ -335,6 +361,7 @@ pub(crate) mod field {
     pub const OPT_REBINDING_TIME_VALUE: u8 = 59;
     pub const OPT_VENDOR_CLASS_ID: u8 = 60;
     pub const OPT_CLIENT_ID: u8 = 61;
+    pub const OPT_CLIENT_FQDN: u8 = 81;
 }
 
 impl<T: AsRef<[u8]>> Packet<T> {
@@ -465,7 +492,28 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 }
 
+/// Return the contents of `data` up to the first null octet.
+fn null_terminated(data: &[u8]) -> &[u8] {
+    let len = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    &data[..len]
+}
+
 impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Returns the server host name (called `sname` in the specification), without the
+    /// terminating null octet.
+    pub fn server_name(&self) -> &'a [u8] {
+        null_terminated(&self.buffer.as_ref()[field::SNAME])
+    }
+
+    /// Returns the boot file name (called `file` in the specification), without the
+    /// terminating null octet.
+    pub fn boot_file(&self) -> &'a [u8] {
+        null_terminated(&self.buffer.as_ref()[field::FILE])
+    }
+
     /// Return a pointer to the options.
     #[inline]
     pub fn options(&self) -> Result<&'a [u8]> {
@@ -482,6 +530,9 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     /// are undefined!**
     pub fn set_sname_and_boot_file_to_zero(&mut self) {
         let data = self.buffer.as_mut();
+        for byte in &mut data[field::CHADDR_PADDING] {
+            *byte = 0;
+        }
         for byte in &mut data[field::SNAME] {
             *byte = 0;
         }
@@ -521,6 +572,30 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u32(field, value)
     }
 
+    /// Sets the server host name field (called `sname` in the specification), padding it
+    /// with null octets.
+    ///
+    /// # Panics
+    /// This function panics if `value` does not fit the field with a terminating null octet.
+    pub fn set_server_name(&mut self, value: &[u8]) {
+        let field = &mut self.buffer.as_mut()[field::SNAME];
+        assert!(value.len() < field.len());
+        field[..value.len()].copy_from_slice(value);
+        field[value.len()..].fill(0);
+    }
+
+    /// Sets the boot file name field (called `file` in the specification), padding it
+    /// with null octets.
+    ///
+    /// # Panics
+    /// This function panics if `value` does not fit the field with a terminating null octet.
+    pub fn set_boot_file(&mut self, value: &[u8]) {
+        let field = &mut self.buffer.as_mut()[field::FILE];
+        assert!(value.len() < field.len());
+        field[..value.len()].copy_from_slice(value);
+        field[value.len()..].fill(0);
+    }
+
     /// Sets the ethernet address of the client.
     ///
     /// Sets the `chaddr` field.
@@ -700,6 +775,22 @@ pub struct Repr<'a> {
     pub parameter_request_list: Option<&'a [u8]>,
     /// DNS servers
     pub dns_servers: Option<[Option<Ipv4Address>; MAX_DNS_SERVER_COUNT]>,
+    /// The "host name" option, specifying the name of the client.
+    pub host_name: Option<&'a str>,
+    /// The "client FQDN" option, used by clients to convey their fully qualified domain
+    /// name and whether the server should update DNS, and by servers to confirm it.
+    pub client_fqdn: Option<ClientFqdn<'a>>,
+    /// Options emitted after all the others, such as vendor-specific options.
+    ///
+    /// When parsing, this is always empty; the options of a packet are available
+    /// with [`Packet::options()`] and [`DhcpOption::parse()`].
+    pub additional_options: &'a [DhcpOption<'a>],
+    /// The name of the server to boot from, from the `sname` field or the "TFTP server
+    /// name" option. When emitting, the option is used if the name does not fit the field.
+    pub server_name: Option<&'a str>,
+    /// The name of the file to boot, from the `file` field or the "bootfile name" option.
+    /// When emitting, the option is used if the name does not fit the field.
+    pub boot_file: Option<&'a str>,
     /// The maximum size dhcp packet the interface can receive
     pub max_size: Option<u16>,
     /// The DHCP IP lease duration, specified in seconds.
@@ -707,6 +798,18 @@ pub struct Repr<'a> {
 }
 
 impl<'a> Repr<'a> {
+    /// Return the server name if it is emitted as an option rather than in the `sname` field.
+    fn server_name_option(&self) -> Option<&'a str> {
+        self.server_name
+            .filter(|name| name.len() >= field::SNAME.end - field::SNAME.start)
+    }
+
+    /// Return the boot file name if it is emitted as an option rather than in the `file` field.
+    fn boot_file_option(&self) -> Option<&'a str> {
+        self.boot_file
+            .filter(|name| name.len() >= field::FILE.end - field::FILE.start)
+    }
+
     /// Return the length of a packet that will be emitted from this high-level representation.
     pub fn buffer_len(&self) -> usize {
         let mut len = field::OPTIONS.start;
@@ -740,6 +843,21 @@ impl<'a> Repr<'a> {
         if let Some(list) = self.parameter_request_list {
             len += list.len() + 2;
         }
+        if let Some(host_name) = self.host_name {
+            len += host_name.len() + 2;
+        }
+        if let Some(fqdn) = self.client_fqdn {
+            len += fqdn.domain_name.len() + 5;
+        }
+        if let Some(name) = self.server_name_option() {
+            len += name.len() + 2;
+        }
+        if let Some(name) = self.boot_file_option() {
+            len += name.len() + 2;
+        }
+        for option in self.additional_options {
+            len += option.buffer_len();
+        }
 
         len
     }
@@ -778,6 +896,11 @@ impl<'a> Repr<'a> {
         let mut subnet_mask = None;
         let mut parameter_request_list = None;
         let mut dns_servers = None;
+        let mut host_name = None;
+        let mut client_fqdn = None;
+        let mut server_name = None;
+        let mut boot_file = None;
+        let mut overload = 0;
         let mut max_size = None;
         let mut lease_duration = None;
 
@@ -833,11 +956,60 @@ impl<'a> Repr<'a> {
                     }
                     dns_servers = Some(servers);
                 }
+                DhcpOption::Other {
+                    kind: field::OPT_HOST_NAME,
+                    data,
+                } => {
+                    host_name = core::str::from_utf8(data).ok();
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_TFTP_SERVER_NAME,
+                    data,
+                } => {
+                    server_name = core::str::from_utf8(data).ok();
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_BOOTFILE_NAME,
+                    data,
+                } => {
+                    boot_file = core::str::from_utf8(data).ok();
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_OPTION_OVERLOAD,
+                    data: &[value],
+                } => {
+                    overload = value;
+                }
+                DhcpOption::Other {
+                    kind: field::OPT_CLIENT_FQDN,
+                    data,
+                } => {
+                    // The two RCODE fields are deprecated, see RFC 4702 § 2.2.
+                    if data.len() >= 3 {
+                        client_fqdn = Some(ClientFqdn {
+                            flags: FqdnFlags::from_bits_truncate(data[0]),
+                            domain_name: &data[3..],
+                        });
+                    }
+                }
                 DhcpOption::Other { .. } => {}
             }
             options = next_options;
         }
 
+        // The fields hold options instead of names if overloaded, see RFC 2132 § 9.3.
+        // Options in the fields are not parsed.
+        let field_name = |name: &'a [u8]| match core::str::from_utf8(name) {
+            Ok(name) if !name.is_empty() => Some(name),
+            _ => None,
+        };
+        if overload & 1 == 0 {
+            boot_file = boot_file.or_else(|| field_name(packet.boot_file()));
+        }
+        if overload & 2 == 0 {
+            server_name = server_name.or_else(|| field_name(packet.server_name()));
+        }
+
         let broadcast = packet.flags().contains(Flags::BROADCAST);
 
         Ok(Repr {
@@ -855,6 +1027,11 @@ impl<'a> Repr<'a> {
             client_identifier,
             parameter_request_list,
             dns_servers,
+            host_name,
+            client_fqdn,
+            server_name,
+            boot_file,
+            additional_options: &[],
             max_size,
             lease_duration,
             message_type: message_type?,
@@ -880,6 +1057,12 @@ impl<'a> Repr<'a> {
         packet.set_your_ip(self.your_ip);
         packet.set_server_ip(self.server_ip);
         packet.set_relay_agent_ip(self.relay_agent_ip);
+        if let (Some(name), None) = (self.server_name, self.server_name_option()) {
+            packet.set_server_name(name.as_bytes());
+        }
+        if let (Some(name), None) = (self.boot_file, self.boot_file_option()) {
+            packet.set_boot_file(name.as_bytes());
+        }
 
         let mut flags = Flags::empty();
         if self.broadcast {
@@ -937,6 +1120,42 @@ impl<'a> Repr<'a> {
                 }
                 .emit(options);
             }
+            if let Some(host_name) = self.host_name {
+                options = DhcpOption::Other {
+                    kind: field::OPT_HOST_NAME,
+                    data: host_name.as_bytes(),
+                }
+                .emit(options);
+            }
+            if let Some(fqdn) = self.client_fqdn {
+                // Clients set both RCODE fields to zero, see RFC 4702 § 2.2.
+                let mut data = [0; 255];
+                let data_len = 3 + fqdn.domain_name.len();
+                data[0] = fqdn.flags.bits();
+                data[3..data_len].copy_from_slice(fqdn.domain_name);
+                options = DhcpOption::Other {
+                    kind: field::OPT_CLIENT_FQDN,
+                    data: &data[..data_len],
+                }
+                .emit(options);
+            }
+            if let Some(name) = self.server_name_option() {
+                options = DhcpOption::Other {
+                    kind: field::OPT_TFTP_SERVER_NAME,
+                    data: name.as_bytes(),
+                }
+                .emit(options);
+            }
+            if let Some(name) = self.boot_file_option() {
+                options = DhcpOption::Other {
+                    kind: field::OPT_BOOTFILE_NAME,
+                    data: name.as_bytes(),
+                }
+                .emit(options);
+            }
+            for option in self.additional_options {
+                options = option.emit(options);
+            }
             DhcpOption::EndOfList.emit(options);
         }
 
@@ -1133,6 +1352,11 @@ mod test {
             server_identifier: None,
             parameter_request_list: None,
             dns_servers: None,
+            host_name: None,
+            client_fqdn: None,
+            server_name: None,
+            boot_file: None,
+            additional_options: &[],
             max_size: None,
             lease_duration: Some(0xffff_ffff), // Infinite lease
         }
@@ -1157,6 +1381,11 @@ mod test {
             server_identifier: None,
             parameter_request_list: Some(&[1, 3, 6, 42]),
             dns_servers: None,
+            host_name: None,
+            client_fqdn: None,
+            server_name: None,
+            boot_file: None,
+            additional_options: &[],
         }
     }
 
@@ -1217,6 +1446,76 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_emit_host_name_fqdn() {
+        let repr = {
+            let mut repr = discover_repr();
+            repr.host_name = Some("sensor");
+            repr.client_fqdn = Some(ClientFqdn {
+                flags: FqdnFlags::SERVER_UPDATE | FqdnFlags::ENCODED,
+                domain_name: b"\x06sensor",
+            });
+            repr
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+        let options = &bytes[bytes.len() - 21..];
+        assert_eq!(&options[..8], b"\x0c\x06sensor");
+        assert_eq!(&options[8..], b"\x51\x0a\x05\x00\x00\x06sensor\xff");
+
+        let packet = Packet::new_unchecked(&bytes);
+        assert_eq!(Repr::parse(&packet), Ok(repr));
+    }
+
+    #[test]
+    fn test_emit_boot_params() {
+        let repr = {
+            let mut repr = offer_repr();
+            repr.server_name = Some("tftp");
+            repr.boot_file = Some("pxelinux.0");
+            repr
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+        let packet = Packet::new_unchecked(&bytes);
+        assert_eq!(packet.server_name(), b"tftp");
+        assert_eq!(packet.boot_file(), b"pxelinux.0");
+        assert_eq!(Repr::parse(&packet), Ok(repr));
+
+        // Names that do not fit the fields are sent as options.
+        let boot_file = core::str::from_utf8(&[b'a'; 128]).unwrap();
+        let repr = Repr {
+            boot_file: Some(boot_file),
+            ..repr
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+        let packet = Packet::new_unchecked(&bytes);
+        assert_eq!(packet.boot_file(), b"");
+        assert_eq!(Repr::parse(&packet), Ok(repr));
+    }
+
+    #[test]
+    fn test_parse_overloaded_boot_file() {
+        let overload = [DhcpOption::Other {
+            kind: field::OPT_OPTION_OVERLOAD,
+            data: &[1],
+        }];
+        let repr = Repr {
+            boot_file: Some("pxelinux.0"),
+            additional_options: &overload,
+            ..offer_repr()
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet).unwrap();
+        let packet = Packet::new_unchecked(&bytes);
+        assert_eq!(Repr::parse(&packet).unwrap().boot_file, None);
+    }
+
     #[test]
     fn test_emit_dhcp_option() {
         static DATA: &[u8] = &[1, 3, 6];
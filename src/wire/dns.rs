@@ -237,6 +237,14 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         NetworkEndian::write_u16(field, (old & !mask) | val);
     }
 
+    pub fn set_rcode(&mut self, val: Rcode) {
+        let field = &mut self.buffer.as_mut()[field::FLAGS];
+        let mask = 0x000F;
+        let val: u8 = val.into();
+        let old = NetworkEndian::read_u16(field);
+        NetworkEndian::write_u16(field, (old & !mask) | (val as u16 & mask));
+    }
+
     pub fn set_question_count(&mut self, val: u16) {
         let field = &mut self.buffer.as_mut()[field::QDCOUNT];
         NetworkEndian::write_u16(field, val)
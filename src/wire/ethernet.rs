@@ -1,4 +1,4 @@
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, write_u16};
 use core::fmt;
 
 use super::{Error, Result};
@@ -8,7 +8,11 @@ enum_with_unknown! {
     pub enum EtherType(u16) {
         Ipv4 = 0x0800,
         Arp  = 0x0806,
-        Ipv6 = 0x86DD
+        WakeOnLan = 0x0842,
+        Vlan = 0x8100,
+        Ipv6 = 0x86DD,
+        Lldp = 0x88CC,
+        Ptp  = 0x88F7
     }
 }
 
@@ -18,6 +22,10 @@ impl fmt::Display for EtherType {
             EtherType::Ipv4 => write!(f, "IPv4"),
             EtherType::Ipv6 => write!(f, "IPv6"),
             EtherType::Arp => write!(f, "ARP"),
+            EtherType::Lldp => write!(f, "LLDP"),
+            EtherType::Ptp => write!(f, "PTP"),
+            EtherType::WakeOnLan => write!(f, "WoL"),
+            EtherType::Vlan => write!(f, "802.1Q"),
             EtherType::Unknown(id) => write!(f, "0x{:04x}", id),
         }
     }
@@ -32,6 +40,11 @@ impl Address {
     /// The broadcast address.
     pub const BROADCAST: Address = Address([0xff; 6]);
 
+    /// Construct an Ethernet address from parts.
+    pub const fn new(a0: u8, a1: u8, a2: u8, a3: u8, a4: u8, a5: u8) -> Address {
+        Address([a0, a1, a2, a3, a4, a5])
+    }
+
     /// Construct an Ethernet address from a sequence of octets, in big-endian.
     ///
     /// # Panics
@@ -159,13 +172,16 @@ impl<T: AsRef<[u8]>> Frame<T> {
     #[inline]
     pub fn ethertype(&self) -> EtherType {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::ETHERTYPE]);
+        let raw = read_u16(data, field::ETHERTYPE);
         EtherType::from(raw)
     }
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Frame<&'a T> {
     /// Return a pointer to the payload, without checking for 802.1Q.
+    ///
+    /// The payload of a frame padded to the minimum frame size includes the padding;
+    /// the length of the packet it carries is given by the header of the packet.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
         let data = self.buffer.as_ref();
@@ -192,7 +208,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
     #[inline]
     pub fn set_ethertype(&mut self, value: EtherType) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ETHERTYPE], value.into())
+        write_u16(data, field::ETHERTYPE, value.into())
     }
 
     /// Return a mutable pointer to the payload.
@@ -251,6 +267,33 @@ impl<T: AsRef<[u8]>> PrettyPrint for Frame<T> {
                 indent.increase(f)?;
                 super::Ipv6Packet::<&[u8]>::pretty_print(&frame.payload(), f, indent)
             }
+            #[cfg(feature = "proto-lldp")]
+            EtherType::Lldp => {
+                indent.increase(f)?;
+                super::LldpPacket::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-ptp")]
+            EtherType::Ptp => {
+                indent.increase(f)?;
+                super::PtpPacket::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-wol")]
+            EtherType::WakeOnLan => {
+                indent.increase(f)?;
+                super::WolPacket::<&[u8]>::pretty_print(&frame.payload(), f, indent)
+            }
+            // 802.3 frames carry a length instead of an EtherType; BPDUs are
+            // recognized by their destination and LLC header.
+            #[cfg(feature = "proto-stp")]
+            EtherType::Unknown(len)
+                if len < 0x0600
+                    && frame.dst_addr() == super::STP_MULTICAST_ADDR
+                    && frame.payload().starts_with(&super::STP_LLC_HEADER) =>
+            {
+                indent.increase(f)?;
+                let bpdu = &frame.payload()[super::STP_LLC_HEADER.len()..];
+                super::StpPacket::<&[u8]>::pretty_print(&bpdu, f, indent)
+            }
             _ => Ok(()),
         }
     }
@@ -289,11 +332,23 @@ impl Repr {
     }
 }
 
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "EthernetII src={} dst={} type={}",
+            self.src_addr, self.dst_addr, self.ethertype
+        )
+    }
+}
+
 #[cfg(test)]
 mod test {
     // Tests that are valid with any combination of
     // "proto-*" features.
     use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
 
     #[test]
     fn test_broadcast() {
@@ -302,6 +357,23 @@ mod test {
         assert!(Address::BROADCAST.is_multicast());
         assert!(Address::BROADCAST.is_local());
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::ethernet_repr()) {
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(&mut Frame::new_unchecked(&mut bytes));
+            let frame = Frame::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&frame).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..32)) {
+            if let Ok(frame) = Frame::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&frame);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -350,6 +422,27 @@ mod test_ipv4 {
         frame.payload_mut().copy_from_slice(&PAYLOAD_BYTES[..]);
         assert_eq!(&frame.into_inner()[..], &FRAME_BYTES[..]);
     }
+
+    #[test]
+    fn test_pretty_print_compact() {
+        use crate::wire::PrettyPrinter;
+
+        static PACKET_BYTES: [u8; 46] = [
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x08, 0x00,
+            0x45, 0x00, 0x00, 0x20, 0x00, 0x00, 0x40, 0x00, 0x40, 0x01, 0xd2, 0x71, 0x11, 0x12,
+            0x13, 0x14, 0x21, 0x22, 0x23, 0x24, 0x08, 0x00, 0x8e, 0xfe, 0x12, 0x34, 0xab, 0xcd,
+            0xaa, 0x00, 0x00, 0xff,
+        ];
+        assert_eq!(
+            format!(
+                "{}",
+                PrettyPrinter::<Frame<&'static [u8]>>::new("", &&PACKET_BYTES[..]).compact()
+            ),
+            "EthernetII src=11-12-13-14-15-16 dst=01-02-03-04-05-06 type=IPv4 \
+             > IPv4 src=17.18.19.20 dst=33.34.35.36 proto=ICMP \
+             > ICMPv4 echo request id=4660 seq=43981 len=4"
+        );
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,154 @@
+/*! Access to the fields of packet buffers.
+
+The field accessors of the packet types read and write multi-octet fields
+through the functions of this module rather than by slicing the buffer and
+calling `byteorder` directly.
+
+The accessors of a packet type rely on `check_len` having proven that the
+buffer holds every field they touch. The functions of this module check that
+the field lies within the buffer and has the width of the value in every
+build, so that a wrong field offset, or a length check missing from
+`check_len`, is reported with the offending field and the location of the
+accessor instead of a slice index panic deep in `byteorder`.
+*/
+
+use byteorder::{ByteOrder, NetworkEndian};
+
+pub type Field = ::core::ops::Range<usize>;
+pub type Rest = ::core::ops::RangeFrom<usize>;
+
+#[cold]
+#[track_caller]
+fn invalid(len: usize, field: Field, width: usize) -> ! {
+    if field.start <= field.end && field.end - field.start != width {
+        panic!(
+            "field {}..{} does not hold a {}-octet value",
+            field.start, field.end, width
+        )
+    }
+    panic!(
+        "field {}..{} is out of bounds of a {}-octet buffer",
+        field.start, field.end, len
+    )
+}
+
+#[inline]
+#[track_caller]
+fn get(data: &[u8], field: Field, width: usize) -> &[u8] {
+    match data.get(field.clone()) {
+        Some(octets) if octets.len() == width => octets,
+        _ => invalid(data.len(), field, width),
+    }
+}
+
+#[inline]
+#[track_caller]
+fn get_mut(data: &mut [u8], field: Field, width: usize) -> &mut [u8] {
+    let len = data.len();
+    match data.get_mut(field.clone()) {
+        Some(octets) if octets.len() == width => octets,
+        _ => invalid(len, field, width),
+    }
+}
+
+/// Read a 16-bit big-endian value from `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn read_u16(data: &[u8], field: Field) -> u16 {
+    NetworkEndian::read_u16(get(data, field, 2))
+}
+
+/// Read a 24-bit big-endian value from `field` of `data`.
+#[cfg(feature = "proto-vxlan")]
+#[inline]
+#[track_caller]
+pub fn read_u24(data: &[u8], field: Field) -> u32 {
+    NetworkEndian::read_u24(get(data, field, 3))
+}
+
+/// Read a 32-bit big-endian value from `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn read_u32(data: &[u8], field: Field) -> u32 {
+    NetworkEndian::read_u32(get(data, field, 4))
+}
+
+/// Read a 32-bit big-endian signed value from `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn read_i32(data: &[u8], field: Field) -> i32 {
+    NetworkEndian::read_i32(get(data, field, 4))
+}
+
+/// Write a 16-bit big-endian value to `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn write_u16(data: &mut [u8], field: Field, value: u16) {
+    NetworkEndian::write_u16(get_mut(data, field, 2), value)
+}
+
+/// Write a 24-bit big-endian value to `field` of `data`.
+#[cfg(feature = "proto-vxlan")]
+#[inline]
+#[track_caller]
+pub fn write_u24(data: &mut [u8], field: Field, value: u32) {
+    NetworkEndian::write_u24(get_mut(data, field, 3), value)
+}
+
+/// Write a 32-bit big-endian value to `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn write_u32(data: &mut [u8], field: Field, value: u32) {
+    NetworkEndian::write_u32(get_mut(data, field, 4), value)
+}
+
+/// Write a 32-bit big-endian signed value to `field` of `data`.
+#[inline]
+#[track_caller]
+pub fn write_i32(data: &mut [u8], field: Field, value: i32) {
+    NetworkEndian::write_i32(get_mut(data, field, 4), value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_write() {
+        let mut data = [0; 8];
+        write_u16(&mut data, 0..2, 0x0102);
+        write_u32(&mut data, 4..8, 0x05060708);
+        assert_eq!(data, [1, 2, 0, 0, 5, 6, 7, 8]);
+        assert_eq!(read_u16(&data, 0..2), 0x0102);
+        assert_eq!(read_u32(&data, 4..8), 0x05060708);
+        write_i32(&mut data, 0..4, -2);
+        assert_eq!(read_i32(&data, 0..4), -2);
+    }
+
+    #[test]
+    #[cfg(feature = "proto-vxlan")]
+    fn test_read_write_u24() {
+        let mut data = [0; 4];
+        write_u24(&mut data, 1..4, 0x020304);
+        assert_eq!(data, [0, 2, 3, 4]);
+        assert_eq!(read_u24(&data, 1..4), 0x020304);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_out_of_bounds() {
+        read_u32(&[0; 6], 4..8);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not hold")]
+    fn test_wrong_width() {
+        read_u16(&[0; 6], 0..4);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_write_out_of_bounds() {
+        write_u16(&mut [0; 2], 1..3, 0);
+    }
+}
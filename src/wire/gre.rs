@@ -0,0 +1,516 @@
+use crate::wire::field::{read_u16, write_u16};
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::ip::checksum;
+use crate::wire::EthernetProtocol;
+
+/// A read/write wrapper around a Generic Routing Encapsulation packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of a GRE header
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |C| |K|S| Reserved0       | Ver |         Protocol Type         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |      Checksum (optional)      |       Reserved1 (Optional)    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         Key (optional)                        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                 Sequence Number (Optional)                    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// See RFC 2784 and RFC 2890 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const FLAGS_VER: Field = 0..2;
+    pub const PROTOCOL: Field = 2..4;
+    pub const OPTIONS: usize = 4;
+
+    pub const FLG_CHECKSUM: u16 = 0x8000;
+    pub const FLG_KEY: u16 = 0x2000;
+    pub const FLG_SEQ: u16 = 0x1000;
+    pub const VER_MASK: u16 = 0x0007;
+}
+
+/// The length of a GRE header without any of the optional fields.
+pub const HEADER_LEN: usize = field::OPTIONS;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with GRE packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    ///
+    /// The result of this check is invalidated by calling [set_flags].
+    ///
+    /// [set_flags]: #method.set_flags
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < HEADER_LEN || len < self.header_len() {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    #[inline]
+    fn flags_ver(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::FLAGS_VER)
+    }
+
+    /// Return the checksum present flag.
+    #[inline]
+    pub fn checksum_present(&self) -> bool {
+        self.flags_ver() & field::FLG_CHECKSUM != 0
+    }
+
+    /// Return the key present flag.
+    #[inline]
+    pub fn key_present(&self) -> bool {
+        self.flags_ver() & field::FLG_KEY != 0
+    }
+
+    /// Return the sequence number present flag.
+    #[inline]
+    pub fn sequence_present(&self) -> bool {
+        self.flags_ver() & field::FLG_SEQ != 0
+    }
+
+    /// Return the version field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        (self.flags_ver() & field::VER_MASK) as u8
+    }
+
+    /// Return the protocol type field.
+    #[inline]
+    pub fn protocol_type(&self) -> EthernetProtocol {
+        let data = self.buffer.as_ref();
+        EthernetProtocol::from(read_u16(data, field::PROTOCOL))
+    }
+
+    /// Return the length of the header, including the optional fields.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        let mut len = HEADER_LEN;
+        if self.checksum_present() {
+            len += 4;
+        }
+        if self.key_present() {
+            len += 4;
+        }
+        if self.sequence_present() {
+            len += 4;
+        }
+        len
+    }
+
+    fn checksum_offset(&self) -> Option<usize> {
+        if self.checksum_present() {
+            Some(field::OPTIONS)
+        } else {
+            None
+        }
+    }
+
+    fn key_offset(&self) -> Option<usize> {
+        if self.key_present() {
+            Some(field::OPTIONS + if self.checksum_present() { 4 } else { 0 })
+        } else {
+            None
+        }
+    }
+
+    fn sequence_offset(&self) -> Option<usize> {
+        if self.sequence_present() {
+            Some(self.header_len() - 4)
+        } else {
+            None
+        }
+    }
+
+    /// Return the checksum field, if present.
+    #[inline]
+    pub fn checksum(&self) -> Option<u16> {
+        let data = self.buffer.as_ref();
+        self.checksum_offset()
+            .map(|offset| NetworkEndian::read_u16(&data[offset..offset + 2]))
+    }
+
+    /// Return the key field, if present.
+    #[inline]
+    pub fn key(&self) -> Option<u32> {
+        let data = self.buffer.as_ref();
+        self.key_offset()
+            .map(|offset| NetworkEndian::read_u32(&data[offset..offset + 4]))
+    }
+
+    /// Return the sequence number field, if present.
+    #[inline]
+    pub fn sequence_number(&self) -> Option<u32> {
+        let data = self.buffer.as_ref();
+        self.sequence_offset()
+            .map(|offset| NetworkEndian::read_u32(&data[offset..offset + 4]))
+    }
+
+    /// Validate the packet checksum. Packets without a checksum are always valid.
+    pub fn verify_checksum(&self) -> bool {
+        if cfg!(fuzzing) {
+            return true;
+        }
+
+        !self.checksum_present() || checksum::data(self.buffer.as_ref()) == !0
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the payload.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let header_len = self.header_len();
+        let data = self.buffer.as_ref();
+        &data[header_len..]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the flags and version fields.
+    #[inline]
+    pub fn set_flags(&mut self, checksum: bool, key: bool, sequence: bool, version: u8) {
+        let mut raw = version as u16 & field::VER_MASK;
+        if checksum {
+            raw |= field::FLG_CHECKSUM;
+        }
+        if key {
+            raw |= field::FLG_KEY;
+        }
+        if sequence {
+            raw |= field::FLG_SEQ;
+        }
+        let data = self.buffer.as_mut();
+        write_u16(data, field::FLAGS_VER, raw)
+    }
+
+    /// Set the protocol type field.
+    #[inline]
+    pub fn set_protocol_type(&mut self, value: EthernetProtocol) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::PROTOCOL, value.into())
+    }
+
+    /// Set the checksum field, along with the reserved field following it.
+    ///
+    /// # Panics
+    /// This function panics if the checksum present flag is not set.
+    #[inline]
+    pub fn set_checksum(&mut self, value: u16) {
+        let offset = self.checksum_offset().unwrap();
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(&mut data[offset..offset + 2], value);
+        NetworkEndian::write_u16(&mut data[offset + 2..offset + 4], 0);
+    }
+
+    /// Set the key field.
+    ///
+    /// # Panics
+    /// This function panics if the key present flag is not set.
+    #[inline]
+    pub fn set_key(&mut self, value: u32) {
+        let offset = self.key_offset().unwrap();
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[offset..offset + 4], value)
+    }
+
+    /// Set the sequence number field.
+    ///
+    /// # Panics
+    /// This function panics if the sequence number present flag is not set.
+    #[inline]
+    pub fn set_sequence_number(&mut self, value: u32) {
+        let offset = self.sequence_offset().unwrap();
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u32(&mut data[offset..offset + 4], value)
+    }
+
+    /// Compute and fill in the checksum, covering the header and the payload.
+    ///
+    /// # Panics
+    /// This function panics if the checksum present flag is not set.
+    pub fn fill_checksum(&mut self) {
+        self.set_checksum(0);
+        let checksum = !checksum::data(self.buffer.as_ref());
+        self.set_checksum(checksum)
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let header_len = self.header_len();
+        let data = self.buffer.as_mut();
+        &mut data[header_len..]
+    }
+}
+
+/// A high-level representation of a GRE header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr {
+    /// Protocol type of the payload.
+    pub protocol_type: EthernetProtocol,
+    /// Whether the packet carries a checksum.
+    pub checksum: bool,
+    pub key: Option<u32>,
+    pub sequence_number: Option<u32>,
+}
+
+impl Repr {
+    /// Parse a GRE packet and return a high-level representation.
+    ///
+    /// Only version 0 packets are accepted. The checksum is verified if present.
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if packet.version() != 0 {
+            return Err(Error);
+        }
+        if !packet.verify_checksum() {
+            return Err(Error);
+        }
+        Ok(Repr {
+            protocol_type: packet.protocol_type(),
+            checksum: packet.checksum_present(),
+            key: packet.key(),
+            sequence_number: packet.sequence_number(),
+        })
+    }
+
+    /// Return the length of a header that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        let mut len = HEADER_LEN;
+        if self.checksum {
+            len += 4;
+        }
+        if self.key.is_some() {
+            len += 4;
+        }
+        if self.sequence_number.is_some() {
+            len += 4;
+        }
+        len
+    }
+
+    /// Emit a high-level representation into a GRE header.
+    ///
+    /// If a checksum is requested, the checksum field is zeroed; since it covers the
+    /// payload, it must be computed with [Packet::fill_checksum] once the payload is written.
+    ///
+    /// [Packet::fill_checksum]: struct.Packet.html#method.fill_checksum
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_flags(
+            self.checksum,
+            self.key.is_some(),
+            self.sequence_number.is_some(),
+            0,
+        );
+        packet.set_protocol_type(self.protocol_type);
+        if self.checksum {
+            packet.set_checksum(0);
+        }
+        if let Some(key) = self.key {
+            packet.set_key(key);
+        }
+        if let Some(seq) = self.sequence_number {
+            packet.set_sequence_number(seq);
+        }
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "GRE proto={}", self.protocol_type)?;
+        if let Some(key) = self.key {
+            write!(f, " key={}", key)?;
+        }
+        if let Some(seq) = self.sequence_number {
+            write!(f, " seq={}", seq)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "GRE ({}) proto={}", err, self.protocol_type()),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        let packet = match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => return write!(f, "{}({})", indent, err),
+            Ok(packet) => packet,
+        };
+        write!(f, "{}{}", indent, packet)?;
+
+        match packet.protocol_type() {
+            #[cfg(feature = "proto-ipv4")]
+            EthernetProtocol::Ipv4 => {
+                indent.increase(f)?;
+                super::Ipv4Packet::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+            }
+            #[cfg(feature = "proto-ipv6")]
+            EthernetProtocol::Ipv6 => {
+                indent.increase(f)?;
+                super::Ipv6Packet::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
+
+    static PACKET_BYTES: [u8; 8] = [0x00, 0x00, 0x08, 0x00, 0xaa, 0x00, 0x00, 0xff];
+
+    static PACKET_BYTES_OPTIONS: [u8; 20] = [
+        0xb0, 0x00, 0x08, 0x00, 0x9c, 0xce, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x00,
+        0x07, 0xaa, 0x00, 0x00, 0xff,
+    ];
+
+    static PAYLOAD_BYTES: [u8; 4] = [0xaa, 0x00, 0x00, 0xff];
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert!(!packet.checksum_present());
+        assert!(!packet.key_present());
+        assert!(!packet.sequence_present());
+        assert_eq!(packet.version(), 0);
+        assert_eq!(packet.protocol_type(), EthernetProtocol::Ipv4);
+        assert_eq!(packet.header_len(), 4);
+        assert_eq!(packet.payload(), &PAYLOAD_BYTES[..]);
+    }
+
+    #[test]
+    fn test_deconstruct_options() {
+        let packet = Packet::new_checked(&PACKET_BYTES_OPTIONS[..]).unwrap();
+        assert!(packet.checksum_present());
+        assert!(packet.key_present());
+        assert!(packet.sequence_present());
+        assert_eq!(packet.header_len(), 16);
+        assert_eq!(packet.checksum(), Some(0x9cce));
+        assert_eq!(packet.key(), Some(42));
+        assert_eq!(packet.sequence_number(), Some(7));
+        assert!(packet.verify_checksum());
+        assert_eq!(packet.payload(), &PAYLOAD_BYTES[..]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..3]), Err(Error));
+        assert_eq!(Packet::new_checked(&PACKET_BYTES_OPTIONS[..15]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES_OPTIONS[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr {
+                protocol_type: EthernetProtocol::Ipv4,
+                checksum: true,
+                key: Some(42),
+                sequence_number: Some(7),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_bad_checksum() {
+        let mut bytes = PACKET_BYTES_OPTIONS;
+        bytes[19] = 0;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = Repr {
+            protocol_type: EthernetProtocol::Ipv4,
+            checksum: true,
+            key: Some(42),
+            sequence_number: Some(7),
+        };
+        assert_eq!(repr.buffer_len(), 16);
+        let mut bytes = vec![0xa5; repr.buffer_len() + PAYLOAD_BYTES.len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&mut packet);
+        packet.payload_mut().copy_from_slice(&PAYLOAD_BYTES);
+        packet.fill_checksum();
+        assert_eq!(&bytes[..], &PACKET_BYTES_OPTIONS[..]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::gre_repr()) {
+            let mut bytes = vec![0; repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes[..]);
+            repr.emit(&mut packet);
+            if repr.checksum {
+                packet.fill_checksum();
+            }
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&packet).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..32)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&packet);
+            }
+        }
+    }
+}
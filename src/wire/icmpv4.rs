@@ -1,7 +1,7 @@
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
 use core::{cmp, fmt};
 
-use super::{Error, Result};
+use super::{Error, ParseMode, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::checksum;
 use crate::wire::{Ipv4Packet, Ipv4Repr};
@@ -236,7 +236,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Return the identifier field (for echo request and reply packets).
@@ -246,7 +246,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn echo_ident(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::ECHO_IDENT])
+        read_u16(data, field::ECHO_IDENT)
     }
 
     /// Return the sequence number field (for echo request and reply packets).
@@ -256,7 +256,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn echo_seq_no(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::ECHO_SEQNO])
+        read_u16(data, field::ECHO_SEQNO)
     }
 
     /// Return the header length.
@@ -312,7 +312,25 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
+    }
+
+    /// Clear any reserved fields in the message header.
+    ///
+    /// # Panics
+    /// This function panics if the message type has not been set.
+    /// See [set_msg_type].
+    ///
+    /// [set_msg_type]: #method.set_msg_type
+    #[inline]
+    pub fn clear_reserved(&mut self) {
+        match self.msg_type() {
+            Message::DstUnreachable | Message::TimeExceeded => {
+                let data = self.buffer.as_mut();
+                write_u32(data, field::UNUSED, 0);
+            }
+            _ => panic!("Message type not yet set!"),
+        }
     }
 
     /// Set the identifier field (for echo request and reply packets).
@@ -322,7 +340,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_echo_ident(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], value)
+        write_u16(data, field::ECHO_IDENT, value)
     }
 
     /// Set the sequence number field (for echo request and reply packets).
@@ -332,7 +350,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_echo_seq_no(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], value)
+        write_u16(data, field::ECHO_SEQNO, value)
     }
 
     /// Compute and fill in the header checksum.
@@ -395,6 +413,7 @@ impl<'a> Repr<'a> {
     pub fn parse<T>(
         packet: &Packet<&'a T>,
         checksum_caps: &ChecksumCapabilities,
+        mode: ParseMode,
     ) -> Result<Repr<'a>>
     where
         T: AsRef<[u8]> + ?Sized,
@@ -404,6 +423,23 @@ impl<'a> Repr<'a> {
             return Err(Error);
         }
 
+        // In the strict mode, the unused field of error messages must be zero, save for
+        // the next-hop MTU of Fragmentation Required messages (RFC 1191).
+        let unused_mask = match (packet.msg_type(), packet.msg_code()) {
+            (Message::DstUnreachable, code)
+                if DstUnreachable::from(code) == DstUnreachable::FragRequired =>
+            {
+                0xffff0000
+            }
+            (Message::DstUnreachable, _) | (Message::TimeExceeded, _) => 0xffffffff,
+            _ => 0,
+        };
+        if mode == ParseMode::Strict
+            && read_u32(packet.buffer.as_ref(), field::UNUSED) & unused_mask != 0
+        {
+            return Err(Error);
+        }
+
         match (packet.msg_type(), packet.msg_code()) {
             (Message::EchoRequest, 0) => Ok(Repr::EchoRequest {
                 ident: packet.echo_ident(),
@@ -467,6 +503,14 @@ impl<'a> Repr<'a> {
         }
     }
 
+    /// Return the length of the header that will be emitted from this high-level representation.
+    pub fn header_len(&self) -> usize {
+        match self {
+            &Repr::EchoRequest { .. } | &Repr::EchoReply { .. } => field::ECHO_SEQNO.end,
+            &Repr::DstUnreachable { .. } | &Repr::TimeExceeded { .. } => field::UNUSED.end,
+        }
+    }
+
     /// Return the length of a packet that will be emitted from this high-level representation.
     pub fn buffer_len(&self) -> usize {
         match self {
@@ -521,6 +565,7 @@ impl<'a> Repr<'a> {
             } => {
                 packet.set_msg_type(Message::DstUnreachable);
                 packet.set_msg_code(reason.into());
+                packet.clear_reserved();
 
                 let mut ip_packet = Ipv4Packet::new_unchecked(packet.data_mut());
                 header.emit(&mut ip_packet, checksum_caps);
@@ -535,6 +580,7 @@ impl<'a> Repr<'a> {
             } => {
                 packet.set_msg_type(Message::TimeExceeded);
                 packet.set_msg_code(reason.into());
+                packet.clear_reserved();
 
                 let mut ip_packet = Ipv4Packet::new_unchecked(packet.data_mut());
                 header.emit(&mut ip_packet, checksum_caps);
@@ -555,7 +601,11 @@ impl<'a> Repr<'a> {
 
 impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match Repr::parse(self, &ChecksumCapabilities::default()) {
+        match Repr::parse(
+            self,
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        ) {
             Ok(repr) => write!(f, "{}", repr),
             Err(err) => {
                 write!(f, "ICMPv4 ({})", err)?;
@@ -599,11 +649,19 @@ impl<'a> fmt::Display for Repr<'a> {
                 seq_no,
                 data.len()
             ),
-            Repr::DstUnreachable { reason, .. } => {
-                write!(f, "ICMPv4 destination unreachable ({})", reason)
+            Repr::DstUnreachable { reason, header, .. } => {
+                write!(f, "ICMPv4 destination unreachable ({})", reason)?;
+                if f.alternate() {
+                    write!(f, " orig=({})", header)?;
+                }
+                Ok(())
             }
-            Repr::TimeExceeded { reason, .. } => {
-                write!(f, "ICMPv4 time exceeded ({})", reason)
+            Repr::TimeExceeded { reason, header, .. } => {
+                write!(f, "ICMPv4 time exceeded ({})", reason)?;
+                if f.alternate() {
+                    write!(f, " orig=({})", header)?;
+                }
+                Ok(())
             }
         }
     }
@@ -636,6 +694,9 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
+    use crate::wire::{IpProtocol, Ipv4Address};
+    use proptest::{prop_assert_eq, proptest};
 
     static ECHO_PACKET_BYTES: [u8; 12] = [
         0x08, 0x00, 0x8e, 0xfe, 0x12, 0x34, 0xab, 0xcd, 0xaa, 0x00, 0x00, 0xff,
@@ -679,7 +740,12 @@ mod test {
     #[test]
     fn test_echo_parse() {
         let packet = Packet::new_unchecked(&ECHO_PACKET_BYTES[..]);
-        let repr = Repr::parse(&packet, &ChecksumCapabilities::default()).unwrap();
+        let repr = Repr::parse(
+            &packet,
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        )
+        .unwrap();
         assert_eq!(repr, echo_packet_repr());
     }
 
@@ -692,6 +758,68 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &ECHO_PACKET_BYTES[..]);
     }
 
+    #[test]
+    fn test_dst_unreachable_emit_ignores_buffer() {
+        let repr = Repr::DstUnreachable {
+            reason: DstUnreachable::PortUnreachable,
+            header: Ipv4Repr {
+                src_addr: Ipv4Address([10, 0, 0, 1]),
+                dst_addr: Ipv4Address([10, 0, 0, 2]),
+                next_header: IpProtocol::Udp,
+                payload_len: 4,
+                hop_limit: 64,
+            },
+            data: &ECHO_DATA_BYTES,
+        };
+        let mut zeroes = vec![0x00; repr.buffer_len()];
+        repr.emit(
+            &mut Packet::new_unchecked(&mut zeroes),
+            &ChecksumCapabilities::default(),
+        );
+        let mut ones = vec![0xff; repr.buffer_len()];
+        repr.emit(
+            &mut Packet::new_unchecked(&mut ones),
+            &ChecksumCapabilities::default(),
+        );
+        assert_eq!(zeroes, ones);
+        assert_eq!(repr.header_len(), 8);
+    }
+
+    #[test]
+    fn test_dst_unreachable_parse_strict() {
+        static DATA_BYTES: [u8; 8] = [0xaa; 8];
+        let caps = ChecksumCapabilities::default();
+        for (reason, unused, strict) in [
+            (DstUnreachable::PortUnreachable, 0x00000000, true),
+            (DstUnreachable::PortUnreachable, 0x00000001, false),
+            (DstUnreachable::FragRequired, 0x000005dc, true),
+            (DstUnreachable::FragRequired, 0x000105dc, false),
+        ] {
+            let repr = Repr::DstUnreachable {
+                reason,
+                header: Ipv4Repr {
+                    src_addr: Ipv4Address([10, 0, 0, 1]),
+                    dst_addr: Ipv4Address([10, 0, 0, 2]),
+                    next_header: IpProtocol::Udp,
+                    payload_len: 8,
+                    hop_limit: 64,
+                },
+                data: &DATA_BYTES,
+            };
+            let mut bytes = vec![0; repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes);
+            repr.emit(&mut packet, &caps);
+            write_u32(packet.buffer.as_mut(), field::UNUSED, unused);
+            packet.fill_checksum();
+            let packet = Packet::new_checked(&*packet.into_inner()).unwrap();
+            assert_eq!(Repr::parse(&packet, &caps, ParseMode::Permissive), Ok(repr));
+            assert_eq!(
+                Repr::parse(&packet, &caps, ParseMode::Strict).is_ok(),
+                strict
+            );
+        }
+    }
+
     #[test]
     fn test_check_len() {
         let bytes = [0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
@@ -699,4 +827,36 @@ mod test {
         assert_eq!(Packet::new_checked(&bytes[..4]), Err(Error));
         assert!(Packet::new_checked(&bytes[..]).is_ok());
     }
+
+    proptest! {
+        #[test]
+        fn test_echo_roundtrip(
+            request: bool,
+            ident: u16,
+            seq_no: u16,
+            data in strategy::packet_buffer(0..64),
+        ) {
+            let repr = if request {
+                Repr::EchoRequest { ident, seq_no, data: &data }
+            } else {
+                Repr::EchoReply { ident, seq_no, data: &data }
+            };
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(&mut Packet::new_unchecked(&mut bytes[..]), &ChecksumCapabilities::default());
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(
+                Repr::parse(&packet, &ChecksumCapabilities::default(), ParseMode::Strict).unwrap(),
+                repr
+            );
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..80)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                for mode in [ParseMode::Permissive, ParseMode::Strict] {
+                    let _ = Repr::parse(&packet, &ChecksumCapabilities::ignored(), mode);
+                }
+            }
+        }
+    }
 }
@@ -1,7 +1,7 @@
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
 use core::{cmp, fmt};
 
-use super::{Error, Result};
+use super::{Error, ParseMode, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::checksum;
 use crate::wire::MldRepr;
@@ -295,35 +295,35 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Return the identifier field (for echo request and reply packets).
     #[inline]
     pub fn echo_ident(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::ECHO_IDENT])
+        read_u16(data, field::ECHO_IDENT)
     }
 
     /// Return the sequence number field (for echo request and reply packets).
     #[inline]
     pub fn echo_seq_no(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::ECHO_SEQNO])
+        read_u16(data, field::ECHO_SEQNO)
     }
 
     /// Return the MTU field (for packet too big messages).
     #[inline]
     pub fn pkt_too_big_mtu(&self) -> u32 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u32(&data[field::MTU])
+        read_u32(data, field::MTU)
     }
 
     /// Return the pointer field (for parameter problem messages).
     #[inline]
     pub fn param_problem_ptr(&self) -> u32 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u32(&data[field::POINTER])
+        read_u32(data, field::POINTER)
     }
 
     /// Return the header length. The result depends on the value of
@@ -402,21 +402,23 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn clear_reserved(&mut self) {
         match self.msg_type() {
-            Message::RouterSolicit
+            Message::DstUnreachable
+            | Message::TimeExceeded
+            | Message::RouterSolicit
             | Message::NeighborSolicit
             | Message::NeighborAdvert
             | Message::Redirect => {
                 let data = self.buffer.as_mut();
-                NetworkEndian::write_u32(&mut data[field::UNUSED], 0);
+                write_u32(data, field::UNUSED, 0);
             }
             Message::MldQuery => {
                 let data = self.buffer.as_mut();
-                NetworkEndian::write_u16(&mut data[field::QUERY_RESV], 0);
+                write_u16(data, field::QUERY_RESV, 0);
                 data[field::SQRV] &= 0xf;
             }
             Message::MldReport => {
                 let data = self.buffer.as_mut();
-                NetworkEndian::write_u16(&mut data[field::RECORD_RESV], 0);
+                write_u16(data, field::RECORD_RESV, 0);
             }
             ty => panic!("Message type `{}` does not have any reserved fields.", ty),
         }
@@ -425,7 +427,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
     }
 
     /// Set the identifier field (for echo request and reply packets).
@@ -435,7 +437,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_echo_ident(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_IDENT], value)
+        write_u16(data, field::ECHO_IDENT, value)
     }
 
     /// Set the sequence number field (for echo request and reply packets).
@@ -445,7 +447,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_echo_seq_no(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ECHO_SEQNO], value)
+        write_u16(data, field::ECHO_SEQNO, value)
     }
 
     /// Set the MTU field (for packet too big messages).
@@ -455,7 +457,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_pkt_too_big_mtu(&mut self, value: u32) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::MTU], value)
+        write_u32(data, field::MTU, value)
     }
 
     /// Set the pointer field (for parameter problem messages).
@@ -465,7 +467,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_param_problem_ptr(&mut self, value: u32) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::POINTER], value)
+        write_u32(data, field::POINTER, value)
     }
 
     /// Compute and fill in the header checksum.
@@ -545,6 +547,7 @@ impl<'a> Repr<'a> {
         dst_addr: &IpAddress,
         packet: &Packet<&'a T>,
         checksum_caps: &ChecksumCapabilities,
+        mode: ParseMode,
     ) -> Result<Repr<'a>>
     where
         T: AsRef<[u8]> + ?Sized,
@@ -572,6 +575,16 @@ impl<'a> Repr<'a> {
         if checksum_caps.icmpv6.rx() && !packet.verify_checksum(src_addr, dst_addr) {
             return Err(Error);
         }
+        // In the strict mode, the unused field of error messages must be zero.
+        if mode == ParseMode::Strict
+            && matches!(
+                packet.msg_type(),
+                Message::DstUnreachable | Message::TimeExceeded
+            )
+            && read_u32(packet.buffer.as_ref(), field::UNUSED) != 0
+        {
+            return Err(Error);
+        }
 
         match (packet.msg_type(), packet.msg_code()) {
             (Message::DstUnreachable, code) => {
@@ -668,6 +681,7 @@ impl<'a> Repr<'a> {
             } => {
                 packet.set_msg_type(Message::DstUnreachable);
                 packet.set_msg_code(reason.into());
+                packet.clear_reserved();
 
                 emit_contained_packet(packet.payload_mut(), header, data);
             }
@@ -687,6 +701,7 @@ impl<'a> Repr<'a> {
             } => {
                 packet.set_msg_type(Message::TimeExceeded);
                 packet.set_msg_code(reason.into());
+                packet.clear_reserved();
 
                 emit_contained_packet(packet.payload_mut(), header, data);
             }
@@ -745,11 +760,78 @@ impl<'a> Repr<'a> {
     }
 }
 
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let header = match *self {
+            Repr::EchoRequest {
+                ident,
+                seq_no,
+                data,
+            } => {
+                return write!(
+                    f,
+                    "ICMPv6 echo request id={} seq={} len={}",
+                    ident,
+                    seq_no,
+                    data.len()
+                )
+            }
+            Repr::EchoReply {
+                ident,
+                seq_no,
+                data,
+            } => {
+                return write!(
+                    f,
+                    "ICMPv6 echo reply id={} seq={} len={}",
+                    ident,
+                    seq_no,
+                    data.len()
+                )
+            }
+            #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
+            Repr::Ndisc(ref ndisc) => return fmt::Display::fmt(ndisc, f),
+            Repr::Mld(ref mld) => return fmt::Display::fmt(mld, f),
+            Repr::DstUnreachable { reason, header, .. } => {
+                write!(f, "ICMPv6 destination unreachable ({})", reason)?;
+                header
+            }
+            Repr::PktTooBig { mtu, header, .. } => {
+                write!(f, "ICMPv6 packet too big mtu={}", mtu)?;
+                header
+            }
+            Repr::TimeExceeded { reason, header, .. } => {
+                write!(f, "ICMPv6 time exceeded ({})", reason)?;
+                header
+            }
+            Repr::ParamProblem {
+                reason,
+                pointer,
+                header,
+                ..
+            } => {
+                write!(
+                    f,
+                    "ICMPv6 parameter problem ({}) pointer={}",
+                    reason, pointer
+                )?;
+                header
+            }
+        };
+        if f.alternate() {
+            write!(f, " orig=({})", header)?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::wire::ip::test::{MOCK_IP_ADDR_1, MOCK_IP_ADDR_2};
+    use crate::wire::strategy;
     use crate::wire::{IpProtocol, Ipv6Address, Ipv6Repr};
+    use proptest::{prop_assert_eq, proptest};
 
     static ECHO_PACKET_BYTES: [u8; 12] = [
         0x80, 0x00, 0x19, 0xb3, 0x12, 0x34, 0xab, 0xcd, 0xaa, 0x00, 0x00, 0xff,
@@ -839,6 +921,7 @@ mod test {
             &MOCK_IP_ADDR_2,
             &packet,
             &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
         )
         .unwrap();
         assert_eq!(repr, echo_packet_repr());
@@ -892,6 +975,7 @@ mod test {
             &MOCK_IP_ADDR_2,
             &packet,
             &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
         )
         .unwrap();
         assert_eq!(repr, too_big_packet_repr());
@@ -910,4 +994,103 @@ mod test {
         );
         assert_eq!(&packet.into_inner()[..], &PKT_TOO_BIG_BYTES[..]);
     }
+
+    #[test]
+    fn test_time_exceeded_emit_ignores_buffer() {
+        let repr = Repr::TimeExceeded {
+            reason: TimeExceeded::HopLimitExceeded,
+            header: match too_big_packet_repr() {
+                Repr::PktTooBig { header, .. } => header,
+                _ => unreachable!(),
+            },
+            data: &PKT_TOO_BIG_UDP_PAYLOAD,
+        };
+        let emit = |fill| {
+            let mut bytes = vec![fill; repr.buffer_len()];
+            repr.emit(
+                &MOCK_IP_ADDR_1,
+                &MOCK_IP_ADDR_2,
+                &mut Packet::new_unchecked(&mut bytes),
+                &ChecksumCapabilities::default(),
+            );
+            bytes
+        };
+        assert_eq!(emit(0x00), emit(0xff));
+    }
+
+    #[test]
+    fn test_time_exceeded_parse_strict() {
+        let repr = Repr::TimeExceeded {
+            reason: TimeExceeded::HopLimitExceeded,
+            header: match too_big_packet_repr() {
+                Repr::PktTooBig { header, .. } => header,
+                _ => unreachable!(),
+            },
+            data: &PKT_TOO_BIG_UDP_PAYLOAD,
+        };
+        let caps = ChecksumCapabilities::default();
+        let mut bytes = vec![0; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit(&MOCK_IP_ADDR_1, &MOCK_IP_ADDR_2, &mut packet, &caps);
+        write_u32(packet.buffer.as_mut(), field::UNUSED, 1);
+        packet.fill_checksum(&MOCK_IP_ADDR_1, &MOCK_IP_ADDR_2);
+        let packet = Packet::new_checked(&*packet.into_inner()).unwrap();
+        let parse = |mode| Repr::parse(&MOCK_IP_ADDR_1, &MOCK_IP_ADDR_2, &packet, &caps, mode);
+        assert_eq!(parse(ParseMode::Permissive), Ok(repr));
+        assert_eq!(parse(ParseMode::Strict), Err(Error));
+    }
+
+    proptest! {
+        #[test]
+        fn test_echo_roundtrip(
+            request: bool,
+            ident: u16,
+            seq_no: u16,
+            data in strategy::packet_buffer(0..64),
+            src_addr in strategy::ipv6_address(),
+            dst_addr in strategy::ipv6_address(),
+        ) {
+            let (src_addr, dst_addr) = (IpAddress::Ipv6(src_addr), IpAddress::Ipv6(dst_addr));
+            let repr = if request {
+                Repr::EchoRequest { ident, seq_no, data: &data }
+            } else {
+                Repr::EchoReply { ident, seq_no, data: &data }
+            };
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(
+                &src_addr,
+                &dst_addr,
+                &mut Packet::new_unchecked(&mut bytes[..]),
+                &ChecksumCapabilities::default(),
+            );
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            let parsed = Repr::parse(
+                &src_addr,
+                &dst_addr,
+                &packet,
+                &ChecksumCapabilities::default(),
+                ParseMode::Strict,
+            );
+            prop_assert_eq!(parsed.unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(
+            bytes in strategy::packet_buffer(0..128),
+            src_addr in strategy::ipv6_address(),
+            dst_addr in strategy::ipv6_address(),
+        ) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                for mode in [ParseMode::Permissive, ParseMode::Strict] {
+                    let _ = Repr::parse(
+                        &IpAddress::Ipv6(src_addr),
+                        &IpAddress::Ipv6(dst_addr),
+                        &packet,
+                        &ChecksumCapabilities::ignored(),
+                        mode,
+                    );
+                }
+            }
+        }
+    }
 }
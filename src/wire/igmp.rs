@@ -1,3 +1,4 @@
+use crate::wire::field::{read_u16, write_u16};
 use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt;
 
@@ -8,7 +9,7 @@ use crate::wire::ip::checksum;
 use crate::wire::Ipv4Address;
 
 enum_with_unknown! {
-    /// Internet Group Management Protocol v1/v2 message version/type.
+    /// Internet Group Management Protocol v1/v2/v3 message version/type.
     pub enum Message(u8) {
         /// Membership Query
         MembershipQuery = 0x11,
@@ -17,11 +18,36 @@ enum_with_unknown! {
         /// Leave Group
         LeaveGroup = 0x17,
         /// Version 1 Membership Report
-        MembershipReportV1 = 0x12
+        MembershipReportV1 = 0x12,
+        /// Version 3 Membership Report
+        MembershipReportV3 = 0x22
     }
 }
 
-/// A read/write wrapper around an Internet Group Management Protocol v1/v2 packet buffer.
+enum_with_unknown! {
+    /// IGMPv3 Membership Report Group Record Type. See [RFC 3376 § 4.2.12] for
+    /// more details.
+    ///
+    /// [RFC 3376 § 4.2.12]: https://tools.ietf.org/html/rfc3376#section-4.2.12
+    pub enum RecordType(u8) {
+        /// Interface has a filter mode of INCLUDE for the specified multicast address.
+        ModeIsInclude   = 0x01,
+        /// Interface has a filter mode of EXCLUDE for the specified multicast address.
+        ModeIsExclude   = 0x02,
+        /// Interface has changed to a filter mode of INCLUDE for the specified
+        /// multicast address.
+        ChangeToInclude = 0x03,
+        /// Interface has changed to a filter mode of EXCLUDE for the specified
+        /// multicast address.
+        ChangeToExclude = 0x04,
+        /// Interface wishes to listen to the sources in the specified list.
+        AllowNewSources = 0x05,
+        /// Interface no longer wishes to listen to the sources in the specified list.
+        BlockOldSources = 0x06
+    }
+}
+
+/// A read/write wrapper around an Internet Group Management Protocol v1/v2/v3 packet buffer.
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Packet<T: AsRef<[u8]>> {
@@ -35,6 +61,23 @@ mod field {
     pub const MAX_RESP_CODE: usize = 1;
     pub const CHECKSUM: Field = 2..4;
     pub const GROUP_ADDRESS: Field = 4..8;
+
+    // IGMPv3 Membership Query
+    pub const QUERY_FLAGS: usize = 8;
+    pub const QUERY_QQIC: usize = 9;
+    pub const QUERY_NUM_SRCS: Field = 10..12;
+
+    // IGMPv3 Membership Report
+    pub const REPORT_NUM_RECORDS: Field = 6..8;
+
+    // IGMPv3 Group Record
+    pub const RECORD_TYPE: usize = 0;
+    pub const RECORD_AUX_DATA_LEN: usize = 1;
+    pub const RECORD_NUM_SRCS: Field = 2..4;
+    pub const RECORD_GROUP_ADDRESS: Field = 4..8;
+
+    pub const QUERY_S_FLAG: u8 = 0x08;
+    pub const QUERY_QRV_MASK: u8 = 0x07;
 }
 
 impl fmt::Display for Message {
@@ -44,16 +87,19 @@ impl fmt::Display for Message {
             Message::MembershipReportV2 => write!(f, "version 2 membership report"),
             Message::LeaveGroup => write!(f, "leave group"),
             Message::MembershipReportV1 => write!(f, "version 1 membership report"),
+            Message::MembershipReportV3 => write!(f, "version 3 membership report"),
             Message::Unknown(id) => write!(f, "{}", id),
         }
     }
 }
 
-/// Internet Group Management Protocol v1/v2 defined in [RFC 2236].
+/// Internet Group Management Protocol v1/v2 defined in [RFC 2236],
+/// and v3 defined in [RFC 3376].
 ///
 /// [RFC 2236]: https://tools.ietf.org/html/rfc2236
+/// [RFC 3376]: https://tools.ietf.org/html/rfc3376
 impl<T: AsRef<[u8]>> Packet<T> {
-    /// Imbue a raw octet buffer with IGMPv2 packet structure.
+    /// Imbue a raw octet buffer with IGMP packet structure.
     pub fn new_unchecked(buffer: T) -> Packet<T> {
         Packet { buffer }
     }
@@ -70,15 +116,34 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
     /// Ensure that no accessor method will panic if called.
     /// Returns `Err(Error)` if the buffer is too short.
+    ///
+    /// A membership query longer than 8 octets is an IGMPv3 query, and must be long
+    /// enough to contain the sources it indicates.
     pub fn check_len(&self) -> Result<()> {
         let len = self.buffer.as_ref().len();
-        if len < field::GROUP_ADDRESS.end as usize {
+        if len < field::GROUP_ADDRESS.end
+            || (self.msg_type() == Message::MembershipQuery
+                && len > field::GROUP_ADDRESS.end
+                && (len < field::QUERY_NUM_SRCS.end
+                    || len < field::QUERY_NUM_SRCS.end + self.num_srcs() as usize * 4))
+        {
             Err(Error)
         } else {
             Ok(())
         }
     }
 
+    /// Query whether the packet is an IGMPv3 membership query.
+    ///
+    /// See [RFC 3376 § 7.1].
+    ///
+    /// [RFC 3376 § 7.1]: https://tools.ietf.org/html/rfc3376#section-7.1
+    #[inline]
+    pub fn is_v3_query(&self) -> bool {
+        self.msg_type() == Message::MembershipQuery
+            && self.buffer.as_ref().len() >= field::QUERY_NUM_SRCS.end
+    }
+
     /// Consume the packet, returning the underlying buffer.
     pub fn into_inner(self) -> T {
         self.buffer
@@ -105,7 +170,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Return the source address field.
@@ -115,6 +180,41 @@ impl<T: AsRef<[u8]>> Packet<T> {
         Ipv4Address::from_bytes(&data[field::GROUP_ADDRESS])
     }
 
+    /// Return the suppress router-side processing flag of an IGMPv3 query.
+    #[inline]
+    pub fn s_flag(&self) -> bool {
+        let data = self.buffer.as_ref();
+        data[field::QUERY_FLAGS] & field::QUERY_S_FLAG != 0
+    }
+
+    /// Return the querier's robustness variable of an IGMPv3 query.
+    #[inline]
+    pub fn qrv(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::QUERY_FLAGS] & field::QUERY_QRV_MASK
+    }
+
+    /// Return the querier's query interval code of an IGMPv3 query.
+    #[inline]
+    pub fn qqic(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::QUERY_QQIC]
+    }
+
+    /// Return the number of sources of an IGMPv3 query.
+    #[inline]
+    pub fn num_srcs(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::QUERY_NUM_SRCS)
+    }
+
+    /// Return the number of group records of an IGMPv3 report.
+    #[inline]
+    pub fn num_group_records(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::REPORT_NUM_RECORDS)
+    }
+
     /// Validate the header checksum.
     ///
     /// # Fuzzing
@@ -129,6 +229,23 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 }
 
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the sources of an IGMPv3 query.
+    #[inline]
+    pub fn query_sources(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        let len = self.num_srcs() as usize * 4;
+        &data[field::QUERY_NUM_SRCS.end..field::QUERY_NUM_SRCS.end + len]
+    }
+
+    /// Return a pointer to the group records of an IGMPv3 report.
+    #[inline]
+    pub fn group_records(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::REPORT_NUM_RECORDS.end..]
+    }
+}
+
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     /// Set the message type field.
     #[inline]
@@ -149,7 +266,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
     }
 
     /// Set the group address field
@@ -159,6 +276,54 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         data[field::GROUP_ADDRESS].copy_from_slice(addr.as_bytes());
     }
 
+    /// Set the suppress router-side processing flag and the querier's robustness
+    /// variable of an IGMPv3 query.
+    #[inline]
+    pub fn set_s_flag_qrv(&mut self, s_flag: bool, qrv: u8) {
+        let data = self.buffer.as_mut();
+        let s_flag = if s_flag { field::QUERY_S_FLAG } else { 0 };
+        data[field::QUERY_FLAGS] = s_flag | (qrv & field::QUERY_QRV_MASK);
+    }
+
+    /// Set the querier's query interval code of an IGMPv3 query.
+    #[inline]
+    pub fn set_qqic(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::QUERY_QQIC] = value;
+    }
+
+    /// Set the number of sources of an IGMPv3 query.
+    #[inline]
+    pub fn set_num_srcs(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::QUERY_NUM_SRCS, value)
+    }
+
+    /// Set the number of group records of an IGMPv3 report, clearing the reserved field.
+    #[inline]
+    pub fn set_num_group_records(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_u16(
+            &mut data[field::GROUP_ADDRESS.start..field::REPORT_NUM_RECORDS.start],
+            0,
+        );
+        write_u16(data, field::REPORT_NUM_RECORDS, value)
+    }
+
+    /// Return a mutable pointer to the sources of an IGMPv3 query.
+    #[inline]
+    pub fn query_sources_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::QUERY_NUM_SRCS.end..]
+    }
+
+    /// Return a mutable pointer to the group records of an IGMPv3 report.
+    #[inline]
+    pub fn group_records_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::REPORT_NUM_RECORDS.end..]
+    }
+
     /// Compute and fill in the header checksum.
     pub fn fill_checksum(&mut self) {
         self.set_checksum(0);
@@ -170,10 +335,153 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     }
 }
 
-/// A high-level representation of an Internet Group Management Protocol v1/v2 header.
+/// A read/write wrapper around an IGMPv3 Membership Report Group Record.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct GroupRecord<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> GroupRecord<T> {
+    /// Imbue a raw octet buffer with a Group Record structure.
+    pub fn new_unchecked(buffer: T) -> Self {
+        Self { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Self> {
+        let record = Self::new_unchecked(buffer);
+        record.check_len()?;
+        Ok(record)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short to contain the record,
+    /// including its sources and auxiliary data.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::RECORD_GROUP_ADDRESS.end || len < self.record_len() {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the record, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the length of the record, including its sources and auxiliary data.
+    ///
+    /// Records are laid out back to back in a report, so this is also the offset of
+    /// the next record.
+    pub fn record_len(&self) -> usize {
+        field::RECORD_GROUP_ADDRESS.end
+            + self.num_srcs() as usize * 4
+            + self.aux_data_len() as usize * 4
+    }
+}
+
+/// Getters for an IGMPv3 Membership Report Group Record.
+/// See [RFC 3376 § 4.2.4].
+///
+/// [RFC 3376 § 4.2.4]: https://tools.ietf.org/html/rfc3376#section-4.2.4
+impl<T: AsRef<[u8]>> GroupRecord<T> {
+    /// Return the record type.
+    #[inline]
+    pub fn record_type(&self) -> RecordType {
+        let data = self.buffer.as_ref();
+        RecordType::from(data[field::RECORD_TYPE])
+    }
+
+    /// Return the length of the auxiliary data, in 32-bit words.
+    #[inline]
+    pub fn aux_data_len(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::RECORD_AUX_DATA_LEN]
+    }
+
+    /// Return the number of sources field.
+    #[inline]
+    pub fn num_srcs(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::RECORD_NUM_SRCS)
+    }
+
+    /// Return the multicast address field.
+    #[inline]
+    pub fn group_addr(&self) -> Ipv4Address {
+        let data = self.buffer.as_ref();
+        Ipv4Address::from_bytes(&data[field::RECORD_GROUP_ADDRESS])
+    }
+
+    /// Return the source address at `index`.
+    ///
+    /// # Panics
+    /// This function panics if `index` is not less than the number of sources.
+    #[inline]
+    pub fn source(&self, index: usize) -> Ipv4Address {
+        assert!(index < self.num_srcs() as usize);
+        let data = self.buffer.as_ref();
+        let start = field::RECORD_GROUP_ADDRESS.end + index * 4;
+        Ipv4Address::from_bytes(&data[start..start + 4])
+    }
+}
+
+/// Setters for an IGMPv3 Membership Report Group Record.
+/// See [RFC 3376 § 4.2.4].
+///
+/// [RFC 3376 § 4.2.4]: https://tools.ietf.org/html/rfc3376#section-4.2.4
+impl<T: AsRef<[u8]> + AsMut<[u8]>> GroupRecord<T> {
+    /// Set the record type.
+    #[inline]
+    pub fn set_record_type(&mut self, value: RecordType) {
+        let data = self.buffer.as_mut();
+        data[field::RECORD_TYPE] = value.into();
+    }
+
+    /// Set the length of the auxiliary data, in 32-bit words.
+    #[inline]
+    pub fn set_aux_data_len(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::RECORD_AUX_DATA_LEN] = value;
+    }
+
+    /// Set the number of sources field.
+    #[inline]
+    pub fn set_num_srcs(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::RECORD_NUM_SRCS, value);
+    }
+
+    /// Set the multicast address field.
+    #[inline]
+    pub fn set_group_addr(&mut self, addr: Ipv4Address) {
+        let data = self.buffer.as_mut();
+        data[field::RECORD_GROUP_ADDRESS].copy_from_slice(addr.as_bytes());
+    }
+
+    /// Set the source address at `index`.
+    ///
+    /// # Panics
+    /// This function panics if `index` is not less than the number of sources.
+    #[inline]
+    pub fn set_source(&mut self, index: usize, addr: Ipv4Address) {
+        assert!(index < self.num_srcs() as usize);
+        let data = self.buffer.as_mut();
+        let start = field::RECORD_GROUP_ADDRESS.end + index * 4;
+        data[start..start + 4].copy_from_slice(addr.as_bytes());
+    }
+}
+
+/// A high-level representation of an Internet Group Management Protocol v1/v2/v3 header.
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub enum Repr {
+pub enum Repr<'a> {
     MembershipQuery {
         max_resp_time: Duration,
         group_addr: Ipv4Address,
@@ -186,6 +494,22 @@ pub enum Repr {
     LeaveGroup {
         group_addr: Ipv4Address,
     },
+    /// An IGMPv3 membership query. `data` holds `num_srcs` source addresses.
+    MembershipQueryV3 {
+        max_resp_time: Duration,
+        group_addr: Ipv4Address,
+        s_flag: bool,
+        qrv: u8,
+        qqic: u8,
+        num_srcs: u16,
+        data: &'a [u8],
+    },
+    /// An IGMPv3 membership report. `data` holds `num_group_records` group records,
+    /// which can be accessed using [GroupRecord].
+    MembershipReportV3 {
+        num_group_records: u16,
+        data: &'a [u8],
+    },
 }
 
 /// Type of IGMP membership report version
@@ -196,15 +520,25 @@ pub enum IgmpVersion {
     Version1,
     /// IGMPv2
     Version2,
+    /// IGMPv3
+    Version3,
 }
 
-impl Repr {
-    /// Parse an Internet Group Management Protocol v1/v2 packet and return
+impl<'a> Repr<'a> {
+    /// Parse an Internet Group Management Protocol v1/v2/v3 packet and return
     /// a high-level representation.
-    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
     where
         T: AsRef<[u8]> + ?Sized,
     {
+        // IGMPv3 reports carry no group address, only a reserved field.
+        if packet.msg_type() == Message::MembershipReportV3 {
+            return Ok(Repr::MembershipReportV3 {
+                num_group_records: packet.num_group_records(),
+                data: packet.group_records(),
+            });
+        }
+
         // Check if the address is 0.0.0.0 or multicast
         let addr = packet.group_addr();
         if !addr.is_unspecified() && !addr.is_multicast() {
@@ -213,6 +547,15 @@ impl Repr {
 
         // construct a packet based on the Type field
         match packet.msg_type() {
+            Message::MembershipQuery if packet.is_v3_query() => Ok(Repr::MembershipQueryV3 {
+                max_resp_time: max_resp_code_to_duration(packet.max_resp_code()),
+                group_addr: addr,
+                s_flag: packet.s_flag(),
+                qrv: packet.qrv(),
+                qqic: packet.qqic(),
+                num_srcs: packet.num_srcs(),
+                data: packet.query_sources(),
+            }),
             Message::MembershipQuery => {
                 let max_resp_time = max_resp_code_to_duration(packet.max_resp_code());
                 // See RFC 3376: 7.1. Query Version Distinctions
@@ -247,11 +590,15 @@ impl Repr {
 
     /// Return the length of a packet that will be emitted from this high-level representation.
     pub fn buffer_len(&self) -> usize {
-        // always 8 bytes
-        field::GROUP_ADDRESS.end
+        match *self {
+            Repr::MembershipQueryV3 { data, .. } => field::QUERY_NUM_SRCS.end + data.len(),
+            Repr::MembershipReportV3 { data, .. } => field::REPORT_NUM_RECORDS.end + data.len(),
+            // always 8 bytes
+            _ => field::GROUP_ADDRESS.end,
+        }
     }
 
-    /// Emit a high-level representation into an Internet Group Management Protocol v2 packet.
+    /// Emit a high-level representation into an Internet Group Management Protocol packet.
     pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
     where
         T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
@@ -265,7 +612,7 @@ impl Repr {
                 packet.set_msg_type(Message::MembershipQuery);
                 match version {
                     IgmpVersion::Version1 => packet.set_max_resp_code(0),
-                    IgmpVersion::Version2 => {
+                    IgmpVersion::Version2 | IgmpVersion::Version3 => {
                         packet.set_max_resp_code(duration_to_max_resp_code(max_resp_time))
                     }
                 }
@@ -278,6 +625,9 @@ impl Repr {
                 match version {
                     IgmpVersion::Version1 => packet.set_msg_type(Message::MembershipReportV1),
                     IgmpVersion::Version2 => packet.set_msg_type(Message::MembershipReportV2),
+                    // v3 reports carry group records, see `Repr::MembershipReportV3`;
+                    // v3 routers also accept v2 reports
+                    IgmpVersion::Version3 => packet.set_msg_type(Message::MembershipReportV2),
                 };
                 packet.set_max_resp_code(0);
                 packet.set_group_address(group_addr);
@@ -286,6 +636,32 @@ impl Repr {
                 packet.set_msg_type(Message::LeaveGroup);
                 packet.set_group_address(group_addr);
             }
+            Repr::MembershipQueryV3 {
+                max_resp_time,
+                group_addr,
+                s_flag,
+                qrv,
+                qqic,
+                num_srcs,
+                data,
+            } => {
+                packet.set_msg_type(Message::MembershipQuery);
+                packet.set_max_resp_code(duration_to_max_resp_code(max_resp_time));
+                packet.set_group_address(group_addr);
+                packet.set_s_flag_qrv(s_flag, qrv);
+                packet.set_qqic(qqic);
+                packet.set_num_srcs(num_srcs);
+                packet.query_sources_mut()[..data.len()].copy_from_slice(data);
+            }
+            Repr::MembershipReportV3 {
+                num_group_records,
+                data,
+            } => {
+                packet.set_msg_type(Message::MembershipReportV3);
+                packet.set_max_resp_code(0);
+                packet.set_num_group_records(num_group_records);
+                packet.group_records_mut()[..data.len()].copy_from_slice(data);
+            }
         }
 
         packet.fill_checksum()
@@ -330,7 +706,7 @@ impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
     }
 }
 
-impl<'a> fmt::Display for Repr {
+impl<'a> fmt::Display for Repr<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Repr::MembershipQuery {
@@ -353,6 +729,23 @@ impl<'a> fmt::Display for Repr {
             Repr::LeaveGroup { group_addr } => {
                 write!(f, "IGMP leave group group_addr={})", group_addr)
             }
+            Repr::MembershipQueryV3 {
+                max_resp_time,
+                group_addr,
+                num_srcs,
+                ..
+            } => write!(
+                f,
+                "IGMP membership query max_resp_time={} group_addr={} num_srcs={} version=Version3",
+                max_resp_time, group_addr, num_srcs
+            ),
+            Repr::MembershipReportV3 {
+                num_group_records, ..
+            } => write!(
+                f,
+                "IGMP membership report num_group_records={} version=Version3",
+                num_group_records
+            ),
         }
     }
 }
@@ -366,8 +759,8 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
         indent: &mut PrettyIndent,
     ) -> fmt::Result {
         match Packet::new_checked(buffer) {
-            Err(err) => writeln!(f, "{}({})", indent, err),
-            Ok(packet) => writeln!(f, "{}{}", indent, packet),
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
         }
     }
 }
@@ -427,6 +820,112 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &REPORT_PACKET_BYTES[..]);
     }
 
+    static QUERY_V3_PACKET_BYTES: [u8; 16] = [
+        0x11, 0x64, 0x00, 0x00, 0xe8, 0x01, 0x01, 0x01, 0x0a, 0x7d, 0x00, 0x01, 0xc0, 0x00, 0x02,
+        0x01,
+    ];
+
+    static REPORT_V3_PACKET_BYTES: [u8; 20] = [
+        0x22, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x01, 0xe8, 0x01, 0x01,
+        0x01, 0xc0, 0x00, 0x02, 0x01,
+    ];
+
+    #[test]
+    fn test_query_v3_deconstruct() {
+        let packet = Packet::new_checked(&QUERY_V3_PACKET_BYTES[..]).unwrap();
+        assert!(packet.is_v3_query());
+        assert!(packet.s_flag());
+        assert_eq!(packet.qrv(), 2);
+        assert_eq!(packet.qqic(), 0x7d);
+        assert_eq!(packet.num_srcs(), 1);
+        assert_eq!(
+            Repr::parse(&packet).unwrap(),
+            Repr::MembershipQueryV3 {
+                max_resp_time: Duration::from_secs(10),
+                group_addr: Ipv4Address::new(232, 1, 1, 1),
+                s_flag: true,
+                qrv: 2,
+                qqic: 0x7d,
+                num_srcs: 1,
+                data: &[0xc0, 0x00, 0x02, 0x01],
+            }
+        );
+    }
+
+    #[test]
+    fn test_query_v3_construct() {
+        let repr = Repr::parse(&Packet::new_unchecked(&QUERY_V3_PACKET_BYTES[..])).unwrap();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut packet);
+        assert!(packet.verify_checksum());
+        packet.set_checksum(0);
+        assert_eq!(&bytes[..], &QUERY_V3_PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_query_v3_truncated() {
+        assert_eq!(
+            Packet::new_checked(&QUERY_V3_PACKET_BYTES[..10]).unwrap_err(),
+            Error
+        );
+        assert_eq!(
+            Packet::new_checked(&QUERY_V3_PACKET_BYTES[..15]).unwrap_err(),
+            Error
+        );
+    }
+
+    #[test]
+    fn test_report_v3_deconstruct() {
+        let packet = Packet::new_checked(&REPORT_V3_PACKET_BYTES[..]).unwrap();
+        assert_eq!(packet.msg_type(), Message::MembershipReportV3);
+        assert_eq!(packet.num_group_records(), 1);
+        let record = GroupRecord::new_checked(packet.group_records()).unwrap();
+        assert_eq!(record.record_type(), RecordType::ModeIsInclude);
+        assert_eq!(record.aux_data_len(), 0);
+        assert_eq!(record.num_srcs(), 1);
+        assert_eq!(record.group_addr(), Ipv4Address::new(232, 1, 1, 1));
+        assert_eq!(record.source(0), Ipv4Address::new(192, 0, 2, 1));
+        assert_eq!(record.record_len(), 12);
+        assert_eq!(
+            Repr::parse(&packet).unwrap(),
+            Repr::MembershipReportV3 {
+                num_group_records: 1,
+                data: &REPORT_V3_PACKET_BYTES[8..],
+            }
+        );
+    }
+
+    #[test]
+    fn test_report_v3_construct() {
+        let mut record_bytes = vec![0xa5; 12];
+        let mut record = GroupRecord::new_unchecked(&mut record_bytes[..]);
+        record.set_record_type(RecordType::ModeIsInclude);
+        record.set_aux_data_len(0);
+        record.set_num_srcs(1);
+        record.set_group_addr(Ipv4Address::new(232, 1, 1, 1));
+        record.set_source(0, Ipv4Address::new(192, 0, 2, 1));
+
+        let repr = Repr::MembershipReportV3 {
+            num_group_records: 1,
+            data: &record_bytes,
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut packet);
+        assert!(packet.verify_checksum());
+        packet.set_checksum(0);
+        assert_eq!(&bytes[..], &REPORT_V3_PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_group_record_truncated() {
+        assert_eq!(
+            GroupRecord::new_checked(&REPORT_V3_PACKET_BYTES[8..19]).unwrap_err(),
+            Error
+        );
+    }
+
     #[test]
     fn max_resp_time_to_duration_and_back() {
         for i in 0..256usize {
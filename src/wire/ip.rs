@@ -51,13 +51,19 @@ enum_with_unknown! {
         HopByHop  = 0x00,
         Icmp      = 0x01,
         Igmp      = 0x02,
+        IpIp      = 0x04,
         Tcp       = 0x06,
         Udp       = 0x11,
+        Ipv6      = 0x29,
         Ipv6Route = 0x2b,
         Ipv6Frag  = 0x2c,
+        Gre       = 0x2f,
+        IpSecEsp  = 0x32,
+        IpSecAh   = 0x33,
         Icmpv6    = 0x3a,
         Ipv6NoNxt = 0x3b,
-        Ipv6Opts  = 0x3c
+        Ipv6Opts  = 0x3c,
+        UdpLite   = 0x88
     }
 }
 
@@ -67,13 +73,19 @@ impl fmt::Display for Protocol {
             Protocol::HopByHop => write!(f, "Hop-by-Hop"),
             Protocol::Icmp => write!(f, "ICMP"),
             Protocol::Igmp => write!(f, "IGMP"),
+            Protocol::IpIp => write!(f, "IPIP"),
             Protocol::Tcp => write!(f, "TCP"),
             Protocol::Udp => write!(f, "UDP"),
+            Protocol::Ipv6 => write!(f, "IPv6"),
             Protocol::Ipv6Route => write!(f, "IPv6-Route"),
             Protocol::Ipv6Frag => write!(f, "IPv6-Frag"),
+            Protocol::Gre => write!(f, "GRE"),
+            Protocol::IpSecEsp => write!(f, "ESP"),
+            Protocol::IpSecAh => write!(f, "AH"),
             Protocol::Icmpv6 => write!(f, "ICMPv6"),
             Protocol::Ipv6NoNxt => write!(f, "IPv6-NoNxt"),
             Protocol::Ipv6Opts => write!(f, "IPv6-Opts"),
+            Protocol::UdpLite => write!(f, "UDP-Lite"),
             Protocol::Unknown(id) => write!(f, "0x{:02x}", id),
         }
     }
@@ -93,14 +105,23 @@ pub enum Address {
 impl Address {
     /// Create an address wrapping an IPv4 address with the given octets.
     #[cfg(feature = "proto-ipv4")]
-    pub fn v4(a0: u8, a1: u8, a2: u8, a3: u8) -> Address {
+    pub const fn v4(a0: u8, a1: u8, a2: u8, a3: u8) -> Address {
         Address::Ipv4(Ipv4Address::new(a0, a1, a2, a3))
     }
 
     /// Create an address wrapping an IPv6 address with the given octets.
     #[cfg(feature = "proto-ipv6")]
     #[allow(clippy::too_many_arguments)]
-    pub fn v6(a0: u16, a1: u16, a2: u16, a3: u16, a4: u16, a5: u16, a6: u16, a7: u16) -> Address {
+    pub const fn v6(
+        a0: u16,
+        a1: u16,
+        a2: u16,
+        a3: u16,
+        a4: u16,
+        a5: u16,
+        a6: u16,
+        a7: u16,
+    ) -> Address {
         Address::Ipv6(Ipv6Address::new(a0, a1, a2, a3, a4, a5, a6, a7))
     }
 
@@ -279,7 +300,7 @@ impl Cidr {
     ///
     /// # Panics
     /// This function panics if the given prefix length is invalid for the given address.
-    pub fn new(addr: Address, prefix_len: u8) -> Cidr {
+    pub const fn new(addr: Address, prefix_len: u8) -> Cidr {
         match addr {
             #[cfg(feature = "proto-ipv4")]
             Address::Ipv4(addr) => Cidr::Ipv4(Ipv4Cidr::new(addr, prefix_len)),
@@ -386,8 +407,8 @@ pub struct Endpoint {
 
 impl Endpoint {
     /// Create an endpoint address from given address and port.
-    pub fn new(addr: Address, port: u16) -> Endpoint {
-        Endpoint { addr: addr, port }
+    pub const fn new(addr: Address, port: u16) -> Endpoint {
+        Endpoint { addr, port }
     }
 }
 
@@ -423,7 +444,13 @@ impl From<::std::net::SocketAddrV6> for Endpoint {
 
 impl fmt::Display for Endpoint {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.addr, self.port)
+        match self.addr {
+            #[cfg(feature = "proto-ipv4")]
+            Address::Ipv4(_) => write!(f, "{}:{}", self.addr, self.port),
+            // Brackets set the port apart from the address, as in RFC 3986 § 3.2.2.
+            #[cfg(feature = "proto-ipv6")]
+            Address::Ipv6(_) => write!(f, "[{}]:{}", self.addr, self.port),
+        }
     }
 }
 
@@ -666,15 +693,22 @@ impl Repr {
     }
 
     /// Return the length of a header that will be emitted from this high-level representation.
-    pub fn buffer_len(&self) -> usize {
+    pub fn header_len(&self) -> usize {
         match *self {
             #[cfg(feature = "proto-ipv4")]
-            Repr::Ipv4(repr) => repr.buffer_len(),
+            Repr::Ipv4(repr) => repr.header_len(),
             #[cfg(feature = "proto-ipv6")]
-            Repr::Ipv6(repr) => repr.buffer_len(),
+            Repr::Ipv6(repr) => repr.header_len(),
         }
     }
 
+    /// Return the length of a header that will be emitted from this high-level representation.
+    ///
+    /// This is the same as `header_len()`, since the payload is emitted separately.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len()
+    }
+
     /// Emit this high-level representation into a buffer.
     pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(
         &self,
@@ -689,6 +723,25 @@ impl Repr {
         }
     }
 
+    /// Emit this high-level representation into a buffer, followed by a payload of
+    /// `payload_len()` octets written by `emit_payload`.
+    ///
+    /// The header is emitted first, so `emit_payload` may compute a transport checksum
+    /// over the pseudo-header with the addresses of this representation.
+    ///
+    /// # Panics
+    /// This function panics if the buffer is shorter than `total_len()`.
+    pub fn emit_with_payload(
+        &self,
+        buffer: &mut [u8],
+        checksum_caps: &ChecksumCapabilities,
+        emit_payload: impl FnOnce(&mut [u8]),
+    ) {
+        let (header, payload) = buffer[..self.total_len()].split_at_mut(self.header_len());
+        self.emit(header, checksum_caps);
+        emit_payload(payload)
+    }
+
     /// Return the total length of a packet that will be emitted from this
     /// high-level representation.
     ///
@@ -708,36 +761,53 @@ pub mod checksum {
         ((sum >> 16) as u16) + (sum as u16)
     }
 
+    fn propagate_carries_wide(word: u64) -> u16 {
+        let sum = (word >> 32) + (word & 0xffff_ffff);
+        let sum = (sum >> 32) + (sum & 0xffff_ffff);
+        propagate_carries(sum as u32)
+    }
+
     /// Compute an RFC 1071 compliant checksum (without the final complement).
+    ///
+    /// The data is summed 32 bits at a time into a 64-bit accumulator, which is folded
+    /// once at the end; per RFC 1071 § 2 (B), this yields the same result as summing
+    /// 16-bit words.
     pub fn data(mut data: &[u8]) -> u16 {
-        let mut accum = 0;
+        let mut accum: u64 = 0;
 
         // For each 32-byte chunk...
         const CHUNK_SIZE: usize = 32;
         while data.len() >= CHUNK_SIZE {
-            let mut d = &data[..CHUNK_SIZE];
-            // ... take by 2 bytes and sum them.
-            while d.len() >= 2 {
-                accum += NetworkEndian::read_u16(d) as u32;
-                d = &d[2..];
-            }
+            // ... take by 4 bytes and sum them, unrolled.
+            accum += NetworkEndian::read_u32(&data[0..4]) as u64;
+            accum += NetworkEndian::read_u32(&data[4..8]) as u64;
+            accum += NetworkEndian::read_u32(&data[8..12]) as u64;
+            accum += NetworkEndian::read_u32(&data[12..16]) as u64;
+            accum += NetworkEndian::read_u32(&data[16..20]) as u64;
+            accum += NetworkEndian::read_u32(&data[20..24]) as u64;
+            accum += NetworkEndian::read_u32(&data[24..28]) as u64;
+            accum += NetworkEndian::read_u32(&data[28..32]) as u64;
 
             data = &data[CHUNK_SIZE..];
         }
 
         // Sum the rest that does not fit the last 32-byte chunk,
-        // taking by 2 bytes.
-        while data.len() >= 2 {
-            accum += NetworkEndian::read_u16(data) as u32;
+        // taking by 4 bytes, and then by 2 bytes.
+        while data.len() >= 4 {
+            accum += NetworkEndian::read_u32(data) as u64;
+            data = &data[4..];
+        }
+        if data.len() >= 2 {
+            accum += NetworkEndian::read_u16(data) as u64;
             data = &data[2..];
         }
 
         // Add the last remaining odd byte, if any.
         if let Some(&value) = data.first() {
-            accum += (value as u32) << 8;
+            accum += (value as u64) << 8;
         }
 
-        propagate_carries(accum)
+        propagate_carries_wide(accum)
     }
 
     /// Combine several RFC 1071 compliant checksums.
@@ -749,6 +819,33 @@ pub mod checksum {
         propagate_carries(accum)
     }
 
+    /// Update a checksum field after some of the data it covers was changed from `old`
+    /// to `new`, without recomputing it over all of the data, as described in [RFC 1624].
+    ///
+    /// `checksum` is the value of the checksum field, i.e. the complement of the sum.
+    /// The changed data must start at an even offset in the checksummed data, and `old`
+    /// and `new` must have the same length. Since the IP pseudo header is covered by the
+    /// TCP and UDP checksums, this also updates them when an address is rewritten.
+    ///
+    /// An UDP checksum of zero means that the checksum is not used, and must not be
+    /// updated.
+    ///
+    /// [RFC 1624]: https://tools.ietf.org/html/rfc1624
+    ///
+    /// # Panics
+    /// This function panics if `old` and `new` have different lengths.
+    pub fn update(checksum: u16, old: &[u8], new: &[u8]) -> u16 {
+        assert_eq!(old.len(), new.len());
+        // HC' = ~(~HC + ~m + m'), eqn. 3 of RFC 1624.
+        !combine(&[!checksum, !data(old), data(new)])
+    }
+
+    /// Update a checksum field after a 16-bit word it covers was changed from `old`
+    /// to `new`. See [update](fn.update.html).
+    pub fn update_word(checksum: u16, old: u16, new: u16) -> u16 {
+        !combine(&[!checksum, !old, new])
+    }
+
     /// Compute an IP pseudo header checksum.
     pub fn pseudo_header(
         src_addr: &Address,
@@ -808,12 +905,11 @@ pub fn pretty_print_ip_payload<T: Into<Repr>>(
     ip_repr: T,
     payload: &[u8],
 ) -> fmt::Result {
-    #[cfg(feature = "proto-ipv4")]
     use super::pretty_print::PrettyPrint;
     use crate::wire::ip::checksum::format_checksum;
     #[cfg(feature = "proto-ipv4")]
     use crate::wire::Icmpv4Packet;
-    use crate::wire::{TcpPacket, TcpRepr, UdpPacket, UdpRepr};
+    use crate::wire::{ParseMode, TcpPacket, TcpRepr, UdpPacket, UdpRepr};
 
     let checksum_caps = ChecksumCapabilities::ignored();
     let repr = ip_repr.into();
@@ -845,7 +941,66 @@ pub fn pretty_print_ip_payload<T: Into<Repr>>(
                             )?;
                             let valid =
                                 udp_packet.verify_checksum(&repr.src_addr(), &repr.dst_addr());
-                            format_checksum(f, valid)
+                            format_checksum(f, valid)?;
+                            #[cfg(feature = "proto-vxlan")]
+                            if udp_repr.dst_port == crate::wire::VXLAN_UDP_PORT {
+                                use crate::wire::VxlanPacket;
+                                indent.increase(f)?;
+                                VxlanPacket::<&[u8]>::pretty_print(
+                                    &udp_packet.payload(),
+                                    f,
+                                    indent,
+                                )?;
+                            }
+                            #[cfg(feature = "proto-ptp")]
+                            if udp_repr.dst_port == crate::wire::PTP_EVENT_PORT
+                                || udp_repr.dst_port == crate::wire::PTP_GENERAL_PORT
+                            {
+                                use crate::wire::PtpPacket;
+                                indent.increase(f)?;
+                                PtpPacket::<&[u8]>::pretty_print(&udp_packet.payload(), f, indent)?;
+                            }
+                            #[cfg(feature = "proto-tftp")]
+                            if udp_repr.dst_port == crate::wire::TFTP_SERVER_PORT {
+                                use crate::wire::TftpPacket;
+                                indent.increase(f)?;
+                                TftpPacket::<&[u8]>::pretty_print(
+                                    &udp_packet.payload(),
+                                    f,
+                                    indent,
+                                )?;
+                            }
+                            #[cfg(feature = "proto-ntp")]
+                            if udp_repr.src_port == crate::wire::NTP_SERVER_PORT
+                                || udp_repr.dst_port == crate::wire::NTP_SERVER_PORT
+                            {
+                                use crate::wire::NtpPacket;
+                                indent.increase(f)?;
+                                NtpPacket::<&[u8]>::pretty_print(&udp_packet.payload(), f, indent)?;
+                            }
+                            #[cfg(feature = "proto-llmnr")]
+                            if udp_repr.src_port == crate::wire::LLMNR_UDP_PORT
+                                || udp_repr.dst_port == crate::wire::LLMNR_UDP_PORT
+                            {
+                                use crate::wire::LlmnrPacket;
+                                indent.increase(f)?;
+                                LlmnrPacket::<&[u8]>::pretty_print(
+                                    &udp_packet.payload(),
+                                    f,
+                                    indent,
+                                )?;
+                            }
+                            #[cfg(feature = "proto-nbns")]
+                            if udp_repr.dst_port == crate::wire::NBNS_UDP_PORT {
+                                use crate::wire::NbnsPacket;
+                                indent.increase(f)?;
+                                NbnsPacket::<&[u8]>::pretty_print(
+                                    &udp_packet.payload(),
+                                    f,
+                                    indent,
+                                )?;
+                            }
+                            Ok(())
                         }
                     }
                 }
@@ -861,6 +1016,7 @@ pub fn pretty_print_ip_payload<T: Into<Repr>>(
                         &repr.src_addr(),
                         &repr.dst_addr(),
                         &checksum_caps,
+                        ParseMode::Permissive,
                     ) {
                         Err(err) => write!(f, "{}{} ({})", indent, tcp_packet, err),
                         Ok(tcp_repr) => {
@@ -873,10 +1029,101 @@ pub fn pretty_print_ip_payload<T: Into<Repr>>(
                 }
             }
         }
+        #[cfg(feature = "proto-ipv4")]
+        Protocol::IpIp => {
+            use crate::wire::Ipv4Packet;
+            indent.increase(f)?;
+            Ipv4Packet::<&[u8]>::pretty_print(&payload, f, indent)
+        }
+        #[cfg(feature = "proto-ipv6")]
+        Protocol::Ipv6 => {
+            use crate::wire::Ipv6Packet;
+            indent.increase(f)?;
+            Ipv6Packet::<&[u8]>::pretty_print(&payload, f, indent)
+        }
+        #[cfg(feature = "proto-udplite")]
+        Protocol::UdpLite => {
+            use crate::wire::{UdpLitePacket, UdpLiteRepr};
+            indent.increase(f)?;
+            match UdpLitePacket::<&[u8]>::new_checked(payload) {
+                Err(err) => write!(f, "{}({})", indent, err),
+                Ok(udplite_packet) => {
+                    match UdpLiteRepr::parse(
+                        &udplite_packet,
+                        &repr.src_addr(),
+                        &repr.dst_addr(),
+                        &checksum_caps,
+                    ) {
+                        Err(err) => write!(f, "{}{} ({})", indent, udplite_packet, err),
+                        Ok(udplite_repr) => write!(
+                            f,
+                            "{}{} len={}",
+                            indent,
+                            udplite_repr,
+                            udplite_packet.payload().len()
+                        ),
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "proto-ipv6")]
+        Protocol::Icmpv6 => {
+            use crate::wire::{Icmpv6Packet, Icmpv6Repr};
+            indent.increase(f)?;
+            match Icmpv6Packet::<&[u8]>::new_checked(payload) {
+                Err(err) => write!(f, "{}({})", indent, err),
+                Ok(icmp_packet) => {
+                    match Icmpv6Repr::parse(
+                        &repr.src_addr(),
+                        &repr.dst_addr(),
+                        &icmp_packet,
+                        &checksum_caps,
+                        ParseMode::Permissive,
+                    ) {
+                        Err(err) => write!(f, "{}ICMPv6 ({})", indent, err),
+                        Ok(icmp_repr) => {
+                            write!(f, "{}{}", indent, icmp_repr)?;
+                            let valid =
+                                icmp_packet.verify_checksum(&repr.src_addr(), &repr.dst_addr());
+                            format_checksum(f, valid)
+                        }
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "proto-gre")]
+        Protocol::Gre => {
+            use crate::wire::GrePacket;
+            indent.increase(f)?;
+            GrePacket::<&[u8]>::pretty_print(&payload, f, indent)
+        }
+        #[cfg(feature = "proto-ipsec")]
+        Protocol::IpSecAh => {
+            use crate::wire::IpSecAuthHeaderPacket;
+            indent.increase(f)?;
+            IpSecAuthHeaderPacket::<&[u8]>::pretty_print(&payload, f, indent)
+        }
+        #[cfg(feature = "proto-ipsec")]
+        Protocol::IpSecEsp => {
+            use crate::wire::IpSecEspPacket;
+            indent.increase(f)?;
+            IpSecEspPacket::<&[u8]>::pretty_print(&payload, f, indent)
+        }
         _ => Ok(()),
     }
 }
 
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "proto-ipv4")]
+            Repr::Ipv4(repr) => fmt::Display::fmt(repr, f),
+            #[cfg(feature = "proto-ipv6")]
+            Repr::Ipv6(repr) => fmt::Display::fmt(repr, f),
+        }
+    }
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     #![allow(unused)]
@@ -916,6 +1163,53 @@ pub(crate) mod test {
     #[cfg(feature = "proto-ipv4")]
     use crate::wire::{Ipv4Address, Ipv4Repr};
 
+    #[test]
+    fn checksum_data() {
+        // Reference implementation, summing 16-bit words.
+        fn reference(data: &[u8]) -> u16 {
+            let mut accum: u32 = 0;
+            for word in data.chunks(2) {
+                accum += (word[0] as u32) << 8;
+                if let Some(&value) = word.get(1) {
+                    accum += value as u32;
+                }
+                accum = (accum >> 16) + (accum & 0xffff);
+            }
+            accum as u16
+        }
+
+        let mut data = [0u8; 300];
+        let mut seed: u32 = 0x1234_5678;
+        for byte in data.iter_mut() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            *byte = (seed >> 16) as u8;
+        }
+        for len in 0..data.len() {
+            assert_eq!(checksum::data(&data[..len]), reference(&data[..len]));
+        }
+        let ones = [0xff; 300];
+        assert_eq!(checksum::data(&ones), 0xffff);
+    }
+
+    #[test]
+    fn checksum_update() {
+        let mut data = [0x45, 0x00, 0x12, 0x34, 0xff, 0xff, 0x00, 0x01, 0x80, 0x06];
+        let old_checksum = !checksum::data(&data);
+
+        data[2..6].copy_from_slice(&[0x00, 0x00, 0xab, 0xcd]);
+        assert_eq!(
+            checksum::update(old_checksum, &[0x12, 0x34, 0xff, 0xff], &data[2..6]),
+            !checksum::data(&data)
+        );
+
+        let new_checksum = !checksum::data(&data);
+        data[8] = 0x7f;
+        assert_eq!(
+            checksum::update_word(new_checksum, 0x8006, 0x7f06),
+            !checksum::data(&data)
+        );
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn to_prefix_len_ipv4() {
@@ -992,4 +1286,67 @@ pub(crate) mod test {
             .prefix_len()
         );
     }
+
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn emit_with_payload() {
+        use crate::wire::{ParseMode, UdpPacket, UdpRepr};
+
+        let ip_repr = Repr::Ipv4(Ipv4Repr {
+            src_addr: Ipv4Address::new(192, 168, 1, 1),
+            dst_addr: Ipv4Address::new(192, 168, 1, 2),
+            next_header: IpProtocol::Udp,
+            payload_len: 8 + 4,
+            hop_limit: 64,
+        });
+        let udp_repr = UdpRepr {
+            src_port: 1234,
+            dst_port: 5678,
+        };
+        let caps = ChecksumCapabilities::default();
+
+        let mut bytes = vec![0xa5; ip_repr.total_len()];
+        ip_repr.emit_with_payload(&mut bytes, &caps, |payload| {
+            udp_repr.emit(
+                &mut UdpPacket::new_unchecked(payload),
+                &ip_repr.src_addr(),
+                &ip_repr.dst_addr(),
+                4,
+                |data| data.copy_from_slice(b"data"),
+                &caps,
+            )
+        });
+
+        let ip_packet = Ipv4Packet::new_checked(&bytes).unwrap();
+        assert_eq!(
+            Repr::Ipv4(Ipv4Repr::parse(&ip_packet, &caps, ParseMode::Permissive).unwrap()),
+            ip_repr
+        );
+        let udp_packet = UdpPacket::new_checked(ip_packet.payload()).unwrap();
+        assert_eq!(
+            UdpRepr::parse(&udp_packet, &ip_repr.src_addr(), &ip_repr.dst_addr(), &caps),
+            Ok(udp_repr)
+        );
+        assert_eq!(udp_packet.payload(), b"data");
+    }
+
+    #[test]
+    #[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+    fn const_constructors() {
+        static CIDRS: [Cidr; 2] = [
+            Cidr::new(Address::v4(192, 168, 1, 1), 24),
+            Cidr::new(Address::v6(0xfe80, 0, 0, 0, 0, 0, 0, 1), 64),
+        ];
+        const ENDPOINT: Endpoint = Endpoint::new(Address::v4(192, 168, 1, 1), 80);
+
+        assert_eq!(
+            CIDRS[0],
+            Cidr::Ipv4(Ipv4Cidr::new(Ipv4Address::new(192, 168, 1, 1), 24))
+        );
+        assert_eq!(
+            CIDRS[1].address().as_bytes(),
+            [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]
+        );
+        assert_eq!(ENDPOINT.to_string(), "192.168.1.1:80");
+    }
 }
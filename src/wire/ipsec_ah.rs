@@ -0,0 +1,338 @@
+use crate::wire::field::{read_u32, write_u16, write_u32};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::IpProtocol;
+
+/// A read/write wrapper around an IPsec Authentication Header packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of an Authentication Header
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// | Next Header   |  Payload Len  |          RESERVED             |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                 Security Parameters Index (SPI)               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                    Sequence Number Field                      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                                                               |
+// +                Integrity Check Value-ICV (variable)           |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The Payload Len field is the length of the header in 4-octet units, minus 2.
+//
+// See RFC 4302 § 2 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const NXT_HDR: usize = 0;
+    pub const PAYLOAD_LEN: usize = 1;
+    pub const RESERVED: Field = 2..4;
+    pub const SPI: Field = 4..8;
+    pub const SEQ_NUM: Field = 8..12;
+    pub const ICV: usize = 12;
+}
+
+/// The length of an Authentication Header without the Integrity Check Value.
+pub const HEADER_LEN: usize = field::ICV;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with Authentication Header packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short, or the header length
+    /// is shorter than the fixed part of the header.
+    ///
+    /// The result of this check is invalidated by calling [set_payload_len].
+    ///
+    /// [set_payload_len]: #method.set_payload_len
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < HEADER_LEN || len < self.header_len() || self.header_len() < HEADER_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the next header field.
+    #[inline]
+    pub fn next_header(&self) -> IpProtocol {
+        let data = self.buffer.as_ref();
+        IpProtocol::from(data[field::NXT_HDR])
+    }
+
+    /// Return the payload length field.
+    #[inline]
+    pub fn payload_len(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::PAYLOAD_LEN]
+    }
+
+    /// Return the length of the header, including the Integrity Check Value.
+    #[inline]
+    pub fn header_len(&self) -> usize {
+        (self.payload_len() as usize + 2) * 4
+    }
+
+    /// Return the Security Parameters Index field.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::SPI)
+    }
+
+    /// Return the sequence number field.
+    #[inline]
+    pub fn sequence_number(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::SEQ_NUM)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return the Integrity Check Value.
+    #[inline]
+    pub fn integrity_check_value(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::ICV..self.header_len()]
+    }
+
+    /// Return a pointer to the payload.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[self.header_len()..]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the next header field.
+    #[inline]
+    pub fn set_next_header(&mut self, value: IpProtocol) {
+        let data = self.buffer.as_mut();
+        data[field::NXT_HDR] = value.into()
+    }
+
+    /// Set the payload length field.
+    #[inline]
+    pub fn set_payload_len(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::PAYLOAD_LEN] = value
+    }
+
+    /// Clear the reserved field.
+    #[inline]
+    pub fn clear_reserved(&mut self) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::RESERVED, 0)
+    }
+
+    /// Set the Security Parameters Index field.
+    #[inline]
+    pub fn set_spi(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::SPI, value)
+    }
+
+    /// Set the sequence number field.
+    #[inline]
+    pub fn set_sequence_number(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::SEQ_NUM, value)
+    }
+
+    /// Return a mutable pointer to the Integrity Check Value.
+    #[inline]
+    pub fn integrity_check_value_mut(&mut self) -> &mut [u8] {
+        let len = self.header_len();
+        let data = self.buffer.as_mut();
+        &mut data[field::ICV..len]
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let len = self.header_len();
+        let data = self.buffer.as_mut();
+        &mut data[len..]
+    }
+}
+
+/// A high-level representation of an IPsec Authentication Header.
+///
+/// The Integrity Check Value is not verified.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    pub next_header: IpProtocol,
+    pub spi: u32,
+    pub sequence_number: u32,
+    pub integrity_check_value: &'a [u8],
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an Authentication Header and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        Ok(Repr {
+            next_header: packet.next_header(),
+            spi: packet.spi(),
+            sequence_number: packet.sequence_number(),
+            integrity_check_value: packet.integrity_check_value(),
+        })
+    }
+
+    /// Return the length of a header that will be emitted from this high-level representation.
+    ///
+    /// The length of the Integrity Check Value must be a multiple of 4 octets, and
+    /// of 8 octets when the header is carried in IPv6.
+    pub fn buffer_len(&self) -> usize {
+        HEADER_LEN + self.integrity_check_value.len()
+    }
+
+    /// Emit a high-level representation into an Authentication Header.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_next_header(self.next_header);
+        packet.set_payload_len((self.buffer_len() / 4 - 2) as u8);
+        packet.clear_reserved();
+        packet.set_spi(self.spi);
+        packet.set_sequence_number(self.sequence_number);
+        packet
+            .integrity_check_value_mut()
+            .copy_from_slice(self.integrity_check_value);
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AH spi=0x{:08x} seq={} nxt_hdr={} icv_len={}",
+            self.spi,
+            self.sequence_number,
+            self.next_header,
+            self.integrity_check_value.len()
+        )
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "AH ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+
+    // An AH with a 96-bit ICV (HMAC-SHA1-96) followed by a UDP header.
+    static PACKET_BYTES: [u8; 32] = [
+        0x11, 0x04, 0x00, 0x00, 0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x2a, 0x01, 0x02, 0x03,
+        0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x04, 0xd2, 0x00, 0x35, 0x00, 0x08,
+        0x00, 0x00,
+    ];
+
+    static ICV: [u8; 12] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+    ];
+
+    fn packet_repr() -> Repr<'static> {
+        Repr {
+            next_header: IpProtocol::Udp,
+            spi: 0x1001,
+            sequence_number: 42,
+            integrity_check_value: &ICV,
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(packet.next_header(), IpProtocol::Udp);
+        assert_eq!(packet.payload_len(), 4);
+        assert_eq!(packet.header_len(), 24);
+        assert_eq!(packet.spi(), 0x1001);
+        assert_eq!(packet.sequence_number(), 42);
+        assert_eq!(packet.integrity_check_value(), &ICV[..]);
+        assert_eq!(packet.payload(), &PACKET_BYTES[24..]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(Repr::parse(&packet), Ok(packet_repr()));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = packet_repr();
+        let mut bytes = vec![0xa5; PACKET_BYTES.len()];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(&mut packet);
+        packet.payload_mut().copy_from_slice(&PACKET_BYTES[24..]);
+        assert_eq!(repr.buffer_len(), 24);
+        assert_eq!(bytes, &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..11]), Err(Error));
+        // The ICV extends past the end of the buffer.
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..20]), Err(Error));
+        // The header is shorter than its fixed part.
+        let mut bytes = PACKET_BYTES;
+        bytes[1] = 0;
+        assert_eq!(Packet::new_checked(&bytes[..]), Err(Error));
+    }
+}
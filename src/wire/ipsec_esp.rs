@@ -0,0 +1,400 @@
+use crate::wire::field::{read_u32, write_u32};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::IpProtocol;
+
+/// A read/write wrapper around an IPsec Encapsulating Security Payload packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of an ESP packet
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |               Security Parameters Index (SPI)                 |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                      Sequence Number                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                    Payload Data (variable)                    |
+// ~                                                               ~
+// |                                                               |
+// +               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |               |     Padding (0-255 bytes)                     |
+// +-+-+-+-+-+-+-+-+               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               |  Pad Length   | Next Header   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |         Integrity Check Value-ICV   (variable)                |
+// ~                                                               ~
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Everything following the sequence number is encrypted, unless the NULL encryption
+// algorithm (RFC 2410) is used. The length of the ICV depends on the integrity algorithm
+// negotiated for the security association, and is not indicated in the packet.
+//
+// See RFC 4303 § 2 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const SPI: Field = 0..4;
+    pub const SEQ_NUM: Field = 4..8;
+    pub const PAYLOAD: Rest = 8..;
+}
+
+/// The length of the ESP header.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+/// The length of the ESP trailer, excluding padding and the Integrity Check Value.
+pub const TRAILER_LEN: usize = 2;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with ESP packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called, except for the accessors
+    /// of the trailer, which require [check_trailer_len].
+    /// Returns `Err(Error)` if the buffer is shorter than the header.
+    ///
+    /// [check_trailer_len]: #method.check_trailer_len
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Ensure that no accessor method of the trailer will panic if called with an
+    /// Integrity Check Value of `icv_len` octets.
+    /// Returns `Err(Error)` if the buffer is too short to contain the trailer and the
+    /// padding it indicates.
+    ///
+    /// The trailer is only meaningful if the payload is not encrypted.
+    pub fn check_trailer_len(&self, icv_len: usize) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < HEADER_LEN + TRAILER_LEN + icv_len
+            || len < HEADER_LEN + TRAILER_LEN + icv_len + self.pad_len(icv_len) as usize
+        {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the Security Parameters Index field.
+    #[inline]
+    pub fn spi(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::SPI)
+    }
+
+    /// Return the sequence number field.
+    #[inline]
+    pub fn sequence_number(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::SEQ_NUM)
+    }
+
+    #[inline]
+    fn trailer_start(&self, icv_len: usize) -> usize {
+        self.buffer.as_ref().len() - icv_len - TRAILER_LEN
+    }
+
+    /// Return the pad length field, given the length of the Integrity Check Value.
+    #[inline]
+    pub fn pad_len(&self, icv_len: usize) -> u8 {
+        let data = self.buffer.as_ref();
+        data[self.trailer_start(icv_len)]
+    }
+
+    /// Return the next header field, given the length of the Integrity Check Value.
+    #[inline]
+    pub fn next_header(&self, icv_len: usize) -> IpProtocol {
+        let data = self.buffer.as_ref();
+        IpProtocol::from(data[self.trailer_start(icv_len) + 1])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to everything following the header, including the trailer
+    /// and the Integrity Check Value.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+
+    /// Return the payload data, without padding, given the length of the
+    /// Integrity Check Value.
+    #[inline]
+    pub fn payload_data(&self, icv_len: usize) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        let end = self.trailer_start(icv_len) - self.pad_len(icv_len) as usize;
+        &data[HEADER_LEN..end]
+    }
+
+    /// Return the Integrity Check Value, given its length.
+    #[inline]
+    pub fn integrity_check_value(&self, icv_len: usize) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[data.len() - icv_len..]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the Security Parameters Index field.
+    #[inline]
+    pub fn set_spi(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::SPI, value)
+    }
+
+    /// Set the sequence number field.
+    #[inline]
+    pub fn set_sequence_number(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::SEQ_NUM, value)
+    }
+
+    /// Set the pad length field, given the length of the Integrity Check Value.
+    #[inline]
+    pub fn set_pad_len(&mut self, icv_len: usize, value: u8) {
+        let start = self.trailer_start(icv_len);
+        let data = self.buffer.as_mut();
+        data[start] = value
+    }
+
+    /// Set the next header field, given the length of the Integrity Check Value.
+    #[inline]
+    pub fn set_next_header(&mut self, icv_len: usize, value: IpProtocol) {
+        let start = self.trailer_start(icv_len);
+        let data = self.buffer.as_mut();
+        data[start + 1] = value.into()
+    }
+
+    /// Return a mutable pointer to everything following the header.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+
+    /// Return a mutable pointer to the Integrity Check Value, given its length.
+    #[inline]
+    pub fn integrity_check_value_mut(&mut self, icv_len: usize) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        let len = data.len();
+        &mut data[len - icv_len..]
+    }
+}
+
+/// A high-level representation of an unencrypted (ESP-NULL) IPsec ESP packet.
+///
+/// The Integrity Check Value is not verified.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    pub spi: u32,
+    pub sequence_number: u32,
+    pub next_header: IpProtocol,
+    pub payload: &'a [u8],
+    pub integrity_check_value: &'a [u8],
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an ESP-NULL packet with an Integrity Check Value of `icv_len` octets,
+    /// and return a high-level representation.
+    ///
+    /// Since the length of the ICV is not indicated in the packet, it must be known
+    /// from the security association.
+    pub fn parse<T>(packet: &Packet<&'a T>, icv_len: usize) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        packet.check_trailer_len(icv_len)?;
+        Ok(Repr {
+            spi: packet.spi(),
+            sequence_number: packet.sequence_number(),
+            next_header: packet.next_header(icv_len),
+            payload: packet.payload_data(icv_len),
+            integrity_check_value: packet.integrity_check_value(icv_len),
+        })
+    }
+
+    /// Return the length of the padding that aligns the trailer to 4 octets.
+    fn pad_len(&self) -> usize {
+        (4 - (self.payload.len() + TRAILER_LEN) % 4) % 4
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        HEADER_LEN
+            + self.payload.len()
+            + self.pad_len()
+            + TRAILER_LEN
+            + self.integrity_check_value.len()
+    }
+
+    /// Emit a high-level representation into an ESP-NULL packet.
+    ///
+    /// The padding uses the default monotonically increasing pattern of RFC 4303 § 2.4.
+    /// Since the trailer is located from the end of the packet, the buffer must be
+    /// exactly [buffer_len] octets long.
+    ///
+    /// [buffer_len]: #method.buffer_len
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        let icv_len = self.integrity_check_value.len();
+        let pad_len = self.pad_len();
+        packet.set_spi(self.spi);
+        packet.set_sequence_number(self.sequence_number);
+        let payload = packet.payload_mut();
+        payload[..self.payload.len()].copy_from_slice(self.payload);
+        for (i, pad) in payload[self.payload.len()..self.payload.len() + pad_len]
+            .iter_mut()
+            .enumerate()
+        {
+            *pad = i as u8 + 1;
+        }
+        packet.set_pad_len(icv_len, pad_len as u8);
+        packet.set_next_header(icv_len, self.next_header);
+        packet
+            .integrity_check_value_mut(icv_len)
+            .copy_from_slice(self.integrity_check_value);
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ESP spi=0x{:08x} seq={} nxt_hdr={} len={}",
+            self.spi,
+            self.sequence_number,
+            self.next_header,
+            self.payload.len()
+        )
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The trailer cannot be located without knowing the length of the ICV.
+        match self.check_len() {
+            Ok(()) => write!(
+                f,
+                "ESP spi=0x{:08x} seq={} len={}",
+                self.spi(),
+                self.sequence_number(),
+                self.payload().len()
+            ),
+            Err(err) => write!(f, "ESP ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+
+    // An ESP-NULL packet carrying a 9-octet payload, with a 96-bit ICV.
+    static PACKET_BYTES: [u8; 32] = [
+        0x00, 0x00, 0x10, 0x01, 0x00, 0x00, 0x00, 0x2a, 0x04, 0xd2, 0x00, 0x35, 0x00, 0x09, 0x00,
+        0x00, 0xaa, 0x01, 0x01, 0x11, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+        0x0b, 0x0c,
+    ];
+
+    static PAYLOAD: [u8; 9] = [0x04, 0xd2, 0x00, 0x35, 0x00, 0x09, 0x00, 0x00, 0xaa];
+
+    static ICV: [u8; 12] = [
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+    ];
+
+    fn packet_repr() -> Repr<'static> {
+        Repr {
+            spi: 0x1001,
+            sequence_number: 42,
+            next_header: IpProtocol::Udp,
+            payload: &PAYLOAD,
+            integrity_check_value: &ICV,
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(packet.spi(), 0x1001);
+        assert_eq!(packet.sequence_number(), 42);
+        assert_eq!(packet.payload(), &PACKET_BYTES[8..]);
+        assert_eq!(packet.check_trailer_len(12), Ok(()));
+        assert_eq!(packet.pad_len(12), 1);
+        assert_eq!(packet.next_header(12), IpProtocol::Udp);
+        assert_eq!(packet.payload_data(12), &PAYLOAD[..]);
+        assert_eq!(packet.integrity_check_value(12), &ICV[..]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(Repr::parse(&packet, 12), Ok(packet_repr()));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = packet_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(bytes, &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_check_trailer_len() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(packet.check_trailer_len(31), Err(Error));
+        // The padding extends past the header.
+        let mut bytes = PACKET_BYTES;
+        bytes[18] = 0xff;
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(Repr::parse(&packet, 12), Err(Error));
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..7]), Err(Error));
+    }
+}
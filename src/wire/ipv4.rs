@@ -1,7 +1,8 @@
+use crate::wire::field::{read_u16, write_u16};
 use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt;
 
-use super::{Error, Result};
+use super::{Error, ParseMode, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::{checksum, pretty_print_ip_payload};
 
@@ -45,6 +46,9 @@ impl Address {
     /// All multicast-capable routers
     pub const MULTICAST_ALL_ROUTERS: Address = Address([224, 0, 0, 2]);
 
+    /// All IGMPv3-capable multicast routers
+    pub const MULTICAST_ALL_IGMPV3_ROUTERS: Address = Address([224, 0, 0, 22]);
+
     /// Construct an IPv4 address from parts.
     pub const fn new(a0: u8, a1: u8, a2: u8, a3: u8) -> Address {
         Address([a0, a1, a2, a3])
@@ -372,35 +376,42 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn total_len(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::LENGTH])
+        read_u16(data, field::LENGTH)
     }
 
     /// Return the fragment identification field.
     #[inline]
     pub fn ident(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::IDENT])
+        read_u16(data, field::IDENT)
+    }
+
+    /// Return the reserved flag, which must be zero.
+    #[inline]
+    pub fn reserved_flag(&self) -> bool {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::FLG_OFF) & 0x8000 != 0
     }
 
     /// Return the "don't fragment" flag.
     #[inline]
     pub fn dont_frag(&self) -> bool {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::FLG_OFF]) & 0x4000 != 0
+        read_u16(data, field::FLG_OFF) & 0x4000 != 0
     }
 
     /// Return the "more fragments" flag.
     #[inline]
     pub fn more_frags(&self) -> bool {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::FLG_OFF]) & 0x2000 != 0
+        read_u16(data, field::FLG_OFF) & 0x2000 != 0
     }
 
     /// Return the fragment offset, in octets.
     #[inline]
     pub fn frag_offset(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::FLG_OFF]) << 3
+        read_u16(data, field::FLG_OFF) << 3
     }
 
     /// Return the time to live field.
@@ -421,7 +432,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Return the source address field.
@@ -462,6 +473,14 @@ impl<T: AsRef<[u8]>> Packet<T> {
 }
 
 impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the options.
+    #[inline]
+    pub fn options(&self) -> &'a [u8] {
+        let range = field::DST_ADDR.end..self.header_len() as usize;
+        let data = self.buffer.as_ref();
+        &data[range]
+    }
+
     /// Return a pointer to the payload.
     #[inline]
     pub fn payload(&self) -> &'a [u8] {
@@ -502,50 +521,50 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_total_len(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+        write_u16(data, field::LENGTH, value)
     }
 
     /// Set the fragment identification field.
     #[inline]
     pub fn set_ident(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::IDENT], value)
+        write_u16(data, field::IDENT, value)
     }
 
     /// Clear the entire flags field.
     #[inline]
     pub fn clear_flags(&mut self) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLG_OFF]);
+        let raw = read_u16(data, field::FLG_OFF);
         let raw = raw & !0xe000;
-        NetworkEndian::write_u16(&mut data[field::FLG_OFF], raw);
+        write_u16(data, field::FLG_OFF, raw);
     }
 
     /// Set the "don't fragment" flag.
     #[inline]
     pub fn set_dont_frag(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLG_OFF]);
+        let raw = read_u16(data, field::FLG_OFF);
         let raw = if value { raw | 0x4000 } else { raw & !0x4000 };
-        NetworkEndian::write_u16(&mut data[field::FLG_OFF], raw);
+        write_u16(data, field::FLG_OFF, raw);
     }
 
     /// Set the "more fragments" flag.
     #[inline]
     pub fn set_more_frags(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLG_OFF]);
+        let raw = read_u16(data, field::FLG_OFF);
         let raw = if value { raw | 0x2000 } else { raw & !0x2000 };
-        NetworkEndian::write_u16(&mut data[field::FLG_OFF], raw);
+        write_u16(data, field::FLG_OFF, raw);
     }
 
     /// Set the fragment offset, in octets.
     #[inline]
     pub fn set_frag_offset(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLG_OFF]);
+        let raw = read_u16(data, field::FLG_OFF);
         let raw = (raw & 0xe000) | (value >> 3);
-        NetworkEndian::write_u16(&mut data[field::FLG_OFF], raw);
+        write_u16(data, field::FLG_OFF, raw);
     }
 
     /// Set the time to live field.
@@ -566,7 +585,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
     }
 
     /// Set the source address field.
@@ -593,6 +612,35 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         self.set_checksum(checksum)
     }
 
+    /// Set the time to live field, and update the header checksum incrementally
+    /// instead of recomputing it.
+    pub fn update_hop_limit(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        let old = [data[field::TTL], data[field::PROTOCOL]];
+        data[field::TTL] = value;
+        let new = [data[field::TTL], data[field::PROTOCOL]];
+        let checksum = checksum::update(self.checksum(), &old, &new);
+        self.set_checksum(checksum)
+    }
+
+    /// Set the source address field, and update the header checksum incrementally
+    /// instead of recomputing it.
+    pub fn update_src_addr(&mut self, value: Address) {
+        let old = self.src_addr();
+        self.set_src_addr(value);
+        let checksum = checksum::update(self.checksum(), old.as_bytes(), value.as_bytes());
+        self.set_checksum(checksum)
+    }
+
+    /// Set the destination address field, and update the header checksum incrementally
+    /// instead of recomputing it.
+    pub fn update_dst_addr(&mut self, value: Address) {
+        let old = self.dst_addr();
+        self.set_dst_addr(value);
+        let checksum = checksum::update(self.checksum(), old.as_bytes(), value.as_bytes());
+        self.set_checksum(checksum)
+    }
+
     /// Return a mutable pointer to the payload.
     #[inline]
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -624,6 +672,7 @@ impl Repr {
     pub fn parse<T: AsRef<[u8]> + ?Sized>(
         packet: &Packet<&T>,
         checksum_caps: &ChecksumCapabilities,
+        mode: ParseMode,
     ) -> Result<Repr> {
         // Version 4 is expected.
         if packet.version() != 4 {
@@ -633,6 +682,12 @@ impl Repr {
         if checksum_caps.ipv4.rx() && !packet.verify_checksum() {
             return Err(Error);
         }
+        // The options are skipped, but in the strict mode they must be well-formed, and
+        // so must be the reserved flag.
+        if mode == ParseMode::Strict && (packet.reserved_flag() || !check_options(packet.options()))
+        {
+            return Err(Error);
+        }
 
         #[cfg(not(feature = "proto-ipv4-fragmentation"))]
         // We do not support fragmentation.
@@ -655,11 +710,18 @@ impl Repr {
     }
 
     /// Return the length of a header that will be emitted from this high-level representation.
-    pub fn buffer_len(&self) -> usize {
+    pub fn header_len(&self) -> usize {
         // We never emit any options.
         field::DST_ADDR.end
     }
 
+    /// Return the length of a header that will be emitted from this high-level representation.
+    ///
+    /// This is the same as `header_len()`, since the payload is emitted separately.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len()
+    }
+
     /// Emit a high-level representation into an Internet Protocol version 4 packet.
     pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(
         &self,
@@ -667,11 +729,10 @@ impl Repr {
         checksum_caps: &ChecksumCapabilities,
     ) {
         packet.set_version(4);
-        packet.set_header_len(field::DST_ADDR.end as u8);
+        packet.set_header_len(self.header_len() as u8);
         packet.set_dscp(0);
         packet.set_ecn(0);
-        let total_len = packet.header_len() as u16 + self.payload_len as u16;
-        packet.set_total_len(total_len);
+        packet.set_total_len((self.header_len() + self.payload_len) as u16);
         packet.set_ident(0);
         packet.clear_flags();
         packet.set_more_frags(false);
@@ -692,9 +753,34 @@ impl Repr {
     }
 }
 
+/// Check that the options are well-formed, and that the octets following the end of
+/// option list, if any, are zero.
+fn check_options(mut options: &[u8]) -> bool {
+    const OPT_END: u8 = 0;
+    const OPT_NOP: u8 = 1;
+
+    while let Some(&kind) = options.first() {
+        match kind {
+            OPT_END => return options.iter().all(|&octet| octet == 0),
+            OPT_NOP => options = &options[1..],
+            _ => match options.get(1) {
+                Some(&length) if length >= 2 && length as usize <= options.len() => {
+                    options = &options[length as usize..]
+                }
+                _ => return false,
+            },
+        }
+    }
+    true
+}
+
 impl<'a, T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&'a T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match Repr::parse(self, &ChecksumCapabilities::ignored()) {
+        match Repr::parse(
+            self,
+            &ChecksumCapabilities::ignored(),
+            ParseMode::Permissive,
+        ) {
             Ok(repr) => write!(f, "{}", repr),
             Err(err) => {
                 write!(f, "IPv4 ({})", err)?;
@@ -743,7 +829,11 @@ impl fmt::Display for Repr {
             f,
             "IPv4 src={} dst={} proto={}",
             self.src_addr, self.dst_addr, self.next_header
-        )
+        )?;
+        if f.alternate() {
+            write!(f, " hop_limit={} len={}", self.hop_limit, self.payload_len)?;
+        }
+        Ok(())
     }
 }
 
@@ -761,7 +851,7 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 
         let (ip_repr, payload) = match Packet::new_checked(buffer) {
             Err(err) => return write!(f, "{}({})", indent, err),
-            Ok(ip_packet) => match Repr::parse(&ip_packet, &checksum_caps) {
+            Ok(ip_packet) => match Repr::parse(&ip_packet, &checksum_caps, ParseMode::Permissive) {
                 Err(_) => return Ok(()),
                 Ok(ip_repr) => {
                     if ip_packet.more_frags() || ip_packet.frag_offset() != 0 {
@@ -789,6 +879,8 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
 
     static PACKET_BYTES: [u8; 30] = [
         0x45, 0x00, 0x00, 0x1e, 0x01, 0x02, 0x62, 0x03, 0x1a, 0x01, 0xd5, 0x6e, 0x11, 0x12, 0x13,
@@ -818,6 +910,20 @@ mod test {
         assert_eq!(packet.payload(), &PAYLOAD_BYTES[..]);
     }
 
+    #[test]
+    fn test_update_checksum() {
+        let mut bytes = PACKET_BYTES.to_vec();
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.update_hop_limit(0x19);
+        packet.update_src_addr(Address([192, 0, 2, 1]));
+        packet.update_dst_addr(Address([198, 51, 100, 254]));
+        assert!(packet.verify_checksum());
+
+        let checksum = packet.checksum();
+        packet.fill_checksum();
+        assert_eq!(packet.checksum(), checksum);
+    }
+
     #[test]
     fn test_construct() {
         let mut bytes = vec![0xa5; 30];
@@ -886,7 +992,12 @@ mod test {
     #[test]
     fn test_parse() {
         let packet = Packet::new_unchecked(&REPR_PACKET_BYTES[..]);
-        let repr = Repr::parse(&packet, &ChecksumCapabilities::default()).unwrap();
+        let repr = Repr::parse(
+            &packet,
+            &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
+        )
+        .unwrap();
         assert_eq!(repr, packet_repr());
     }
 
@@ -899,11 +1010,63 @@ mod test {
         packet.fill_checksum();
         let packet = Packet::new_unchecked(&*packet.into_inner());
         assert_eq!(
-            Repr::parse(&packet, &ChecksumCapabilities::default()),
+            Repr::parse(
+                &packet,
+                &ChecksumCapabilities::default(),
+                ParseMode::Permissive
+            ),
             Err(Error)
         );
     }
 
+    #[test]
+    fn test_parse_strict_reserved_flag() {
+        let mut bytes = vec![0; 24];
+        bytes.copy_from_slice(&REPR_PACKET_BYTES[..]);
+        bytes[6] |= 0x80;
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.fill_checksum();
+        let packet = Packet::new_unchecked(&*packet.into_inner());
+        let caps = ChecksumCapabilities::default();
+        assert_eq!(
+            Repr::parse(&packet, &caps, ParseMode::Permissive),
+            Ok(packet_repr())
+        );
+        assert_eq!(Repr::parse(&packet, &caps, ParseMode::Strict), Err(Error));
+    }
+
+    #[test]
+    fn test_parse_strict_options() {
+        let caps = ChecksumCapabilities::default();
+        for (options, strict) in [
+            ([0x01, 0x01, 0x00, 0x00], true),
+            ([0x94, 0x04, 0x00, 0x00], true),
+            ([0x00, 0x00, 0x00, 0x01], false),
+            ([0x94, 0x05, 0x00, 0x00], false),
+            ([0x01, 0x01, 0x01, 0x94], false),
+        ] {
+            let mut bytes = vec![0; 28];
+            bytes[..20].copy_from_slice(&REPR_PACKET_BYTES[..20]);
+            bytes[20..24].copy_from_slice(&options);
+            bytes[24..].copy_from_slice(&REPR_PAYLOAD_BYTES);
+            let mut packet = Packet::new_unchecked(&mut bytes);
+            packet.set_header_len(24);
+            packet.set_total_len(28);
+            packet.fill_checksum();
+            let packet = Packet::new_checked(&*packet.into_inner()).unwrap();
+            assert_eq!(
+                Repr::parse(&packet, &caps, ParseMode::Permissive),
+                Ok(packet_repr())
+            );
+            assert_eq!(
+                Repr::parse(&packet, &caps, ParseMode::Strict).is_ok(),
+                strict,
+                "options {:?}",
+                options
+            );
+        }
+    }
+
     #[test]
     fn test_parse_total_len_less_than_header_len() {
         let mut bytes = vec![0; 40];
@@ -1167,4 +1330,26 @@ mod test {
             Cidr::new(Address([255, 255, 255, 255]), 32)
         );
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::ipv4_repr()) {
+            let mut bytes = vec![0; repr.buffer_len() + repr.payload_len];
+            repr.emit(&mut Packet::new_unchecked(&mut bytes), &ChecksumCapabilities::default());
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(
+                Repr::parse(&packet, &ChecksumCapabilities::default(), ParseMode::Strict).unwrap(),
+                repr
+            );
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..80)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                for mode in [ParseMode::Permissive, ParseMode::Strict] {
+                    let _ = Repr::parse(&packet, &ChecksumCapabilities::ignored(), mode);
+                }
+            }
+        }
+    }
 }
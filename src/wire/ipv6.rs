@@ -1,5 +1,6 @@
 #![deny(missing_docs)]
 
+use crate::wire::field::{read_u16, write_u16};
 use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt;
 
@@ -52,17 +53,34 @@ impl Address {
 
     /// Construct an IPv6 address from parts.
     #[allow(clippy::too_many_arguments)]
-    pub fn new(a0: u16, a1: u16, a2: u16, a3: u16, a4: u16, a5: u16, a6: u16, a7: u16) -> Address {
-        let mut addr = [0u8; 16];
-        NetworkEndian::write_u16(&mut addr[0..2], a0);
-        NetworkEndian::write_u16(&mut addr[2..4], a1);
-        NetworkEndian::write_u16(&mut addr[4..6], a2);
-        NetworkEndian::write_u16(&mut addr[6..8], a3);
-        NetworkEndian::write_u16(&mut addr[8..10], a4);
-        NetworkEndian::write_u16(&mut addr[10..12], a5);
-        NetworkEndian::write_u16(&mut addr[12..14], a6);
-        NetworkEndian::write_u16(&mut addr[14..16], a7);
-        Address(addr)
+    pub const fn new(
+        a0: u16,
+        a1: u16,
+        a2: u16,
+        a3: u16,
+        a4: u16,
+        a5: u16,
+        a6: u16,
+        a7: u16,
+    ) -> Address {
+        Address([
+            (a0 >> 8) as u8,
+            a0 as u8,
+            (a1 >> 8) as u8,
+            a1 as u8,
+            (a2 >> 8) as u8,
+            a2 as u8,
+            (a3 >> 8) as u8,
+            a3 as u8,
+            (a4 >> 8) as u8,
+            a4 as u8,
+            (a5 >> 8) as u8,
+            a5 as u8,
+            (a6 >> 8) as u8,
+            a6 as u8,
+            (a7 >> 8) as u8,
+            a7 as u8,
+        ])
     }
 
     /// Construct an IPv6 address from a sequence of octets, in big-endian.
@@ -305,8 +323,11 @@ impl Cidr {
     ///
     /// # Panics
     /// This function panics if the prefix length is larger than 128.
-    pub fn new(address: Address, prefix_len: u8) -> Cidr {
-        assert!(prefix_len <= 128);
+    #[allow(clippy::no_effect)]
+    pub const fn new(address: Address, prefix_len: u8) -> Cidr {
+        // Replace with const panic (or assert) when stabilized
+        // see: https://github.com/rust-lang/rust/issues/51999
+        ["Prefix length should be <= 128"][(prefix_len > 128) as usize];
         Cidr {
             address,
             prefix_len,
@@ -314,12 +335,12 @@ impl Cidr {
     }
 
     /// Return the address of this IPv6 CIDR block.
-    pub fn address(&self) -> Address {
+    pub const fn address(&self) -> Address {
         self.address
     }
 
     /// Return the prefix length of this IPv6 CIDR block.
-    pub fn prefix_len(&self) -> u8 {
+    pub const fn prefix_len(&self) -> u8 {
         self.prefix_len
     }
 
@@ -476,7 +497,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn payload_len(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::LENGTH])
+        read_u16(data, field::LENGTH)
     }
 
     /// Return the payload length added to the known header length.
@@ -559,7 +580,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_payload_len(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::LENGTH], value);
+        write_u16(data, field::LENGTH, value);
     }
 
     /// Set the next header field.
@@ -651,11 +672,18 @@ impl Repr {
     }
 
     /// Return the length of a header that will be emitted from this high-level representation.
-    pub fn buffer_len(&self) -> usize {
+    pub fn header_len(&self) -> usize {
         // This function is not strictly necessary, but it can make client code more readable.
         field::DST_ADDR.end
     }
 
+    /// Return the length of a header that will be emitted from this high-level representation.
+    ///
+    /// This is the same as `header_len()`, since the payload is emitted separately.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len()
+    }
+
     /// Emit a high-level representation into an Internet Protocol version 6 packet.
     pub fn emit<T: AsRef<[u8]> + AsMut<[u8]>>(&self, packet: &mut Packet<T>) {
         // Make no assumptions about the original state of the packet buffer.
@@ -675,9 +703,13 @@ impl fmt::Display for Repr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "IPv6 src={} dst={} nxt_hdr={} hop_limit={}",
-            self.src_addr, self.dst_addr, self.next_header, self.hop_limit
-        )
+            "IPv6 src={} dst={} nxt_hdr={}",
+            self.src_addr, self.dst_addr, self.next_header
+        )?;
+        if f.alternate() {
+            write!(f, " hop_limit={} len={}", self.hop_limit, self.payload_len)?;
+        }
+        Ok(())
     }
 }
 
@@ -712,6 +744,8 @@ mod test {
     use super::{Address, Cidr};
     use super::{Packet, Protocol, Repr};
     use crate::wire::pretty_print::PrettyPrinter;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
 
     #[cfg(feature = "proto-ipv4")]
     use crate::wire::ipv4::Address as Ipv4Address;
@@ -1185,7 +1219,45 @@ mod test {
                 "{}",
                 PrettyPrinter::<Packet<&'static [u8]>>::new("\n", &&REPR_PACKET_BYTES[..])
             ),
-            "\nIPv6 src=fe80::1 dst=ff02::1 nxt_hdr=UDP hop_limit=64\n \\ UDP src=1 dst=2 len=4"
+            "\nIPv6 src=fe80::1 dst=ff02::1 nxt_hdr=UDP\n \\ UDP src=1 dst=2 len=4"
+        );
+        assert_eq!(
+            format!(
+                "{}",
+                PrettyPrinter::<Packet<&'static [u8]>>::new("-> ", &&REPR_PACKET_BYTES[..])
+                    .compact()
+            ),
+            "-> IPv6 src=fe80::1 dst=ff02::1 nxt_hdr=UDP > UDP src=1 dst=2 len=4"
+        );
+    }
+
+    #[test]
+    fn test_repr_display() {
+        let repr = packet_repr();
+        assert_eq!(
+            format!("{}", repr),
+            "IPv6 src=fe80::1 dst=ff02::1 nxt_hdr=UDP"
         );
+        assert_eq!(
+            format!("{:#}", repr),
+            "IPv6 src=fe80::1 dst=ff02::1 nxt_hdr=UDP hop_limit=64 len=12"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::ipv6_repr()) {
+            let mut bytes = vec![0; repr.buffer_len() + repr.payload_len];
+            repr.emit(&mut Packet::new_unchecked(&mut bytes));
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&packet).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..80)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&packet);
+            }
+        }
     }
 }
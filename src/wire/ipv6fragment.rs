@@ -1,7 +1,7 @@
 use super::{Error, Result};
 use core::fmt;
 
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
 
 pub use super::IpProtocol as Protocol;
 
@@ -79,7 +79,7 @@ impl<T: AsRef<[u8]>> Header<T> {
     #[inline]
     pub fn frag_offset(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::FR_OF_M]) >> 3
+        read_u16(data, field::FR_OF_M) >> 3
     }
 
     /// Return more fragment flag field.
@@ -93,7 +93,7 @@ impl<T: AsRef<[u8]>> Header<T> {
     #[inline]
     pub fn ident(&self) -> u32 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u32(&data[field::IDENT])
+        read_u32(data, field::IDENT)
     }
 }
 
@@ -125,7 +125,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Header<T> {
         let data = self.buffer.as_mut();
         // Retain the lower order 3 bits
         let raw = ((value & 0x1fff) << 3) | ((data[3] & 0x7) as u16);
-        NetworkEndian::write_u16(&mut data[field::FR_OF_M], raw);
+        write_u16(data, field::FR_OF_M, raw);
     }
 
     /// Set the more fragments flag field.
@@ -141,7 +141,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Header<T> {
     #[inline]
     pub fn set_ident(&mut self, value: u32) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::IDENT], value);
+        write_u32(data, field::IDENT, value);
     }
 }
 
@@ -215,6 +215,8 @@ impl<'a> fmt::Display for Repr {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
 
     // A Fragment Header with more fragments remaining
     static BYTES_HEADER_MORE_FRAG: [u8; 8] = [0x6, 0x0, 0x0, 0x1, 0x0, 0x0, 0x30, 0x39];
@@ -309,4 +311,21 @@ mod test {
         let repr = Repr::parse(&header).unwrap();
         assert_eq!(repr.buffer_len(), BYTES_HEADER_MORE_FRAG.len());
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::ipv6_fragment_repr()) {
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(&mut Header::new_unchecked(&mut bytes[..]));
+            let header = Header::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&header).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..16)) {
+            if let Ok(header) = Header::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&header);
+            }
+        }
+    }
 }
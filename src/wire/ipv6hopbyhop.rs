@@ -43,8 +43,8 @@ mod field {
     // Length of the header is in 8-octet units, not including the first 8 octets. The first two
     // octets are the next header type and the header length.
     pub fn OPTIONS(length_field: u8) -> Field {
-        let bytes = length_field * 8 + 8;
-        2..bytes as usize
+        let bytes = length_field as usize * 8 + 8;
+        2..bytes
     }
 }
 
@@ -249,6 +249,14 @@ mod test {
         // length field value greater than number of bytes
         let header: [u8; 8] = [0x06, 0x2, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0];
         assert_eq!(Err(Error), Header::new_unchecked(&header).check_len());
+        // length field value too large to be converted to octets in an octet
+        let mut header = [0; 2048];
+        header[1] = 0xff;
+        assert_eq!(Ok(()), Header::new_unchecked(&header[..]).check_len());
+        assert_eq!(
+            Err(Error),
+            Header::new_unchecked(&header[..2047]).check_len()
+        );
     }
 
     #[test]
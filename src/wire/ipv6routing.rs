@@ -92,8 +92,8 @@ mod field {
     // Length of the header is in 8-octet units, not including the first 8 octets. The first four
     // octets are the next header type, the header length, routing type and segments left.
     pub fn DATA(length_field: u8) -> Field {
-        let bytes = length_field * 8 + 8;
-        4..bytes as usize
+        let bytes = length_field as usize * 8 + 8;
+        4..bytes
     }
 
     // The Type 2 Routing Header has the following format:
@@ -599,6 +599,11 @@ mod test {
         assert_eq!(Ok(()), Header::new(&BYTES_TYPE2[..]).check_len());
         assert_eq!(Ok(()), Header::new(&BYTES_SRH_FULL[..]).check_len());
         assert_eq!(Ok(()), Header::new(&BYTES_SRH_ELIDED[..]).check_len());
+        // length field value too large to be converted to octets in an octet
+        let mut header = [0; 2048];
+        header[1] = 0xff;
+        assert_eq!(Ok(()), Header::new(&header[..]).check_len());
+        assert_eq!(Err(Error), Header::new(&header[..2047]).check_len());
     }
 
     #[test]
@@ -0,0 +1,562 @@
+use crate::wire::field::{read_u16, write_u16};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::EthernetAddress;
+
+/// The nearest-bridge multicast address LLDP frames are sent to.
+pub const MULTICAST_ADDR: EthernetAddress = EthernetAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]);
+
+enum_with_unknown! {
+    /// LLDP TLV type.
+    pub enum TlvType(u8) {
+        /// End of LLDPDU
+        End                 = 0,
+        /// Chassis ID
+        ChassisId           = 1,
+        /// Port ID
+        PortId              = 2,
+        /// Time To Live
+        TimeToLive          = 3,
+        /// Port Description
+        PortDescription     = 4,
+        /// System Name
+        SystemName          = 5,
+        /// System Description
+        SystemDescription   = 6,
+        /// System Capabilities
+        SystemCapabilities  = 7,
+        /// Management Address
+        ManagementAddress   = 8,
+        /// Organizationally Specific
+        OrganizationSpecific = 127
+    }
+}
+
+impl fmt::Display for TlvType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TlvType::End => write!(f, "end"),
+            TlvType::ChassisId => write!(f, "chassis id"),
+            TlvType::PortId => write!(f, "port id"),
+            TlvType::TimeToLive => write!(f, "ttl"),
+            TlvType::PortDescription => write!(f, "port description"),
+            TlvType::SystemName => write!(f, "system name"),
+            TlvType::SystemDescription => write!(f, "system description"),
+            TlvType::SystemCapabilities => write!(f, "system capabilities"),
+            TlvType::ManagementAddress => write!(f, "management address"),
+            TlvType::OrganizationSpecific => write!(f, "organizationally specific"),
+            TlvType::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+enum_with_unknown! {
+    /// Chassis ID subtype.
+    pub enum ChassisIdSubtype(u8) {
+        ChassisComponent = 1,
+        InterfaceAlias   = 2,
+        PortComponent    = 3,
+        MacAddress       = 4,
+        NetworkAddress   = 5,
+        InterfaceName    = 6,
+        LocallyAssigned  = 7
+    }
+}
+
+enum_with_unknown! {
+    /// Port ID subtype.
+    pub enum PortIdSubtype(u8) {
+        InterfaceAlias   = 1,
+        PortComponent    = 2,
+        MacAddress       = 3,
+        NetworkAddress   = 4,
+        InterfaceName    = 5,
+        AgentCircuitId   = 6,
+        LocallyAssigned  = 7
+    }
+}
+
+bitflags! {
+    /// System capabilities, as advertised in the System Capabilities TLV.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Capabilities: u16 {
+        const OTHER     = 0b0000_0000_0000_0001;
+        const REPEATER  = 0b0000_0000_0000_0010;
+        const BRIDGE    = 0b0000_0000_0000_0100;
+        const WLAN_AP   = 0b0000_0000_0000_1000;
+        const ROUTER    = 0b0000_0000_0001_0000;
+        const TELEPHONE = 0b0000_0000_0010_0000;
+        const DOCSIS    = 0b0000_0000_0100_0000;
+        const STATION   = 0b0000_0000_1000_0000;
+    }
+}
+
+/// A read/write wrapper around a single LLDP TLV.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tlv<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of an LLDP TLV
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |    Type     |     Length      |           Value ...           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// See IEEE 802.1AB-2016 § 8.4 for details.
+mod field {
+    #![allow(non_snake_case)]
+
+    use crate::wire::field::*;
+
+    // 7-bit type and 9-bit length, packed into a single 16-bit word.
+    pub const TYPE_LENGTH: Field = 0..2;
+    // Length of the TLV header.
+    pub const HEADER_LEN: usize = TYPE_LENGTH.end;
+    // Variable-length field. TLV-type-specific data.
+    pub fn VALUE(length: u16) -> Field {
+        HEADER_LEN..HEADER_LEN + length as usize
+    }
+}
+
+impl<T: AsRef<[u8]>> Tlv<T> {
+    /// Imbue a raw octet buffer with LLDP TLV structure.
+    pub fn new_unchecked(buffer: T) -> Tlv<T> {
+        Tlv { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Tlv<T>> {
+        let tlv = Self::new_unchecked(buffer);
+        tlv.check_len()?;
+        Ok(tlv)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    ///
+    /// The result of this check is invalidated by calling [set_length].
+    ///
+    /// [set_length]: #method.set_length
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::HEADER_LEN || len < field::VALUE(self.length()).end {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the TLV, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the TLV type.
+    #[inline]
+    pub fn tlv_type(&self) -> TlvType {
+        let data = self.buffer.as_ref();
+        TlvType::from((read_u16(data, field::TYPE_LENGTH) >> 9) as u8)
+    }
+
+    /// Return the length of the TLV value.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::TYPE_LENGTH) & 0x1ff
+    }
+
+    /// Return the total length of the TLV, including the header.
+    #[inline]
+    pub fn total_len(&self) -> usize {
+        field::VALUE(self.length()).end
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Tlv<&'a T> {
+    /// Return the TLV value.
+    #[inline]
+    pub fn value(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::VALUE(self.length())]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Tlv<T> {
+    /// Set the TLV type and length.
+    #[inline]
+    pub fn set_type_length(&mut self, tlv_type: TlvType, length: u16) {
+        let data = self.buffer.as_mut();
+        let raw = ((u8::from(tlv_type) as u16) << 9) | (length & 0x1ff);
+        write_u16(data, field::TYPE_LENGTH, raw)
+    }
+
+    /// Return a mutable pointer to the TLV value.
+    #[inline]
+    pub fn value_mut(&mut self) -> &mut [u8] {
+        let length = self.length();
+        let data = self.buffer.as_mut();
+        &mut data[field::VALUE(length)]
+    }
+}
+
+/// A read/write wrapper around an LLDP data unit, i.e. a sequence of TLVs
+/// terminated by an End of LLDPDU TLV.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with LLDPDU structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that every TLV up to and including the End of LLDPDU TLV
+    /// lies within the buffer.
+    /// Returns `Err(Error)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        let mut data = self.buffer.as_ref();
+        loop {
+            let tlv = Tlv::new_checked(data)?;
+            if tlv.tlv_type() == TlvType::End {
+                return Ok(());
+            }
+            data = &data[tlv.total_len()..];
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return an iterator over the TLVs of the LLDPDU, excluding the End TLV.
+    pub fn tlvs(&self) -> TlvIterator<'a> {
+        TlvIterator {
+            data: self.buffer.as_ref(),
+        }
+    }
+}
+
+/// An iterator over the TLVs of an LLDPDU.
+#[derive(Debug)]
+pub struct TlvIterator<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for TlvIterator<'a> {
+    type Item = Tlv<&'a [u8]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tlv = Tlv::new_checked(self.data).ok()?;
+        if tlv.tlv_type() == TlvType::End {
+            self.data = &[];
+            return None;
+        }
+        self.data = &self.data[tlv.total_len()..];
+        Some(tlv)
+    }
+}
+
+/// A high-level representation of an LLDP data unit.
+///
+/// Only the mandatory TLVs and the basic management TLVs describing the
+/// system are represented; other TLVs are skipped when parsing.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    pub chassis_id_subtype: ChassisIdSubtype,
+    pub chassis_id: &'a [u8],
+    pub port_id_subtype: PortIdSubtype,
+    pub port_id: &'a [u8],
+    /// Time to live of the advertised information, in seconds.
+    pub ttl: u16,
+    pub port_description: Option<&'a [u8]>,
+    pub system_name: Option<&'a [u8]>,
+    pub system_description: Option<&'a [u8]>,
+    /// Supported and enabled system capabilities.
+    pub system_capabilities: Option<(Capabilities, Capabilities)>,
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an LLDP data unit and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let mut tlvs = packet.tlvs();
+
+        // The first three TLVs are mandatory and must appear in order.
+        let chassis = tlvs.next().ok_or(Error)?;
+        let port = tlvs.next().ok_or(Error)?;
+        let ttl = tlvs.next().ok_or(Error)?;
+        if chassis.tlv_type() != TlvType::ChassisId
+            || port.tlv_type() != TlvType::PortId
+            || ttl.tlv_type() != TlvType::TimeToLive
+        {
+            return Err(Error);
+        }
+        if chassis.value().len() < 2 || port.value().len() < 2 || ttl.value().len() < 2 {
+            return Err(Error);
+        }
+
+        let mut repr = Repr {
+            chassis_id_subtype: ChassisIdSubtype::from(chassis.value()[0]),
+            chassis_id: &chassis.value()[1..],
+            port_id_subtype: PortIdSubtype::from(port.value()[0]),
+            port_id: &port.value()[1..],
+            ttl: NetworkEndian::read_u16(ttl.value()),
+            port_description: None,
+            system_name: None,
+            system_description: None,
+            system_capabilities: None,
+        };
+
+        for tlv in tlvs {
+            match tlv.tlv_type() {
+                TlvType::ChassisId | TlvType::PortId | TlvType::TimeToLive => return Err(Error),
+                TlvType::PortDescription => repr.port_description = Some(tlv.value()),
+                TlvType::SystemName => repr.system_name = Some(tlv.value()),
+                TlvType::SystemDescription => repr.system_description = Some(tlv.value()),
+                TlvType::SystemCapabilities => {
+                    let value = tlv.value();
+                    if value.len() != 4 {
+                        return Err(Error);
+                    }
+                    repr.system_capabilities = Some((
+                        Capabilities::from_bits_truncate(NetworkEndian::read_u16(&value[0..2])),
+                        Capabilities::from_bits_truncate(NetworkEndian::read_u16(&value[2..4])),
+                    ));
+                }
+                // Skip everything else.
+                _ => (),
+            }
+        }
+
+        Ok(repr)
+    }
+
+    /// Return the length of an LLDPDU that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        let mut len = 0;
+        len += field::HEADER_LEN + 1 + self.chassis_id.len();
+        len += field::HEADER_LEN + 1 + self.port_id.len();
+        len += field::HEADER_LEN + 2;
+        for value in [
+            self.port_description,
+            self.system_name,
+            self.system_description,
+        ]
+        .iter()
+        .flatten()
+        {
+            len += field::HEADER_LEN + value.len();
+        }
+        if self.system_capabilities.is_some() {
+            len += field::HEADER_LEN + 4;
+        }
+        len + field::HEADER_LEN
+    }
+
+    /// Emit a high-level representation into an LLDP data unit.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        let mut data = packet.buffer.as_mut();
+
+        data = emit_tlv(
+            data,
+            TlvType::ChassisId,
+            1 + self.chassis_id.len(),
+            |value| {
+                value[0] = self.chassis_id_subtype.into();
+                value[1..].copy_from_slice(self.chassis_id);
+            },
+        );
+        data = emit_tlv(data, TlvType::PortId, 1 + self.port_id.len(), |value| {
+            value[0] = self.port_id_subtype.into();
+            value[1..].copy_from_slice(self.port_id);
+        });
+        data = emit_tlv(data, TlvType::TimeToLive, 2, |value| {
+            NetworkEndian::write_u16(value, self.ttl)
+        });
+        for (tlv_type, value) in [
+            (TlvType::PortDescription, self.port_description),
+            (TlvType::SystemName, self.system_name),
+            (TlvType::SystemDescription, self.system_description),
+        ] {
+            if let Some(value) = value {
+                data = emit_tlv(data, tlv_type, value.len(), |buf| {
+                    buf.copy_from_slice(value)
+                });
+            }
+        }
+        if let Some((supported, enabled)) = self.system_capabilities {
+            data = emit_tlv(data, TlvType::SystemCapabilities, 4, |value| {
+                NetworkEndian::write_u16(&mut value[0..2], supported.bits());
+                NetworkEndian::write_u16(&mut value[2..4], enabled.bits());
+            });
+        }
+        emit_tlv(data, TlvType::End, 0, |_| ());
+    }
+}
+
+fn emit_tlv<F>(data: &mut [u8], tlv_type: TlvType, length: usize, f: F) -> &mut [u8]
+where
+    F: FnOnce(&mut [u8]),
+{
+    let total_len = field::VALUE(length as u16).end;
+    let (head, rest) = data.split_at_mut(total_len);
+    let mut tlv = Tlv::new_unchecked(head);
+    tlv.set_type_length(tlv_type, length as u16);
+    f(tlv.value_mut());
+    rest
+}
+
+/// Format an LLDP identifier or string field, falling back to hex when it is not valid UTF-8.
+struct DisplayId<'a>(&'a [u8]);
+
+impl<'a> fmt::Display for DisplayId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match core::str::from_utf8(self.0) {
+            Ok(s) => write!(f, "{:?}", s),
+            Err(_) => {
+                for (i, b) in self.0.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ":")?;
+                    }
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LLDP chassis=")?;
+        match (self.chassis_id_subtype, self.chassis_id.len()) {
+            (ChassisIdSubtype::MacAddress, 6) => {
+                write!(f, "{}", EthernetAddress::from_bytes(self.chassis_id))?
+            }
+            _ => write!(f, "{}", DisplayId(self.chassis_id))?,
+        }
+        write!(f, " port={} ttl={}", DisplayId(self.port_id), self.ttl)?;
+        if let Some(name) = self.system_name {
+            write!(f, " name={}", DisplayId(name))?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "LLDP ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static LLDPDU_BYTES: [u8; 35] = [
+        // Chassis ID, MAC address
+        0x02, 0x07, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, // Port ID, interface name
+        0x04, 0x05, 0x05, b'e', b't', b'h', b'0', // TTL
+        0x06, 0x02, 0x00, 0x78, // System name
+        0x0a, 0x05, b'n', b'o', b'd', b'e', b'1', // System capabilities
+        0x0e, 0x04, 0x00, 0x80, 0x00, 0x80, // End
+        0x00, 0x00,
+    ];
+
+    fn repr() -> Repr<'static> {
+        Repr {
+            chassis_id_subtype: ChassisIdSubtype::MacAddress,
+            chassis_id: &[0x02, 0x00, 0x00, 0x00, 0x00, 0x01],
+            port_id_subtype: PortIdSubtype::InterfaceName,
+            port_id: b"eth0",
+            ttl: 120,
+            port_description: None,
+            system_name: Some(b"node1"),
+            system_description: None,
+            system_capabilities: Some((Capabilities::STATION, Capabilities::STATION)),
+        }
+    }
+
+    #[test]
+    fn test_tlv_deconstruct() {
+        let tlv = Tlv::new_checked(&LLDPDU_BYTES[..]).unwrap();
+        assert_eq!(tlv.tlv_type(), TlvType::ChassisId);
+        assert_eq!(tlv.length(), 7);
+        assert_eq!(tlv.value(), &LLDPDU_BYTES[2..9]);
+    }
+
+    #[test]
+    fn test_tlv_truncated() {
+        assert_eq!(Tlv::new_checked(&[0x02, 0x07, 0x04][..]), Err(Error));
+        assert_eq!(Packet::new_checked(&LLDPDU_BYTES[..34]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_checked(&LLDPDU_BYTES[..]).unwrap();
+        assert_eq!(Repr::parse(&packet), Ok(repr()));
+    }
+
+    #[test]
+    fn test_parse_missing_ttl() {
+        let bytes = [0x02, 0x02, 0x07, b'a', 0x04, 0x02, 0x07, b'b', 0x00, 0x00];
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(Repr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(&bytes[..], &LLDPDU_BYTES[..]);
+    }
+}
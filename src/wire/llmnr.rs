@@ -0,0 +1,661 @@
+use crate::wire::field::{read_u16, write_u16};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::IpAddress;
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::Ipv4Address;
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::Ipv6Address;
+
+/// The UDP port LLMNR queries are sent to.
+pub const UDP_PORT: u16 = 5355;
+
+/// The IPv4 multicast group LLMNR queries are sent to.
+#[cfg(feature = "proto-ipv4")]
+pub const IPV4_MULTICAST_ADDR: Ipv4Address = Ipv4Address([224, 0, 0, 252]);
+
+/// The IPv6 multicast group LLMNR queries are sent to.
+#[cfg(feature = "proto-ipv6")]
+pub const IPV6_MULTICAST_ADDR: Ipv6Address =
+    Ipv6Address([0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 3]);
+
+/// The maximum length of an encoded name.
+const MAX_NAME_LEN: usize = 255;
+
+// LLMNR class IN (Internet)
+const CLASS_IN: u16 = 1;
+
+enum_with_unknown! {
+    /// LLMNR response code.
+    pub enum Rcode(u8) {
+        NoError  = 0x00,
+        FormErr  = 0x01,
+        ServFail = 0x02,
+        NXDomain = 0x03,
+        NotImp   = 0x04,
+        Refused  = 0x05
+    }
+}
+
+impl fmt::Display for Rcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rcode::NoError => write!(f, "no error"),
+            Rcode::FormErr => write!(f, "format error"),
+            Rcode::ServFail => write!(f, "server failure"),
+            Rcode::NXDomain => write!(f, "no such name"),
+            Rcode::NotImp => write!(f, "not implemented"),
+            Rcode::Refused => write!(f, "refused"),
+            Rcode::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+enum_with_unknown! {
+    /// LLMNR question and record type.
+    pub enum Type(u16) {
+        A    = 0x0001,
+        Aaaa = 0x001c,
+        Any  = 0x00ff
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::A => write!(f, "A"),
+            Type::Aaaa => write!(f, "AAAA"),
+            Type::Any => write!(f, "ANY"),
+            Type::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+bitflags! {
+    /// LLMNR header flags.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Flags: u16 {
+        const RESPONSE   = 0b1000_0000_0000_0000;
+        const CONFLICT   = 0b0000_0100_0000_0000;
+        const TRUNCATION = 0b0000_0010_0000_0000;
+        const TENTATIVE  = 0b0000_0001_0000_0000;
+    }
+}
+
+/// A read/write wrapper around an LLMNR packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// The LLMNR header has the same layout as the DNS header, except for the flags:
+//
+//  | QR | Opcode | C | TC | T | Z | Z | Z | Z | RCODE |
+//
+// See RFC 4795 § 2.1.1 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const ID: Field = 0..2;
+    pub const FLAGS: Field = 2..4;
+    pub const QDCOUNT: Field = 4..6;
+    pub const ANCOUNT: Field = 6..8;
+    pub const NSCOUNT: Field = 8..10;
+    pub const ARCOUNT: Field = 10..12;
+    pub const PAYLOAD: Rest = 12..;
+}
+
+/// The length of an LLMNR header.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with LLMNR packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is shorter than the header.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the transaction ID field.
+    #[inline]
+    pub fn transaction_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ID)
+    }
+
+    /// Return the flags.
+    #[inline]
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags::from_bits_truncate(read_u16(data, field::FLAGS))
+    }
+
+    /// Return the opcode field.
+    #[inline]
+    pub fn opcode(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        (read_u16(data, field::FLAGS) >> 11 & 0xf) as u8
+    }
+
+    /// Return the response code field.
+    #[inline]
+    pub fn rcode(&self) -> Rcode {
+        let data = self.buffer.as_ref();
+        Rcode::from((read_u16(data, field::FLAGS) & 0xf) as u8)
+    }
+
+    /// Return the question count field.
+    #[inline]
+    pub fn question_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::QDCOUNT)
+    }
+
+    /// Return the answer record count field.
+    #[inline]
+    pub fn answer_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ANCOUNT)
+    }
+
+    /// Return the authority record count field.
+    #[inline]
+    pub fn authority_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::NSCOUNT)
+    }
+
+    /// Return the additional record count field.
+    #[inline]
+    pub fn additional_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ARCOUNT)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the sections following the header.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the transaction ID field.
+    #[inline]
+    pub fn set_transaction_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ID, value)
+    }
+
+    /// Set the flags, clearing the opcode and response code.
+    #[inline]
+    pub fn set_flags(&mut self, value: Flags) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::FLAGS, value.bits())
+    }
+
+    /// Set the response code field.
+    #[inline]
+    pub fn set_rcode(&mut self, value: Rcode) {
+        let data = self.buffer.as_mut();
+        let raw = read_u16(data, field::FLAGS) & !0xf;
+        write_u16(data, field::FLAGS, raw | u8::from(value) as u16)
+    }
+
+    /// Set the question count field.
+    #[inline]
+    pub fn set_question_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::QDCOUNT, value)
+    }
+
+    /// Set the answer record count field.
+    #[inline]
+    pub fn set_answer_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ANCOUNT, value)
+    }
+
+    /// Set the authority record count field.
+    #[inline]
+    pub fn set_authority_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::NSCOUNT, value)
+    }
+
+    /// Set the additional record count field.
+    #[inline]
+    pub fn set_additional_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ARCOUNT, value)
+    }
+
+    /// Return a mutable pointer to the sections following the header.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+}
+
+/// A name in DNS wire format, as a sequence of length-prefixed labels.
+///
+/// Names in LLMNR questions are never compressed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Name<'a>(&'a [u8]);
+
+impl<'a> Name<'a> {
+    /// Split an uncompressed name off the front of `data`.
+    fn parse(data: &'a [u8]) -> Result<(Name<'a>, &'a [u8])> {
+        let mut pos = 0;
+        loop {
+            let len = *data.get(pos).ok_or(Error)? as usize;
+            if len & 0xc0 != 0 {
+                return Err(Error);
+            }
+            pos += 1 + len;
+            if pos > MAX_NAME_LEN {
+                return Err(Error);
+            }
+            if len == 0 {
+                break;
+            }
+        }
+        if data.len() < pos {
+            return Err(Error);
+        }
+        Ok((Name(&data[..pos]), &data[pos..]))
+    }
+
+    /// Encode the dot-separated `name` into `buffer`, and return it as a wire format name.
+    ///
+    /// Returns `Err(Error)` if a label is empty or too long, or `buffer` is too short.
+    pub fn encode(name: &str, buffer: &'a mut [u8]) -> Result<Name<'a>> {
+        let mut pos = 0;
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(Error);
+            }
+            let end = pos + 1 + label.len();
+            if end > buffer.len() || end >= MAX_NAME_LEN {
+                return Err(Error);
+            }
+            buffer[pos] = label.len() as u8;
+            buffer[pos + 1..end].copy_from_slice(label.as_bytes());
+            pos = end;
+        }
+        *buffer.get_mut(pos).ok_or(Error)? = 0;
+        Ok(Name(&buffer[..pos + 1]))
+    }
+
+    /// Return the name in wire format.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Return an iterator over the labels of the name.
+    pub fn labels(&self) -> impl Iterator<Item = &'a [u8]> {
+        let mut data = self.0;
+        core::iter::from_fn(move || {
+            let len = *data.first()? as usize;
+            if len == 0 {
+                return None;
+            }
+            let label = &data[1..1 + len];
+            data = &data[1 + len..];
+            Some(label)
+        })
+    }
+
+    /// Query whether the name is equal to the dot-separated `name`, ignoring ASCII case.
+    pub fn eq_ignore_ascii_case(&self, name: &str) -> bool {
+        let mut labels = self.labels();
+        name.split('.').all(
+            |label| matches!(labels.next(), Some(l) if l.eq_ignore_ascii_case(label.as_bytes())),
+        ) && labels.next().is_none()
+    }
+}
+
+impl<'a> fmt::Display for Name<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, label) in self.labels().enumerate() {
+            if i != 0 {
+                write!(f, ".")?;
+            }
+            for &b in label {
+                if b.is_ascii_graphic() {
+                    write!(f, "{}", b as char)?;
+                } else {
+                    write!(f, "\\{:03}", b)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An address record answering an LLMNR question.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Answer {
+    pub ttl: u32,
+    pub address: IpAddress,
+}
+
+impl Answer {
+    fn type_(&self) -> Type {
+        match self.address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(_) => Type::A,
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(_) => Type::Aaaa,
+        }
+    }
+
+    fn data_len(&self) -> usize {
+        match self.address {
+            #[cfg(feature = "proto-ipv4")]
+            IpAddress::Ipv4(_) => 4,
+            #[cfg(feature = "proto-ipv6")]
+            IpAddress::Ipv6(_) => 16,
+        }
+    }
+}
+
+/// A high-level representation of an LLMNR query or response.
+///
+/// Only packets with a single question are valid in LLMNR. Of the answer records, only the
+/// first is represented, and only if it is an A or AAAA record.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    pub transaction_id: u16,
+    pub flags: Flags,
+    pub rcode: Rcode,
+    pub name: Name<'a>,
+    pub type_: Type,
+    pub answer: Option<Answer>,
+}
+
+impl<'a> Repr<'a> {
+    /// Parse an LLMNR packet and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        // Packets with a non-zero opcode or other than one question must be discarded.
+        if packet.opcode() != 0 || packet.question_count() != 1 {
+            return Err(Error);
+        }
+
+        let (name, rest) = Name::parse(packet.payload())?;
+        if rest.len() < 4 || NetworkEndian::read_u16(&rest[2..4]) != CLASS_IN {
+            return Err(Error);
+        }
+        let type_ = Type::from(NetworkEndian::read_u16(&rest[0..2]));
+        let mut rest = &rest[4..];
+
+        let mut answer = None;
+        if packet.answer_record_count() != 0 {
+            // The name of the record is usually a pointer to the question.
+            loop {
+                let len = *rest.first().ok_or(Error)? as usize;
+                if len & 0xc0 == 0xc0 {
+                    rest = rest.get(2..).ok_or(Error)?;
+                    break;
+                }
+                rest = rest.get(1 + len..).ok_or(Error)?;
+                if len == 0 {
+                    break;
+                }
+            }
+            if rest.len() < 10 {
+                return Err(Error);
+            }
+            let rtype = Type::from(NetworkEndian::read_u16(&rest[0..2]));
+            let ttl = NetworkEndian::read_u32(&rest[4..8]);
+            let len = NetworkEndian::read_u16(&rest[8..10]) as usize;
+            let data = rest.get(10..10 + len).ok_or(Error)?;
+            let address = match (rtype, len) {
+                #[cfg(feature = "proto-ipv4")]
+                (Type::A, 4) => Some(IpAddress::Ipv4(Ipv4Address::from_bytes(data))),
+                #[cfg(feature = "proto-ipv6")]
+                (Type::Aaaa, 16) => Some(IpAddress::Ipv6(Ipv6Address::from_bytes(data))),
+                _ => None,
+            };
+            answer = address.map(|address| Answer { ttl, address });
+        }
+
+        Ok(Repr {
+            transaction_id: packet.transaction_id(),
+            flags: packet.flags(),
+            rcode: packet.rcode(),
+            name,
+            type_,
+            answer,
+        })
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        let answer_len = match self.answer {
+            // A pointer to the question name, type, class, TTL, length and data.
+            Some(answer) => 2 + 10 + answer.data_len(),
+            None => 0,
+        };
+        HEADER_LEN + self.name.as_bytes().len() + 4 + answer_len
+    }
+
+    /// Emit a high-level representation into an LLMNR packet.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_transaction_id(self.transaction_id);
+        packet.set_flags(self.flags);
+        packet.set_rcode(self.rcode);
+        packet.set_question_count(1);
+        packet.set_answer_record_count(self.answer.is_some() as u16);
+        packet.set_authority_record_count(0);
+        packet.set_additional_record_count(0);
+
+        let name = self.name.as_bytes();
+        let payload = packet.payload_mut();
+        payload[..name.len()].copy_from_slice(name);
+        let rest = &mut payload[name.len()..];
+        NetworkEndian::write_u16(&mut rest[0..2], self.type_.into());
+        NetworkEndian::write_u16(&mut rest[2..4], CLASS_IN);
+
+        if let Some(answer) = self.answer {
+            let rest = &mut rest[4..];
+            // Compress the name of the record into a pointer to the question.
+            NetworkEndian::write_u16(&mut rest[0..2], 0xc000 | HEADER_LEN as u16);
+            NetworkEndian::write_u16(&mut rest[2..4], answer.type_().into());
+            NetworkEndian::write_u16(&mut rest[4..6], CLASS_IN);
+            NetworkEndian::write_u32(&mut rest[6..10], answer.ttl);
+            NetworkEndian::write_u16(&mut rest[10..12], answer.data_len() as u16);
+            rest[12..12 + answer.data_len()].copy_from_slice(answer.address.as_bytes());
+        }
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.flags.contains(Flags::RESPONSE) {
+            write!(
+                f,
+                "LLMNR response id={} name={} type={} rcode={}",
+                self.transaction_id, self.name, self.type_, self.rcode
+            )?;
+        } else {
+            write!(
+                f,
+                "LLMNR query id={} name={} type={}",
+                self.transaction_id, self.name, self.type_
+            )?;
+        }
+        if let Some(answer) = self.answer {
+            write!(f, " addr={} ttl={}", answer.address, answer.ttl)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "LLMNR ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(feature = "proto-ipv4")] // tests assume ipv4
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+
+    static QUERY_BYTES: [u8; 24] = [
+        0x12, 0x34, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x64, 0x65,
+        0x76, 0x69, 0x63, 0x65, 0x00, 0x00, 0x01, 0x00, 0x01,
+    ];
+
+    static RESPONSE_BYTES: [u8; 40] = [
+        0x12, 0x34, 0x80, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x06, 0x64, 0x65,
+        0x76, 0x69, 0x63, 0x65, 0x00, 0x00, 0x01, 0x00, 0x01, 0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01,
+        0x00, 0x00, 0x00, 0x1e, 0x00, 0x04, 0xc0, 0xa8, 0x01, 0x0a,
+    ];
+
+    fn query_repr() -> Repr<'static> {
+        Repr {
+            transaction_id: 0x1234,
+            flags: Flags::empty(),
+            rcode: Rcode::NoError,
+            name: Name(&QUERY_BYTES[12..20]),
+            type_: Type::A,
+            answer: None,
+        }
+    }
+
+    fn response_repr() -> Repr<'static> {
+        Repr {
+            flags: Flags::RESPONSE,
+            answer: Some(Answer {
+                ttl: 30,
+                address: IpAddress::v4(192, 168, 1, 10),
+            }),
+            ..query_repr()
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let packet = Packet::new_checked(&QUERY_BYTES[..]).unwrap();
+        assert_eq!(packet.transaction_id(), 0x1234);
+        assert_eq!(packet.question_count(), 1);
+        assert_eq!(Repr::parse(&packet), Ok(query_repr()));
+    }
+
+    #[test]
+    fn test_emit_query() {
+        let repr = query_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(bytes, &QUERY_BYTES[..]);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let packet = Packet::new_checked(&RESPONSE_BYTES[..]).unwrap();
+        assert_eq!(Repr::parse(&packet), Ok(response_repr()));
+    }
+
+    #[test]
+    fn test_emit_response() {
+        let repr = response_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(bytes, &RESPONSE_BYTES[..]);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut bytes = QUERY_BYTES;
+        bytes[2] = 0x08; // opcode 1
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = QUERY_BYTES;
+        bytes[5] = 2; // two questions
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = QUERY_BYTES;
+        bytes[12] = 0xc0; // compressed question name
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        assert_eq!(
+            Repr::parse(&Packet::new_unchecked(&QUERY_BYTES[..22])),
+            Err(Error)
+        );
+    }
+
+    #[test]
+    fn test_name() {
+        let mut buffer = [0; 32];
+        let name = Name::encode("Device.lan", &mut buffer).unwrap();
+        assert_eq!(name.as_bytes(), b"\x06Device\x03lan\x00");
+        assert!(name.eq_ignore_ascii_case("device.LAN"));
+        assert!(!name.eq_ignore_ascii_case("device"));
+        assert!(!name.eq_ignore_ascii_case("device.lan.local"));
+
+        assert_eq!(Name::encode("device..lan", &mut buffer), Err(Error));
+        assert_eq!(Name::encode("device", &mut buffer[..7]), Err(Error));
+    }
+}
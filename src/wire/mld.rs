@@ -4,7 +4,8 @@
 // [RFC 3810]: https://tools.ietf.org/html/rfc3810
 // [RFC 2710]: https://tools.ietf.org/html/rfc2710
 
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, write_u16};
+use core::fmt;
 
 use super::{Error, Result};
 use crate::wire::icmpv6::{field, Message, Packet};
@@ -42,7 +43,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn max_resp_code(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::MAX_RESP_CODE])
+        read_u16(data, field::MAX_RESP_CODE)
     }
 
     /// Return the address being queried.
@@ -77,7 +78,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn num_srcs(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::QUERY_NUM_SRCS])
+        read_u16(data, field::QUERY_NUM_SRCS)
     }
 }
 
@@ -90,7 +91,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn nr_mcast_addr_rcrds(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::NR_MCAST_RCRDS])
+        read_u16(data, field::NR_MCAST_RCRDS)
     }
 }
 
@@ -103,7 +104,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_max_resp_code(&mut self, code: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::MAX_RESP_CODE], code);
+        write_u16(data, field::MAX_RESP_CODE, code);
     }
 
     /// Set the address being queried.
@@ -147,7 +148,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_num_srcs(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::QUERY_NUM_SRCS], value);
+        write_u16(data, field::QUERY_NUM_SRCS, value);
     }
 }
 
@@ -160,7 +161,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_nr_mcast_addr_rcrds(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::NR_MCAST_RCRDS], value)
+        write_u16(data, field::NR_MCAST_RCRDS, value)
     }
 }
 
@@ -227,7 +228,7 @@ impl<T: AsRef<[u8]>> AddressRecord<T> {
     #[inline]
     pub fn num_srcs(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::RECORD_NUM_SRCS])
+        read_u16(data, field::RECORD_NUM_SRCS)
     }
 
     /// Return the multicast address field.
@@ -270,7 +271,7 @@ impl<T: AsMut<[u8]> + AsRef<[u8]>> AddressRecord<T> {
     #[inline]
     pub fn set_num_srcs(&mut self, num_srcs: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::RECORD_NUM_SRCS], num_srcs);
+        write_u16(data, field::RECORD_NUM_SRCS, num_srcs);
     }
 
     /// Return the multicast address field.
@@ -389,12 +390,35 @@ impl<'a> Repr<'a> {
     }
 }
 
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Repr::Query {
+                mcast_addr,
+                max_resp_code,
+                num_srcs,
+                ..
+            } => {
+                write!(f, "ICMPv6 MLD query group={}", mcast_addr)?;
+                if f.alternate() {
+                    write!(f, " max_resp={} srcs={}", max_resp_code, num_srcs)?;
+                }
+                Ok(())
+            }
+            Repr::Report {
+                nr_mcast_addr_rcrds,
+                ..
+            } => write!(f, "ICMPv6 MLD report records={}", nr_mcast_addr_rcrds),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::phy::ChecksumCapabilities;
     use crate::wire::icmpv6::Message;
-    use crate::wire::Icmpv6Repr;
+    use crate::wire::{Icmpv6Repr, ParseMode};
 
     static QUERY_PACKET_BYTES: [u8; 44] = [
         0x82, 0x00, 0x73, 0x74, 0x04, 0x00, 0x00, 0x00, 0xff, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -532,6 +556,7 @@ mod test {
             &Ipv6Address::LINK_LOCAL_ALL_ROUTERS.into(),
             &packet,
             &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
         );
         assert_eq!(repr, Ok(create_repr(Message::MldQuery)));
     }
@@ -544,6 +569,7 @@ mod test {
             &Ipv6Address::LINK_LOCAL_ALL_ROUTERS.into(),
             &packet,
             &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
         );
         assert_eq!(repr, Ok(create_repr(Message::MldReport)));
     }
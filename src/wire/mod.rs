@@ -62,7 +62,8 @@ let mut buffer = vec![0; repr.buffer_len() + repr.payload_len];
 { // parsing
     let packet = Ipv4Packet::new_checked(&buffer)
                             .expect("truncated packet");
-    let parsed = Ipv4Repr::parse(&packet, &ChecksumCapabilities::default())
+    let parsed = Ipv4Repr::parse(&packet, &ChecksumCapabilities::default(),
+                                 ParseMode::Permissive)
                           .expect("malformed packet");
     assert_eq!(repr, parsed);
 }
@@ -70,21 +71,22 @@ let mut buffer = vec![0; repr.buffer_len() + repr.payload_len];
 ```
 */
 
-mod field {
-    pub type Field = ::core::ops::Range<usize>;
-    pub type Rest = ::core::ops::RangeFrom<usize>;
-}
+mod field;
 
 pub mod pretty_print;
 
 #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
 mod arp;
+#[cfg(any(feature = "proto-ipv4", feature = "proto-ipv6"))]
+pub mod builder;
 #[cfg(feature = "proto-dhcpv4")]
 pub(crate) mod dhcpv4;
 #[cfg(feature = "proto-dns")]
 pub(crate) mod dns;
 #[cfg(feature = "medium-ethernet")]
 mod ethernet;
+#[cfg(feature = "proto-gre")]
+mod gre;
 #[cfg(any(feature = "proto-ipv4", feature = "proto-ipv6"))]
 mod icmp;
 #[cfg(feature = "proto-ipv4")]
@@ -96,6 +98,10 @@ pub mod ieee802154;
 #[cfg(feature = "proto-igmp")]
 mod igmp;
 pub(crate) mod ip;
+#[cfg(feature = "proto-ipsec")]
+mod ipsec_ah;
+#[cfg(feature = "proto-ipsec")]
+mod ipsec_esp;
 #[cfg(feature = "proto-ipv4")]
 mod ipv4;
 #[cfg(feature = "proto-ipv6")]
@@ -108,8 +114,14 @@ mod ipv6hopbyhop;
 mod ipv6option;
 #[cfg(feature = "proto-ipv6")]
 mod ipv6routing;
+#[cfg(feature = "proto-lldp")]
+mod lldp;
+#[cfg(feature = "proto-llmnr")]
+mod llmnr;
 #[cfg(feature = "proto-ipv6")]
 mod mld;
+#[cfg(feature = "proto-nbns")]
+mod nbns;
 #[cfg(all(
     feature = "proto-ipv6",
     any(feature = "medium-ethernet", feature = "medium-ieee802154")
@@ -120,10 +132,28 @@ mod ndisc;
     any(feature = "medium-ethernet", feature = "medium-ieee802154")
 ))]
 mod ndiscoption;
+#[cfg(feature = "proto-ntp")]
+mod ntp;
+#[cfg(feature = "proto-ppp")]
+mod ppp;
+#[cfg(feature = "proto-ptp")]
+mod ptp;
 #[cfg(all(feature = "proto-sixlowpan", feature = "medium-ieee802154"))]
 mod sixlowpan;
+#[cfg(feature = "proto-stp")]
+mod stp;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategy;
 mod tcp;
+#[cfg(feature = "proto-tftp")]
+mod tftp;
 mod udp;
+#[cfg(feature = "proto-udplite")]
+mod udplite;
+#[cfg(feature = "proto-vxlan")]
+mod vxlan;
+#[cfg(feature = "proto-wol")]
+mod wol;
 
 use core::fmt;
 
@@ -137,6 +167,26 @@ pub use self::ethernet::{
     Repr as EthernetRepr, HEADER_LEN as ETHERNET_HEADER_LEN,
 };
 
+#[cfg(feature = "proto-lldp")]
+pub use self::lldp::{
+    Capabilities as LldpCapabilities, ChassisIdSubtype as LldpChassisIdSubtype,
+    Packet as LldpPacket, PortIdSubtype as LldpPortIdSubtype, Repr as LldpRepr, Tlv as LldpTlv,
+    TlvType as LldpTlvType, MULTICAST_ADDR as LLDP_MULTICAST_ADDR,
+};
+
+#[cfg(feature = "proto-stp")]
+pub use self::stp::{
+    BpduType as StpBpduType, BridgeId as StpBridgeId, ConfigRepr as StpConfigRepr,
+    Flags as StpFlags, Packet as StpPacket, PortRole as StpPortRole, Repr as StpRepr,
+    LLC_HEADER as STP_LLC_HEADER, MULTICAST_ADDR as STP_MULTICAST_ADDR,
+};
+
+#[cfg(feature = "proto-ppp")]
+pub use self::ppp::{LcpCode, LcpPacket, LcpRepr, Protocol as PppProtocol, LCP_HEADER_LEN};
+
+#[cfg(feature = "proto-wol")]
+pub use self::wol::{Packet as WolPacket, Repr as WolRepr, UDP_PORT as WOL_UDP_PORT};
+
 #[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
 pub use self::arp::{
     Hardware as ArpHardware, Operation as ArpOperation, Packet as ArpPacket, Repr as ArpRepr,
@@ -201,8 +251,14 @@ pub use self::icmpv4::{
     TimeExceeded as Icmpv4TimeExceeded,
 };
 
+#[cfg(feature = "proto-gre")]
+pub use self::gre::{Packet as GrePacket, Repr as GreRepr, HEADER_LEN as GRE_HEADER_LEN};
+
 #[cfg(feature = "proto-igmp")]
-pub use self::igmp::{IgmpVersion, Packet as IgmpPacket, Repr as IgmpRepr};
+pub use self::igmp::{
+    GroupRecord as IgmpGroupRecord, IgmpVersion, Packet as IgmpPacket,
+    RecordType as IgmpRecordType, Repr as IgmpRepr,
+};
 
 #[cfg(feature = "proto-ipv6")]
 pub use self::icmpv6::{
@@ -226,9 +282,11 @@ pub use self::ndisc::{
     any(feature = "medium-ethernet", feature = "medium-ieee802154")
 ))]
 pub use self::ndiscoption::{
-    NdiscOption, PrefixInfoFlags as NdiscPrefixInfoFlags,
-    PrefixInformation as NdiscPrefixInformation, RedirectedHeader as NdiscRedirectedHeader,
-    Repr as NdiscOptionRepr, Type as NdiscOptionType,
+    DnsSearchList as NdiscDnsSearchList, NdiscOption, PrefixInfoFlags as NdiscPrefixInfoFlags,
+    PrefixInformation as NdiscPrefixInformation, RecursiveDnsServer as NdiscRecursiveDnsServer,
+    RedirectedHeader as NdiscRedirectedHeader, Repr as NdiscOptionRepr,
+    RouteInformation as NdiscRouteInformation, RoutePreference as NdiscRoutePreference,
+    Type as NdiscOptionType,
 };
 
 #[cfg(feature = "proto-ipv6")]
@@ -236,13 +294,75 @@ pub use self::mld::{AddressRecord as MldAddressRecord, Repr as MldRepr};
 
 pub use self::udp::{Packet as UdpPacket, Repr as UdpRepr, HEADER_LEN as UDP_HEADER_LEN};
 
+#[cfg(feature = "proto-udplite")]
+pub use self::udplite::{
+    Packet as UdpLitePacket, Repr as UdpLiteRepr, HEADER_LEN as UDPLITE_HEADER_LEN,
+};
+
+#[cfg(feature = "proto-vxlan")]
+pub use self::vxlan::{
+    Packet as VxlanPacket, Repr as VxlanRepr, HEADER_LEN as VXLAN_HEADER_LEN,
+    UDP_PORT as VXLAN_UDP_PORT,
+};
+
 pub use self::tcp::{
     Control as TcpControl, Packet as TcpPacket, Repr as TcpRepr, SeqNumber as TcpSeqNumber,
     TcpOption, HEADER_LEN as TCP_HEADER_LEN,
 };
 
+#[cfg(feature = "proto-ipsec")]
+pub use self::ipsec_ah::{
+    Packet as IpSecAuthHeaderPacket, Repr as IpSecAuthHeaderRepr,
+    HEADER_LEN as IPSEC_AUTH_HEADER_LEN,
+};
+#[cfg(all(feature = "proto-llmnr", feature = "proto-ipv4"))]
+pub use self::llmnr::IPV4_MULTICAST_ADDR as LLMNR_IPV4_MULTICAST_ADDR;
+#[cfg(all(feature = "proto-llmnr", feature = "proto-ipv6"))]
+pub use self::llmnr::IPV6_MULTICAST_ADDR as LLMNR_IPV6_MULTICAST_ADDR;
+
+#[cfg(feature = "proto-ipsec")]
+pub use self::ipsec_esp::{
+    Packet as IpSecEspPacket, Repr as IpSecEspRepr, HEADER_LEN as IPSEC_ESP_HEADER_LEN,
+    TRAILER_LEN as IPSEC_ESP_TRAILER_LEN,
+};
+
+#[cfg(feature = "proto-llmnr")]
+pub use self::llmnr::{
+    Answer as LlmnrAnswer, Flags as LlmnrFlags, Name as LlmnrName, Packet as LlmnrPacket,
+    Rcode as LlmnrRcode, Repr as LlmnrRepr, Type as LlmnrType, HEADER_LEN as LLMNR_HEADER_LEN,
+    UDP_PORT as LLMNR_UDP_PORT,
+};
+
+#[cfg(feature = "proto-nbns")]
+pub use self::nbns::{
+    Flags as NbnsFlags, Name as NbnsName, OpCode as NbnsOpCode, Packet as NbnsPacket,
+    Repr as NbnsRepr, HEADER_LEN as NBNS_HEADER_LEN, UDP_PORT as NBNS_UDP_PORT,
+};
+
+#[cfg(feature = "proto-ntp")]
+pub use self::ntp::{
+    LeapIndicator as NtpLeapIndicator, Packet as NtpPacket, ProtocolMode as NtpProtocolMode,
+    Repr as NtpRepr, Timestamp as NtpTimestamp, PACKET_LEN as NTP_PACKET_LEN,
+    SERVER_PORT as NTP_SERVER_PORT,
+};
+
+#[cfg(feature = "proto-ptp")]
+pub use self::ptp::{
+    Announce as PtpAnnounce, ClockIdentity as PtpClockIdentity, ClockQuality as PtpClockQuality,
+    Flags as PtpFlags, Message as PtpMessage, MessageType as PtpMessageType, Packet as PtpPacket,
+    PortIdentity as PtpPortIdentity, Repr as PtpRepr, Timestamp as PtpTimestamp,
+    EVENT_PORT as PTP_EVENT_PORT, GENERAL_PORT as PTP_GENERAL_PORT, HEADER_LEN as PTP_HEADER_LEN,
+};
+
+#[cfg(feature = "proto-tftp")]
+pub use self::tftp::{
+    ErrorCode as TftpErrorCode, Mode as TftpMode, OpCode as TftpOpCode, Packet as TftpPacket,
+    Repr as TftpRepr, BLOCK_SIZE as TFTP_BLOCK_SIZE, SERVER_PORT as TFTP_SERVER_PORT,
+};
+
 #[cfg(feature = "proto-dhcpv4")]
 pub use self::dhcpv4::{
+    ClientFqdn as DhcpClientFqdn, DhcpOption, FqdnFlags as DhcpFqdnFlags,
     MessageType as DhcpMessageType, Packet as DhcpPacket, Repr as DhcpRepr,
     CLIENT_PORT as DHCP_CLIENT_PORT, MAX_DNS_SERVER_COUNT as DHCP_MAX_DNS_SERVER_COUNT,
     SERVER_PORT as DHCP_SERVER_PORT,
@@ -266,6 +386,22 @@ impl fmt::Display for Error {
 
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// How strictly `Repr::parse()` checks the fields that the representation does not carry.
+///
+/// In the permissive mode, reserved bits and padding are ignored, and so are options that
+/// are only partially understood, as the robustness principle suggests. In the strict mode,
+/// packets with nonzero reserved bits or padding, or with malformed options, are rejected,
+/// which suits deployments that would rather lose interoperability with sloppy peers than
+/// accept packets that do not follow the specifications.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ParseMode {
+    /// Ignore reserved bits, padding and malformed options.
+    Permissive,
+    /// Reject packets with nonzero reserved bits or padding, or with malformed options.
+    Strict,
+}
+
 /// Representation of an hardware address, such as an Ethernet address or an IEEE802.15.4 address.
 #[cfg(any(feature = "medium-ethernet", feature = "medium-ieee802154"))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
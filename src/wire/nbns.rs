@@ -0,0 +1,618 @@
+use crate::wire::field::{read_u16, write_u16};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::Ipv4Address;
+
+/// The UDP port of the NetBIOS name service.
+pub const UDP_PORT: u16 = 137;
+
+// NetBIOS record type NB (general name service resource record)
+const TYPE_NB: u16 = 0x0020;
+// NetBIOS class IN (Internet)
+const CLASS_IN: u16 = 0x0001;
+// NB_FLAGS group name bit
+const NB_FLAGS_GROUP: u16 = 0x8000;
+
+/// The length of an encoded name without scope: the length octet, 32 half-octet
+/// characters, and the terminating empty label.
+const ENCODED_NAME_LEN: usize = 34;
+
+bitflags! {
+    /// NetBIOS name service header flags.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Flags: u16 {
+        const RESPONSE            = 0b1000_0000_0000_0000;
+        const AUTHORITATIVE       = 0b0000_0100_0000_0000;
+        const TRUNCATED           = 0b0000_0010_0000_0000;
+        const RECURSION_DESIRED   = 0b0000_0001_0000_0000;
+        const RECURSION_AVAILABLE = 0b0000_0000_1000_0000;
+        const BROADCAST           = 0b0000_0000_0001_0000;
+    }
+}
+
+enum_with_unknown! {
+    /// NetBIOS name service opcode.
+    pub enum OpCode(u8) {
+        Query        = 0,
+        Registration = 5,
+        Release      = 6,
+        Wack         = 7,
+        Refresh      = 8
+    }
+}
+
+/// A NetBIOS name: up to 15 characters padded with spaces, followed by a suffix
+/// octet identifying the service.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Name([u8; 16]);
+
+impl Name {
+    /// The suffix of the name of a workstation.
+    pub const SUFFIX_WORKSTATION: u8 = 0x00;
+    /// The suffix of the name of a file server.
+    pub const SUFFIX_SERVER: u8 = 0x20;
+
+    /// Construct a name from `name` and the service `suffix`.
+    ///
+    /// NetBIOS names are case insensitive, and are converted to upper case.
+    /// Returns `Err(Error)` if `name` is empty, longer than 15 characters, or not ASCII.
+    pub fn new(name: &str, suffix: u8) -> Result<Name> {
+        if name.is_empty() || name.len() > 15 || !name.is_ascii() {
+            return Err(Error);
+        }
+        let mut bytes = [b' '; 16];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        bytes[..15].make_ascii_uppercase();
+        bytes[15] = suffix;
+        Ok(Name(bytes))
+    }
+
+    /// Return the name, without padding or suffix.
+    pub fn name(&self) -> &[u8] {
+        let len = self.0[..15]
+            .iter()
+            .rposition(|b| *b != b' ')
+            .map_or(0, |pos| pos + 1);
+        &self.0[..len]
+    }
+
+    /// Return the service suffix.
+    pub fn suffix(&self) -> u8 {
+        self.0[15]
+    }
+
+    /// Split a first-level encoded name (RFC 1001 § 14.1) off the front of `data`.
+    ///
+    /// Names with a scope identifier are not supported.
+    fn parse(data: &[u8]) -> Result<(Name, &[u8])> {
+        if data.len() < ENCODED_NAME_LEN || data[0] != 32 || data[33] != 0 {
+            return Err(Error);
+        }
+        let mut bytes = [0; 16];
+        for (byte, pair) in bytes.iter_mut().zip(data[1..33].chunks(2)) {
+            let hi = pair[0].wrapping_sub(b'A');
+            let lo = pair[1].wrapping_sub(b'A');
+            if hi > 0xf || lo > 0xf {
+                return Err(Error);
+            }
+            *byte = hi << 4 | lo;
+        }
+        Ok((Name(bytes), &data[ENCODED_NAME_LEN..]))
+    }
+
+    /// Emit the first-level encoded name into the front of `data`.
+    fn emit(&self, data: &mut [u8]) {
+        data[0] = 32;
+        for (byte, pair) in self.0.iter().zip(data[1..33].chunks_mut(2)) {
+            pair[0] = b'A' + (byte >> 4);
+            pair[1] = b'A' + (byte & 0xf);
+        }
+        data[33] = 0;
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &b in self.name() {
+            if b.is_ascii_graphic() || b == b' ' {
+                write!(f, "{}", b as char)?;
+            } else {
+                write!(f, "\\x{:02x}", b)?;
+            }
+        }
+        write!(f, "<{:02x}>", self.suffix())
+    }
+}
+
+/// A read/write wrapper around a NetBIOS name service packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// The name service header has the same layout as the DNS header, except for the flags:
+//
+//  | R | OPCODE | AA | TC | RD | RA | 0 | 0 | B | RCODE |
+//
+// See RFC 1002 § 4.2.1 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const ID: Field = 0..2;
+    pub const FLAGS: Field = 2..4;
+    pub const QDCOUNT: Field = 4..6;
+    pub const ANCOUNT: Field = 6..8;
+    pub const NSCOUNT: Field = 8..10;
+    pub const ARCOUNT: Field = 10..12;
+    pub const PAYLOAD: Rest = 12..;
+}
+
+/// The length of a NetBIOS name service header.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with NetBIOS name service packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is shorter than the header.
+    pub fn check_len(&self) -> Result<()> {
+        if self.buffer.as_ref().len() < HEADER_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the transaction ID field.
+    #[inline]
+    pub fn transaction_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ID)
+    }
+
+    /// Return the flags.
+    #[inline]
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags::from_bits_truncate(read_u16(data, field::FLAGS))
+    }
+
+    /// Return the opcode field.
+    #[inline]
+    pub fn opcode(&self) -> OpCode {
+        let data = self.buffer.as_ref();
+        OpCode::from((read_u16(data, field::FLAGS) >> 11 & 0xf) as u8)
+    }
+
+    /// Return the response code field.
+    #[inline]
+    pub fn rcode(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        (read_u16(data, field::FLAGS) & 0xf) as u8
+    }
+
+    /// Return the question count field.
+    #[inline]
+    pub fn question_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::QDCOUNT)
+    }
+
+    /// Return the answer record count field.
+    #[inline]
+    pub fn answer_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ANCOUNT)
+    }
+
+    /// Return the authority record count field.
+    #[inline]
+    pub fn authority_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::NSCOUNT)
+    }
+
+    /// Return the additional record count field.
+    #[inline]
+    pub fn additional_record_count(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::ARCOUNT)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the sections following the header.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the transaction ID field.
+    #[inline]
+    pub fn set_transaction_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ID, value)
+    }
+
+    /// Set the flags and opcode, clearing the response code.
+    #[inline]
+    pub fn set_flags(&mut self, flags: Flags, opcode: OpCode) {
+        let data = self.buffer.as_mut();
+        let raw = flags.bits() | (u8::from(opcode) as u16 & 0xf) << 11;
+        write_u16(data, field::FLAGS, raw)
+    }
+
+    /// Set the question count field.
+    #[inline]
+    pub fn set_question_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::QDCOUNT, value)
+    }
+
+    /// Set the answer record count field.
+    #[inline]
+    pub fn set_answer_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ANCOUNT, value)
+    }
+
+    /// Set the authority record count field.
+    #[inline]
+    pub fn set_authority_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::NSCOUNT, value)
+    }
+
+    /// Set the additional record count field.
+    #[inline]
+    pub fn set_additional_record_count(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ARCOUNT, value)
+    }
+
+    /// Return a mutable pointer to the sections following the header.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+}
+
+/// A high-level representation of a NetBIOS name service packet.
+///
+/// Currently only supports name queries and positive name query responses.
+/// Of the addresses in a response, only the first is represented.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Repr {
+    Query {
+        transaction_id: u16,
+        broadcast: bool,
+        recursion_desired: bool,
+        name: Name,
+    },
+    Response {
+        transaction_id: u16,
+        name: Name,
+        ttl: u32,
+        group: bool,
+        address: Ipv4Address,
+    },
+}
+
+impl Repr {
+    /// Parse a NetBIOS name service packet and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if packet.opcode() != OpCode::Query {
+            return Err(Error);
+        }
+
+        let flags = packet.flags();
+        if !flags.contains(Flags::RESPONSE) {
+            if packet.question_count() != 1 {
+                return Err(Error);
+            }
+            let (name, rest) = Name::parse(packet.payload())?;
+            if rest.len() < 4
+                || NetworkEndian::read_u16(&rest[0..2]) != TYPE_NB
+                || NetworkEndian::read_u16(&rest[2..4]) != CLASS_IN
+            {
+                return Err(Error);
+            }
+            Ok(Repr::Query {
+                transaction_id: packet.transaction_id(),
+                broadcast: flags.contains(Flags::BROADCAST),
+                recursion_desired: flags.contains(Flags::RECURSION_DESIRED),
+                name,
+            })
+        } else {
+            if packet.rcode() != 0 || packet.answer_record_count() == 0 {
+                return Err(Error);
+            }
+            let (name, rest) = Name::parse(packet.payload())?;
+            if rest.len() < 16
+                || NetworkEndian::read_u16(&rest[0..2]) != TYPE_NB
+                || NetworkEndian::read_u16(&rest[2..4]) != CLASS_IN
+                || NetworkEndian::read_u16(&rest[8..10]) < 6
+            {
+                return Err(Error);
+            }
+            Ok(Repr::Response {
+                transaction_id: packet.transaction_id(),
+                name,
+                ttl: NetworkEndian::read_u32(&rest[4..8]),
+                group: NetworkEndian::read_u16(&rest[10..12]) & NB_FLAGS_GROUP != 0,
+                address: Ipv4Address::from_bytes(&rest[12..16]),
+            })
+        }
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        match *self {
+            // Name, type and class.
+            Repr::Query { .. } => HEADER_LEN + ENCODED_NAME_LEN + 4,
+            // Name, type, class, TTL, length, flags and address.
+            Repr::Response { .. } => HEADER_LEN + ENCODED_NAME_LEN + 10 + 6,
+        }
+    }
+
+    /// Emit a high-level representation into a NetBIOS name service packet.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_authority_record_count(0);
+        packet.set_additional_record_count(0);
+        match *self {
+            Repr::Query {
+                transaction_id,
+                broadcast,
+                recursion_desired,
+                name,
+            } => {
+                let mut flags = Flags::empty();
+                flags.set(Flags::BROADCAST, broadcast);
+                flags.set(Flags::RECURSION_DESIRED, recursion_desired);
+                packet.set_transaction_id(transaction_id);
+                packet.set_flags(flags, OpCode::Query);
+                packet.set_question_count(1);
+                packet.set_answer_record_count(0);
+
+                let payload = packet.payload_mut();
+                name.emit(payload);
+                let rest = &mut payload[ENCODED_NAME_LEN..];
+                NetworkEndian::write_u16(&mut rest[0..2], TYPE_NB);
+                NetworkEndian::write_u16(&mut rest[2..4], CLASS_IN);
+            }
+            Repr::Response {
+                transaction_id,
+                name,
+                ttl,
+                group,
+                address,
+            } => {
+                packet.set_transaction_id(transaction_id);
+                packet.set_flags(
+                    Flags::RESPONSE | Flags::AUTHORITATIVE | Flags::RECURSION_DESIRED,
+                    OpCode::Query,
+                );
+                packet.set_question_count(0);
+                packet.set_answer_record_count(1);
+
+                let payload = packet.payload_mut();
+                name.emit(payload);
+                let rest = &mut payload[ENCODED_NAME_LEN..];
+                NetworkEndian::write_u16(&mut rest[0..2], TYPE_NB);
+                NetworkEndian::write_u16(&mut rest[2..4], CLASS_IN);
+                NetworkEndian::write_u32(&mut rest[4..8], ttl);
+                NetworkEndian::write_u16(&mut rest[8..10], 6);
+                let nb_flags = if group { NB_FLAGS_GROUP } else { 0 };
+                NetworkEndian::write_u16(&mut rest[10..12], nb_flags);
+                rest[12..16].copy_from_slice(address.as_bytes());
+            }
+        }
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Repr::Query {
+                transaction_id,
+                broadcast,
+                name,
+                ..
+            } => {
+                write!(f, "NBNS query id={} name={}", transaction_id, name)?;
+                if broadcast {
+                    write!(f, " broadcast")?;
+                }
+                Ok(())
+            }
+            Repr::Response {
+                transaction_id,
+                name,
+                ttl,
+                group,
+                address,
+            } => {
+                write!(
+                    f,
+                    "NBNS response id={} name={} addr={} ttl={}",
+                    transaction_id, name, address, ttl
+                )?;
+                if group {
+                    write!(f, " group")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "NBNS ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::vec;
+    use std::vec::Vec;
+
+    // "DEVICE<00>", first-level encoded.
+    const ENCODED_NAME: &[u8; 32] = b"EEEFFGEJEDEFCACACACACACACACACAAA";
+
+    fn query_bytes() -> Vec<u8> {
+        let mut bytes = vec![
+            0x12, 0x34, 0x01, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20,
+        ];
+        bytes.extend_from_slice(ENCODED_NAME);
+        bytes.extend_from_slice(&[0x00, 0x00, 0x20, 0x00, 0x01]);
+        bytes
+    }
+
+    fn response_bytes() -> Vec<u8> {
+        let mut bytes = vec![
+            0x12, 0x34, 0x85, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x20,
+        ];
+        bytes.extend_from_slice(ENCODED_NAME);
+        bytes.extend_from_slice(&[
+            0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x04, 0x93, 0xe0, 0x00, 0x06, 0x00, 0x00, 0xc0,
+            0xa8, 0x01, 0x0a,
+        ]);
+        bytes
+    }
+
+    fn name() -> Name {
+        Name::new("device", Name::SUFFIX_WORKSTATION).unwrap()
+    }
+
+    fn query_repr() -> Repr {
+        Repr::Query {
+            transaction_id: 0x1234,
+            broadcast: true,
+            recursion_desired: true,
+            name: name(),
+        }
+    }
+
+    fn response_repr() -> Repr {
+        Repr::Response {
+            transaction_id: 0x1234,
+            name: name(),
+            ttl: 300_000,
+            group: false,
+            address: Ipv4Address([192, 168, 1, 10]),
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        let name = name();
+        assert_eq!(name.name(), b"DEVICE");
+        assert_eq!(name.suffix(), 0x00);
+        assert_eq!(format!("{}", name), "DEVICE<00>");
+
+        assert_eq!(Name::new("", 0), Err(Error));
+        assert_eq!(Name::new("sixteen-letters!", 0), Err(Error));
+        assert_eq!(Name::new("dévice", 0), Err(Error));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let bytes = query_bytes();
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.opcode(), OpCode::Query);
+        assert_eq!(Repr::parse(&packet), Ok(query_repr()));
+    }
+
+    #[test]
+    fn test_emit_query() {
+        let repr = query_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(bytes, query_bytes());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let bytes = response_bytes();
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(Repr::parse(&packet), Ok(response_repr()));
+    }
+
+    #[test]
+    fn test_emit_response() {
+        let repr = response_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+        assert_eq!(bytes, response_bytes());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut bytes = query_bytes();
+        bytes[13] = b'Z'; // not a half-octet character
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = query_bytes();
+        bytes[45] = 0x04; // scope identifier
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = query_bytes();
+        bytes[2] = 0x29; // registration
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+    }
+}
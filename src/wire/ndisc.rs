@@ -1,5 +1,6 @@
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
 use bitflags::bitflags;
-use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
 
 use super::{Error, Result};
 use crate::time::Duration;
@@ -7,8 +8,11 @@ use crate::wire::icmpv6::{field, Message, Packet};
 use crate::wire::Ipv6Address;
 use crate::wire::RawHardwareAddress;
 use crate::wire::{Ipv6Packet, Ipv6Repr};
+use crate::wire::{
+    NdiscDnsSearchList, NdiscPrefixInformation, NdiscRecursiveDnsServer, NdiscRedirectedHeader,
+    NdiscRouteInformation, NdiscRoutePreference,
+};
 use crate::wire::{NdiscOption, NdiscOptionRepr, NdiscOptionType};
-use crate::wire::{NdiscPrefixInformation, NdiscRedirectedHeader};
 
 bitflags! {
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -46,25 +50,37 @@ impl<T: AsRef<[u8]>> Packet<T> {
         RouterFlags::from_bits_truncate(data[field::ROUTER_FLAGS])
     }
 
+    /// Return the default router preference, see [RFC 4191 § 2.2].
+    ///
+    /// The reserved value is returned as the medium preference.
+    ///
+    /// [RFC 4191 § 2.2]: https://tools.ietf.org/html/rfc4191#section-2.2
+    #[inline]
+    pub fn router_preference(&self) -> NdiscRoutePreference {
+        let data = self.buffer.as_ref();
+        NdiscRoutePreference::from_bits(data[field::ROUTER_FLAGS])
+            .unwrap_or(NdiscRoutePreference::Medium)
+    }
+
     /// Return the router lifetime field.
     #[inline]
     pub fn router_lifetime(&self) -> Duration {
         let data = self.buffer.as_ref();
-        Duration::from_secs(NetworkEndian::read_u16(&data[field::ROUTER_LT]) as u64)
+        Duration::from_secs(read_u16(data, field::ROUTER_LT) as u64)
     }
 
     /// Return the reachable time field.
     #[inline]
     pub fn reachable_time(&self) -> Duration {
         let data = self.buffer.as_ref();
-        Duration::from_millis(NetworkEndian::read_u32(&data[field::REACHABLE_TM]) as u64)
+        Duration::from_millis(read_u32(data, field::REACHABLE_TM) as u64)
     }
 
     /// Return the retransmit time field.
     #[inline]
     pub fn retrans_time(&self) -> Duration {
         let data = self.buffer.as_ref();
-        Duration::from_millis(NetworkEndian::read_u32(&data[field::RETRANS_TM]) as u64)
+        Duration::from_millis(read_u32(data, field::RETRANS_TM) as u64)
     }
 }
 
@@ -124,28 +140,36 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     /// Set the Router Advertisement flags.
     #[inline]
     pub fn set_router_flags(&mut self, flags: RouterFlags) {
-        self.buffer.as_mut()[field::ROUTER_FLAGS] = flags.bits();
+        let data = self.buffer.as_mut();
+        data[field::ROUTER_FLAGS] = flags.bits() | (data[field::ROUTER_FLAGS] & 0b0001_1000);
+    }
+
+    /// Set the default router preference.
+    #[inline]
+    pub fn set_router_preference(&mut self, preference: NdiscRoutePreference) {
+        let data = self.buffer.as_mut();
+        data[field::ROUTER_FLAGS] = (data[field::ROUTER_FLAGS] & !0b0001_1000) | preference.bits();
     }
 
     /// Set the router lifetime field.
     #[inline]
     pub fn set_router_lifetime(&mut self, value: Duration) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::ROUTER_LT], value.secs() as u16);
+        write_u16(data, field::ROUTER_LT, value.secs() as u16);
     }
 
     /// Set the reachable time field.
     #[inline]
     pub fn set_reachable_time(&mut self, value: Duration) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::REACHABLE_TM], value.total_millis() as u32);
+        write_u32(data, field::REACHABLE_TM, value.total_millis() as u32);
     }
 
     /// Set the retransmit time field.
     #[inline]
     pub fn set_retrans_time(&mut self, value: Duration) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::RETRANS_TM], value.total_millis() as u32);
+        write_u32(data, field::RETRANS_TM, value.total_millis() as u32);
     }
 }
 
@@ -199,12 +223,16 @@ pub enum Repr<'a> {
     RouterAdvert {
         hop_limit: u8,
         flags: RouterFlags,
+        preference: NdiscRoutePreference,
         router_lifetime: Duration,
         reachable_time: Duration,
         retrans_time: Duration,
         lladdr: Option<RawHardwareAddress>,
         mtu: Option<u32>,
         prefix_info: Option<NdiscPrefixInformation>,
+        route_info: Option<NdiscRouteInformation>,
+        rdnss: Option<NdiscRecursiveDnsServer<'a>>,
+        dnssl: Option<NdiscDnsSearchList<'a>>,
     },
     NeighborSolicit {
         target_addr: Ipv6Address,
@@ -247,29 +275,48 @@ impl<'a> Repr<'a> {
             }
             Message::RouterAdvert => {
                 let mut offset = 0;
-                let (mut lladdr, mut mtu, mut prefix_info) = (None, None, None);
+                let (mut lladdr, mut mtu, mut prefix_info, mut route_info) =
+                    (None, None, None, None);
+                let (mut rdnss, mut dnssl) = (None, None);
                 while packet.payload().len() - offset > 0 {
                     let pkt = NdiscOption::new_checked(&packet.payload()[offset..])?;
-                    let opt = NdiscOptionRepr::parse(&pkt)?;
-                    match opt {
-                        NdiscOptionRepr::SourceLinkLayerAddr(addr) => lladdr = Some(addr),
-                        NdiscOptionRepr::Mtu(val) => mtu = Some(val),
-                        NdiscOptionRepr::PrefixInformation(info) => prefix_info = Some(info),
+                    match NdiscOptionRepr::parse(&pkt) {
+                        Ok(NdiscOptionRepr::SourceLinkLayerAddr(addr)) => lladdr = Some(addr),
+                        Ok(NdiscOptionRepr::Mtu(val)) => mtu = Some(val),
+                        Ok(NdiscOptionRepr::PrefixInformation(info)) => prefix_info = Some(info),
+                        Ok(NdiscOptionRepr::RouteInformation(info)) => route_info = Some(info),
+                        Ok(NdiscOptionRepr::RecursiveDnsServer(info)) => rdnss = Some(info),
+                        Ok(NdiscOptionRepr::DnsSearchList(info)) => dnssl = Some(info),
+                        // Unrecognized options are ignored, per RFC 4861 § 4.6, and so are
+                        // invalid Route Information options, per RFC 4191 § 2.3, and invalid
+                        // DNS options, per RFC 8106 § 5.3.1.
+                        Ok(NdiscOptionRepr::Unknown { .. }) => (),
+                        Err(_)
+                            if matches!(
+                                pkt.option_type(),
+                                NdiscOptionType::RouteInformation
+                                    | NdiscOptionType::RecursiveDnsServer
+                                    | NdiscOptionType::DnsSearchList
+                            ) => {}
                         _ => {
                             return Err(Error);
                         }
                     }
-                    offset += opt.buffer_len();
+                    offset += pkt.data_len() as usize * 8;
                 }
                 Ok(Repr::RouterAdvert {
                     hop_limit: packet.current_hop_limit(),
                     flags: packet.router_flags(),
+                    preference: packet.router_preference(),
                     router_lifetime: packet.router_lifetime(),
                     reachable_time: packet.reachable_time(),
                     retrans_time: packet.retrans_time(),
                     lladdr,
                     mtu,
                     prefix_info,
+                    route_info,
+                    rdnss,
+                    dnssl,
                 })
             }
             Message::NeighborSolicit => {
@@ -358,6 +405,9 @@ impl<'a> Repr<'a> {
                 lladdr,
                 mtu,
                 prefix_info,
+                route_info,
+                rdnss,
+                dnssl,
                 ..
             } => {
                 let mut offset = 0;
@@ -370,6 +420,15 @@ impl<'a> Repr<'a> {
                 if prefix_info.is_some() {
                     offset += 32;
                 }
+                if let Some(route_info) = route_info {
+                    offset += NdiscOptionRepr::RouteInformation(route_info).buffer_len();
+                }
+                if let Some(rdnss) = rdnss {
+                    offset += NdiscOptionRepr::RecursiveDnsServer(rdnss).buffer_len();
+                }
+                if let Some(dnssl) = dnssl {
+                    offset += NdiscOptionRepr::DnsSearchList(dnssl).buffer_len();
+                }
                 field::RETRANS_TM.end + offset
             }
             &Repr::NeighborSolicit { lladdr, .. } | &Repr::NeighborAdvert { lladdr, .. } => {
@@ -414,17 +473,22 @@ impl<'a> Repr<'a> {
             Repr::RouterAdvert {
                 hop_limit,
                 flags,
+                preference,
                 router_lifetime,
                 reachable_time,
                 retrans_time,
                 lladdr,
                 mtu,
                 prefix_info,
+                route_info,
+                rdnss,
+                dnssl,
             } => {
                 packet.set_msg_type(Message::RouterAdvert);
                 packet.set_msg_code(0);
                 packet.set_current_hop_limit(hop_limit);
                 packet.set_router_flags(flags);
+                packet.set_router_preference(preference);
                 packet.set_router_lifetime(router_lifetime);
                 packet.set_reachable_time(reachable_time);
                 packet.set_retrans_time(retrans_time);
@@ -444,7 +508,27 @@ impl<'a> Repr<'a> {
                 if let Some(prefix_info) = prefix_info {
                     let mut opt_pkt =
                         NdiscOption::new_unchecked(&mut packet.payload_mut()[offset..]);
-                    NdiscOptionRepr::PrefixInformation(prefix_info).emit(&mut opt_pkt)
+                    NdiscOptionRepr::PrefixInformation(prefix_info).emit(&mut opt_pkt);
+                    offset += 32;
+                }
+                if let Some(route_info) = route_info {
+                    let mut opt_pkt =
+                        NdiscOption::new_unchecked(&mut packet.payload_mut()[offset..]);
+                    let opt = NdiscOptionRepr::RouteInformation(route_info);
+                    opt.emit(&mut opt_pkt);
+                    offset += opt.buffer_len();
+                }
+                if let Some(rdnss) = rdnss {
+                    let mut opt_pkt =
+                        NdiscOption::new_unchecked(&mut packet.payload_mut()[offset..]);
+                    let opt = NdiscOptionRepr::RecursiveDnsServer(rdnss);
+                    opt.emit(&mut opt_pkt);
+                    offset += opt.buffer_len();
+                }
+                if let Some(dnssl) = dnssl {
+                    let mut opt_pkt =
+                        NdiscOption::new_unchecked(&mut packet.payload_mut()[offset..]);
+                    NdiscOptionRepr::DnsSearchList(dnssl).emit(&mut opt_pkt)
                 }
             }
 
@@ -507,13 +591,76 @@ impl<'a> Repr<'a> {
     }
 }
 
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lladdr = match *self {
+            Repr::RouterSolicit { lladdr } => {
+                write!(f, "ICMPv6 router solicitation")?;
+                lladdr
+            }
+            Repr::RouterAdvert {
+                router_lifetime,
+                lladdr,
+                hop_limit,
+                mtu,
+                ..
+            } => {
+                write!(
+                    f,
+                    "ICMPv6 router advertisement lifetime={}",
+                    router_lifetime
+                )?;
+                if f.alternate() {
+                    write!(f, " hop_limit={}", hop_limit)?;
+                    if let Some(mtu) = mtu {
+                        write!(f, " mtu={}", mtu)?;
+                    }
+                }
+                lladdr
+            }
+            Repr::NeighborSolicit {
+                target_addr,
+                lladdr,
+            } => {
+                write!(f, "ICMPv6 neighbor solicitation target={}", target_addr)?;
+                lladdr
+            }
+            Repr::NeighborAdvert {
+                target_addr,
+                lladdr,
+                ..
+            } => {
+                write!(f, "ICMPv6 neighbor advertisement target={}", target_addr)?;
+                lladdr
+            }
+            Repr::Redirect {
+                target_addr,
+                dest_addr,
+                lladdr,
+                ..
+            } => {
+                write!(
+                    f,
+                    "ICMPv6 redirect target={} dst={}",
+                    target_addr, dest_addr
+                )?;
+                lladdr
+            }
+        };
+        match lladdr {
+            Some(lladdr) if f.alternate() => write!(f, " lladdr={}", lladdr),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::phy::ChecksumCapabilities;
     use crate::wire::ip::test::{MOCK_IP_ADDR_1, MOCK_IP_ADDR_2};
     use crate::wire::EthernetAddress;
-    use crate::wire::Icmpv6Repr;
+    use crate::wire::{Icmpv6Repr, ParseMode};
 
     static ROUTER_ADVERT_BYTES: [u8; 24] = [
         0x86, 0x00, 0xa9, 0xde, 0x40, 0x80, 0x03, 0x84, 0x00, 0x00, 0x03, 0x84, 0x00, 0x00, 0x03,
@@ -525,12 +672,16 @@ mod test {
         Icmpv6Repr::Ndisc(Repr::RouterAdvert {
             hop_limit: 64,
             flags: RouterFlags::MANAGED,
+            preference: NdiscRoutePreference::Medium,
             router_lifetime: Duration::from_secs(900),
             reachable_time: Duration::from_millis(900),
             retrans_time: Duration::from_millis(900),
             lladdr: Some(EthernetAddress([0x52, 0x54, 0x00, 0x12, 0x34, 0x56]).into()),
             mtu: None,
             prefix_info: None,
+            route_info: None,
+            rdnss: None,
+            dnssl: None,
         })
     }
 
@@ -573,7 +724,8 @@ mod test {
                 &MOCK_IP_ADDR_1,
                 &MOCK_IP_ADDR_2,
                 &packet,
-                &ChecksumCapabilities::default()
+                &ChecksumCapabilities::default(),
+                ParseMode::Permissive
             )
             .unwrap(),
             create_repr()
@@ -592,4 +744,92 @@ mod test {
         );
         assert_eq!(&packet.into_inner()[..], &ROUTER_ADVERT_BYTES[..]);
     }
+
+    #[test]
+    fn test_router_advert_route_info() {
+        let repr = Icmpv6Repr::Ndisc(Repr::RouterAdvert {
+            hop_limit: 64,
+            flags: RouterFlags::empty(),
+            preference: NdiscRoutePreference::High,
+            router_lifetime: Duration::from_secs(1800),
+            reachable_time: Duration::ZERO,
+            retrans_time: Duration::ZERO,
+            lladdr: None,
+            mtu: Some(1500),
+            prefix_info: None,
+            route_info: Some(NdiscRouteInformation {
+                prefix_len: 48,
+                preference: NdiscRoutePreference::Low,
+                lifetime: Duration::from_secs(600),
+                prefix: Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 0),
+            }),
+            rdnss: Some(NdiscRecursiveDnsServer {
+                lifetime: Duration::from_secs(1200),
+                addresses: &[
+                    0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x53,
+                ],
+            }),
+            dnssl: Some(NdiscDnsSearchList {
+                lifetime: Duration::from_secs(1200),
+                domains: b"\x07example\x03com\x00\x00\x00\x00",
+            }),
+        });
+        let mut bytes = vec![0x2a; repr.buffer_len()];
+        let mut packet = Packet::new_unchecked(&mut bytes[..]);
+        repr.emit(
+            &MOCK_IP_ADDR_1,
+            &MOCK_IP_ADDR_2,
+            &mut packet,
+            &ChecksumCapabilities::default(),
+        );
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(packet.router_flags(), RouterFlags::empty());
+        assert_eq!(packet.router_preference(), NdiscRoutePreference::High);
+        assert_eq!(
+            Icmpv6Repr::parse(
+                &MOCK_IP_ADDR_1,
+                &MOCK_IP_ADDR_2,
+                &packet,
+                &ChecksumCapabilities::default(),
+                ParseMode::Permissive
+            ),
+            Ok(repr)
+        );
+    }
+
+    #[test]
+    fn test_router_advert_ignored_options() {
+        let mut bytes = ROUTER_ADVERT_BYTES.to_vec();
+        // The reserved router preference is treated as medium.
+        bytes[5] |= 0x10;
+        // Unknown options and invalid Route Information and DNS options are skipped.
+        bytes.extend_from_slice(&[0x2a, 0x01, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0x18, 0x01, 0x00, 0x10, 0, 0, 0x07, 0x08]);
+        bytes.extend_from_slice(&[0x19, 0x01, 0, 0, 0, 0, 0x0e, 0x10]);
+        bytes.extend_from_slice(&[0x1f, 0x01, 0, 0, 0, 0, 0x0e, 0x10]);
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(
+            Icmpv6Repr::parse(
+                &MOCK_IP_ADDR_1,
+                &MOCK_IP_ADDR_2,
+                &packet,
+                &ChecksumCapabilities::ignored(),
+                ParseMode::Permissive
+            )
+            .unwrap(),
+            create_repr()
+        );
+
+        // A zero-length option invalidates the message.
+        bytes.extend_from_slice(&[0x2a, 0x00, 0, 0, 0, 0, 0, 0]);
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert!(Icmpv6Repr::parse(
+            &MOCK_IP_ADDR_1,
+            &MOCK_IP_ADDR_2,
+            &packet,
+            &ChecksumCapabilities::ignored(),
+            ParseMode::Permissive
+        )
+        .is_err());
+    }
 }
@@ -1,5 +1,5 @@
+use crate::wire::field::{read_u32, write_u16, write_u32};
 use bitflags::bitflags;
-use byteorder::{ByteOrder, NetworkEndian};
 use core::fmt;
 
 use super::{Error, Result};
@@ -20,7 +20,13 @@ enum_with_unknown! {
         /// Redirected Header
         RedirectedHeader    = 0x4,
         /// MTU
-        Mtu                 = 0x5
+        Mtu                 = 0x5,
+        /// Route Information
+        RouteInformation    = 0x18,
+        /// Recursive DNS Server
+        RecursiveDnsServer  = 0x19,
+        /// DNS Search List
+        DnsSearchList       = 0x1f
     }
 }
 
@@ -32,6 +38,9 @@ impl fmt::Display for Type {
             Type::PrefixInformation => write!(f, "prefix information"),
             Type::RedirectedHeader => write!(f, "redirected header"),
             Type::Mtu => write!(f, "mtu"),
+            Type::RouteInformation => write!(f, "route information"),
+            Type::RecursiveDnsServer => write!(f, "recursive dns server"),
+            Type::DnsSearchList => write!(f, "dns search list"),
             Type::Unknown(id) => write!(f, "{}", id),
         }
     }
@@ -45,6 +54,50 @@ bitflags! {
     }
 }
 
+/// A router or route preference, as specified in [RFC 4191 § 2.1].
+///
+/// [RFC 4191 § 2.1]: https://tools.ietf.org/html/rfc4191#section-2.1
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RoutePreference {
+    Low,
+    Medium,
+    High,
+}
+
+impl RoutePreference {
+    /// Return the preference encoded in the two bits of `bits` at `field::PRF_SHIFT`,
+    /// or `None` for the reserved value.
+    pub(crate) fn from_bits(bits: u8) -> Option<RoutePreference> {
+        match (bits >> field::PRF_SHIFT) & 0b11 {
+            0b00 => Some(RoutePreference::Medium),
+            0b01 => Some(RoutePreference::High),
+            0b11 => Some(RoutePreference::Low),
+            _ => None,
+        }
+    }
+
+    /// Return the encoding of the preference, shifted into position.
+    pub(crate) fn bits(&self) -> u8 {
+        let bits = match self {
+            RoutePreference::Medium => 0b00,
+            RoutePreference::High => 0b01,
+            RoutePreference::Low => 0b11,
+        };
+        bits << field::PRF_SHIFT
+    }
+}
+
+impl fmt::Display for RoutePreference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoutePreference::Low => write!(f, "low"),
+            RoutePreference::Medium => write!(f, "medium"),
+            RoutePreference::High => write!(f, "high"),
+        }
+    }
+}
+
 /// A read/write wrapper around an [NDISC Option].
 ///
 /// [NDISC Option]: https://tools.ietf.org/html/rfc4861#section-4.6
@@ -142,6 +195,43 @@ mod field {
 
     //  MTU
     pub const MTU: Field = 4..8;
+
+    // Route Information Option fields, see RFC 4191 § 2.3.
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |     Type      |    Length     | Prefix Length |Resvd|Prf|Resvd|
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |                        Route Lifetime                         |
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |                   Prefix (Variable Length)                    |
+    //  .                                                               .
+    //  .                                                               .
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+    // Route lifetime.
+    pub const ROUTE_LT: Field = 4..8;
+    // Start of the variable-length prefix.
+    pub const ROUTE_PREFIX: usize = 8;
+    // Position of the two preference bits in the flags field, here and in the
+    // Router Advertisement header.
+    pub const PRF_SHIFT: u8 = 3;
+
+    // Recursive DNS Server and DNS Search List Option fields, see RFC 8106 § 5.
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |     Type      |     Length    |           Reserved            |
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |                           Lifetime                            |
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    //  |                                                               |
+    //  :     Addresses of IPv6 Recursive DNS Servers, or Domain Names  :
+    //  |                                                               |
+    //  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+    // Reserved bits.
+    pub const DNS_RESERVED: Field = 2..4;
+    // Lifetime of the servers or domain names.
+    pub const DNS_LT: Field = 4..8;
+    // Start of the servers or domain names.
+    pub const DNS_DATA: usize = 8;
 }
 
 /// Core getter methods relevant to any type of NDISC option.
@@ -171,7 +261,7 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
         let data = self.buffer.as_ref();
         let len = data.len();
 
-        if len < field::MIN_OPT_LEN {
+        if len < field::MIN_OPT_LEN || data[field::LENGTH] == 0 {
             Err(Error)
         } else {
             let data_range = field::DATA(data[field::LENGTH]);
@@ -182,6 +272,8 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
                     Type::SourceLinkLayerAddr | Type::TargetLinkLayerAddr | Type::Mtu => Ok(()),
                     Type::PrefixInformation if data_range.end >= field::PREFIX.end => Ok(()),
                     Type::RedirectedHeader if data_range.end >= field::REDIR_MIN_SZ => Ok(()),
+                    Type::RouteInformation => Ok(()),
+                    Type::RecursiveDnsServer | Type::DnsSearchList => Ok(()),
                     Type::Unknown(_) => Ok(()),
                     _ => Err(Error),
                 }
@@ -226,7 +318,7 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
     #[inline]
     pub fn mtu(&self) -> u32 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u32(&data[field::MTU])
+        read_u32(data, field::MTU)
     }
 }
 
@@ -248,14 +340,14 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
     #[inline]
     pub fn valid_lifetime(&self) -> Duration {
         let data = self.buffer.as_ref();
-        Duration::from_secs(NetworkEndian::read_u32(&data[field::VALID_LT]) as u64)
+        Duration::from_secs(read_u32(data, field::VALID_LT) as u64)
     }
 
     /// Return the preferred lifetime of the prefix.
     #[inline]
     pub fn preferred_lifetime(&self) -> Duration {
         let data = self.buffer.as_ref();
-        Duration::from_secs(NetworkEndian::read_u32(&data[field::PREF_LT]) as u64)
+        Duration::from_secs(read_u32(data, field::PREF_LT) as u64)
     }
 
     /// Return the prefix.
@@ -266,6 +358,50 @@ impl<T: AsRef<[u8]>> NdiscOption<T> {
     }
 }
 
+/// Getter methods only relevant for the Route Information option.
+impl<T: AsRef<[u8]>> NdiscOption<T> {
+    /// Return the route preference, or `None` if it has the reserved value.
+    #[inline]
+    pub fn route_preference(&self) -> Option<RoutePreference> {
+        RoutePreference::from_bits(self.buffer.as_ref()[field::FLAGS])
+    }
+
+    /// Return the lifetime of the route.
+    #[inline]
+    pub fn route_lifetime(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        Duration::from_secs(read_u32(data, field::ROUTE_LT) as u64)
+    }
+
+    /// Return the prefix of the route, with the bits after the prefix length cleared.
+    ///
+    /// # Panics
+    /// This function may panic if the option is too short for the prefix length.
+    #[inline]
+    pub fn route_prefix(&self) -> Ipv6Address {
+        let prefix_len = self.prefix_len().min(128) as usize;
+        let data = &self.buffer.as_ref()[field::ROUTE_PREFIX..];
+        let mut bytes = [0; 16];
+        let full_len = prefix_len / 8;
+        bytes[..full_len].copy_from_slice(&data[..full_len]);
+        let rest_len = prefix_len - full_len * 8;
+        if rest_len > 0 {
+            bytes[full_len] = data[full_len] & (0xff << (8 - rest_len));
+        }
+        Ipv6Address(bytes)
+    }
+}
+
+/// Getter methods only relevant for the Recursive DNS Server and DNS Search List options.
+impl<T: AsRef<[u8]>> NdiscOption<T> {
+    /// Return the lifetime of the servers or domain names.
+    #[inline]
+    pub fn dns_lifetime(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        Duration::from_secs(read_u32(data, field::DNS_LT) as u64)
+    }
+}
+
 impl<'a, T: AsRef<[u8]> + ?Sized> NdiscOption<&'a T> {
     /// Return the option data.
     #[inline]
@@ -309,7 +445,7 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
     #[inline]
     pub fn set_mtu(&mut self, value: u32) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::MTU], value);
+        write_u32(data, field::MTU, value);
     }
 }
 
@@ -331,21 +467,21 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
     #[inline]
     pub fn set_valid_lifetime(&mut self, time: Duration) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::VALID_LT], time.secs() as u32);
+        write_u32(data, field::VALID_LT, time.secs() as u32);
     }
 
     /// Set the preferred lifetime of the prefix.
     #[inline]
     pub fn set_preferred_lifetime(&mut self, time: Duration) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::PREF_LT], time.secs() as u32);
+        write_u32(data, field::PREF_LT, time.secs() as u32);
     }
 
     /// Clear the reserved bits.
     #[inline]
     pub fn clear_prefix_reserved(&mut self) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::PREF_RESERVED], 0);
+        write_u32(data, field::PREF_RESERVED, 0);
     }
 
     /// Set the prefix.
@@ -356,13 +492,55 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
     }
 }
 
+/// Setter methods only relevant for the Route Information option.
+impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
+    /// Set the route preference, and clear the reserved bits.
+    #[inline]
+    pub fn set_route_preference(&mut self, preference: RoutePreference) {
+        self.buffer.as_mut()[field::FLAGS] = preference.bits();
+    }
+
+    /// Set the lifetime of the route.
+    #[inline]
+    pub fn set_route_lifetime(&mut self, time: Duration) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::ROUTE_LT, time.secs() as u32);
+    }
+
+    /// Set the prefix of the route, truncated to the length of the option.
+    #[inline]
+    pub fn set_route_prefix(&mut self, addr: Ipv6Address) {
+        let len = self.data_len() as usize * 8 - field::ROUTE_PREFIX;
+        let data = self.buffer.as_mut();
+        data[field::ROUTE_PREFIX..field::ROUTE_PREFIX + len]
+            .copy_from_slice(&addr.as_bytes()[..len]);
+    }
+}
+
+/// Setter methods only relevant for the Recursive DNS Server and DNS Search List options.
+impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
+    /// Set the lifetime of the servers or domain names.
+    #[inline]
+    pub fn set_dns_lifetime(&mut self, time: Duration) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::DNS_LT, time.secs() as u32);
+    }
+
+    /// Clear the reserved bits.
+    #[inline]
+    pub fn clear_dns_reserved(&mut self) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::DNS_RESERVED, 0);
+    }
+}
+
 /// Setter methods only relevant for the Redirected Header option.
 impl<T: AsRef<[u8]> + AsMut<[u8]>> NdiscOption<T> {
     /// Clear the reserved bits.
     #[inline]
     pub fn clear_redirected_reserved(&mut self) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u32(&mut data[field::IP_RESERVED], 0);
+        write_u32(data, field::IP_RESERVED, 0);
     }
 }
 
@@ -398,6 +576,87 @@ pub struct PrefixInformation {
     pub prefix: Ipv6Address,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RouteInformation {
+    pub prefix_len: u8,
+    pub preference: RoutePreference,
+    pub lifetime: Duration,
+    pub prefix: Ipv6Address,
+}
+
+impl RouteInformation {
+    /// Return the length of the option, in units of 8 octets.
+    fn data_len(&self) -> u8 {
+        match self.prefix_len {
+            0 => 1,
+            1..=64 => 2,
+            _ => 3,
+        }
+    }
+}
+
+/// The content of a Recursive DNS Server option, see [RFC 8106 § 5.1].
+///
+/// [RFC 8106 § 5.1]: https://tools.ietf.org/html/rfc8106#section-5.1
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RecursiveDnsServer<'a> {
+    pub lifetime: Duration,
+    /// The addresses of the servers, 16 octets each.
+    pub addresses: &'a [u8],
+}
+
+impl<'a> RecursiveDnsServer<'a> {
+    /// Return an iterator over the addresses of the servers.
+    pub fn addresses(&self) -> impl Iterator<Item = Ipv6Address> + 'a {
+        self.addresses.chunks_exact(16).map(Ipv6Address::from_bytes)
+    }
+}
+
+/// The content of a DNS Search List option, see [RFC 8106 § 5.2].
+///
+/// [RFC 8106 § 5.2]: https://tools.ietf.org/html/rfc8106#section-5.2
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DnsSearchList<'a> {
+    pub lifetime: Duration,
+    /// The domain names, in the encoding of RFC 1035 § 3.1, followed by zero padding.
+    pub domains: &'a [u8],
+}
+
+impl<'a> DnsSearchList<'a> {
+    /// Return an iterator over the domain names, each a sequence of length-prefixed
+    /// labels without the terminating empty label.
+    ///
+    /// The iteration stops at the padding, or at the first malformed name.
+    pub fn domains(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let mut rest = self.domains;
+        core::iter::from_fn(move || {
+            let mut len = 0;
+            loop {
+                match rest.get(len) {
+                    Some(0) if len == 0 => return None,
+                    Some(0) => {
+                        let name = &rest[..len];
+                        rest = &rest[len + 1..];
+                        return Some(name);
+                    }
+                    Some(&label_len)
+                        if label_len < 64 && len + 1 + (label_len as usize) < rest.len() =>
+                    {
+                        len += 1 + label_len as usize;
+                    }
+                    _ => {
+                        rest = &[];
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct RedirectedHeader<'a> {
@@ -414,6 +673,9 @@ pub enum Repr<'a> {
     PrefixInformation(PrefixInformation),
     RedirectedHeader(RedirectedHeader<'a>),
     Mtu(u32),
+    RouteInformation(RouteInformation),
+    RecursiveDnsServer(RecursiveDnsServer<'a>),
+    DnsSearchList(DnsSearchList<'a>),
     Unknown {
         type_: u8,
         length: u8,
@@ -423,7 +685,7 @@ pub enum Repr<'a> {
 
 impl<'a> Repr<'a> {
     /// Parse an NDISC Option and return a high-level representation.
-    pub fn parse<T>(opt: &'a NdiscOption<&'a T>) -> Result<Repr<'a>>
+    pub fn parse<T>(opt: &NdiscOption<&'a T>) -> Result<Repr<'a>>
     where
         T: AsRef<[u8]> + ?Sized,
     {
@@ -477,6 +739,48 @@ impl<'a> Repr<'a> {
                     Err(Error)
                 }
             }
+            Type::RouteInformation => {
+                // The option must be long enough for the prefix, and a reserved
+                // preference invalidates it, per RFC 4191 § 2.3.
+                let min_len = match opt.prefix_len() {
+                    0 => 1,
+                    1..=64 => 2,
+                    65..=128 => 3,
+                    _ => return Err(Error),
+                };
+                match opt.route_preference() {
+                    Some(preference) if (min_len..=3).contains(&opt.data_len()) => {
+                        Ok(Repr::RouteInformation(RouteInformation {
+                            prefix_len: opt.prefix_len(),
+                            preference,
+                            lifetime: opt.route_lifetime(),
+                            prefix: opt.route_prefix(),
+                        }))
+                    }
+                    _ => Err(Error),
+                }
+            }
+            Type::RecursiveDnsServer => {
+                // The option holds at least one address.
+                if opt.data_len() >= 3 && opt.data_len() % 2 == 1 {
+                    Ok(Repr::RecursiveDnsServer(RecursiveDnsServer {
+                        lifetime: opt.dns_lifetime(),
+                        addresses: &opt.data()[field::DNS_DATA - 2..],
+                    }))
+                } else {
+                    Err(Error)
+                }
+            }
+            Type::DnsSearchList => {
+                if opt.data_len() >= 2 {
+                    Ok(Repr::DnsSearchList(DnsSearchList {
+                        lifetime: opt.dns_lifetime(),
+                        domains: &opt.data()[field::DNS_DATA - 2..],
+                    }))
+                } else {
+                    Err(Error)
+                }
+            }
             Type::Unknown(id) => Ok(Repr::Unknown {
                 type_: id,
                 length: opt.data_len(),
@@ -498,6 +802,14 @@ impl<'a> Repr<'a> {
                 field::IP_DATA + header.buffer_len() + data.len()
             }
             &Repr::Mtu(_) => field::MTU.end,
+            &Repr::RouteInformation(info) => info.data_len() as usize * 8,
+            &Repr::RecursiveDnsServer(RecursiveDnsServer { addresses, .. }) => {
+                field::DNS_DATA + addresses.len()
+            }
+            &Repr::DnsSearchList(DnsSearchList { domains, .. }) => {
+                // Round up to next multiple of 8
+                (field::DNS_DATA + domains.len() + 7) & !7
+            }
             &Repr::Unknown { length, .. } => field::DATA(length).end,
         }
     }
@@ -552,6 +864,33 @@ impl<'a> Repr<'a> {
                 opt.set_data_len(1);
                 opt.set_mtu(mtu);
             }
+            Repr::RouteInformation(info) => {
+                opt.set_option_type(Type::RouteInformation);
+                opt.set_data_len(info.data_len());
+                opt.set_prefix_len(info.prefix_len);
+                opt.set_route_preference(info.preference);
+                opt.set_route_lifetime(info.lifetime);
+                opt.set_route_prefix(info.prefix);
+            }
+            Repr::RecursiveDnsServer(RecursiveDnsServer {
+                lifetime,
+                addresses,
+            }) => {
+                opt.set_option_type(Type::RecursiveDnsServer);
+                opt.set_data_len((self.buffer_len() / 8) as u8);
+                opt.clear_dns_reserved();
+                opt.set_dns_lifetime(lifetime);
+                opt.data_mut()[field::DNS_DATA - 2..].copy_from_slice(addresses);
+            }
+            Repr::DnsSearchList(DnsSearchList { lifetime, domains }) => {
+                opt.set_option_type(Type::DnsSearchList);
+                opt.set_data_len((self.buffer_len() / 8) as u8);
+                opt.clear_dns_reserved();
+                opt.set_dns_lifetime(lifetime);
+                let data = &mut opt.data_mut()[field::DNS_DATA - 2..];
+                data[..domains.len()].copy_from_slice(domains);
+                data[domains.len()..].fill(0);
+            }
             Repr::Unknown {
                 type_: id,
                 length,
@@ -586,6 +925,24 @@ impl<'a> fmt::Display for Repr<'a> {
             Repr::Mtu(mtu) => {
                 write!(f, "MTU mtu={}", mtu)
             }
+            Repr::RouteInformation(RouteInformation {
+                prefix,
+                prefix_len,
+                preference,
+                ..
+            }) => {
+                write!(
+                    f,
+                    "RouteInformation prefix={}/{} preference={}",
+                    prefix, prefix_len, preference
+                )
+            }
+            Repr::RecursiveDnsServer(RecursiveDnsServer { lifetime, .. }) => {
+                write!(f, "RecursiveDnsServer lifetime={}", lifetime)
+            }
+            Repr::DnsSearchList(DnsSearchList { lifetime, .. }) => {
+                write!(f, "DnsSearchList lifetime={}", lifetime)
+            }
             Repr::Unknown {
                 type_: id, length, ..
             } => {
@@ -618,7 +975,10 @@ impl<T: AsRef<[u8]>> PrettyPrint for NdiscOption<T> {
 #[cfg(test)]
 mod test {
     use super::Error;
-    use super::{NdiscOption, PrefixInfoFlags, PrefixInformation, Repr, Type};
+    use super::{
+        DnsSearchList, NdiscOption, PrefixInfoFlags, PrefixInformation, Repr, RouteInformation,
+        RoutePreference, Type,
+    };
     use crate::time::Duration;
     use crate::wire::{EthernetAddress, Ipv6Address};
 
@@ -721,4 +1081,122 @@ mod test {
             Ok(Repr::Mtu(1500))
         );
     }
+
+    #[test]
+    fn test_zero_length() {
+        let bytes = [0x2a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        assert_eq!(NdiscOption::new_checked(&bytes), Err(Error));
+    }
+
+    #[test]
+    fn test_repr_parse_route_info() {
+        let bytes = [
+            0x18, 0x02, 0x30, 0x08, 0x00, 0x00, 0x07, 0x08, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01,
+            0xff, 0xff,
+        ];
+        let repr = Repr::RouteInformation(RouteInformation {
+            prefix_len: 48,
+            preference: RoutePreference::High,
+            lifetime: Duration::from_secs(1800),
+            prefix: Ipv6Address::new(0x2001, 0xdb8, 1, 0, 0, 0, 0, 0),
+        });
+        assert_eq!(Repr::parse(&NdiscOption::new_unchecked(&bytes)), Ok(repr));
+
+        // The reserved preference invalidates the option.
+        let mut reserved = bytes;
+        reserved[3] = 0x10;
+        assert_eq!(
+            Repr::parse(&NdiscOption::new_unchecked(&reserved)),
+            Err(Error)
+        );
+
+        // The option must be long enough for the prefix.
+        let mut short = bytes;
+        short[1] = 0x01;
+        assert_eq!(Repr::parse(&NdiscOption::new_unchecked(&short)), Err(Error));
+    }
+
+    #[test]
+    fn test_repr_emit_route_info() {
+        let repr = Repr::RouteInformation(RouteInformation {
+            prefix_len: 0,
+            preference: RoutePreference::Low,
+            lifetime: Duration::from_secs(600),
+            prefix: Ipv6Address::UNSPECIFIED,
+        });
+        assert_eq!(repr.buffer_len(), 8);
+        let mut bytes = [0x2a; 8];
+        let mut opt = NdiscOption::new_unchecked(&mut bytes);
+        repr.emit(&mut opt);
+        assert_eq!(
+            &opt.into_inner()[..],
+            &[0x18, 0x01, 0x00, 0x18, 0x00, 0x00, 0x02, 0x58]
+        );
+    }
+
+    #[test]
+    fn test_repr_recursive_dns_server() {
+        let bytes = [
+            0x19, 0x03, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x10, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x53,
+        ];
+        let opt = NdiscOption::new_unchecked(&bytes);
+        let repr = Repr::parse(&opt).unwrap();
+        let rdnss = match repr {
+            Repr::RecursiveDnsServer(rdnss) => rdnss,
+            repr => panic!("unexpected {:?}", repr),
+        };
+        assert_eq!(rdnss.lifetime, Duration::from_secs(3600));
+        assert_eq!(
+            rdnss.addresses().collect::<std::vec::Vec<_>>(),
+            [Ipv6Address::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x53)]
+        );
+
+        let mut emitted = [0x2a; 24];
+        assert_eq!(repr.buffer_len(), 24);
+        repr.emit(&mut NdiscOption::new_unchecked(&mut emitted));
+        assert_eq!(emitted, bytes);
+
+        // The option holds at least one address.
+        let bytes = [0x19, 0x01, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x10];
+        assert_eq!(Repr::parse(&NdiscOption::new_unchecked(&bytes)), Err(Error));
+    }
+
+    #[test]
+    fn test_repr_dns_search_list() {
+        let domains = b"\x07example\x03com\x00\x03lan\x00";
+        let repr = Repr::DnsSearchList(DnsSearchList {
+            lifetime: Duration::from_secs(3600),
+            domains,
+        });
+        assert_eq!(repr.buffer_len(), 32);
+        let mut bytes = [0x2a; 32];
+        repr.emit(&mut NdiscOption::new_unchecked(&mut bytes));
+        assert_eq!(
+            &bytes[..8],
+            &[0x1f, 0x04, 0x00, 0x00, 0x00, 0x00, 0x0e, 0x10]
+        );
+        assert_eq!(&bytes[8..26], &domains[..]);
+        assert_eq!(&bytes[26..], &[0; 6]);
+
+        let opt = NdiscOption::new_unchecked(&bytes);
+        let dnssl = match Repr::parse(&opt).unwrap() {
+            Repr::DnsSearchList(dnssl) => dnssl,
+            repr => panic!("unexpected {:?}", repr),
+        };
+        assert_eq!(
+            dnssl.domains().collect::<std::vec::Vec<_>>(),
+            [&b"\x07example\x03com"[..], &b"\x03lan"[..]]
+        );
+
+        // Iteration stops at a malformed name.
+        let dnssl = DnsSearchList {
+            lifetime: Duration::from_secs(3600),
+            domains: b"\x03lan\x00\x09short\x00",
+        };
+        assert_eq!(
+            dnssl.domains().collect::<std::vec::Vec<_>>(),
+            [&b"\x03lan"[..]]
+        );
+    }
 }
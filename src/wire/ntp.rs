@@ -0,0 +1,631 @@
+use crate::wire::field::{read_u32, write_u32};
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::time::Duration;
+
+/// The UDP port NTP servers listen on.
+pub const SERVER_PORT: u16 = 123;
+
+enum_with_unknown! {
+    /// Leap second warning.
+    pub enum LeapIndicator(u8) {
+        /// No leap second pending
+        NoWarning      = 0,
+        /// The last minute of the day has 61 seconds
+        LastMinute61   = 1,
+        /// The last minute of the day has 59 seconds
+        LastMinute59   = 2,
+        /// The clock of the sender is not synchronized
+        Unsynchronized = 3
+    }
+}
+
+enum_with_unknown! {
+    /// Association mode.
+    pub enum ProtocolMode(u8) {
+        Reserved         = 0,
+        SymmetricActive  = 1,
+        SymmetricPassive = 2,
+        Client           = 3,
+        Server           = 4,
+        Broadcast        = 5,
+        Control          = 6,
+        Private          = 7
+    }
+}
+
+impl fmt::Display for ProtocolMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtocolMode::Reserved => write!(f, "reserved"),
+            ProtocolMode::SymmetricActive => write!(f, "symmetric-active"),
+            ProtocolMode::SymmetricPassive => write!(f, "symmetric-passive"),
+            ProtocolMode::Client => write!(f, "client"),
+            ProtocolMode::Server => write!(f, "server"),
+            ProtocolMode::Broadcast => write!(f, "broadcast"),
+            ProtocolMode::Control => write!(f, "control"),
+            ProtocolMode::Private => write!(f, "private"),
+            ProtocolMode::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+/// An NTP timestamp: seconds and fractions of a second since the start of the current era.
+///
+/// Era 0 starts on 1900-01-01 00:00 UTC, and era 1 on 2036-02-07 06:28:16 UTC.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    pub seconds: u32,
+    pub fraction: u32,
+}
+
+impl Timestamp {
+    /// The zero timestamp, used to mean "unknown".
+    pub const ZERO: Timestamp = Timestamp {
+        seconds: 0,
+        fraction: 0,
+    };
+
+    /// Seconds between the start of NTP era 0 and the Unix epoch.
+    const UNIX_OFFSET: i64 = 2_208_988_800;
+
+    /// Create a timestamp from the number of microseconds since the Unix epoch.
+    pub fn from_unix_micros(micros: i64) -> Timestamp {
+        let seconds = micros.div_euclid(1_000_000) + Self::UNIX_OFFSET;
+        let micros = micros.rem_euclid(1_000_000) as u64;
+        Timestamp {
+            seconds: seconds as u32,
+            fraction: ((micros << 32) / 1_000_000) as u32,
+        }
+    }
+
+    /// Return the number of microseconds since the Unix epoch.
+    ///
+    /// Following RFC 4330 § 3, timestamps with the most significant bit of the seconds
+    /// clear are assumed to belong to era 1, which makes this function correct
+    /// for times between 1968 and 2104.
+    pub fn unix_micros(&self) -> i64 {
+        let mut seconds = self.seconds as i64;
+        if self.seconds & 0x8000_0000 == 0 {
+            seconds += 1 << 32;
+        }
+        let micros = ((self.fraction as u64 * 1_000_000 + (1 << 31)) >> 32) as i64;
+        (seconds - Self::UNIX_OFFSET) * 1_000_000 + micros
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let micros = ((self.fraction as u64 * 1_000_000) >> 32) as u32;
+        write!(f, "{}.{:06}", self.seconds, micros)
+    }
+}
+
+/// A read/write wrapper around an NTP packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of an NTP packet
+//
+// +--+-----+-----+---------------+---------------+---------------+
+// |LI| VN  |Mode |    Stratum    |     Poll      |   Precision   |
+// +--+-----+-----+---------------+---------------+---------------+
+// |                          Root Delay                          |
+// |                       Root Dispersion                        |
+// |                        Reference ID                          |
+// |                  Reference Timestamp (64)                    |
+// |                   Origin Timestamp (64)                      |
+// |                   Receive Timestamp (64)                     |
+// |                   Transmit Timestamp (64)                    |
+// +--------------------------------------------------------------+
+//
+// See RFC 5905 § 7.3 for details. Extension fields and the MAC are ignored.
+mod field {
+    use crate::wire::field::*;
+
+    pub const LI_VN_MODE: usize = 0;
+    pub const STRATUM: usize = 1;
+    pub const POLL: usize = 2;
+    pub const PRECISION: usize = 3;
+    pub const ROOT_DELAY: Field = 4..8;
+    pub const ROOT_DISPERSION: Field = 8..12;
+    pub const REF_ID: Field = 12..16;
+    pub const REF_TIMESTAMP: Field = 16..24;
+    pub const ORIG_TIMESTAMP: Field = 24..32;
+    pub const RECV_TIMESTAMP: Field = 32..40;
+    pub const XMIT_TIMESTAMP: Field = 40..48;
+}
+
+/// The length of an NTP packet without extension fields.
+pub const PACKET_LEN: usize = field::XMIT_TIMESTAMP.end;
+
+fn read_timestamp(data: &[u8]) -> Timestamp {
+    Timestamp {
+        seconds: NetworkEndian::read_u32(&data[..4]),
+        fraction: NetworkEndian::read_u32(&data[4..]),
+    }
+}
+
+fn write_timestamp(data: &mut [u8], value: Timestamp) {
+    NetworkEndian::write_u32(&mut data[..4], value.seconds);
+    NetworkEndian::write_u32(&mut data[4..], value.fraction);
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with NTP packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < PACKET_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the leap indicator field.
+    #[inline]
+    pub fn leap_indicator(&self) -> LeapIndicator {
+        let data = self.buffer.as_ref();
+        LeapIndicator::from(data[field::LI_VN_MODE] >> 6)
+    }
+
+    /// Return the version number field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        (data[field::LI_VN_MODE] >> 3) & 0x7
+    }
+
+    /// Return the mode field.
+    #[inline]
+    pub fn mode(&self) -> ProtocolMode {
+        let data = self.buffer.as_ref();
+        ProtocolMode::from(data[field::LI_VN_MODE] & 0x7)
+    }
+
+    /// Return the stratum field.
+    #[inline]
+    pub fn stratum(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::STRATUM]
+    }
+
+    /// Return the poll interval field, as the base-2 logarithm of seconds.
+    #[inline]
+    pub fn poll_interval(&self) -> i8 {
+        let data = self.buffer.as_ref();
+        data[field::POLL] as i8
+    }
+
+    /// Return the precision field, as the base-2 logarithm of seconds.
+    #[inline]
+    pub fn precision(&self) -> i8 {
+        let data = self.buffer.as_ref();
+        data[field::PRECISION] as i8
+    }
+
+    /// Return the root delay field.
+    #[inline]
+    pub fn root_delay(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        short_to_duration(read_u32(data, field::ROOT_DELAY))
+    }
+
+    /// Return the root dispersion field.
+    #[inline]
+    pub fn root_dispersion(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        short_to_duration(read_u32(data, field::ROOT_DISPERSION))
+    }
+
+    /// Return the reference ID field.
+    ///
+    /// In kiss-o'-death packets, this field holds the ASCII kiss code.
+    #[inline]
+    pub fn ref_id(&self) -> [u8; 4] {
+        let data = self.buffer.as_ref();
+        let mut ref_id = [0; 4];
+        ref_id.copy_from_slice(&data[field::REF_ID]);
+        ref_id
+    }
+
+    /// Return the reference timestamp field.
+    #[inline]
+    pub fn ref_timestamp(&self) -> Timestamp {
+        let data = self.buffer.as_ref();
+        read_timestamp(&data[field::REF_TIMESTAMP])
+    }
+
+    /// Return the origin timestamp field.
+    #[inline]
+    pub fn orig_timestamp(&self) -> Timestamp {
+        let data = self.buffer.as_ref();
+        read_timestamp(&data[field::ORIG_TIMESTAMP])
+    }
+
+    /// Return the receive timestamp field.
+    #[inline]
+    pub fn recv_timestamp(&self) -> Timestamp {
+        let data = self.buffer.as_ref();
+        read_timestamp(&data[field::RECV_TIMESTAMP])
+    }
+
+    /// Return the transmit timestamp field.
+    #[inline]
+    pub fn xmit_timestamp(&self) -> Timestamp {
+        let data = self.buffer.as_ref();
+        read_timestamp(&data[field::XMIT_TIMESTAMP])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the leap indicator field.
+    #[inline]
+    pub fn set_leap_indicator(&mut self, value: LeapIndicator) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE] & !0xc0;
+        data[field::LI_VN_MODE] = raw | (u8::from(value) << 6);
+    }
+
+    /// Set the version number field.
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE] & !0x38;
+        data[field::LI_VN_MODE] = raw | ((value & 0x7) << 3);
+    }
+
+    /// Set the mode field.
+    #[inline]
+    pub fn set_mode(&mut self, value: ProtocolMode) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::LI_VN_MODE] & !0x07;
+        data[field::LI_VN_MODE] = raw | (u8::from(value) & 0x7);
+    }
+
+    /// Set the stratum field.
+    #[inline]
+    pub fn set_stratum(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::STRATUM] = value
+    }
+
+    /// Set the poll interval field.
+    #[inline]
+    pub fn set_poll_interval(&mut self, value: i8) {
+        let data = self.buffer.as_mut();
+        data[field::POLL] = value as u8
+    }
+
+    /// Set the precision field.
+    #[inline]
+    pub fn set_precision(&mut self, value: i8) {
+        let data = self.buffer.as_mut();
+        data[field::PRECISION] = value as u8
+    }
+
+    /// Set the root delay field.
+    #[inline]
+    pub fn set_root_delay(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::ROOT_DELAY, duration_to_short(value))
+    }
+
+    /// Set the root dispersion field.
+    #[inline]
+    pub fn set_root_dispersion(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::ROOT_DISPERSION, duration_to_short(value))
+    }
+
+    /// Set the reference ID field.
+    #[inline]
+    pub fn set_ref_id(&mut self, value: [u8; 4]) {
+        let data = self.buffer.as_mut();
+        data[field::REF_ID].copy_from_slice(&value)
+    }
+
+    /// Set the reference timestamp field.
+    #[inline]
+    pub fn set_ref_timestamp(&mut self, value: Timestamp) {
+        let data = self.buffer.as_mut();
+        write_timestamp(&mut data[field::REF_TIMESTAMP], value)
+    }
+
+    /// Set the origin timestamp field.
+    #[inline]
+    pub fn set_orig_timestamp(&mut self, value: Timestamp) {
+        let data = self.buffer.as_mut();
+        write_timestamp(&mut data[field::ORIG_TIMESTAMP], value)
+    }
+
+    /// Set the receive timestamp field.
+    #[inline]
+    pub fn set_recv_timestamp(&mut self, value: Timestamp) {
+        let data = self.buffer.as_mut();
+        write_timestamp(&mut data[field::RECV_TIMESTAMP], value)
+    }
+
+    /// Set the transmit timestamp field.
+    #[inline]
+    pub fn set_xmit_timestamp(&mut self, value: Timestamp) {
+        let data = self.buffer.as_mut();
+        write_timestamp(&mut data[field::XMIT_TIMESTAMP], value)
+    }
+}
+
+/// Convert an NTP short format value (16.16 fixed point seconds) to a duration.
+fn short_to_duration(value: u32) -> Duration {
+    Duration::from_micros((value as u64 * 1_000_000) >> 16)
+}
+
+/// Convert a duration to an NTP short format value, saturating on overflow.
+fn duration_to_short(value: Duration) -> u32 {
+    let short = (value.total_micros() << 16) / 1_000_000;
+    short.min(u32::MAX as u64) as u32
+}
+
+/// A high-level representation of an NTP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr {
+    pub leap_indicator: LeapIndicator,
+    pub version: u8,
+    pub mode: ProtocolMode,
+    pub stratum: u8,
+    pub poll_interval: i8,
+    pub precision: i8,
+    pub root_delay: Duration,
+    pub root_dispersion: Duration,
+    pub ref_id: [u8; 4],
+    pub ref_timestamp: Timestamp,
+    pub orig_timestamp: Timestamp,
+    pub recv_timestamp: Timestamp,
+    pub xmit_timestamp: Timestamp,
+}
+
+impl Repr {
+    /// Return the representation of an SNTP client request with the given transmit timestamp.
+    pub fn client_request(xmit_timestamp: Timestamp) -> Repr {
+        Repr {
+            leap_indicator: LeapIndicator::NoWarning,
+            version: 4,
+            mode: ProtocolMode::Client,
+            stratum: 0,
+            poll_interval: 0,
+            precision: 0,
+            root_delay: Duration::ZERO,
+            root_dispersion: Duration::ZERO,
+            ref_id: [0; 4],
+            ref_timestamp: Timestamp::ZERO,
+            orig_timestamp: Timestamp::ZERO,
+            recv_timestamp: Timestamp::ZERO,
+            xmit_timestamp,
+        }
+    }
+
+    /// Parse an NTP packet and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if !(1..=4).contains(&packet.version()) {
+            return Err(Error);
+        }
+        Ok(Repr {
+            leap_indicator: packet.leap_indicator(),
+            version: packet.version(),
+            mode: packet.mode(),
+            stratum: packet.stratum(),
+            poll_interval: packet.poll_interval(),
+            precision: packet.precision(),
+            root_delay: packet.root_delay(),
+            root_dispersion: packet.root_dispersion(),
+            ref_id: packet.ref_id(),
+            ref_timestamp: packet.ref_timestamp(),
+            orig_timestamp: packet.orig_timestamp(),
+            recv_timestamp: packet.recv_timestamp(),
+            xmit_timestamp: packet.xmit_timestamp(),
+        })
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub const fn buffer_len(&self) -> usize {
+        PACKET_LEN
+    }
+
+    /// Emit a high-level representation into an NTP packet.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_leap_indicator(self.leap_indicator);
+        packet.set_version(self.version);
+        packet.set_mode(self.mode);
+        packet.set_stratum(self.stratum);
+        packet.set_poll_interval(self.poll_interval);
+        packet.set_precision(self.precision);
+        packet.set_root_delay(self.root_delay);
+        packet.set_root_dispersion(self.root_dispersion);
+        packet.set_ref_id(self.ref_id);
+        packet.set_ref_timestamp(self.ref_timestamp);
+        packet.set_orig_timestamp(self.orig_timestamp);
+        packet.set_recv_timestamp(self.recv_timestamp);
+        packet.set_xmit_timestamp(self.xmit_timestamp);
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "NTPv{} {} stratum={} xmit={}",
+            self.version, self.mode, self.stratum, self.xmit_timestamp
+        )
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "NTP ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static PACKET_BYTES: [u8; 48] = [
+        0x24, 0x02, 0x03, 0xe8, 0x00, 0x00, 0x80, 0x00, 0x00, 0x01, 0x00, 0x00, 0xc0, 0x00, 0x02,
+        0x01, 0xe6, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc,
+        0xde, 0xf0, 0xe6, 0x00, 0x00, 0x01, 0x80, 0x00, 0x00, 0x00, 0xe6, 0x00, 0x00, 0x01, 0xc0,
+        0x00, 0x00, 0x00,
+    ];
+
+    fn packet_repr() -> Repr {
+        Repr {
+            leap_indicator: LeapIndicator::NoWarning,
+            version: 4,
+            mode: ProtocolMode::Server,
+            stratum: 2,
+            poll_interval: 3,
+            precision: -24,
+            root_delay: Duration::from_millis(500),
+            root_dispersion: Duration::from_secs(1),
+            ref_id: [192, 0, 2, 1],
+            ref_timestamp: Timestamp {
+                seconds: 0xe6000000,
+                fraction: 0,
+            },
+            orig_timestamp: Timestamp {
+                seconds: 0x12345678,
+                fraction: 0x9abcdef0,
+            },
+            recv_timestamp: Timestamp {
+                seconds: 0xe6000001,
+                fraction: 0x80000000,
+            },
+            xmit_timestamp: Timestamp {
+                seconds: 0xe6000001,
+                fraction: 0xc0000000,
+            },
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert_eq!(packet.leap_indicator(), LeapIndicator::NoWarning);
+        assert_eq!(packet.version(), 4);
+        assert_eq!(packet.mode(), ProtocolMode::Server);
+        assert_eq!(packet.stratum(), 2);
+        assert_eq!(packet.poll_interval(), 3);
+        assert_eq!(packet.precision(), -24);
+        assert_eq!(packet.root_delay(), Duration::from_millis(500));
+        assert_eq!(packet.root_dispersion(), Duration::from_secs(1));
+        assert_eq!(packet.ref_id(), [192, 0, 2, 1]);
+        assert_eq!(packet.orig_timestamp().seconds, 0x12345678);
+        assert_eq!(packet.xmit_timestamp().fraction, 0xc0000000);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..47]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(packet_repr()));
+    }
+
+    #[test]
+    fn test_parse_bad_version() {
+        let mut bytes = PACKET_BYTES;
+        bytes[0] = 0x3c;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = packet_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &PACKET_BYTES[..]);
+    }
+
+    #[test]
+    fn test_client_request() {
+        let repr = Repr::client_request(Timestamp {
+            seconds: 1,
+            fraction: 2,
+        });
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(bytes[0], 0x23);
+        assert_eq!(&bytes[1..40], &[0; 39][..]);
+        assert_eq!(&bytes[40..], &[0, 0, 0, 1, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_timestamp_unix() {
+        // 2022-01-01 00:00:00.5 UTC
+        let micros = 1_640_995_200_500_000;
+        let timestamp = Timestamp::from_unix_micros(micros);
+        assert_eq!(timestamp.seconds, 3_849_984_000);
+        assert_eq!(timestamp.fraction, 0x8000_0000);
+        assert_eq!(timestamp.unix_micros(), micros);
+
+        // 2036-02-07 06:28:16 UTC, the start of era 1
+        let micros = 2_085_978_496_000_000;
+        let timestamp = Timestamp::from_unix_micros(micros);
+        assert_eq!(timestamp.seconds, 0);
+        assert_eq!(timestamp.unix_micros(), micros);
+    }
+}
@@ -0,0 +1,404 @@
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
+use core::fmt;
+
+use super::{Error, Result};
+
+enum_with_unknown! {
+    /// PPP protocol field, identifying the datagram encapsulated in a PPP frame.
+    pub enum Protocol(u16) {
+        Ipv4   = 0x0021,
+        Ipv6   = 0x0057,
+        Ipcp   = 0x8021,
+        Ipv6cp = 0x8057,
+        Lcp    = 0xc021,
+        Pap    = 0xc023,
+        Chap   = 0xc223
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Protocol::Ipv4 => write!(f, "IPv4"),
+            Protocol::Ipv6 => write!(f, "IPv6"),
+            Protocol::Ipcp => write!(f, "IPCP"),
+            Protocol::Ipv6cp => write!(f, "IPV6CP"),
+            Protocol::Lcp => write!(f, "LCP"),
+            Protocol::Pap => write!(f, "PAP"),
+            Protocol::Chap => write!(f, "CHAP"),
+            Protocol::Unknown(id) => write!(f, "0x{:04x}", id),
+        }
+    }
+}
+
+enum_with_unknown! {
+    /// Link Control Protocol packet code.
+    pub enum LcpCode(u8) {
+        ConfigureRequest = 1,
+        ConfigureAck     = 2,
+        ConfigureNak     = 3,
+        ConfigureReject  = 4,
+        TerminateRequest = 5,
+        TerminateAck     = 6,
+        CodeReject       = 7,
+        ProtocolReject   = 8,
+        EchoRequest      = 9,
+        EchoReply        = 10,
+        DiscardRequest   = 11
+    }
+}
+
+/// A read/write wrapper around a Link Control Protocol packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LcpPacket<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of an LCP packet
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     Code      |  Identifier   |            Length             |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          Magic-Number                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |    Data ...
+// +-+-+-+-+-
+//
+// The Magic-Number field is only present in Echo-Request, Echo-Reply and
+// Discard-Request packets. See RFC 1661 § 5 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const CODE: usize = 0;
+    pub const IDENTIFIER: usize = 1;
+    pub const LENGTH: Field = 2..4;
+    pub const DATA: Rest = 4..;
+
+    pub const MAGIC_NUMBER: Field = 4..8;
+    pub const ECHO_DATA: Rest = 8..;
+}
+
+/// The length of an LCP header.
+pub const LCP_HEADER_LEN: usize = field::DATA.start;
+
+impl<T: AsRef<[u8]>> LcpPacket<T> {
+    /// Imbue a raw octet buffer with LCP packet structure.
+    pub fn new_unchecked(buffer: T) -> LcpPacket<T> {
+        LcpPacket { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<LcpPacket<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short, or the length field is
+    /// shorter than the header or longer than the buffer.
+    ///
+    /// The [magic_number] accessor is only checked by [LcpRepr::parse].
+    ///
+    /// [magic_number]: #method.magic_number
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < LCP_HEADER_LEN {
+            return Err(Error);
+        }
+        let length = self.length() as usize;
+        if length < LCP_HEADER_LEN || length > len {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the code field.
+    #[inline]
+    pub fn code(&self) -> LcpCode {
+        let data = self.buffer.as_ref();
+        LcpCode::from(data[field::CODE])
+    }
+
+    /// Return the identifier field.
+    #[inline]
+    pub fn identifier(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::IDENTIFIER]
+    }
+
+    /// Return the length field, including the header.
+    #[inline]
+    pub fn length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::LENGTH)
+    }
+
+    /// Return the magic number field of an Echo-Request, Echo-Reply or Discard-Request.
+    ///
+    /// # Panics
+    /// This function panics if the length field is shorter than 8 octets.
+    #[inline]
+    pub fn magic_number(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::MAGIC_NUMBER)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> LcpPacket<&'a T> {
+    /// Return a pointer to the data following the header, up to the length field.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::DATA.start..self.length() as usize]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> LcpPacket<T> {
+    /// Set the code field.
+    #[inline]
+    pub fn set_code(&mut self, value: LcpCode) {
+        let data = self.buffer.as_mut();
+        data[field::CODE] = value.into()
+    }
+
+    /// Set the identifier field.
+    #[inline]
+    pub fn set_identifier(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::IDENTIFIER] = value
+    }
+
+    /// Set the length field.
+    #[inline]
+    pub fn set_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::LENGTH, value)
+    }
+
+    /// Set the magic number field.
+    #[inline]
+    pub fn set_magic_number(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::MAGIC_NUMBER, value)
+    }
+
+    /// Return a mutable pointer to the data following the header.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::DATA]
+    }
+}
+
+/// A high-level representation of the Link Control Protocol packets used to
+/// test the link, i.e. Echo-Request and Echo-Reply.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LcpRepr<'a> {
+    EchoRequest {
+        identifier: u8,
+        magic_number: u32,
+        data: &'a [u8],
+    },
+    EchoReply {
+        identifier: u8,
+        magic_number: u32,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> LcpRepr<'a> {
+    /// Parse an Echo-Request or Echo-Reply packet and return a high-level representation.
+    ///
+    /// Returns `Err(Error)` for the packets of any other code.
+    pub fn parse<T>(packet: &LcpPacket<&'a T>) -> Result<LcpRepr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if (packet.length() as usize) < field::ECHO_DATA.start {
+            return Err(Error);
+        }
+
+        let identifier = packet.identifier();
+        let magic_number = packet.magic_number();
+        let data = &packet.data()[field::ECHO_DATA.start - field::DATA.start..];
+        match packet.code() {
+            LcpCode::EchoRequest => Ok(LcpRepr::EchoRequest {
+                identifier,
+                magic_number,
+                data,
+            }),
+            LcpCode::EchoReply => Ok(LcpRepr::EchoReply {
+                identifier,
+                magic_number,
+                data,
+            }),
+            _ => Err(Error),
+        }
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        match *self {
+            LcpRepr::EchoRequest { data, .. } | LcpRepr::EchoReply { data, .. } => {
+                field::ECHO_DATA.start + data.len()
+            }
+        }
+    }
+
+    /// Emit a high-level representation into a Link Control Protocol packet.
+    pub fn emit<T>(&self, packet: &mut LcpPacket<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        let (code, identifier, magic_number, data) = match *self {
+            LcpRepr::EchoRequest {
+                identifier,
+                magic_number,
+                data,
+            } => (LcpCode::EchoRequest, identifier, magic_number, data),
+            LcpRepr::EchoReply {
+                identifier,
+                magic_number,
+                data,
+            } => (LcpCode::EchoReply, identifier, magic_number, data),
+        };
+        packet.set_code(code);
+        packet.set_identifier(identifier);
+        packet.set_length(self.buffer_len() as u16);
+        packet.set_magic_number(magic_number);
+        packet.data_mut()[field::ECHO_DATA.start - field::DATA.start..][..data.len()]
+            .copy_from_slice(data);
+    }
+}
+
+impl<'a> fmt::Display for LcpRepr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LcpRepr::EchoRequest {
+                identifier,
+                magic_number,
+                data,
+            } => write!(
+                f,
+                "LCP echo request id={} magic=0x{:08x} len={}",
+                identifier,
+                magic_number,
+                data.len()
+            ),
+            LcpRepr::EchoReply {
+                identifier,
+                magic_number,
+                data,
+            } => write!(
+                f,
+                "LCP echo reply id={} magic=0x{:08x} len={}",
+                identifier,
+                magic_number,
+                data.len()
+            ),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for LcpPacket<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match LcpRepr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(_) => write!(
+                f,
+                "LCP code={:?} id={} len={}",
+                self.code(),
+                self.identifier(),
+                self.length()
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static ECHO_REQUEST_BYTES: [u8; 12] = [
+        0x09, 0x2a, 0x00, 0x0c, 0x12, 0x34, 0x56, 0x78, 0xaa, 0x00, 0x00, 0xff,
+    ];
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = LcpPacket::new_checked(&ECHO_REQUEST_BYTES[..]).unwrap();
+        assert_eq!(packet.code(), LcpCode::EchoRequest);
+        assert_eq!(packet.identifier(), 0x2a);
+        assert_eq!(packet.length(), 12);
+        assert_eq!(packet.magic_number(), 0x12345678);
+        assert_eq!(
+            packet.data(),
+            &[0x12, 0x34, 0x56, 0x78, 0xaa, 0x00, 0x00, 0xff]
+        );
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(LcpPacket::new_checked(&ECHO_REQUEST_BYTES[..3]), Err(Error));
+        // The length field exceeds the buffer.
+        assert_eq!(
+            LcpPacket::new_checked(&ECHO_REQUEST_BYTES[..11]),
+            Err(Error)
+        );
+
+        // Octets past the length field are padding.
+        let mut bytes = [0u8; 16];
+        bytes[..12].copy_from_slice(&ECHO_REQUEST_BYTES);
+        let packet = LcpPacket::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.data().len(), 8);
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = LcpPacket::new_unchecked(&ECHO_REQUEST_BYTES[..]);
+        assert_eq!(
+            LcpRepr::parse(&packet),
+            Ok(LcpRepr::EchoRequest {
+                identifier: 0x2a,
+                magic_number: 0x12345678,
+                data: &[0xaa, 0x00, 0x00, 0xff],
+            })
+        );
+
+        let mut bytes = ECHO_REQUEST_BYTES;
+        bytes[0] = LcpCode::ConfigureRequest.into();
+        let packet = LcpPacket::new_unchecked(&bytes[..]);
+        assert_eq!(LcpRepr::parse(&packet), Err(Error));
+
+        // Too short to hold a magic number.
+        let bytes = [0x0a, 0x01, 0x00, 0x06, 0x00, 0x00];
+        let packet = LcpPacket::new_unchecked(&bytes[..]);
+        assert_eq!(LcpRepr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = LcpRepr::EchoRequest {
+            identifier: 0x2a,
+            magic_number: 0x12345678,
+            data: &[0xaa, 0x00, 0x00, 0xff],
+        };
+        assert_eq!(repr.buffer_len(), 12);
+        let mut bytes = vec![0xa5; 12];
+        let mut packet = LcpPacket::new_unchecked(&mut bytes);
+        repr.emit(&mut packet);
+        assert_eq!(&bytes[..], &ECHO_REQUEST_BYTES[..]);
+    }
+}
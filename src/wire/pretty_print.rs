@@ -27,6 +27,19 @@ let buffer = vec![
 ];
 print!("{}", PrettyPrinter::<EthernetFrame<&'static [u8]>>::new("", &buffer));
 ```
+
+Each layer is printed on its own line, indented below the layer carrying it.
+A [compact] listing prints all the layers on a single line instead, each one
+prefixed by the layers carrying it, which is easier to search in a log:
+
+```text
+EthernetII src=11-12-13-14-15-16 dst=01-02-03-04-05-06 type=IPv4 > IPv4 src=17.18.19.20 dst=33.34.35.36 proto=ICMP > ICMPv4 echo request id=4660 seq=43981 len=4
+```
+
+The representations of the packets (e.g. `TcpRepr`) print the same single-line
+summary with `{}`, and add the less commonly needed fields with `{:#}`.
+
+[compact]: struct.PrettyPrinter.html#method.compact
 */
 
 use core::fmt;
@@ -38,18 +51,38 @@ use core::marker::PhantomData;
 pub struct PrettyIndent {
     prefix: &'static str,
     level: usize,
+    compact: bool,
 }
 
 impl PrettyIndent {
     /// Create an indentation state. The entire listing will be indented by the width
     /// of `prefix`, and `prefix` will appear at the start of the first line.
     pub fn new(prefix: &'static str) -> PrettyIndent {
-        PrettyIndent { prefix, level: 0 }
+        PrettyIndent {
+            prefix,
+            level: 0,
+            compact: false,
+        }
+    }
+
+    /// Create an indentation state for a single-line listing, where nested packets are
+    /// separated by ` > ` instead of being printed on their own line. `prefix` will appear
+    /// at the start of the line.
+    pub fn new_compact(prefix: &'static str) -> PrettyIndent {
+        PrettyIndent {
+            prefix,
+            level: 0,
+            compact: true,
+        }
     }
 
     /// Increase indentation level.
     pub fn increase(&mut self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f)?;
+        if self.compact {
+            write!(f, " > ")?;
+        } else {
+            writeln!(f)?;
+        }
         self.level += 1;
         Ok(())
     }
@@ -59,6 +92,8 @@ impl fmt::Display for PrettyIndent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.level == 0 {
             write!(f, "{}", self.prefix)
+        } else if self.compact {
+            Ok(())
         } else {
             write!(f, "{0:1$}{0:2$}\\ ", "", self.prefix.len(), self.level - 1)
         }
@@ -83,6 +118,7 @@ pub trait PrettyPrint {
 pub struct PrettyPrinter<'a, T: PrettyPrint> {
     prefix: &'static str,
     buffer: &'a dyn AsRef<[u8]>,
+    compact: bool,
     phantom: PhantomData<T>,
 }
 
@@ -92,9 +128,18 @@ impl<'a, T: PrettyPrint> PrettyPrinter<'a, T> {
         PrettyPrinter {
             prefix: prefix,
             buffer: buffer,
+            compact: false,
             phantom: PhantomData,
         }
     }
+
+    /// Print the listing on a single line, with nested packets separated by ` > `.
+    pub fn compact(self) -> PrettyPrinter<'a, T> {
+        PrettyPrinter {
+            compact: true,
+            ..self
+        }
+    }
 }
 
 impl<'a, T: PrettyPrint + AsRef<[u8]>> PrettyPrinter<'a, T> {
@@ -103,6 +148,7 @@ impl<'a, T: PrettyPrint + AsRef<[u8]>> PrettyPrinter<'a, T> {
         PrettyPrinter {
             prefix: "",
             buffer: printable,
+            compact: false,
             phantom: PhantomData,
         }
     }
@@ -110,6 +156,11 @@ impl<'a, T: PrettyPrint + AsRef<[u8]>> PrettyPrinter<'a, T> {
 
 impl<'a, T: PrettyPrint> fmt::Display for PrettyPrinter<'a, T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        T::pretty_print(&self.buffer, f, &mut PrettyIndent::new(self.prefix))
+        let mut indent = if self.compact {
+            PrettyIndent::new_compact(self.prefix)
+        } else {
+            PrettyIndent::new(self.prefix)
+        };
+        T::pretty_print(&self.buffer, f, &mut indent)
     }
 }
@@ -0,0 +1,977 @@
+use crate::wire::field::{read_u16, write_u16};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+
+/// The UDP port event messages (Sync, Delay_Req, ...) are sent to.
+pub const EVENT_PORT: u16 = 319;
+/// The UDP port general messages (Follow_Up, Delay_Resp, Announce, ...) are sent to.
+pub const GENERAL_PORT: u16 = 320;
+
+enum_with_unknown! {
+    /// PTP message type.
+    pub enum MessageType(u8) {
+        Sync               = 0x0,
+        DelayReq           = 0x1,
+        PdelayReq          = 0x2,
+        PdelayResp         = 0x3,
+        FollowUp           = 0x8,
+        DelayResp          = 0x9,
+        PdelayRespFollowUp = 0xa,
+        Announce           = 0xb,
+        Signaling          = 0xc,
+        Management         = 0xd
+    }
+}
+
+impl MessageType {
+    /// Return whether messages of this type are event messages, which are timestamped
+    /// on transmission and reception and sent to [EVENT_PORT] over UDP.
+    pub fn is_event(&self) -> bool {
+        u8::from(*self) < 0x8
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MessageType::Sync => write!(f, "Sync"),
+            MessageType::DelayReq => write!(f, "Delay_Req"),
+            MessageType::PdelayReq => write!(f, "Pdelay_Req"),
+            MessageType::PdelayResp => write!(f, "Pdelay_Resp"),
+            MessageType::FollowUp => write!(f, "Follow_Up"),
+            MessageType::DelayResp => write!(f, "Delay_Resp"),
+            MessageType::PdelayRespFollowUp => write!(f, "Pdelay_Resp_Follow_Up"),
+            MessageType::Announce => write!(f, "Announce"),
+            MessageType::Signaling => write!(f, "Signaling"),
+            MessageType::Management => write!(f, "Management"),
+            MessageType::Unknown(id) => write!(f, "0x{:x}", id),
+        }
+    }
+}
+
+bitflags! {
+    /// Flags of the common PTP header.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Flags: u16 {
+        const ALTERNATE_MASTER    = 0x0100;
+        const TWO_STEP            = 0x0200;
+        const UNICAST             = 0x0400;
+        const PROFILE_SPECIFIC_1  = 0x2000;
+        const PROFILE_SPECIFIC_2  = 0x4000;
+        const LEAP_61             = 0x0001;
+        const LEAP_59             = 0x0002;
+        const CURRENT_UTC_OFFSET_VALID = 0x0004;
+        const PTP_TIMESCALE       = 0x0008;
+        const TIME_TRACEABLE      = 0x0010;
+        const FREQUENCY_TRACEABLE = 0x0020;
+    }
+}
+
+/// A PTP timestamp: a 48-bit number of seconds and a number of nanoseconds.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// Seconds since the PTP epoch; only the lower 48 bits are used.
+    pub seconds: u64,
+    pub nanoseconds: u32,
+}
+
+impl Timestamp {
+    const LEN: usize = 10;
+
+    fn read(data: &[u8]) -> Timestamp {
+        Timestamp {
+            seconds: NetworkEndian::read_u48(&data[0..6]),
+            nanoseconds: NetworkEndian::read_u32(&data[6..10]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        NetworkEndian::write_u48(&mut data[0..6], self.seconds);
+        NetworkEndian::write_u32(&mut data[6..10], self.nanoseconds);
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:09}", self.seconds, self.nanoseconds)
+    }
+}
+
+/// An identifier of a PTP clock, usually derived from a MAC address.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClockIdentity(pub [u8; 8]);
+
+impl fmt::Display for ClockIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let bytes = self.0;
+        write!(
+            f,
+            "{:02x}{:02x}{:02x}.{:02x}{:02x}.{:02x}{:02x}{:02x}",
+            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]
+        )
+    }
+}
+
+/// An identifier of a PTP port: a clock identity and a port number within that clock.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PortIdentity {
+    pub clock_identity: ClockIdentity,
+    pub port_number: u16,
+}
+
+impl PortIdentity {
+    const LEN: usize = 10;
+
+    fn read(data: &[u8]) -> PortIdentity {
+        let mut clock_identity = [0; 8];
+        clock_identity.copy_from_slice(&data[0..8]);
+        PortIdentity {
+            clock_identity: ClockIdentity(clock_identity),
+            port_number: NetworkEndian::read_u16(&data[8..10]),
+        }
+    }
+
+    fn write(&self, data: &mut [u8]) {
+        data[0..8].copy_from_slice(&self.clock_identity.0);
+        NetworkEndian::write_u16(&mut data[8..10], self.port_number);
+    }
+}
+
+impl fmt::Display for PortIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-{}", self.clock_identity, self.port_number)
+    }
+}
+
+/// The quality of a grandmaster clock, as advertised in Announce messages.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ClockQuality {
+    pub clock_class: u8,
+    pub clock_accuracy: u8,
+    pub offset_scaled_log_variance: u16,
+}
+
+/// A read/write wrapper around a PTP message buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of the common PTP header
+//
+// +-----------+-----------+-----------+-----------+
+// | transport | msg type  |  minor    |  version  |
+// +-----------+-----------+-----------+-----------+
+// |     message length    | domain    | reserved  |
+// +-----------------------+-----------+-----------+
+// |         flags         |   correction field    |
+// +-----------------------+                       |
+// |                   (64 bits)                   |
+// +-----------------------+-----------------------+
+// |                    reserved                   |
+// +-----------------------------------------------+
+// |         source port identity (80 bits)        |
+// +-----------------------+-----------+-----------+
+// |      sequence id      |  control  | log intvl |
+// +-----------------------+-----------+-----------+
+//
+// See IEEE 1588-2008 § 13.3 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const TYPE: usize = 0;
+    pub const VERSION: usize = 1;
+    pub const LENGTH: Field = 2..4;
+    pub const DOMAIN: usize = 4;
+    pub const FLAGS: Field = 6..8;
+    pub const CORRECTION: Field = 8..16;
+    pub const SOURCE_PORT: Field = 20..30;
+    pub const SEQUENCE_ID: Field = 30..32;
+    pub const CONTROL: usize = 32;
+    pub const LOG_INTERVAL: usize = 33;
+    pub const BODY: Rest = 34..;
+
+    // Sync, Delay_Req: originTimestamp
+    // Follow_Up: preciseOriginTimestamp
+    // Delay_Resp: receiveTimestamp
+    // Announce: originTimestamp
+    pub const TIMESTAMP: Field = 34..44;
+
+    // Delay_Resp
+    pub const REQUESTING_PORT: Field = 44..54;
+
+    // Announce
+    pub const UTC_OFFSET: Field = 44..46;
+    pub const GM_PRIORITY_1: usize = 47;
+    pub const GM_CLOCK_CLASS: usize = 48;
+    pub const GM_CLOCK_ACCURACY: usize = 49;
+    pub const GM_CLOCK_VARIANCE: Field = 50..52;
+    pub const GM_PRIORITY_2: usize = 52;
+    pub const GM_IDENTITY: Field = 53..61;
+    pub const STEPS_REMOVED: Field = 61..63;
+    pub const TIME_SOURCE: usize = 63;
+}
+
+/// The length of the common PTP header.
+pub const HEADER_LEN: usize = field::BODY.start;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with PTP message structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    ///
+    /// The result of this check is invalidated by calling [set_message_length].
+    ///
+    /// [set_message_length]: #method.set_message_length
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < HEADER_LEN {
+            return Err(Error);
+        }
+        let message_len = self.message_length() as usize;
+        if message_len < HEADER_LEN || len < message_len {
+            return Err(Error);
+        }
+        let body_len = match self.message_type() {
+            MessageType::Sync | MessageType::DelayReq | MessageType::FollowUp => {
+                field::TIMESTAMP.end
+            }
+            MessageType::DelayResp => field::REQUESTING_PORT.end,
+            MessageType::Announce => field::TIME_SOURCE + 1,
+            _ => HEADER_LEN,
+        };
+        if message_len < body_len {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the transport specific field.
+    #[inline]
+    pub fn transport_specific(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::TYPE] >> 4
+    }
+
+    /// Return the message type field.
+    #[inline]
+    pub fn message_type(&self) -> MessageType {
+        let data = self.buffer.as_ref();
+        MessageType::from(data[field::TYPE] & 0x0f)
+    }
+
+    /// Return the PTP version field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::VERSION] & 0x0f
+    }
+
+    /// Return the message length field.
+    #[inline]
+    pub fn message_length(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::LENGTH)
+    }
+
+    /// Return the domain number field.
+    #[inline]
+    pub fn domain_number(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::DOMAIN]
+    }
+
+    /// Return the flags field.
+    #[inline]
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags::from_bits_truncate(read_u16(data, field::FLAGS))
+    }
+
+    /// Return the correction field, in nanoseconds multiplied by 2<sup>16</sup>.
+    #[inline]
+    pub fn correction(&self) -> i64 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_i64(&data[field::CORRECTION])
+    }
+
+    /// Return the source port identity field.
+    #[inline]
+    pub fn source_port_identity(&self) -> PortIdentity {
+        let data = self.buffer.as_ref();
+        PortIdentity::read(&data[field::SOURCE_PORT])
+    }
+
+    /// Return the sequence ID field.
+    #[inline]
+    pub fn sequence_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::SEQUENCE_ID)
+    }
+
+    /// Return the control field.
+    ///
+    /// This field is deprecated and only meaningful to PTPv1 implementations.
+    #[inline]
+    pub fn control(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::CONTROL]
+    }
+
+    /// Return the log message interval field.
+    #[inline]
+    pub fn log_message_interval(&self) -> i8 {
+        let data = self.buffer.as_ref();
+        data[field::LOG_INTERVAL] as i8
+    }
+
+    /// Return the timestamp at the start of the message body: the origin timestamp
+    /// of Sync, Delay_Req and Announce messages, the precise origin timestamp of
+    /// Follow_Up messages, and the receive timestamp of Delay_Resp messages.
+    #[inline]
+    pub fn timestamp(&self) -> Timestamp {
+        let data = self.buffer.as_ref();
+        Timestamp::read(&data[field::TIMESTAMP])
+    }
+
+    /// Return the requesting port identity field of a Delay_Resp message.
+    #[inline]
+    pub fn requesting_port_identity(&self) -> PortIdentity {
+        let data = self.buffer.as_ref();
+        PortIdentity::read(&data[field::REQUESTING_PORT])
+    }
+
+    /// Return the current UTC offset field of an Announce message.
+    #[inline]
+    pub fn current_utc_offset(&self) -> i16 {
+        let data = self.buffer.as_ref();
+        NetworkEndian::read_i16(&data[field::UTC_OFFSET])
+    }
+
+    /// Return the grandmaster priority 1 field of an Announce message.
+    #[inline]
+    pub fn grandmaster_priority_1(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::GM_PRIORITY_1]
+    }
+
+    /// Return the grandmaster clock quality field of an Announce message.
+    #[inline]
+    pub fn grandmaster_clock_quality(&self) -> ClockQuality {
+        let data = self.buffer.as_ref();
+        ClockQuality {
+            clock_class: data[field::GM_CLOCK_CLASS],
+            clock_accuracy: data[field::GM_CLOCK_ACCURACY],
+            offset_scaled_log_variance: read_u16(data, field::GM_CLOCK_VARIANCE),
+        }
+    }
+
+    /// Return the grandmaster priority 2 field of an Announce message.
+    #[inline]
+    pub fn grandmaster_priority_2(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::GM_PRIORITY_2]
+    }
+
+    /// Return the grandmaster identity field of an Announce message.
+    #[inline]
+    pub fn grandmaster_identity(&self) -> ClockIdentity {
+        let data = self.buffer.as_ref();
+        let mut identity = [0; 8];
+        identity.copy_from_slice(&data[field::GM_IDENTITY]);
+        ClockIdentity(identity)
+    }
+
+    /// Return the steps removed field of an Announce message.
+    #[inline]
+    pub fn steps_removed(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::STEPS_REMOVED)
+    }
+
+    /// Return the time source field of an Announce message.
+    #[inline]
+    pub fn time_source(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::TIME_SOURCE]
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the message body, excluding any trailing bytes
+    /// beyond the message length, such as Ethernet padding.
+    #[inline]
+    pub fn body(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[HEADER_LEN..self.message_length() as usize]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the transport specific and message type fields.
+    #[inline]
+    pub fn set_message_type(&mut self, transport_specific: u8, value: MessageType) {
+        let data = self.buffer.as_mut();
+        data[field::TYPE] = (transport_specific << 4) | (u8::from(value) & 0x0f)
+    }
+
+    /// Set the PTP version field, clearing the minor version.
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::VERSION] = value & 0x0f
+    }
+
+    /// Set the message length field.
+    #[inline]
+    pub fn set_message_length(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::LENGTH, value)
+    }
+
+    /// Set the domain number field.
+    #[inline]
+    pub fn set_domain_number(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::DOMAIN] = value
+    }
+
+    /// Set the flags field.
+    #[inline]
+    pub fn set_flags(&mut self, value: Flags) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::FLAGS, value.bits())
+    }
+
+    /// Set the correction field.
+    #[inline]
+    pub fn set_correction(&mut self, value: i64) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_i64(&mut data[field::CORRECTION], value)
+    }
+
+    /// Set the source port identity field.
+    #[inline]
+    pub fn set_source_port_identity(&mut self, value: PortIdentity) {
+        let data = self.buffer.as_mut();
+        value.write(&mut data[field::SOURCE_PORT])
+    }
+
+    /// Set the sequence ID field.
+    #[inline]
+    pub fn set_sequence_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::SEQUENCE_ID, value)
+    }
+
+    /// Set the control field.
+    #[inline]
+    pub fn set_control(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::CONTROL] = value
+    }
+
+    /// Set the log message interval field.
+    #[inline]
+    pub fn set_log_message_interval(&mut self, value: i8) {
+        let data = self.buffer.as_mut();
+        data[field::LOG_INTERVAL] = value as u8
+    }
+
+    /// Clear the reserved fields of the common header.
+    #[inline]
+    pub fn clear_reserved(&mut self) {
+        let data = self.buffer.as_mut();
+        data[5] = 0;
+        data[16..20].fill(0);
+    }
+
+    /// Set the timestamp at the start of the message body.
+    #[inline]
+    pub fn set_timestamp(&mut self, value: Timestamp) {
+        let data = self.buffer.as_mut();
+        value.write(&mut data[field::TIMESTAMP])
+    }
+
+    /// Set the requesting port identity field of a Delay_Resp message.
+    #[inline]
+    pub fn set_requesting_port_identity(&mut self, value: PortIdentity) {
+        let data = self.buffer.as_mut();
+        value.write(&mut data[field::REQUESTING_PORT])
+    }
+
+    /// Set the body fields of an Announce message following the origin timestamp.
+    #[inline]
+    pub fn set_announce(&mut self, value: &Announce) {
+        let data = self.buffer.as_mut();
+        NetworkEndian::write_i16(&mut data[field::UTC_OFFSET], value.current_utc_offset);
+        data[field::UTC_OFFSET.end] = 0;
+        data[field::GM_PRIORITY_1] = value.grandmaster_priority_1;
+        data[field::GM_CLOCK_CLASS] = value.grandmaster_clock_quality.clock_class;
+        data[field::GM_CLOCK_ACCURACY] = value.grandmaster_clock_quality.clock_accuracy;
+        NetworkEndian::write_u16(
+            &mut data[field::GM_CLOCK_VARIANCE],
+            value.grandmaster_clock_quality.offset_scaled_log_variance,
+        );
+        data[field::GM_PRIORITY_2] = value.grandmaster_priority_2;
+        data[field::GM_IDENTITY].copy_from_slice(&value.grandmaster_identity.0);
+        write_u16(data, field::STEPS_REMOVED, value.steps_removed);
+        data[field::TIME_SOURCE] = value.time_source;
+    }
+}
+
+/// The body of an Announce message.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Announce {
+    pub origin_timestamp: Timestamp,
+    pub current_utc_offset: i16,
+    pub grandmaster_priority_1: u8,
+    pub grandmaster_clock_quality: ClockQuality,
+    pub grandmaster_priority_2: u8,
+    pub grandmaster_identity: ClockIdentity,
+    pub steps_removed: u16,
+    pub time_source: u8,
+}
+
+/// A high-level representation of the body of a PTP message.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Message {
+    Sync {
+        origin_timestamp: Timestamp,
+    },
+    DelayReq {
+        origin_timestamp: Timestamp,
+    },
+    FollowUp {
+        precise_origin_timestamp: Timestamp,
+    },
+    DelayResp {
+        receive_timestamp: Timestamp,
+        requesting_port_identity: PortIdentity,
+    },
+    Announce(Announce),
+}
+
+impl Message {
+    /// Return the message type of this message.
+    pub fn message_type(&self) -> MessageType {
+        match self {
+            Message::Sync { .. } => MessageType::Sync,
+            Message::DelayReq { .. } => MessageType::DelayReq,
+            Message::FollowUp { .. } => MessageType::FollowUp,
+            Message::DelayResp { .. } => MessageType::DelayResp,
+            Message::Announce(_) => MessageType::Announce,
+        }
+    }
+
+    /// Return the value of the deprecated control field for this message.
+    fn control(&self) -> u8 {
+        match self {
+            Message::Sync { .. } => 0,
+            Message::DelayReq { .. } => 1,
+            Message::FollowUp { .. } => 2,
+            Message::DelayResp { .. } => 3,
+            Message::Announce(_) => 5,
+        }
+    }
+
+    /// Return the length of the message body.
+    pub fn buffer_len(&self) -> usize {
+        match self {
+            Message::Sync { .. } | Message::DelayReq { .. } | Message::FollowUp { .. } => {
+                Timestamp::LEN
+            }
+            Message::DelayResp { .. } => Timestamp::LEN + PortIdentity::LEN,
+            Message::Announce(_) => field::TIME_SOURCE + 1 - HEADER_LEN,
+        }
+    }
+}
+
+/// A high-level representation of a PTPv2 message.
+///
+/// The representation is the same for the Ethernet (EtherType 0x88F7) and UDP transports;
+/// over UDP, event messages are sent to [EVENT_PORT] and general messages to [GENERAL_PORT],
+/// see [Repr::udp_port].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr {
+    /// Transport specific field; 0 for the default profiles, 1 for IEEE 802.1AS.
+    pub transport_specific: u8,
+    pub domain_number: u8,
+    pub flags: Flags,
+    /// Correction, in nanoseconds multiplied by 2<sup>16</sup>.
+    pub correction: i64,
+    pub source_port_identity: PortIdentity,
+    pub sequence_id: u16,
+    pub log_message_interval: i8,
+    pub message: Message,
+}
+
+impl Repr {
+    /// Parse a PTP message and return a high-level representation.
+    ///
+    /// Returns `Err(Error)` for message types not supported by [Message].
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if packet.version() != 2 {
+            return Err(Error);
+        }
+
+        let message = match packet.message_type() {
+            MessageType::Sync => Message::Sync {
+                origin_timestamp: packet.timestamp(),
+            },
+            MessageType::DelayReq => Message::DelayReq {
+                origin_timestamp: packet.timestamp(),
+            },
+            MessageType::FollowUp => Message::FollowUp {
+                precise_origin_timestamp: packet.timestamp(),
+            },
+            MessageType::DelayResp => Message::DelayResp {
+                receive_timestamp: packet.timestamp(),
+                requesting_port_identity: packet.requesting_port_identity(),
+            },
+            MessageType::Announce => Message::Announce(Announce {
+                origin_timestamp: packet.timestamp(),
+                current_utc_offset: packet.current_utc_offset(),
+                grandmaster_priority_1: packet.grandmaster_priority_1(),
+                grandmaster_clock_quality: packet.grandmaster_clock_quality(),
+                grandmaster_priority_2: packet.grandmaster_priority_2(),
+                grandmaster_identity: packet.grandmaster_identity(),
+                steps_removed: packet.steps_removed(),
+                time_source: packet.time_source(),
+            }),
+            _ => return Err(Error),
+        };
+
+        Ok(Repr {
+            transport_specific: packet.transport_specific(),
+            domain_number: packet.domain_number(),
+            flags: packet.flags(),
+            correction: packet.correction(),
+            source_port_identity: packet.source_port_identity(),
+            sequence_id: packet.sequence_id(),
+            log_message_interval: packet.log_message_interval(),
+            message,
+        })
+    }
+
+    /// Return the length of a message that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        HEADER_LEN + self.message.buffer_len()
+    }
+
+    /// Return the UDP destination port this message is sent to.
+    pub fn udp_port(&self) -> u16 {
+        if self.message.message_type().is_event() {
+            EVENT_PORT
+        } else {
+            GENERAL_PORT
+        }
+    }
+
+    /// Emit a high-level representation into a PTP message.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_message_type(self.transport_specific, self.message.message_type());
+        packet.set_version(2);
+        packet.set_message_length(self.buffer_len() as u16);
+        packet.set_domain_number(self.domain_number);
+        packet.set_flags(self.flags);
+        packet.set_correction(self.correction);
+        packet.clear_reserved();
+        packet.set_source_port_identity(self.source_port_identity);
+        packet.set_sequence_id(self.sequence_id);
+        packet.set_control(self.message.control());
+        packet.set_log_message_interval(self.log_message_interval);
+
+        match &self.message {
+            Message::Sync { origin_timestamp } | Message::DelayReq { origin_timestamp } => {
+                packet.set_timestamp(*origin_timestamp)
+            }
+            Message::FollowUp {
+                precise_origin_timestamp,
+            } => packet.set_timestamp(*precise_origin_timestamp),
+            Message::DelayResp {
+                receive_timestamp,
+                requesting_port_identity,
+            } => {
+                packet.set_timestamp(*receive_timestamp);
+                packet.set_requesting_port_identity(*requesting_port_identity);
+            }
+            Message::Announce(announce) => {
+                packet.set_timestamp(announce.origin_timestamp);
+                packet.set_announce(announce);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "PTPv2 {} domain={} src={} seq={}",
+            self.message.message_type(),
+            self.domain_number,
+            self.source_port_identity,
+            self.sequence_id
+        )?;
+        match &self.message {
+            Message::Sync { origin_timestamp } | Message::DelayReq { origin_timestamp } => {
+                write!(f, " origin={}", origin_timestamp)
+            }
+            Message::FollowUp {
+                precise_origin_timestamp,
+            } => write!(f, " origin={}", precise_origin_timestamp),
+            Message::DelayResp {
+                receive_timestamp,
+                requesting_port_identity,
+            } => write!(
+                f,
+                " recv={} req={}",
+                receive_timestamp, requesting_port_identity
+            ),
+            Message::Announce(announce) => write!(
+                f,
+                " gm={} prio={}/{} class={} steps={}",
+                announce.grandmaster_identity,
+                announce.grandmaster_priority_1,
+                announce.grandmaster_priority_2,
+                announce.grandmaster_clock_quality.clock_class,
+                announce.steps_removed
+            ),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "PTP {} ({})", self.message_type(), err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: PortIdentity = PortIdentity {
+        clock_identity: ClockIdentity([0x00, 0x11, 0x22, 0xff, 0xfe, 0x33, 0x44, 0x55]),
+        port_number: 1,
+    };
+
+    static SYNC_BYTES: [u8; 44] = [
+        0x00, 0x02, 0x00, 0x2c, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11, 0x22, 0xff, 0xfe, 0x33, 0x44, 0x55, 0x00, 0x01,
+        0x12, 0x34, 0x00, 0x00, 0x00, 0x00, 0x61, 0xcf, 0xba, 0x80, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    fn sync_repr() -> Repr {
+        Repr {
+            transport_specific: 0,
+            domain_number: 0,
+            flags: Flags::TWO_STEP,
+            correction: 0x10000,
+            source_port_identity: SOURCE,
+            sequence_id: 0x1234,
+            log_message_interval: 0,
+            message: Message::Sync {
+                origin_timestamp: Timestamp {
+                    seconds: 1_641_003_648,
+                    nanoseconds: 0,
+                },
+            },
+        }
+    }
+
+    static DELAY_RESP_BYTES: [u8; 54] = [
+        0x09, 0x02, 0x00, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11, 0x22, 0xff, 0xfe, 0x33, 0x44, 0x55, 0x00, 0x01,
+        0x00, 0x07, 0x03, 0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2a, 0x00, 0x00, 0x03, 0xe8, 0xaa,
+        0xbb, 0xcc, 0xff, 0xfe, 0xdd, 0xee, 0xff, 0x00, 0x02,
+    ];
+
+    fn delay_resp_repr() -> Repr {
+        Repr {
+            transport_specific: 0,
+            domain_number: 0,
+            flags: Flags::empty(),
+            correction: 0,
+            source_port_identity: SOURCE,
+            sequence_id: 7,
+            log_message_interval: -2,
+            message: Message::DelayResp {
+                receive_timestamp: Timestamp {
+                    seconds: 42,
+                    nanoseconds: 1000,
+                },
+                requesting_port_identity: PortIdentity {
+                    clock_identity: ClockIdentity([0xaa, 0xbb, 0xcc, 0xff, 0xfe, 0xdd, 0xee, 0xff]),
+                    port_number: 2,
+                },
+            },
+        }
+    }
+
+    fn announce_repr() -> Repr {
+        Repr {
+            transport_specific: 0,
+            domain_number: 24,
+            flags: Flags::PTP_TIMESCALE | Flags::CURRENT_UTC_OFFSET_VALID,
+            correction: 0,
+            source_port_identity: SOURCE,
+            sequence_id: 3,
+            log_message_interval: 1,
+            message: Message::Announce(Announce {
+                origin_timestamp: Timestamp::default(),
+                current_utc_offset: 37,
+                grandmaster_priority_1: 128,
+                grandmaster_clock_quality: ClockQuality {
+                    clock_class: 6,
+                    clock_accuracy: 0x21,
+                    offset_scaled_log_variance: 0x4e5d,
+                },
+                grandmaster_priority_2: 128,
+                grandmaster_identity: SOURCE.clock_identity,
+                steps_removed: 0,
+                time_source: 0x20,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&SYNC_BYTES[..]).unwrap();
+        assert_eq!(packet.message_type(), MessageType::Sync);
+        assert_eq!(packet.version(), 2);
+        assert_eq!(packet.message_length(), 44);
+        assert_eq!(packet.flags(), Flags::TWO_STEP);
+        assert_eq!(packet.correction(), 0x10000);
+        assert_eq!(packet.source_port_identity(), SOURCE);
+        assert_eq!(packet.sequence_id(), 0x1234);
+        assert_eq!(packet.timestamp().seconds, 1_641_003_648);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&SYNC_BYTES[..33]), Err(Error));
+        assert_eq!(Packet::new_checked(&SYNC_BYTES[..43]), Err(Error));
+
+        // The message length must cover the body.
+        let mut bytes = SYNC_BYTES;
+        bytes[3] = 40;
+        assert_eq!(Packet::new_checked(&bytes[..]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let packet = Packet::new_unchecked(&SYNC_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(sync_repr()));
+    }
+
+    #[test]
+    fn test_emit_sync() {
+        let repr = sync_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &SYNC_BYTES[..]);
+    }
+
+    #[test]
+    fn test_parse_delay_resp() {
+        let packet = Packet::new_unchecked(&DELAY_RESP_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(delay_resp_repr()));
+    }
+
+    #[test]
+    fn test_emit_delay_resp() {
+        let repr = delay_resp_repr();
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &DELAY_RESP_BYTES[..]);
+    }
+
+    #[test]
+    fn test_announce_roundtrip() {
+        let repr = announce_repr();
+        assert_eq!(repr.buffer_len(), 64);
+        // Over Ethernet, short frames are padded; trailing bytes are ignored.
+        let mut bytes = vec![0xa5; 80];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.body().len(), 30);
+        assert_eq!(Repr::parse(&packet), Ok(repr));
+    }
+
+    #[test]
+    fn test_udp_port() {
+        assert_eq!(sync_repr().udp_port(), EVENT_PORT);
+        assert_eq!(delay_resp_repr().udp_port(), GENERAL_PORT);
+        assert_eq!(announce_repr().udp_port(), GENERAL_PORT);
+    }
+
+    #[test]
+    fn test_parse_unsupported() {
+        let mut bytes = SYNC_BYTES;
+        bytes[0] = 0x0d;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = SYNC_BYTES;
+        bytes[1] = 0x01;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+    }
+}
@@ -151,6 +151,7 @@ pub mod frag {
     //! [RFC 4944 § 5.3]: https://datatracker.ietf.org/doc/html/rfc4944#section-5.3
 
     use super::{DISPATCH_FIRST_FRAGMENT_HEADER, DISPATCH_FRAGMENT_HEADER};
+    use crate::wire::field::read_u16;
     use crate::{
         wire::{Ieee802154Address, Ieee802154Repr},
         Error, Result,
@@ -271,13 +272,13 @@ pub mod frag {
         /// Return the total datagram size.
         pub fn datagram_size(&self) -> u16 {
             let raw = self.buffer.as_ref();
-            NetworkEndian::read_u16(&raw[field::DATAGRAM_SIZE]) & 0b111_1111_1111
+            read_u16(raw, field::DATAGRAM_SIZE) & 0b111_1111_1111
         }
 
         /// Return the datagram tag.
         pub fn datagram_tag(&self) -> u16 {
             let raw = self.buffer.as_ref();
-            NetworkEndian::read_u16(&raw[field::DATAGRAM_TAG])
+            read_u16(raw, field::DATAGRAM_TAG)
         }
 
         /// Return the datagram offset.
@@ -333,7 +334,7 @@ pub mod frag {
 
         fn set_datagram_size(&mut self, size: u16) {
             let raw = self.buffer.as_mut();
-            let mut v = NetworkEndian::read_u16(&raw[field::DATAGRAM_SIZE]);
+            let mut v = read_u16(raw, field::DATAGRAM_SIZE);
             v = (v & !0b111_1111_1111) | size;
 
             NetworkEndian::write_u16(&mut raw[field::DATAGRAM_SIZE], v);
@@ -424,6 +425,7 @@ pub mod iphc {
     //! [RFC 6282 § 3.1]: https://datatracker.ietf.org/doc/html/rfc6282#section-3.1
 
     use super::{AddressMode, Error, NextHeader, Result, UnresolvedAddress, DISPATCH_IPHC_HEADER};
+    use crate::wire::field::read_u16;
     use crate::wire::{ieee802154::Address as LlAddress, ipv6, IpProtocol};
     use byteorder::{ByteOrder, NetworkEndian};
 
@@ -437,7 +439,7 @@ pub mod iphc {
         ($name:ident, $mask:expr, $shift:expr) => {
             fn $name(&self) -> u8 {
                 let data = self.buffer.as_ref();
-                let raw = NetworkEndian::read_u16(&data[field::IPHC_FIELD]);
+                let raw = read_u16(data, field::IPHC_FIELD);
                 ((raw >> $shift) & $mask) as u8
             }
         };
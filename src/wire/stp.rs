@@ -0,0 +1,705 @@
+use crate::wire::field::{read_u16, read_u32, write_u16, write_u32};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, NetworkEndian};
+use core::fmt;
+
+use super::{Error, Result};
+use crate::time::Duration;
+use crate::wire::EthernetAddress;
+
+/// The bridge group multicast address BPDUs are sent to.
+pub const MULTICAST_ADDR: EthernetAddress = EthernetAddress([0x01, 0x80, 0xc2, 0x00, 0x00, 0x00]);
+
+/// The IEEE 802.2 LLC header preceding every BPDU in an 802.3 frame.
+pub const LLC_HEADER: [u8; 3] = [0x42, 0x42, 0x03];
+
+enum_with_unknown! {
+    /// BPDU type.
+    pub enum BpduType(u8) {
+        /// Configuration BPDU
+        Config = 0x00,
+        /// Rapid Spanning Tree BPDU
+        Rst    = 0x02,
+        /// Topology Change Notification BPDU
+        Tcn    = 0x80
+    }
+}
+
+impl fmt::Display for BpduType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BpduType::Config => write!(f, "config"),
+            BpduType::Rst => write!(f, "RST"),
+            BpduType::Tcn => write!(f, "TCN"),
+            BpduType::Unknown(id) => write!(f, "0x{:02x}", id),
+        }
+    }
+}
+
+bitflags! {
+    /// BPDU flags, excluding the port role bits of RST BPDUs.
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct Flags: u8 {
+        const TOPOLOGY_CHANGE     = 0b0000_0001;
+        const PROPOSAL            = 0b0000_0010;
+        const LEARNING            = 0b0001_0000;
+        const FORWARDING          = 0b0010_0000;
+        const AGREEMENT           = 0b0100_0000;
+        const TOPOLOGY_CHANGE_ACK = 0b1000_0000;
+    }
+}
+
+/// Port role carried in the flags of an RST BPDU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PortRole {
+    Unknown,
+    AlternateOrBackup,
+    Root,
+    Designated,
+}
+
+impl PortRole {
+    const SHIFT: u8 = 2;
+    const MASK: u8 = 0b0000_1100;
+
+    fn from_flags(flags: u8) -> PortRole {
+        match (flags & Self::MASK) >> Self::SHIFT {
+            1 => PortRole::AlternateOrBackup,
+            2 => PortRole::Root,
+            3 => PortRole::Designated,
+            _ => PortRole::Unknown,
+        }
+    }
+
+    fn to_flags(self) -> u8 {
+        let role = match self {
+            PortRole::Unknown => 0,
+            PortRole::AlternateOrBackup => 1,
+            PortRole::Root => 2,
+            PortRole::Designated => 3,
+        };
+        role << Self::SHIFT
+    }
+}
+
+impl fmt::Display for PortRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PortRole::Unknown => write!(f, "unknown"),
+            PortRole::AlternateOrBackup => write!(f, "alternate/backup"),
+            PortRole::Root => write!(f, "root"),
+            PortRole::Designated => write!(f, "designated"),
+        }
+    }
+}
+
+/// A bridge identifier, made of a priority and a MAC address.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+pub struct BridgeId {
+    pub priority: u16,
+    pub addr: EthernetAddress,
+}
+
+impl BridgeId {
+    /// Construct a bridge identifier from a sequence of octets, in big-endian.
+    ///
+    /// # Panics
+    /// The function panics if `data` is not eight octets long.
+    pub fn from_bytes(data: &[u8]) -> BridgeId {
+        BridgeId {
+            priority: NetworkEndian::read_u16(&data[0..2]),
+            addr: EthernetAddress::from_bytes(&data[2..8]),
+        }
+    }
+
+    /// Write the bridge identifier into a sequence of octets, in big-endian.
+    ///
+    /// # Panics
+    /// The function panics if `data` is not eight octets long.
+    pub fn write_bytes(&self, data: &mut [u8]) {
+        NetworkEndian::write_u16(&mut data[0..2], self.priority);
+        data[2..8].copy_from_slice(self.addr.as_bytes());
+    }
+}
+
+impl fmt::Display for BridgeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.priority, self.addr)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for BridgeId {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}/{}", self.priority, self.addr)
+    }
+}
+
+/// A read/write wrapper around a Spanning Tree Bridge Protocol Data Unit.
+///
+/// The buffer starts right after the LLC header of the 802.3 frame.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of a BPDU
+//
+// See IEEE 802.1D-2004 § 9.3 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const PROTOCOL_ID: Field = 0..2;
+    pub const VERSION: usize = 2;
+    pub const BPDU_TYPE: usize = 3;
+    // Length of a Topology Change Notification BPDU.
+    pub const TCN_LEN: usize = 4;
+
+    pub const FLAGS: usize = 4;
+    pub const ROOT_ID: Field = 5..13;
+    pub const ROOT_PATH_COST: Field = 13..17;
+    pub const BRIDGE_ID: Field = 17..25;
+    pub const PORT_ID: Field = 25..27;
+    pub const MESSAGE_AGE: Field = 27..29;
+    pub const MAX_AGE: Field = 29..31;
+    pub const HELLO_TIME: Field = 31..33;
+    pub const FORWARD_DELAY: Field = 33..35;
+    // Length of a Configuration BPDU.
+    pub const CONFIG_LEN: usize = FORWARD_DELAY.end;
+
+    pub const VERSION_1_LEN: usize = 35;
+    // Length of an RST BPDU.
+    pub const RST_LEN: usize = VERSION_1_LEN + 1;
+}
+
+/// Convert a BPDU timer value, expressed in units of 1/256 of a second, to a duration.
+fn timer_to_duration(value: u16) -> Duration {
+    Duration::from_micros(value as u64 * 1_000_000 / 256)
+}
+
+/// Convert a duration to a BPDU timer value, rounding to the nearest unit.
+fn duration_to_timer(duration: Duration) -> u16 {
+    let value = (duration.total_micros().saturating_mul(256) + 500_000) / 1_000_000;
+    if value > u16::MAX as u64 {
+        u16::MAX
+    } else {
+        value as u16
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with BPDU structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short for the BPDU type.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::TCN_LEN {
+            return Err(Error);
+        }
+        let min_len = match self.bpdu_type() {
+            BpduType::Tcn => field::TCN_LEN,
+            BpduType::Rst => field::RST_LEN,
+            _ => field::CONFIG_LEN,
+        };
+        if len < min_len {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the protocol identifier field.
+    #[inline]
+    pub fn protocol_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::PROTOCOL_ID)
+    }
+
+    /// Return the protocol version field.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        data[field::VERSION]
+    }
+
+    /// Return the BPDU type field.
+    #[inline]
+    pub fn bpdu_type(&self) -> BpduType {
+        let data = self.buffer.as_ref();
+        BpduType::from(data[field::BPDU_TYPE])
+    }
+
+    /// Return the flags field.
+    #[inline]
+    pub fn flags(&self) -> Flags {
+        let data = self.buffer.as_ref();
+        Flags::from_bits_truncate(data[field::FLAGS])
+    }
+
+    /// Return the port role encoded in the flags field.
+    #[inline]
+    pub fn port_role(&self) -> PortRole {
+        let data = self.buffer.as_ref();
+        PortRole::from_flags(data[field::FLAGS])
+    }
+
+    /// Return the root bridge identifier field.
+    #[inline]
+    pub fn root_id(&self) -> BridgeId {
+        let data = self.buffer.as_ref();
+        BridgeId::from_bytes(&data[field::ROOT_ID])
+    }
+
+    /// Return the root path cost field.
+    #[inline]
+    pub fn root_path_cost(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u32(data, field::ROOT_PATH_COST)
+    }
+
+    /// Return the bridge identifier field.
+    #[inline]
+    pub fn bridge_id(&self) -> BridgeId {
+        let data = self.buffer.as_ref();
+        BridgeId::from_bytes(&data[field::BRIDGE_ID])
+    }
+
+    /// Return the port identifier field.
+    #[inline]
+    pub fn port_id(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::PORT_ID)
+    }
+
+    /// Return the message age field.
+    #[inline]
+    pub fn message_age(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        timer_to_duration(read_u16(data, field::MESSAGE_AGE))
+    }
+
+    /// Return the max age field.
+    #[inline]
+    pub fn max_age(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        timer_to_duration(read_u16(data, field::MAX_AGE))
+    }
+
+    /// Return the hello time field.
+    #[inline]
+    pub fn hello_time(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        timer_to_duration(read_u16(data, field::HELLO_TIME))
+    }
+
+    /// Return the forward delay field.
+    #[inline]
+    pub fn forward_delay(&self) -> Duration {
+        let data = self.buffer.as_ref();
+        timer_to_duration(read_u16(data, field::FORWARD_DELAY))
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the protocol identifier field.
+    #[inline]
+    pub fn set_protocol_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::PROTOCOL_ID, value)
+    }
+
+    /// Set the protocol version field.
+    #[inline]
+    pub fn set_version(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::VERSION] = value
+    }
+
+    /// Set the BPDU type field.
+    #[inline]
+    pub fn set_bpdu_type(&mut self, value: BpduType) {
+        let data = self.buffer.as_mut();
+        data[field::BPDU_TYPE] = value.into()
+    }
+
+    /// Set the flags field, including the port role.
+    #[inline]
+    pub fn set_flags(&mut self, flags: Flags, port_role: PortRole) {
+        let data = self.buffer.as_mut();
+        data[field::FLAGS] = flags.bits() | port_role.to_flags()
+    }
+
+    /// Set the root bridge identifier field.
+    #[inline]
+    pub fn set_root_id(&mut self, value: BridgeId) {
+        let data = self.buffer.as_mut();
+        value.write_bytes(&mut data[field::ROOT_ID])
+    }
+
+    /// Set the root path cost field.
+    #[inline]
+    pub fn set_root_path_cost(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u32(data, field::ROOT_PATH_COST, value)
+    }
+
+    /// Set the bridge identifier field.
+    #[inline]
+    pub fn set_bridge_id(&mut self, value: BridgeId) {
+        let data = self.buffer.as_mut();
+        value.write_bytes(&mut data[field::BRIDGE_ID])
+    }
+
+    /// Set the port identifier field.
+    #[inline]
+    pub fn set_port_id(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::PORT_ID, value)
+    }
+
+    /// Set the message age field.
+    #[inline]
+    pub fn set_message_age(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::MESSAGE_AGE, duration_to_timer(value))
+    }
+
+    /// Set the max age field.
+    #[inline]
+    pub fn set_max_age(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::MAX_AGE, duration_to_timer(value))
+    }
+
+    /// Set the hello time field.
+    #[inline]
+    pub fn set_hello_time(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::HELLO_TIME, duration_to_timer(value))
+    }
+
+    /// Set the forward delay field.
+    #[inline]
+    pub fn set_forward_delay(&mut self, value: Duration) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::FORWARD_DELAY, duration_to_timer(value))
+    }
+
+    /// Set the version 1 length field of an RST BPDU.
+    #[inline]
+    pub fn set_version_1_len(&mut self, value: u8) {
+        let data = self.buffer.as_mut();
+        data[field::VERSION_1_LEN] = value
+    }
+}
+
+/// The spanning tree information carried by Configuration and RST BPDUs.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConfigRepr {
+    pub flags: Flags,
+    /// Port role; always `PortRole::Unknown` in Configuration BPDUs.
+    pub port_role: PortRole,
+    pub root_id: BridgeId,
+    pub root_path_cost: u32,
+    pub bridge_id: BridgeId,
+    pub port_id: u16,
+    pub message_age: Duration,
+    pub max_age: Duration,
+    pub hello_time: Duration,
+    pub forward_delay: Duration,
+}
+
+/// A high-level representation of a Spanning Tree BPDU.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Repr {
+    /// Configuration BPDU (STP).
+    Config(ConfigRepr),
+    /// Rapid Spanning Tree BPDU (RSTP).
+    Rst(ConfigRepr),
+    /// Topology Change Notification BPDU.
+    Tcn,
+}
+
+impl Repr {
+    /// Parse a BPDU and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+
+        if packet.protocol_id() != 0 {
+            return Err(Error);
+        }
+
+        let config = || ConfigRepr {
+            flags: packet.flags(),
+            port_role: PortRole::Unknown,
+            root_id: packet.root_id(),
+            root_path_cost: packet.root_path_cost(),
+            bridge_id: packet.bridge_id(),
+            port_id: packet.port_id(),
+            message_age: packet.message_age(),
+            max_age: packet.max_age(),
+            hello_time: packet.hello_time(),
+            forward_delay: packet.forward_delay(),
+        };
+
+        match packet.bpdu_type() {
+            BpduType::Config => Ok(Repr::Config(ConfigRepr {
+                // Only the topology change bits are defined for Configuration BPDUs.
+                flags: packet.flags() & (Flags::TOPOLOGY_CHANGE | Flags::TOPOLOGY_CHANGE_ACK),
+                ..config()
+            })),
+            BpduType::Rst => Ok(Repr::Rst(ConfigRepr {
+                port_role: packet.port_role(),
+                ..config()
+            })),
+            BpduType::Tcn => Ok(Repr::Tcn),
+            BpduType::Unknown(_) => Err(Error),
+        }
+    }
+
+    /// Return the length of a BPDU that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        match self {
+            Repr::Config(_) => field::CONFIG_LEN,
+            Repr::Rst(_) => field::RST_LEN,
+            Repr::Tcn => field::TCN_LEN,
+        }
+    }
+
+    /// Emit a high-level representation into a BPDU.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.set_protocol_id(0);
+        let config = match self {
+            Repr::Config(config) => {
+                packet.set_version(0);
+                packet.set_bpdu_type(BpduType::Config);
+                config
+            }
+            Repr::Rst(config) => {
+                packet.set_version(2);
+                packet.set_bpdu_type(BpduType::Rst);
+                packet.set_version_1_len(0);
+                config
+            }
+            Repr::Tcn => {
+                packet.set_version(0);
+                packet.set_bpdu_type(BpduType::Tcn);
+                return;
+            }
+        };
+        packet.set_flags(config.flags, config.port_role);
+        packet.set_root_id(config.root_id);
+        packet.set_root_path_cost(config.root_path_cost);
+        packet.set_bridge_id(config.bridge_id);
+        packet.set_port_id(config.port_id);
+        packet.set_message_age(config.message_age);
+        packet.set_max_age(config.max_age);
+        packet.set_hello_time(config.hello_time);
+        packet.set_forward_delay(config.forward_delay);
+    }
+}
+
+impl fmt::Display for ConfigRepr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "root={} cost={} bridge={} port=0x{:04x}",
+            self.root_id, self.root_path_cost, self.bridge_id, self.port_id
+        )?;
+        if self.port_role != PortRole::Unknown {
+            write!(f, " role={}", self.port_role)?;
+        }
+        if self.flags.contains(Flags::TOPOLOGY_CHANGE) {
+            write!(f, " tc")?;
+        }
+        if self.flags.contains(Flags::TOPOLOGY_CHANGE_ACK) {
+            write!(f, " tca")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Repr::Config(config) => write!(f, "STP config {}", config),
+            Repr::Rst(config) => write!(f, "STP RST {}", config),
+            Repr::Tcn => write!(f, "STP TCN"),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "STP ({}) type={}", err, self.bpdu_type()),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static CONFIG_BYTES: [u8; 35] = [
+        0x00, 0x00, 0x00, 0x00, 0x81, 0x80, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x04, 0x80, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x02, 0x80, 0x01, 0x01, 0x00, 0x14,
+        0x00, 0x02, 0x00, 0x0f, 0x00,
+    ];
+
+    static RST_BYTES: [u8; 36] = [
+        0x00, 0x00, 0x02, 0x02, 0x3c, 0x80, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x00, 0x00, 0x80, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x01, 0x80, 0x02, 0x00, 0x00, 0x14,
+        0x00, 0x02, 0x00, 0x0f, 0x00, 0x00,
+    ];
+
+    static TCN_BYTES: [u8; 4] = [0x00, 0x00, 0x00, 0x80];
+
+    fn config_repr() -> ConfigRepr {
+        ConfigRepr {
+            flags: Flags::TOPOLOGY_CHANGE | Flags::TOPOLOGY_CHANGE_ACK,
+            port_role: PortRole::Unknown,
+            root_id: BridgeId {
+                priority: 0x8000,
+                addr: EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]),
+            },
+            root_path_cost: 4,
+            bridge_id: BridgeId {
+                priority: 0x8000,
+                addr: EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x02]),
+            },
+            port_id: 0x8001,
+            message_age: Duration::from_secs(1),
+            max_age: Duration::from_secs(20),
+            hello_time: Duration::from_secs(2),
+            forward_delay: Duration::from_secs(15),
+        }
+    }
+
+    fn rst_repr() -> ConfigRepr {
+        ConfigRepr {
+            flags: Flags::LEARNING | Flags::FORWARDING,
+            port_role: PortRole::Designated,
+            root_path_cost: 0,
+            bridge_id: BridgeId {
+                priority: 0x8000,
+                addr: EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]),
+            },
+            port_id: 0x8002,
+            message_age: Duration::from_secs(0),
+            ..config_repr()
+        }
+    }
+
+    #[test]
+    fn test_config_deconstruct() {
+        let packet = Packet::new_checked(&CONFIG_BYTES[..]).unwrap();
+        assert_eq!(packet.protocol_id(), 0);
+        assert_eq!(packet.version(), 0);
+        assert_eq!(packet.bpdu_type(), BpduType::Config);
+        assert_eq!(
+            packet.flags(),
+            Flags::TOPOLOGY_CHANGE | Flags::TOPOLOGY_CHANGE_ACK
+        );
+        assert_eq!(packet.root_path_cost(), 4);
+        assert_eq!(packet.port_id(), 0x8001);
+        assert_eq!(packet.message_age(), Duration::from_secs(1));
+        assert_eq!(packet.max_age(), Duration::from_secs(20));
+        assert_eq!(packet.hello_time(), Duration::from_secs(2));
+        assert_eq!(packet.forward_delay(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&TCN_BYTES[..3]), Err(Error));
+        assert!(Packet::new_checked(&TCN_BYTES[..]).is_ok());
+        assert_eq!(Packet::new_checked(&CONFIG_BYTES[..34]), Err(Error));
+        assert_eq!(Packet::new_checked(&RST_BYTES[..35]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&CONFIG_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(Repr::Config(config_repr())));
+
+        let packet = Packet::new_unchecked(&RST_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(Repr::Rst(rst_repr())));
+
+        let packet = Packet::new_unchecked(&TCN_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(Repr::Tcn));
+    }
+
+    #[test]
+    fn test_parse_bad_protocol_id() {
+        let mut bytes = TCN_BYTES;
+        bytes[1] = 0x01;
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(Repr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        for (repr, expected) in [
+            (Repr::Config(config_repr()), &CONFIG_BYTES[..]),
+            (Repr::Rst(rst_repr()), &RST_BYTES[..]),
+            (Repr::Tcn, &TCN_BYTES[..]),
+        ] {
+            let mut bytes = vec![0xa5; repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes);
+            repr.emit(&mut packet);
+            assert_eq!(&bytes[..], expected);
+        }
+    }
+
+    #[test]
+    fn test_timer_round_trip() {
+        for value in [0, 1, 255, 256, 5120, u16::MAX] {
+            assert_eq!(duration_to_timer(timer_to_duration(value)), value);
+        }
+    }
+}
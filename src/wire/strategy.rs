@@ -0,0 +1,358 @@
+/*! Generators of wire types for property tests.
+
+The `strategy` module provides [proptest] strategies for addresses, high-level
+representations and packet buffers, so that property tests can check e.g. that
+parsing an emitted representation returns the same representation. It is
+available with the `proptest` feature.
+
+The representations only hold values that can be emitted; e.g. the
+`ipv4_repr` strategy never generates a payload longer than what fits in the
+total length field.
+
+# Example
+
+```rust
+# #[cfg(all(feature = "proptest", feature = "medium-ethernet"))]
+# {
+use proptest::prelude::*;
+use smoltcp::wire::{strategy, EthernetFrame, EthernetRepr};
+
+proptest!(|(repr in strategy::ethernet_repr())| {
+    let mut bytes = vec![0; repr.buffer_len()];
+    repr.emit(&mut EthernetFrame::new_unchecked(&mut bytes));
+    let frame = EthernetFrame::new_checked(&bytes).unwrap();
+    prop_assert_eq!(EthernetRepr::parse(&frame).unwrap(), repr);
+});
+# }
+```
+
+[proptest]: https://docs.rs/proptest
+*/
+
+use core::ops::Range;
+
+use proptest::collection::vec;
+use proptest::prelude::*;
+use proptest::std_facade::Vec;
+
+#[cfg(feature = "proto-gre")]
+use crate::wire::GreRepr;
+#[cfg(feature = "proto-vxlan")]
+use crate::wire::VxlanRepr;
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+use crate::wire::{ArpOperation, ArpRepr};
+#[cfg(feature = "medium-ethernet")]
+use crate::wire::{EthernetAddress, EthernetProtocol, EthernetRepr};
+use crate::wire::{IpAddress, IpCidr, IpEndpoint, IpProtocol, IpRepr};
+#[cfg(feature = "proto-ipv4")]
+use crate::wire::{Ipv4Address, Ipv4Cidr, Ipv4Repr};
+#[cfg(feature = "proto-ipv6")]
+use crate::wire::{Ipv6Address, Ipv6Cidr, Ipv6FragmentRepr, Ipv6Repr};
+use crate::wire::{TcpControl, TcpRepr, TcpSeqNumber, UdpRepr};
+
+/// Return a strategy generating packet buffers with a length in `len`.
+pub fn packet_buffer(len: Range<usize>) -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), len)
+}
+
+/// Return a strategy generating Ethernet addresses.
+#[cfg(feature = "medium-ethernet")]
+pub fn ethernet_address() -> impl Strategy<Value = EthernetAddress> {
+    any::<[u8; 6]>().prop_map(EthernetAddress)
+}
+
+/// Return a strategy generating EtherType values, known or not.
+#[cfg(feature = "medium-ethernet")]
+pub fn ethernet_protocol() -> impl Strategy<Value = EthernetProtocol> {
+    any::<u16>().prop_map(EthernetProtocol::from)
+}
+
+/// Return a strategy generating IPv4 addresses.
+#[cfg(feature = "proto-ipv4")]
+pub fn ipv4_address() -> impl Strategy<Value = Ipv4Address> {
+    any::<[u8; 4]>().prop_map(Ipv4Address)
+}
+
+/// Return a strategy generating IPv4 CIDR blocks.
+#[cfg(feature = "proto-ipv4")]
+pub fn ipv4_cidr() -> impl Strategy<Value = Ipv4Cidr> {
+    (ipv4_address(), 0..=32u8).prop_map(|(address, prefix_len)| Ipv4Cidr::new(address, prefix_len))
+}
+
+/// Return a strategy generating IPv6 addresses.
+#[cfg(feature = "proto-ipv6")]
+pub fn ipv6_address() -> impl Strategy<Value = Ipv6Address> {
+    any::<[u8; 16]>().prop_map(Ipv6Address)
+}
+
+/// Return a strategy generating IPv6 CIDR blocks.
+#[cfg(feature = "proto-ipv6")]
+pub fn ipv6_cidr() -> impl Strategy<Value = Ipv6Cidr> {
+    (ipv6_address(), 0..=128u8).prop_map(|(address, prefix_len)| Ipv6Cidr::new(address, prefix_len))
+}
+
+/// Return a strategy generating IP addresses of any enabled version.
+#[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+pub fn ip_address() -> impl Strategy<Value = IpAddress> {
+    prop_oneof![
+        ipv4_address().prop_map(IpAddress::Ipv4),
+        ipv6_address().prop_map(IpAddress::Ipv6),
+    ]
+}
+
+/// Return a strategy generating IP addresses of any enabled version.
+#[cfg(all(feature = "proto-ipv4", not(feature = "proto-ipv6")))]
+pub fn ip_address() -> impl Strategy<Value = IpAddress> {
+    ipv4_address().prop_map(IpAddress::Ipv4)
+}
+
+/// Return a strategy generating IP addresses of any enabled version.
+#[cfg(all(not(feature = "proto-ipv4"), feature = "proto-ipv6"))]
+pub fn ip_address() -> impl Strategy<Value = IpAddress> {
+    ipv6_address().prop_map(IpAddress::Ipv6)
+}
+
+/// Return a strategy generating pairs of source and destination IP addresses of the same
+/// version.
+#[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+pub fn ip_address_pair() -> impl Strategy<Value = (IpAddress, IpAddress)> {
+    prop_oneof![
+        (ipv4_address(), ipv4_address())
+            .prop_map(|(src, dst)| (IpAddress::Ipv4(src), IpAddress::Ipv4(dst))),
+        (ipv6_address(), ipv6_address())
+            .prop_map(|(src, dst)| (IpAddress::Ipv6(src), IpAddress::Ipv6(dst))),
+    ]
+}
+
+/// Return a strategy generating pairs of source and destination IP addresses of the same
+/// version.
+#[cfg(not(all(feature = "proto-ipv4", feature = "proto-ipv6")))]
+pub fn ip_address_pair() -> impl Strategy<Value = (IpAddress, IpAddress)> {
+    (ip_address(), ip_address())
+}
+
+/// Return a strategy generating IP CIDR blocks of any enabled version.
+pub fn ip_cidr() -> impl Strategy<Value = IpCidr> {
+    ip_address().prop_flat_map(|address| {
+        (0..=address.as_bytes().len() as u8 * 8)
+            .prop_map(move |prefix_len| IpCidr::new(address, prefix_len))
+    })
+}
+
+/// Return a strategy generating IP endpoints of any enabled version.
+pub fn ip_endpoint() -> impl Strategy<Value = IpEndpoint> {
+    (ip_address(), any::<u16>()).prop_map(|(addr, port)| IpEndpoint::new(addr, port))
+}
+
+/// Return a strategy generating IP protocol numbers, known or not.
+pub fn ip_protocol() -> impl Strategy<Value = IpProtocol> {
+    any::<u8>().prop_map(IpProtocol::from)
+}
+
+/// Return a strategy generating Ethernet II frame headers.
+#[cfg(feature = "medium-ethernet")]
+pub fn ethernet_repr() -> impl Strategy<Value = EthernetRepr> {
+    (ethernet_address(), ethernet_address(), ethernet_protocol()).prop_map(
+        |(src_addr, dst_addr, ethertype)| EthernetRepr {
+            src_addr,
+            dst_addr,
+            ethertype,
+        },
+    )
+}
+
+/// Return a strategy generating Ethernet and IPv4 ARP packets.
+#[cfg(all(feature = "proto-ipv4", feature = "medium-ethernet"))]
+pub fn arp_repr() -> impl Strategy<Value = ArpRepr> {
+    (
+        any::<u16>().prop_map(ArpOperation::from),
+        ethernet_address(),
+        ipv4_address(),
+        ethernet_address(),
+        ipv4_address(),
+    )
+        .prop_map(
+            |(
+                operation,
+                source_hardware_addr,
+                source_protocol_addr,
+                target_hardware_addr,
+                target_protocol_addr,
+            )| ArpRepr::EthernetIpv4 {
+                operation,
+                source_hardware_addr,
+                source_protocol_addr,
+                target_hardware_addr,
+                target_protocol_addr,
+            },
+        )
+}
+
+/// Return a strategy generating IPv4 packet headers.
+#[cfg(feature = "proto-ipv4")]
+pub fn ipv4_repr() -> impl Strategy<Value = Ipv4Repr> {
+    (
+        ipv4_address(),
+        ipv4_address(),
+        ip_protocol(),
+        0..=(u16::MAX as usize - crate::wire::IPV4_HEADER_LEN),
+        any::<u8>(),
+    )
+        .prop_map(
+            |(src_addr, dst_addr, next_header, payload_len, hop_limit)| Ipv4Repr {
+                src_addr,
+                dst_addr,
+                next_header,
+                payload_len,
+                hop_limit,
+            },
+        )
+}
+
+/// Return a strategy generating IPv6 packet headers.
+#[cfg(feature = "proto-ipv6")]
+pub fn ipv6_repr() -> impl Strategy<Value = Ipv6Repr> {
+    (
+        ipv6_address(),
+        ipv6_address(),
+        ip_protocol(),
+        0..=(u16::MAX as usize),
+        any::<u8>(),
+    )
+        .prop_map(
+            |(src_addr, dst_addr, next_header, payload_len, hop_limit)| Ipv6Repr {
+                src_addr,
+                dst_addr,
+                next_header,
+                payload_len,
+                hop_limit,
+            },
+        )
+}
+
+/// Return a strategy generating IP packet headers of any enabled version.
+#[cfg(all(feature = "proto-ipv4", feature = "proto-ipv6"))]
+pub fn ip_repr() -> impl Strategy<Value = IpRepr> {
+    prop_oneof![
+        ipv4_repr().prop_map(IpRepr::Ipv4),
+        ipv6_repr().prop_map(IpRepr::Ipv6),
+    ]
+}
+
+/// Return a strategy generating IP packet headers of any enabled version.
+#[cfg(all(feature = "proto-ipv4", not(feature = "proto-ipv6")))]
+pub fn ip_repr() -> impl Strategy<Value = IpRepr> {
+    ipv4_repr().prop_map(IpRepr::Ipv4)
+}
+
+/// Return a strategy generating IP packet headers of any enabled version.
+#[cfg(all(not(feature = "proto-ipv4"), feature = "proto-ipv6"))]
+pub fn ip_repr() -> impl Strategy<Value = IpRepr> {
+    ipv6_repr().prop_map(IpRepr::Ipv6)
+}
+
+/// Return a strategy generating IPv6 Fragment headers.
+#[cfg(feature = "proto-ipv6")]
+pub fn ipv6_fragment_repr() -> impl Strategy<Value = Ipv6FragmentRepr> {
+    (ip_protocol(), 0..=0x1fffu16, any::<bool>(), any::<u32>()).prop_map(
+        |(next_header, frag_offset, more_frags, ident)| Ipv6FragmentRepr {
+            next_header,
+            frag_offset,
+            more_frags,
+            ident,
+        },
+    )
+}
+
+/// Return a strategy generating UDP headers.
+///
+/// The destination port is never zero, since such datagrams are rejected when parsed.
+pub fn udp_repr() -> impl Strategy<Value = UdpRepr> {
+    (any::<u16>(), 1..=u16::MAX).prop_map(|(src_port, dst_port)| UdpRepr { src_port, dst_port })
+}
+
+/// Return a strategy generating TCP control flags.
+pub fn tcp_control() -> impl Strategy<Value = TcpControl> {
+    prop_oneof![
+        Just(TcpControl::None),
+        Just(TcpControl::Psh),
+        Just(TcpControl::Syn),
+        Just(TcpControl::Fin),
+        Just(TcpControl::Rst),
+    ]
+}
+
+/// Return a strategy generating TCP headers, with an empty payload.
+///
+/// The ports are never zero, and the window scale never exceeds 14, since such segments
+/// are not represented as is when parsed. SACK blocks are only present in segments
+/// acknowledging data and not permitting SACK, since they are not emitted otherwise.
+pub fn tcp_repr() -> impl Strategy<Value = TcpRepr<'static>> {
+    (
+        (1..=u16::MAX, 1..=u16::MAX),
+        tcp_control(),
+        any::<i32>().prop_map(TcpSeqNumber),
+        proptest::option::of(any::<i32>().prop_map(TcpSeqNumber)),
+        any::<u16>(),
+        proptest::option::of(0..=14u8),
+        proptest::option::of(any::<u16>()),
+        any::<bool>(),
+        vec(any::<(u32, u32)>(), 0..=3),
+    )
+        .prop_map(
+            |(
+                (src_port, dst_port),
+                control,
+                seq_number,
+                ack_number,
+                window_len,
+                window_scale,
+                max_seg_size,
+                sack_permitted,
+                ranges,
+            )| {
+                let mut sack_ranges = [None; 3];
+                if ack_number.is_some() && !sack_permitted {
+                    for (slot, range) in sack_ranges.iter_mut().zip(ranges) {
+                        *slot = Some(range);
+                    }
+                }
+                TcpRepr {
+                    src_port,
+                    dst_port,
+                    control,
+                    seq_number,
+                    ack_number,
+                    window_len,
+                    window_scale,
+                    max_seg_size,
+                    sack_permitted,
+                    sack_ranges,
+                    payload: &[],
+                }
+            },
+        )
+}
+
+/// Return a strategy generating GRE headers.
+#[cfg(feature = "proto-gre")]
+pub fn gre_repr() -> impl Strategy<Value = GreRepr> {
+    (
+        ethernet_protocol(),
+        any::<bool>(),
+        proptest::option::of(any::<u32>()),
+        proptest::option::of(any::<u32>()),
+    )
+        .prop_map(|(protocol_type, checksum, key, sequence_number)| GreRepr {
+            protocol_type,
+            checksum,
+            key,
+            sequence_number,
+        })
+}
+
+/// Return a strategy generating VXLAN headers.
+#[cfg(feature = "proto-vxlan")]
+pub fn vxlan_repr() -> impl Strategy<Value = VxlanRepr> {
+    (0..1u32 << 24).prop_map(|vni| VxlanRepr { vni })
+}
@@ -1,7 +1,8 @@
+use crate::wire::field::{read_i32, read_u16, write_i32, write_u16};
 use byteorder::{ByteOrder, NetworkEndian};
 use core::{cmp, fmt, i32, ops};
 
-use super::{Error, Result};
+use super::{Error, ParseMode, Result};
 use crate::phy::ChecksumCapabilities;
 use crate::wire::ip::checksum;
 use crate::wire::{IpAddress, IpProtocol};
@@ -100,6 +101,7 @@ mod field {
     pub const FLG_ECE: u16 = 0x040;
     pub const FLG_CWR: u16 = 0x080;
     pub const FLG_NS: u16 = 0x100;
+    pub const FLG_RESERVED: u16 = 0xe00;
 
     pub const OPT_END: u8 = 0x00;
     pub const OPT_NOP: u8 = 0x01;
@@ -158,35 +160,43 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn src_port(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::SRC_PORT])
+        read_u16(data, field::SRC_PORT)
     }
 
     /// Return the destination port field.
     #[inline]
     pub fn dst_port(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::DST_PORT])
+        read_u16(data, field::DST_PORT)
     }
 
     /// Return the sequence number field.
     #[inline]
     pub fn seq_number(&self) -> SeqNumber {
         let data = self.buffer.as_ref();
-        SeqNumber(NetworkEndian::read_i32(&data[field::SEQ_NUM]))
+        SeqNumber(read_i32(data, field::SEQ_NUM))
     }
 
     /// Return the acknowledgement number field.
     #[inline]
     pub fn ack_number(&self) -> SeqNumber {
         let data = self.buffer.as_ref();
-        SeqNumber(NetworkEndian::read_i32(&data[field::ACK_NUM]))
+        SeqNumber(read_i32(data, field::ACK_NUM))
+    }
+
+    /// Return the reserved bits of the flags field, which must be zero.
+    #[inline]
+    pub fn reserved(&self) -> u8 {
+        let data = self.buffer.as_ref();
+        let raw = read_u16(data, field::FLAGS);
+        ((raw & field::FLG_RESERVED) >> 9) as u8
     }
 
     /// Return the FIN flag.
     #[inline]
     pub fn fin(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_FIN != 0
     }
 
@@ -194,7 +204,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn syn(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_SYN != 0
     }
 
@@ -202,7 +212,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn rst(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_RST != 0
     }
 
@@ -210,7 +220,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn psh(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_PSH != 0
     }
 
@@ -218,7 +228,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn ack(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_ACK != 0
     }
 
@@ -226,7 +236,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn urg(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_URG != 0
     }
 
@@ -234,7 +244,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn ece(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_ECE != 0
     }
 
@@ -242,7 +252,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn cwr(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_CWR != 0
     }
 
@@ -250,7 +260,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn ns(&self) -> bool {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         raw & field::FLG_NS != 0
     }
 
@@ -258,7 +268,7 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn header_len(&self) -> u8 {
         let data = self.buffer.as_ref();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         ((raw >> 12) * 4) as u8
     }
 
@@ -266,21 +276,21 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn window_len(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::WIN_SIZE])
+        read_u16(data, field::WIN_SIZE)
     }
 
     /// Return the checksum field.
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Return the urgent pointer field.
     #[inline]
     pub fn urgent_at(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::URGENT])
+        read_u16(data, field::URGENT)
     }
 
     /// Return the length of the segment, in terms of sequence space.
@@ -370,184 +380,184 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_src_port(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::SRC_PORT], value)
+        write_u16(data, field::SRC_PORT, value)
     }
 
     /// Set the destination port field.
     #[inline]
     pub fn set_dst_port(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::DST_PORT], value)
+        write_u16(data, field::DST_PORT, value)
     }
 
     /// Set the sequence number field.
     #[inline]
     pub fn set_seq_number(&mut self, value: SeqNumber) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_i32(&mut data[field::SEQ_NUM], value.0)
+        write_i32(data, field::SEQ_NUM, value.0)
     }
 
     /// Set the acknowledgement number field.
     #[inline]
     pub fn set_ack_number(&mut self, value: SeqNumber) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_i32(&mut data[field::ACK_NUM], value.0)
+        write_i32(data, field::ACK_NUM, value.0)
     }
 
     /// Clear the entire flags field.
     #[inline]
     pub fn clear_flags(&mut self) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = raw & !0x0fff;
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the FIN flag.
     #[inline]
     pub fn set_fin(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_FIN
         } else {
             raw & !field::FLG_FIN
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the SYN flag.
     #[inline]
     pub fn set_syn(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_SYN
         } else {
             raw & !field::FLG_SYN
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the RST flag.
     #[inline]
     pub fn set_rst(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_RST
         } else {
             raw & !field::FLG_RST
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the PSH flag.
     #[inline]
     pub fn set_psh(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_PSH
         } else {
             raw & !field::FLG_PSH
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the ACK flag.
     #[inline]
     pub fn set_ack(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_ACK
         } else {
             raw & !field::FLG_ACK
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the URG flag.
     #[inline]
     pub fn set_urg(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_URG
         } else {
             raw & !field::FLG_URG
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the ECE flag.
     #[inline]
     pub fn set_ece(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_ECE
         } else {
             raw & !field::FLG_ECE
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the CWR flag.
     #[inline]
     pub fn set_cwr(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_CWR
         } else {
             raw & !field::FLG_CWR
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the NS flag.
     #[inline]
     pub fn set_ns(&mut self, value: bool) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = if value {
             raw | field::FLG_NS
         } else {
             raw & !field::FLG_NS
         };
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Set the header length, in octets.
     #[inline]
     pub fn set_header_len(&mut self, value: u8) {
         let data = self.buffer.as_mut();
-        let raw = NetworkEndian::read_u16(&data[field::FLAGS]);
+        let raw = read_u16(data, field::FLAGS);
         let raw = (raw & !0xf000) | ((value as u16) / 4) << 12;
-        NetworkEndian::write_u16(&mut data[field::FLAGS], raw)
+        write_u16(data, field::FLAGS, raw)
     }
 
     /// Return the window size field.
     #[inline]
     pub fn set_window_len(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::WIN_SIZE], value)
+        write_u16(data, field::WIN_SIZE, value)
     }
 
     /// Set the checksum field.
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
     }
 
     /// Set the urgent pointer field.
     #[inline]
     pub fn set_urgent_at(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::URGENT], value)
+        write_u16(data, field::URGENT, value)
     }
 
     /// Compute and fill in the header checksum.
@@ -567,6 +577,34 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         self.set_checksum(checksum)
     }
 
+    /// Set the source port field, and update the checksum incrementally instead of
+    /// recomputing it.
+    pub fn update_src_port(&mut self, value: u16) {
+        let old = self.src_port();
+        self.set_src_port(value);
+        let checksum = checksum::update_word(self.checksum(), old, value);
+        self.set_checksum(checksum)
+    }
+
+    /// Set the destination port field, and update the checksum incrementally instead of
+    /// recomputing it.
+    pub fn update_dst_port(&mut self, value: u16) {
+        let old = self.dst_port();
+        self.set_dst_port(value);
+        let checksum = checksum::update_word(self.checksum(), old, value);
+        self.set_checksum(checksum)
+    }
+
+    /// Update the checksum incrementally after an address of the IP pseudo header
+    /// was changed from `old` to `new`, e.g. by network address translation.
+    ///
+    /// # Panics
+    /// This function panics unless `old` and `new` belong to the same family.
+    pub fn update_ip_addr(&mut self, old: &IpAddress, new: &IpAddress) {
+        let checksum = checksum::update(self.checksum(), old.as_bytes(), new.as_bytes());
+        self.set_checksum(checksum)
+    }
+
     /// Return a pointer to the options.
     #[inline]
     pub fn options_mut(&mut self) -> &mut [u8] {
@@ -793,6 +831,7 @@ impl<'a> Repr<'a> {
         src_addr: &IpAddress,
         dst_addr: &IpAddress,
         checksum_caps: &ChecksumCapabilities,
+        mode: ParseMode,
     ) -> Result<Repr<'a>>
     where
         T: AsRef<[u8]> + ?Sized,
@@ -808,6 +847,9 @@ impl<'a> Repr<'a> {
         if checksum_caps.tcp.rx() && !packet.verify_checksum(src_addr, dst_addr) {
             return Err(Error);
         }
+        if mode == ParseMode::Strict && packet.reserved() != 0 {
+            return Err(Error);
+        }
 
         let control = match (packet.syn(), packet.fin(), packet.rst(), packet.psh()) {
             (false, false, false, false) => Control::None,
@@ -834,6 +876,13 @@ impl<'a> Repr<'a> {
         while !options.is_empty() {
             let (next_options, option) = TcpOption::parse(options)?;
             match option {
+                // In the strict mode, the options must be padded with zeros.
+                TcpOption::EndOfList
+                    if mode == ParseMode::Strict
+                        && next_options.iter().any(|&octet| octet != 0) =>
+                {
+                    return Err(Error)
+                }
                 TcpOption::EndOfList => break,
                 TcpOption::NoOperation => (),
                 TcpOption::MaxSegmentSize(value) => max_seg_size = Some(value),
@@ -920,6 +969,32 @@ impl<'a> Repr<'a> {
         checksum_caps: &ChecksumCapabilities,
     ) where
         T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        self.emit_with_payload(
+            packet,
+            src_addr,
+            dst_addr,
+            self.payload.len(),
+            |payload| payload.copy_from_slice(self.payload),
+            checksum_caps,
+        )
+    }
+
+    /// Emit a high-level representation into a Transmission Control Protocol packet,
+    /// with a payload of `payload_len` octets written by `emit_payload` instead of
+    /// the `payload` field.
+    ///
+    /// The checksum is computed after `emit_payload` returns.
+    pub fn emit_with_payload<T>(
+        &self,
+        packet: &mut Packet<&mut T>,
+        src_addr: &IpAddress,
+        dst_addr: &IpAddress,
+        payload_len: usize,
+        emit_payload: impl FnOnce(&mut [u8]),
+        checksum_caps: &ChecksumCapabilities,
+    ) where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
     {
         packet.set_src_port(self.src_port);
         packet.set_dst_port(self.dst_port);
@@ -959,7 +1034,7 @@ impl<'a> Repr<'a> {
             }
         }
         packet.set_urgent_at(0);
-        packet.payload_mut()[..self.payload.len()].copy_from_slice(self.payload);
+        emit_payload(&mut packet.payload_mut()[..payload_len]);
 
         if checksum_caps.tcp.tx() {
             packet.fill_checksum(src_addr, dst_addr)
@@ -1060,6 +1135,17 @@ impl<'a> fmt::Display for Repr<'a> {
         if let Some(max_seg_size) = self.max_seg_size {
             write!(f, " mss={}", max_seg_size)?;
         }
+        if f.alternate() {
+            if let Some(window_scale) = self.window_scale {
+                write!(f, " wscale={}", window_scale)?;
+            }
+            if self.sack_permitted {
+                write!(f, " sack_perm")?;
+            }
+            for (left, right) in self.sack_ranges.iter().flatten() {
+                write!(f, " sack={}-{}", left, right)?;
+            }
+        }
         Ok(())
     }
 }
@@ -1082,8 +1168,10 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
     #[cfg(feature = "proto-ipv4")]
     use crate::wire::Ipv4Address;
+    use proptest::{prop_assert_eq, proptest};
 
     #[cfg(feature = "proto-ipv4")]
     const SRC_ADDR: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
@@ -1125,6 +1213,18 @@ mod test {
         assert!(packet.verify_checksum(&SRC_ADDR.into(), &DST_ADDR.into()));
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_update_checksum() {
+        let new_addr = Ipv4Address([203, 0, 113, 7]);
+        let mut bytes = PACKET_BYTES.to_vec();
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.update_src_port(1234);
+        packet.update_dst_port(8080);
+        packet.update_ip_addr(&SRC_ADDR.into(), &new_addr.into());
+        assert!(packet.verify_checksum(&new_addr.into(), &DST_ADDR.into()));
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_construct() {
@@ -1189,6 +1289,27 @@ mod test {
         }
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_repr_display() {
+        let mut repr = packet_repr();
+        assert_eq!(
+            format!("{}", repr),
+            "TCP src=48896 dst=80 syn seq=19088743 win=291 len=4"
+        );
+        repr.window_scale = Some(7);
+        repr.sack_permitted = true;
+        repr.sack_ranges[0] = Some((100, 200));
+        assert_eq!(
+            format!("{}", repr),
+            "TCP src=48896 dst=80 syn seq=19088743 win=291 len=4"
+        );
+        assert_eq!(
+            format!("{:#}", repr),
+            "TCP src=48896 dst=80 syn seq=19088743 win=291 len=4 wscale=7 sack_perm sack=100-200"
+        );
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_parse() {
@@ -1198,11 +1319,36 @@ mod test {
             &SRC_ADDR.into(),
             &DST_ADDR.into(),
             &ChecksumCapabilities::default(),
+            ParseMode::Permissive,
         )
         .unwrap();
         assert_eq!(repr, packet_repr());
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_parse_strict() {
+        let caps = ChecksumCapabilities::default();
+        for (reserved, options, strict) in [
+            (0x00, [0x01, 0x00, 0x00, 0x00], true),
+            (0x02, [0x01, 0x00, 0x00, 0x00], false),
+            (0x00, [0x00, 0x01, 0x00, 0x00], false),
+        ] {
+            let mut bytes = vec![0; 28];
+            bytes[..20].copy_from_slice(&SYN_PACKET_BYTES[..20]);
+            bytes[12] = 0x60 | reserved;
+            bytes[20..24].copy_from_slice(&options);
+            bytes[24..].copy_from_slice(&PAYLOAD_BYTES);
+            let mut packet = Packet::new_unchecked(&mut bytes);
+            packet.fill_checksum(&SRC_ADDR.into(), &DST_ADDR.into());
+            let packet = Packet::new_checked(&*packet.into_inner()).unwrap();
+            let parse =
+                |mode| Repr::parse(&packet, &SRC_ADDR.into(), &DST_ADDR.into(), &caps, mode);
+            assert_eq!(parse(ParseMode::Permissive), Ok(packet_repr()));
+            assert_eq!(parse(ParseMode::Strict).is_ok(), strict);
+        }
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_emit() {
@@ -1218,6 +1364,26 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &SYN_PACKET_BYTES[..]);
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_emit_with_payload() {
+        let repr = Repr {
+            payload: &[],
+            ..packet_repr()
+        };
+        let mut bytes = vec![0xa5; repr.header_len() + PAYLOAD_BYTES.len()];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        repr.emit_with_payload(
+            &mut packet,
+            &SRC_ADDR.into(),
+            &DST_ADDR.into(),
+            PAYLOAD_BYTES.len(),
+            |payload| payload.copy_from_slice(&PAYLOAD_BYTES),
+            &ChecksumCapabilities::default(),
+        );
+        assert_eq!(&packet.into_inner()[..], &SYN_PACKET_BYTES[..]);
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_header_len_multiple_of_4() {
@@ -1282,4 +1448,43 @@ mod test {
         assert_eq!(TcpOption::parse(&[0x2, 0x02]), Err(Error));
         assert_eq!(TcpOption::parse(&[0x3, 0x02]), Err(Error));
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(
+            repr in strategy::tcp_repr(),
+            (src_addr, dst_addr) in strategy::ip_address_pair(),
+            payload in strategy::packet_buffer(0..64),
+        ) {
+            let repr = Repr {
+                payload: &payload,
+                ..repr
+            };
+            let mut bytes = vec![0; repr.buffer_len()];
+            let mut packet = Packet::new_unchecked(&mut bytes[..]);
+            repr.emit(&mut packet, &src_addr, &dst_addr, &ChecksumCapabilities::default());
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            let parsed = Repr::parse(
+                &packet,
+                &src_addr,
+                &dst_addr,
+                &ChecksumCapabilities::default(),
+                ParseMode::Strict,
+            );
+            prop_assert_eq!(parsed.unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(
+            bytes in strategy::packet_buffer(0..80),
+            (src_addr, dst_addr) in strategy::ip_address_pair(),
+        ) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                for mode in [ParseMode::Permissive, ParseMode::Strict] {
+                    let caps = ChecksumCapabilities::ignored();
+                    let _ = Repr::parse(&packet, &src_addr, &dst_addr, &caps, mode);
+                }
+            }
+        }
+    }
 }
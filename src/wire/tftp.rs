@@ -0,0 +1,515 @@
+use crate::wire::field::{read_u16, write_u16};
+use core::fmt;
+
+use super::{Error, Result};
+
+/// The UDP port TFTP servers listen on for requests.
+pub const SERVER_PORT: u16 = 69;
+
+/// The maximum amount of file data carried by a single DATA packet.
+///
+/// A DATA packet shorter than this signals the end of the transfer.
+pub const BLOCK_SIZE: usize = 512;
+
+enum_with_unknown! {
+    /// TFTP opcode.
+    pub enum OpCode(u16) {
+        Read  = 1,
+        Write = 2,
+        Data  = 3,
+        Ack   = 4,
+        Error = 5
+    }
+}
+
+enum_with_unknown! {
+    /// TFTP error code.
+    pub enum ErrorCode(u16) {
+        Undefined         = 0,
+        FileNotFound      = 1,
+        AccessViolation   = 2,
+        DiskFull          = 3,
+        IllegalOperation  = 4,
+        UnknownTransferId = 5,
+        FileExists        = 6,
+        NoSuchUser        = 7
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ErrorCode::Undefined => write!(f, "undefined"),
+            ErrorCode::FileNotFound => write!(f, "file not found"),
+            ErrorCode::AccessViolation => write!(f, "access violation"),
+            ErrorCode::DiskFull => write!(f, "disk full"),
+            ErrorCode::IllegalOperation => write!(f, "illegal operation"),
+            ErrorCode::UnknownTransferId => write!(f, "unknown transfer id"),
+            ErrorCode::FileExists => write!(f, "file exists"),
+            ErrorCode::NoSuchUser => write!(f, "no such user"),
+            ErrorCode::Unknown(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+/// Transfer mode of a read or write request.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mode {
+    NetAscii,
+    Octet,
+    Mail,
+}
+
+impl Mode {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            Mode::NetAscii => "netascii",
+            Mode::Octet => "octet",
+            Mode::Mail => "mail",
+        }
+    }
+
+    fn parse(data: &[u8]) -> Result<Mode> {
+        // Modes are case insensitive.
+        if data.eq_ignore_ascii_case(b"netascii") {
+            Ok(Mode::NetAscii)
+        } else if data.eq_ignore_ascii_case(b"octet") {
+            Ok(Mode::Octet)
+        } else if data.eq_ignore_ascii_case(b"mail") {
+            Ok(Mode::Mail)
+        } else {
+            Err(Error)
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A read/write wrapper around a TFTP packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of TFTP packets
+//
+//  RRQ/WRQ: | opcode | filename | 0 | mode | 0 |
+//  DATA:    | opcode | block #  | data         |
+//  ACK:     | opcode | block #  |
+//  ERROR:   | opcode | code     | message | 0 |
+//
+// See RFC 1350 § 5 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const OPCODE: Field = 0..2;
+    pub const BLOCK_NUM: Field = 2..4;
+    pub const ERROR_CODE: Field = 2..4;
+    pub const REQUEST: Rest = 2..;
+    pub const DATA: Rest = 4..;
+    pub const ERROR_MSG: Rest = 4..;
+}
+
+/// Split a NUL-terminated string off the front of `data`.
+fn split_cstr(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    match data.iter().position(|b| *b == 0) {
+        Some(pos) => Ok((&data[..pos], &data[pos + 1..])),
+        None => Err(Error),
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with TFTP packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::OPCODE.end {
+            return Err(Error);
+        }
+        match self.opcode() {
+            OpCode::Data | OpCode::Ack | OpCode::Error if len < field::BLOCK_NUM.end => Err(Error),
+            _ => Ok(()),
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the opcode field.
+    #[inline]
+    pub fn opcode(&self) -> OpCode {
+        let data = self.buffer.as_ref();
+        OpCode::from(read_u16(data, field::OPCODE))
+    }
+
+    /// Return the block number field of a DATA or ACK packet.
+    #[inline]
+    pub fn block_number(&self) -> u16 {
+        let data = self.buffer.as_ref();
+        read_u16(data, field::BLOCK_NUM)
+    }
+
+    /// Return the error code field of an ERROR packet.
+    #[inline]
+    pub fn error_code(&self) -> ErrorCode {
+        let data = self.buffer.as_ref();
+        ErrorCode::from(read_u16(data, field::ERROR_CODE))
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return the filename of a RRQ or WRQ packet.
+    #[inline]
+    pub fn filename(&self) -> Result<&'a [u8]> {
+        let data = self.buffer.as_ref();
+        split_cstr(&data[field::REQUEST]).map(|(filename, _)| filename)
+    }
+
+    /// Return the mode of a RRQ or WRQ packet, as sent.
+    #[inline]
+    pub fn mode(&self) -> Result<&'a [u8]> {
+        let data = self.buffer.as_ref();
+        let (_, rest) = split_cstr(&data[field::REQUEST])?;
+        split_cstr(rest).map(|(mode, _)| mode)
+    }
+
+    /// Return the data of a DATA packet.
+    #[inline]
+    pub fn data(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::DATA]
+    }
+
+    /// Return the message of an ERROR packet.
+    #[inline]
+    pub fn error_msg(&self) -> Result<&'a [u8]> {
+        let data = self.buffer.as_ref();
+        split_cstr(&data[field::ERROR_MSG]).map(|(msg, _)| msg)
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Set the opcode field.
+    #[inline]
+    pub fn set_opcode(&mut self, value: OpCode) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::OPCODE, value.into())
+    }
+
+    /// Set the block number field of a DATA or ACK packet.
+    #[inline]
+    pub fn set_block_number(&mut self, value: u16) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::BLOCK_NUM, value)
+    }
+
+    /// Set the error code field of an ERROR packet.
+    #[inline]
+    pub fn set_error_code(&mut self, value: ErrorCode) {
+        let data = self.buffer.as_mut();
+        write_u16(data, field::ERROR_CODE, value.into())
+    }
+
+    /// Return a mutable pointer to the data of a DATA packet.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::DATA]
+    }
+
+    /// Write the NUL-terminated strings of a RRQ, WRQ or ERROR packet, starting at `offset`.
+    fn set_cstrs(&mut self, mut offset: usize, values: &[&[u8]]) {
+        let data = self.buffer.as_mut();
+        for value in values {
+            data[offset..offset + value.len()].copy_from_slice(value);
+            data[offset + value.len()] = 0;
+            offset += value.len() + 1;
+        }
+    }
+}
+
+/// A high-level representation of a TFTP packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Repr<'a> {
+    ReadRequest { filename: &'a str, mode: Mode },
+    WriteRequest { filename: &'a str, mode: Mode },
+    Data { block_num: u16, data: &'a [u8] },
+    Ack { block_num: u16 },
+    Error { code: ErrorCode, msg: &'a str },
+}
+
+impl<'a> Repr<'a> {
+    /// Parse a TFTP packet and return a high-level representation.
+    ///
+    /// Options (RFC 2347) following the mode of a request are ignored.
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        match packet.opcode() {
+            OpCode::Read | OpCode::Write => {
+                let filename = core::str::from_utf8(packet.filename()?).map_err(|_| Error)?;
+                if filename.is_empty() {
+                    return Err(Error);
+                }
+                let mode = Mode::parse(packet.mode()?)?;
+                if packet.opcode() == OpCode::Read {
+                    Ok(Repr::ReadRequest { filename, mode })
+                } else {
+                    Ok(Repr::WriteRequest { filename, mode })
+                }
+            }
+            OpCode::Data => {
+                let data = packet.data();
+                if data.len() > BLOCK_SIZE {
+                    return Err(Error);
+                }
+                Ok(Repr::Data {
+                    block_num: packet.block_number(),
+                    data,
+                })
+            }
+            OpCode::Ack => Ok(Repr::Ack {
+                block_num: packet.block_number(),
+            }),
+            OpCode::Error => Ok(Repr::Error {
+                code: packet.error_code(),
+                msg: core::str::from_utf8(packet.error_msg()?).map_err(|_| Error)?,
+            }),
+            OpCode::Unknown(_) => Err(Error),
+        }
+    }
+
+    /// Return the length of a packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        field::OPCODE.end
+            + match self {
+                Repr::ReadRequest { filename, mode } | Repr::WriteRequest { filename, mode } => {
+                    filename.len() + 1 + mode.as_str().len() + 1
+                }
+                Repr::Data { data, .. } => 2 + data.len(),
+                Repr::Ack { .. } => 2,
+                Repr::Error { msg, .. } => 2 + msg.len() + 1,
+            }
+    }
+
+    /// Emit a high-level representation into a TFTP packet.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        match *self {
+            Repr::ReadRequest { filename, mode } => {
+                packet.set_opcode(OpCode::Read);
+                packet.set_cstrs(
+                    field::REQUEST.start,
+                    &[filename.as_bytes(), mode.as_str().as_bytes()],
+                );
+            }
+            Repr::WriteRequest { filename, mode } => {
+                packet.set_opcode(OpCode::Write);
+                packet.set_cstrs(
+                    field::REQUEST.start,
+                    &[filename.as_bytes(), mode.as_str().as_bytes()],
+                );
+            }
+            Repr::Data { block_num, data } => {
+                packet.set_opcode(OpCode::Data);
+                packet.set_block_number(block_num);
+                packet.data_mut()[..data.len()].copy_from_slice(data);
+            }
+            Repr::Ack { block_num } => {
+                packet.set_opcode(OpCode::Ack);
+                packet.set_block_number(block_num);
+            }
+            Repr::Error { code, msg } => {
+                packet.set_opcode(OpCode::Error);
+                packet.set_error_code(code);
+                packet.set_cstrs(field::ERROR_MSG.start, &[msg.as_bytes()]);
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Repr::ReadRequest { filename, mode } => {
+                write!(f, "TFTP RRQ file={} mode={}", filename, mode)
+            }
+            Repr::WriteRequest { filename, mode } => {
+                write!(f, "TFTP WRQ file={} mode={}", filename, mode)
+            }
+            Repr::Data { block_num, data } => {
+                write!(f, "TFTP DATA block={} len={}", block_num, data.len())
+            }
+            Repr::Ack { block_num } => write!(f, "TFTP ACK block={}", block_num),
+            Repr::Error { code, msg } => write!(f, "TFTP ERROR code={} msg={:?}", code, msg),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "TFTP ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static RRQ_BYTES: [u8; 20] = [
+        0x00, 0x01, b'b', b'o', b'o', b't', b'.', b'b', b'i', b'n', 0x00, b'o', b'c', b't', b'e',
+        b't', 0x00, b'x', 0x00, 0x00,
+    ];
+
+    static DATA_BYTES: [u8; 7] = [0x00, 0x03, 0x01, 0x02, 0xaa, 0xbb, 0xcc];
+
+    static ERROR_BYTES: [u8; 9] = [0x00, 0x05, 0x00, 0x01, b'n', b'o', b'p', b'e', 0x00];
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&DATA_BYTES[..]).unwrap();
+        assert_eq!(packet.opcode(), OpCode::Data);
+        assert_eq!(packet.block_number(), 0x0102);
+        assert_eq!(packet.data(), &[0xaa, 0xbb, 0xcc]);
+
+        let packet = Packet::new_checked(&RRQ_BYTES[..]).unwrap();
+        assert_eq!(packet.opcode(), OpCode::Read);
+        assert_eq!(packet.filename(), Ok(&b"boot.bin"[..]));
+        assert_eq!(packet.mode(), Ok(&b"octet"[..]));
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&DATA_BYTES[..1]), Err(Error));
+        assert_eq!(Packet::new_checked(&DATA_BYTES[..3]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&RRQ_BYTES[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr::ReadRequest {
+                filename: "boot.bin",
+                mode: Mode::Octet
+            })
+        );
+
+        let packet = Packet::new_unchecked(&DATA_BYTES[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr::Data {
+                block_num: 0x0102,
+                data: &[0xaa, 0xbb, 0xcc]
+            })
+        );
+
+        let packet = Packet::new_unchecked(&ERROR_BYTES[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr::Error {
+                code: ErrorCode::FileNotFound,
+                msg: "nope"
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_mode_case() {
+        let mut bytes = RRQ_BYTES;
+        bytes[11..16].copy_from_slice(b"OCTet");
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert!(matches!(
+            Repr::parse(&packet),
+            Ok(Repr::ReadRequest {
+                mode: Mode::Octet,
+                ..
+            })
+        ));
+
+        bytes[11..16].copy_from_slice(b"octex");
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(Repr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_parse_unterminated() {
+        let packet = Packet::new_unchecked(&RRQ_BYTES[..15]);
+        assert_eq!(Repr::parse(&packet), Err(Error));
+        let packet = Packet::new_unchecked(&ERROR_BYTES[..8]);
+        assert_eq!(Repr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = Repr::ReadRequest {
+            filename: "boot.bin",
+            mode: Mode::Octet,
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &RRQ_BYTES[..17]);
+
+        let repr = Repr::Data {
+            block_num: 0x0102,
+            data: &[0xaa, 0xbb, 0xcc],
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &DATA_BYTES[..]);
+
+        let repr = Repr::Error {
+            code: ErrorCode::FileNotFound,
+            msg: "nope",
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(&bytes[..], &ERROR_BYTES[..]);
+    }
+}
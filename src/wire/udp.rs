@@ -1,4 +1,4 @@
-use byteorder::{ByteOrder, NetworkEndian};
+use crate::wire::field::{read_u16, write_u16};
 use core::fmt;
 
 use super::{Error, Result};
@@ -78,28 +78,28 @@ impl<T: AsRef<[u8]>> Packet<T> {
     #[inline]
     pub fn src_port(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::SRC_PORT])
+        read_u16(data, field::SRC_PORT)
     }
 
     /// Return the destination port field.
     #[inline]
     pub fn dst_port(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::DST_PORT])
+        read_u16(data, field::DST_PORT)
     }
 
     /// Return the length field.
     #[inline]
     pub fn len(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::LENGTH])
+        read_u16(data, field::LENGTH)
     }
 
     /// Return the checksum field.
     #[inline]
     pub fn checksum(&self) -> u16 {
         let data = self.buffer.as_ref();
-        NetworkEndian::read_u16(&data[field::CHECKSUM])
+        read_u16(data, field::CHECKSUM)
     }
 
     /// Validate the packet checksum.
@@ -138,28 +138,28 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     #[inline]
     pub fn set_src_port(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::SRC_PORT], value)
+        write_u16(data, field::SRC_PORT, value)
     }
 
     /// Set the destination port field.
     #[inline]
     pub fn set_dst_port(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::DST_PORT], value)
+        write_u16(data, field::DST_PORT, value)
     }
 
     /// Set the length field.
     #[inline]
     pub fn set_len(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::LENGTH], value)
+        write_u16(data, field::LENGTH, value)
     }
 
     /// Set the checksum field.
     #[inline]
     pub fn set_checksum(&mut self, value: u16) {
         let data = self.buffer.as_mut();
-        NetworkEndian::write_u16(&mut data[field::CHECKSUM], value)
+        write_u16(data, field::CHECKSUM, value)
     }
 
     /// Compute and fill in the header checksum.
@@ -183,6 +183,40 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
         self.set_checksum(if checksum == 0 { 0xffff } else { checksum })
     }
 
+    /// Set the source port field, and update the checksum incrementally instead of
+    /// recomputing it. A checksum of zero, meaning that there is none, is left as is.
+    pub fn update_src_port(&mut self, value: u16) {
+        let old = self.src_port();
+        self.set_src_port(value);
+        self.update_checksum(&old.to_be_bytes(), &value.to_be_bytes())
+    }
+
+    /// Set the destination port field, and update the checksum incrementally instead of
+    /// recomputing it. A checksum of zero, meaning that there is none, is left as is.
+    pub fn update_dst_port(&mut self, value: u16) {
+        let old = self.dst_port();
+        self.set_dst_port(value);
+        self.update_checksum(&old.to_be_bytes(), &value.to_be_bytes())
+    }
+
+    /// Update the checksum incrementally after an address of the IP pseudo header
+    /// was changed from `old` to `new`, e.g. by network address translation.
+    /// A checksum of zero, meaning that there is none, is left as is.
+    ///
+    /// # Panics
+    /// This function panics unless `old` and `new` belong to the same family.
+    pub fn update_ip_addr(&mut self, old: &IpAddress, new: &IpAddress) {
+        self.update_checksum(old.as_bytes(), new.as_bytes())
+    }
+
+    fn update_checksum(&mut self, old: &[u8], new: &[u8]) {
+        if self.checksum() == 0 {
+            return;
+        }
+        let checksum = checksum::update(self.checksum(), old, new);
+        self.set_checksum(if checksum == 0 { 0xffff } else { checksum })
+    }
+
     /// Return a mutable pointer to the payload.
     #[inline]
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -242,6 +276,12 @@ impl Repr {
         HEADER_LEN
     }
 
+    /// Return the length of a packet with a payload of `payload_len` octets that will be
+    /// emitted from this high-level representation.
+    pub fn buffer_len(&self, payload_len: usize) -> usize {
+        HEADER_LEN + payload_len
+    }
+
     /// Emit a high-level representation into an User Datagram Protocol packet.
     pub fn emit<T: ?Sized>(
         &self,
@@ -256,7 +296,7 @@ impl Repr {
     {
         packet.set_src_port(self.src_port);
         packet.set_dst_port(self.dst_port);
-        packet.set_len((HEADER_LEN + payload_len) as u16);
+        packet.set_len(self.buffer_len(payload_len) as u16);
         emit_payload(packet.payload_mut());
 
         if checksum_caps.udp.tx() {
@@ -306,8 +346,10 @@ impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::wire::strategy;
     #[cfg(feature = "proto-ipv4")]
     use crate::wire::Ipv4Address;
+    use proptest::{prop_assert_eq, proptest};
 
     #[cfg(feature = "proto-ipv4")]
     const SRC_ADDR: Ipv4Address = Ipv4Address([192, 168, 1, 1]);
@@ -339,6 +381,24 @@ mod test {
         assert!(packet.verify_checksum(&SRC_ADDR.into(), &DST_ADDR.into()));
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_update_checksum() {
+        let new_addr = Ipv4Address([203, 0, 113, 7]);
+        let mut bytes = PACKET_BYTES.to_vec();
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.update_src_port(1234);
+        packet.update_dst_port(5353);
+        packet.update_ip_addr(&DST_ADDR.into(), &new_addr.into());
+        assert!(packet.verify_checksum(&SRC_ADDR.into(), &new_addr.into()));
+
+        // A missing checksum stays missing.
+        let mut bytes = NO_CHECKSUM_PACKET.to_vec();
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        packet.update_src_port(1234);
+        assert_eq!(packet.checksum(), 0);
+    }
+
     #[test]
     #[cfg(feature = "proto-ipv4")]
     fn test_construct() {
@@ -353,6 +413,18 @@ mod test {
         assert_eq!(&packet.into_inner()[..], &PACKET_BYTES[..]);
     }
 
+    #[test]
+    #[cfg(feature = "proto-ipv4")]
+    fn test_overlong() {
+        let mut bytes = vec![];
+        bytes.extend(&PACKET_BYTES[..]);
+        bytes.extend(&[0xa5; 6]);
+
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert_eq!(packet.payload(), &PAYLOAD_BYTES[..]);
+        assert!(packet.verify_checksum(&SRC_ADDR.into(), &DST_ADDR.into()));
+    }
+
     #[test]
     fn test_impossible_len() {
         let mut bytes = vec![0; 12];
@@ -425,4 +497,43 @@ mod test {
         .unwrap();
         assert_eq!(repr, packet_repr());
     }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(
+            repr in strategy::udp_repr(),
+            (src_addr, dst_addr) in strategy::ip_address_pair(),
+            payload in strategy::packet_buffer(0..64),
+        ) {
+            let mut bytes = vec![0; repr.buffer_len(payload.len())];
+            let mut packet = Packet::new_unchecked(&mut bytes[..]);
+            repr.emit(
+                &mut packet,
+                &src_addr,
+                &dst_addr,
+                payload.len(),
+                |buf| buf.copy_from_slice(&payload),
+                &ChecksumCapabilities::default(),
+            );
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            let parsed = Repr::parse(
+                &packet,
+                &src_addr,
+                &dst_addr,
+                &ChecksumCapabilities::default(),
+            );
+            prop_assert_eq!(parsed.unwrap(), repr);
+            prop_assert_eq!(packet.payload(), &payload[..]);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(
+            bytes in strategy::packet_buffer(0..32),
+            (src_addr, dst_addr) in strategy::ip_address_pair(),
+        ) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&packet, &src_addr, &dst_addr, &ChecksumCapabilities::ignored());
+            }
+        }
+    }
 }
@@ -0,0 +1,263 @@
+use crate::wire::field::{read_u24, write_u24};
+use core::fmt;
+
+use super::{Error, Result};
+
+/// The IANA-assigned UDP port for VXLAN.
+pub const UDP_PORT: u16 = 4789;
+
+/// A read/write wrapper around a VXLAN packet buffer.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of a VXLAN header
+//
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |R|R|R|R|I|R|R|R|            Reserved                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                VXLAN Network Identifier (VNI) |   Reserved    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// See RFC 7348 § 5 for details.
+mod field {
+    use crate::wire::field::*;
+
+    pub const FLAGS: usize = 0;
+    pub const VNI: Field = 4..7;
+    pub const PAYLOAD: Rest = 8..;
+
+    pub const FLG_VNI: u8 = 0x08;
+}
+
+/// The length of a VXLAN header.
+pub const HEADER_LEN: usize = field::PAYLOAD.start;
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with VXLAN packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < HEADER_LEN {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return the "VNI valid" flag.
+    #[inline]
+    pub fn vni_valid(&self) -> bool {
+        let data = self.buffer.as_ref();
+        data[field::FLAGS] & field::FLG_VNI != 0
+    }
+
+    /// Return the VXLAN network identifier.
+    #[inline]
+    pub fn vni(&self) -> u32 {
+        let data = self.buffer.as_ref();
+        read_u24(data, field::VNI)
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return a pointer to the payload, i.e. the encapsulated Ethernet frame.
+    #[inline]
+    pub fn payload(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Clear the entire header, including the reserved fields.
+    #[inline]
+    pub fn clear_header(&mut self) {
+        let data = self.buffer.as_mut();
+        data[..HEADER_LEN].fill(0)
+    }
+
+    /// Set the "VNI valid" flag.
+    #[inline]
+    pub fn set_vni_valid(&mut self, value: bool) {
+        let data = self.buffer.as_mut();
+        let raw = data[field::FLAGS];
+        data[field::FLAGS] = if value {
+            raw | field::FLG_VNI
+        } else {
+            raw & !field::FLG_VNI
+        }
+    }
+
+    /// Set the VXLAN network identifier.
+    ///
+    /// # Panics
+    /// This function panics if `value` does not fit in 24 bits.
+    #[inline]
+    pub fn set_vni(&mut self, value: u32) {
+        let data = self.buffer.as_mut();
+        write_u24(data, field::VNI, value)
+    }
+
+    /// Return a mutable pointer to the payload.
+    #[inline]
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PAYLOAD]
+    }
+}
+
+/// A high-level representation of a VXLAN header.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr {
+    /// VXLAN network identifier; only the lower 24 bits are used.
+    pub vni: u32,
+}
+
+impl Repr {
+    /// Parse a VXLAN packet and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if !packet.vni_valid() {
+            return Err(Error);
+        }
+        Ok(Repr { vni: packet.vni() })
+    }
+
+    /// Return the length of a header that will be emitted from this high-level representation.
+    pub const fn buffer_len(&self) -> usize {
+        HEADER_LEN
+    }
+
+    /// Emit a high-level representation into a VXLAN header.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.clear_header();
+        packet.set_vni_valid(true);
+        packet.set_vni(self.vni);
+    }
+}
+
+impl fmt::Display for Repr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VXLAN vni={}", self.vni)
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "VXLAN ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        let packet = match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => return write!(f, "{}({})", indent, err),
+            Ok(packet) => packet,
+        };
+        write!(f, "{}{}", indent, packet)?;
+        indent.increase(f)?;
+        super::EthernetFrame::<&[u8]>::pretty_print(&packet.payload(), f, indent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::wire::strategy;
+    use proptest::{prop_assert_eq, proptest};
+
+    static PACKET_BYTES: [u8; 12] = [
+        0x08, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x00, 0xaa, 0x00, 0x00, 0xff,
+    ];
+
+    #[test]
+    fn test_deconstruct() {
+        let packet = Packet::new_checked(&PACKET_BYTES[..]).unwrap();
+        assert!(packet.vni_valid());
+        assert_eq!(packet.vni(), 0x123456);
+        assert_eq!(packet.payload(), &[0xaa, 0x00, 0x00, 0xff]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        assert_eq!(Packet::new_checked(&PACKET_BYTES[..7]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let packet = Packet::new_unchecked(&PACKET_BYTES[..]);
+        assert_eq!(Repr::parse(&packet), Ok(Repr { vni: 0x123456 }));
+
+        let mut bytes = PACKET_BYTES;
+        bytes[0] = 0;
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(Repr::parse(&packet), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let mut bytes = vec![0xa5; 12];
+        let mut packet = Packet::new_unchecked(&mut bytes);
+        Repr { vni: 0x123456 }.emit(&mut packet);
+        packet
+            .payload_mut()
+            .copy_from_slice(&[0xaa, 0x00, 0x00, 0xff]);
+        assert_eq!(&bytes[..], &PACKET_BYTES[..]);
+    }
+
+    proptest! {
+        #[test]
+        fn test_repr_roundtrip(repr in strategy::vxlan_repr()) {
+            let mut bytes = vec![0; repr.buffer_len()];
+            repr.emit(&mut Packet::new_unchecked(&mut bytes[..]));
+            let packet = Packet::new_checked(&bytes[..]).unwrap();
+            prop_assert_eq!(Repr::parse(&packet).unwrap(), repr);
+        }
+
+        #[test]
+        fn test_parse_arbitrary(bytes in strategy::packet_buffer(0..16)) {
+            if let Ok(packet) = Packet::new_checked(&bytes[..]) {
+                let _ = Repr::parse(&packet);
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use core::fmt;
+
+use super::{Error, Result};
+use crate::wire::EthernetAddress;
+
+/// The UDP port magic packets are conventionally sent to.
+pub const UDP_PORT: u16 = 9;
+
+/// A read/write wrapper around a Wake-on-LAN magic packet.
+///
+/// The same format is used both as the payload of an Ethernet frame with the
+/// Wake-on-LAN EtherType, and as the payload of a UDP datagram.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+// Format of a magic packet
+//
+// +--------------------+-----------------------------+---------------------+
+// | 6 octets of 0xff   | 16 repetitions of the MAC   | password (optional) |
+// +--------------------+-----------------------------+---------------------+
+mod field {
+    use crate::wire::field::*;
+
+    pub const SYNC: Field = 0..6;
+    pub const REPETITIONS: usize = 16;
+    pub const TARGETS: Field = SYNC.end..SYNC.end + REPETITIONS * 6;
+    pub const PASSWORD: Rest = TARGETS.end..;
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Imbue a raw octet buffer with magic packet structure.
+    pub fn new_unchecked(buffer: T) -> Packet<T> {
+        Packet { buffer }
+    }
+
+    /// Shorthand for a combination of [new_unchecked] and [check_len].
+    ///
+    /// [new_unchecked]: #method.new_unchecked
+    /// [check_len]: #method.check_len
+    pub fn new_checked(buffer: T) -> Result<Packet<T>> {
+        let packet = Self::new_unchecked(buffer);
+        packet.check_len()?;
+        Ok(packet)
+    }
+
+    /// Ensure that no accessor method will panic if called.
+    /// Returns `Err(Error)` if the buffer is too short.
+    pub fn check_len(&self) -> Result<()> {
+        let len = self.buffer.as_ref().len();
+        if len < field::TARGETS.end {
+            Err(Error)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Consume the packet, returning the underlying buffer.
+    pub fn into_inner(self) -> T {
+        self.buffer
+    }
+
+    /// Return whether the synchronization stream consists of six `0xff` octets.
+    #[inline]
+    pub fn sync_valid(&self) -> bool {
+        let data = self.buffer.as_ref();
+        data[field::SYNC].iter().all(|&b| b == 0xff)
+    }
+
+    /// Return the target hardware address, as given by its first repetition.
+    #[inline]
+    pub fn target_addr(&self) -> EthernetAddress {
+        let data = self.buffer.as_ref();
+        EthernetAddress::from_bytes(&data[field::TARGETS][..6])
+    }
+
+    /// Return whether all sixteen repetitions of the target hardware address match.
+    #[inline]
+    pub fn targets_valid(&self) -> bool {
+        let data = self.buffer.as_ref();
+        let targets = &data[field::TARGETS];
+        targets.chunks(6).all(|target| target == &targets[..6])
+    }
+}
+
+impl<'a, T: AsRef<[u8]> + ?Sized> Packet<&'a T> {
+    /// Return the SecureOn password following the target addresses, if any.
+    #[inline]
+    pub fn password(&self) -> &'a [u8] {
+        let data = self.buffer.as_ref();
+        &data[field::PASSWORD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    /// Fill the synchronization stream with `0xff` octets.
+    #[inline]
+    pub fn fill_sync(&mut self) {
+        let data = self.buffer.as_mut();
+        data[field::SYNC].fill(0xff)
+    }
+
+    /// Write all sixteen repetitions of the target hardware address.
+    #[inline]
+    pub fn set_target_addr(&mut self, value: EthernetAddress) {
+        let data = self.buffer.as_mut();
+        for target in data[field::TARGETS].chunks_mut(6) {
+            target.copy_from_slice(value.as_bytes())
+        }
+    }
+
+    /// Return a mutable pointer to the SecureOn password.
+    #[inline]
+    pub fn password_mut(&mut self) -> &mut [u8] {
+        let data = self.buffer.as_mut();
+        &mut data[field::PASSWORD]
+    }
+}
+
+/// A high-level representation of a Wake-on-LAN magic packet.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Repr<'a> {
+    /// Hardware address of the host to wake.
+    pub target_addr: EthernetAddress,
+    /// SecureOn password; either four or six octets long.
+    pub password: Option<&'a [u8]>,
+}
+
+impl<'a> Repr<'a> {
+    /// Parse a magic packet and return a high-level representation.
+    pub fn parse<T>(packet: &Packet<&'a T>) -> Result<Repr<'a>>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        packet.check_len()?;
+        if !packet.sync_valid() || !packet.targets_valid() {
+            return Err(Error);
+        }
+        let password = match packet.password().len() {
+            0 => None,
+            4 | 6 => Some(packet.password()),
+            _ => return Err(Error),
+        };
+        Ok(Repr {
+            target_addr: packet.target_addr(),
+            password,
+        })
+    }
+
+    /// Return the length of a magic packet that will be emitted from this high-level representation.
+    pub fn buffer_len(&self) -> usize {
+        field::TARGETS.end + self.password.map_or(0, |password| password.len())
+    }
+
+    /// Emit a high-level representation into a magic packet.
+    pub fn emit<T>(&self, packet: &mut Packet<&mut T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]> + ?Sized,
+    {
+        packet.fill_sync();
+        packet.set_target_addr(self.target_addr);
+        if let Some(password) = self.password {
+            packet.password_mut().copy_from_slice(password);
+        }
+    }
+}
+
+impl<'a> fmt::Display for Repr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WoL target={}", self.target_addr)?;
+        if self.password.is_some() {
+            write!(f, " password")?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]> + ?Sized> fmt::Display for Packet<&T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Repr::parse(self) {
+            Ok(repr) => write!(f, "{}", repr),
+            Err(err) => write!(f, "WoL ({})", err),
+        }
+    }
+}
+
+use crate::wire::pretty_print::{PrettyIndent, PrettyPrint};
+
+impl<T: AsRef<[u8]>> PrettyPrint for Packet<T> {
+    fn pretty_print(
+        buffer: &dyn AsRef<[u8]>,
+        f: &mut fmt::Formatter,
+        indent: &mut PrettyIndent,
+    ) -> fmt::Result {
+        match Packet::new_checked(buffer.as_ref()) {
+            Err(err) => write!(f, "{}({})", indent, err),
+            Ok(packet) => write!(f, "{}{}", indent, packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TARGET: EthernetAddress = EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
+
+    fn magic_bytes(password: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0xff; 6];
+        for _ in 0..16 {
+            bytes.extend_from_slice(TARGET.as_bytes());
+        }
+        bytes.extend_from_slice(password);
+        bytes
+    }
+
+    #[test]
+    fn test_deconstruct() {
+        let bytes = magic_bytes(&[]);
+        let packet = Packet::new_checked(&bytes[..]).unwrap();
+        assert!(packet.sync_valid());
+        assert!(packet.targets_valid());
+        assert_eq!(packet.target_addr(), TARGET);
+        assert_eq!(packet.password(), &[]);
+    }
+
+    #[test]
+    fn test_check_len() {
+        let bytes = magic_bytes(&[]);
+        assert_eq!(Packet::new_checked(&bytes[..101]), Err(Error));
+    }
+
+    #[test]
+    fn test_parse() {
+        let bytes = magic_bytes(&[]);
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr {
+                target_addr: TARGET,
+                password: None
+            })
+        );
+
+        let bytes = magic_bytes(&[1, 2, 3, 4, 5, 6]);
+        let packet = Packet::new_unchecked(&bytes[..]);
+        assert_eq!(
+            Repr::parse(&packet),
+            Ok(Repr {
+                target_addr: TARGET,
+                password: Some(&[1, 2, 3, 4, 5, 6])
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut bytes = magic_bytes(&[]);
+        bytes[0] = 0;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let mut bytes = magic_bytes(&[]);
+        bytes[101] = 0;
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+
+        let bytes = magic_bytes(&[1, 2]);
+        assert_eq!(Repr::parse(&Packet::new_unchecked(&bytes[..])), Err(Error));
+    }
+
+    #[test]
+    fn test_emit() {
+        let repr = Repr {
+            target_addr: TARGET,
+            password: Some(&[1, 2, 3, 4]),
+        };
+        let mut bytes = vec![0xa5; repr.buffer_len()];
+        repr.emit(&mut Packet::new_unchecked(&mut bytes));
+        assert_eq!(bytes, magic_bytes(&[1, 2, 3, 4]));
+    }
+}
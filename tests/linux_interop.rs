@@ -0,0 +1,269 @@
+//! Interoperability tests against the network stack of the Linux kernel.
+//!
+//! smoltcp runs on a TAP interface, and talks to sockets of the kernel stack, which sees the
+//! other end of the interface. The tests need the `tap0` interface to be set up as described
+//! in the README:
+//!
+//! ```sh
+//! sudo ip tuntap add name tap0 mode tap user $USER
+//! sudo ip link set tap0 up
+//! sudo ip addr add 192.168.69.100/24 dev tap0
+//! ```
+//!
+//! They are ignored by default, and must be run one at a time, as they share the interface:
+//!
+//! ```sh
+//! cargo test --test linux_interop -- --ignored --test-threads=1
+//! ```
+//!
+//! The retransmission test adds a `netem` queueing discipline to `tap0` to drop packets,
+//! which needs the `CAP_NET_ADMIN` capability and the `sch_netem` kernel module.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, UdpSocket};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::process::Command;
+use std::thread;
+
+#[cfg(any(
+    feature = "proto-ipv4-fragmentation",
+    feature = "proto-sixlowpan-fragmentation"
+))]
+use smoltcp::iface::FragmentsCache;
+use smoltcp::iface::{Interface, InterfaceBuilder, NeighborCache, SocketHandle, SocketSet};
+use smoltcp::phy::{wait as phy_wait, Medium, TunTapInterface};
+use smoltcp::socket::{tcp, udp};
+use smoltcp::time::{Duration, Instant};
+use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address};
+
+const TAP_NAME: &str = "tap0";
+const LOCAL_ADDR: Ipv4Address = Ipv4Address([192, 168, 69, 1]);
+const HOST_ADDR: Ipv4Addr = Ipv4Addr::new(192, 168, 69, 100);
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Stack {
+    device: TunTapInterface,
+    fd: RawFd,
+    iface: Interface<'static>,
+    sockets: SocketSet<'static>,
+}
+
+impl Stack {
+    fn new() -> Stack {
+        let mut device = TunTapInterface::new(TAP_NAME, Medium::Ethernet)
+            .expect("cannot open the TAP interface, see the setup in the module documentation");
+        let fd = device.as_raw_fd();
+        let builder = InterfaceBuilder::new()
+            .hardware_addr(EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]).into())
+            .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+            .ip_addrs(vec![IpCidr::new(LOCAL_ADDR.into(), 24)]);
+        #[cfg(feature = "proto-ipv4-fragmentation")]
+        let builder = builder.ipv4_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()));
+        #[cfg(feature = "proto-sixlowpan-fragmentation")]
+        let builder = builder
+            .sixlowpan_fragments_cache(FragmentsCache::new(vec![], BTreeMap::new()))
+            .sixlowpan_out_packet_cache(vec![0; 1280]);
+        let iface = builder.try_finalize(&mut device).unwrap();
+        Stack {
+            device,
+            fd,
+            iface,
+            sockets: SocketSet::new(vec![]),
+        }
+    }
+
+    /// Poll the interface until `f` returns `true`, panicking after [TIMEOUT].
+    fn poll_until<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut SocketSet<'static>) -> bool,
+    {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let timestamp = Instant::now();
+            let _ = self
+                .iface
+                .poll(timestamp, &mut self.device, &mut self.sockets);
+            if f(&mut self.sockets) {
+                return;
+            }
+            assert!(timestamp < deadline, "timed out");
+            let delay = self
+                .iface
+                .poll_delay(timestamp, &self.sockets)
+                .map_or(Duration::from_millis(10), |delay| {
+                    delay.min(Duration::from_millis(10))
+                });
+            phy_wait(self.fd, Some(delay)).expect("wait error");
+        }
+    }
+
+    /// Poll the interface for `duration`.
+    fn poll_for(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        self.poll_until(|_| Instant::now() >= until)
+    }
+}
+
+/// A `netem` queueing discipline dropping a share of the packets sent to smoltcp, removed
+/// when dropped.
+struct Netem;
+
+impl Netem {
+    fn loss(percent: u32) -> Netem {
+        let status = Command::new("tc")
+            .args(["qdisc", "replace", "dev", TAP_NAME, "root", "netem", "loss"])
+            .arg(format!("{}%", percent))
+            .status()
+            .expect("cannot run tc");
+        assert!(status.success(), "cannot add the netem queueing discipline");
+        Netem
+    }
+}
+
+impl Drop for Netem {
+    fn drop(&mut self) {
+        let _ = Command::new("tc")
+            .args(["qdisc", "del", "dev", TAP_NAME, "root"])
+            .status();
+    }
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// Send `len` octets to a kernel TCP socket, which checks them and sends them back.
+fn tcp_echo(port: u16, len: usize) {
+    let listener = TcpListener::bind(SocketAddrV4::new(HOST_ADDR, port)).unwrap();
+    let host = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut data = vec![0; len];
+        stream.read_exact(&mut data).unwrap();
+        assert!(
+            data == pattern(len),
+            "corrupted data received by the kernel"
+        );
+        stream.write_all(&data).unwrap();
+    });
+
+    let mut stack = Stack::new();
+    let socket = tcp::Socket::new(
+        tcp::SocketBuffer::new(vec![0; 65535]),
+        tcp::SocketBuffer::new(vec![0; 65535]),
+    );
+    let handle: SocketHandle = stack.sockets.add(socket);
+    let remote = IpEndpoint::new(IpAddress::Ipv4(HOST_ADDR.into()), port);
+    stack
+        .sockets
+        .get_mut::<tcp::Socket>(handle)
+        .connect(stack.iface.context(), remote, 49152)
+        .unwrap();
+
+    let data = pattern(len);
+    let mut sent = 0;
+    let mut received = Vec::with_capacity(len);
+    stack.poll_until(|sockets| {
+        let socket = sockets.get_mut::<tcp::Socket>(handle);
+        if socket.can_send() && sent < len {
+            sent += socket.send_slice(&data[sent..]).unwrap();
+        }
+        if socket.can_recv() {
+            socket
+                .recv(|buffer| {
+                    received.extend_from_slice(buffer);
+                    (buffer.len(), ())
+                })
+                .unwrap();
+        }
+        received.len() == len
+    });
+    assert!(received == data, "corrupted data received by smoltcp");
+
+    stack.sockets.get_mut::<tcp::Socket>(handle).close();
+    stack.poll_until(|sockets| !sockets.get_mut::<tcp::Socket>(handle).is_open());
+    host.join().unwrap();
+}
+
+#[test]
+#[ignore]
+fn test_tcp_bulk_transfer() {
+    tcp_echo(6970, 4 * 1024 * 1024);
+}
+
+#[test]
+#[ignore]
+fn test_tcp_retransmission_under_loss() {
+    let _netem = Netem::loss(5);
+    tcp_echo(6971, 512 * 1024);
+}
+
+#[test]
+#[ignore]
+fn test_udp_broadcast() {
+    const HOST_PORT: u16 = 6972;
+    const LOCAL_PORT: u16 = 6973;
+
+    let mut stack = Stack::new();
+    let socket = udp::Socket::new(
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+        udp::PacketBuffer::new(vec![udp::PacketMetadata::EMPTY; 4], vec![0; 1024]),
+    );
+    let handle = stack.sockets.add(socket);
+    stack
+        .sockets
+        .get_mut::<udp::Socket>(handle)
+        .bind(LOCAL_PORT)
+        .unwrap();
+
+    // The kernel receives the broadcasts of smoltcp.
+    let host = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, HOST_PORT)).unwrap();
+    host.set_broadcast(true).unwrap();
+    host.set_read_timeout(Some(std::time::Duration::from_millis(100)))
+        .unwrap();
+    let broadcast = IpEndpoint::new(Ipv4Address::BROADCAST.into(), HOST_PORT);
+    let mut buffer = [0; 64];
+    let mut attempts = 0;
+    let from = loop {
+        attempts += 1;
+        assert!(attempts <= 50, "timed out");
+        stack
+            .sockets
+            .get_mut::<udp::Socket>(handle)
+            .send_slice(b"from smoltcp", broadcast)
+            .unwrap();
+        stack.poll_for(Duration::from_millis(10));
+        if let Ok((len, from)) = host.recv_from(&mut buffer) {
+            assert_eq!(&buffer[..len], b"from smoltcp");
+            break from;
+        }
+    };
+    assert_eq!(
+        from.ip(),
+        std::net::IpAddr::V4(Ipv4Addr::from(LOCAL_ADDR.0))
+    );
+
+    // smoltcp receives the broadcasts of the kernel, sent from the address of the kernel
+    // on the TAP interface so that they are routed to it.
+    let host = UdpSocket::bind(SocketAddrV4::new(HOST_ADDR, HOST_PORT + 2)).unwrap();
+    host.set_broadcast(true).unwrap();
+    host.send_to(
+        b"from the kernel",
+        SocketAddrV4::new(Ipv4Addr::BROADCAST, LOCAL_PORT),
+    )
+    .unwrap();
+    let mut received = None;
+    stack.poll_until(|sockets| {
+        let socket = sockets.get_mut::<udp::Socket>(handle);
+        if let Ok((data, endpoint)) = socket.recv() {
+            received = Some((data.to_vec(), endpoint));
+        }
+        received.is_some()
+    });
+    let (data, endpoint) = received.unwrap();
+    assert_eq!(data, b"from the kernel");
+    assert_eq!(
+        endpoint.endpoint,
+        IpEndpoint::new(IpAddress::Ipv4(HOST_ADDR.into()), HOST_PORT + 2)
+    );
+}